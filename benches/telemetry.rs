@@ -0,0 +1,79 @@
+use criterion::{black_box, criterion_group, criterion_main, Criterion};
+use iracing::pool::BufferPool;
+use iracing::recording::SnapshotBuilder;
+use iracing::telemetry::{Header, SampleReader, Value};
+
+/// Number of channels in a typical live session.
+const CHANNELS: usize = 300;
+
+/// Snapshot with `CHANNELS` channels, a quarter of them per-car arrays.
+fn snapshot() -> Vec<u8> {
+    let mut builder = SnapshotBuilder::new(1);
+
+    for i in 0..CHANNELS - 1 {
+        let name = format!("Channel{:03}", i);
+        builder = match i % 4 {
+            0 => builder.with_value(&name, Value::FloatVec(vec![i as f32; 64])),
+            1 => builder.with_value(&name, Value::INT(i as i32)),
+            _ => builder.with_value(&name, Value::FLOAT(i as f32)),
+        };
+    }
+
+    builder.with_value("Speed", Value::FLOAT(42.0)).build()
+}
+
+/// Copy a snapshot into 8-byte aligned memory, as the memory map would be.
+fn aligned(snapshot: &[u8]) -> Vec<u64> {
+    snapshot
+        .chunks(8)
+        .map(|c| {
+            let mut word = [0u8; 8];
+            word[..c.len()].copy_from_slice(c);
+            u64::from_ne_bytes(word)
+        })
+        .collect()
+}
+
+fn header_parse(c: &mut Criterion) {
+    let snapshot = snapshot();
+
+    c.bench_function("header_parse", |b| {
+        b.iter(|| Header::from_bytes(black_box(&snapshot)).map(|h| h.map_size()))
+    });
+}
+
+fn sample_copy(c: &mut Criterion) {
+    let snapshot = snapshot();
+    let memory = aligned(&snapshot);
+    let header = Header::from_bytes(&snapshot).unwrap();
+
+    c.bench_function("sample_copy", |b| {
+        // `memory` holds the whole snapshot for the life of the benchmark
+        b.iter(|| unsafe {
+            header.telemetry(black_box(memory.as_ptr() as *const std::ffi::c_void))
+        })
+    });
+
+    let mut reader = SampleReader::new(BufferPool::new(4));
+    c.bench_function("sample_copy_reader", |b| {
+        // `memory` holds the whole snapshot for the life of the benchmark
+        b.iter(|| unsafe { reader.read(black_box(memory.as_ptr() as *const std::ffi::c_void)) })
+    });
+}
+
+fn var_lookup(c: &mut Criterion) {
+    let snapshot = snapshot();
+    let memory = aligned(&snapshot);
+    let header = Header::from_bytes(&snapshot).unwrap();
+    let sample = unsafe { header.telemetry(memory.as_ptr() as *const std::ffi::c_void) }.unwrap();
+
+    c.bench_function("var_lookup_first", |b| {
+        b.iter(|| sample.get(black_box("Channel000")))
+    });
+    c.bench_function("var_lookup_last", |b| {
+        b.iter(|| sample.get(black_box("Speed")))
+    });
+}
+
+criterion_group!(benches, header_parse, sample_copy, var_lookup);
+criterion_main!(benches);
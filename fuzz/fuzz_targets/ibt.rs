@@ -0,0 +1,23 @@
+//! IBT files, from the disk header through variable headers, samples and
+//! session info.
+#![no_main]
+
+use iracing::ibt::IBT;
+use libfuzzer_sys::fuzz_target;
+
+fuzz_target!(|data: &[u8]| {
+    let ibt = match IBT::from_bytes(data.to_vec()) {
+        Ok(ibt) => ibt,
+        Err(_) => return,
+    };
+
+    let _ = ibt.header().buffers();
+    let _ = ibt.variables();
+    let _ = ibt.session_info_lenient();
+    for sample in ibt.samples().take(16) {
+        if let Ok(sample) = sample {
+            let _ = sample.get("SessionTime");
+        }
+    }
+    let _ = ibt.lap_times(0..=2);
+});
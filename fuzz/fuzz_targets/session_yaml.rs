@@ -0,0 +1,17 @@
+//! Session info YAML, as decoded from the sim or a file, through the
+//! sanitizer and lenient parser.
+#![no_main]
+
+use iracing::session::SessionDetails;
+use iracing::yaml::{decode, sanitize};
+use libfuzzer_sys::fuzz_target;
+
+fuzz_target!(|data: &[u8]| {
+    let (yaml, _) = decode(data);
+
+    let (sanitized, repairs) = sanitize(&yaml);
+    assert!(repairs.iter().all(|r| r.line >= 1));
+
+    let _ = sanitize(&sanitized);
+    let _ = SessionDetails::parse_lenient(&yaml);
+});
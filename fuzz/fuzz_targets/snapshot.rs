@@ -0,0 +1,21 @@
+//! Raw memory map snapshots, such as those sent by a remote telemetry
+//! source, through the telemetry header and sample parsers.
+#![no_main]
+
+use iracing::recording::Recording;
+use iracing::telemetry::Header;
+use libfuzzer_sys::fuzz_target;
+
+fuzz_target!(|data: &[u8]| {
+    if let Some(header) = Header::from_bytes(data) {
+        let _ = header.map_size();
+        let _ = header.buffers();
+    }
+
+    let mut recording = Recording::new();
+    recording.push(data.to_vec());
+    if let Some(Ok(sample)) = recording.sample(0) {
+        let _ = sample.all();
+    }
+    let _ = recording.session_info(0);
+});
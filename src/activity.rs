@@ -0,0 +1,170 @@
+use crate::states::SimActivity;
+
+#[cfg(feature = "telemetry")]
+use crate::telemetry::Sample;
+#[cfg(feature = "telemetry")]
+use std::convert::TryInto;
+#[cfg(feature = "telemetry")]
+use std::error::Error;
+
+///
+/// Activity Sample
+///
+/// Flags describing what the sim is doing at a point in time.
+#[derive(Debug, Copy, Clone, Default)]
+pub struct ActivitySample {
+    pub session_time: f64,       // Seconds since session start
+    pub is_on_track: bool,       // Player's car is on track and being driven
+    pub is_in_garage: bool,      // Player is in the garage
+    pub is_replay_playing: bool, // A replay is being watched
+}
+
+///
+/// Activity Tracker
+///
+/// Derives a single `SimActivity` from the scattered flags in the telemetry,
+/// reporting when it changes. A sim is considered paused once the session
+/// clock has stood still for a number of samples while on track.
+///
+/// # Examples
+///
+/// ```
+/// use iracing::activity::{ActivitySample, ActivityTracker};
+/// use iracing::states::SimActivity;
+///
+/// let mut tracker = ActivityTracker::new();
+/// let sample = ActivitySample {
+///     is_on_track: true,
+///     ..Default::default()
+/// };
+///
+/// assert_eq!(tracker.update(&sample), Some(SimActivity::OnTrack));
+/// ```
+#[derive(Debug, Clone)]
+pub struct ActivityTracker {
+    pause_samples: u32,
+    activity: Option<SimActivity>,
+    last_time: Option<f64>,
+    still: u32,
+}
+
+impl ActivitySample {
+    ///
+    /// Read an activity sample from a telemetry sample.
+    #[cfg(feature = "telemetry")]
+    pub fn from_sample(sample: &Sample) -> Result<Self, Box<dyn Error>> {
+        let mut activity = ActivitySample {
+            session_time: sample.get("SessionTime")?.try_into()?,
+            is_on_track: sample.get("IsOnTrack")?.into(),
+            is_replay_playing: sample.get("IsReplayPlaying")?.into(),
+            ..Default::default()
+        };
+
+        if sample.has("IsInGarage") {
+            activity.is_in_garage = sample.get("IsInGarage")?.into();
+        }
+
+        Ok(activity)
+    }
+}
+
+impl Default for ActivityTracker {
+    fn default() -> Self {
+        ActivityTracker {
+            pause_samples: 3,
+            activity: None,
+            last_time: None,
+            still: 0,
+        }
+    }
+}
+
+impl ActivityTracker {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    ///
+    /// Number of consecutive samples without the session clock advancing
+    /// before the sim is considered paused.
+    pub fn with_pause_samples(mut self, samples: u32) -> Self {
+        self.pause_samples = samples.max(1);
+        self
+    }
+
+    ///
+    /// Update the tracker with a new sample, returning the new activity if it
+    /// changed.
+    pub fn update(&mut self, sample: &ActivitySample) -> Option<SimActivity> {
+        match self.last_time {
+            Some(last) if last == sample.session_time => self.still += 1,
+            _ => self.still = 0,
+        }
+        self.last_time = Some(sample.session_time);
+
+        let activity = if sample.is_replay_playing {
+            SimActivity::Replay
+        } else if sample.is_on_track && self.still >= self.pause_samples {
+            SimActivity::Paused
+        } else if sample.is_on_track {
+            SimActivity::OnTrack
+        } else if sample.is_in_garage {
+            SimActivity::InGarage
+        } else {
+            SimActivity::InMenus
+        };
+
+        if self.activity == Some(activity) {
+            return None;
+        }
+
+        self.activity = Some(activity);
+        Some(activity)
+    }
+
+    /// Current activity, `InMenus` until the first update.
+    pub fn activity(&self) -> SimActivity {
+        self.activity.unwrap_or_default()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn derive_activity() {
+        let mut tracker = ActivityTracker::new().with_pause_samples(2);
+        let mut sample = ActivitySample {
+            is_in_garage: true,
+            ..Default::default()
+        };
+
+        assert_eq!(tracker.update(&sample), Some(SimActivity::InGarage));
+
+        sample.is_in_garage = false;
+        sample.is_on_track = true;
+        let changes: Vec<Option<SimActivity>> = [1.0, 1.1, 1.1, 1.1, 1.1, 1.2]
+            .iter()
+            .map(|t| {
+                sample.session_time = *t;
+                tracker.update(&sample)
+            })
+            .collect();
+        assert_eq!(
+            changes,
+            vec![
+                Some(SimActivity::OnTrack),
+                None,
+                None,
+                Some(SimActivity::Paused),
+                None,
+                Some(SimActivity::OnTrack)
+            ]
+        );
+
+        sample.is_replay_playing = true;
+        assert_eq!(tracker.update(&sample), Some(SimActivity::Replay));
+        assert_eq!(tracker.activity(), SimActivity::Replay);
+    }
+}
@@ -0,0 +1,294 @@
+use crate::session::Driver;
+use std::error::Error;
+use std::fmt;
+
+#[cfg(all(target_os = "windows", feature = "chat"))]
+use crate::chat::ChatSender;
+
+///
+/// Errors building an admin command.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum AdminError {
+    /// No car in the session matches the driver name or car number
+    UnknownDriver(String),
+
+    /// More than one car matches the driver name, with their car numbers
+    AmbiguousDriver {
+        target: String,
+        matches: Vec<String>,
+    },
+
+    InvalidArgument(String),
+}
+
+///
+/// Penalty given with a black flag.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum Penalty {
+    /// Stop and hold in the pit box for this many seconds
+    StopAndHold(u32),
+
+    /// Time penalty served as laps
+    Laps(u32),
+
+    DriveThrough,
+}
+
+///
+/// Admin Command
+///
+/// Race control commands which session admins type into chat. Drivers are
+/// given by car number, such as "#044", or by name, which may be part of
+/// their name as long as it matches only one car. They are resolved
+/// against the session's drivers and sent by car number, so the sim can't
+/// pick the wrong driver.
+///
+/// # Examples
+///
+/// ```
+/// use iracing::admin::{AdminCommand, Penalty};
+/// use iracing::session::SessionDetails;
+///
+/// let content = std::fs::read_to_string("./session.yaml").unwrap();
+/// let session: SessionDetails = serde_yaml::from_str(&content).unwrap();
+/// let drivers = &session.drivers.other_drivers;
+///
+/// let black = AdminCommand::Black {
+///     driver: "virtanen".to_string(),
+///     penalty: Some(Penalty::DriveThrough),
+/// };
+///
+/// assert_eq!(black.to_chat(drivers).unwrap(), "!black #21 D");
+/// assert_eq!(AdminCommand::Advance.to_chat(drivers).unwrap(), "!advance");
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum AdminCommand {
+    /// Black flag a driver, with the sim's default penalty if none is given
+    Black {
+        driver: String,
+        penalty: Option<Penalty>,
+    },
+
+    /// Send a driver to the end of the line under caution
+    EndOfLine {
+        driver: String,
+        message: Option<String>,
+    },
+
+    /// Advance to the next session
+    Advance,
+
+    /// Throw a full course caution
+    Yellow { message: Option<String> },
+}
+
+impl fmt::Display for AdminError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            AdminError::UnknownDriver(target) => write!(f, "No driver matches '{}'", target),
+            AdminError::AmbiguousDriver { target, matches } => write!(
+                f,
+                "'{}' matches more than one car: #{}",
+                target,
+                matches.join(", #")
+            ),
+            AdminError::InvalidArgument(reason) => write!(f, "{}", reason),
+        }
+    }
+}
+
+impl Error for AdminError {}
+
+impl fmt::Display for Penalty {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Penalty::StopAndHold(seconds) => write!(f, "{}", seconds),
+            Penalty::Laps(laps) => write!(f, "L{}", laps),
+            Penalty::DriveThrough => write!(f, "D"),
+        }
+    }
+}
+
+impl AdminCommand {
+    ///
+    /// The chat text for the command, resolving its driver against the
+    /// session's drivers.
+    pub fn to_chat(&self, drivers: &[Driver]) -> Result<String, AdminError> {
+        let command = match self {
+            AdminCommand::Black { driver, penalty } => {
+                let car = resolve(driver, drivers)?;
+                match penalty {
+                    Some(Penalty::StopAndHold(0)) | Some(Penalty::Laps(0)) => {
+                        return Err(AdminError::InvalidArgument(
+                            "Penalty must be at least one second or lap".to_string(),
+                        ))
+                    }
+                    Some(penalty) => format!("!black #{} {}", car, penalty),
+                    None => format!("!black #{}", car),
+                }
+            }
+            AdminCommand::EndOfLine { driver, message } => {
+                with_message(format!("!eol #{}", resolve(driver, drivers)?), message)?
+            }
+            AdminCommand::Advance => "!advance".to_string(),
+            AdminCommand::Yellow { message } => with_message("!yellow".to_string(), message)?,
+        };
+
+        Ok(command)
+    }
+
+    ///
+    /// Send the command from the sim's chat, pasting it so the whole command
+    /// arrives at once. Needs the `chat` feature and admin rights in the
+    /// session.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+    /// use iracing::admin::AdminCommand;
+    /// use iracing::chat::ChatSender;
+    /// use iracing::telemetry::Connection;
+    ///
+    /// let session = Connection::new()?.session_info()?;
+    /// let mut chat = ChatSender::new().with_paste(true);
+    ///
+    /// let eol = AdminCommand::EndOfLine {
+    ///     driver: "#7".to_string(),
+    ///     message: Some("Passing under yellow".to_string()),
+    /// };
+    /// eol.send(&mut chat, &session.drivers.other_drivers)?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    #[cfg(all(target_os = "windows", feature = "chat"))]
+    pub fn send(&self, chat: &mut ChatSender, drivers: &[Driver]) -> Result<(), Box<dyn Error>> {
+        chat.send(&self.to_chat(drivers)?)?;
+        Ok(())
+    }
+}
+
+///
+/// Find the car number of the driver a target names, from a car number with
+/// or without "#", or a unique part of a driver's name.
+fn resolve(target: &str, drivers: &[Driver]) -> Result<String, AdminError> {
+    let target = target.trim();
+    if target.is_empty() {
+        return Err(AdminError::InvalidArgument("No driver given".to_string()));
+    }
+
+    let field = || {
+        drivers
+            .iter()
+            .filter(|d| !d.is_pace_car() && d.is_spectator == 0)
+    };
+
+    let number = target.strip_prefix('#').unwrap_or(target);
+    if !number.is_empty() && number.chars().all(|c| c.is_ascii_digit()) {
+        // "44" finds car "044" when no car is painted "44"
+        let exact = field().find(|d| d.number() == number);
+        let loose = || field().find(|d| d.number().parse::<u32>() == number.parse::<u32>());
+
+        return exact
+            .or_else(loose)
+            .map(Driver::number)
+            .ok_or_else(|| AdminError::UnknownDriver(target.to_string()));
+    }
+
+    let lower = target.to_lowercase();
+    let exact: Vec<String> = field()
+        .filter(|d| d.user_name.to_lowercase() == lower)
+        .map(Driver::number)
+        .collect();
+    let mut matches = if exact.is_empty() {
+        field()
+            .filter(|d| d.user_name.to_lowercase().contains(&lower))
+            .map(Driver::number)
+            .collect()
+    } else {
+        exact
+    };
+
+    // Team drivers share a car
+    matches.sort();
+    matches.dedup();
+
+    match matches.len() {
+        0 => Err(AdminError::UnknownDriver(target.to_string())),
+        1 => Ok(matches.remove(0)),
+        _ => Err(AdminError::AmbiguousDriver {
+            target: target.to_string(),
+            matches,
+        }),
+    }
+}
+
+fn with_message(command: String, message: &Option<String>) -> Result<String, AdminError> {
+    match message.as_deref().map(str::trim) {
+        Some(message) if message.chars().any(char::is_control) => Err(AdminError::InvalidArgument(
+            "Messages can't hold line breaks".to_string(),
+        )),
+        Some(message) if !message.is_empty() => Ok(format!("{} {}", command, message)),
+        _ => Ok(command),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::session::SessionDetails;
+
+    #[test]
+    fn build_admin_commands() {
+        let content = std::fs::read_to_string("./session.yaml").unwrap();
+        let session: SessionDetails = serde_yaml::from_str(&content).unwrap();
+        let drivers = &session.drivers.other_drivers;
+
+        let black = |driver: &str, penalty| {
+            AdminCommand::Black {
+                driver: driver.to_string(),
+                penalty,
+            }
+            .to_chat(drivers)
+        };
+
+        assert_eq!(black("#7", None).unwrap(), "!black #7");
+        assert_eq!(
+            black("44", Some(Penalty::StopAndHold(30))).unwrap(),
+            "!black #044 30"
+        );
+        assert_eq!(
+            black("thomas müller-graf", Some(Penalty::Laps(2))).unwrap(),
+            "!black #044 L2"
+        );
+        assert!(matches!(
+            black("#0", None),
+            Err(AdminError::UnknownDriver(_))
+        ));
+        assert!(matches!(
+            black("a", None),
+            Err(AdminError::AmbiguousDriver { .. })
+        ));
+        assert!(black("#7", Some(Penalty::Laps(0))).is_err());
+
+        let eol = AdminCommand::EndOfLine {
+            driver: "Blake".to_string(),
+            message: Some("Passing under yellow".to_string()),
+        };
+        assert_eq!(
+            eol.to_chat(drivers).unwrap(),
+            "!eol #77 Passing under yellow"
+        );
+
+        let yellow = AdminCommand::Yellow {
+            message: Some("Debris\nturn 1".to_string()),
+        };
+        assert!(yellow.to_chat(drivers).is_err());
+        assert_eq!(
+            AdminCommand::Yellow { message: None }
+                .to_chat(drivers)
+                .unwrap(),
+            "!yellow"
+        );
+    }
+}
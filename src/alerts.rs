@@ -0,0 +1,405 @@
+use serde::{Deserialize, Serialize};
+
+#[cfg(feature = "telemetry")]
+use crate::telemetry::Sample;
+#[cfg(feature = "telemetry")]
+use std::convert::TryInto;
+#[cfg(feature = "telemetry")]
+use std::error::Error;
+
+/// Surface temperature channels, inside to outside for each corner
+#[cfg(feature = "telemetry")]
+const TIRE_TEMPS: [&str; 12] = [
+    "LFtempCL", "LFtempCM", "LFtempCR", "RFtempCL", "RFtempCM", "RFtempCR", "LRtempCL", "LRtempCM",
+    "LRtempCR", "RRtempCL", "RRtempCM", "RRtempCR",
+];
+
+///
+/// Alert Sample
+///
+/// The values alert rules are checked against.
+#[derive(Debug, Clone, Default)]
+pub struct AlertSample {
+    pub session_time: f64,         // Seconds since session start
+    pub fuel_level: f32,           // Liters of fuel remaining
+    pub fuel_per_lap: Option<f32>, // Liters used per lap - not a channel, set from a fuel model
+    pub tire_temps: Vec<f32>,      // Tire surface temperatures (C)
+    pub incidents: i32,            // PlayerCarTeamIncidentCount
+}
+
+///
+/// A value an alert rule watches.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Metric {
+    /// Liters of fuel remaining
+    FuelLevel,
+
+    /// Laps of fuel remaining, requires `fuel_per_lap`
+    FuelLaps,
+
+    /// Hottest tire surface temperature (C)
+    TireTemp,
+
+    /// Incident count
+    Incidents,
+
+    /// Sim frame rate (FPS), averaged by `SimPerformance`
+    FrameRate,
+
+    /// Busiest sim CPU thread, 0.0 to 1.0, averaged by `SimPerformance`
+    CpuUsage,
+
+    /// GPU usage, 0.0 to 1.0, averaged by `SimPerformance`
+    GpuUsage,
+
+    /// Network quality, 0.0 to 1.0, averaged by `NetworkMonitor`
+    NetworkQuality,
+
+    /// Network latency (s), averaged by `NetworkMonitor`
+    Latency,
+
+    /// Seconds since the sim last heard from the server, from `NetworkMonitor`
+    Disconnected,
+}
+
+///
+/// Which side of the threshold raises the alert.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Condition {
+    Above,
+    Below,
+}
+
+///
+/// A configurable alert.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct AlertRule {
+    pub name: String,
+    pub metric: Metric,
+    pub condition: Condition,
+    pub threshold: f32,
+    pub hysteresis: f32, // Distance back past the threshold before the alert clears
+    pub cooldown: f64,   // Minimum seconds between raising the alert
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum AlertState {
+    Raised,
+    Cleared,
+}
+
+///
+/// An alert being raised or cleared.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct AlertEvent {
+    pub session_time: f64,
+    pub name: String,
+    pub metric: Metric,
+    pub value: f32,
+    pub state: AlertState,
+}
+
+#[derive(Debug, Clone)]
+struct RuleState {
+    rule: AlertRule,
+    raised: bool,
+    last_raised: Option<f64>,
+}
+
+///
+/// Alerts
+///
+/// Checks samples against a set of alert rules, with hysteresis so values
+/// hovering around a threshold don't flap, and a cooldown between repeats.
+///
+/// # Examples
+///
+/// ```
+/// use iracing::alerts::{AlertRule, AlertSample, Alerts};
+///
+/// let mut alerts = Alerts::new()
+///     .with_rule(AlertRule::fuel_laps_below(2.0))
+///     .with_rule(AlertRule::tire_temp_above(110.0))
+///     .with_rule(AlertRule::incidents_approaching(17, 4));
+///
+/// for event in alerts.update(&AlertSample::default()) {
+///     println!("{} {:?} at {}", event.name, event.state, event.value);
+/// }
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct Alerts {
+    rules: Vec<RuleState>,
+}
+
+impl AlertSample {
+    ///
+    /// Read an alert sample from a telemetry sample.
+    ///
+    /// `fuel_per_lap` is left unset.
+    #[cfg(feature = "telemetry")]
+    pub fn from_sample(sample: &Sample) -> Result<Self, Box<dyn Error>> {
+        let mut tire_temps = Vec::with_capacity(TIRE_TEMPS.len());
+        for channel in TIRE_TEMPS.iter().filter(|c| sample.has(c)) {
+            tire_temps.push(sample.get(channel)?.try_into()?);
+        }
+
+        Ok(AlertSample {
+            session_time: sample.get("SessionTime")?.try_into()?,
+            fuel_level: sample.get("FuelLevel")?.try_into()?,
+            fuel_per_lap: None,
+            tire_temps,
+            incidents: sample.get("PlayerCarTeamIncidentCount")?.try_into()?,
+        })
+    }
+
+    /// Current value of a metric, if it is known.
+    pub fn value(&self, metric: Metric) -> Option<f32> {
+        match metric {
+            Metric::FuelLevel => Some(self.fuel_level),
+            Metric::FuelLaps => self
+                .fuel_per_lap
+                .filter(|per_lap| *per_lap > 0.0)
+                .map(|per_lap| self.fuel_level / per_lap),
+            Metric::TireTemp => self.tire_temps.iter().copied().reduce(f32::max),
+            Metric::Incidents => Some(self.incidents as f32),
+            _ => None,
+        }
+    }
+}
+
+impl AlertRule {
+    pub fn new(name: &str, metric: Metric, condition: Condition, threshold: f32) -> Self {
+        AlertRule {
+            name: name.to_owned(),
+            metric,
+            condition,
+            threshold,
+            hysteresis: 0.0,
+            cooldown: 0.0,
+        }
+    }
+
+    /// Fuel for fewer than a number of laps.
+    pub fn fuel_laps_below(laps: f32) -> Self {
+        Self::new("low_fuel", Metric::FuelLaps, Condition::Below, laps).with_hysteresis(0.5)
+    }
+
+    /// Any tire surface hotter than a temperature (C).
+    pub fn tire_temp_above(celsius: f32) -> Self {
+        Self::new("tire_temp", Metric::TireTemp, Condition::Above, celsius)
+            .with_hysteresis(5.0)
+            .with_cooldown(30.0)
+    }
+
+    /// Incidents within `margin` of the disqualification limit.
+    pub fn incidents_approaching(limit: i32, margin: i32) -> Self {
+        let threshold = (limit - margin) as f32 - 0.5;
+        Self::new(
+            "incident_limit",
+            Metric::Incidents,
+            Condition::Above,
+            threshold,
+        )
+    }
+
+    /// Sim frame rate below an FPS.
+    pub fn frame_rate_below(fps: f32) -> Self {
+        Self::new("low_frame_rate", Metric::FrameRate, Condition::Below, fps)
+            .with_hysteresis(5.0)
+            .with_cooldown(60.0)
+    }
+
+    /// Busiest sim CPU thread above a fraction of a core, e.g. 0.9.
+    pub fn cpu_usage_above(fraction: f32) -> Self {
+        Self::new("high_cpu", Metric::CpuUsage, Condition::Above, fraction)
+            .with_hysteresis(0.05)
+            .with_cooldown(60.0)
+    }
+
+    /// GPU usage above a fraction, e.g. 0.98.
+    pub fn gpu_usage_above(fraction: f32) -> Self {
+        Self::new("high_gpu", Metric::GpuUsage, Condition::Above, fraction)
+            .with_hysteresis(0.05)
+            .with_cooldown(60.0)
+    }
+
+    /// Network quality below a fraction, e.g. 0.8.
+    pub fn network_quality_below(quality: f32) -> Self {
+        Self::new(
+            "poor_network",
+            Metric::NetworkQuality,
+            Condition::Below,
+            quality,
+        )
+        .with_hysteresis(0.05)
+        .with_cooldown(30.0)
+    }
+
+    /// Network latency above a number of seconds.
+    pub fn latency_above(seconds: f32) -> Self {
+        Self::new("high_latency", Metric::Latency, Condition::Above, seconds)
+            .with_hysteresis(0.02)
+            .with_cooldown(30.0)
+    }
+
+    /// Nothing heard from the server for a number of seconds.
+    pub fn disconnected_for(seconds: f32) -> Self {
+        Self::new(
+            "disconnected",
+            Metric::Disconnected,
+            Condition::Above,
+            seconds,
+        )
+    }
+
+    pub fn with_hysteresis(mut self, hysteresis: f32) -> Self {
+        self.hysteresis = hysteresis.abs();
+        self
+    }
+
+    pub fn with_cooldown(mut self, seconds: f64) -> Self {
+        self.cooldown = seconds;
+        self
+    }
+
+    fn triggered(&self, value: f32) -> bool {
+        match self.condition {
+            Condition::Above => value > self.threshold,
+            Condition::Below => value < self.threshold,
+        }
+    }
+
+    fn recovered(&self, value: f32) -> bool {
+        match self.condition {
+            Condition::Above => value <= self.threshold - self.hysteresis,
+            Condition::Below => value >= self.threshold + self.hysteresis,
+        }
+    }
+}
+
+impl Alerts {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with_rule(mut self, rule: AlertRule) -> Self {
+        self.add_rule(rule);
+        self
+    }
+
+    pub fn add_rule(&mut self, rule: AlertRule) {
+        self.rules.push(RuleState {
+            rule,
+            raised: false,
+            last_raised: None,
+        });
+    }
+
+    /// Names of the alerts currently raised.
+    pub fn raised(&self) -> impl Iterator<Item = &str> {
+        self.rules
+            .iter()
+            .filter(|r| r.raised)
+            .map(|r| r.rule.name.as_str())
+    }
+
+    ///
+    /// Check a sample against every rule, returning alerts raised or cleared.
+    pub fn update(&mut self, sample: &AlertSample) -> Vec<AlertEvent> {
+        self.check(sample.session_time, |metric| sample.value(metric))
+    }
+
+    ///
+    /// Check every rule against values from elsewhere, such as a monitor
+    /// averaging a metric. Rules whose metric has no value are skipped.
+    pub fn check<F>(&mut self, now: f64, values: F) -> Vec<AlertEvent>
+    where
+        F: Fn(Metric) -> Option<f32>,
+    {
+        let mut events = Vec::new();
+
+        for state in self.rules.iter_mut() {
+            let value = match values(state.rule.metric) {
+                Some(value) => value,
+                None => continue,
+            };
+
+            let state_change = if !state.raised && state.rule.triggered(value) {
+                let cooling = matches!(state.last_raised, Some(t) if now - t < state.rule.cooldown);
+                if cooling {
+                    None
+                } else {
+                    state.raised = true;
+                    state.last_raised = Some(now);
+                    Some(AlertState::Raised)
+                }
+            } else if state.raised && state.rule.recovered(value) {
+                state.raised = false;
+                Some(AlertState::Cleared)
+            } else {
+                None
+            };
+
+            if let Some(alert_state) = state_change {
+                events.push(AlertEvent {
+                    session_time: now,
+                    name: state.rule.name.clone(),
+                    metric: state.rule.metric,
+                    value,
+                    state: alert_state,
+                });
+            }
+        }
+
+        events
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn hysteresis_and_cooldown() {
+        let mut alerts = Alerts::new().with_rule(
+            AlertRule::new("hot", Metric::TireTemp, Condition::Above, 100.0)
+                .with_hysteresis(5.0)
+                .with_cooldown(60.0),
+        );
+
+        let mut sample = AlertSample::default();
+        let mut states = Vec::new();
+
+        for (t, temp) in [
+            (0.0, 99.0),
+            (1.0, 101.0),
+            (2.0, 99.0),
+            (3.0, 101.0),
+            (4.0, 94.0),
+            (5.0, 102.0),
+            (70.0, 102.0),
+        ]
+        .iter()
+        {
+            sample.session_time = *t;
+            sample.tire_temps = vec![80.0, *temp];
+            states.extend(
+                alerts
+                    .update(&sample)
+                    .into_iter()
+                    .map(|e| (e.session_time, e.state)),
+            );
+        }
+
+        // Flapping around 100 raises once, clears at 95, then waits out the cooldown
+        assert_eq!(
+            states,
+            vec![
+                (1.0, AlertState::Raised),
+                (4.0, AlertState::Cleared),
+                (70.0, AlertState::Raised)
+            ]
+        );
+        assert_eq!(alerts.raised().collect::<Vec<_>>(), vec!["hot"]);
+    }
+}
@@ -0,0 +1,276 @@
+use crate::incidents::Incident;
+use crate::replay::Header;
+use crate::results::Results;
+use crate::session::SessionDetails;
+use crate::team::Stint;
+use serde_yaml::Value;
+use std::collections::HashMap;
+
+///
+/// How identities are hidden.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Default)]
+pub enum Mode {
+    /// Replace names and IDs with stable pseudonyms, e.g. "Driver 3" and "Team 1"
+    #[default]
+    Pseudonymize,
+
+    /// Blank names and zero IDs
+    Strip,
+}
+
+///
+/// Anonymizer
+///
+/// Hides driver names, customer IDs and team names so telemetry and session
+/// data can be shared publicly. Car indices, car numbers and everything else
+/// are left alone, so data from different sources still lines up.
+///
+/// Pseudonyms are numbered in the order drivers and teams are first seen and
+/// stay the same across everything passed through one anonymizer, e.g. the
+/// session string and the results exported from it. The pace car and AI
+/// drivers (user IDs of 0 or less) are left as they are.
+///
+/// # Examples
+///
+/// ```
+/// use iracing::anonymize::Anonymizer;
+/// use iracing::session::SessionDetails;
+///
+/// let session = std::fs::read_to_string("./session.yaml").unwrap();
+///
+/// let mut anonymizer = Anonymizer::new();
+/// let shared = anonymizer.session_string(&session).unwrap();
+///
+/// let details: SessionDetails = serde_yaml::from_str(&shared).unwrap();
+/// assert_eq!(details.drivers.other_drivers[1].user_name, "Driver 1");
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct Anonymizer {
+    mode: Mode,
+    users: HashMap<i64, i64>,
+    teams: HashMap<u64, u64>,
+}
+
+impl Anonymizer {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with_mode(mut self, mode: Mode) -> Self {
+        self.mode = mode;
+        self
+    }
+
+    ///
+    /// Pseudonymous ID of a driver, or the ID itself for the pace car and AI.
+    pub fn user_id(&mut self, user_id: i64) -> i64 {
+        if user_id <= 0 {
+            return user_id;
+        }
+
+        match self.mode {
+            Mode::Strip => 0,
+            Mode::Pseudonymize => {
+                let next = self.users.len() as i64 + 1;
+                *self.users.entry(user_id).or_insert(next)
+            }
+        }
+    }
+
+    ///
+    /// Pseudonymous ID of a team.
+    pub fn team_id(&mut self, team_id: u64) -> u64 {
+        if team_id == 0 {
+            return team_id;
+        }
+
+        match self.mode {
+            Mode::Strip => 0,
+            Mode::Pseudonymize => {
+                let next = self.teams.len() as u64 + 1;
+                *self.teams.entry(team_id).or_insert(next)
+            }
+        }
+    }
+
+    ///
+    /// Name to show for a driver. `name` is kept for the pace car and AI.
+    pub fn user_name(&mut self, user_id: i64, name: &str) -> String {
+        if user_id <= 0 {
+            return name.to_owned();
+        }
+
+        match self.mode {
+            Mode::Strip => String::new(),
+            Mode::Pseudonymize => format!("Driver {}", self.user_id(user_id)),
+        }
+    }
+
+    ///
+    /// Name to show for a team. Outside team sessions the team is named after
+    /// its driver, so it gets the driver's name.
+    pub fn team_name(&mut self, team_id: u64, user_id: i64, name: &str) -> String {
+        if team_id == 0 {
+            return self.user_name(user_id, name);
+        }
+
+        match self.mode {
+            Mode::Strip => String::new(),
+            Mode::Pseudonymize => format!("Team {}", self.team_id(team_id)),
+        }
+    }
+
+    ///
+    /// Anonymize parsed session info.
+    pub fn session(&mut self, details: &mut SessionDetails) {
+        for driver in details.drivers.other_drivers.iter_mut() {
+            let (user_id, team_id) = (driver.user_id, driver.team_id);
+            let name = self.user_name(user_id, &driver.user_name);
+
+            if user_id > 0 {
+                driver.abbrev_name = name.clone();
+                driver.initials = initials(&name);
+            }
+            driver.user_name = name;
+            driver.user_id = self.user_id(user_id);
+            driver.team_name = self.team_name(team_id, user_id, &driver.team_name);
+            driver.team_id = self.team_id(team_id);
+        }
+    }
+
+    ///
+    /// Anonymize a raw session string, keeping every field, including those
+    /// `SessionDetails` doesn't parse.
+    pub fn session_string(&mut self, yaml: &str) -> serde_yaml::Result<String> {
+        let mut root: Value = serde_yaml::from_str(yaml)?;
+
+        if let Some(info) = root.get_mut("DriverInfo") {
+            if let Some(id) = info.get_mut("DriverUserID") {
+                *id = Value::from(self.user_id(id.as_i64().unwrap_or_default()));
+            }
+
+            let drivers = info.get_mut("Drivers").and_then(Value::as_sequence_mut);
+            for driver in drivers.into_iter().flatten() {
+                self.driver_value(driver);
+            }
+        }
+
+        serde_yaml::to_string(&root)
+    }
+
+    fn driver_value(&mut self, driver: &mut Value) {
+        let user_id = driver["UserID"].as_i64().unwrap_or_default();
+        let team_id = driver["TeamID"].as_u64().unwrap_or_default();
+
+        if user_id > 0 {
+            let name = self.user_name(user_id, "");
+            set(driver, "UserName", Value::from(name.clone()));
+            set(driver, "AbbrevName", Value::from(name.clone()));
+            set(driver, "Initials", Value::from(initials(&name)));
+            set(driver, "UserID", Value::from(self.user_id(user_id)));
+        }
+
+        let team_name = driver["TeamName"].as_str().unwrap_or_default().to_owned();
+        let name = self.team_name(team_id, user_id, &team_name);
+        set(driver, "TeamName", Value::from(name));
+        set(driver, "TeamID", Value::from(self.team_id(team_id)));
+    }
+
+    ///
+    /// Anonymize results.
+    pub fn results(&mut self, results: &mut Results) {
+        for s in results.standings.iter_mut() {
+            s.driver_name = self.user_name(s.user_id, &s.driver_name);
+            s.user_id = self.user_id(s.user_id);
+            s.team_name = self.team_name(s.team_id, s.user_id, &s.team_name);
+            s.team_id = self.team_id(s.team_id);
+        }
+    }
+
+    ///
+    /// Anonymize driver stints.
+    pub fn stints(&mut self, stints: &mut [Stint]) {
+        for s in stints.iter_mut() {
+            s.user_name = self.user_name(s.user_id, &s.user_name);
+            s.user_id = self.user_id(s.user_id);
+            s.team_name = self.team_name(s.team_id, s.user_id, &s.team_name);
+            s.team_id = self.team_id(s.team_id);
+        }
+    }
+
+    ///
+    /// Anonymize incidents.
+    pub fn incidents(&mut self, incidents: &mut [Incident]) {
+        for incident in incidents.iter_mut() {
+            incident.user_id = incident.user_id.map(|id| self.user_id(id));
+        }
+    }
+
+    ///
+    /// Anonymize the recording driver in a replay header.
+    pub fn replay_header(&mut self, header: &mut Header) {
+        let user_id = i64::from(header.user_id);
+        header.user_name = self.user_name(user_id, &header.user_name);
+        header.user_id = self.user_id(user_id) as u32;
+    }
+}
+
+fn set(map: &mut Value, key: &str, value: Value) {
+    if let Some(field) = map.get_mut(key) {
+        *field = value;
+    }
+}
+
+fn initials(name: &str) -> String {
+    name.split_whitespace()
+        .filter_map(|w| w.chars().next())
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn anonymize_session() {
+        let content = std::fs::read_to_string("./session.yaml").unwrap();
+
+        let mut anonymizer = Anonymizer::new();
+        let shared = anonymizer.session_string(&content).unwrap();
+        assert!(!shared.contains("Adamek"));
+        assert!(!shared.contains("81797"));
+        assert!(!shared.contains("Breaker Racing"));
+
+        let details: SessionDetails = serde_yaml::from_str(&shared).unwrap();
+        let drivers = &details.drivers.other_drivers;
+        assert_eq!(drivers[0].user_name, "Pace Car");
+        assert_eq!(drivers[1].index, 1);
+        assert_eq!(drivers[1].user_id, 1);
+        assert_eq!(drivers[1].initials, "D1");
+        assert_eq!(drivers[1].team_name, "Team 1");
+
+        // Results from the original session get the same pseudonyms
+        let original: SessionDetails = serde_yaml::from_str(&content).unwrap();
+        let mut results = Results::from_session(&original, 2).unwrap();
+        anonymizer.results(&mut results);
+        let standing = results.for_car(1).unwrap();
+        assert_eq!(standing.driver_name, "Driver 1");
+        assert_eq!(standing.team_name, "Team 1");
+
+        let mut typed = original.clone();
+        Anonymizer::new().with_mode(Mode::Strip).session(&mut typed);
+        assert_eq!(typed.drivers.other_drivers[1].user_name, "");
+        assert_eq!(typed.drivers.other_drivers[1].user_id, 0);
+        assert_eq!(typed.drivers.other_drivers[0].user_id, -1);
+
+        // Solo sessions name the team after the driver
+        let solo = content
+            .replace("TeamID: 9001", "TeamID: 0")
+            .replace("TeamName: Breaker Racing", "TeamName: L W Adamek");
+        let shared = Anonymizer::new().session_string(&solo).unwrap();
+        assert!(!shared.contains("Adamek"));
+        let details: SessionDetails = serde_yaml::from_str(&shared).unwrap();
+        assert_eq!(details.drivers.other_drivers[1].team_name, "Driver 1");
+        assert_eq!(details.drivers.other_drivers[0].team_name, "Pace Car");
+    }
+}
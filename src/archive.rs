@@ -0,0 +1,314 @@
+use crate::boundary::SessionKey;
+use crate::history::History;
+use serde::{Deserialize, Serialize};
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, BufRead, BufReader, Write};
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
+
+/// Latest session info snapshot
+const SESSION_FILE: &str = "session.yaml";
+
+/// Events, one JSON object per line
+const EVENTS_FILE: &str = "events.jsonl";
+
+/// Channel history
+const HISTORY_FILE: &str = "history.json";
+
+///
+/// An event stored in the archive.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ArchivedEvent {
+    pub session_time: f64,
+    pub kind: String, // Event type, e.g. "pit_stop" or "incident"
+    pub data: serde_json::Value,
+}
+
+///
+/// Archive
+///
+/// The contents of an archive directory, as loaded after a crash or restart.
+#[derive(Debug, Clone, Default)]
+pub struct Archive {
+    pub session_info: Option<String>,
+    pub events: Vec<ArchivedEvent>,
+    pub history: Option<serde_json::Value>,
+}
+
+///
+/// Archiver
+///
+/// Periodically flushes the session info, derived events, and channel history
+/// to a directory, so a crash of the sim or the consuming tool loses at most
+/// one flush interval of data.
+///
+/// Events are appended to `events.jsonl`; the session info and history are
+/// replaced atomically by writing a temporary file and renaming it.
+///
+/// # Examples
+///
+/// ```no_run
+/// use iracing::archive::Archiver;
+/// use iracing::history::History;
+/// use std::time::Duration;
+/// # fn main() -> std::io::Result<()> {
+/// # let session_info = String::new();
+///
+/// let history = History::with_interval(1.0).with_channel("FuelLevel");
+/// let mut archiver = Archiver::new("./archive", Duration::from_secs(30))?;
+///
+/// archiver.session_info(&session_info);
+/// archiver.event(12.5, "incident", &4)?;
+/// archiver.maybe_flush(&history)?;
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Debug)]
+pub struct Archiver {
+    root: PathBuf,
+    dir: PathBuf,
+    key: Option<SessionKey>,
+    interval: Duration,
+    last_flush: Option<Instant>,
+    session_info: Option<String>,
+    session_info_dirty: bool,
+    events: Vec<ArchivedEvent>,
+}
+
+impl Archiver {
+    ///
+    /// Archive into `dir`, creating it if needed, flushing at most every `interval`.
+    pub fn new<P: AsRef<Path>>(dir: P, interval: Duration) -> io::Result<Self> {
+        let dir = dir.as_ref().to_path_buf();
+        fs::create_dir_all(&dir)?;
+
+        Ok(Archiver {
+            root: dir.clone(),
+            dir,
+            key: None,
+            interval,
+            last_flush: None,
+            session_info: None,
+            session_info_dirty: false,
+            events: Vec::new(),
+        })
+    }
+
+    /// Directory being archived into.
+    pub fn dir(&self) -> &Path {
+        &self.dir
+    }
+
+    ///
+    /// Start archiving a session, flushing everything pending for the
+    /// previous one first. Each session is archived into a directory of its
+    /// own under the original directory, named by `SessionKey::name`, so
+    /// reconnecting to a session carries on its archive.
+    ///
+    /// The session info is carried over to the first session archived and to
+    /// later sessions of the same event, but not to another event's.
+    pub fn rotate(&mut self, key: &SessionKey, history: &History) -> io::Result<()> {
+        self.flush(history)?;
+
+        let dir = self.root.join(key.name());
+        fs::create_dir_all(&dir)?;
+        self.dir = dir;
+        self.last_flush = None;
+
+        if self.key.is_none_or(|current| current.same_event(key)) {
+            self.session_info_dirty = self.session_info.is_some();
+        } else {
+            self.session_info = None;
+        }
+        self.key = Some(*key);
+        Ok(())
+    }
+
+    /// Key of the session being archived, once rotated to one.
+    pub fn key(&self) -> Option<SessionKey> {
+        self.key
+    }
+
+    ///
+    /// Update the session info snapshot. It is only rewritten when it changes.
+    pub fn session_info(&mut self, yaml: &str) {
+        if self.session_info.as_deref() != Some(yaml) {
+            self.session_info = Some(yaml.to_owned());
+            self.session_info_dirty = true;
+        }
+    }
+
+    ///
+    /// Queue an event for the next flush.
+    pub fn event<E: Serialize>(
+        &mut self,
+        session_time: f64,
+        kind: &str,
+        event: &E,
+    ) -> io::Result<()> {
+        let data = serde_json::to_value(event).map_err(io::Error::from)?;
+
+        self.events.push(ArchivedEvent {
+            session_time,
+            kind: kind.to_owned(),
+            data,
+        });
+        Ok(())
+    }
+
+    ///
+    /// Flush if the interval has elapsed since the last flush.
+    ///
+    /// Returns true if a flush was performed.
+    pub fn maybe_flush(&mut self, history: &History) -> io::Result<bool> {
+        match self.last_flush {
+            Some(last) if last.elapsed() < self.interval => Ok(false),
+            _ => self.flush(history).map(|_| true),
+        }
+    }
+
+    ///
+    /// Write everything pending to disk now.
+    pub fn flush(&mut self, history: &History) -> io::Result<()> {
+        if self.session_info_dirty {
+            if let Some(info) = &self.session_info {
+                write_atomic(&self.dir.join(SESSION_FILE), info.as_bytes())?;
+            }
+            self.session_info_dirty = false;
+        }
+
+        if !self.events.is_empty() {
+            let mut file = OpenOptions::new()
+                .create(true)
+                .append(true)
+                .open(self.dir.join(EVENTS_FILE))?;
+
+            let mut buffer = Vec::new();
+            for event in &self.events {
+                serde_json::to_writer(&mut buffer, event).map_err(io::Error::from)?;
+                buffer.push(b'\n');
+            }
+
+            file.write_all(&buffer)?;
+            file.sync_data()?;
+            self.events.clear();
+        }
+
+        let json = serde_json::to_vec(history).map_err(io::Error::from)?;
+        write_atomic(&self.dir.join(HISTORY_FILE), &json)?;
+
+        self.last_flush = Some(Instant::now());
+        Ok(())
+    }
+}
+
+/// Write a file by writing a temporary file beside it and renaming it into place.
+fn write_atomic(path: &Path, contents: &[u8]) -> io::Result<()> {
+    let temp = path.with_extension("tmp");
+
+    let mut file = File::create(&temp)?;
+    file.write_all(contents)?;
+    file.sync_all()?;
+
+    fs::rename(&temp, path)
+}
+
+impl Archive {
+    ///
+    /// Load an archive directory.
+    ///
+    /// A partially written last line in the events file (from a crash mid-write) is ignored.
+    pub fn load<P: AsRef<Path>>(dir: P) -> io::Result<Self> {
+        let dir = dir.as_ref();
+
+        let session_info = match fs::read_to_string(dir.join(SESSION_FILE)) {
+            Ok(info) => Some(info),
+            Err(e) if e.kind() == io::ErrorKind::NotFound => None,
+            Err(e) => return Err(e),
+        };
+
+        let mut events = Vec::new();
+        match File::open(dir.join(EVENTS_FILE)) {
+            Ok(file) => {
+                for line in BufReader::new(file).lines() {
+                    if let Ok(event) = serde_json::from_str(&line?) {
+                        events.push(event);
+                    }
+                }
+            }
+            Err(e) if e.kind() == io::ErrorKind::NotFound => {}
+            Err(e) => return Err(e),
+        }
+
+        let history = match fs::read(dir.join(HISTORY_FILE)) {
+            Ok(json) => serde_json::from_slice(&json).ok(),
+            Err(e) if e.kind() == io::ErrorKind::NotFound => None,
+            Err(e) => return Err(e),
+        };
+
+        Ok(Archive {
+            session_info,
+            events,
+            history,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn flush_and_load() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut history = History::with_interval(1.0).with_channel("FuelLevel");
+        history.record(1.0, 1, &[("FuelLevel", 50.0)]);
+
+        let mut archiver = Archiver::new(dir.path(), Duration::from_secs(3600)).unwrap();
+        archiver.session_info("WeekendInfo: {}\n");
+        archiver.event(1.0, "incident", &2).unwrap();
+
+        assert!(archiver.maybe_flush(&history).unwrap());
+        archiver.event(2.0, "incident", &4).unwrap();
+        assert!(!archiver.maybe_flush(&history).unwrap());
+        archiver.flush(&history).unwrap();
+
+        // Simulate a crash part way through writing an event
+        let mut events = OpenOptions::new()
+            .append(true)
+            .open(dir.path().join(EVENTS_FILE))
+            .unwrap();
+        events.write_all(b"{\"session_time\":3.0,\"ki").unwrap();
+
+        let archive = Archive::load(dir.path()).unwrap();
+        assert_eq!(archive.session_info.as_deref(), Some("WeekendInfo: {}\n"));
+        assert_eq!(archive.events.len(), 2);
+
+        // Each session is archived alongside, keeping the event's session info
+        let key = |session_num| SessionKey {
+            sub_session_id: 9,
+            session_unique_id: 3,
+            session_num,
+        };
+        archiver.rotate(&key(0), &history).unwrap();
+        archiver.session_info("WeekendInfo: {}\n");
+        archiver.event(3.0, "incident", &1).unwrap();
+        archiver.rotate(&key(1), &history).unwrap();
+        archiver.event(4.0, "incident", &2).unwrap();
+        archiver.flush(&history).unwrap();
+        assert_eq!(archiver.dir(), dir.path().join("9-1"));
+        assert_eq!(
+            Archive::load(dir.path().join("9-0")).unwrap().events.len(),
+            1
+        );
+
+        let next = Archive::load(archiver.dir()).unwrap();
+        assert_eq!(next.session_info.as_deref(), Some("WeekendInfo: {}\n"));
+        assert_eq!(next.events.len(), 1);
+        assert_eq!(archive.events[1].data, serde_json::json!(4));
+        assert_eq!(
+            archive.history.unwrap()["channels"][0]["values"][0],
+            serde_json::json!(50.0)
+        );
+    }
+}
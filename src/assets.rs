@@ -0,0 +1,277 @@
+use crate::session::{Driver, WeekendInfo};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::error::Error;
+use std::fs;
+use std::path::Path;
+
+const BUNDLED: &str = include_str!("../data/assets.json");
+
+///
+/// Metadata for a car, keyed by its `CarPath`.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct CarAsset {
+    pub path: String,       // CarPath from the session info, e.g. "porsche992cup"
+    pub name: String,       // Display name
+    pub short_name: String, // Abbreviated name for tight layouts
+
+    #[serde(default)]
+    pub make: String,
+
+    #[serde(default)]
+    pub class: String,
+
+    #[serde(default)]
+    pub logo: Option<String>, // Path or URL of a manufacturer logo
+}
+
+///
+/// Metadata for a track configuration, keyed by its `TrackName`.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct TrackAsset {
+    pub name: String,         // TrackName from the session info, e.g. "imola gp"
+    pub display_name: String, // Full display name
+    pub short_name: String,
+
+    #[serde(default)]
+    pub config: String, // Layout name
+
+    pub length_km: f32,
+
+    #[serde(default)]
+    pub sectors: Vec<f32>, // Start of each sector as a fraction of the lap
+
+    #[serde(default)]
+    pub logo: Option<String>, // Path or URL of a track logo
+
+    #[serde(default)]
+    pub map: Option<String>, // Path or URL of a track map
+
+    #[serde(default)]
+    pub corners: Vec<Corner>,
+}
+
+///
+/// A named corner on the track map, between two lap fractions. A corner
+/// across the line starts after it ends.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Corner {
+    pub name: String, // e.g. "Tamburello" or "T2"
+    pub start: f32,   // Lap fraction the corner starts at
+    pub end: f32,     // Lap fraction the corner ends at
+}
+
+#[derive(Debug, Default, Deserialize)]
+struct Dataset {
+    #[serde(default)]
+    cars: Vec<CarAsset>,
+
+    #[serde(default)]
+    tracks: Vec<TrackAsset>,
+}
+
+///
+/// Asset Catalog
+///
+/// Resolves the `CarPath` and `TrackName` in the session info to display
+/// names, logos, track lengths and sectors, so overlays don't need their own
+/// lookup tables.
+///
+/// The bundled dataset covers a few common cars and tracks; load a JSON file
+/// with the same layout (`{"cars": [...], "tracks": [...]}`) to add entries or
+/// replace bundled ones. Anything not in the catalog falls back to what the
+/// session info says.
+///
+/// # Examples
+///
+/// ```no_run
+/// use iracing::assets::AssetCatalog;
+/// # let session: iracing::session::SessionDetails =
+/// #     serde_yaml::from_str(&std::fs::read_to_string("./session.yaml").unwrap()).unwrap();
+///
+/// let mut catalog = AssetCatalog::bundled();
+/// catalog.load("my_assets.json").unwrap();
+///
+/// let track = catalog.resolve_track(&session.weekend);
+/// println!("{} ({:.3} km)", track.display_name, track.length_km);
+///
+/// for driver in session.drivers.other_drivers.iter() {
+///     println!("{}: {}", driver.user_name, catalog.resolve_car(driver).name);
+/// }
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct AssetCatalog {
+    cars: HashMap<String, CarAsset>,
+    tracks: HashMap<String, TrackAsset>,
+}
+
+impl AssetCatalog {
+    /// An empty catalog.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// The catalog bundled with the crate.
+    pub fn bundled() -> Self {
+        let mut catalog = Self::new();
+        catalog
+            .extend_from_json(BUNDLED)
+            .expect("Bundled asset data is invalid");
+        catalog
+    }
+
+    ///
+    /// Add entries from a JSON file, replacing any with the same key.
+    pub fn load<P: AsRef<Path>>(&mut self, path: P) -> Result<(), Box<dyn Error>> {
+        let content = fs::read_to_string(path)?;
+        self.extend_from_json(&content)?;
+        Ok(())
+    }
+
+    ///
+    /// Add entries from a JSON string, replacing any with the same key.
+    pub fn extend_from_json(&mut self, json: &str) -> serde_json::Result<()> {
+        let dataset: Dataset = serde_json::from_str(json)?;
+
+        for car in dataset.cars {
+            self.add_car(car);
+        }
+        for track in dataset.tracks {
+            self.add_track(track);
+        }
+
+        Ok(())
+    }
+
+    pub fn add_car(&mut self, car: CarAsset) {
+        self.cars.insert(car.path.clone(), car);
+    }
+
+    pub fn add_track(&mut self, track: TrackAsset) {
+        self.tracks.insert(track.name.clone(), track);
+    }
+
+    /// Car by its `CarPath`.
+    pub fn car(&self, path: &str) -> Option<&CarAsset> {
+        self.cars.get(path)
+    }
+
+    /// Track by its `TrackName`.
+    pub fn track(&self, name: &str) -> Option<&TrackAsset> {
+        self.tracks.get(name)
+    }
+
+    ///
+    /// Metadata for a driver's car, built from the session info if the car
+    /// isn't in the catalog.
+    pub fn resolve_car(&self, driver: &Driver) -> CarAsset {
+        if let Some(car) = self.car(&driver.car_path) {
+            return car.clone();
+        }
+
+        CarAsset {
+            path: driver.car_path.clone(),
+            name: driver.car_screen_name.clone(),
+            short_name: driver.car_screen_name_short.clone(),
+            make: String::new(),
+            class: driver.car_class_short_name.clone(),
+            logo: None,
+        }
+    }
+
+    ///
+    /// Metadata for the weekend's track, built from the session info if the
+    /// track isn't in the catalog.
+    pub fn resolve_track(&self, weekend: &WeekendInfo) -> TrackAsset {
+        if let Some(track) = self.track(&weekend.track_name) {
+            return track.clone();
+        }
+
+        TrackAsset {
+            name: weekend.track_name.clone(),
+            display_name: weekend.track_display_name.clone(),
+            short_name: weekend.track_display_short_name.clone(),
+            config: weekend.track_config_name.clone(),
+            length_km: weekend
+                .length()
+                .map_or(0.0, |length| length.kilometers() as f32),
+            sectors: Vec::new(),
+            logo: None,
+            map: None,
+            corners: Vec::new(),
+        }
+    }
+}
+
+impl TrackAsset {
+    ///
+    /// Sector (0-based) containing a lap distance percentage, if sectors are
+    /// known.
+    pub fn sector_at(&self, lap_dist_pct: f32) -> Option<usize> {
+        if self.sectors.is_empty() {
+            return None;
+        }
+
+        let sector = self
+            .sectors
+            .iter()
+            .rposition(|start| lap_dist_pct >= *start)
+            .unwrap_or(0);
+        Some(sector)
+    }
+
+    /// The corner containing a lap distance percentage, if any.
+    pub fn corner_at(&self, lap_dist_pct: f32) -> Option<&Corner> {
+        self.corners.iter().find(|c| c.contains(lap_dist_pct))
+    }
+}
+
+impl Corner {
+    pub fn contains(&self, lap_dist_pct: f32) -> bool {
+        if self.start <= self.end {
+            lap_dist_pct >= self.start && lap_dist_pct < self.end
+        } else {
+            lap_dist_pct >= self.start || lap_dist_pct < self.end
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::session::SessionDetails;
+
+    #[test]
+    fn resolve_assets() {
+        let content = std::fs::read_to_string("./session.yaml").unwrap();
+        let session: SessionDetails = serde_yaml::from_str(&content).unwrap();
+        let mut catalog = AssetCatalog::bundled();
+
+        let track = catalog.resolve_track(&session.weekend);
+        assert_eq!(track.short_name, "Imola");
+        assert_eq!(track.sector_at(0.5), Some(1));
+        assert_eq!(track.sector_at(0.9), Some(2));
+
+        let drivers = &session.drivers.other_drivers;
+        assert_eq!(catalog.resolve_car(&drivers[1]).make, "Porsche");
+
+        catalog
+            .extend_from_json(
+                r#"{"tracks": [{"name": "imola gp", "display_name": "Imola", "short_name": "IMO", "length_km": 4.909}]}"#,
+            )
+            .unwrap();
+        assert_eq!(catalog.resolve_track(&session.weekend).short_name, "IMO");
+
+        let mut empty = AssetCatalog::new();
+        empty.extend_from_json("{}").unwrap();
+
+        let fallback = empty.resolve_track(&session.weekend);
+        assert_eq!(fallback.display_name, "Autodromo Enzo e Dino Ferrari");
+        assert!((fallback.length_km - 4.86).abs() < 1e-3);
+        assert_eq!(fallback.sector_at(0.5), None);
+        assert_eq!(
+            empty.resolve_car(&drivers[1]).name,
+            drivers[1].car_screen_name
+        );
+    }
+}
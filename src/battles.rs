@@ -0,0 +1,308 @@
+use crate::validity::{self, Sentinel};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+#[cfg(feature = "telemetry")]
+use crate::telemetry::Sample;
+#[cfg(feature = "telemetry")]
+use std::convert::TryInto;
+#[cfg(feature = "telemetry")]
+use std::error::Error;
+
+///
+/// Battle Sample
+///
+/// Per-car track positions at a point in time, indexed by car index.
+#[derive(Debug, Clone, Default)]
+pub struct BattleSample {
+    pub session_time: f64,      // Seconds since session start
+    pub positions: Vec<i32>,    // CarIdxPosition
+    pub laps: Vec<i32>,         // CarIdxLap
+    pub lap_dist_pct: Vec<f32>, // CarIdxLapDistPct - -1 when the car isn't on track
+    pub est_time: Vec<f32>, // CarIdxEstTime - estimated time to reach the current location on track (s)
+    pub on_pit_road: Vec<bool>, // CarIdxOnPitRoad
+}
+
+///
+/// A group of cars running close together.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Battle {
+    pub cars: Vec<usize>, // Car indexes, front to back
+    pub position: i32,    // Position being fought for (that of the front car)
+    pub gaps: Vec<f32>,   // Gap from each car to the car ahead of it (s)
+    pub corners: u32,     // Corners the group has run together
+    pub swaps: u32,       // Changes of order within the group
+    pub started: f64,     // Session time the battle began
+    pub intensity: f32,   // Higher is closer and busier
+}
+
+#[derive(Debug, Copy, Clone)]
+struct Pair {
+    ahead: usize,
+    corners: u32,
+    swaps: u32,
+    started: f64,
+}
+
+///
+/// Battles
+///
+/// Identifies groups of cars within a time gap of each other which have stayed
+/// together over several corners, ranked by intensity.
+///
+/// Corners are given as lap distance percentages. Without them the lap is
+/// split into ten equal segments.
+///
+/// # Examples
+///
+/// ```
+/// use iracing::battles::{BattleSample, Battles};
+///
+/// let mut battles = Battles::new(92.5).with_max_gap(1.0).with_corners(vec![0.08, 0.31, 0.55, 0.8]);
+///
+/// for battle in battles.update(&BattleSample::default()) {
+///     println!("{:?} fighting for P{}", battle.cars, battle.position);
+/// }
+/// ```
+#[derive(Debug, Clone)]
+pub struct Battles {
+    lap_time: f32,
+    max_gap: f32,
+    min_corners: u32,
+    corners: Vec<f32>,
+    pairs: HashMap<(usize, usize), Pair>,
+    last_pct: Vec<f32>,
+    battles: Vec<Battle>,
+}
+
+impl BattleSample {
+    ///
+    /// Read a battle sample from a telemetry sample.
+    #[cfg(feature = "telemetry")]
+    pub fn from_sample(sample: &Sample) -> Result<Self, Box<dyn Error>> {
+        Ok(BattleSample {
+            session_time: sample.get("SessionTime")?.try_into()?,
+            positions: sample.get("CarIdxPosition")?.try_into()?,
+            laps: sample.get("CarIdxLap")?.try_into()?,
+            lap_dist_pct: sample.get("CarIdxLapDistPct")?.try_into()?,
+            est_time: sample.get("CarIdxEstTime")?.try_into()?,
+            on_pit_road: sample.get("CarIdxOnPitRoad")?.into(),
+        })
+    }
+}
+
+impl Battles {
+    ///
+    /// Create a battle detector for a track with the given estimated lap time (s).
+    pub fn new(lap_time: f32) -> Self {
+        Battles {
+            lap_time,
+            max_gap: 1.0,
+            min_corners: 3,
+            corners: (0..10).map(|i| i as f32 / 10.0).collect(),
+            pairs: HashMap::new(),
+            last_pct: Vec::new(),
+            battles: Vec::new(),
+        }
+    }
+
+    /// Largest gap between two cars for them to be battling (s).
+    pub fn with_max_gap(mut self, seconds: f32) -> Self {
+        self.max_gap = seconds;
+        self
+    }
+
+    /// Number of corners cars must stay together before a battle is reported.
+    pub fn with_min_corners(mut self, corners: u32) -> Self {
+        self.min_corners = corners;
+        self
+    }
+
+    /// Lap distance percentages of the track's corners.
+    pub fn with_corners(mut self, corners: Vec<f32>) -> Self {
+        self.corners = corners;
+        self
+    }
+
+    ///
+    /// Update with a new sample, returning the current battles, most intense first.
+    pub fn update(&mut self, sample: &BattleSample) -> &[Battle] {
+        let count = sample.lap_dist_pct.len();
+        self.last_pct.resize(count, -1.0);
+
+        // Cars running on track, furthest round first
+        let mut order: Vec<(usize, f64)> = (0..count)
+            .filter(|&idx| {
+                validity::lap_dist_pct(sample.lap_dist_pct[idx]).is_some()
+                    && !sample.on_pit_road.get(idx).copied().unwrap_or(false)
+            })
+            .map(|idx| {
+                let lap = sample.laps.get(idx).copied().unwrap_or(0);
+                (idx, lap as f64 + sample.lap_dist_pct[idx] as f64)
+            })
+            .collect();
+        order.sort_by(|a, b| b.1.partial_cmp(&a.1).unwrap_or(std::cmp::Ordering::Equal));
+
+        let mut pairs = HashMap::new();
+        let mut gaps = HashMap::new();
+
+        for window in order.windows(2) {
+            let ((ahead, ahead_dist), (behind, behind_dist)) = (window[0], window[1]);
+            if ahead_dist - behind_dist > 0.5 {
+                continue;
+            }
+
+            let gap = match self.gap(sample, ahead, behind) {
+                Some(gap) if gap <= self.max_gap => gap,
+                _ => continue,
+            };
+
+            let key = (ahead.min(behind), ahead.max(behind));
+            let crossed = self.corners_crossed(self.last_pct[behind], sample.lap_dist_pct[behind]);
+            let pair = match self.pairs.get(&key) {
+                Some(pair) => Pair {
+                    ahead,
+                    corners: pair.corners + crossed,
+                    swaps: pair.swaps + (pair.ahead != ahead) as u32,
+                    started: pair.started,
+                },
+                None => Pair {
+                    ahead,
+                    corners: 0,
+                    swaps: 0,
+                    started: sample.session_time,
+                },
+            };
+
+            pairs.insert(key, pair);
+            gaps.insert(behind, gap);
+        }
+
+        self.pairs = pairs;
+        self.last_pct.copy_from_slice(&sample.lap_dist_pct);
+
+        // Chain sustained pairs into groups
+        let mut battles: Vec<Battle> = Vec::new();
+        let mut current: Option<Battle> = None;
+
+        for window in order.windows(2) {
+            let (ahead, behind) = (window[0].0, window[1].0);
+            let key = (ahead.min(behind), ahead.max(behind));
+
+            match self.pairs.get(&key) {
+                Some(pair) if pair.corners >= self.min_corners => {
+                    let battle = current.get_or_insert_with(|| Battle {
+                        cars: vec![ahead],
+                        position: sample.positions.get(ahead).copied().unwrap_or(0),
+                        gaps: Vec::new(),
+                        corners: pair.corners,
+                        swaps: 0,
+                        started: pair.started,
+                        intensity: 0.0,
+                    });
+
+                    battle.cars.push(behind);
+                    battle.gaps.push(gaps[&behind]);
+                    battle.corners = battle.corners.min(pair.corners);
+                    battle.swaps += pair.swaps;
+                    battle.started = battle.started.min(pair.started);
+                }
+                _ => battles.extend(current.take()),
+            }
+        }
+        battles.extend(current.take());
+
+        for battle in battles.iter_mut() {
+            let closeness: f32 = battle.gaps.iter().map(|gap| 1.0 - gap / self.max_gap).sum();
+            battle.intensity = closeness + 0.5 * battle.swaps as f32;
+        }
+
+        battles.sort_by(|a, b| {
+            b.intensity
+                .partial_cmp(&a.intensity)
+                .unwrap_or(std::cmp::Ordering::Equal)
+                .then(a.position.cmp(&b.position))
+        });
+
+        self.battles = battles;
+        &self.battles
+    }
+
+    /// The battles found by the last update, most intense first.
+    pub fn battles(&self) -> &[Battle] {
+        &self.battles
+    }
+
+    /// Battle a given car is part of.
+    pub fn for_car(&self, car_idx: usize) -> Option<&Battle> {
+        self.battles.iter().find(|b| b.cars.contains(&car_idx))
+    }
+
+    /// Gap in seconds from one car to the car ahead of it on track, if both
+    /// cars' estimated times are valid.
+    fn gap(&self, sample: &BattleSample, ahead: usize, behind: usize) -> Option<f32> {
+        let policy = Sentinel::for_channel("CarIdxEstTime");
+        let est = |idx: usize| policy.check(*sample.est_time.get(idx)?).value();
+        let mut gap = est(ahead)? - est(behind)?;
+
+        if sample.lap_dist_pct[ahead] < sample.lap_dist_pct[behind] {
+            gap += self.lap_time;
+        }
+
+        validity::gap(gap.max(0.0) as f64).map(|gap| gap as f32)
+    }
+
+    fn corners_crossed(&self, from: f32, to: f32) -> u32 {
+        if from < 0.0 || to < 0.0 {
+            return 0;
+        }
+
+        self.corners
+            .iter()
+            .filter(|&&corner| {
+                if to >= from {
+                    corner > from && corner <= to
+                } else {
+                    corner > from || corner <= to
+                }
+            })
+            .count() as u32
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn find_battles() {
+        let mut battles = Battles::new(100.0).with_max_gap(1.0).with_min_corners(2);
+
+        // Cars 1 and 2 run together, car 3 is well clear
+        for step in 0..40 {
+            let base = step as f32 * 0.01;
+            let sample = BattleSample {
+                session_time: step as f64,
+                positions: vec![0, 1, 2, 3],
+                laps: vec![0, 5, 5, 5],
+                lap_dist_pct: vec![-1.0, base + 0.5, base + 0.495, base + 0.1],
+                est_time: vec![
+                    0.0,
+                    100.0 * (base + 0.5),
+                    100.0 * (base + 0.495),
+                    100.0 * (base + 0.1),
+                ],
+                on_pit_road: vec![false; 4],
+            };
+            battles.update(&sample);
+        }
+
+        let current = battles.battles();
+        assert_eq!(current.len(), 1);
+        assert_eq!(current[0].cars, vec![1, 2]);
+        assert_eq!(current[0].position, 1);
+        assert!((current[0].gaps[0] - 0.5).abs() < 1e-3);
+        assert!(current[0].corners >= 2);
+        assert!(battles.for_car(3).is_none());
+    }
+}
@@ -0,0 +1,219 @@
+use serde::{Deserialize, Serialize};
+
+#[cfg(feature = "telemetry")]
+use crate::telemetry::Sample;
+#[cfg(feature = "telemetry")]
+use std::convert::TryInto;
+#[cfg(feature = "telemetry")]
+use std::error::Error;
+
+///
+/// Boundary Sample
+///
+/// The session the sim is in at a point in time.
+#[derive(Debug, Copy, Clone, Default, PartialEq, Eq)]
+pub struct BoundarySample {
+    pub session_num: i32,       // SessionNum - Session of the event
+    pub session_unique_id: i32, // SessionUniqueID - New for every session joined
+}
+
+///
+/// What changed at a session boundary.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum BoundaryKind {
+    /// The event moved on to its next session, such as practice to qualifying
+    Session,
+
+    /// A different server or event was joined
+    Server,
+}
+
+///
+/// A session transition, from the session the tracker was last in to the one
+/// it is in now.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SessionBoundary {
+    pub kind: BoundaryKind,
+    pub previous: (i32, i32), // Session unique ID and number left
+    pub session_num: i32,
+    pub session_unique_id: i32,
+}
+
+///
+/// Session Key
+///
+/// Identifies a session for everything stored about it, so data from
+/// reconnecting to the same session is merged and data from different
+/// sessions never mixes.
+///
+/// Hosted and official sessions are identified by their sub-session ID, which
+/// stays the same across reconnects. Offline sessions all have sub-session 0,
+/// so the sim's `SessionUniqueID` is used instead.
+///
+/// # Examples
+///
+/// ```
+/// use iracing::boundary::{BoundarySample, SessionKey};
+///
+/// let sample = BoundarySample { session_num: 2, session_unique_id: 41 };
+///
+/// assert_eq!(SessionKey::new(31470051, &sample).name(), "31470051-2");
+/// assert_eq!(SessionKey::new(0, &sample).name(), "offline-41-2");
+/// ```
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct SessionKey {
+    pub sub_session_id: i32,    // SubSessionID of the session info, 0 offline
+    pub session_unique_id: i32, // SessionUniqueID
+    pub session_num: i32,       // SessionNum
+}
+
+///
+/// Boundary Tracker
+///
+/// Reports a `SessionBoundary` when the sim moves to a new session, so
+/// recordings and exports can be split by session rather than by run of the
+/// app.
+///
+/// # Examples
+///
+/// ```
+/// use iracing::boundary::{BoundaryKind, BoundarySample, BoundaryTracker};
+///
+/// let mut tracker = BoundaryTracker::new();
+/// let practice = BoundarySample { session_num: 0, session_unique_id: 7 };
+/// let qualifying = BoundarySample { session_num: 1, ..practice };
+///
+/// assert!(tracker.update(&practice).is_none());
+/// assert_eq!(tracker.update(&qualifying).unwrap().kind, BoundaryKind::Session);
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct BoundaryTracker {
+    current: Option<BoundarySample>,
+}
+
+impl BoundarySample {
+    ///
+    /// Read a boundary sample from a telemetry sample.
+    #[cfg(feature = "telemetry")]
+    pub fn from_sample(sample: &Sample) -> Result<Self, Box<dyn Error>> {
+        Ok(BoundarySample {
+            session_num: sample.get("SessionNum")?.try_into()?,
+            session_unique_id: sample.get("SessionUniqueID")?.try_into()?,
+        })
+    }
+}
+
+impl SessionBoundary {
+    ///
+    /// Name for files holding the new session, as `<unique id>-<session num>`.
+    pub fn file_stem(&self) -> String {
+        format!("{}-{}", self.session_unique_id, self.session_num)
+    }
+}
+
+impl SessionKey {
+    pub fn new(sub_session_id: i32, sample: &BoundarySample) -> Self {
+        SessionKey {
+            sub_session_id,
+            session_unique_id: sample.session_unique_id,
+            session_num: sample.session_num,
+        }
+    }
+
+    pub fn is_offline(&self) -> bool {
+        self.sub_session_id <= 0
+    }
+
+    ///
+    /// Name for the session's files and database rows, as
+    /// `<sub-session id>-<session num>`, or `offline-<unique id>-<session num>`.
+    pub fn name(&self) -> String {
+        if self.is_offline() {
+            format!("offline-{}-{}", self.session_unique_id, self.session_num)
+        } else {
+            format!("{}-{}", self.sub_session_id, self.session_num)
+        }
+    }
+
+    ///
+    /// True if both keys are sessions of the same event, such as its
+    /// qualifying and race.
+    pub fn same_event(&self, other: &SessionKey) -> bool {
+        match (self.is_offline(), other.is_offline()) {
+            (false, false) => self.sub_session_id == other.sub_session_id,
+            (true, true) => self.session_unique_id == other.session_unique_id,
+            _ => false,
+        }
+    }
+}
+
+impl BoundaryTracker {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// The session the tracker is in, once it has been updated.
+    pub fn current(&self) -> Option<BoundarySample> {
+        self.current
+    }
+
+    ///
+    /// Update with a new sample, returning the boundary crossed if the session
+    /// changed. The first sample sets the session without reporting one.
+    pub fn update(&mut self, sample: &BoundarySample) -> Option<SessionBoundary> {
+        let previous = self.current.replace(*sample)?;
+
+        let kind = if sample.session_unique_id != previous.session_unique_id {
+            BoundaryKind::Server
+        } else if sample.session_num != previous.session_num {
+            BoundaryKind::Session
+        } else {
+            return None;
+        };
+
+        Some(SessionBoundary {
+            kind,
+            previous: (previous.session_unique_id, previous.session_num),
+            session_num: sample.session_num,
+            session_unique_id: sample.session_unique_id,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn detect_boundaries() {
+        let mut tracker = BoundaryTracker::new();
+        let mut sample = BoundarySample {
+            session_num: 0,
+            session_unique_id: 3,
+        };
+
+        assert_eq!(tracker.update(&sample), None);
+        assert_eq!(tracker.update(&sample), None);
+
+        sample.session_num = 2;
+        let boundary = tracker.update(&sample).unwrap();
+        assert_eq!(boundary.kind, BoundaryKind::Session);
+        assert_eq!(boundary.previous, (3, 0));
+        assert_eq!(boundary.file_stem(), "3-2");
+
+        // Joining another server in the same session number is still a boundary
+        sample.session_unique_id = 4;
+        let boundary = tracker.update(&sample).unwrap();
+        assert_eq!(boundary.kind, BoundaryKind::Server);
+        assert_eq!(boundary.previous, (3, 2));
+        assert_eq!(tracker.current(), Some(sample));
+
+        // Reconnecting to a hosted session is the same session, offline it isn't
+        let practice = SessionKey::new(7, &BoundarySample::default());
+        let race = SessionKey::new(7, &sample);
+        assert!(practice.same_event(&race));
+        assert_eq!(race.name(), "7-2");
+        assert!(!SessionKey::new(0, &BoundarySample::default())
+            .same_event(&SessionKey::new(0, &sample)));
+    }
+}
@@ -24,6 +24,48 @@ impl From<ReplayPositionMode> for u16 {
     }
 }
 
+///
+/// Replay State Mode
+///
+#[repr(u16)]
+pub enum ReplayStateMode {
+    /// Erase the replay tape
+    EraseTape = 0,
+}
+
+impl From<ReplayStateMode> for u16 {
+    fn from(mode: ReplayStateMode) -> Self {
+        mode as u16
+    }
+}
+
+///
+/// Camera Focus
+///
+/// What the camera should focus on when switching by position.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum CameraFocus {
+    Incident,
+    Leader,
+    Exiting,
+
+    /// Car in a given race position
+    Position(u8),
+}
+
+impl From<CameraFocus> for u16 {
+    fn from(focus: CameraFocus) -> Self {
+        let mode: i16 = match focus {
+            CameraFocus::Incident => -3,
+            CameraFocus::Leader => -2,
+            CameraFocus::Exiting => -1,
+            CameraFocus::Position(position) => position.into(),
+        };
+
+        mode as u16
+    }
+}
+
 ///
 /// Replay Search Mode
 ///
@@ -96,6 +138,9 @@ pub enum PitCommandMode {
     ClearTearoff,
     ClearFastRepair,
     ClearFuel,
+
+    /// Change to the given tire compound, as listed in the car's setup
+    TireCompound(u8),
 }
 
 impl PitCommandMode {
@@ -114,6 +159,28 @@ impl PitCommandMode {
             PitCommandMode::ClearTearoff => (9, 0),
             PitCommandMode::ClearFastRepair => (10, 0),
             PitCommandMode::ClearFuel => (11, 0),
+            PitCommandMode::TireCompound(compound) => (12, compound as u16),
+        }
+    }
+}
+
+///
+/// Force Feedback Command Mode
+///
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum FFBCommandMode {
+    /// Set the steering wheel max force (Nm)
+    MaxForce(f32),
+}
+
+impl FFBCommandMode {
+    /// Encode into (var1, var2, var3) words; the value is sent as 16.16 fixed point across var2 and var3.
+    pub fn encode(self) -> (u16, u16, u16) {
+        match self {
+            FFBCommandMode::MaxForce(force) => {
+                let fixed = (force * 65536.0) as i32 as u32;
+                (0, fixed as u16, (fixed >> 16) as u16)
+            }
         }
     }
 }
@@ -171,20 +238,25 @@ trait BroadcastMessageProvider {
 /// ```
 pub enum BroadcastMessage {
     CameraSwitchPosition(u8, u8, u8),
+    CameraSwitchFocus(CameraFocus, u8, u8),
     CameraSwitchNumber(String, u8, u8),
     CameraSetState(CameraState),
-    ReplaySetPlaySpeed(u8, bool),
-    ReplaySetPlayPosition(ReplayPositionMode, u16),
+
+    /// Play speed, negative to rewind, and whether the speed is a slow motion divisor
+    ReplaySetPlaySpeed(i8, bool),
+    ReplaySetPlayPosition(ReplayPositionMode, u32),
     ReplaySearch(ReplaySearchMode),
-    ReplaySetState,
+
+    /// Prefer `iracing::replay::EraseRequest`, which confirms and checks the erase
+    ReplaySetState(ReplayStateMode),
     ReloadAllTextures,
     ReloadTextures(u8),
     ChatCommand(ChatCommandMode),
     ChatCommandMacro(u8),
     PitCommand(PitCommandMode),
     TelemetryCommand(TelemetryCommandMode),
-    FFBCommand(u16),
-    ReplaySearchSessionTime(u8, u16),
+    FFBCommand(FFBCommandMode),
+    ReplaySearchSessionTime(u8, u32),
     VideoCapture(VideoCaptureMode),
 }
 
@@ -197,6 +269,12 @@ impl BroadcastMessageProvider for BroadcastMessage {
                 group.into(),
                 camera.into(),
             ),
+            BroadcastMessage::CameraSwitchFocus(focus, group, camera) => (
+                BroadcastMessageType::CameraSwitchPosition,
+                focus.into(),
+                group.into(),
+                camera.into(),
+            ),
             BroadcastMessage::CameraSwitchNumber(car_number, group, camera) => (
                 BroadcastMessageType::CameraSwitchNumber,
                 pad_car_number(&car_number),
@@ -211,24 +289,29 @@ impl BroadcastMessageProvider for BroadcastMessage {
             ),
             BroadcastMessage::ReplaySetPlaySpeed(speed, slow_motion) => (
                 BroadcastMessageType::ReplaySetPlaySpeed,
-                speed.into(),
+                i16::from(speed) as u16,
                 slow_motion.into(),
                 0,
             ),
             BroadcastMessage::ReplaySetPlayPosition(mode, frame_number) => (
                 BroadcastMessageType::ReplaySetPlayPosition,
                 mode.into(),
-                frame_number.into(),
-                0,
+                frame_number as u16,
+                (frame_number >> 16) as u16,
             ),
             BroadcastMessage::ReplaySearch(mode) => {
                 (BroadcastMessageType::ReplaySearch, mode.into(), 0, 0)
             }
-            BroadcastMessage::ReplaySetState => (BroadcastMessageType::ReplaySetState, 0, 0, 0),
-            BroadcastMessage::ReloadAllTextures => (BroadcastMessageType::ReloadTextures, 0, 0, 0),
-            BroadcastMessage::ReloadTextures(car_index) => {
-                (BroadcastMessageType::ReloadTextures, car_index.into(), 0, 0)
+            BroadcastMessage::ReplaySetState(mode) => {
+                (BroadcastMessageType::ReplaySetState, mode.into(), 0, 0)
             }
+            BroadcastMessage::ReloadAllTextures => (BroadcastMessageType::ReloadTextures, 0, 0, 0),
+            BroadcastMessage::ReloadTextures(car_index) => (
+                BroadcastMessageType::ReloadTextures,
+                1, // Reload a single car
+                car_index.into(),
+                0,
+            ),
             BroadcastMessage::ChatCommand(mode) => {
                 (BroadcastMessageType::ChatCommand, mode.into(), 0, 0)
             }
@@ -245,17 +328,15 @@ impl BroadcastMessageProvider for BroadcastMessage {
             BroadcastMessage::TelemetryCommand(mode) => {
                 (BroadcastMessageType::TelemetryCommand, mode.into(), 0, 0)
             }
-            BroadcastMessage::FFBCommand(_value) => (
-                BroadcastMessageType::FFBCommand,
-                0,
-                0, // (value * 65536).into(),
-                0,
-            ),
+            BroadcastMessage::FFBCommand(mode) => {
+                let (var1, var2, var3) = mode.encode();
+                (BroadcastMessageType::FFBCommand, var1, var2, var3)
+            }
             BroadcastMessage::ReplaySearchSessionTime(session_number, session_time_ms) => (
                 BroadcastMessageType::ReplaySearchSessionTime,
                 session_number.into(),
-                session_time_ms,
-                0,
+                session_time_ms as u16,
+                (session_time_ms >> 16) as u16,
             ),
             BroadcastMessage::VideoCapture(mode) => {
                 (BroadcastMessageType::VideoCapture, mode.into(), 0, 0)
@@ -326,3 +407,47 @@ impl Broadcast {
         unsafe { SendNotifyMessageW(HWND_BROADCAST, self.message_id, wparam, lparam) };
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn words(message: BroadcastMessage) -> (u16, u16, u16, u16) {
+        let (kind, var1, var2, var3) = message.to_message();
+        (kind as u16, var1, var2, var3)
+    }
+
+    #[test]
+    fn pack_messages() {
+        assert_eq!(words(BroadcastMessage::ReloadTextures(12)), (7, 1, 12, 0));
+        assert_eq!(
+            words(BroadcastMessage::ReplaySetPlaySpeed(-4, false)),
+            (3, 0xFFFC, 0, 0)
+        );
+        assert_eq!(
+            words(BroadcastMessage::CameraSwitchFocus(
+                CameraFocus::Leader,
+                1,
+                0
+            )),
+            (0, 0xFFFE, 1, 0)
+        );
+        assert_eq!(
+            words(BroadcastMessage::ReplaySetPlayPosition(
+                ReplayPositionMode::Begin,
+                0x12345
+            )),
+            (4, 0, 0x2345, 1)
+        );
+        assert_eq!(
+            words(BroadcastMessage::PitCommand(PitCommandMode::TireCompound(
+                2
+            ))),
+            (9, 12, 2, 0)
+        );
+        assert_eq!(
+            words(BroadcastMessage::ReplaySetState(ReplayStateMode::EraseTape)),
+            (6, 0, 0, 0)
+        );
+    }
+}
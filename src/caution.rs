@@ -0,0 +1,570 @@
+use crate::states::{Flags, PaceFlags, PaceMode};
+use crate::strategy::{CarState, FuelModel};
+use serde::{Deserialize, Serialize};
+use std::fmt;
+
+#[cfg(feature = "telemetry")]
+use crate::telemetry::Sample;
+#[cfg(feature = "telemetry")]
+use std::convert::TryInto;
+#[cfg(feature = "telemetry")]
+use std::error::Error;
+
+///
+/// Caution Sample
+///
+/// Session and per-car pacing state at a point in the session.
+/// Per-car values are indexed by car index.
+#[derive(Debug, Clone, Default)]
+pub struct CautionSample {
+    pub session_time: f64,          // Seconds since session start
+    pub session_flags: Flags,       // Session flags
+    pub pace_mode: PaceMode,        // Pacing mode of the field
+    pub pits_open: bool,            // Pit road is open
+    pub pace_line: Vec<i32>,        // Pace line per car (-1 when not pacing)
+    pub pace_row: Vec<i32>,         // Pace row per car (-1 when not pacing)
+    pub pace_flags: Vec<PaceFlags>, // Pacing flags per car
+}
+
+///
+/// A car's place in the pace line.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PaceAssignment {
+    pub car_idx: usize,
+    pub line: i32,
+    pub row: i32,
+
+    #[serde(skip)]
+    pub flags: PaceFlags,
+}
+
+///
+/// Events reported by the caution tracker.
+#[derive(Debug, Copy, Clone, PartialEq, Serialize, Deserialize)]
+pub enum CautionEvent {
+    CautionStarted { session_time: f64 },
+    CautionEnded { session_time: f64 },
+    PitsClosed { session_time: f64 },
+    PitsOpened { session_time: f64 },
+    WaveAround { session_time: f64, car_idx: usize },
+    FreePass { session_time: f64, car_idx: usize },
+    EndOfLine { session_time: f64, car_idx: usize },
+}
+
+///
+/// Caution Tracker
+///
+/// Combines the session flags, pace mode, and per-car pace line channels to
+/// track full-course cautions, pace line assignments, wave-arounds, free passes
+/// and the state of pit road.
+///
+/// # Examples
+///
+/// ```
+/// use iracing::caution::{CautionSample, CautionTracker};
+///
+/// let mut tracker = CautionTracker::new();
+/// for event in tracker.update(&CautionSample::default()) {
+///     println!("{:?}", event);
+/// }
+///
+/// if tracker.is_caution() {
+///     println!("Pace order: {:?}", tracker.pace_order());
+/// }
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct CautionTracker {
+    caution: bool,
+    pits_open: Option<bool>,
+    cautions: u32,
+    caution_started: Option<f64>,
+    assignments: Vec<PaceAssignment>,
+    flags: Vec<PaceFlags>,
+}
+
+///
+/// The race as seen by the caution advisor.
+#[derive(Debug, Clone, PartialEq)]
+pub struct RaceState {
+    pub car: CarState,
+    pub others: Vec<CarState>,
+    pub fuel: FuelModel,
+    pub laps_remaining: i32, // Laps left in the race
+}
+
+///
+/// What to do under a caution.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum PitCall {
+    PitNow,
+    StayOut,
+    WaitForPitsOpen,
+}
+
+///
+/// Recommendation made when a caution starts or pit road opens.
+#[derive(Debug, Copy, Clone, PartialEq, Serialize, Deserialize)]
+pub struct PitAdvice {
+    pub session_time: f64,
+    pub call: PitCall,
+    pub stop_needed: bool, // The fuel doesn't reach the finish
+    pub lost_now: usize,   // Positions lost pitting under the caution
+    pub lost_green: usize, // Positions lost making the stop under green instead, 0 if not needed
+    pub time_saved: f64,   // Time saved making a needed stop now rather than under green (s)
+}
+
+///
+/// Caution Advisor
+///
+/// Decides whether to pit under a full-course caution. When the caution
+/// starts, and again when pit road opens, it compares the positions lost
+/// pitting now with those lost making the stop under green, if the fuel
+/// doesn't reach the finish.
+///
+/// Pitting under caution costs a fraction of the green flag pit loss, as the
+/// field is running slowly; see `with_caution_loss`. Other cars are assumed to
+/// stay out. With pit road closed the advice is to wait, unless the car can't
+/// make another lap.
+///
+/// # Examples
+///
+/// ```
+/// use iracing::caution::{CautionAdvisor, CautionSample, CautionTracker, RaceState};
+/// use iracing::strategy::{CarState, FuelModel};
+///
+/// let mut tracker = CautionTracker::new();
+/// let mut advisor = CautionAdvisor::new(30.0);
+/// let state = RaceState {
+///     car: CarState { car_idx: 1, gap: 10.0, lap_time: 100.0, pit_lap: None },
+///     others: Vec::new(),
+///     fuel: FuelModel { fuel: 30.0, per_lap: 3.0, capacity: 100.0, fill_rate: 2.5 },
+///     laps_remaining: 25,
+/// };
+///
+/// let events = tracker.update(&CautionSample::default());
+/// if let Some(advice) = advisor.update(&events, &tracker, &state) {
+///     println!("{}", advice);
+/// }
+/// ```
+#[derive(Debug, Clone)]
+pub struct CautionAdvisor {
+    pit_loss: f64,
+    caution_loss: f64,
+    last: Option<PitAdvice>,
+}
+
+impl CautionSample {
+    ///
+    /// Read a caution sample from a telemetry sample.
+    #[cfg(feature = "telemetry")]
+    pub fn from_sample(sample: &Sample) -> Result<Self, Box<dyn Error>> {
+        let session_flags: u32 = sample.get("SessionFlags")?.try_into()?;
+        let pace_mode: i32 = sample.get("PaceMode")?.try_into()?;
+        let pace_flags: Vec<u32> = sample.get("CarIdxPaceFlags")?.try_into()?;
+
+        Ok(CautionSample {
+            session_time: sample.get("SessionTime")?.try_into()?,
+            session_flags: Flags::from_bits_truncate(session_flags),
+            pace_mode: PaceMode::from(pace_mode),
+            pits_open: sample.get("PitsOpen")?.into(),
+            pace_line: sample.get("CarIdxPaceLine")?.try_into()?,
+            pace_row: sample.get("CarIdxPaceRow")?.try_into()?,
+            pace_flags: pace_flags
+                .into_iter()
+                .map(PaceFlags::from_bits_truncate)
+                .collect(),
+        })
+    }
+
+    /// True if the session flags indicate a full-course caution.
+    pub fn is_caution(&self) -> bool {
+        self.session_flags
+            .intersects(Flags::CAUTION | Flags::CAUTION_WAVING)
+    }
+}
+
+impl CautionTracker {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    ///
+    /// Update the tracker with a new sample, returning any events which occurred.
+    pub fn update(&mut self, sample: &CautionSample) -> Vec<CautionEvent> {
+        let mut events = Vec::new();
+        let session_time = sample.session_time;
+
+        let caution = sample.is_caution();
+        if caution && !self.caution {
+            self.cautions += 1;
+            self.caution_started = Some(session_time);
+            events.push(CautionEvent::CautionStarted { session_time });
+        } else if !caution && self.caution {
+            self.caution_started = None;
+            events.push(CautionEvent::CautionEnded { session_time });
+        }
+        self.caution = caution;
+
+        match self.pits_open.replace(sample.pits_open) {
+            Some(true) if !sample.pits_open => {
+                events.push(CautionEvent::PitsClosed { session_time })
+            }
+            Some(false) if sample.pits_open => {
+                events.push(CautionEvent::PitsOpened { session_time })
+            }
+            _ => {}
+        }
+
+        for (car_idx, flags) in sample.pace_flags.iter().enumerate() {
+            let previous = self.flags.get(car_idx).copied().unwrap_or_default();
+            let added = *flags - previous;
+
+            if added.contains(PaceFlags::WAVED_AROUND) {
+                events.push(CautionEvent::WaveAround {
+                    session_time,
+                    car_idx,
+                });
+            }
+            if added.contains(PaceFlags::FREE_PASS) {
+                events.push(CautionEvent::FreePass {
+                    session_time,
+                    car_idx,
+                });
+            }
+            if added.contains(PaceFlags::END_OF_LINE) {
+                events.push(CautionEvent::EndOfLine {
+                    session_time,
+                    car_idx,
+                });
+            }
+        }
+        self.flags = sample.pace_flags.clone();
+
+        self.assignments = sample
+            .pace_line
+            .iter()
+            .zip(sample.pace_row.iter())
+            .enumerate()
+            .filter(|(_, (&line, &row))| line >= 0 && row >= 0)
+            .map(|(car_idx, (&line, &row))| PaceAssignment {
+                car_idx,
+                line,
+                row,
+                flags: sample.pace_flags.get(car_idx).copied().unwrap_or_default(),
+            })
+            .collect();
+        self.assignments.sort_by_key(|a| (a.row, a.line));
+
+        events
+    }
+
+    /// True while a full-course caution is in effect.
+    pub fn is_caution(&self) -> bool {
+        self.caution
+    }
+
+    /// Session time at which the current caution started.
+    pub fn caution_started(&self) -> Option<f64> {
+        self.caution_started
+    }
+
+    /// Number of cautions seen so far.
+    pub fn caution_count(&self) -> u32 {
+        self.cautions
+    }
+
+    /// True if pit road is open (defaults to open until a sample has been seen).
+    pub fn pits_open(&self) -> bool {
+        self.pits_open.unwrap_or(true)
+    }
+
+    ///
+    /// Current pace line assignments, ordered by row then line.
+    pub fn pace_order(&self) -> &[PaceAssignment] {
+        &self.assignments
+    }
+
+    /// Pace line assignment for a given car.
+    pub fn assignment(&self, car_idx: usize) -> Option<&PaceAssignment> {
+        self.assignments.iter().find(|a| a.car_idx == car_idx)
+    }
+}
+
+impl fmt::Display for PitAdvice {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let net = self.lost_green as i64 - self.lost_now as i64;
+        let positions = |n: i64| if n == 1 { "position" } else { "positions" };
+
+        match self.call {
+            PitCall::PitNow if !self.stop_needed || self.lost_green == 0 => {
+                let lost = self.lost_now as i64;
+                write!(f, "Pit now, losing {} {}", lost, positions(lost))
+            }
+            PitCall::PitNow => write!(
+                f,
+                "Pit now, {} {} better than stopping under green, saving {:.1}s",
+                net,
+                positions(net),
+                self.time_saved
+            ),
+            PitCall::StayOut => {
+                let lost = self.lost_now as i64;
+                write!(f, "Stay out, pitting loses {} {}", lost, positions(lost))
+            }
+            PitCall::WaitForPitsOpen => write!(f, "Pit when pit road opens"),
+        }
+    }
+}
+
+impl CautionAdvisor {
+    ///
+    /// Advisor for a track where driving through pit lane under green loses
+    /// `pit_loss` seconds, excluding service.
+    pub fn new(pit_loss: f64) -> Self {
+        CautionAdvisor {
+            pit_loss,
+            caution_loss: 0.5,
+            last: None,
+        }
+    }
+
+    ///
+    /// Fraction of the green flag pit loss lost pitting under caution, 0.5 by
+    /// default.
+    pub fn with_caution_loss(mut self, fraction: f64) -> Self {
+        self.caution_loss = fraction;
+        self
+    }
+
+    ///
+    /// Update with the caution tracker's latest events, returning a new
+    /// recommendation when a caution starts or pit road opens during one.
+    pub fn update(
+        &mut self,
+        events: &[CautionEvent],
+        tracker: &CautionTracker,
+        state: &RaceState,
+    ) -> Option<PitAdvice> {
+        let session_time = events.iter().find_map(|e| match e {
+            CautionEvent::CautionStarted { session_time } => Some(*session_time),
+            CautionEvent::PitsOpened { session_time } if tracker.is_caution() => {
+                Some(*session_time)
+            }
+            _ => None,
+        })?;
+
+        let advice = self.recommend(session_time, tracker.pits_open(), state);
+        self.last = Some(advice);
+        Some(advice)
+    }
+
+    /// The most recent recommendation.
+    pub fn last(&self) -> Option<&PitAdvice> {
+        self.last.as_ref()
+    }
+
+    ///
+    /// Recommend whether to pit now, under caution.
+    pub fn recommend(&self, session_time: f64, pits_open: bool, state: &RaceState) -> PitAdvice {
+        let fuel = &state.fuel;
+        let stop_needed = fuel.laps_remaining() < state.laps_remaining;
+        let service_time = fuel.fill_time(fuel.fuel, state.laps_remaining);
+
+        let caution_loss = self.pit_loss * self.caution_loss + service_time;
+        let green_loss = self.pit_loss + service_time;
+        let lost_now = self.positions_lost(state, caution_loss);
+        let lost_green = if stop_needed {
+            self.positions_lost(state, green_loss)
+        } else {
+            0
+        };
+
+        let call = if fuel.laps_remaining() < 1 {
+            // Out of fuel, pit whether open or not
+            PitCall::PitNow
+        } else if !stop_needed || lost_now > lost_green {
+            PitCall::StayOut
+        } else if !pits_open {
+            PitCall::WaitForPitsOpen
+        } else {
+            PitCall::PitNow
+        };
+
+        PitAdvice {
+            session_time,
+            call,
+            stop_needed,
+            lost_now,
+            lost_green,
+            time_saved: if stop_needed {
+                green_loss - caution_loss
+            } else {
+                0.0
+            },
+        }
+    }
+
+    /// Cars behind close enough to pass while the car loses `time` in the pits.
+    fn positions_lost(&self, state: &RaceState, time: f64) -> usize {
+        state
+            .others
+            .iter()
+            .filter(|c| c.car_idx != state.car.car_idx)
+            .filter(|c| c.gap > state.car.gap && c.gap - state.car.gap < time)
+            .count()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn caution_cycle() {
+        let mut tracker = CautionTracker::new();
+        let mut sample = CautionSample {
+            pits_open: true,
+            pace_line: vec![-1, -1, -1],
+            pace_row: vec![-1, -1, -1],
+            pace_flags: vec![PaceFlags::empty(); 3],
+            ..CautionSample::default()
+        };
+
+        assert!(tracker.update(&sample).is_empty());
+
+        sample.session_time = 10.0;
+        sample.session_flags = Flags::CAUTION_WAVING;
+        sample.pits_open = false;
+        sample.pace_line = vec![0, 1, 0];
+        sample.pace_row = vec![1, 0, 0];
+        sample.pace_flags[1] = PaceFlags::WAVED_AROUND;
+
+        let events = tracker.update(&sample);
+        assert_eq!(
+            events,
+            vec![
+                CautionEvent::CautionStarted { session_time: 10.0 },
+                CautionEvent::PitsClosed { session_time: 10.0 },
+                CautionEvent::WaveAround {
+                    session_time: 10.0,
+                    car_idx: 1
+                },
+            ]
+        );
+        assert!(tracker.is_caution());
+        assert_eq!(
+            tracker
+                .pace_order()
+                .iter()
+                .map(|a| a.car_idx)
+                .collect::<Vec<_>>(),
+            vec![2, 1, 0]
+        );
+
+        // Flags persisting across samples aren't reported again
+        sample.session_time = 11.0;
+        assert!(tracker.update(&sample).is_empty());
+
+        sample.session_time = 60.0;
+        sample.session_flags = Flags::GREEN_FLAG;
+        let events = tracker.update(&sample);
+        assert_eq!(
+            events,
+            vec![CautionEvent::CautionEnded { session_time: 60.0 }]
+        );
+        assert_eq!(tracker.caution_count(), 1);
+    }
+
+    #[test]
+    fn caution_pit_advice() {
+        let car = |car_idx, gap| CarState {
+            car_idx,
+            gap,
+            lap_time: 100.0,
+            pit_lap: None,
+        };
+        let mut state = RaceState {
+            car: car(1, 10.0),
+            others: vec![car(0, 0.0), car(2, 14.0), car(3, 22.0), car(4, 38.0)],
+            fuel: FuelModel {
+                fuel: 30.0,
+                per_lap: 3.0,
+                capacity: 100.0,
+                fill_rate: 10.0,
+            },
+            laps_remaining: 15,
+        };
+
+        let mut tracker = CautionTracker::new();
+        let mut advisor = CautionAdvisor::new(30.0);
+        let mut sample = CautionSample {
+            pits_open: true,
+            ..CautionSample::default()
+        };
+        assert!(advisor
+            .update(&tracker.update(&sample), &tracker, &state)
+            .is_none());
+
+        // 15l to add takes 1.5s, losing 16.5s now against 31.5s under green
+        sample.session_time = 10.0;
+        sample.session_flags = Flags::CAUTION;
+        sample.pits_open = false;
+        let advice = advisor
+            .update(&tracker.update(&sample), &tracker, &state)
+            .unwrap();
+        assert_eq!(advice.call, PitCall::WaitForPitsOpen);
+        assert_eq!((advice.lost_now, advice.lost_green), (2, 3));
+        assert!((advice.time_saved - 15.0).abs() < 1e-9);
+
+        sample.session_time = 20.0;
+        sample.pits_open = true;
+        let advice = advisor
+            .update(&tracker.update(&sample), &tracker, &state)
+            .unwrap();
+        assert_eq!(advice.call, PitCall::PitNow);
+        assert_eq!(
+            advice.to_string(),
+            "Pit now, 1 position better than stopping under green, saving 15.0s"
+        );
+
+        // With enough fuel to finish, pitting only loses places
+        state.laps_remaining = 10;
+        let advice = advisor.recommend(30.0, true, &state);
+        assert_eq!((advice.call, advice.stop_needed), (PitCall::StayOut, false));
+        assert_eq!(advisor.last().unwrap().session_time, 20.0);
+    }
+
+    #[cfg(feature = "telemetry")]
+    #[test]
+    fn from_sample() {
+        use crate::recording::{Recording, SnapshotBuilder};
+        use crate::telemetry::Value;
+
+        // Types as the sim publishes them, flags are bitfields
+        let mut recording = Recording::new();
+        recording.push(
+            SnapshotBuilder::new(1)
+                .with_value("SessionTime", Value::DOUBLE(300.0))
+                .with_value("SessionFlags", Value::BITS(Flags::CAUTION.bits()))
+                .with_value("PaceMode", Value::INT(3))
+                .with_value("PitsOpen", Value::BOOL(true))
+                .with_value("CarIdxPaceLine", Value::IntVec(vec![0, 1]))
+                .with_value("CarIdxPaceRow", Value::IntVec(vec![0, 0]))
+                .with_value(
+                    "CarIdxPaceFlags",
+                    Value::BitsVec(vec![0, PaceFlags::FREE_PASS.bits()]),
+                )
+                .build(),
+        );
+        let sample = recording.player().last().unwrap().unwrap();
+
+        let sample = CautionSample::from_sample(&sample).unwrap();
+        assert!(sample.is_caution());
+        assert_eq!(sample.pace_mode, PaceMode::DoubleFileRestart);
+        assert_eq!(sample.pace_line, vec![0, 1]);
+        assert_eq!(
+            sample.pace_flags,
+            vec![PaceFlags::empty(), PaceFlags::FREE_PASS]
+        );
+    }
+}
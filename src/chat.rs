@@ -0,0 +1,286 @@
+use std::io::{Error, ErrorKind, Result as IOResult};
+use std::mem::size_of;
+use std::ptr::{copy_nonoverlapping, null_mut};
+use std::sync::Mutex;
+use std::thread;
+use std::time::{Duration, Instant};
+use winapi::um::winbase::{GlobalAlloc, GlobalFree, GlobalLock, GlobalUnlock, GMEM_MOVEABLE};
+use winapi::um::winuser::{
+    CloseClipboard, EmptyClipboard, OpenClipboard, SendInput, SetClipboardData, CF_UNICODETEXT,
+    INPUT, INPUT_KEYBOARD, KEYEVENTF_KEYUP, KEYEVENTF_UNICODE, VK_CONTROL, VK_RETURN,
+};
+
+use crate::broadcast::{Broadcast, BroadcastMessage, ChatCommandMode};
+use crate::window::SimWindow;
+
+/// Sender used by `send_text`, so its rate limit applies across callers.
+static SENDER: Mutex<Option<ChatSender>> = Mutex::new(None);
+
+///
+/// Chat Sender
+///
+/// Types arbitrary chat messages into the sim. The broadcast API can only
+/// send chat macros, so this opens the chat box with a broadcast and types
+/// the text as keystrokes, which need the sim to have focus.
+///
+/// Messages are spaced at least `with_interval` apart, so automated messages
+/// don't flood the session. Typing stops if the sim loses focus part way
+/// through, rather than typing into whichever window took it.
+///
+/// Long messages such as admin commands can be pasted from the clipboard
+/// instead of typed, see `with_paste`.
+///
+/// # Examples
+///
+/// ```no_run
+/// use iracing::chat::ChatSender;
+/// use std::time::Duration;
+///
+/// let mut chat = ChatSender::new().with_interval(Duration::from_secs(5));
+///
+/// chat.send("Blue flag for #11, please let the leaders by").expect("Unable to chat");
+/// ```
+#[derive(Debug)]
+pub struct ChatSender {
+    broadcast: Broadcast,
+    interval: Duration,
+    key_delay: Duration,
+    open_delay: Duration,
+    paste: bool,
+    last_sent: Option<Instant>,
+}
+
+impl Default for ChatSender {
+    fn default() -> Self {
+        ChatSender {
+            broadcast: Broadcast::new(),
+            interval: Duration::from_secs(1),
+            key_delay: Duration::from_millis(5),
+            open_delay: Duration::from_millis(100),
+            paste: false,
+            last_sent: None,
+        }
+    }
+}
+
+impl ChatSender {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Least time between messages. 1s by default.
+    pub fn with_interval(mut self, interval: Duration) -> Self {
+        self.interval = interval;
+        self
+    }
+
+    /// Time between keystrokes. 5ms by default.
+    pub fn with_key_delay(mut self, delay: Duration) -> Self {
+        self.key_delay = delay;
+        self
+    }
+
+    /// Time allowed for the chat box to open before typing. 100ms by default.
+    pub fn with_open_delay(mut self, delay: Duration) -> Self {
+        self.open_delay = delay;
+        self
+    }
+
+    ///
+    /// Paste messages from the clipboard rather than typing them, which is
+    /// quicker and leaves less time for focus to be lost. Replaces whatever
+    /// was on the clipboard.
+    pub fn with_paste(mut self, paste: bool) -> Self {
+        self.paste = paste;
+        self
+    }
+
+    ///
+    /// Type and send a message, waiting out the interval since the last one.
+    ///
+    /// Fails if the text is empty or holds control characters such as line
+    /// breaks, or if the sim isn't running or loses focus. A message which
+    /// fails part way is cancelled, closing the chat box.
+    pub fn send(&mut self, text: &str) -> IOResult<()> {
+        validate(text)?;
+
+        if let Some(last) = self.last_sent {
+            thread::sleep((last + self.interval).saturating_duration_since(Instant::now()));
+        }
+
+        let window = SimWindow::find()
+            .ok_or_else(|| Error::new(ErrorKind::NotFound, "Sim window not found"))?;
+        window.focus()?;
+
+        self.broadcast
+            .send_message(BroadcastMessage::ChatCommand(ChatCommandMode::Begin));
+        thread::sleep(self.open_delay);
+        self.last_sent = Some(Instant::now());
+
+        let typed = self.type_message(&window, text);
+        if typed.is_err() {
+            // Otherwise the driver's next keystrokes go into the chat box
+            self.broadcast
+                .send_message(BroadcastMessage::ChatCommand(ChatCommandMode::Cancel));
+        }
+        typed
+    }
+
+    /// Type a message into the open chat box and send it.
+    fn type_message(&self, window: &SimWindow, text: &str) -> IOResult<()> {
+        if self.paste {
+            set_clipboard(text)?;
+            focused(window)?;
+            send_keys(&mut [
+                key(VK_CONTROL as u16, 0, 0),
+                key(b'V'.into(), 0, 0),
+                key(b'V'.into(), 0, KEYEVENTF_KEYUP),
+                key(VK_CONTROL as u16, 0, KEYEVENTF_KEYUP),
+            ])?;
+        } else {
+            for unit in text.encode_utf16() {
+                focused(window)?;
+                send_keys(&mut [
+                    key(0, unit, KEYEVENTF_UNICODE),
+                    key(0, unit, KEYEVENTF_UNICODE | KEYEVENTF_KEYUP),
+                ])?;
+                thread::sleep(self.key_delay);
+            }
+        }
+
+        focused(window)?;
+        send_keys(&mut [
+            key(VK_RETURN as u16, 0, 0),
+            key(VK_RETURN as u16, 0, KEYEVENTF_KEYUP),
+        ])
+    }
+}
+
+///
+/// Send a chat message from the sim, typing it into the chat box.
+///
+/// Opt in with the `chat` feature. Messages from every caller share one
+/// `ChatSender`, so are at least a second apart. See `ChatSender` to
+/// configure the pacing.
+///
+/// # Examples
+///
+/// ```no_run
+/// use iracing::chat;
+///
+/// chat::send_text("Race control: full course yellow, pits closed").expect("Unable to chat");
+/// ```
+pub fn send_text(text: &str) -> IOResult<()> {
+    let mut sender = SENDER.lock().unwrap_or_else(|e| e.into_inner());
+
+    sender.get_or_insert_with(ChatSender::new).send(text)
+}
+
+fn validate(text: &str) -> IOResult<()> {
+    if text.trim().is_empty() {
+        return Err(Error::new(ErrorKind::InvalidInput, "Empty chat message"));
+    }
+    if text.chars().any(char::is_control) {
+        // A line break would send the message early
+        return Err(Error::new(
+            ErrorKind::InvalidInput,
+            "Chat messages can't hold control characters",
+        ));
+    }
+
+    Ok(())
+}
+
+/// Keystrokes go to whichever window has focus, so stop if it isn't the sim.
+fn focused(window: &SimWindow) -> IOResult<()> {
+    if window.is_focused() {
+        Ok(())
+    } else {
+        Err(Error::new(
+            ErrorKind::Interrupted,
+            "Sim lost focus while typing",
+        ))
+    }
+}
+
+fn set_clipboard(text: &str) -> IOResult<()> {
+    let wide: Vec<u16> = text.encode_utf16().chain(std::iter::once(0)).collect();
+
+    unsafe {
+        if OpenClipboard(null_mut()) == 0 {
+            return Err(Error::last_os_error());
+        }
+        EmptyClipboard();
+
+        let memory = GlobalAlloc(GMEM_MOVEABLE, wide.len() * size_of::<u16>());
+        let target = if memory.is_null() {
+            null_mut()
+        } else {
+            GlobalLock(memory) as *mut u16
+        };
+        if target.is_null() {
+            let error = Error::last_os_error();
+            if !memory.is_null() {
+                GlobalFree(memory);
+            }
+            CloseClipboard();
+            return Err(error);
+        }
+
+        copy_nonoverlapping(wide.as_ptr(), target, wide.len());
+        GlobalUnlock(memory);
+
+        // The clipboard owns the memory once set
+        if SetClipboardData(CF_UNICODETEXT, memory).is_null() {
+            let error = Error::last_os_error();
+            GlobalFree(memory);
+            CloseClipboard();
+            return Err(error);
+        }
+        CloseClipboard();
+    }
+
+    Ok(())
+}
+
+fn key(vk: u16, scan: u16, flags: u32) -> INPUT {
+    let mut input = INPUT {
+        type_: INPUT_KEYBOARD,
+        u: unsafe { std::mem::zeroed() },
+    };
+
+    let ki = unsafe { input.u.ki_mut() };
+    ki.wVk = vk;
+    ki.wScan = scan;
+    ki.dwFlags = flags;
+
+    input
+}
+
+fn send_keys(inputs: &mut [INPUT]) -> IOResult<()> {
+    let sent = unsafe {
+        SendInput(
+            inputs.len() as u32,
+            inputs.as_mut_ptr(),
+            size_of::<INPUT>() as i32,
+        )
+    };
+
+    if sent as usize == inputs.len() {
+        Ok(())
+    } else {
+        Err(Error::last_os_error())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn validate_messages() {
+        assert!(validate("Good race all, see you next week 🏁").is_ok());
+        assert!(validate("   ").is_err());
+        assert!(validate("Blue flag\n#11").is_err());
+    }
+}
@@ -0,0 +1,1005 @@
+use crate::archive::ArchivedEvent;
+use crate::boundary::{BoundaryKind, BoundarySample, BoundaryTracker, SessionBoundary, SessionKey};
+use crate::session::sub_session_id;
+use crate::telemetry::{Sample, SampleLayout};
+use serde::{Deserialize, Serialize};
+use std::convert::TryInto;
+use std::error::Error;
+use std::fmt;
+use std::fs::File;
+use std::io::{self, BufReader, BufWriter, Read, Seek, SeekFrom, Write};
+use std::path::{Path, PathBuf};
+
+#[cfg(feature = "compression")]
+use crate::compression::{CompressedReader, CompressedWriter, Compression};
+
+/// Identifies a chunked recording.
+pub const MAGIC: [u8; 8] = *b"IRCHUNKS";
+
+/// Version of the chunked format.
+pub const FORMAT_VERSION: u32 = 1;
+
+/// Samples per chunk unless set with `with_chunk_samples`: 10 seconds at 60Hz.
+pub const DEFAULT_CHUNK_SAMPLES: usize = 600;
+
+const FOOTER_MAGIC: [u8; 8] = *b"IRCHUNKX";
+const FILE_HEADER_SIZE: u64 = 16;
+const BLOCK_HEADER_SIZE: usize = 8;
+const FOOTER_SIZE: i64 = 16;
+const ROW_HEADER_SIZE: usize = 12;
+
+const BLOCK_LAYOUT: u8 = 1;
+const BLOCK_SESSION: u8 = 2;
+const BLOCK_CHUNK: u8 = 3;
+const BLOCK_EVENTS: u8 = 4;
+const BLOCK_INDEX: u8 = 5;
+
+const CODEC_NONE: u8 = 0;
+const CODEC_ZSTD: u8 = 1;
+
+#[derive(Debug)]
+pub enum ChunkedError {
+    BadMagic,
+    UnsupportedVersion(u32),
+
+    /// The file doesn't match its own index
+    Corrupt(String),
+
+    /// A block is compressed and the `compression` feature is disabled
+    Compressed,
+
+    /// A sample has no `SessionTime`
+    MissingSessionTime,
+    IO(io::Error),
+}
+
+impl fmt::Display for ChunkedError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::BadMagic => write!(f, "Not a chunked recording"),
+            Self::UnsupportedVersion(v) => write!(f, "Unsupported chunked recording version {}", v),
+            Self::Corrupt(e) => write!(f, "Corrupt chunked recording: {}", e),
+            Self::Compressed => {
+                write!(f, "Recording is compressed; enable the compression feature")
+            }
+            Self::MissingSessionTime => write!(f, "Sample has no SessionTime"),
+            Self::IO(e) => write!(f, "IO Error: {}", e),
+        }
+    }
+}
+
+impl Error for ChunkedError {}
+
+impl From<io::Error> for ChunkedError {
+    fn from(e: io::Error) -> Self {
+        Self::IO(e)
+    }
+}
+
+///
+/// Position and time span of a chunk of samples.
+#[derive(Debug, Copy, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ChunkInfo {
+    pub start_time: f64, // Session time of the first sample
+    pub end_time: f64,   // Session time of the last sample
+    pub first_tick: i32,
+    pub samples: u32,
+
+    offset: u64,
+    layout: usize,
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Serialize, Deserialize)]
+struct SessionEntry {
+    session_time: f64,
+    offset: u64,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+struct Index {
+    layouts: Vec<u64>,
+    sessions: Vec<SessionEntry>,
+    chunks: Vec<ChunkInfo>,
+    events: Option<u64>,
+}
+
+///
+/// Chunked Writer
+///
+/// Writes telemetry to the crate's own recording format, built for seeking by
+/// session time and reading only part of a long session.
+///
+/// A file is the magic `"IRCHUNKS"`, the format version and 4 reserved bytes,
+/// followed by blocks and a footer. Each block is a kind byte, a codec byte
+/// (0 for none, 1 for zstd), 2 reserved bytes and the payload length (u32),
+/// then the payload:
+///
+/// | Kind | Block    | Payload |
+/// |------|----------|---------|
+/// | 1    | Layout   | Buffer length (u32) and raw variable headers |
+/// | 2    | Session  | Session time (f64) and session info YAML |
+/// | 3    | Chunk    | Layout number (u32), then per sample its tick (i32), session time (f64) and data buffer |
+/// | 4    | Events   | Events as a JSON array |
+/// | 5    | Index    | Offsets and time spans of every other block, as JSON |
+///
+/// The footer is the offset of the index block (u64) and `"IRCHUNKX"`. All
+/// numbers are little-endian. With the `compression` feature, session, chunk
+/// and event blocks are compressed with zstd.
+///
+/// Events are held in memory and written by `finish`.
+///
+/// # Examples
+///
+/// ```no_run
+/// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+/// use iracing::chunked::{ChunkedReader, ChunkedWriter};
+/// # let samples: Vec<iracing::telemetry::Sample> = Vec::new();
+/// # let session_info = String::new();
+///
+/// let mut writer = ChunkedWriter::create("race.irc")?;
+/// writer.session_info(&session_info)?;
+/// for sample in samples.iter() {
+///     writer.push(sample)?;
+/// }
+/// writer.event(1834.5, "pit_stop", &32.1)?;
+/// writer.finish()?;
+///
+/// let mut reader = ChunkedReader::open("race.irc")?;
+/// let lap = reader.samples_between(1800.0, 1900.0)?;
+/// # Ok(())
+/// # }
+/// ```
+pub struct ChunkedWriter<W: Write> {
+    inner: W,
+    position: u64,
+    chunk_samples: usize,
+    layout: Option<SampleLayout>,
+    rows: Vec<u8>,
+    chunk: Option<ChunkInfo>,
+    session_info: Option<String>,
+    last_time: f64,
+    events: Vec<ArchivedEvent>,
+    index: Index,
+}
+
+///
+/// Chunked Reader
+///
+/// Reads a recording written by `ChunkedWriter`. Only the index is read up
+/// front; chunks are read as they are needed.
+pub struct ChunkedReader<R: Read + Seek> {
+    reader: R,
+    index: Index,
+    layouts: Vec<Option<SampleLayout>>,
+}
+
+///
+/// Rotating Writer
+///
+/// Writes a chunked recording of each session into a directory, finishing
+/// the current file and starting another at every session boundary.
+///
+/// Files are named by their `SessionKey`, as `<prefix>-<key name>.irc`, once
+/// they are finished, so the session info giving the sub-session ID may
+/// arrive after the session's first samples. Reconnecting to the same session
+/// adds a numbered part, `<prefix>-<key name>-2.irc`, beside the first.
+///
+/// Samples without `SessionNum` and `SessionUniqueID` are written to the
+/// current file.
+///
+/// # Examples
+///
+/// ```no_run
+/// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+/// use iracing::chunked::RotatingWriter;
+/// # let samples: Vec<iracing::telemetry::Sample> = Vec::new();
+/// # let session_info = String::new();
+///
+/// let mut writer = RotatingWriter::new("./recordings", "weekend")?;
+/// writer.session_info(&session_info)?;
+/// for sample in samples.iter() {
+///     if let Some(boundary) = writer.push(sample)? {
+///         println!("Now recording session {}", boundary.session_num);
+///     }
+/// }
+/// let files = writer.finish()?;
+/// # Ok(())
+/// # }
+/// ```
+pub struct RotatingWriter {
+    dir: PathBuf,
+    prefix: String,
+    chunk_samples: usize,
+    tracker: BoundaryTracker,
+    writer: Option<OpenFile>,
+    sub_session_id: i32,
+    session_info: Option<String>,
+    events: Vec<ArchivedEvent>,
+    files: Vec<PathBuf>,
+}
+
+/// A file being written by a `RotatingWriter`, with the session it holds.
+struct OpenFile {
+    path: PathBuf,
+    session: Option<BoundarySample>,
+    sub_session_id: i32, // 0 until the session info is known
+    writer: ChunkedWriter<BufWriter<File>>,
+}
+
+impl ChunkedWriter<BufWriter<File>> {
+    pub fn create<P: AsRef<Path>>(path: P) -> Result<Self, ChunkedError> {
+        Self::new(BufWriter::new(File::create(path)?))
+    }
+}
+
+impl<W: Write> ChunkedWriter<W> {
+    pub fn new(mut inner: W) -> Result<Self, ChunkedError> {
+        inner.write_all(&MAGIC)?;
+        inner.write_all(&FORMAT_VERSION.to_le_bytes())?;
+        inner.write_all(&[0; 4])?;
+
+        Ok(ChunkedWriter {
+            inner,
+            position: FILE_HEADER_SIZE,
+            chunk_samples: DEFAULT_CHUNK_SAMPLES,
+            layout: None,
+            rows: Vec::new(),
+            chunk: None,
+            session_info: None,
+            last_time: 0.0,
+            events: Vec::new(),
+            index: Index::default(),
+        })
+    }
+
+    ///
+    /// Samples per chunk. Smaller chunks make seeking cheaper and compress
+    /// less well.
+    pub fn with_chunk_samples(mut self, samples: usize) -> Self {
+        self.chunk_samples = samples.max(1);
+        self
+    }
+
+    ///
+    /// Add a sample. A new chunk is started whenever the current one is full
+    /// or the sample's layout differs from the previous one.
+    pub fn push(&mut self, sample: &Sample) -> Result<(), ChunkedError> {
+        let session_time: f64 = sample
+            .get("SessionTime")
+            .ok()
+            .and_then(|v| v.try_into().ok())
+            .ok_or(ChunkedError::MissingSessionTime)?;
+
+        let layout = SampleLayout::of(sample);
+        let changed = match &self.layout {
+            Some(current) => !current.same_values(&layout),
+            None => true,
+        };
+
+        if changed {
+            self.flush_chunk()?;
+
+            let mut payload = (layout.buffer_length() as u32).to_le_bytes().to_vec();
+            payload.extend_from_slice(&layout.to_bytes());
+
+            let offset = self.write_block(BLOCK_LAYOUT, &payload, false)?;
+            self.index.layouts.push(offset);
+            self.layout = Some(layout);
+        }
+
+        let chunk = self.chunk.get_or_insert(ChunkInfo {
+            start_time: session_time,
+            end_time: session_time,
+            first_tick: sample.tick(),
+            samples: 0,
+            offset: 0,
+            layout: self.index.layouts.len() - 1,
+        });
+        chunk.end_time = session_time;
+        chunk.samples += 1;
+
+        self.rows.extend_from_slice(&sample.tick().to_le_bytes());
+        self.rows.extend_from_slice(&session_time.to_le_bytes());
+        self.rows.extend_from_slice(sample.data());
+        self.last_time = session_time;
+
+        if chunk.samples as usize >= self.chunk_samples {
+            self.flush_chunk()?;
+        }
+
+        Ok(())
+    }
+
+    ///
+    /// Store the session info, if it has changed, at the time of the latest
+    /// sample.
+    pub fn session_info(&mut self, yaml: &str) -> Result<(), ChunkedError> {
+        if self.session_info.as_deref() == Some(yaml) {
+            return Ok(());
+        }
+
+        let mut payload = self.last_time.to_le_bytes().to_vec();
+        payload.extend_from_slice(yaml.as_bytes());
+
+        let offset = self.write_block(BLOCK_SESSION, &payload, true)?;
+        self.index.sessions.push(SessionEntry {
+            session_time: self.last_time,
+            offset,
+        });
+        self.session_info = Some(yaml.to_owned());
+
+        Ok(())
+    }
+
+    ///
+    /// Record an event, such as a pit stop or incident.
+    pub fn event<E: Serialize>(
+        &mut self,
+        session_time: f64,
+        kind: &str,
+        data: &E,
+    ) -> Result<(), ChunkedError> {
+        let data = serde_json::to_value(data).map_err(|e| ChunkedError::Corrupt(e.to_string()))?;
+        self.events.push(ArchivedEvent {
+            session_time,
+            kind: kind.to_owned(),
+            data,
+        });
+        Ok(())
+    }
+
+    ///
+    /// Write the remaining samples, the events and the index, returning the
+    /// inner writer.
+    pub fn finish(mut self) -> Result<W, ChunkedError> {
+        self.flush_chunk()?;
+
+        let events =
+            serde_json::to_vec(&self.events).map_err(|e| ChunkedError::Corrupt(e.to_string()))?;
+        self.index.events = Some(self.write_block(BLOCK_EVENTS, &events, true)?);
+
+        let index =
+            serde_json::to_vec(&self.index).map_err(|e| ChunkedError::Corrupt(e.to_string()))?;
+        let index_offset = self.write_block(BLOCK_INDEX, &index, false)?;
+
+        self.inner.write_all(&index_offset.to_le_bytes())?;
+        self.inner.write_all(&FOOTER_MAGIC)?;
+        self.inner.flush()?;
+
+        Ok(self.inner)
+    }
+
+    fn flush_chunk(&mut self) -> Result<(), ChunkedError> {
+        let mut chunk = match self.chunk.take() {
+            Some(chunk) => chunk,
+            None => return Ok(()),
+        };
+
+        let mut payload = (chunk.layout as u32).to_le_bytes().to_vec();
+        payload.append(&mut self.rows);
+
+        chunk.offset = self.write_block(BLOCK_CHUNK, &payload, true)?;
+        self.index.chunks.push(chunk);
+
+        Ok(())
+    }
+
+    /// Write a block, returning its offset.
+    fn write_block(
+        &mut self,
+        kind: u8,
+        payload: &[u8],
+        compress: bool,
+    ) -> Result<u64, ChunkedError> {
+        let (codec, stored) = encode(payload, compress)?;
+        let length: u32 = stored
+            .len()
+            .try_into()
+            .map_err(|_| ChunkedError::Corrupt(String::from("Block is larger than 4GB")))?;
+
+        let offset = self.position;
+        self.inner.write_all(&[kind, codec, 0, 0])?;
+        self.inner.write_all(&length.to_le_bytes())?;
+        self.inner.write_all(&stored)?;
+        self.position += (BLOCK_HEADER_SIZE + stored.len()) as u64;
+
+        Ok(offset)
+    }
+}
+
+#[cfg(feature = "compression")]
+fn encode(payload: &[u8], compress: bool) -> io::Result<(u8, Vec<u8>)> {
+    if !compress {
+        return Ok((CODEC_NONE, payload.to_vec()));
+    }
+
+    let mut writer = CompressedWriter::new(Vec::new(), Compression::Zstd)?;
+    writer.write_all(payload)?;
+    Ok((CODEC_ZSTD, writer.finish()?))
+}
+
+#[cfg(not(feature = "compression"))]
+fn encode(payload: &[u8], _compress: bool) -> io::Result<(u8, Vec<u8>)> {
+    Ok((CODEC_NONE, payload.to_vec()))
+}
+
+fn decode(codec: u8, stored: Vec<u8>) -> Result<Vec<u8>, ChunkedError> {
+    match codec {
+        CODEC_NONE => Ok(stored),
+
+        #[cfg(feature = "compression")]
+        CODEC_ZSTD => {
+            let mut payload = Vec::new();
+            CompressedReader::new(stored.as_slice())?.read_to_end(&mut payload)?;
+            Ok(payload)
+        }
+
+        #[cfg(not(feature = "compression"))]
+        CODEC_ZSTD => Err(ChunkedError::Compressed),
+
+        c => Err(ChunkedError::Corrupt(format!("Unknown codec {}", c))),
+    }
+}
+
+impl ChunkedReader<BufReader<File>> {
+    pub fn open<P: AsRef<Path>>(path: P) -> Result<Self, ChunkedError> {
+        Self::new(BufReader::new(File::open(path)?))
+    }
+}
+
+impl RotatingWriter {
+    ///
+    /// Write recordings into `dir`, creating it if needed. The first file is
+    /// started by the first sample.
+    pub fn new<P: AsRef<Path>>(dir: P, prefix: &str) -> Result<Self, ChunkedError> {
+        let dir = dir.as_ref().to_path_buf();
+        std::fs::create_dir_all(&dir)?;
+
+        Ok(RotatingWriter {
+            dir,
+            prefix: prefix.to_owned(),
+            chunk_samples: DEFAULT_CHUNK_SAMPLES,
+            tracker: BoundaryTracker::new(),
+            writer: None,
+            sub_session_id: 0,
+            session_info: None,
+            events: Vec::new(),
+            files: Vec::new(),
+        })
+    }
+
+    /// Samples per chunk of each file.
+    pub fn with_chunk_samples(mut self, samples: usize) -> Self {
+        self.chunk_samples = samples.max(1);
+        self
+    }
+
+    ///
+    /// Key of the session being recorded, once the first sample has been
+    /// pushed.
+    pub fn key(&self) -> Option<SessionKey> {
+        self.tracker
+            .current()
+            .map(|current| SessionKey::new(self.sub_session_id, &current))
+    }
+
+    /// Every file finished so far, oldest first.
+    pub fn files(&self) -> &[PathBuf] {
+        &self.files
+    }
+
+    ///
+    /// Add a sample, first starting a new file if the sample is from a new
+    /// session. Returns the boundary crossed, if any.
+    pub fn push(&mut self, sample: &Sample) -> Result<Option<SessionBoundary>, ChunkedError> {
+        let boundary = match BoundarySample::from_sample(sample) {
+            Ok(current) => self.tracker.update(&current),
+            Err(_) => None,
+        };
+
+        if boundary.is_some() || self.writer.is_none() {
+            self.finish_file()?;
+
+            // The event's session info covers all of its sessions, but not another server's
+            if boundary.is_some_and(|b| b.kind == BoundaryKind::Server) {
+                self.session_info = None;
+                self.sub_session_id = 0;
+            }
+            self.start_file()?;
+        }
+
+        match self.writer.as_mut() {
+            Some(file) => file.writer.push(sample).map(|_| boundary),
+            None => Ok(boundary),
+        }
+    }
+
+    ///
+    /// Store the session info in the current file if it has changed, and in
+    /// files started for later sessions of the same event.
+    pub fn session_info(&mut self, yaml: &str) -> Result<(), ChunkedError> {
+        self.session_info = Some(yaml.to_owned());
+        if let Some(id) = sub_session_id(yaml) {
+            self.sub_session_id = id;
+        }
+
+        match self.writer.as_mut() {
+            Some(file) => {
+                // A file takes the ID it was opened with, or failing that the
+                // first one it's given
+                if file.sub_session_id == 0 {
+                    file.sub_session_id = self.sub_session_id;
+                }
+                file.writer.session_info(yaml)
+            }
+            None => Ok(()),
+        }
+    }
+
+    ///
+    /// Record an event in the current file, or the first file if none has
+    /// been started.
+    pub fn event<E: Serialize>(
+        &mut self,
+        session_time: f64,
+        kind: &str,
+        data: &E,
+    ) -> Result<(), ChunkedError> {
+        match self.writer.as_mut() {
+            Some(file) => file.writer.event(session_time, kind, data),
+            None => {
+                let data =
+                    serde_json::to_value(data).map_err(|e| ChunkedError::Corrupt(e.to_string()))?;
+                self.events.push(ArchivedEvent {
+                    session_time,
+                    kind: kind.to_owned(),
+                    data,
+                });
+                Ok(())
+            }
+        }
+    }
+
+    ///
+    /// Finish the current file, returning every file written.
+    pub fn finish(mut self) -> Result<Vec<PathBuf>, ChunkedError> {
+        self.finish_file()?;
+        Ok(self.files)
+    }
+
+    /// Start writing the current session to a temporary file.
+    fn start_file(&mut self) -> Result<(), ChunkedError> {
+        let path = self.available(&format!("{}.irc.part", self.prefix));
+
+        let mut writer = ChunkedWriter::create(&path)?.with_chunk_samples(self.chunk_samples);
+        if let Some(yaml) = &self.session_info {
+            writer.session_info(yaml)?;
+        }
+        writer.events.append(&mut self.events);
+
+        self.writer = Some(OpenFile {
+            path,
+            session: self.tracker.current(),
+            sub_session_id: self.sub_session_id,
+            writer,
+        });
+        Ok(())
+    }
+
+    /// Finish the current file and name it after its session.
+    fn finish_file(&mut self) -> Result<(), ChunkedError> {
+        let OpenFile {
+            path: part,
+            session,
+            sub_session_id,
+            writer,
+        } = match self.writer.take() {
+            Some(file) => file,
+            None => return Ok(()),
+        };
+        writer.finish()?;
+
+        let name = match session.map(|s| SessionKey::new(sub_session_id, &s)) {
+            Some(key) => format!("{}-{}.irc", self.prefix, key.name()),
+            None => format!("{}.irc", self.prefix),
+        };
+        let path = self.available(&name);
+        std::fs::rename(&part, &path)?;
+        self.files.push(path);
+
+        Ok(())
+    }
+
+    /// Path in the directory for `name`, numbered if it is taken.
+    fn available(&self, name: &str) -> PathBuf {
+        let (stem, extension) = name.split_at(name.find('.').unwrap_or(name.len()));
+
+        let mut path = self.dir.join(name);
+        let mut part = 1;
+        while path.exists() {
+            part += 1;
+            path = self.dir.join(format!("{}-{}{}", stem, part, extension));
+        }
+        path
+    }
+}
+
+impl<R: Read + Seek> ChunkedReader<R> {
+    ///
+    /// Read the file header and index.
+    pub fn new(mut reader: R) -> Result<Self, ChunkedError> {
+        let mut header = [0u8; FILE_HEADER_SIZE as usize];
+        reader.read_exact(&mut header)?;
+        if header[..8] != MAGIC {
+            return Err(ChunkedError::BadMagic);
+        }
+
+        let version = u32::from_le_bytes([header[8], header[9], header[10], header[11]]);
+        if version != FORMAT_VERSION {
+            return Err(ChunkedError::UnsupportedVersion(version));
+        }
+
+        let mut footer = [0u8; FOOTER_SIZE as usize];
+        reader.seek(SeekFrom::End(-FOOTER_SIZE))?;
+        reader.read_exact(&mut footer)?;
+        if footer[8..] != FOOTER_MAGIC {
+            return Err(ChunkedError::Corrupt(String::from(
+                "Missing footer, the recording wasn't finished",
+            )));
+        }
+
+        let mut word = [0u8; 8];
+        word.copy_from_slice(&footer[..8]);
+        let mut chunked = ChunkedReader {
+            reader,
+            index: Index::default(),
+            layouts: Vec::new(),
+        };
+
+        let index = chunked.read_block(u64::from_le_bytes(word), BLOCK_INDEX)?;
+        chunked.index = serde_json::from_slice(&index)
+            .map_err(|e| ChunkedError::Corrupt(format!("Invalid index: {}", e)))?;
+        chunked.layouts = vec![None; chunked.index.layouts.len()];
+
+        Ok(chunked)
+    }
+
+    /// Number of samples in the recording.
+    pub fn len(&self) -> usize {
+        self.index.chunks.iter().map(|c| c.samples as usize).sum()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Chunks of samples, in order.
+    pub fn chunks(&self) -> &[ChunkInfo] {
+        &self.index.chunks
+    }
+
+    /// Session time of the first and last samples.
+    pub fn time_range(&self) -> Option<(f64, f64)> {
+        let first = self.index.chunks.first()?;
+        let last = self.index.chunks.last()?;
+        Some((first.start_time, last.end_time))
+    }
+
+    ///
+    /// Samples of a chunk, with their session times.
+    pub fn read_chunk(&mut self, idx: usize) -> Result<Vec<(f64, Sample)>, ChunkedError> {
+        let info = *self
+            .index
+            .chunks
+            .get(idx)
+            .ok_or_else(|| ChunkedError::Corrupt(format!("No chunk {}", idx)))?;
+
+        let payload = self.read_block(info.offset, BLOCK_CHUNK)?;
+        let layout = self.layout(info.layout)?;
+
+        let row_size = ROW_HEADER_SIZE + layout.buffer_length();
+        let rows = payload.get(4..).unwrap_or_default();
+        if rows.len() != row_size * info.samples as usize {
+            return Err(ChunkedError::Corrupt(format!(
+                "Chunk {} doesn't hold {} samples",
+                idx, info.samples
+            )));
+        }
+
+        rows.chunks_exact(row_size)
+            .map(|row| {
+                let tick = i32::from_le_bytes([row[0], row[1], row[2], row[3]]);
+                let mut time = [0u8; 8];
+                time.copy_from_slice(&row[4..12]);
+
+                let sample = layout
+                    .sample(tick, &row[ROW_HEADER_SIZE..])
+                    .map_err(ChunkedError::Corrupt)?;
+                Ok((f64::from_le_bytes(time), sample))
+            })
+            .collect()
+    }
+
+    ///
+    /// Samples with session times from `start` to `end`, inclusive. Only the
+    /// chunks overlapping the window are read.
+    pub fn samples_between(&mut self, start: f64, end: f64) -> Result<Vec<Sample>, ChunkedError> {
+        let chunks: Vec<usize> = (0..self.index.chunks.len())
+            .filter(|i| {
+                let c = &self.index.chunks[*i];
+                c.end_time >= start && c.start_time <= end
+            })
+            .collect();
+
+        let mut samples = Vec::new();
+        for idx in chunks {
+            samples.extend(
+                self.read_chunk(idx)?
+                    .into_iter()
+                    .filter(|(t, _)| *t >= start && *t <= end)
+                    .map(|(_, s)| s),
+            );
+        }
+
+        Ok(samples)
+    }
+
+    ///
+    /// First sample at or after `session_time`.
+    pub fn sample_at(&mut self, session_time: f64) -> Result<Option<Sample>, ChunkedError> {
+        let idx = self
+            .index
+            .chunks
+            .partition_point(|c| c.end_time < session_time);
+        if idx >= self.index.chunks.len() {
+            return Ok(None);
+        }
+
+        Ok(self
+            .read_chunk(idx)?
+            .into_iter()
+            .find(|(t, _)| *t >= session_time)
+            .map(|(_, s)| s))
+    }
+
+    ///
+    /// The session info in effect at `session_time`.
+    pub fn session_info_at(&mut self, session_time: f64) -> Result<Option<String>, ChunkedError> {
+        let entry = match self
+            .index
+            .sessions
+            .iter()
+            .rev()
+            .find(|s| s.session_time <= session_time)
+            .or_else(|| self.index.sessions.first())
+        {
+            Some(entry) => *entry,
+            None => return Ok(None),
+        };
+
+        let payload = self.read_block(entry.offset, BLOCK_SESSION)?;
+        let yaml = payload
+            .get(8..)
+            .ok_or_else(|| ChunkedError::Corrupt(String::from("Truncated session block")))?;
+
+        String::from_utf8(yaml.to_vec())
+            .map(Some)
+            .map_err(|e| ChunkedError::Corrupt(e.to_string()))
+    }
+
+    /// Every event, in the order they were recorded.
+    pub fn events(&mut self) -> Result<Vec<ArchivedEvent>, ChunkedError> {
+        let offset = match self.index.events {
+            Some(offset) => offset,
+            None => return Ok(Vec::new()),
+        };
+
+        let payload = self.read_block(offset, BLOCK_EVENTS)?;
+        serde_json::from_slice(&payload).map_err(|e| ChunkedError::Corrupt(e.to_string()))
+    }
+
+    fn layout(&mut self, idx: usize) -> Result<SampleLayout, ChunkedError> {
+        if let Some(Some(layout)) = self.layouts.get(idx) {
+            return Ok(layout.clone());
+        }
+
+        let offset = *self
+            .index
+            .layouts
+            .get(idx)
+            .ok_or_else(|| ChunkedError::Corrupt(format!("No layout {}", idx)))?;
+        let payload = self.read_block(offset, BLOCK_LAYOUT)?;
+        if payload.len() < 4 {
+            return Err(ChunkedError::Corrupt(String::from("Truncated layout")));
+        }
+
+        let buffer_length = u32::from_le_bytes([payload[0], payload[1], payload[2], payload[3]]);
+        let layout = SampleLayout::from_bytes(&payload[4..], buffer_length as usize)
+            .map_err(ChunkedError::Corrupt)?;
+
+        self.layouts[idx] = Some(layout.clone());
+        Ok(layout)
+    }
+
+    fn read_block(&mut self, offset: u64, kind: u8) -> Result<Vec<u8>, ChunkedError> {
+        let mut header = [0u8; BLOCK_HEADER_SIZE];
+        self.reader.seek(SeekFrom::Start(offset))?;
+        self.reader.read_exact(&mut header)?;
+
+        if header[0] != kind {
+            return Err(ChunkedError::Corrupt(format!(
+                "Expected block kind {} at {}, found {}",
+                kind, offset, header[0]
+            )));
+        }
+
+        let length = u32::from_le_bytes([header[4], header[5], header[6], header[7]]);
+        let mut stored = Vec::new();
+        self.reader
+            .by_ref()
+            .take(length as u64)
+            .read_to_end(&mut stored)?;
+        if stored.len() != length as usize {
+            return Err(ChunkedError::Corrupt(format!(
+                "Block at {} is truncated",
+                offset
+            )));
+        }
+
+        decode(header[1], stored)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::recording::{Recording, SnapshotBuilder};
+    use crate::telemetry::Value;
+    use std::io::Cursor;
+
+    #[test]
+    fn rotate_at_boundaries() {
+        let dir = tempfile::tempdir().unwrap();
+        let sample = |tick: i32, session_num: i32, session_unique_id: i32| {
+            let mut recording = Recording::new();
+            recording.push(
+                SnapshotBuilder::new(tick)
+                    .with_value("SessionTime", Value::DOUBLE(tick as f64))
+                    .with_value("SessionNum", Value::INT(session_num))
+                    .with_value("SessionUniqueID", Value::INT(session_unique_id))
+                    .build(),
+            );
+            recording.sample(0).unwrap().unwrap()
+        };
+
+        let mut writer = RotatingWriter::new(dir.path(), "test")
+            .unwrap()
+            .with_chunk_samples(4);
+        writer.event(0.0, "joined", &1).unwrap();
+        for tick in 0..10 {
+            assert!(writer.push(&sample(tick, 0, 5)).unwrap().is_none());
+        }
+        let yaml = "WeekendInfo:\n SubSessionID: 900\n";
+        writer.session_info(yaml).unwrap();
+
+        // Qualifying, then an offline session and back again
+        assert!(writer.push(&sample(10, 1, 5)).unwrap().is_some());
+        assert_eq!(writer.key().unwrap().name(), "900-1");
+        let boundary = writer.push(&sample(11, 0, 6)).unwrap().unwrap();
+        assert_eq!(boundary.kind, BoundaryKind::Server);
+        writer.push(&sample(12, 1, 5)).unwrap();
+        writer.session_info(yaml).unwrap();
+
+        let files = writer.finish().unwrap();
+        let names: Vec<String> = files
+            .iter()
+            .map(|f| f.file_name().unwrap().to_string_lossy().into_owned())
+            .collect();
+        assert_eq!(
+            names,
+            vec![
+                "test-900-0.irc",
+                "test-900-1.irc",
+                "test-offline-6-0.irc",
+                "test-900-1-2.irc"
+            ]
+        );
+
+        let mut first = ChunkedReader::open(&files[0]).unwrap();
+        assert_eq!(first.len(), 10);
+        assert_eq!(first.events().unwrap()[0].kind, "joined");
+
+        // Session info carries over to the next session, but not another server's
+        let mut qualifying = ChunkedReader::open(&files[1]).unwrap();
+        assert_eq!(qualifying.session_info_at(10.0).unwrap().unwrap(), yaml);
+        let mut other = ChunkedReader::open(&files[2]).unwrap();
+        assert!(other.session_info_at(11.0).unwrap().is_none());
+    }
+
+    #[test]
+    fn name_files_by_opening_session() {
+        let dir = tempfile::tempdir().unwrap();
+        let sample = |tick: i32, session_unique_id: i32| {
+            let mut recording = Recording::new();
+            recording.push(
+                SnapshotBuilder::new(tick)
+                    .with_value("SessionTime", Value::DOUBLE(tick as f64))
+                    .with_value("SessionNum", Value::INT(0))
+                    .with_value("SessionUniqueID", Value::INT(session_unique_id))
+                    .build(),
+            );
+            recording.sample(0).unwrap().unwrap()
+        };
+
+        let mut writer = RotatingWriter::new(dir.path(), "test").unwrap();
+        writer
+            .session_info("WeekendInfo:\n SubSessionID: 900\n")
+            .unwrap();
+        writer.push(&sample(0, 5)).unwrap();
+
+        // The next server's session info arrives before its first sample
+        writer
+            .session_info("WeekendInfo:\n SubSessionID: 901\n")
+            .unwrap();
+        writer.push(&sample(1, 6)).unwrap();
+
+        let files = writer.finish().unwrap();
+        assert_eq!(files[0].file_name().unwrap(), "test-900-0.irc");
+    }
+
+    fn sample(tick: i32, extra: bool) -> Sample {
+        let mut builder = SnapshotBuilder::new(tick)
+            .with_value("SessionTime", Value::DOUBLE(tick as f64 / 60.0))
+            .with_value("Gear", Value::INT(tick % 6));
+        if extra {
+            builder = builder.with_value("Speed", Value::FLOAT(tick as f32));
+        }
+
+        let mut recording = Recording::new();
+        recording.push(builder.build());
+        recording.sample(0).unwrap().unwrap()
+    }
+
+    #[test]
+    fn write_and_seek() {
+        let mut writer = ChunkedWriter::new(Cursor::new(Vec::new()))
+            .unwrap()
+            .with_chunk_samples(50);
+
+        writer.session_info("first").unwrap();
+        for tick in 0..300 {
+            // The layout changes part way through
+            writer.push(&sample(tick, tick >= 120)).unwrap();
+            if tick == 200 {
+                writer.session_info("second").unwrap();
+            }
+        }
+        writer.event(2.5, "incident", &4).unwrap();
+        let file = writer.finish().unwrap().into_inner();
+
+        let mut reader = ChunkedReader::new(Cursor::new(file)).unwrap();
+        assert_eq!(reader.len(), 300);
+        assert_eq!(reader.chunks().len(), 7);
+        assert_eq!(reader.time_range(), Some((0.0, 299.0 / 60.0)));
+
+        let window = reader.samples_between(1.0, 2.5).unwrap();
+        assert_eq!(window.len(), 91);
+        assert_eq!(window[0].tick(), 60);
+
+        let gear: i32 = window[0].get("Gear").unwrap().try_into().unwrap();
+        assert_eq!(gear, 0);
+        assert!(!window[0].has("Speed"));
+        let speed: f32 = window[90].get("Speed").unwrap().try_into().unwrap();
+        assert_eq!(speed, 150.0);
+
+        assert_eq!(reader.sample_at(4.0).unwrap().unwrap().tick(), 240);
+        assert!(reader.sample_at(10.0).unwrap().is_none());
+
+        assert_eq!(reader.session_info_at(1.0).unwrap().unwrap(), "first");
+        assert_eq!(reader.session_info_at(4.0).unwrap().unwrap(), "second");
+
+        let events = reader.events().unwrap();
+        assert_eq!(events[0].kind, "incident");
+        assert_eq!(events[0].data, 4);
+    }
+}
@@ -0,0 +1,236 @@
+use crate::results::{Gap, Standing};
+use crate::session::Driver;
+use serde::{Deserialize, Serialize};
+
+///
+/// A class of car in the session.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct CarClass {
+    pub id: u64,             // CarClassID
+    pub short_name: String,  // Class name, e.g. "GT3 Class"
+    pub color: u32,          // Class color as 0xRRGGBB
+    pub relative_speed: i64, // Relative speed of the class, higher is faster
+    pub cars: Vec<usize>,    // Car indexes in the class
+
+    #[serde(default)]
+    pub est_lap_time: Option<f32>, // Estimated lap time of the class (s)
+}
+
+///
+/// A car's gaps within its class.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ClassGap {
+    pub car_idx: usize,
+    pub class_position: u32, // Position in class (1-based)
+    pub to_class_leader: Gap,
+    pub to_class_ahead: Gap, // Gap to the car one position ahead in the same class
+}
+
+///
+/// Classes
+///
+/// The car classes in a session, built from the `DriverInfo` section.
+///
+/// Multiclass sessions interleave cars of different speeds, so overall
+/// positions and gaps are rarely meaningful; these helpers group standings and
+/// live positions by class instead.
+///
+/// # Examples
+///
+/// ```
+/// use iracing::classes::Classes;
+/// use iracing::results::Results;
+/// # let session: iracing::session::SessionDetails =
+/// #     serde_yaml::from_str(&std::fs::read_to_string("./session.yaml").unwrap()).unwrap();
+///
+/// let classes = Classes::from_drivers(&session.drivers.other_drivers);
+/// let results = Results::from_session(&session, 2).unwrap();
+///
+/// for (class, standings) in classes.by_class(&results.standings) {
+///     println!("{}: {} cars", class.short_name, standings.len());
+/// }
+/// ```
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct Classes {
+    classes: Vec<CarClass>,
+}
+
+impl CarClass {
+    /// Class color as an (r, g, b) triple.
+    pub fn rgb(&self) -> (u8, u8, u8) {
+        (
+            (self.color >> 16) as u8,
+            (self.color >> 8) as u8,
+            self.color as u8,
+        )
+    }
+}
+
+/// Parse a `CarClassColor` value such as "0xffda59".
+fn parse_color(color: &str) -> u32 {
+    let hex = color
+        .trim()
+        .trim_start_matches("0x")
+        .trim_start_matches("0X");
+    u32::from_str_radix(hex, 16).unwrap_or(0xffffff)
+}
+
+impl Classes {
+    ///
+    /// Group the cars in a session by class, fastest class first.
+    ///
+    /// The pace car and spectators are ignored.
+    pub fn from_drivers(drivers: &[Driver]) -> Self {
+        let mut classes: Vec<CarClass> = Vec::new();
+
+        for driver in drivers
+            .iter()
+            .filter(|d| !d.is_pace_car() && d.is_spectator == 0)
+        {
+            match classes.iter_mut().find(|c| c.id == driver.car_class_id) {
+                Some(class) => class.cars.push(driver.index),
+                None => classes.push(CarClass {
+                    id: driver.car_class_id,
+                    short_name: driver.car_class_short_name.clone(),
+                    color: parse_color(&driver.car_class_color),
+                    relative_speed: driver.car_class_relative_speed,
+                    cars: vec![driver.index],
+                    est_lap_time: driver.car_class_est_lap_time,
+                }),
+            }
+        }
+
+        classes.sort_by(|a, b| {
+            b.relative_speed
+                .cmp(&a.relative_speed)
+                .then(a.id.cmp(&b.id))
+        });
+        Classes { classes }
+    }
+
+    /// All classes, fastest first.
+    pub fn all(&self) -> &[CarClass] {
+        &self.classes
+    }
+
+    /// True if more than one class is racing.
+    pub fn is_multiclass(&self) -> bool {
+        self.classes.len() > 1
+    }
+
+    /// The class of a given car.
+    pub fn for_car(&self, car_idx: usize) -> Option<&CarClass> {
+        self.classes.iter().find(|c| c.cars.contains(&car_idx))
+    }
+
+    ///
+    /// Group standings by class, fastest class first, each in class position order.
+    pub fn by_class<'a>(
+        &'a self,
+        standings: &'a [Standing],
+    ) -> Vec<(&'a CarClass, Vec<&'a Standing>)> {
+        self.classes
+            .iter()
+            .map(|class| {
+                let mut cars: Vec<&Standing> = standings
+                    .iter()
+                    .filter(|s| class.cars.contains(&s.car_idx))
+                    .collect();
+                cars.sort_by_key(|s| s.class_position);
+                (class, cars)
+            })
+            .filter(|(_, cars)| !cars.is_empty())
+            .collect()
+    }
+
+    ///
+    /// Gaps to the class leader and the car ahead in class for each standing.
+    ///
+    /// `race` selects lap-down gaps, as for race sessions.
+    pub fn class_gaps(&self, standings: &[Standing], race: bool) -> Vec<ClassGap> {
+        let mut gaps = Vec::new();
+
+        for (_, cars) in self.by_class(standings) {
+            let summary: Vec<(i32, Option<f32>)> =
+                cars.iter().map(|s| (s.laps_complete, s.time)).collect();
+
+            for (i, standing) in cars.iter().enumerate() {
+                let (to_class_leader, to_class_ahead) = if i == 0 {
+                    (Gap::None, Gap::None)
+                } else {
+                    (
+                        Gap::between(summary[0], summary[i], race),
+                        Gap::between(summary[i - 1], summary[i], race),
+                    )
+                };
+
+                gaps.push(ClassGap {
+                    car_idx: standing.car_idx,
+                    class_position: standing.class_position,
+                    to_class_leader,
+                    to_class_ahead,
+                });
+            }
+        }
+
+        gaps
+    }
+
+    ///
+    /// The car one position ahead in the same class, from live class positions
+    /// (`CarIdxClassPosition`, indexed by car index).
+    pub fn class_ahead(&self, car_idx: usize, class_positions: &[i32]) -> Option<usize> {
+        let position = *class_positions.get(car_idx)?;
+        if position <= 1 {
+            return None;
+        }
+
+        self.for_car(car_idx)?
+            .cars
+            .iter()
+            .copied()
+            .find(|&c| class_positions.get(c) == Some(&(position - 1)))
+    }
+
+    ///
+    /// The leader of a car's class, from live class positions (`CarIdxClassPosition`).
+    pub fn class_leader(&self, car_idx: usize, class_positions: &[i32]) -> Option<usize> {
+        self.for_car(car_idx)?
+            .cars
+            .iter()
+            .copied()
+            .find(|&c| class_positions.get(c) == Some(&1))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::results::Results;
+    use crate::session::SessionDetails;
+
+    #[test]
+    fn multiclass_race() {
+        let content = std::fs::read_to_string("./session.yaml").unwrap();
+        let session: SessionDetails = serde_yaml::from_str(&content).unwrap();
+
+        let classes = Classes::from_drivers(&session.drivers.other_drivers);
+        assert!(classes.is_multiclass());
+        assert_eq!(classes.all()[0].short_name, "GT3 Class");
+        assert_eq!(classes.all()[0].rgb(), (0x33, 0xce, 0xff));
+        assert_eq!(classes.for_car(1).unwrap().cars, vec![1, 2, 5, 6]);
+
+        let results = Results::from_session(&session, 2).unwrap();
+        let grouped = classes.by_class(&results.standings);
+        assert_eq!(grouped[1].1[0].car_idx, 1);
+
+        let gaps = classes.class_gaps(&results.standings, true);
+        let leader = gaps.iter().find(|g| g.car_idx == 1).unwrap();
+        assert_eq!(leader.to_class_leader, Gap::None);
+
+        let class_positions = vec![0, 1, 3, 1, 2, 2, 4];
+        assert_eq!(classes.class_ahead(2, &class_positions), Some(5));
+        assert_eq!(classes.class_leader(4, &class_positions), Some(3));
+        assert_eq!(classes.class_ahead(1, &class_positions), None);
+    }
+}
@@ -0,0 +1,502 @@
+use crate::validity;
+use chrono::NaiveTime;
+use serde::{Deserialize, Serialize};
+
+#[cfg(feature = "telemetry")]
+use crate::telemetry::Sample;
+#[cfg(feature = "telemetry")]
+use std::convert::TryInto;
+#[cfg(feature = "telemetry")]
+use std::error::Error;
+
+/// Seconds in a day
+const DAY_SECONDS: f64 = 86400.0;
+
+/// Minimum session time (s) which must elapse before a time multiplier is reported.
+const MIN_MULTIPLIER_WINDOW: f64 = 10.0;
+
+/// Sun altitude (rad) below which civil twilight ends (-6 degrees)
+const TWILIGHT_ALTITUDE: f32 = -0.104_719_76;
+
+///
+/// Clock Sample
+///
+/// The in-sim time of day at a given session time.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct ClockSample {
+    pub session_time: f64,           // Seconds since session start
+    pub time_of_day: f32,            // In-sim local time of day (seconds since midnight)
+    pub solar_altitude: Option<f32>, // Sun altitude above the horizon (rad)
+    pub solar_azimuth: Option<f32>,  // Sun azimuth (rad)
+}
+
+///
+/// Phase of the day, derived from the sun's altitude.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum DayPhase {
+    Day,
+    Twilight,
+    Night,
+}
+
+///
+/// Change in the phase of the day.
+#[derive(Debug, Copy, Clone, PartialEq, Serialize, Deserialize)]
+pub struct DayPhaseChange {
+    pub session_time: f64,
+    pub from: DayPhase,
+    pub to: DayPhase,
+}
+
+///
+/// Sim Clock
+///
+/// Tracks the in-sim time of day, the rate at which it advances relative to
+/// session time (the time multiplier), and transitions between day and night.
+///
+/// # Examples
+///
+/// ```
+/// use iracing::clock::{ClockSample, SimClock};
+///
+/// let mut clock = SimClock::new();
+/// clock.update(ClockSample { session_time: 0.0, time_of_day: 43200.0, solar_altitude: None, solar_azimuth: None });
+///
+/// println!("Local time: {}", clock.local_time().unwrap());
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct SimClock {
+    first: Option<ClockSample>,
+    latest: Option<ClockSample>,
+    elapsed_time_of_day: f64,
+    phase: Option<DayPhase>,
+}
+
+///
+/// Race Clock Sample
+///
+/// Session limits and the progress of each car at a point in the race.
+/// Per-car values are indexed by car index.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct RaceClockSample {
+    pub session_time: f64,           // Seconds since session start
+    pub time_remaining: Option<f64>, // SessionTimeRemain - None if the session isn't timed (s)
+    pub laps_remaining: Option<i32>, // SessionLapsRemainEx - None if the session isn't lap limited
+    pub player_car_idx: usize,       // PlayerCarIdx
+    pub positions: Vec<i32>,         // CarIdxPosition - 0 until classified
+    pub lap_dist_pct: Vec<f32>,      // CarIdxLapDistPct - -1 when the car isn't on track
+    pub last_lap_time: Vec<f32>,     // CarIdxLastLapTime - -1 until a lap is timed (s)
+}
+
+///
+/// What ends the race.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum RaceLimit {
+    Laps,
+    Time,
+}
+
+///
+/// Projected end of the race.
+#[derive(Debug, Copy, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RaceEstimate {
+    pub limit: RaceLimit,
+    pub leader_laps: i32, // Times the leader will cross the line, the last taking the checkered flag
+    pub leader_finish: f64, // Time until the leader finishes (s)
+    pub laps: i32,        // Times the player will cross the line
+    pub finish: f64,      // Time until the player finishes (s)
+}
+
+///
+/// Race Clock
+///
+/// Answers how many more laps the player will actually drive.
+///
+/// The raw channels are easy to misread: `SessionLapsRemainEx` counts the
+/// leader's laps rather than the player's, and a timed race doesn't end when
+/// the clock reaches zero but when the leader next crosses the line, with
+/// everyone else finishing as they next cross it after the leader.
+/// The race clock combines the session limits with the leader's and the
+/// player's pace and positions to project both finishes.
+///
+/// Lap times are taken from each car's last lap, falling back to the
+/// estimated lap time until a lap has been timed.
+///
+/// # Examples
+///
+/// ```
+/// use iracing::clock::{RaceClock, RaceClockSample};
+///
+/// let mut clock = RaceClock::new(92.5);
+///
+/// if let Some(estimate) = clock.update(&RaceClockSample::default()) {
+///     println!("{} laps to go, finishing in {:.0}s", estimate.laps, estimate.finish);
+/// }
+/// ```
+#[derive(Debug, Clone)]
+pub struct RaceClock {
+    est_lap_time: f32,
+    lap_times: Vec<f32>,
+    estimate: Option<RaceEstimate>,
+}
+
+impl DayPhase {
+    ///
+    /// Phase of the day for a given sun altitude (rad).
+    pub fn from_solar_altitude(altitude: f32) -> DayPhase {
+        if altitude > 0.0 {
+            Self::Day
+        } else if altitude > TWILIGHT_ALTITUDE {
+            Self::Twilight
+        } else {
+            Self::Night
+        }
+    }
+}
+
+impl ClockSample {
+    ///
+    /// Read a clock sample from a telemetry sample.
+    #[cfg(feature = "telemetry")]
+    pub fn from_sample(sample: &Sample) -> Result<Self, Box<dyn Error>> {
+        let solar_altitude: Option<f32> = if sample.has("SolarAltitude") {
+            Some(sample.get("SolarAltitude")?.try_into()?)
+        } else {
+            None
+        };
+
+        let solar_azimuth: Option<f32> = if sample.has("SolarAzimuth") {
+            Some(sample.get("SolarAzimuth")?.try_into()?)
+        } else {
+            None
+        };
+
+        Ok(ClockSample {
+            session_time: sample.get("SessionTime")?.try_into()?,
+            time_of_day: sample.get("SessionTimeOfDay")?.try_into()?,
+            solar_altitude,
+            solar_azimuth,
+        })
+    }
+}
+
+impl SimClock {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    ///
+    /// Update the clock with a new sample.
+    ///
+    /// Returns the change in day phase, if the sample moved the sim from day to
+    /// twilight, twilight to night etc. A sample with an earlier session time than
+    /// the last (e.g. a new session) resets the clock.
+    pub fn update(&mut self, sample: ClockSample) -> Option<DayPhaseChange> {
+        match self.latest {
+            Some(last) if sample.session_time >= last.session_time => {
+                let mut delta = sample.time_of_day as f64 - last.time_of_day as f64;
+
+                // Wrapped past midnight
+                if delta < -DAY_SECONDS / 2.0 {
+                    delta += DAY_SECONDS;
+                }
+
+                self.elapsed_time_of_day += delta;
+            }
+            _ => {
+                self.first = Some(sample);
+                self.elapsed_time_of_day = 0.0;
+            }
+        }
+
+        self.latest = Some(sample);
+
+        let phase = sample.solar_altitude.map(DayPhase::from_solar_altitude)?;
+        let previous = self.phase.replace(phase);
+
+        match previous {
+            Some(from) if from != phase => Some(DayPhaseChange {
+                session_time: sample.session_time,
+                from,
+                to: phase,
+            }),
+            _ => None,
+        }
+    }
+
+    /// The most recent sample.
+    pub fn latest(&self) -> Option<&ClockSample> {
+        self.latest.as_ref()
+    }
+
+    ///
+    /// In-sim local time of day.
+    pub fn local_time(&self) -> Option<NaiveTime> {
+        let seconds = self.latest?.time_of_day.max(0.0) as u32 % DAY_SECONDS as u32;
+        NaiveTime::from_num_seconds_from_midnight_opt(seconds, 0)
+    }
+
+    ///
+    /// Current phase of the day, if the sim reports the sun's position.
+    pub fn phase(&self) -> Option<DayPhase> {
+        self.phase
+    }
+
+    ///
+    /// Rate at which the in-sim time of day advances relative to session time.
+    ///
+    /// A value of 1.0 is real time. Returns None until enough session time has
+    /// elapsed to measure the rate.
+    pub fn time_multiplier(&self) -> Option<f64> {
+        let elapsed = self.latest?.session_time - self.first?.session_time;
+
+        if elapsed < MIN_MULTIPLIER_WINDOW {
+            None
+        } else {
+            Some(self.elapsed_time_of_day / elapsed)
+        }
+    }
+
+    ///
+    /// Session time (s) remaining until the in-sim clock reaches `time`.
+    ///
+    /// Useful for planning stints around sunset or sunrise in endurance races.
+    pub fn session_time_until(&self, time: NaiveTime) -> Option<f64> {
+        use chrono::Timelike;
+
+        let multiplier = self.time_multiplier()?;
+        if multiplier <= 0.0 {
+            return None;
+        }
+
+        let now = self.latest?.time_of_day as f64;
+        let mut delta = time.num_seconds_from_midnight() as f64 - now;
+        if delta < 0.0 {
+            delta += DAY_SECONDS;
+        }
+
+        Some(delta / multiplier)
+    }
+}
+
+impl RaceClockSample {
+    ///
+    /// Read a race clock sample from a telemetry sample.
+    #[cfg(feature = "telemetry")]
+    pub fn from_sample(sample: &Sample) -> Result<Self, Box<dyn Error>> {
+        let time_remaining = validity::read(sample, "SessionTimeRemain")?;
+        let laps_remaining = validity::read(sample, "SessionLapsRemainEx")?;
+        let player_car_idx: i32 = sample.get("PlayerCarIdx")?.try_into()?;
+
+        Ok(RaceClockSample {
+            session_time: sample.get("SessionTime")?.try_into()?,
+            time_remaining: time_remaining.first().and_then(|t| t.value()),
+            laps_remaining: laps_remaining
+                .first()
+                .and_then(|l| l.value())
+                .map(|l| l as i32),
+            player_car_idx: player_car_idx.max(0) as usize,
+            positions: sample.get("CarIdxPosition")?.try_into()?,
+            lap_dist_pct: sample.get("CarIdxLapDistPct")?.try_into()?,
+            last_lap_time: sample.get("CarIdxLastLapTime")?.try_into()?,
+        })
+    }
+}
+
+impl RaceClock {
+    /// Race clock using `est_lap_time` until laps have been timed (s).
+    pub fn new(est_lap_time: f32) -> Self {
+        RaceClock {
+            est_lap_time,
+            lap_times: Vec::new(),
+            estimate: None,
+        }
+    }
+
+    ///
+    /// Update with a new sample, returning the projected finish. Returns None
+    /// when the race has no limit or the leader or player isn't on track.
+    pub fn update(&mut self, sample: &RaceClockSample) -> Option<RaceEstimate> {
+        if self.lap_times.len() < sample.last_lap_time.len() {
+            self.lap_times
+                .resize(sample.last_lap_time.len(), self.est_lap_time);
+        }
+        for (time, last) in self.lap_times.iter_mut().zip(sample.last_lap_time.iter()) {
+            if let Some(last) = validity::lap_time(*last) {
+                *time = last;
+            }
+        }
+
+        self.estimate = self.project(sample);
+        self.estimate
+    }
+
+    fn project(&self, sample: &RaceClockSample) -> Option<RaceEstimate> {
+        let player = sample.player_car_idx;
+        let leader = sample
+            .positions
+            .iter()
+            .position(|p| *p == 1)
+            .unwrap_or(player);
+
+        // Time until a car next crosses the line, and its lap time
+        let progress = |car_idx: usize| {
+            let pct = sample
+                .lap_dist_pct
+                .get(car_idx)
+                .copied()
+                .and_then(validity::lap_dist_pct)?;
+            let lap_time = self
+                .lap_times
+                .get(car_idx)
+                .copied()
+                .unwrap_or(self.est_lap_time) as f64;
+            Some(((1.0 - pct as f64) * lap_time, lap_time))
+        };
+
+        let (to_line, lap_time) = progress(leader)?;
+        if lap_time <= 0.0 {
+            return None;
+        }
+
+        // A timed race ends as the leader first crosses the line after time expires
+        let by_time = sample.time_remaining.map(|remaining| {
+            let crossings = ((remaining - to_line) / lap_time).ceil().max(0.0);
+            (
+                to_line + crossings * lap_time,
+                crossings as i32 + 1,
+                RaceLimit::Time,
+            )
+        });
+        let by_laps = sample.laps_remaining.map(|laps| {
+            let laps = laps.max(1);
+            (
+                to_line + (laps - 1) as f64 * lap_time,
+                laps,
+                RaceLimit::Laps,
+            )
+        });
+        let (leader_finish, leader_laps, limit) = match (by_time, by_laps) {
+            (Some(time), Some(laps)) if laps.0 <= time.0 => laps,
+            (Some(time), _) => time,
+            (None, laps) => laps?,
+        };
+
+        // Everyone else finishes as they next cross the line after the leader
+        let (laps, finish) = if leader == player {
+            (leader_laps, leader_finish)
+        } else {
+            let (to_line, lap_time) = progress(player)?;
+            if lap_time <= 0.0 {
+                return None;
+            }
+            let crossings = ((leader_finish - to_line) / lap_time).ceil().max(0.0);
+            (crossings as i32 + 1, to_line + crossings * lap_time)
+        };
+
+        Some(RaceEstimate {
+            limit,
+            leader_laps,
+            leader_finish,
+            laps,
+            finish,
+        })
+    }
+
+    /// The latest projection.
+    pub fn estimate(&self) -> Option<&RaceEstimate> {
+        self.estimate.as_ref()
+    }
+
+    ///
+    /// Laps the player has left to drive, counting the lap in progress.
+    pub fn laps_remaining(&self) -> Option<i32> {
+        self.estimate.map(|e| e.laps)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sample(session_time: f64, time_of_day: f32, solar_altitude: f32) -> ClockSample {
+        ClockSample {
+            session_time,
+            time_of_day,
+            solar_altitude: Some(solar_altitude),
+            solar_azimuth: None,
+        }
+    }
+
+    #[test]
+    fn multiplier_across_midnight() {
+        let mut clock = SimClock::new();
+
+        clock.update(sample(0.0, 86300.0, -1.0));
+        assert!(clock.time_multiplier().is_none());
+
+        clock.update(sample(50.0, 100.0, -1.0));
+        assert!((clock.time_multiplier().unwrap() - 4.0).abs() < 1e-6);
+        assert_eq!(
+            clock.local_time().unwrap(),
+            NaiveTime::from_hms_opt(0, 1, 40).unwrap()
+        );
+
+        let until = clock
+            .session_time_until(NaiveTime::from_hms_opt(0, 5, 0).unwrap())
+            .unwrap();
+        assert!((until - 50.0).abs() < 1e-6);
+    }
+
+    #[test]
+    fn phase_changes() {
+        let mut clock = SimClock::new();
+
+        assert!(clock.update(sample(0.0, 70000.0, 0.1)).is_none());
+        assert!(clock.update(sample(1.0, 70001.0, 0.05)).is_none());
+
+        let change = clock.update(sample(2.0, 70002.0, -0.05)).unwrap();
+        assert_eq!(change.from, DayPhase::Day);
+        assert_eq!(change.to, DayPhase::Twilight);
+
+        let change = clock.update(sample(3.0, 70003.0, -0.2)).unwrap();
+        assert_eq!(change.to, DayPhase::Night);
+    }
+
+    #[test]
+    fn race_clock() {
+        let mut clock = RaceClock::new(100.0);
+        let mut sample = RaceClockSample {
+            session_time: 1000.0,
+            time_remaining: Some(300.0),
+            laps_remaining: None,
+            player_car_idx: 1,
+            positions: vec![1, 2],
+            lap_dist_pct: vec![0.5, 0.8],
+            last_lap_time: vec![-1.0, 110.0],
+        };
+
+        // The leader crosses the line after 50s, 150s and 250s, then takes the
+        // checkered flag at 350s. The player still has four laps to drive, not
+        // the 2.7 the time remaining suggests.
+        let estimate = clock.update(&sample).unwrap();
+        assert_eq!(estimate.limit, RaceLimit::Time);
+        assert_eq!((estimate.leader_laps, estimate.leader_finish), (4, 350.0));
+        assert_eq!(estimate.laps, 4);
+        assert!((estimate.finish - 352.0).abs() < 1e-3);
+
+        // A lapped player a lap down, leaving the limit to laps
+        sample.time_remaining = None;
+        sample.laps_remaining = Some(5);
+        sample.lap_dist_pct = vec![0.5, 0.9];
+        sample.last_lap_time = vec![100.0, 100.0];
+        let estimate = clock.update(&sample).unwrap();
+        assert_eq!(estimate.limit, RaceLimit::Laps);
+        assert_eq!(estimate.leader_laps, 5);
+        assert_eq!(clock.laps_remaining(), Some(6));
+
+        // Unlimited sessions have no end
+        sample.laps_remaining = None;
+        assert!(clock.update(&sample).is_none());
+    }
+}
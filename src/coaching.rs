@@ -0,0 +1,428 @@
+use serde::{Deserialize, Serialize};
+use std::fmt::Write;
+
+#[cfg(feature = "telemetry")]
+use crate::telemetry::Sample;
+#[cfg(feature = "telemetry")]
+use std::convert::TryInto;
+#[cfg(feature = "telemetry")]
+use std::error::Error;
+
+/// Pedal input below this counts as released
+const RELEASED: f32 = 0.05;
+
+/// Pedal input above this counts as applied
+const APPLIED: f32 = 0.1;
+
+/// A lap must start before and end after these lap fractions to be complete
+const LAP_START: f32 = 0.05;
+const LAP_END: f32 = 0.95;
+
+///
+/// Coaching Sample
+///
+/// Position and driver inputs at a point in time.
+#[derive(Debug, Copy, Clone, Default)]
+pub struct CoachingSample {
+    pub session_time: f64, // Seconds since session start
+    pub lap: i32,
+    pub lap_dist_pct: f32, // LapDistPct - distance around the lap, 0.0 to 1.0
+    pub throttle: f32,     // Throttle - pedal position, 0.0 to 1.0
+    pub brake: f32,        // Brake - pedal position, 0.0 to 1.0
+}
+
+///
+/// Times and inputs over a single complete lap.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct LapAnalysis {
+    pub lap: i32,
+    pub lap_time: f64,
+    pub sectors: Vec<f64>,  // Time in each sector (s)
+    pub full_throttle: f32, // Fraction of samples at full throttle
+    pub braking: f32,       // Fraction of samples on the brake
+    pub coasting: f32,      // Fraction of samples on neither pedal
+    pub overlap: f32,       // Fraction of samples on both pedals
+}
+
+///
+/// Coaching Report
+///
+/// A driver's laps with sector times and input analysis, with their best and
+/// theoretical best laps, consistency and notes on where time is being lost.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct CoachingReport {
+    pub driver: String,
+    pub laps: Vec<LapAnalysis>,
+    pub best_lap: Option<i32>,
+    pub best_lap_time: Option<f64>,
+    pub best_sectors: Vec<f64>,        // Fastest time in each sector (s)
+    pub theoretical_best: Option<f64>, // Sum of the best sectors (s)
+    pub mean_lap_time: Option<f64>,
+    pub consistency: Option<f64>, // Standard deviation of lap times (s)
+    pub notes: Vec<String>,
+}
+
+///
+/// Coaching Report Builder
+///
+/// Splits a stint into laps and sectors and analyses the driver's inputs,
+/// producing a `CoachingReport`. Only complete laps are analysed, so out laps
+/// and laps cut short by a tow are left out.
+///
+/// Sectors are equal thirds of the lap unless set with `with_sectors`, e.g.
+/// from `TrackAsset::sectors`.
+///
+/// # Examples
+///
+/// ```
+/// use iracing::coaching::{CoachingReportBuilder, CoachingSample};
+///
+/// let mut builder = CoachingReportBuilder::new("Driver 1");
+/// builder.update(&CoachingSample { session_time: 0.0, lap: 1, ..Default::default() });
+///
+/// let report = builder.build();
+/// println!("{}", report.to_markdown());
+/// ```
+#[derive(Debug, Clone)]
+pub struct CoachingReportBuilder {
+    driver: String,
+    sectors: Vec<f32>,
+    current: Option<LapInProgress>,
+    laps: Vec<LapAnalysis>,
+}
+
+#[derive(Debug, Clone)]
+struct LapInProgress {
+    lap: i32,
+    start: f64,
+    first_pct: f32,
+    last_pct: f32,
+    splits: Vec<f64>, // Session time each sector after the first was entered
+    samples: u32,
+    full_throttle: u32,
+    braking: u32,
+    coasting: u32,
+    overlap: u32,
+}
+
+impl CoachingSample {
+    ///
+    /// Read a coaching sample from a telemetry sample.
+    #[cfg(feature = "telemetry")]
+    pub fn from_sample(sample: &Sample) -> Result<Self, Box<dyn Error>> {
+        Ok(CoachingSample {
+            session_time: sample.get("SessionTime")?.try_into()?,
+            lap: sample.get("Lap")?.try_into()?,
+            lap_dist_pct: sample.get("LapDistPct")?.try_into()?,
+            throttle: sample.get("Throttle")?.try_into()?,
+            brake: sample.get("Brake")?.try_into()?,
+        })
+    }
+}
+
+impl CoachingReportBuilder {
+    pub fn new(driver: &str) -> Self {
+        CoachingReportBuilder {
+            driver: driver.to_owned(),
+            sectors: vec![0.0, 1.0 / 3.0, 2.0 / 3.0],
+            current: None,
+            laps: Vec::new(),
+        }
+    }
+
+    ///
+    /// Start of each sector as a fraction of the lap. The first sector always
+    /// starts at the line.
+    pub fn with_sectors(mut self, sectors: &[f32]) -> Self {
+        let mut sectors: Vec<f32> = sectors.iter().copied().filter(|s| *s > 0.0).collect();
+        sectors.insert(0, 0.0);
+        self.sectors = sectors;
+        self
+    }
+
+    ///
+    /// Add a sample, returning the analysis of the previous lap when a new lap
+    /// starts and the previous lap was complete.
+    pub fn update(&mut self, sample: &CoachingSample) -> Option<LapAnalysis> {
+        let completed = match &self.current {
+            Some(current) if current.lap != sample.lap => {
+                let analysis = self.finish(sample.session_time);
+                self.current = None;
+                analysis
+            }
+            _ => None,
+        };
+
+        let lap = self.current.get_or_insert_with(|| LapInProgress {
+            lap: sample.lap,
+            start: sample.session_time,
+            first_pct: sample.lap_dist_pct,
+            last_pct: sample.lap_dist_pct,
+            splits: Vec::new(),
+            samples: 0,
+            full_throttle: 0,
+            braking: 0,
+            coasting: 0,
+            overlap: 0,
+        });
+
+        if let Some(next) = self.sectors.get(lap.splits.len() + 1) {
+            if sample.lap_dist_pct >= *next {
+                lap.splits.push(sample.session_time);
+            }
+        }
+
+        lap.last_pct = sample.lap_dist_pct;
+        lap.samples += 1;
+        if sample.throttle >= 1.0 - RELEASED {
+            lap.full_throttle += 1;
+        }
+        if sample.brake > APPLIED {
+            lap.braking += 1;
+        }
+        if sample.throttle < RELEASED && sample.brake < RELEASED {
+            lap.coasting += 1;
+        }
+        if sample.throttle > APPLIED && sample.brake > APPLIED {
+            lap.overlap += 1;
+        }
+
+        if let Some(analysis) = &completed {
+            self.laps.push(analysis.clone());
+        }
+        completed
+    }
+
+    fn finish(&self, end: f64) -> Option<LapAnalysis> {
+        let lap = self.current.as_ref()?;
+        if lap.first_pct > LAP_START
+            || lap.last_pct < LAP_END
+            || lap.splits.len() + 1 != self.sectors.len()
+        {
+            return None;
+        }
+
+        let mut bounds = vec![lap.start];
+        bounds.extend(lap.splits.iter());
+        bounds.push(end);
+
+        let fraction = |n: u32| n as f32 / lap.samples as f32;
+        Some(LapAnalysis {
+            lap: lap.lap,
+            lap_time: end - lap.start,
+            sectors: bounds.windows(2).map(|w| w[1] - w[0]).collect(),
+            full_throttle: fraction(lap.full_throttle),
+            braking: fraction(lap.braking),
+            coasting: fraction(lap.coasting),
+            overlap: fraction(lap.overlap),
+        })
+    }
+
+    /// Complete laps analysed so far.
+    pub fn laps(&self) -> &[LapAnalysis] {
+        &self.laps
+    }
+
+    ///
+    /// Build the report from the complete laps so far.
+    pub fn build(&self) -> CoachingReport {
+        let laps = self.laps.clone();
+        let best = laps.iter().min_by(|a, b| {
+            a.lap_time
+                .partial_cmp(&b.lap_time)
+                .unwrap_or(std::cmp::Ordering::Equal)
+        });
+
+        let best_sectors: Vec<f64> = (0..self.sectors.len())
+            .filter_map(|i| {
+                laps.iter()
+                    .map(|l| l.sectors[i])
+                    .min_by(|a, b| a.partial_cmp(b).unwrap_or(std::cmp::Ordering::Equal))
+            })
+            .collect();
+
+        let times: Vec<f64> = laps.iter().map(|l| l.lap_time).collect();
+        let mean = if times.is_empty() {
+            None
+        } else {
+            Some(times.iter().sum::<f64>() / times.len() as f64)
+        };
+        let consistency = mean.filter(|_| times.len() > 1).map(|mean| {
+            (times.iter().map(|t| (t - mean).powi(2)).sum::<f64>() / times.len() as f64).sqrt()
+        });
+
+        let mut report = CoachingReport {
+            driver: self.driver.clone(),
+            best_lap: best.map(|l| l.lap),
+            best_lap_time: best.map(|l| l.lap_time),
+            theoretical_best: if best_sectors.is_empty() {
+                None
+            } else {
+                Some(best_sectors.iter().sum())
+            },
+            best_sectors,
+            mean_lap_time: mean,
+            consistency,
+            notes: Vec::new(),
+            laps,
+        };
+        report.notes = report.findings();
+        report
+    }
+}
+
+impl CoachingReport {
+    fn findings(&self) -> Vec<String> {
+        let mut notes = Vec::new();
+        if self.laps.is_empty() {
+            return notes;
+        }
+        let n = self.laps.len() as f64;
+
+        // Sector with the most time lost to the best, on average
+        let losses = self.best_sectors.iter().enumerate().map(|(i, best)| {
+            let mean = self.laps.iter().map(|l| l.sectors[i]).sum::<f64>() / n;
+            (i, mean - best)
+        });
+        if let Some((sector, loss)) = losses
+            .filter(|(_, loss)| *loss > 0.0)
+            .max_by(|a, b| a.1.partial_cmp(&b.1).unwrap_or(std::cmp::Ordering::Equal))
+        {
+            notes.push(format!(
+                "Sector {} is {:.3}s slower than your best on average",
+                sector + 1,
+                loss
+            ));
+        }
+
+        if let (Some(best), Some(theoretical)) = (self.best_lap_time, self.theoretical_best) {
+            if best - theoretical > 0.001 {
+                notes.push(format!(
+                    "Combining your best sectors would be {:.3}s faster than your best lap",
+                    best - theoretical
+                ));
+            }
+        }
+
+        let mean = |f: fn(&LapAnalysis) -> f32| self.laps.iter().map(f).sum::<f32>() / n as f32;
+        let coasting = mean(|l| l.coasting);
+        if coasting > 0.1 {
+            notes.push(format!(
+                "Coasting for {:.0}% of the lap; carry more throttle or brake later",
+                coasting * 100.0
+            ));
+        }
+        let overlap = mean(|l| l.overlap);
+        if overlap > 0.02 {
+            notes.push(format!(
+                "On throttle and brake together for {:.0}% of the lap",
+                overlap * 100.0
+            ));
+        }
+
+        notes
+    }
+
+    ///
+    /// Serialize the report as JSON.
+    pub fn to_json(&self) -> serde_json::Result<String> {
+        serde_json::to_string_pretty(self)
+    }
+
+    ///
+    /// Format the report as Markdown, with a table of laps.
+    pub fn to_markdown(&self) -> String {
+        let mut out = String::new();
+        let _ = writeln!(out, "# Coaching report: {}\n", self.driver);
+
+        let time = |t: Option<f64>| t.map_or(String::from("-"), |t| format!("{:.3}", t));
+        let _ = writeln!(out, "- Best lap: {}", time(self.best_lap_time));
+        let _ = writeln!(out, "- Theoretical best: {}", time(self.theoretical_best));
+        let _ = writeln!(out, "- Mean lap: {}", time(self.mean_lap_time));
+        let _ = writeln!(out, "- Consistency (std dev): {}\n", time(self.consistency));
+
+        let sectors = self.best_sectors.len();
+        let _ = write!(out, "| Lap | Time |");
+        for i in 0..sectors {
+            let _ = write!(out, " S{} |", i + 1);
+        }
+        let _ = writeln!(out, " Full throttle | Braking | Coasting |");
+        let _ = writeln!(out, "|{}", "---|".repeat(sectors + 5));
+
+        for lap in self.laps.iter() {
+            let _ = write!(out, "| {} | {:.3} |", lap.lap, lap.lap_time);
+            for sector in lap.sectors.iter() {
+                let _ = write!(out, " {:.3} |", sector);
+            }
+            let _ = writeln!(
+                out,
+                " {:.0}% | {:.0}% | {:.0}% |",
+                lap.full_throttle * 100.0,
+                lap.braking * 100.0,
+                lap.coasting * 100.0
+            );
+        }
+
+        if !self.notes.is_empty() {
+            let _ = writeln!(out, "\n## Notes\n");
+            for note in self.notes.iter() {
+                let _ = writeln!(out, "- {}", note);
+            }
+        }
+
+        out
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn coaching_report() {
+        let mut builder = CoachingReportBuilder::new("Driver 1").with_sectors(&[0.0, 0.5]);
+        let mut completed = 0;
+        let mut time = 0.0;
+
+        // An out lap from the middle of the track, then three full laps
+        for lap in 0..4 {
+            let start = if lap == 0 { 50 } else { 0 };
+            for i in start..100 {
+                let pct = i as f32 / 100.0;
+                let slow = lap == 2 && pct >= 0.5;
+                time += if slow { 1.2 } else { 1.0 };
+
+                let sample = CoachingSample {
+                    session_time: time,
+                    lap,
+                    lap_dist_pct: pct,
+                    throttle: if i < 80 { 1.0 } else { 0.0 },
+                    brake: if i >= 90 { 1.0 } else { 0.0 },
+                };
+                completed += builder.update(&sample).is_some() as i32;
+            }
+        }
+        builder.update(&CoachingSample {
+            session_time: time + 1.0,
+            lap: 4,
+            ..Default::default()
+        });
+
+        let report = builder.build();
+        assert_eq!(completed, 2);
+        assert_eq!(report.laps.len(), 3);
+        assert_eq!(report.best_lap, Some(1));
+        assert!((report.laps[1].lap_time - 110.0).abs() < 1e-6);
+        assert!((report.laps[1].sectors[1] - 59.8).abs() < 1e-6);
+        assert!((report.laps[0].full_throttle - 0.8).abs() < 1e-6);
+        assert!((report.laps[0].coasting - 0.1).abs() < 1e-6);
+        assert!((report.theoretical_best.unwrap() - 100.0).abs() < 1e-6);
+        assert!(report.consistency.unwrap() > 4.0);
+        assert!(report.notes[0].starts_with("Sector 2"));
+
+        let markdown = report.to_markdown();
+        assert!(markdown.contains("| Lap | Time | S1 | S2 |"));
+        assert!(markdown.contains("| 2 | 110.000 |"));
+        assert!(report.to_json().unwrap().contains("\"best_lap\": 1"));
+    }
+}
@@ -0,0 +1,404 @@
+use crate::broadcast::{
+    Broadcast, BroadcastMessage, CameraFocus, ChatCommandMode, FFBCommandMode, PitCommandMode,
+    ReplayPositionMode, ReplaySearchMode, TelemetryCommandMode, VideoCaptureMode,
+};
+use std::error::Error;
+use std::fmt;
+use std::str::FromStr;
+
+type Search = fn() -> ReplaySearchMode;
+type Builder = Box<dyn Fn(&Args) -> Result<BroadcastMessage, CommandError> + Send + Sync>;
+
+///
+/// Errors parsing a command string.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum CommandError {
+    Unknown(String),
+    MissingArgument { command: String, index: usize },
+    InvalidArgument { command: String, value: String },
+}
+
+///
+/// Arguments to a command, the comma separated list after its name.
+#[derive(Debug, Clone)]
+pub struct Args<'a> {
+    command: &'a str,
+    values: Vec<&'a str>,
+}
+
+struct Command {
+    name: String,
+    help: String,
+    build: Builder,
+}
+
+///
+/// Command Registry
+///
+/// Maps command strings to broadcast messages, so hotkey and Stream Deck
+/// bridges can drive the sim with strings such as `pit.fuel.add:10`,
+/// `camera.car:11` or `replay.prev_incident`.
+///
+/// A command is a name optionally followed by a colon and comma separated
+/// arguments. `standard()` registers commands for the whole broadcast API;
+/// see `help()` for the list.
+///
+/// # Examples
+///
+/// ```
+/// use iracing::broadcast::Broadcast;
+/// use iracing::commands::CommandRegistry;
+///
+/// let commands = CommandRegistry::standard();
+/// let broadcast = Broadcast::new();
+///
+/// for input in ["camera.car:11", "pit.fuel.add:10"] {
+///     if let Err(e) = commands.send(&broadcast, input) {
+///         println!("{}", e);
+///     }
+/// }
+/// ```
+#[derive(Default)]
+pub struct CommandRegistry {
+    commands: Vec<Command>,
+}
+
+impl fmt::Display for CommandError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            CommandError::Unknown(name) => write!(f, "Unknown command '{}'", name),
+            CommandError::MissingArgument { command, index } => {
+                write!(f, "'{}' needs argument {}", command, index + 1)
+            }
+            CommandError::InvalidArgument { command, value } => {
+                write!(f, "Invalid argument '{}' for '{}'", value, command)
+            }
+        }
+    }
+}
+
+impl Error for CommandError {}
+
+impl<'a> Args<'a> {
+    /// Number of arguments given.
+    pub fn len(&self) -> usize {
+        self.values.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.values.is_empty()
+    }
+
+    /// A required argument.
+    pub fn get<T: FromStr>(&self, index: usize) -> Result<T, CommandError> {
+        self.get_opt(index)?
+            .ok_or_else(|| CommandError::MissingArgument {
+                command: self.command.to_owned(),
+                index,
+            })
+    }
+
+    /// An optional argument, `default` if not given.
+    pub fn get_or<T: FromStr>(&self, index: usize, default: T) -> Result<T, CommandError> {
+        Ok(self.get_opt(index)?.unwrap_or(default))
+    }
+
+    /// The argument as a string, as given.
+    pub fn raw(&self, index: usize) -> Option<&'a str> {
+        self.values.get(index).copied()
+    }
+
+    fn get_opt<T: FromStr>(&self, index: usize) -> Result<Option<T>, CommandError> {
+        match self.values.get(index) {
+            None => Ok(None),
+            Some(value) => value
+                .parse()
+                .map(Some)
+                .map_err(|_| CommandError::InvalidArgument {
+                    command: self.command.to_owned(),
+                    value: (*value).to_owned(),
+                }),
+        }
+    }
+}
+
+impl CommandRegistry {
+    /// An empty registry.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    ///
+    /// A registry with commands for the whole broadcast API.
+    pub fn standard() -> Self {
+        use BroadcastMessage as M;
+
+        let mut registry = Self::new();
+
+        // Camera
+        registry.register("camera.car", "car number[,group,camera]", |a| {
+            Ok(M::CameraSwitchNumber(
+                a.get(0)?,
+                a.get_or(1, 0)?,
+                a.get_or(2, 0)?,
+            ))
+        });
+        registry.register("camera.position", "position[,group,camera]", |a| {
+            Ok(M::CameraSwitchPosition(
+                a.get(0)?,
+                a.get_or(1, 0)?,
+                a.get_or(2, 0)?,
+            ))
+        });
+        for (name, focus) in [
+            ("camera.leader", CameraFocus::Leader),
+            ("camera.incident", CameraFocus::Incident),
+            ("camera.exiting", CameraFocus::Exiting),
+        ] {
+            registry.register(name, "[group,camera]", move |a| {
+                Ok(M::CameraSwitchFocus(
+                    focus,
+                    a.get_or(0, 0)?,
+                    a.get_or(1, 0)?,
+                ))
+            });
+        }
+
+        // Replay
+        registry.register("replay.play", "", |_| Ok(M::ReplaySetPlaySpeed(1, false)));
+        registry.register("replay.pause", "", |_| Ok(M::ReplaySetPlaySpeed(0, false)));
+        registry.register("replay.speed", "speed[,slow motion]", |a| {
+            Ok(M::ReplaySetPlaySpeed(a.get(0)?, a.get_or(1, false)?))
+        });
+        registry.register("replay.frame", "frame", |a| {
+            Ok(M::ReplaySetPlayPosition(
+                ReplayPositionMode::Begin,
+                a.get(0)?,
+            ))
+        });
+        registry.register("replay.session_time", "session,milliseconds", |a| {
+            Ok(M::ReplaySearchSessionTime(a.get(0)?, a.get(1)?))
+        });
+        let searches: [(&str, Search); 10] = [
+            ("replay.start", || ReplaySearchMode::ToStart),
+            ("replay.end", || ReplaySearchMode::ToEnd),
+            ("replay.prev_session", || ReplaySearchMode::PreviousSession),
+            ("replay.next_session", || ReplaySearchMode::NextSession),
+            ("replay.prev_lap", || ReplaySearchMode::PreviousLap),
+            ("replay.next_lap", || ReplaySearchMode::NextLap),
+            ("replay.prev_frame", || ReplaySearchMode::PreviousFrame),
+            ("replay.next_frame", || ReplaySearchMode::NextFrame),
+            ("replay.prev_incident", || {
+                ReplaySearchMode::PreviousIncident
+            }),
+            ("replay.next_incident", || ReplaySearchMode::NextIncident),
+        ];
+        for (name, mode) in searches {
+            registry.register(name, "", move |_| Ok(M::ReplaySearch(mode())));
+        }
+
+        // Pit service
+        registry.register("pit.clear", "", |_| {
+            Ok(M::PitCommand(PitCommandMode::Clear))
+        });
+        registry.register("pit.fuel.add", "litres", |a| {
+            Ok(M::PitCommand(PitCommandMode::Fuel(a.get(0)?)))
+        });
+        registry.register("pit.fuel.clear", "", |_| {
+            Ok(M::PitCommand(PitCommandMode::ClearFuel))
+        });
+        registry.register("pit.tires.lf", "[pressure]", |a| {
+            Ok(M::PitCommand(PitCommandMode::LF(a.get_or(0, 0)?)))
+        });
+        registry.register("pit.tires.rf", "[pressure]", |a| {
+            Ok(M::PitCommand(PitCommandMode::RF(a.get_or(0, 0)?)))
+        });
+        registry.register("pit.tires.lr", "[pressure]", |a| {
+            Ok(M::PitCommand(PitCommandMode::LR(a.get_or(0, 0)?)))
+        });
+        registry.register("pit.tires.rr", "[pressure]", |a| {
+            Ok(M::PitCommand(PitCommandMode::RR(a.get_or(0, 0)?)))
+        });
+        registry.register("pit.tires.clear", "", |_| {
+            Ok(M::PitCommand(PitCommandMode::ClearTires))
+        });
+        registry.register("pit.tires.compound", "compound", |a| {
+            Ok(M::PitCommand(PitCommandMode::TireCompound(a.get(0)?)))
+        });
+        registry.register("pit.tearoff", "", |_| {
+            Ok(M::PitCommand(PitCommandMode::Tearoff))
+        });
+        registry.register("pit.tearoff.clear", "", |_| {
+            Ok(M::PitCommand(PitCommandMode::ClearTearoff))
+        });
+        registry.register("pit.fast_repair", "", |_| {
+            Ok(M::PitCommand(PitCommandMode::FastRepair))
+        });
+        registry.register("pit.fast_repair.clear", "", |_| {
+            Ok(M::PitCommand(PitCommandMode::ClearFastRepair))
+        });
+
+        // Chat
+        registry.register("chat.macro", "macro number", |a| {
+            Ok(M::ChatCommandMacro(a.get(0)?))
+        });
+        registry.register("chat.begin", "", |_| {
+            Ok(M::ChatCommand(ChatCommandMode::Begin))
+        });
+        registry.register("chat.reply", "", |_| {
+            Ok(M::ChatCommand(ChatCommandMode::Reply))
+        });
+        registry.register("chat.cancel", "", |_| {
+            Ok(M::ChatCommand(ChatCommandMode::Cancel))
+        });
+
+        // Telemetry, video and textures
+        registry.register("telemetry.start", "", |_| {
+            Ok(M::TelemetryCommand(TelemetryCommandMode::Start))
+        });
+        registry.register("telemetry.stop", "", |_| {
+            Ok(M::TelemetryCommand(TelemetryCommandMode::Stop))
+        });
+        registry.register("telemetry.restart", "", |_| {
+            Ok(M::TelemetryCommand(TelemetryCommandMode::Restart))
+        });
+        registry.register("video.screenshot", "", |_| {
+            Ok(M::VideoCapture(VideoCaptureMode::ScreenShot))
+        });
+        registry.register("video.start", "", |_| {
+            Ok(M::VideoCapture(VideoCaptureMode::StartCapture))
+        });
+        registry.register("video.stop", "", |_| {
+            Ok(M::VideoCapture(VideoCaptureMode::EndCapture))
+        });
+        registry.register("video.toggle", "", |_| {
+            Ok(M::VideoCapture(VideoCaptureMode::ToggleCapture))
+        });
+        registry.register("video.timer.show", "", |_| {
+            Ok(M::VideoCapture(VideoCaptureMode::ShowTimer))
+        });
+        registry.register("video.timer.hide", "", |_| {
+            Ok(M::VideoCapture(VideoCaptureMode::HideTimer))
+        });
+        registry.register("textures.reload", "[car index]", |a| match a.raw(0) {
+            None => Ok(M::ReloadAllTextures),
+            Some(_) => Ok(M::ReloadTextures(a.get(0)?)),
+        });
+        registry.register("ffb.max_force", "newton metres", |a| {
+            Ok(M::FFBCommand(FFBCommandMode::MaxForce(a.get(0)?)))
+        });
+
+        registry
+    }
+
+    ///
+    /// Register a command, replacing any existing command of the same name.
+    ///
+    /// `help` describes the arguments, e.g. `"litres"` or `"[group,camera]"`.
+    pub fn register<F>(&mut self, name: &str, help: &str, build: F)
+    where
+        F: Fn(&Args) -> Result<BroadcastMessage, CommandError> + Send + Sync + 'static,
+    {
+        self.commands.retain(|c| c.name != name);
+        self.commands.push(Command {
+            name: name.to_owned(),
+            help: help.to_owned(),
+            build: Box::new(build),
+        });
+    }
+
+    /// Registered command names, in the order they were registered.
+    pub fn names(&self) -> impl Iterator<Item = &str> {
+        self.commands.iter().map(|c| c.name.as_str())
+    }
+
+    /// One line per command, with its arguments.
+    pub fn help(&self) -> String {
+        self.commands
+            .iter()
+            .map(|c| match c.help.as_str() {
+                "" => format!("{}\n", c.name),
+                help => format!("{}:{}\n", c.name, help),
+            })
+            .collect()
+    }
+
+    ///
+    /// Parse a command string into the message it sends.
+    pub fn parse(&self, input: &str) -> Result<BroadcastMessage, CommandError> {
+        let input = input.trim();
+        let (name, args) = match input.split_once(':') {
+            Some((name, args)) => (name.trim(), args),
+            None => (input, ""),
+        };
+
+        let command = self
+            .commands
+            .iter()
+            .find(|c| c.name == name)
+            .ok_or_else(|| CommandError::Unknown(name.to_owned()))?;
+
+        let values = args
+            .split(',')
+            .map(str::trim)
+            .filter(|v| !v.is_empty())
+            .collect();
+
+        (command.build)(&Args {
+            command: name,
+            values,
+        })
+    }
+
+    ///
+    /// Parse a command string and send it to the sim.
+    pub fn send(&self, broadcast: &Broadcast, input: &str) -> Result<(), CommandError> {
+        broadcast.send_message(self.parse(input)?);
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_commands() {
+        let commands = CommandRegistry::standard();
+
+        assert!(matches!(
+            commands.parse("pit.fuel.add:10"),
+            Ok(BroadcastMessage::PitCommand(PitCommandMode::Fuel(10)))
+        ));
+        assert!(matches!(
+            commands.parse(" camera.car: 011 , 2 "),
+            Ok(BroadcastMessage::CameraSwitchNumber(ref n, 2, 0)) if n == "011"
+        ));
+        assert!(matches!(
+            commands.parse("replay.prev_incident"),
+            Ok(BroadcastMessage::ReplaySearch(
+                ReplaySearchMode::PreviousIncident
+            ))
+        ));
+
+        assert_eq!(
+            commands.parse("pit.fuel.add").err(),
+            Some(CommandError::MissingArgument {
+                command: String::from("pit.fuel.add"),
+                index: 0
+            })
+        );
+        assert_eq!(
+            commands.parse("pit.fuel.add:lots").err(),
+            Some(CommandError::InvalidArgument {
+                command: String::from("pit.fuel.add"),
+                value: String::from("lots")
+            })
+        );
+        assert!(matches!(
+            commands.parse("pit.fly"),
+            Err(CommandError::Unknown(_))
+        ));
+        assert!(commands.help().contains("pit.fuel.add:litres\n"));
+    }
+}
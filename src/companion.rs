@@ -0,0 +1,380 @@
+use crate::simulation::Simulation;
+use std::time::{Duration, Instant};
+
+#[cfg(target_os = "windows")]
+use crate::fps::Fps;
+#[cfg(target_os = "windows")]
+use crate::pipeline::{ExporterStats, Pipeline};
+#[cfg(target_os = "windows")]
+use crate::telemetry::{CancelToken, Connection, TelemetryError};
+#[cfg(target_os = "windows")]
+use std::error::Error;
+#[cfg(target_os = "windows")]
+use std::io::Result as IOResult;
+#[cfg(target_os = "windows")]
+use std::sync::atomic::{AtomicBool, Ordering};
+#[cfg(target_os = "windows")]
+use std::sync::Arc;
+#[cfg(target_os = "windows")]
+use std::thread;
+
+///
+/// How a companion tells the sim is running.
+#[derive(Debug, Clone, Default)]
+pub enum Detection {
+    /// The telemetry memory map exists and the sim reports itself connected
+    #[default]
+    MemoryMap,
+
+    /// The sim's status port reports it running, see `Simulation::check_status`
+    Status(Simulation),
+}
+
+///
+/// A change in whether the sim is running.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum SimEvent {
+    Started,
+    Stopped,
+}
+
+///
+/// Sim Watch
+///
+/// Turns regular checks of whether the sim is running into `Started` and
+/// `Stopped` events. The sim briefly drops its connection when loading a
+/// session, so it is only reported stopped once it has been gone for the
+/// grace period.
+///
+/// # Examples
+///
+/// ```
+/// use iracing::companion::{SimEvent, SimWatch};
+/// use std::time::{Duration, Instant};
+///
+/// let start = Instant::now();
+/// let mut watch = SimWatch::new(Duration::from_secs(5));
+///
+/// assert_eq!(watch.update_at(start, true), Some(SimEvent::Started));
+/// assert_eq!(watch.update_at(start + Duration::from_secs(1), false), None);
+/// assert_eq!(watch.update_at(start + Duration::from_secs(6), false), Some(SimEvent::Stopped));
+/// ```
+#[derive(Debug, Clone)]
+pub struct SimWatch {
+    grace: Duration,
+    running: bool,
+    missing_since: Option<Instant>,
+}
+
+///
+/// Companion
+///
+/// Waits for the sim to start, launches a pipeline for it and feeds it every
+/// sample until the sim exits, then finishes the pipeline and waits for the
+/// sim again. Runs until its cancel token is cancelled, such as from a
+/// Ctrl-C handler or a service stop request.
+///
+/// # Examples
+///
+/// ```no_run
+/// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+/// use iracing::chunked::ChunkedWriter;
+/// use iracing::companion::Companion;
+/// use iracing::pipeline::{Backpressure, Pipeline};
+///
+/// let companion = Companion::new()?;
+///
+/// companion.run(
+///     |connection| {
+///         let track = connection.session_info()?.weekend.track_name;
+///         let file = ChunkedWriter::create(format!("{}.irc", track))?;
+///
+///         Ok(Pipeline::new().with_exporter("file", file, Backpressure::Buffer(600)))
+///     },
+///     |stats| println!("Sim exited, {} samples written", stats[0].exported),
+///     |e| eprintln!("Failed to launch pipeline, retrying: {}", e),
+/// )?;
+/// # Ok(())
+/// # }
+/// ```
+#[cfg(target_os = "windows")]
+#[derive(Debug)]
+pub struct Companion {
+    detection: Detection,
+    poll_interval: Duration,
+    grace: Duration,
+    fps: Fps,
+    token: CancelToken,
+}
+
+impl SimWatch {
+    pub fn new(grace: Duration) -> Self {
+        SimWatch {
+            grace,
+            running: false,
+            missing_since: None,
+        }
+    }
+
+    pub fn is_running(&self) -> bool {
+        self.running
+    }
+
+    /// Record whether the sim is running now.
+    pub fn update(&mut self, present: bool) -> Option<SimEvent> {
+        self.update_at(Instant::now(), present)
+    }
+
+    /// Record whether the sim was running at `now`.
+    pub fn update_at(&mut self, now: Instant, present: bool) -> Option<SimEvent> {
+        if present {
+            self.missing_since = None;
+            if self.running {
+                return None;
+            }
+
+            self.running = true;
+            return Some(SimEvent::Started);
+        }
+
+        if !self.running {
+            return None;
+        }
+
+        let since = *self.missing_since.get_or_insert(now);
+        if now.saturating_duration_since(since) < self.grace {
+            return None;
+        }
+
+        self.running = false;
+        self.missing_since = None;
+        Some(SimEvent::Stopped)
+    }
+}
+
+///
+/// The sim's status port, checked on a thread of its own so a slow answer
+/// doesn't hold up sampling. Stops once dropped.
+#[cfg(target_os = "windows")]
+struct StatusPoll {
+    running: Arc<AtomicBool>,
+    stop: Arc<AtomicBool>,
+}
+
+#[cfg(target_os = "windows")]
+impl StatusPoll {
+    fn start(simulation: Simulation, interval: Duration) -> Self {
+        let running = Arc::new(AtomicBool::new(false));
+        let stop = Arc::new(AtomicBool::new(false));
+
+        let (r, s) = (running.clone(), stop.clone());
+        thread::spawn(move || {
+            while !s.load(Ordering::Relaxed) {
+                r.store(simulation.check_status(), Ordering::Relaxed);
+                thread::sleep(interval);
+            }
+        });
+
+        StatusPoll { running, stop }
+    }
+
+    fn running(&self) -> bool {
+        self.running.load(Ordering::Relaxed)
+    }
+}
+
+#[cfg(target_os = "windows")]
+impl Drop for StatusPoll {
+    fn drop(&mut self) {
+        self.stop.store(true, Ordering::Relaxed);
+    }
+}
+
+#[cfg(target_os = "windows")]
+impl Companion {
+    pub fn new() -> IOResult<Self> {
+        Ok(Companion {
+            detection: Detection::default(),
+            poll_interval: Duration::from_secs(1),
+            grace: Duration::from_secs(10),
+            fps: Fps::MAX,
+            token: CancelToken::new()?,
+        })
+    }
+
+    pub fn with_detection(mut self, detection: Detection) -> Self {
+        self.detection = detection;
+        self
+    }
+
+    /// How often to check whether the sim is running. 1s by default.
+    pub fn with_poll_interval(mut self, interval: Duration) -> Self {
+        self.poll_interval = interval;
+        self
+    }
+
+    ///
+    /// How long the sim must be gone before the pipeline is finished. 10s by
+    /// default, long enough to ride out loading a session.
+    pub fn with_grace(mut self, grace: Duration) -> Self {
+        self.grace = grace;
+        self
+    }
+
+    /// Rate samples are pushed to the pipeline at. 60 FPS by default.
+    pub fn with_fps(mut self, fps: Fps) -> Self {
+        self.fps = fps;
+        self
+    }
+
+    /// Token stopping `run`, finishing any running pipeline first.
+    pub fn token(&self) -> CancelToken {
+        self.token.clone()
+    }
+
+    ///
+    /// Launch a pipeline each time the sim starts, until cancelled.
+    ///
+    /// `launch` is called with a connection to the sim once it is running.
+    /// Session info often isn't ready straight away, so if it fails the error
+    /// is passed to `failed` and it is tried again after the poll interval.
+    /// `stopped` gets the pipeline's final stats once the sim exits or the
+    /// companion is cancelled.
+    pub fn run<L, S, F>(
+        &self,
+        mut launch: L,
+        mut stopped: S,
+        mut failed: F,
+    ) -> Result<(), Box<dyn Error>>
+    where
+        L: FnMut(&mut Connection) -> Result<Pipeline, Box<dyn Error>>,
+        S: FnMut(Vec<ExporterStats>),
+        F: FnMut(&dyn Error),
+    {
+        let mut watch = SimWatch::new(self.grace);
+        let mut connection: Option<Connection> = None;
+        let status = match &self.detection {
+            Detection::MemoryMap => None,
+            Detection::Status(simulation) => {
+                Some(StatusPoll::start(simulation.clone(), self.poll_interval))
+            }
+        };
+
+        loop {
+            // The map stays open between runs, the sim reuses it when restarted
+            if connection.is_none() {
+                connection = Connection::new().ok();
+            }
+
+            let running = match connection.as_ref() {
+                Some(connection) => {
+                    watch.update(self.is_running(connection, status.as_ref()));
+                    watch.is_running()
+                }
+                None => false,
+            };
+            let connection = match connection.as_mut() {
+                Some(connection) if running => connection,
+                _ => {
+                    if self.token.wait(self.poll_interval) {
+                        return Ok(());
+                    }
+                    continue;
+                }
+            };
+
+            let mut pipeline = match launch(connection) {
+                Ok(pipeline) => pipeline,
+                Err(e) => {
+                    failed(e.as_ref());
+                    if self.token.wait(self.poll_interval) {
+                        return Ok(());
+                    }
+                    continue;
+                }
+            };
+            let cancelled = self.feed(connection, &mut pipeline, &mut watch, status.as_ref());
+
+            stopped(pipeline.finish());
+            if cancelled? {
+                return Ok(());
+            }
+        }
+    }
+
+    /// Push samples to the pipeline until the sim stops, returning true if cancelled.
+    fn feed(
+        &self,
+        connection: &Connection,
+        pipeline: &mut Pipeline,
+        watch: &mut SimWatch,
+        status: Option<&StatusPoll>,
+    ) -> Result<bool, Box<dyn Error>> {
+        let blocking = connection.blocking()?.with_cancel(self.token.clone());
+        let mut next = Instant::now();
+        let mut next_check = next + self.poll_interval;
+
+        loop {
+            match blocking.sample(self.poll_interval) {
+                Ok(sample) => {
+                    let now = Instant::now();
+                    if now >= next {
+                        next = now + self.fps.to_duration();
+                        pipeline.push(&sample);
+                    }
+                }
+                Err(e) => match e.downcast_ref::<TelemetryError>() {
+                    Some(TelemetryError::CANCELLED) => return Ok(true),
+                    Some(TelemetryError::TIMEOUT(_)) => (),
+                    Some(_) => return Err(e),
+                    // The sample couldn't be read, such as mid session change
+                    None => (),
+                },
+            }
+
+            let now = Instant::now();
+            if now < next_check {
+                continue;
+            }
+            next_check = now + self.poll_interval;
+
+            if watch.update(self.is_running(connection, status)) == Some(SimEvent::Stopped) {
+                return Ok(false);
+            }
+        }
+    }
+
+    fn is_running(&self, connection: &Connection, status: Option<&StatusPoll>) -> bool {
+        match status {
+            Some(status) => status.running(),
+            None => connection.health().connected,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn watch_sim_lifecycle() {
+        let start = Instant::now();
+        let at = |s: u64| start + Duration::from_secs(s);
+        let mut watch = SimWatch::new(Duration::from_secs(10));
+
+        assert_eq!(watch.update_at(at(0), false), None);
+        assert_eq!(watch.update_at(at(1), true), Some(SimEvent::Started));
+        assert_eq!(watch.update_at(at(2), true), None);
+
+        // Loading a session drops the connection for a few seconds
+        assert_eq!(watch.update_at(at(3), false), None);
+        assert_eq!(watch.update_at(at(8), false), None);
+        assert_eq!(watch.update_at(at(9), true), None);
+        assert_eq!(watch.update_at(at(15), false), None);
+        assert!(watch.is_running());
+
+        assert_eq!(watch.update_at(at(25), false), Some(SimEvent::Stopped));
+        assert_eq!(watch.update_at(at(40), false), None);
+        assert_eq!(watch.update_at(at(41), true), Some(SimEvent::Started));
+    }
+}
@@ -0,0 +1,197 @@
+//!
+//! Compressed streams, enabled by the `compression` feature.
+//!
+//! Recordings and exports of long sessions get large quickly; these wrap any
+//! reader or writer in gzip or zstd compression. Readers detect the format
+//! from the stream itself, so plain and compressed files can be mixed.
+
+use flate2::bufread::MultiGzDecoder;
+use flate2::write::GzEncoder;
+use std::io::{self, BufRead, BufReader, Read, Write};
+use std::path::Path;
+
+const GZIP_MAGIC: [u8; 2] = [0x1f, 0x8b];
+const ZSTD_MAGIC: [u8; 4] = [0x28, 0xb5, 0x2f, 0xfd];
+
+///
+/// Compression format of a stream.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Default)]
+pub enum Compression {
+    #[default]
+    None,
+    Gzip,
+    Zstd,
+}
+
+///
+/// Compressed Writer
+///
+/// Compresses everything written to it. Call `finish` when done to write the
+/// end of the compressed stream; dropping the writer without finishing may
+/// leave a truncated stream.
+///
+/// # Examples
+///
+/// ```
+/// use iracing::compression::{Compression, CompressedReader, CompressedWriter};
+/// use std::io::{Read, Write};
+///
+/// let mut writer = CompressedWriter::new(Vec::new(), Compression::Zstd).unwrap();
+/// writer.write_all(b"SessionTime,Speed\n").unwrap();
+/// let compressed = writer.finish().unwrap();
+///
+/// let mut text = String::new();
+/// CompressedReader::new(compressed.as_slice())
+///     .unwrap()
+///     .read_to_string(&mut text)
+///     .unwrap();
+/// assert_eq!(text, "SessionTime,Speed\n");
+/// ```
+pub enum CompressedWriter<W: Write> {
+    Plain(W),
+    Gzip(GzEncoder<W>),
+    Zstd(zstd::Encoder<'static, W>),
+}
+
+///
+/// Compressed Reader
+///
+/// Decompresses a stream, detecting gzip, zstd or uncompressed data from its
+/// first bytes.
+pub enum CompressedReader<R: Read> {
+    Plain(BufReader<R>),
+    Gzip(MultiGzDecoder<BufReader<R>>),
+    Zstd(zstd::Decoder<'static, BufReader<R>>),
+}
+
+impl Compression {
+    ///
+    /// Compression implied by a file extension: `.gz` or `.zst`.
+    pub fn from_path<P: AsRef<Path>>(path: P) -> Self {
+        match path.as_ref().extension().and_then(|e| e.to_str()) {
+            Some("gz") => Compression::Gzip,
+            Some("zst") | Some("zstd") => Compression::Zstd,
+            _ => Compression::None,
+        }
+    }
+
+    ///
+    /// Compression of a stream, from its first bytes.
+    pub fn detect(data: &[u8]) -> Self {
+        if data.starts_with(&GZIP_MAGIC) {
+            Compression::Gzip
+        } else if data.starts_with(&ZSTD_MAGIC) {
+            Compression::Zstd
+        } else {
+            Compression::None
+        }
+    }
+}
+
+impl<W: Write> CompressedWriter<W> {
+    /// Compress into `inner` at the default level.
+    pub fn new(inner: W, compression: Compression) -> io::Result<Self> {
+        Ok(match compression {
+            Compression::None => CompressedWriter::Plain(inner),
+            Compression::Gzip => {
+                CompressedWriter::Gzip(GzEncoder::new(inner, flate2::Compression::default()))
+            }
+            Compression::Zstd => CompressedWriter::Zstd(zstd::Encoder::new(inner, 0)?),
+        })
+    }
+
+    ///
+    /// Write the end of the compressed stream, returning the inner writer.
+    pub fn finish(self) -> io::Result<W> {
+        match self {
+            CompressedWriter::Plain(mut w) => {
+                w.flush()?;
+                Ok(w)
+            }
+            CompressedWriter::Gzip(w) => w.finish(),
+            CompressedWriter::Zstd(w) => w.finish(),
+        }
+    }
+}
+
+impl<W: Write> Write for CompressedWriter<W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        match self {
+            CompressedWriter::Plain(w) => w.write(buf),
+            CompressedWriter::Gzip(w) => w.write(buf),
+            CompressedWriter::Zstd(w) => w.write(buf),
+        }
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        match self {
+            CompressedWriter::Plain(w) => w.flush(),
+            CompressedWriter::Gzip(w) => w.flush(),
+            CompressedWriter::Zstd(w) => w.flush(),
+        }
+    }
+}
+
+impl<R: Read> CompressedReader<R> {
+    /// Decompress `inner`, whatever its format.
+    pub fn new(inner: R) -> io::Result<Self> {
+        let mut reader = BufReader::new(inner);
+
+        Ok(match Compression::detect(reader.fill_buf()?) {
+            Compression::None => CompressedReader::Plain(reader),
+            Compression::Gzip => CompressedReader::Gzip(MultiGzDecoder::new(reader)),
+            Compression::Zstd => CompressedReader::Zstd(zstd::Decoder::with_buffer(reader)?),
+        })
+    }
+
+    /// Format of the stream being read.
+    pub fn compression(&self) -> Compression {
+        match self {
+            CompressedReader::Plain(_) => Compression::None,
+            CompressedReader::Gzip(_) => Compression::Gzip,
+            CompressedReader::Zstd(_) => Compression::Zstd,
+        }
+    }
+}
+
+impl<R: Read> Read for CompressedReader<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        match self {
+            CompressedReader::Plain(r) => r.read(buf),
+            CompressedReader::Gzip(r) => r.read(buf),
+            CompressedReader::Zstd(r) => r.read(buf),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn round_trip() {
+        let data: Vec<u8> = (0..10_000u32).flat_map(|i| (i % 7).to_le_bytes()).collect();
+
+        for compression in [Compression::None, Compression::Gzip, Compression::Zstd] {
+            let mut writer = CompressedWriter::new(Vec::new(), compression).unwrap();
+            writer.write_all(&data).unwrap();
+            let stored = writer.finish().unwrap();
+
+            assert_eq!(Compression::detect(&stored), compression);
+            if compression != Compression::None {
+                assert!(stored.len() < data.len() / 10);
+            }
+
+            let mut reader = CompressedReader::new(stored.as_slice()).unwrap();
+            assert_eq!(reader.compression(), compression);
+
+            let mut read = Vec::new();
+            reader.read_to_end(&mut read).unwrap();
+            assert_eq!(read, data);
+        }
+
+        assert_eq!(Compression::from_path("race.irrec.zst"), Compression::Zstd);
+        assert_eq!(Compression::from_path("laps.csv.gz"), Compression::Gzip);
+        assert_eq!(Compression::from_path("race.irrec"), Compression::None);
+    }
+}
@@ -0,0 +1,303 @@
+use crate::results::Results;
+use crate::validity::PendingLap;
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, VecDeque};
+
+#[cfg(feature = "telemetry")]
+use crate::telemetry::Sample;
+#[cfg(feature = "telemetry")]
+use std::convert::TryInto;
+#[cfg(feature = "telemetry")]
+use std::error::Error;
+
+///
+/// Consistency Sample
+///
+/// Per-car lap counts and last lap times at a point in time, indexed by car
+/// index.
+#[derive(Debug, Clone, Default)]
+pub struct ConsistencySample {
+    pub session_time: f64,        // SessionTime
+    pub laps_completed: Vec<i32>, // CarIdxLapCompleted
+    pub last_lap_time: Vec<f32>,  // CarIdxLastLapTime - -1 until a lap is timed (s)
+}
+
+///
+/// Lap time consistency of a car over its recent laps.
+#[derive(Debug, Copy, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Consistency {
+    pub car_idx: usize,
+    pub laps: usize,     // Laps counted
+    pub rejected: usize, // Laps in the window left out as outliers
+    pub mean: f32,       // Mean lap time (s)
+    pub stddev: f32,     // Standard deviation of lap times (s)
+}
+
+///
+/// Consistency Tracker
+///
+/// Keeps a rolling window of each car's lap times and measures how consistent
+/// they are. Laps much slower than the car's median, such as laps in traffic,
+/// under caution or through the pits, are left out.
+///
+/// # Examples
+///
+/// ```
+/// use iracing::consistency::{ConsistencySample, ConsistencyTracker};
+///
+/// let mut tracker = ConsistencyTracker::new().with_window(10);
+/// tracker.update(&ConsistencySample::default());
+///
+/// for c in tracker.most_consistent(5).iter().take(3) {
+///     println!("Car {}: ±{:.3}s over {} laps", c.car_idx, c.stddev, c.laps);
+/// }
+/// ```
+#[derive(Debug, Clone)]
+pub struct ConsistencyTracker {
+    window: usize,
+    outlier: f32,
+    laps_completed: Vec<i32>,
+    last_lap_time: Vec<f32>,
+    pending: Vec<Option<PendingLap>>,
+    times: HashMap<usize, VecDeque<f32>>,
+}
+
+impl ConsistencySample {
+    ///
+    /// Read a consistency sample from a telemetry sample.
+    #[cfg(feature = "telemetry")]
+    pub fn from_sample(sample: &Sample) -> Result<Self, Box<dyn Error>> {
+        Ok(ConsistencySample {
+            session_time: sample.get("SessionTime")?.try_into()?,
+            laps_completed: sample.get("CarIdxLapCompleted")?.try_into()?,
+            last_lap_time: sample.get("CarIdxLastLapTime")?.try_into()?,
+        })
+    }
+}
+
+impl Default for ConsistencyTracker {
+    fn default() -> Self {
+        ConsistencyTracker {
+            window: 10,
+            outlier: 0.03,
+            laps_completed: Vec::new(),
+            last_lap_time: Vec::new(),
+            pending: Vec::new(),
+            times: HashMap::new(),
+        }
+    }
+}
+
+impl ConsistencyTracker {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Number of recent laps to measure over.
+    pub fn with_window(mut self, laps: usize) -> Self {
+        self.window = laps.max(2);
+        self
+    }
+
+    ///
+    /// Leave out laps slower than the car's median by more than this fraction,
+    /// 0.03 (3%) by default.
+    pub fn with_outlier_threshold(mut self, fraction: f32) -> Self {
+        self.outlier = fraction;
+        self
+    }
+
+    ///
+    /// Record the lap time of every car which has completed a lap, once the
+    /// sim has updated it, see `PendingLap`.
+    pub fn update(&mut self, sample: &ConsistencySample) {
+        let cars = sample.laps_completed.len();
+        if self.laps_completed.len() < cars {
+            self.laps_completed.resize(cars, -1);
+            self.last_lap_time.resize(cars, -1.0);
+            self.pending.resize(cars, None);
+        }
+
+        for (car_idx, completed) in sample.laps_completed.iter().enumerate() {
+            let time = sample.last_lap_time.get(car_idx).copied().unwrap_or(-1.0);
+            let previous = std::mem::replace(&mut self.laps_completed[car_idx], *completed);
+            let previous_time = std::mem::replace(&mut self.last_lap_time[car_idx], time);
+
+            // A lap still waiting is timed before the next is added
+            if let Some(lap) = self.pending[car_idx] {
+                if let Some(reading) = lap.poll(time, sample.session_time) {
+                    self.pending[car_idx] = None;
+                    self.record(car_idx, reading.value());
+                }
+            }
+
+            if *completed > previous && previous >= 0 {
+                let lap = PendingLap::new(previous_time, sample.session_time);
+                match lap.poll(time, sample.session_time) {
+                    Some(reading) => self.record(car_idx, reading.value()),
+                    None => self.pending[car_idx] = Some(lap),
+                }
+            }
+        }
+    }
+
+    fn record(&mut self, car_idx: usize, time: Option<f32>) {
+        if let Some(time) = time {
+            let times = self.times.entry(car_idx).or_default();
+            times.push_back(time);
+            while times.len() > self.window {
+                times.pop_front();
+            }
+        }
+    }
+
+    /// Forget all lap times, e.g. at the start of a new session.
+    pub fn reset(&mut self) {
+        self.laps_completed.clear();
+        self.last_lap_time.clear();
+        self.pending.clear();
+        self.times.clear();
+    }
+
+    ///
+    /// Consistency of a car over its recent laps, once at least two laps
+    /// remain after leaving out outliers.
+    pub fn consistency(&self, car_idx: usize) -> Option<Consistency> {
+        let times = self.times.get(&car_idx)?;
+
+        let mut sorted: Vec<f32> = times.iter().copied().collect();
+        sorted.sort_by(|a, b| a.partial_cmp(b).unwrap_or(std::cmp::Ordering::Equal));
+        let median = sorted[sorted.len() / 2];
+
+        let kept: Vec<f32> = times
+            .iter()
+            .copied()
+            .filter(|t| *t <= median * (1.0 + self.outlier))
+            .collect();
+        if kept.len() < 2 {
+            return None;
+        }
+
+        let n = kept.len() as f32;
+        let mean = kept.iter().sum::<f32>() / n;
+        let variance = kept.iter().map(|t| (t - mean).powi(2)).sum::<f32>() / n;
+
+        Some(Consistency {
+            car_idx,
+            laps: kept.len(),
+            rejected: times.len() - kept.len(),
+            mean,
+            stddev: variance.sqrt(),
+        })
+    }
+
+    ///
+    /// Cars with at least `min_laps` counted laps, most consistent first.
+    pub fn most_consistent(&self, min_laps: usize) -> Vec<Consistency> {
+        let mut all: Vec<Consistency> = self
+            .times
+            .keys()
+            .filter_map(|car_idx| self.consistency(*car_idx))
+            .filter(|c| c.laps >= min_laps)
+            .collect();
+
+        all.sort_by(|a, b| {
+            a.stddev
+                .partial_cmp(&b.stddev)
+                .unwrap_or(std::cmp::Ordering::Equal)
+                .then(a.car_idx.cmp(&b.car_idx))
+        });
+        all
+    }
+
+    ///
+    /// Fill in the consistency of each car in a set of standings.
+    pub fn apply(&self, results: &mut Results) {
+        for standing in results.standings.iter_mut() {
+            standing.consistency = self.consistency(standing.car_idx).map(|c| c.stddev);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::session::SessionDetails;
+
+    #[test]
+    fn rolling_consistency() {
+        let mut tracker = ConsistencyTracker::new().with_window(5);
+        let mut sample = ConsistencySample {
+            session_time: 0.0,
+            laps_completed: vec![0, 0, 0],
+            last_lap_time: vec![-1.0; 3],
+        };
+        tracker.update(&sample);
+
+        let laps: [[f32; 3]; 6] = [
+            [100.0, 100.0, 100.0],
+            [100.5, 101.0, 100.2],
+            [99.5, 99.0, 112.0], // Car 2 gets caught in traffic
+            [100.0, 100.0, 100.1],
+            [100.5, 102.0, 100.0],
+            [99.5, 98.0, 100.2],
+        ];
+        for (lap, times) in laps.iter().enumerate() {
+            sample.session_time = (lap + 1) as f64 * 100.0;
+            sample.laps_completed = vec![lap as i32 + 1; 3];
+            sample.last_lap_time = times.to_vec();
+            tracker.update(&sample);
+
+            // Lap times are only counted once
+            tracker.update(&sample);
+        }
+
+        let steady = tracker.consistency(0).unwrap();
+        assert_eq!((steady.laps, steady.rejected), (5, 0));
+        assert!((steady.mean - 100.0).abs() < 1e-4);
+
+        let traffic = tracker.consistency(2).unwrap();
+        assert_eq!((traffic.laps, traffic.rejected), (4, 1));
+
+        let ranked = tracker.most_consistent(4);
+        assert_eq!(ranked[0].car_idx, 2);
+        assert_eq!(ranked[2].car_idx, 1);
+
+        let content = std::fs::read_to_string("./session.yaml").unwrap();
+        let session: SessionDetails = serde_yaml::from_str(&content).unwrap();
+        let mut results = Results::from_session(&session, 2).unwrap();
+        tracker.apply(&mut results);
+        assert_eq!(
+            results.for_car(1).and_then(|s| s.consistency),
+            tracker.consistency(1).map(|c| c.stddev)
+        );
+        assert!(results.for_car(1).unwrap().consistency.unwrap() > 1.0);
+    }
+
+    #[test]
+    fn late_lap_times() {
+        let mut tracker = ConsistencyTracker::new();
+        let mut update = |session_time: f64, completed: i32, time: f32| {
+            tracker.update(&ConsistencySample {
+                session_time,
+                laps_completed: vec![completed],
+                last_lap_time: vec![time],
+            });
+        };
+
+        // Each lap's time is updated a tick after the lap completes
+        update(0.0, 0, -1.0);
+        update(100.0, 1, -1.0);
+        update(100.02, 1, 100.0);
+        update(201.0, 2, 100.0);
+        update(201.02, 2, 101.0);
+
+        // Timed the same as the lap before, so only booked after the delay
+        update(302.0, 3, 101.0);
+        update(303.5, 3, 101.0);
+
+        let c = tracker.consistency(0).unwrap();
+        assert_eq!(c.laps, 3);
+        assert!((c.mean - 100.6667).abs() < 1e-3);
+    }
+}
@@ -0,0 +1,510 @@
+//!
+//! Client for the iRacing members `/data` web API, enabled by the `http` feature.
+//!
+//! Official results, series, teams and car/track metadata can be fetched and
+//! matched against the session info, e.g. with the sub-session ID from
+//! `WeekendInfo` or the car ID of a `Driver`.
+
+use crate::results::{Gap, Results, Standing};
+use crate::session::{Driver, SessionDetails, WeekendInfo};
+use base64::engine::general_purpose::STANDARD;
+use base64::Engine;
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use sha2::{Digest, Sha256};
+use std::error::Error;
+use std::fmt;
+
+/// Base URL of the members site.
+pub const BASE_URL: &str = "https://members-ng.iracing.com";
+
+/// Times in the API are in ten-thousandths of a second.
+const TIME_SCALE: f32 = 10_000.0;
+
+///
+/// Data Client
+///
+/// An authenticated session with the `/data` API. Responses are links to
+/// cached JSON documents, which are followed automatically.
+///
+/// # Examples
+///
+/// ```no_run
+/// use iracing::data::DataClient;
+/// # let session: iracing::session::SessionDetails =
+/// #     serde_yaml::from_str(&std::fs::read_to_string("./session.yaml").unwrap()).unwrap();
+///
+/// let client = DataClient::login("driver@example.com", "password").unwrap();
+///
+/// let official = client.session_results(&session).unwrap();
+/// if let Some(race) = official.session("RACE") {
+///     let results = race.to_results(&session);
+///     print!("{}", iracing::results::Results::to_csv(&results.standings));
+/// }
+///
+/// let cars = client.cars().unwrap();
+/// for driver in session.drivers.other_drivers.iter() {
+///     if let Some(car) = cars.iter().find(|c| c.matches(driver)) {
+///         println!("{}: {} hp", driver.user_name, car.hp);
+///     }
+/// }
+/// ```
+pub struct DataClient {
+    agent: ureq::Agent,
+    base_url: String,
+}
+
+///
+/// Errors from the `/data` API.
+#[derive(Debug)]
+pub enum DataError {
+    Http(Box<ureq::Error>),
+    Io(std::io::Error),
+    Authentication(String),
+}
+
+#[derive(Debug, Deserialize)]
+struct Link {
+    link: String,
+}
+
+///
+/// Official results of a sub-session.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SubsessionResult {
+    pub subsession_id: i64,
+    pub session_id: i64,
+
+    #[serde(default)]
+    pub series_id: i64,
+
+    #[serde(default)]
+    pub series_name: String,
+
+    #[serde(default)]
+    pub start_time: String, // ISO 8601
+
+    pub track: SubsessionTrack,
+    pub session_results: Vec<SimSessionResult>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SubsessionTrack {
+    pub track_id: u32,
+    pub track_name: String,
+
+    #[serde(default)]
+    pub config_name: Option<String>,
+}
+
+///
+/// Results of one session (practice, qualifying, race) of a sub-session.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SimSessionResult {
+    pub simsession_number: i32, // 0 for the race, negative for earlier sessions
+    pub simsession_type_name: String, // e.g. "Race", "Lone Qualifying"
+
+    #[serde(default)]
+    pub simsession_name: String, // e.g. "RACE", "QUALIFY"
+
+    pub results: Vec<DriverResult>,
+}
+
+///
+/// A driver's (or team's) official result.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DriverResult {
+    #[serde(default)]
+    pub cust_id: i64,
+
+    #[serde(default)]
+    pub team_id: i64,
+
+    pub display_name: String,
+    pub finish_position: i32,          // 0-based
+    pub finish_position_in_class: i32, // 0-based
+    pub laps_complete: i32,
+    pub laps_lead: i32,
+    pub incidents: i32,
+    pub interval: i64,      // Gap to the leader (1/10000 s), -1 when lapped
+    pub best_lap_time: i64, // 1/10000 s, -1 if none
+    pub best_lap_num: i32,
+
+    #[serde(default)]
+    pub car_id: u64,
+
+    #[serde(default)]
+    pub car_class_short_name: String,
+
+    #[serde(default)]
+    pub reason_out: String,
+
+    #[serde(default)]
+    pub oldi_rating: i32,
+
+    #[serde(default)]
+    pub newi_rating: i32,
+
+    #[serde(default)]
+    pub champ_points: i32,
+}
+
+///
+/// A series from the series list.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Series {
+    pub series_id: i64,
+    pub series_name: String,
+
+    #[serde(default)]
+    pub series_short_name: String,
+
+    #[serde(default)]
+    pub category: String,
+}
+
+///
+/// A team and its members.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Team {
+    pub team_id: i64,
+    pub team_name: String,
+
+    #[serde(default)]
+    pub owner_id: i64,
+
+    #[serde(default)]
+    pub roster: Vec<TeamMember>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TeamMember {
+    pub cust_id: i64,
+    pub display_name: String,
+
+    #[serde(default)]
+    pub owner: bool,
+
+    #[serde(default)]
+    pub admin: bool,
+}
+
+///
+/// Car metadata.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CarInfo {
+    pub car_id: u64,
+    pub car_name: String,
+
+    #[serde(default)]
+    pub car_name_abbreviated: String,
+
+    #[serde(default)]
+    pub car_make: Option<String>,
+
+    #[serde(default)]
+    pub car_model: Option<String>,
+
+    #[serde(default)]
+    pub hp: i32,
+
+    #[serde(default)]
+    pub car_weight: i32, // lb
+}
+
+///
+/// Track metadata.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TrackInfo {
+    pub track_id: u32,
+    pub track_name: String,
+
+    #[serde(default)]
+    pub config_name: Option<String>,
+
+    #[serde(default)]
+    pub track_config_length: f32, // Miles
+
+    #[serde(default)]
+    pub corners_per_lap: i32,
+
+    #[serde(default)]
+    pub location: String,
+
+    #[serde(default)]
+    pub category: String,
+}
+
+///
+/// Password as sent to the auth endpoint: the base64 SHA-256 digest of the
+/// password followed by the lower-cased email address.
+pub fn encode_password(email: &str, password: &str) -> String {
+    let digest = Sha256::digest(format!("{}{}", password, email.to_lowercase()));
+    STANDARD.encode(digest)
+}
+
+impl DataClient {
+    ///
+    /// Log in to the members site.
+    pub fn login(email: &str, password: &str) -> Result<Self, DataError> {
+        Self::login_at(BASE_URL, email, password)
+    }
+
+    ///
+    /// Log in to a members site at `base_url`, e.g. a test server.
+    pub fn login_at(base_url: &str, email: &str, password: &str) -> Result<Self, DataError> {
+        let agent = ureq::AgentBuilder::new().build();
+
+        let response: serde_json::Value = agent
+            .post(&format!("{}/auth", base_url))
+            .send_json(json!({
+                "email": email,
+                "password": encode_password(email, password),
+            }))?
+            .into_json()?;
+
+        // The auth code is a token on success and 0 on failure
+        let authcode = &response["authcode"];
+        if authcode.is_null() || authcode == 0 {
+            let message = response["message"].as_str().unwrap_or("Login failed");
+            return Err(DataError::Authentication(message.to_owned()));
+        }
+
+        Ok(DataClient {
+            agent,
+            base_url: base_url.to_owned(),
+        })
+    }
+
+    ///
+    /// Fetch any `/data` endpoint, e.g. `get("/data/member/info", &[])`.
+    pub fn get<T: DeserializeOwned>(
+        &self,
+        endpoint: &str,
+        query: &[(&str, &str)],
+    ) -> Result<T, DataError> {
+        let mut request = self.agent.get(&format!("{}{}", self.base_url, endpoint));
+        for (key, value) in query {
+            request = request.query(key, value);
+        }
+
+        let link: Link = request.call()?.into_json()?;
+        Ok(self.agent.get(&link.link).call()?.into_json()?)
+    }
+
+    /// Official results of a sub-session.
+    pub fn subsession(&self, subsession_id: i64) -> Result<SubsessionResult, DataError> {
+        let id = subsession_id.to_string();
+        self.get("/data/results/get", &[("subsession_id", &id)])
+    }
+
+    /// Official results of the session described by the session info.
+    pub fn session_results(&self, details: &SessionDetails) -> Result<SubsessionResult, DataError> {
+        self.subsession(details.weekend.sub_session_id.into())
+    }
+
+    /// All series.
+    pub fn series(&self) -> Result<Vec<Series>, DataError> {
+        self.get("/data/series/get", &[])
+    }
+
+    /// A team and its roster.
+    pub fn team(&self, team_id: i64) -> Result<Team, DataError> {
+        let id = team_id.to_string();
+        self.get("/data/team/get", &[("team_id", &id)])
+    }
+
+    /// All cars.
+    pub fn cars(&self) -> Result<Vec<CarInfo>, DataError> {
+        self.get("/data/car/get", &[])
+    }
+
+    /// All tracks and their configurations.
+    pub fn tracks(&self) -> Result<Vec<TrackInfo>, DataError> {
+        self.get("/data/track/get", &[])
+    }
+}
+
+impl SubsessionResult {
+    ///
+    /// A session by its name, e.g. "RACE" or "QUALIFY".
+    pub fn session(&self, name: &str) -> Option<&SimSessionResult> {
+        self.session_results
+            .iter()
+            .find(|s| s.simsession_name.eq_ignore_ascii_case(name))
+    }
+}
+
+impl SimSessionResult {
+    ///
+    /// Convert to typed results, matching drivers (or teams, in team events)
+    /// to cars in the session info. Drivers missing from the session info are
+    /// left out.
+    pub fn to_results(&self, details: &SessionDetails) -> Results {
+        let race = self.simsession_type_name.contains("Race");
+
+        let mut standings: Vec<(Standing, Option<f32>)> = self
+            .results
+            .iter()
+            .filter_map(|r| {
+                let driver = find_driver(details, r)?;
+                let mut standing = Standing::new(
+                    r.finish_position.max(0) as u32 + 1,
+                    r.finish_position_in_class.max(0) as u32 + 1,
+                    driver.index,
+                    Some(driver),
+                );
+
+                standing.laps_complete = r.laps_complete;
+                standing.laps_led = r.laps_lead;
+                standing.fastest_lap = Some(r.best_lap_num).filter(|l| *l > 0);
+                standing.fastest_time = scaled_time(r.best_lap_time);
+                standing.incidents = r.incidents;
+                standing.reason_out = r.reason_out.clone();
+
+                let behind = match r.interval {
+                    i if i >= 0 => Some(i as f32 / TIME_SCALE),
+                    _ => None,
+                };
+                Some((standing, behind))
+            })
+            .collect();
+
+        standings.sort_by_key(|(s, _)| s.position);
+
+        // Outside races, cars are compared on their best laps
+        let summary: Vec<(i32, Option<f32>)> = standings
+            .iter()
+            .map(|(s, behind)| match race {
+                true => (s.laps_complete, *behind),
+                false => (s.laps_complete, s.fastest_time),
+            })
+            .collect();
+
+        for (i, (standing, _)) in standings.iter_mut().enumerate().skip(1) {
+            standing.gap_to_leader = Gap::between(summary[0], summary[i], race);
+            standing.interval = Gap::between(summary[i - 1], summary[i], race);
+        }
+
+        Results {
+            session_number: details
+                .session
+                .sessions
+                .iter()
+                .find(|s| s.session_type.eq_ignore_ascii_case(&self.simsession_name))
+                .map(|s| s.session_number)
+                .unwrap_or_default(),
+            session_type: self.simsession_type_name.clone(),
+            official: true,
+            standings: standings.into_iter().map(|(s, _)| s).collect(),
+        }
+    }
+}
+
+fn find_driver<'a>(details: &'a SessionDetails, result: &DriverResult) -> Option<&'a Driver> {
+    details.drivers.other_drivers.iter().find(|d| {
+        (result.cust_id > 0 && d.user_id == result.cust_id)
+            || (result.cust_id == 0 && result.team_id > 0 && d.team_id == result.team_id as u64)
+    })
+}
+
+fn scaled_time(t: i64) -> Option<f32> {
+    if t > 0 {
+        Some(t as f32 / TIME_SCALE)
+    } else {
+        None
+    }
+}
+
+impl CarInfo {
+    /// True if this is the driver's car.
+    pub fn matches(&self, driver: &Driver) -> bool {
+        self.car_id == driver.car_id
+    }
+}
+
+impl TrackInfo {
+    /// True if this is the weekend's track. Each configuration has its own ID.
+    pub fn matches(&self, weekend: &WeekendInfo) -> bool {
+        self.track_id == weekend.track_id
+    }
+}
+
+impl fmt::Display for DataError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            DataError::Http(e) => write!(f, "iRacing data API error: {}", e),
+            DataError::Io(e) => write!(f, "Invalid iRacing data API response: {}", e),
+            DataError::Authentication(message) => write!(f, "iRacing login failed: {}", message),
+        }
+    }
+}
+
+impl Error for DataError {}
+
+impl From<ureq::Error> for DataError {
+    fn from(e: ureq::Error) -> Self {
+        DataError::Http(Box::new(e))
+    }
+}
+
+impl From<std::io::Error> for DataError {
+    fn from(e: std::io::Error) -> Self {
+        DataError::Io(e)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SUBSESSION: &str = r#"{
+        "subsession_id": 31470051,
+        "session_id": 1,
+        "track": { "track_id": 266, "track_name": "Test Track" },
+        "session_results": [{
+            "simsession_number": 0,
+            "simsession_type_name": "Race",
+            "simsession_name": "RACE",
+            "results": [
+                { "cust_id": 412377, "display_name": "Mika Virtanen", "finish_position": 1,
+                  "finish_position_in_class": 1, "laps_complete": 3, "laps_lead": 0, "incidents": 0,
+                  "interval": 25602, "best_lap_time": 1016629, "best_lap_num": 2 },
+                { "cust_id": 81797, "display_name": "L W Adamek", "finish_position": 0,
+                  "finish_position_in_class": 0, "laps_complete": 3, "laps_lead": 3, "incidents": 1,
+                  "interval": 0, "best_lap_time": 1016629, "best_lap_num": 2, "reason_out": "Running" },
+                { "cust_id": 501223, "display_name": "Jordan Blake", "finish_position": 2,
+                  "finish_position_in_class": 2, "laps_complete": 2, "laps_lead": 0, "incidents": 4,
+                  "interval": -1, "best_lap_time": -1, "best_lap_num": -1 },
+                { "cust_id": 1, "display_name": "Not In Session", "finish_position": 3,
+                  "finish_position_in_class": 3, "laps_complete": 1, "laps_lead": 0, "incidents": 0,
+                  "interval": -1, "best_lap_time": -1, "best_lap_num": -1 }
+            ]
+        }]
+    }"#;
+
+    #[test]
+    fn official_results() {
+        assert_eq!(
+            encode_password("CLunky@iRacing.Com", "MyPassWord"),
+            "xGKecAR27ALXNuMLsGaG0v5Q9pSs2tZTZRKNgmHMg+Q="
+        );
+
+        let content = std::fs::read_to_string("./session.yaml").unwrap();
+        let session: SessionDetails = serde_yaml::from_str(&content).unwrap();
+
+        let official: SubsessionResult = serde_json::from_str(SUBSESSION).unwrap();
+        let results = official.session("race").unwrap().to_results(&session);
+
+        assert!(results.official);
+        assert_eq!(results.standings.len(), 3);
+        assert_eq!(results.standings[0].car_idx, 1);
+        assert_eq!(results.standings[1].driver_name, "Mika Virtanen");
+        assert!(
+            matches!(results.standings[1].gap_to_leader, Gap::Time(t) if (t - 2.5602).abs() < 1e-3)
+        );
+        assert_eq!(results.standings[2].gap_to_leader, Gap::Laps(1));
+        assert_eq!(results.standings[2].fastest_time, None);
+    }
+}
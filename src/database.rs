@@ -0,0 +1,467 @@
+//!
+//! SQLite persistence for race data, enabled by the `sqlite` feature.
+//!
+//! # Schema
+//!
+//! | Table        | Contents                                                                    |
+//! |--------------|-----------------------------------------------------------------------------|
+//! | `sessions`   | One row per session: key, sub-session and unique IDs, number, type and track |
+//! | `laps`       | Lap times per car: car index, lap number, lap time (s), session time (s)     |
+//! | `stints`     | Driver stints per car: driver, start/end lap and session time                |
+//! | `pit_stops`  | Trips through pit lane: entry/exit time, stationary time, speeding, time lost|
+//! | `incidents`  | Incident points per car: driver, points, running total, lap, session time    |
+//! | `results`    | Final standings: positions, driver, team, laps, times, incidents, status     |
+//!
+//! Every table except `sessions` has a `session_id` column referencing `sessions.id`.
+//! Sessions are identified by the name of their [`SessionKey`], so data from
+//! reconnecting to a session is added to it. The full definition is in [`SCHEMA`].
+
+use crate::boundary::{BoundarySample, SessionKey};
+use crate::incidents::Incident;
+use crate::pits::PitStop;
+use crate::results::Results;
+use crate::session::SessionDetails;
+use crate::team::Stint;
+use rusqlite::{params, Connection, Result};
+use std::path::Path;
+
+/// SQL used to create the database tables.
+pub const SCHEMA: &str = "
+CREATE TABLE IF NOT EXISTS sessions (
+    id                INTEGER PRIMARY KEY,
+    session_key       TEXT NOT NULL UNIQUE,
+    sub_session_id    INTEGER NOT NULL,
+    session_unique_id INTEGER NOT NULL DEFAULT 0,
+    session_number    INTEGER NOT NULL,
+    session_type      TEXT NOT NULL,
+    track             TEXT NOT NULL
+);
+
+CREATE TABLE IF NOT EXISTS laps (
+    session_id   INTEGER NOT NULL REFERENCES sessions(id),
+    car_idx      INTEGER NOT NULL,
+    lap          INTEGER NOT NULL,
+    lap_time     REAL,
+    session_time REAL NOT NULL,
+    PRIMARY KEY (session_id, car_idx, lap)
+);
+
+CREATE TABLE IF NOT EXISTS stints (
+    session_id INTEGER NOT NULL REFERENCES sessions(id),
+    car_idx    INTEGER NOT NULL,
+    team_id    INTEGER NOT NULL,
+    team_name  TEXT NOT NULL,
+    user_id    INTEGER NOT NULL,
+    user_name  TEXT NOT NULL,
+    start_lap  INTEGER NOT NULL,
+    start_time REAL NOT NULL,
+    end_lap    INTEGER,
+    end_time   REAL
+);
+
+CREATE TABLE IF NOT EXISTS pit_stops (
+    session_id INTEGER NOT NULL REFERENCES sessions(id),
+    car_idx    INTEGER NOT NULL,
+    lap        INTEGER NOT NULL,
+    entry_time REAL NOT NULL,
+    exit_time  REAL NOT NULL,
+    stationary REAL NOT NULL,
+    max_speed  REAL NOT NULL,
+    speeding   REAL NOT NULL,
+    time_lost  REAL
+);
+
+CREATE TABLE IF NOT EXISTS incidents (
+    session_id   INTEGER NOT NULL REFERENCES sessions(id),
+    car_idx      INTEGER NOT NULL,
+    user_id      INTEGER,
+    points       INTEGER NOT NULL,
+    total        INTEGER NOT NULL,
+    lap          INTEGER,
+    session_time REAL NOT NULL
+);
+
+CREATE TABLE IF NOT EXISTS results (
+    session_id     INTEGER NOT NULL REFERENCES sessions(id),
+    position       INTEGER NOT NULL,
+    class_position INTEGER NOT NULL,
+    car_idx        INTEGER NOT NULL,
+    car_number     TEXT NOT NULL,
+    car_class      TEXT NOT NULL,
+    user_id        INTEGER NOT NULL,
+    driver_name    TEXT NOT NULL,
+    team_name      TEXT NOT NULL,
+    laps_complete  INTEGER NOT NULL,
+    laps_led       INTEGER NOT NULL,
+    time           REAL,
+    fastest_time   REAL,
+    incidents      INTEGER NOT NULL,
+    reason_out     TEXT NOT NULL,
+    PRIMARY KEY (session_id, car_idx)
+);
+";
+
+///
+/// Database
+///
+/// A queryable race database of laps, stints, pit stops, incidents and results.
+///
+/// # Examples
+///
+/// ```no_run
+/// use iracing::database::Database;
+/// # fn main() -> rusqlite::Result<()> {
+/// # let session: iracing::session::SessionDetails =
+/// #     serde_yaml::from_str(&std::fs::read_to_string("./session.yaml").unwrap()).unwrap();
+///
+/// let db = Database::open("races.db")?;
+/// let race = db.add_session(&session, 2, 41)?;
+///
+/// db.add_lap(race, 1, 5, Some(101.52), 512.3)?;
+/// # Ok(())
+/// # }
+/// ```
+pub struct Database {
+    connection: Connection,
+}
+
+impl Database {
+    ///
+    /// Open (or create) a database file.
+    pub fn open<P: AsRef<Path>>(path: P) -> Result<Self> {
+        Self::with_connection(Connection::open(path)?)
+    }
+
+    /// Create a database in memory.
+    pub fn in_memory() -> Result<Self> {
+        Self::with_connection(Connection::open_in_memory()?)
+    }
+
+    fn with_connection(mut connection: Connection) -> Result<Self> {
+        connection.execute_batch(SCHEMA)?;
+        migrate(&mut connection)?;
+        Ok(Database { connection })
+    }
+
+    /// The underlying connection, for queries.
+    pub fn connection(&self) -> &Connection {
+        &self.connection
+    }
+
+    ///
+    /// Add a session by its number and `SessionUniqueID`, returning its ID.
+    /// Adding the same session again returns the existing ID.
+    ///
+    /// Offline sessions all have the same sub-session ID, so they are kept
+    /// apart by their unique ID.
+    pub fn add_session(
+        &self,
+        details: &SessionDetails,
+        session_number: u64,
+        session_unique_id: i32,
+    ) -> Result<i64> {
+        let sample = BoundarySample {
+            session_num: session_number as i32,
+            session_unique_id,
+        };
+        self.add_session_key(details, &details.key(&sample))
+    }
+
+    ///
+    /// Add a session by its key, returning its ID. Adding the same session
+    /// again, such as after reconnecting, returns the existing ID.
+    pub fn add_session_key(&self, details: &SessionDetails, key: &SessionKey) -> Result<i64> {
+        let session_type = details
+            .session
+            .sessions
+            .iter()
+            .find(|s| s.session_number as i64 == key.session_num as i64)
+            .map(|s| s.session_type.clone())
+            .unwrap_or_default();
+
+        self.connection.execute(
+            "INSERT OR IGNORE INTO sessions (session_key, sub_session_id, session_unique_id,
+                                             session_number, session_type, track)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
+            params![
+                key.name(),
+                key.sub_session_id,
+                key.session_unique_id,
+                key.session_num,
+                session_type,
+                details.weekend.track_display_name,
+            ],
+        )?;
+
+        self.session_id(key)
+    }
+
+    /// ID of a session already added.
+    pub fn session_id(&self, key: &SessionKey) -> Result<i64> {
+        self.connection.query_row(
+            "SELECT id FROM sessions WHERE session_key = ?1",
+            params![key.name()],
+            |row| row.get(0),
+        )
+    }
+
+    /// Add (or replace) a lap time.
+    pub fn add_lap(
+        &self,
+        session: i64,
+        car_idx: usize,
+        lap: i32,
+        lap_time: Option<f32>,
+        session_time: f64,
+    ) -> Result<()> {
+        self.connection.execute(
+            "INSERT OR REPLACE INTO laps (session_id, car_idx, lap, lap_time, session_time)
+             VALUES (?1, ?2, ?3, ?4, ?5)",
+            params![session, car_idx as i64, lap, lap_time, session_time],
+        )?;
+        Ok(())
+    }
+
+    pub fn add_stint(&self, session: i64, stint: &Stint) -> Result<()> {
+        self.connection.execute(
+            "INSERT INTO stints (session_id, car_idx, team_id, team_name, user_id, user_name,
+                                 start_lap, start_time, end_lap, end_time)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10)",
+            params![
+                session,
+                stint.car_idx as i64,
+                stint.team_id as i64,
+                stint.team_name,
+                stint.user_id,
+                stint.user_name,
+                stint.start_lap,
+                stint.start_time,
+                stint.end_lap,
+                stint.end_time,
+            ],
+        )?;
+        Ok(())
+    }
+
+    pub fn add_pit_stop(&self, session: i64, car_idx: usize, stop: &PitStop) -> Result<()> {
+        self.connection.execute(
+            "INSERT INTO pit_stops (session_id, car_idx, lap, entry_time, exit_time, stationary,
+                                    max_speed, speeding, time_lost)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9)",
+            params![
+                session,
+                car_idx as i64,
+                stop.lap,
+                stop.entry_time,
+                stop.exit_time,
+                stop.stationary,
+                stop.max_speed,
+                stop.speeding,
+                stop.time_lost,
+            ],
+        )?;
+        Ok(())
+    }
+
+    pub fn add_incident(&self, session: i64, incident: &Incident) -> Result<()> {
+        self.connection.execute(
+            "INSERT INTO incidents (session_id, car_idx, user_id, points, total, lap, session_time)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)",
+            params![
+                session,
+                incident.car_idx as i64,
+                incident.user_id,
+                incident.points,
+                incident.total,
+                incident.lap,
+                incident.session_time,
+            ],
+        )?;
+        Ok(())
+    }
+
+    ///
+    /// Add (or replace) the standings of a session, in a single transaction.
+    pub fn add_results(&mut self, session: i64, results: &Results) -> Result<()> {
+        let tx = self.connection.transaction()?;
+
+        for s in &results.standings {
+            tx.execute(
+                "INSERT OR REPLACE INTO results (session_id, position, class_position, car_idx,
+                     car_number, car_class, user_id, driver_name, team_name, laps_complete,
+                     laps_led, time, fastest_time, incidents, reason_out)
+                 VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13, ?14, ?15)",
+                params![
+                    session,
+                    s.position,
+                    s.class_position,
+                    s.car_idx as i64,
+                    s.car_number,
+                    s.car_class,
+                    s.user_id,
+                    s.driver_name,
+                    s.team_name,
+                    s.laps_complete,
+                    s.laps_led,
+                    s.time,
+                    s.fastest_time,
+                    s.incidents,
+                    s.reason_out,
+                ],
+            )?;
+        }
+
+        tx.commit()
+    }
+}
+
+///
+/// Bring a database created before sessions were keyed up to date. The
+/// sessions table is rebuilt without its old unique constraint, and its rows
+/// keyed as `SessionKey` names them. Their unique IDs weren't stored, so are 0.
+fn migrate(connection: &mut Connection) -> Result<()> {
+    let keyed: bool = connection.query_row(
+        "SELECT COUNT(*) > 0 FROM pragma_table_info('sessions') WHERE name = 'session_key'",
+        [],
+        |row| row.get(0),
+    )?;
+    if keyed {
+        return Ok(());
+    }
+
+    let tx = connection.transaction()?;
+    tx.execute_batch(
+        "CREATE TABLE sessions_new (
+             id                INTEGER PRIMARY KEY,
+             session_key       TEXT NOT NULL UNIQUE,
+             sub_session_id    INTEGER NOT NULL,
+             session_unique_id INTEGER NOT NULL DEFAULT 0,
+             session_number    INTEGER NOT NULL,
+             session_type      TEXT NOT NULL,
+             track             TEXT NOT NULL
+         );",
+    )?;
+
+    let rows = {
+        let mut statement = tx.prepare(
+            "SELECT id, sub_session_id, session_number, session_type, track FROM sessions",
+        )?;
+        let rows = statement.query_map([], |row| {
+            Ok((
+                row.get::<_, i64>(0)?,
+                row.get::<_, i32>(1)?,
+                row.get::<_, i32>(2)?,
+                row.get::<_, String>(3)?,
+                row.get::<_, String>(4)?,
+            ))
+        })?;
+        rows.collect::<Result<Vec<_>>>()?
+    };
+
+    for (id, sub_session_id, session_num, session_type, track) in rows {
+        let key = SessionKey {
+            sub_session_id,
+            session_unique_id: 0,
+            session_num,
+        };
+        tx.execute(
+            "INSERT INTO sessions_new (id, session_key, sub_session_id, session_unique_id,
+                                   session_number, session_type, track)
+             VALUES (?1, ?2, ?3, 0, ?4, ?5, ?6)",
+            params![
+                id,
+                key.name(),
+                sub_session_id,
+                session_num,
+                session_type,
+                track
+            ],
+        )?;
+    }
+
+    tx.execute_batch(
+        "DROP TABLE sessions;
+         ALTER TABLE sessions_new RENAME TO sessions;",
+    )?;
+    tx.commit()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn store_race() {
+        let content = std::fs::read_to_string("./session.yaml").unwrap();
+        let session: SessionDetails = serde_yaml::from_str(&content).unwrap();
+
+        let mut db = Database::in_memory().unwrap();
+        let race = db.add_session(&session, 2, 77).unwrap();
+        assert_eq!(db.add_session(&session, 2, 77).unwrap(), race);
+
+        // Reconnecting to the race finds it again
+        let sample = BoundarySample {
+            session_num: 2,
+            session_unique_id: 77,
+        };
+        assert_eq!(
+            db.add_session_key(&session, &session.key(&sample)).unwrap(),
+            race
+        );
+
+        db.add_lap(race, 1, 1, Some(101.5), 101.5).unwrap();
+        db.add_lap(race, 1, 1, Some(101.4), 101.4).unwrap();
+        db.add_results(race, &Results::from_session(&session, 2).unwrap())
+            .unwrap();
+
+        let laps: i64 = db
+            .connection()
+            .query_row("SELECT COUNT(*) FROM laps", [], |r| r.get(0))
+            .unwrap();
+        assert_eq!(laps, 1);
+
+        let winner: String = db
+            .connection()
+            .query_row(
+                "SELECT driver_name FROM results WHERE session_id = ?1 AND position = 1",
+                [race],
+                |r| r.get(0),
+            )
+            .unwrap();
+        assert_eq!(winner, "Ana Lucia Ferreira");
+    }
+
+    #[test]
+    fn migrate_sessions() {
+        let connection = Connection::open_in_memory().unwrap();
+        connection
+            .execute_batch(
+                "CREATE TABLE sessions (
+                     id             INTEGER PRIMARY KEY,
+                     sub_session_id INTEGER NOT NULL,
+                     session_number INTEGER NOT NULL,
+                     session_type   TEXT NOT NULL,
+                     track          TEXT NOT NULL,
+                     UNIQUE (sub_session_id, session_number)
+                 );
+                 INSERT INTO sessions VALUES (4, 31470051, 2, 'Race', 'Imola');",
+            )
+            .unwrap();
+
+        let content = std::fs::read_to_string("./session.yaml").unwrap();
+        let session: SessionDetails = serde_yaml::from_str(&content).unwrap();
+        let db = Database::with_connection(connection).unwrap();
+
+        // Legacy sessions are keyed as hosted sessions are, whatever their
+        // unique ID
+        assert_eq!(db.add_session(&session, 2, 12).unwrap(), 4);
+
+        // Offline sessions are kept apart once the old constraint is gone
+        let mut offline = session.clone();
+        offline.weekend.sub_session_id = 0;
+        let practice = db.add_session(&offline, 2, 1).unwrap();
+        let race = db.add_session(&offline, 2, 2).unwrap();
+        assert_ne!(practice, race);
+        assert_eq!(db.add_session(&offline, 2, 1).unwrap(), practice);
+    }
+}
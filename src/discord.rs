@@ -0,0 +1,586 @@
+//!
+//! Discord publishing, enabled by the `discord` feature.
+//!
+//! Session summaries, lap milestones and results are posted to a channel
+//! through a webhook, and the local Discord client's Rich Presence can show
+//! the current track, car and position.
+
+use crate::results::{Gap, Results};
+use crate::session::SessionDetails;
+use serde::Serialize;
+use serde_json::json;
+use std::collections::HashMap;
+use std::error::Error;
+use std::fmt;
+use std::io::{self, Read, Write};
+#[cfg(target_os = "windows")]
+use std::os::windows::io::AsRawHandle;
+use std::time::Duration;
+#[cfg(target_os = "windows")]
+use std::{fs::File, ptr, thread, time::Instant};
+#[cfg(target_os = "windows")]
+use winapi::um::namedpipeapi::PeekNamedPipe;
+
+/// Embed colour used for messages (iRacing blue).
+pub const EMBED_COLOR: u32 = 0x1e_4b_9b;
+
+const OP_HANDSHAKE: u32 = 0;
+const OP_FRAME: u32 = 1;
+const IPC_PIPES: u32 = 10;
+
+/// Largest IPC frame payload the client sends (bytes)
+const MAX_FRAME: u32 = 64 * 1024;
+
+/// Longest wait for the client to reply
+const READ_TIMEOUT: Duration = Duration::from_secs(5);
+
+///
+/// A message posted through a webhook.
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct WebhookMessage {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub content: Option<String>,
+
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub username: Option<String>, // Overrides the webhook's name
+
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub embeds: Vec<Embed>,
+}
+
+///
+/// A rich block of content in a message.
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct Embed {
+    pub title: String,
+
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+
+    pub color: u32,
+
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub fields: Vec<EmbedField>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct EmbedField {
+    pub name: String,
+    pub value: String,
+    pub inline: bool,
+}
+
+///
+/// Webhook
+///
+/// Posts messages to a Discord channel webhook.
+///
+/// # Examples
+///
+/// ```no_run
+/// use iracing::discord::{Webhook, WebhookMessage};
+/// use iracing::results::Results;
+/// # let session: iracing::session::SessionDetails =
+/// #     serde_yaml::from_str(&std::fs::read_to_string("./session.yaml").unwrap()).unwrap();
+///
+/// let webhook = Webhook::new("https://discord.com/api/webhooks/...").with_username("Race Control");
+///
+/// webhook.post(&WebhookMessage::session_summary(&session)).unwrap();
+///
+/// if let Some(results) = Results::from_session(&session, 2) {
+///     webhook.post(&WebhookMessage::results(&results, 10)).unwrap();
+/// }
+/// ```
+#[derive(Debug, Clone)]
+pub struct Webhook {
+    url: String,
+    username: Option<String>,
+}
+
+///
+/// Lap Milestones
+///
+/// Reports when cars complete every `n`th lap, e.g. each 100 laps of an
+/// endurance race. The first laps seen for a car only set its baseline, so
+/// joining mid-race doesn't report old milestones.
+///
+/// # Examples
+///
+/// ```
+/// use iracing::discord::LapMilestones;
+///
+/// let mut milestones = LapMilestones::every(50);
+/// milestones.update(&[49, 12]);
+///
+/// assert_eq!(milestones.update(&[50, 13]), vec![(0, 50)]);
+/// ```
+#[derive(Debug, Clone)]
+pub struct LapMilestones {
+    every: i32,
+    laps: HashMap<usize, i32>,
+}
+
+///
+/// What Rich Presence shows on the user's profile.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Presence {
+    pub details: String,             // First line, e.g. car and track
+    pub state: String,               // Second line, e.g. session and position
+    pub start: Option<i64>,          // Unix time the activity started, shown as elapsed time
+    pub large_image: Option<String>, // Art asset key of the application
+}
+
+///
+/// Rich Presence
+///
+/// A connection to the local Discord client's IPC socket, which updates the
+/// user's activity. `client_id` is the ID of a Discord application.
+///
+/// # Examples
+///
+/// ```no_run
+/// use iracing::discord::{Presence, RichPresence};
+/// # let session: iracing::session::SessionDetails =
+/// #     serde_yaml::from_str(&std::fs::read_to_string("./session.yaml").unwrap()).unwrap();
+///
+/// let mut discord = RichPresence::connect("123456789012345678").expect("Discord isn't running");
+/// discord.set(&Presence::racing(&session, Some(3))).unwrap();
+/// ```
+pub struct RichPresence {
+    pipe: Box<dyn Pipe>,
+    nonce: u64,
+}
+
+///
+/// Errors publishing to Discord.
+#[derive(Debug)]
+pub enum DiscordError {
+    Http(Box<ureq::Error>),
+    Io(io::Error),
+    Protocol(String),
+}
+
+trait Pipe: Read + Write {}
+
+impl<T: Read + Write> Pipe for T {}
+
+///
+/// The client's named pipe. Reads fail with `TimedOut` once `READ_TIMEOUT`
+/// passes without a reply, as pipes opened as files can't time out.
+#[cfg(target_os = "windows")]
+struct NamedPipe(File);
+
+impl WebhookMessage {
+    ///
+    /// Summary of the event: track, series and session schedule.
+    pub fn session_summary(details: &SessionDetails) -> Self {
+        let weekend = &details.weekend;
+
+        let mut track = weekend.track_display_name.clone();
+        if !weekend.track_config_name.is_empty() {
+            track = format!("{} - {}", track, weekend.track_config_name);
+        }
+
+        let sessions = details
+            .session
+            .sessions
+            .iter()
+            .map(|s| format!("{} ({})", s.session_type, s.time))
+            .collect::<Vec<String>>()
+            .join("\n");
+
+        let cars = details
+            .drivers
+            .other_drivers
+            .iter()
+            .filter(|d| d.car_is_pace_car.unwrap_or(0) == 0)
+            .count();
+
+        let embed = Embed {
+            title: track,
+            description: Some(format!("{} {}", weekend.category, weekend.event_type)),
+            color: EMBED_COLOR,
+            fields: vec![
+                field("Cars", cars.to_string(), true),
+                field("Weather", weekend.track_skies.clone(), true),
+                field("Sessions", sessions, false),
+            ],
+        };
+
+        WebhookMessage {
+            embeds: vec![embed],
+            ..Default::default()
+        }
+    }
+
+    ///
+    /// A car reaching a lap milestone.
+    pub fn milestone(driver_name: &str, lap: i32) -> Self {
+        WebhookMessage {
+            content: Some(format!("**{}** has completed {} laps", driver_name, lap)),
+            ..Default::default()
+        }
+    }
+
+    ///
+    /// Standings for the top `top` cars of a session.
+    pub fn results(results: &Results, top: usize) -> Self {
+        let mut table = String::from("```\n");
+        for s in results.standings.iter().take(top) {
+            let gap = match s.gap_to_leader {
+                Gap::None => String::new(),
+                Gap::Time(t) => format!("+{:.3}", t),
+                Gap::Laps(1) => String::from("+1 lap"),
+                Gap::Laps(l) => format!("+{} laps", l),
+            };
+
+            table.push_str(&format!(
+                "P{:<3} #{:<4} {:<24} {}\n",
+                s.position, s.car_number, s.driver_name, gap
+            ));
+        }
+        table.push_str("```");
+
+        let title = match results.official {
+            true => format!("{} Results", results.session_type),
+            false => format!("{} Results (Unofficial)", results.session_type),
+        };
+
+        WebhookMessage {
+            embeds: vec![Embed {
+                title,
+                description: Some(table),
+                color: EMBED_COLOR,
+                fields: Vec::new(),
+            }],
+            ..Default::default()
+        }
+    }
+}
+
+fn field(name: &str, value: String, inline: bool) -> EmbedField {
+    EmbedField {
+        name: name.to_owned(),
+        value,
+        inline,
+    }
+}
+
+impl Webhook {
+    pub fn new(url: &str) -> Self {
+        Webhook {
+            url: url.to_owned(),
+            username: None,
+        }
+    }
+
+    /// Post as `username` instead of the webhook's own name.
+    pub fn with_username(mut self, username: &str) -> Self {
+        self.username = Some(username.to_owned());
+        self
+    }
+
+    ///
+    /// Post a message to the channel.
+    pub fn post(&self, message: &WebhookMessage) -> Result<(), DiscordError> {
+        let mut message = message.clone();
+        if message.username.is_none() {
+            message.username = self.username.clone();
+        }
+
+        ureq::post(&self.url).send_json(&message)?;
+        Ok(())
+    }
+}
+
+impl LapMilestones {
+    pub fn every(laps: i32) -> Self {
+        LapMilestones {
+            every: laps.max(1),
+            laps: HashMap::new(),
+        }
+    }
+
+    ///
+    /// Update with laps completed by each car (indexed by car index), returning
+    /// the car index and milestone of each car which reached one.
+    pub fn update(&mut self, laps_completed: &[i32]) -> Vec<(usize, i32)> {
+        let mut reached = Vec::new();
+
+        for (car_idx, &laps) in laps_completed.iter().enumerate() {
+            if laps < 0 {
+                continue;
+            }
+
+            if let Some(last) = self.laps.insert(car_idx, laps) {
+                if laps / self.every > last / self.every {
+                    reached.push((car_idx, laps - laps % self.every));
+                }
+            }
+        }
+
+        reached
+    }
+}
+
+impl Presence {
+    ///
+    /// The player racing in a session, e.g. "Mazda MX-5 Cup" and
+    /// "Lime Rock Park - P3 of 20".
+    pub fn racing(details: &SessionDetails, position: Option<u32>) -> Self {
+        let drivers = &details.drivers;
+        let car = drivers
+            .other_drivers
+            .iter()
+            .find(|d| d.index == drivers.car_index)
+            .map(|d| d.car_screen_name.clone())
+            .unwrap_or_default();
+
+        let field = drivers
+            .other_drivers
+            .iter()
+            .filter(|d| d.car_is_pace_car.unwrap_or(0) == 0)
+            .count();
+
+        let track = details.weekend.track_display_name.clone();
+        let state = match position {
+            Some(p) if p > 0 => format!("{} - P{} of {}", track, p, field),
+            _ => track,
+        };
+
+        Presence {
+            details: car,
+            state,
+            ..Default::default()
+        }
+    }
+
+    fn activity(&self) -> serde_json::Value {
+        let mut activity = json!({
+            "details": self.details,
+            "state": self.state,
+        });
+
+        if let Some(start) = self.start {
+            activity["timestamps"] = json!({ "start": start });
+        }
+        if let Some(image) = &self.large_image {
+            activity["assets"] = json!({ "large_image": image });
+        }
+
+        activity
+    }
+}
+
+impl RichPresence {
+    ///
+    /// Connect to the Discord client and identify as application `client_id`.
+    pub fn connect(client_id: &str) -> Result<Self, DiscordError> {
+        let pipe = (0..IPC_PIPES)
+            .find_map(|n| open_pipe(n).ok())
+            .ok_or_else(|| DiscordError::Protocol(String::from("Discord isn't running")))?;
+
+        let mut presence = RichPresence { pipe, nonce: 0 };
+        presence.write(OP_HANDSHAKE, json!({ "v": 1, "client_id": client_id }))?;
+        presence.read()?;
+
+        Ok(presence)
+    }
+
+    /// Show `presence` on the user's profile.
+    pub fn set(&mut self, presence: &Presence) -> Result<(), DiscordError> {
+        self.set_activity(presence.activity())
+    }
+
+    /// Clear the user's activity.
+    pub fn clear(&mut self) -> Result<(), DiscordError> {
+        self.set_activity(serde_json::Value::Null)
+    }
+
+    fn set_activity(&mut self, activity: serde_json::Value) -> Result<(), DiscordError> {
+        self.nonce += 1;
+        let command = json!({
+            "cmd": "SET_ACTIVITY",
+            "args": { "pid": std::process::id(), "activity": activity },
+            "nonce": self.nonce.to_string(),
+        });
+
+        self.write(OP_FRAME, command)?;
+        let response = self.read()?;
+
+        match response["evt"].as_str() {
+            Some("ERROR") => Err(DiscordError::Protocol(
+                response["data"]["message"]
+                    .as_str()
+                    .unwrap_or("SET_ACTIVITY failed")
+                    .to_owned(),
+            )),
+            _ => Ok(()),
+        }
+    }
+
+    fn write(&mut self, op: u32, payload: serde_json::Value) -> Result<(), DiscordError> {
+        self.pipe.write_all(&frame(op, &payload.to_string()))?;
+        Ok(())
+    }
+
+    fn read(&mut self) -> Result<serde_json::Value, DiscordError> {
+        let mut header = [0u8; 8];
+        self.pipe.read_exact(&mut header)?;
+
+        let len = u32::from_le_bytes([header[4], header[5], header[6], header[7]]);
+        if len > MAX_FRAME {
+            return Err(DiscordError::Protocol(format!(
+                "Frame of {} bytes is too long",
+                len
+            )));
+        }
+
+        let mut payload = vec![0u8; len as usize];
+        self.pipe.read_exact(&mut payload)?;
+
+        serde_json::from_slice(&payload)
+            .map_err(|e| DiscordError::Protocol(format!("Invalid message: {}", e)))
+    }
+}
+
+///
+/// An IPC frame: opcode and payload length (both u32, little-endian) followed
+/// by the JSON payload.
+fn frame(op: u32, payload: &str) -> Vec<u8> {
+    let mut data = Vec::with_capacity(8 + payload.len());
+    data.extend_from_slice(&op.to_le_bytes());
+    data.extend_from_slice(&(payload.len() as u32).to_le_bytes());
+    data.extend_from_slice(payload.as_bytes());
+    data
+}
+
+#[cfg(target_os = "windows")]
+fn open_pipe(n: u32) -> io::Result<Box<dyn Pipe>> {
+    let pipe = std::fs::OpenOptions::new()
+        .read(true)
+        .write(true)
+        .open(format!(r"\\.\pipe\discord-ipc-{}", n))?;
+    Ok(Box::new(NamedPipe(pipe)))
+}
+
+#[cfg(not(target_os = "windows"))]
+fn open_pipe(n: u32) -> io::Result<Box<dyn Pipe>> {
+    let dir = ["XDG_RUNTIME_DIR", "TMPDIR", "TMP", "TEMP"]
+        .iter()
+        .find_map(|v| std::env::var(v).ok())
+        .unwrap_or_else(|| String::from("/tmp"));
+
+    let path = std::path::Path::new(&dir).join(format!("discord-ipc-{}", n));
+    let stream = std::os::unix::net::UnixStream::connect(path)?;
+    stream.set_read_timeout(Some(READ_TIMEOUT))?;
+    Ok(Box::new(stream))
+}
+
+#[cfg(target_os = "windows")]
+impl Read for NamedPipe {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let deadline = Instant::now() + READ_TIMEOUT;
+
+        loop {
+            let mut available = 0;
+            let peeked = unsafe {
+                PeekNamedPipe(
+                    self.0.as_raw_handle() as _,
+                    ptr::null_mut(),
+                    0,
+                    ptr::null_mut(),
+                    &mut available,
+                    ptr::null_mut(),
+                )
+            };
+
+            // A broken pipe is reported by the read itself
+            if peeked == 0 || available > 0 {
+                return self.0.read(buf);
+            }
+            if Instant::now() >= deadline {
+                return Err(io::Error::new(
+                    io::ErrorKind::TimedOut,
+                    "Discord didn't reply in time",
+                ));
+            }
+            thread::sleep(Duration::from_millis(10));
+        }
+    }
+}
+
+#[cfg(target_os = "windows")]
+impl Write for NamedPipe {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.0.write(buf)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.0.flush()
+    }
+}
+
+impl fmt::Display for DiscordError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            DiscordError::Http(e) => write!(f, "Discord webhook error: {}", e),
+            DiscordError::Io(e) => write!(f, "Discord IPC error: {}", e),
+            DiscordError::Protocol(message) => write!(f, "Discord error: {}", message),
+        }
+    }
+}
+
+impl Error for DiscordError {}
+
+impl From<ureq::Error> for DiscordError {
+    fn from(e: ureq::Error) -> Self {
+        DiscordError::Http(Box::new(e))
+    }
+}
+
+impl From<io::Error> for DiscordError {
+    fn from(e: io::Error) -> Self {
+        DiscordError::Io(e)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn messages() {
+        let content = std::fs::read_to_string("./session.yaml").unwrap();
+        let session: SessionDetails = serde_yaml::from_str(&content).unwrap();
+
+        let summary = serde_json::to_value(WebhookMessage::session_summary(&session)).unwrap();
+        assert_eq!(summary["embeds"][0]["fields"][0]["name"], "Cars");
+        assert!(summary.get("content").is_none());
+
+        let results = Results::from_session(&session, 2).unwrap();
+        let message = WebhookMessage::results(&results, 3);
+        let table = message.embeds[0].description.as_ref().unwrap();
+        assert_eq!(table.lines().count(), 5);
+        assert!(table.contains("Ana Lucia Ferreira"));
+
+        let mut milestones = LapMilestones::every(10);
+        assert!(milestones.update(&[8, 25, -1]).is_empty());
+        assert_eq!(milestones.update(&[11, 29, -1]), vec![(0, 10)]);
+        assert_eq!(milestones.update(&[12, 31, 50]), vec![(1, 30)]);
+
+        assert_eq!(&frame(1, "{}")[..], &[1, 0, 0, 0, 2, 0, 0, 0, b'{', b'}']);
+    }
+
+    #[test]
+    fn oversized_frames() {
+        let mut reply = frame(OP_FRAME, "{}");
+        reply[4..8].copy_from_slice(&u32::MAX.to_le_bytes());
+
+        let mut discord = RichPresence {
+            pipe: Box::new(io::Cursor::new(reply)),
+            nonce: 0,
+        };
+        assert!(matches!(discord.read(), Err(DiscordError::Protocol(_))));
+    }
+}
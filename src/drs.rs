@@ -0,0 +1,186 @@
+use crate::states::DrsState;
+use serde::{Deserialize, Serialize};
+
+#[cfg(feature = "telemetry")]
+use crate::telemetry::Sample;
+#[cfg(feature = "telemetry")]
+use std::convert::TryInto;
+#[cfg(feature = "telemetry")]
+use std::error::Error;
+
+///
+/// DRS Sample
+///
+/// The player's DRS state at a point in time.
+#[derive(Debug, Copy, Clone, Default)]
+pub struct DrsSample {
+    pub session_time: f64, // Seconds since session start
+    pub lap: i32,
+    pub state: DrsState,
+}
+
+///
+/// Events reported by the DRS tracker.
+#[derive(Debug, Copy, Clone, PartialEq, Serialize, Deserialize)]
+pub enum DrsEvent {
+    Available { session_time: f64, lap: i32 },
+    Opened { session_time: f64, lap: i32 },
+    Closed { session_time: f64, duration: f64 },
+}
+
+///
+/// DRS Tracker
+///
+/// Counts DRS activations per lap and, for series which limit their use, the
+/// number of activations remaining.
+///
+/// # Examples
+///
+/// ```
+/// use iracing::drs::{DrsSample, DrsTracker};
+///
+/// let mut drs = DrsTracker::with_limit(15);
+/// drs.update(&DrsSample::default());
+///
+/// println!("{:?} activations left", drs.remaining());
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct DrsTracker {
+    limit: Option<u32>,
+    state: DrsState,
+    opened_at: Option<f64>,
+    activations: Vec<(i32, u32)>,
+}
+
+impl DrsSample {
+    ///
+    /// Read a DRS sample from a telemetry sample.
+    #[cfg(feature = "telemetry")]
+    pub fn from_sample(sample: &Sample) -> Result<Self, Box<dyn Error>> {
+        let state: i32 = sample.get("DRS_Status")?.try_into()?;
+
+        Ok(DrsSample {
+            session_time: sample.get("SessionTime")?.try_into()?,
+            lap: sample.get("Lap")?.try_into()?,
+            state: DrsState::from(state),
+        })
+    }
+}
+
+impl DrsTracker {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Tracker for a series allowing `limit` activations per race.
+    pub fn with_limit(limit: u32) -> Self {
+        DrsTracker {
+            limit: Some(limit),
+            ..Self::default()
+        }
+    }
+
+    ///
+    /// Update the tracker with a new sample, returning any events which occurred.
+    pub fn update(&mut self, sample: &DrsSample) -> Vec<DrsEvent> {
+        let mut events = Vec::new();
+        let session_time = sample.session_time;
+
+        if sample.state != self.state {
+            if let Some(opened) = self.opened_at.take() {
+                events.push(DrsEvent::Closed {
+                    session_time,
+                    duration: session_time - opened,
+                });
+            }
+
+            match sample.state {
+                DrsState::Open => {
+                    match self.activations.iter_mut().find(|(l, _)| *l == sample.lap) {
+                        Some((_, count)) => *count += 1,
+                        None => self.activations.push((sample.lap, 1)),
+                    }
+
+                    self.opened_at = Some(session_time);
+                    events.push(DrsEvent::Opened {
+                        session_time,
+                        lap: sample.lap,
+                    });
+                }
+                DrsState::Available => events.push(DrsEvent::Available {
+                    session_time,
+                    lap: sample.lap,
+                }),
+                _ => {}
+            }
+        }
+
+        self.state = sample.state;
+        events
+    }
+
+    pub fn state(&self) -> DrsState {
+        self.state
+    }
+
+    /// Activations on a given lap.
+    pub fn on_lap(&self, lap: i32) -> u32 {
+        self.activations
+            .iter()
+            .find(|(l, _)| *l == lap)
+            .map_or(0, |(_, count)| *count)
+    }
+
+    /// Activations per lap, as (lap, activations).
+    pub fn per_lap(&self) -> &[(i32, u32)] {
+        &self.activations
+    }
+
+    /// Total activations.
+    pub fn total(&self) -> u32 {
+        self.activations.iter().map(|(_, count)| count).sum()
+    }
+
+    /// Activations remaining, for series which limit DRS use.
+    pub fn remaining(&self) -> Option<u32> {
+        self.limit.map(|limit| limit.saturating_sub(self.total()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn count_activations() {
+        let mut drs = DrsTracker::with_limit(3);
+        let states = [
+            (1, DrsState::Armed),
+            (1, DrsState::Available),
+            (1, DrsState::Open),
+            (1, DrsState::Unavailable),
+            (2, DrsState::Available),
+            (2, DrsState::Open),
+            (2, DrsState::Available),
+            (2, DrsState::Open),
+        ];
+
+        let mut events = Vec::new();
+        for (i, (lap, state)) in states.iter().enumerate() {
+            events.extend(drs.update(&DrsSample {
+                session_time: i as f64,
+                lap: *lap,
+                state: *state,
+            }));
+        }
+
+        assert_eq!(drs.on_lap(1), 1);
+        assert_eq!(drs.on_lap(2), 2);
+        assert_eq!(drs.remaining(), Some(0));
+        assert!(events.contains(&DrsEvent::Closed {
+            session_time: 3.0,
+            duration: 1.0
+        }));
+        assert_eq!(drs.state(), DrsState::Open);
+    }
+}
@@ -0,0 +1,231 @@
+use serde::Serialize;
+
+#[cfg(feature = "telemetry")]
+use crate::telemetry::Sample;
+#[cfg(feature = "telemetry")]
+use std::convert::TryInto;
+#[cfg(feature = "telemetry")]
+use std::error::Error;
+
+/// Standard gravity (m/s^2)
+pub const G: f32 = 9.80665;
+
+/// Below this speed the slip angle is too noisy to be useful (m/s)
+const MIN_SLIP_SPEED: f32 = 5.0;
+
+/// Names of the derived channels, in the order `Dynamics::channels` returns them.
+pub const CHANNELS: [&str; 12] = [
+    "LatG",
+    "LongG",
+    "VertG",
+    "CombinedG",
+    "SlipAngle",
+    "RideHeightFront",
+    "RideHeightRear",
+    "Rake",
+    "RideHeightRoll",
+    "BrakeFront",
+    "BrakeRear",
+    "BrakeBiasFront",
+];
+
+///
+/// Dynamics Sample
+///
+/// Vehicle motion at a point in time. Ride heights and brake bias are only
+/// available for some cars.
+#[derive(Debug, Copy, Clone, Default)]
+pub struct DynamicsSample {
+    pub lat_accel: f32,  // LatAccel - lateral acceleration, including gravity (m/s^2)
+    pub long_accel: f32, // LongAccel - longitudinal acceleration, including gravity (m/s^2)
+    pub vert_accel: f32, // VertAccel - vertical acceleration, including gravity (m/s^2)
+    pub velocity_x: f32, // VelocityX - forward velocity in the car's frame (m/s)
+    pub velocity_y: f32, // VelocityY - leftward velocity in the car's frame (m/s)
+    pub brake: f32,      // Brake - pedal position, 0.0 to 1.0
+
+    pub ride_heights: Option<[f32; 4]>, // LFrideHeight, RFrideHeight, LRrideHeight, RRrideHeight (m)
+    pub brake_bias: Option<f32>,        // dcBrakeBias - front brake bias (%)
+}
+
+///
+/// Vehicle dynamics derived from a single sample.
+#[derive(Debug, Copy, Clone, Default, PartialEq, Serialize)]
+pub struct Dynamics {
+    pub lat_g: f32,
+    pub long_g: f32,
+    pub vert_g: f32,
+    pub combined_g: f32,         // Horizontal G, from lateral and longitudinal
+    pub slip_angle: Option<f32>, // Angle between heading and direction of travel (degrees)
+    pub ride_height_front: Option<f32>, // Average of the front corners (m)
+    pub ride_height_rear: Option<f32>, // Average of the rear corners (m)
+    pub rake: Option<f32>,       // Rear ride height less front (m)
+    pub ride_height_roll: Option<f32>, // Left ride height less right (m)
+    pub brake_front: Option<f32>, // Share of pedal input acting on the front axle
+    pub brake_rear: Option<f32>, // Share of pedal input acting on the rear axle
+    pub brake_bias_front: Option<f32>, // Front brake bias, 0.0 to 1.0
+}
+
+impl DynamicsSample {
+    ///
+    /// Read a dynamics sample from a telemetry sample.
+    #[cfg(feature = "telemetry")]
+    pub fn from_sample(sample: &Sample) -> Result<Self, Box<dyn Error>> {
+        let corners = [
+            "LFrideHeight",
+            "RFrideHeight",
+            "LRrideHeight",
+            "RRrideHeight",
+        ];
+        let ride_heights = if corners.iter().all(|c| sample.has(c)) {
+            let mut heights = [0.0; 4];
+            for (height, corner) in heights.iter_mut().zip(corners.iter()) {
+                *height = sample.get(corner)?.try_into()?;
+            }
+            Some(heights)
+        } else {
+            None
+        };
+
+        let brake_bias = if sample.has("dcBrakeBias") {
+            Some(sample.get("dcBrakeBias")?.try_into()?)
+        } else {
+            None
+        };
+
+        Ok(DynamicsSample {
+            lat_accel: sample.get("LatAccel")?.try_into()?,
+            long_accel: sample.get("LongAccel")?.try_into()?,
+            vert_accel: sample.get("VertAccel")?.try_into()?,
+            velocity_x: sample.get("VelocityX")?.try_into()?,
+            velocity_y: sample.get("VelocityY")?.try_into()?,
+            brake: sample.get("Brake")?.try_into()?,
+            ride_heights,
+            brake_bias,
+        })
+    }
+}
+
+impl Dynamics {
+    ///
+    /// Derive dynamics from a sample.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use iracing::dynamics::{Dynamics, DynamicsSample, G};
+    ///
+    /// let dynamics = Dynamics::derive(&DynamicsSample {
+    ///     lat_accel: 2.0 * G,
+    ///     long_accel: -1.0 * G,
+    ///     vert_accel: G,
+    ///     velocity_x: 40.0,
+    ///     brake_bias: Some(56.0),
+    ///     brake: 1.0,
+    ///     ..Default::default()
+    /// });
+    ///
+    /// assert_eq!(dynamics.lat_g, 2.0);
+    /// assert_eq!(dynamics.brake_front, Some(0.56));
+    /// ```
+    pub fn derive(sample: &DynamicsSample) -> Self {
+        let lat_g = sample.lat_accel / G;
+        let long_g = sample.long_accel / G;
+
+        let speed = sample.velocity_x.hypot(sample.velocity_y);
+        let slip_angle = if speed >= MIN_SLIP_SPEED {
+            Some(sample.velocity_y.atan2(sample.velocity_x).to_degrees())
+        } else {
+            None
+        };
+
+        let heights = sample.ride_heights;
+        let front = heights.map(|[lf, rf, _, _]| (lf + rf) / 2.0);
+        let rear = heights.map(|[_, _, lr, rr]| (lr + rr) / 2.0);
+        let bias = sample.brake_bias.map(|b| b / 100.0);
+
+        Dynamics {
+            lat_g,
+            long_g,
+            vert_g: sample.vert_accel / G,
+            combined_g: lat_g.hypot(long_g),
+            slip_angle,
+            ride_height_front: front,
+            ride_height_rear: rear,
+            rake: front.zip(rear).map(|(f, r)| r - f),
+            ride_height_roll: heights.map(|[lf, rf, lr, rr]| (lf + lr - rf - rr) / 2.0),
+            brake_front: bias.map(|b| sample.brake * b),
+            brake_rear: bias.map(|b| sample.brake * (1.0 - b)),
+            brake_bias_front: bias,
+        }
+    }
+
+    ///
+    /// Derived values as named channels, named as in `CHANNELS`, skipping
+    /// those the car doesn't report. These can be passed straight to
+    /// `History::record` or `Stats::record` alongside the sim's own channels.
+    pub fn channels(&self) -> Vec<(&'static str, f64)> {
+        let values = [
+            Some(self.lat_g),
+            Some(self.long_g),
+            Some(self.vert_g),
+            Some(self.combined_g),
+            self.slip_angle,
+            self.ride_height_front,
+            self.ride_height_rear,
+            self.rake,
+            self.ride_height_roll,
+            self.brake_front,
+            self.brake_rear,
+            self.brake_bias_front,
+        ];
+
+        CHANNELS
+            .iter()
+            .zip(values.iter())
+            .filter_map(|(name, value)| value.map(|v| (*name, v as f64)))
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::stats::Stats;
+
+    #[test]
+    fn derive_dynamics() {
+        let sample = DynamicsSample {
+            lat_accel: 3.0 * G,
+            long_accel: 4.0 * G,
+            vert_accel: G,
+            velocity_x: 30.0,
+            velocity_y: 30.0,
+            brake: 0.5,
+            ride_heights: Some([0.05, 0.04, 0.07, 0.06]),
+            brake_bias: None,
+        };
+
+        let dynamics = Dynamics::derive(&sample);
+        assert!((dynamics.combined_g - 5.0).abs() < 1e-5);
+        assert!((dynamics.slip_angle.unwrap() - 45.0).abs() < 1e-4);
+        assert!((dynamics.rake.unwrap() - 0.02).abs() < 1e-6);
+        assert!((dynamics.ride_height_roll.unwrap() - 0.01).abs() < 1e-6);
+        assert_eq!(dynamics.brake_front, None);
+
+        let slow = Dynamics::derive(&DynamicsSample {
+            velocity_x: 1.0,
+            velocity_y: 1.0,
+            ..sample
+        });
+        assert_eq!(slow.slip_angle, None);
+
+        // Missing channels are left out
+        let channels = slow.channels();
+        assert_eq!(channels.len(), 8);
+        assert!(!channels.iter().any(|(name, _)| *name == "BrakeFront"));
+
+        let mut stats = Stats::new();
+        stats.record(1, &channels);
+        assert!((stats.summary("LatG").unwrap().max - 3.0).abs() < 1e-5);
+    }
+}
@@ -0,0 +1,546 @@
+use crate::strategy::{CarState, FuelModel, Planner, TireModel};
+use crate::validity::{self, PendingLap, Sentinel};
+use serde::{Deserialize, Serialize};
+use std::fmt;
+
+#[cfg(all(target_os = "windows", feature = "broadcast"))]
+use crate::broadcast::{Broadcast, BroadcastMessage, PitCommandMode};
+#[cfg(feature = "telemetry")]
+use crate::telemetry::Sample;
+#[cfg(feature = "telemetry")]
+use std::convert::TryInto;
+#[cfg(feature = "telemetry")]
+use std::error::Error;
+
+/// Points around the lap at which the delta reference is kept.
+const DELTA_POINTS: usize = 200;
+
+/// Laps of fuel use averaged for the fuel model.
+const FUEL_LAPS: usize = 5;
+
+///
+/// Engineer Sample
+///
+/// The player's car at a point in time.
+#[derive(Debug, Copy, Clone, Default)]
+pub struct EngineerSample {
+    pub session_time: f64,   // Seconds since session start
+    pub lap: i32,            // Lap being driven
+    pub lap_dist_pct: f32,   // Distance around the lap (0-1)
+    pub lap_time: f32,       // Time into the current lap (s)
+    pub last_lap_time: f32,  // Last lap time (s), <= 0 if none
+    pub fuel_level: f32,     // Fuel in the car (l)
+    pub on_pit_road: bool,   // Car is on pit road
+    pub position: i32,       // Overall position, 0 if unknown
+    pub class_position: i32, // Position in class, 0 if unknown
+    pub laps_remaining: i32, // Laps left in the session, < 0 if timed
+}
+
+///
+/// Snapshot of the car's state as seen by the engineer.
+#[derive(Debug, Copy, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct Status {
+    pub lap: i32,
+    pub position: i32,
+    pub class_position: i32,
+    pub fuel: f32,                   // Fuel in the car (l)
+    pub fuel_per_lap: Option<f32>,   // Average fuel used on recent green laps (l)
+    pub laps_of_fuel: Option<f32>,   // Laps the fuel lasts
+    pub laps_remaining: Option<i32>, // Laps left in the race, if lap limited
+    pub stint_laps: i32,             // Laps since leaving the pits
+    pub tire_age: i32,               // Laps on the current tires
+    pub last_lap: Option<f32>,       // Last lap time (s)
+    pub best_lap: Option<f32>,       // Best green lap time (s)
+    pub delta: Option<f32>,          // Time gained (-) or lost (+) against the best lap (s)
+}
+
+///
+/// When to pit and what to do when there.
+#[derive(Debug, Copy, Clone, PartialEq, Serialize, Deserialize)]
+pub struct PitRecommendation {
+    pub lap: i32,         // Lap to pit at the end of
+    pub last_lap: i32,    // Last lap the fuel reaches
+    pub fuel_to_add: f32, // Fuel to add to reach the end (l)
+    pub change_tires: bool,
+    pub service_time: f64, // Expected time stationary (s)
+}
+
+///
+/// Engineer
+///
+/// An opinionated race engineer bundling a fuel model, stint and tire
+/// tracking, a delta timer against the best lap and the player's standing.
+/// For more control use the `strategy`, `pits` and `gaps` modules directly.
+///
+/// # Examples
+///
+/// ```
+/// # #[cfg(feature = "telemetry")]
+/// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+/// use iracing::engineer::{Engineer, EngineerSample};
+/// # let samples: Vec<iracing::telemetry::Sample> = Vec::new();
+///
+/// let mut engineer = Engineer::new(110.0);
+///
+/// for sample in samples.iter() {
+///     engineer.update(&EngineerSample::from_sample(sample)?);
+///     println!("{}", engineer.status());
+///
+///     if let Some(pit) = engineer.pit_recommendation() {
+///         println!("Box on lap {} for {:.1}l", pit.lap, pit.fuel_to_add);
+///     }
+/// }
+/// # Ok(())
+/// # }
+/// # #[cfg(not(feature = "telemetry"))]
+/// # fn main() {}
+/// ```
+#[derive(Debug, Clone)]
+pub struct Engineer {
+    capacity: f32,
+    fill_rate: f32,
+    pit_loss: f64,
+    tire_change: f64,
+    degradation: f64,
+
+    last: Option<EngineerSample>,
+    lap_fuel: Option<f32>,
+    lap_pitted: bool,
+    fuel_used: Vec<f32>,
+    stint_start: i32,
+    tires_from: i32,
+    best_lap: Option<f32>,
+    best_ref: Vec<f32>,
+    lap_ref: Vec<f32>,
+    pending: Option<(PendingLap, Vec<f32>)>, // Lap waiting for its time, and its delta reference
+}
+
+impl EngineerSample {
+    ///
+    /// Read an engineer sample from a telemetry sample.
+    #[cfg(feature = "telemetry")]
+    pub fn from_sample(sample: &Sample) -> Result<Self, Box<dyn Error>> {
+        let mut engineer = EngineerSample {
+            session_time: sample.get("SessionTime")?.try_into()?,
+            lap: sample.get("Lap")?.try_into()?,
+            lap_dist_pct: sample.get("LapDistPct")?.try_into()?,
+            lap_time: sample.get("LapCurrentLapTime")?.try_into()?,
+            last_lap_time: sample.get("LapLastLapTime")?.try_into()?,
+            fuel_level: sample.get("FuelLevel")?.try_into()?,
+            on_pit_road: sample.get("OnPitRoad")?.into(),
+            laps_remaining: -1,
+            ..Default::default()
+        };
+
+        if sample.has("PlayerCarPosition") {
+            engineer.position = sample.get("PlayerCarPosition")?.try_into()?;
+            engineer.class_position = sample.get("PlayerCarClassPosition")?.try_into()?;
+        }
+        if sample.has("SessionLapsRemainEx") {
+            engineer.laps_remaining = sample.get("SessionLapsRemainEx")?.try_into()?;
+        }
+
+        Ok(engineer)
+    }
+
+    /// Laps left in a lap limited session, or `None` if it's timed or has
+    /// no limit.
+    pub fn laps_left(&self) -> Option<i32> {
+        Sentinel::for_channel("SessionLapsRemainEx")
+            .check(self.laps_remaining)
+            .value()
+    }
+}
+
+impl fmt::Display for Status {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Lap {}", self.lap)?;
+        if self.position > 0 {
+            write!(f, " P{} (P{} in class)", self.position, self.class_position)?;
+        }
+
+        write!(f, " | Fuel {:.1}l", self.fuel)?;
+        if let Some(laps) = self.laps_of_fuel {
+            write!(f, ", {:.1} laps", laps)?;
+        }
+
+        write!(f, " | Stint {} laps", self.stint_laps)?;
+        if let Some(delta) = self.delta {
+            write!(f, " | Delta {:+.3}", delta)?;
+        }
+
+        Ok(())
+    }
+}
+
+impl Engineer {
+    /// Engineer for a car with a `capacity` litre tank.
+    pub fn new(capacity: f32) -> Self {
+        Engineer {
+            capacity,
+            fill_rate: 2.5,
+            pit_loss: 25.0,
+            tire_change: 20.0,
+            degradation: 0.05,
+            last: None,
+            lap_fuel: None,
+            lap_pitted: false,
+            fuel_used: Vec::new(),
+            stint_start: 0,
+            tires_from: 0,
+            best_lap: None,
+            best_ref: Vec::new(),
+            lap_ref: vec![f32::NAN; DELTA_POINTS],
+            pending: None,
+        }
+    }
+
+    /// Refuelling rate (l/s).
+    pub fn with_fill_rate(mut self, fill_rate: f32) -> Self {
+        self.fill_rate = fill_rate;
+        self
+    }
+
+    /// Time lost driving through pit lane, excluding service (s).
+    pub fn with_pit_loss(mut self, pit_loss: f64) -> Self {
+        self.pit_loss = pit_loss;
+        self
+    }
+
+    ///
+    /// Time to change tires (s), and lap time lost per lap of tire age (s).
+    pub fn with_tires(mut self, change_time: f64, degradation: f64) -> Self {
+        self.tire_change = change_time;
+        self.degradation = degradation;
+        self
+    }
+
+    ///
+    /// Update the engineer with a new sample.
+    pub fn update(&mut self, sample: &EngineerSample) {
+        let last = match self.last.replace(*sample) {
+            Some(last) => last,
+            None => {
+                self.lap_fuel = Some(sample.fuel_level);
+                self.stint_start = sample.lap;
+                self.tires_from = sample.lap;
+                self.record_delta(sample);
+                return;
+            }
+        };
+
+        // A lap still waiting is timed before the next is completed
+        if let Some((lap, lap_ref)) = self.pending.take() {
+            match lap.poll(sample.last_lap_time, sample.session_time) {
+                Some(reading) => self.book_lap(reading.value(), lap_ref),
+                None => self.pending = Some((lap, lap_ref)),
+            }
+        }
+
+        // Leaving the pits starts a new stint on new tires
+        if last.on_pit_road && !sample.on_pit_road {
+            self.stint_start = sample.lap;
+            self.tires_from = sample.lap;
+        }
+
+        if sample.lap > last.lap {
+            self.complete_lap(sample, last.last_lap_time);
+        } else {
+            self.lap_pitted |= sample.on_pit_road;
+            self.record_delta(sample);
+        }
+    }
+
+    ///
+    /// Finish a lap. The sim updates the last lap time a few ticks after the
+    /// lap rolls over, so the lap is booked once it does, see `PendingLap`.
+    fn complete_lap(&mut self, sample: &EngineerSample, previous_time: f32) {
+        let lap_ref = std::mem::replace(&mut self.lap_ref, vec![f32::NAN; DELTA_POINTS]);
+
+        if !self.lap_pitted {
+            if let Some(start) = self.lap_fuel {
+                let used = start - sample.fuel_level;
+                if used > 0.0 {
+                    self.fuel_used.push(used);
+                    if self.fuel_used.len() > FUEL_LAPS {
+                        self.fuel_used.remove(0);
+                    }
+                }
+            }
+
+            let lap = PendingLap::new(previous_time, sample.session_time);
+            match lap.poll(sample.last_lap_time, sample.session_time) {
+                Some(reading) => self.book_lap(reading.value(), lap_ref),
+                None => self.pending = Some((lap, lap_ref)),
+            }
+        }
+
+        self.lap_fuel = Some(sample.fuel_level);
+        self.lap_pitted = sample.on_pit_road;
+        self.record_delta(sample);
+    }
+
+    /// Keep a green lap's time and delta reference if it's the best yet.
+    fn book_lap(&mut self, lap_time: Option<f32>, lap_ref: Vec<f32>) {
+        if let Some(lap_time) =
+            lap_time.filter(|time| self.best_lap.is_none_or(|best| *time < best))
+        {
+            self.best_lap = Some(lap_time);
+            self.best_ref = lap_ref;
+        }
+    }
+
+    fn record_delta(&mut self, sample: &EngineerSample) {
+        let point = delta_point(sample.lap_dist_pct);
+        if self.lap_ref[point].is_nan() {
+            self.lap_ref[point] = sample.lap_time;
+        }
+    }
+
+    /// Average fuel used per green lap (l).
+    pub fn fuel_per_lap(&self) -> Option<f32> {
+        if self.fuel_used.is_empty() {
+            return None;
+        }
+
+        Some(self.fuel_used.iter().sum::<f32>() / self.fuel_used.len() as f32)
+    }
+
+    ///
+    /// Time gained (-) or lost (+) against the best lap at the current point
+    /// of the lap.
+    pub fn delta(&self) -> Option<f32> {
+        let sample = self.last?;
+        let point = delta_point(sample.lap_dist_pct);
+        let best = *self.best_ref.get(point)?;
+
+        if best.is_nan() || sample.on_pit_road {
+            None
+        } else {
+            Some(sample.lap_time - best)
+        }
+    }
+
+    pub fn status(&self) -> Status {
+        let sample = self.last.unwrap_or_default();
+        let fuel_per_lap = self.fuel_per_lap();
+
+        Status {
+            lap: sample.lap,
+            position: sample.position,
+            class_position: sample.class_position,
+            fuel: sample.fuel_level,
+            fuel_per_lap,
+            laps_of_fuel: fuel_per_lap.map(|per_lap| sample.fuel_level / per_lap),
+            laps_remaining: sample.laps_left(),
+            stint_laps: sample.lap - self.stint_start,
+            tire_age: sample.lap - self.tires_from,
+            last_lap: validity::lap_time(sample.last_lap_time),
+            best_lap: self.best_lap,
+            delta: self.delta(),
+        }
+    }
+
+    ///
+    /// When to pit, if the fuel won't reach the end of a lap limited race.
+    ///
+    /// The stop lap is the quickest to the finish according to a
+    /// `strategy::Planner`, which weighs fresh tires against stopping early.
+    pub fn pit_recommendation(&self) -> Option<PitRecommendation> {
+        let sample = self.last?;
+        let per_lap = self.fuel_per_lap()?;
+        let laps_remaining = sample.laps_left()?;
+        let race_laps = sample.lap + laps_remaining;
+
+        let fuel = FuelModel {
+            fuel: sample.fuel_level,
+            per_lap,
+            capacity: self.capacity,
+            fill_rate: self.fill_rate,
+        };
+        if fuel.laps_remaining() >= laps_remaining {
+            return None;
+        }
+
+        let tires = TireModel {
+            age: sample.lap - self.tires_from,
+            degradation: self.degradation,
+            change_time: self.tire_change,
+        };
+        let car = CarState {
+            car_idx: 0,
+            gap: 0.0,
+            lap_time: self.best_lap.unwrap_or(sample.last_lap_time).max(0.0) as f64,
+            pit_lap: None,
+        };
+
+        let planner = Planner::new(car, fuel, tires, self.pit_loss, sample.lap, race_laps);
+        let best = planner.best()?;
+
+        let laps_to_stop = (best.stop_lap - sample.lap + 1).max(0) as f32;
+        let fuel_left = (sample.fuel_level - per_lap * laps_to_stop).max(0.0);
+        let needed = per_lap * (race_laps - best.stop_lap) as f32 - fuel_left;
+
+        Some(PitRecommendation {
+            lap: best.stop_lap,
+            last_lap: planner.window().end - 1,
+            fuel_to_add: needed.min(self.capacity - fuel_left).max(0.0),
+            change_tires: self.tire_change > 0.0,
+            service_time: best.service_time,
+        })
+    }
+
+    ///
+    /// Set the sim's pit service to the current recommendation.
+    ///
+    /// Returns false if there is no recommendation.
+    #[cfg(all(target_os = "windows", feature = "broadcast"))]
+    pub fn apply_pit_plan(&self, broadcast: &Broadcast) -> bool {
+        let plan = match self.pit_recommendation() {
+            Some(plan) => plan,
+            None => return false,
+        };
+
+        broadcast.send_message(BroadcastMessage::PitCommand(PitCommandMode::Clear));
+        broadcast.send_message(BroadcastMessage::PitCommand(PitCommandMode::Fuel(
+            plan.fuel_to_add.ceil().clamp(0.0, 255.0) as u8,
+        )));
+
+        if plan.change_tires {
+            // A pressure of 0 keeps the current pressure
+            for tire in [
+                PitCommandMode::LF(0),
+                PitCommandMode::RF(0),
+                PitCommandMode::LR(0),
+                PitCommandMode::RR(0),
+            ] {
+                broadcast.send_message(BroadcastMessage::PitCommand(tire));
+            }
+        }
+
+        true
+    }
+}
+
+fn delta_point(lap_dist_pct: f32) -> usize {
+    ((lap_dist_pct.clamp(0.0, 1.0) * DELTA_POINTS as f32) as usize).min(DELTA_POINTS - 1)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn race_engineer() {
+        let mut engineer = Engineer::new(60.0).with_tires(0.0, 0.0);
+        let mut sample = EngineerSample {
+            position: 3,
+            class_position: 1,
+            ..Default::default()
+        };
+
+        // Three green laps using 4l each, the first the quickest
+        for (lap, lap_time) in [100.0, 102.0, 101.0].iter().enumerate() {
+            for step in 0..10 {
+                sample.lap = lap as i32;
+                sample.lap_dist_pct = step as f32 / 10.0;
+                sample.lap_time = lap_time * step as f32 / 10.0;
+                sample.fuel_level = 20.0 - 4.0 * (lap as f32 + step as f32 / 10.0);
+                sample.laps_remaining = 10 - lap as i32;
+                engineer.update(&sample);
+            }
+            sample.last_lap_time = *lap_time;
+        }
+
+        // Half way round the next lap, a second up on the best lap
+        for (pct, lap_time, fuel) in [(0.0, 0.0, 8.0), (0.5, 49.0, 6.0)].iter() {
+            sample.lap = 3;
+            sample.lap_dist_pct = *pct;
+            sample.lap_time = *lap_time;
+            sample.fuel_level = *fuel;
+            sample.laps_remaining = 7;
+            engineer.update(&sample);
+        }
+
+        let status = engineer.status();
+        assert_eq!(status.fuel_per_lap, Some(4.0));
+        assert_eq!(status.best_lap, Some(100.0));
+        assert_eq!(
+            status.to_string(),
+            "Lap 3 P3 (P1 in class) | Fuel 6.0l, 1.5 laps | Stint 3 laps | Delta -1.000"
+        );
+
+        let pit = engineer.pit_recommendation().unwrap();
+        assert_eq!(pit.lap, 3);
+        assert!(!pit.change_tires);
+        assert!((pit.fuel_to_add - 26.0).abs() < 1e-3);
+    }
+
+    #[test]
+    fn lap_time_arrives_late() {
+        let mut engineer = Engineer::new(60.0).with_tires(0.0, 0.0);
+        let mut sample = EngineerSample {
+            last_lap_time: -1.0,
+            ..Default::default()
+        };
+        engineer.update(&sample);
+
+        let mut start = 0.0;
+        for (lap, lap_time) in [100.0f32, 102.0].iter().enumerate() {
+            for step in 1..10 {
+                sample.lap_dist_pct = step as f32 / 10.0;
+                sample.lap_time = lap_time * step as f32 / 10.0;
+                sample.session_time = start + sample.lap_time as f64;
+                engineer.update(&sample);
+            }
+            start += *lap_time as f64;
+
+            // The lap rolls over, and the sim sets its time a few ticks later
+            sample.lap = lap as i32 + 1;
+            sample.lap_dist_pct = 0.0;
+            for tick in 0..4 {
+                if tick == 3 {
+                    assert_eq!(engineer.status().best_lap, [None, Some(100.0)][lap]);
+                    sample.last_lap_time = *lap_time;
+                }
+                sample.lap_time = tick as f32 / 60.0;
+                sample.session_time = start + sample.lap_time as f64;
+                engineer.update(&sample);
+            }
+        }
+
+        // Half way round, a second up on the first lap rather than the second
+        sample.lap_dist_pct = 0.5;
+        sample.lap_time = 49.0;
+        sample.session_time = start + 49.0;
+        engineer.update(&sample);
+
+        let status = engineer.status();
+        assert_eq!(status.best_lap, Some(100.0));
+        assert_eq!(status.delta, Some(-1.0));
+    }
+
+    #[test]
+    fn unlimited_laps() {
+        // A tank smaller than the fuel in it doesn't upset the plan
+        let mut engineer = Engineer::new(1.0).with_tires(0.0, 0.0);
+        let mut sample = EngineerSample {
+            laps_remaining: 20,
+            ..Default::default()
+        };
+
+        for lap in 0..3 {
+            sample.lap = lap;
+            sample.last_lap_time = 100.0;
+            sample.fuel_level = 20.0 - 4.0 * lap as f32;
+            engineer.update(&sample);
+        }
+        let pit = engineer.pit_recommendation().unwrap();
+        assert_eq!(pit.fuel_to_add, 0.0);
+
+        sample.laps_remaining = 32767;
+        engineer.update(&sample);
+        assert_eq!(engineer.status().laps_remaining, None);
+        assert!(engineer.pit_recommendation().is_none());
+    }
+}
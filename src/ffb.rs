@@ -0,0 +1,221 @@
+use serde::{Deserialize, Serialize};
+
+#[cfg(feature = "telemetry")]
+use crate::telemetry::Sample;
+#[cfg(feature = "telemetry")]
+use std::convert::TryInto;
+#[cfg(feature = "telemetry")]
+use std::error::Error;
+
+/// Resolution of the torque histogram (Nm)
+const BUCKET_NM: f32 = 0.1;
+
+/// Largest torque tracked by the histogram (Nm)
+const MAX_TRACKED_NM: f32 = 100.0;
+
+/// Output above this fraction of max force counts as clipping
+const CLIP_THRESHOLD: f32 = 0.995;
+
+///
+/// FFB Sample
+///
+/// Steering torque output at a point in time.
+#[derive(Debug, Copy, Clone, Default)]
+pub struct FfbSample {
+    pub lap: i32,
+    pub torque: f32,     // SteeringWheelTorque - torque requested by the car (Nm)
+    pub pct_torque: f32, // SteeringWheelPctTorque - output as a fraction of max force
+    pub max_force: f32,  // SteeringWheelMaxForceNm - current max force setting (Nm)
+}
+
+///
+/// Force feedback statistics for a single lap.
+#[derive(Debug, Copy, Clone, PartialEq, Serialize, Deserialize)]
+pub struct LapFfb {
+    pub lap: i32,
+    pub samples: u32,
+    pub clipped: u32,     // Samples where the output was at max force
+    pub peak_torque: f32, // Highest absolute torque requested (Nm)
+}
+
+///
+/// FFB Monitor
+///
+/// Tracks steering torque to report how often force feedback clips each lap,
+/// and suggests a max force setting which keeps clipping to a target level.
+///
+/// # Examples
+///
+/// ```
+/// use iracing::ffb::{FfbMonitor, FfbSample};
+///
+/// let mut monitor = FfbMonitor::new();
+/// monitor.update(&FfbSample { lap: 1, torque: 12.0, pct_torque: 0.6, max_force: 20.0 });
+///
+/// if let Some(force) = monitor.suggest_max_force(0.02) {
+///     println!("Try a max force of {:.1} Nm", force);
+/// }
+/// ```
+#[derive(Debug, Clone)]
+pub struct FfbMonitor {
+    laps: Vec<LapFfb>,
+    histogram: Vec<u32>,
+    samples: u64,
+}
+
+impl FfbSample {
+    ///
+    /// Read a force feedback sample from a telemetry sample.
+    #[cfg(feature = "telemetry")]
+    pub fn from_sample(sample: &Sample) -> Result<Self, Box<dyn Error>> {
+        Ok(FfbSample {
+            lap: sample.get("Lap")?.try_into()?,
+            torque: sample.get("SteeringWheelTorque")?.try_into()?,
+            pct_torque: sample.get("SteeringWheelPctTorque")?.try_into()?,
+            max_force: sample.get("SteeringWheelMaxForceNm")?.try_into()?,
+        })
+    }
+
+    /// True if the output is at max force.
+    pub fn is_clipping(&self) -> bool {
+        self.pct_torque.abs() >= CLIP_THRESHOLD
+    }
+}
+
+impl LapFfb {
+    /// Fraction of the lap spent clipping, 0.0 to 1.0.
+    pub fn clipping(&self) -> f32 {
+        if self.samples == 0 {
+            0.0
+        } else {
+            self.clipped as f32 / self.samples as f32
+        }
+    }
+}
+
+impl Default for FfbMonitor {
+    fn default() -> Self {
+        FfbMonitor {
+            laps: Vec::new(),
+            histogram: vec![0; (MAX_TRACKED_NM / BUCKET_NM) as usize + 1],
+            samples: 0,
+        }
+    }
+}
+
+impl FfbMonitor {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    ///
+    /// Add a sample, returning the previous lap's statistics when a new lap starts.
+    pub fn update(&mut self, sample: &FfbSample) -> Option<LapFfb> {
+        let completed = match self.laps.last() {
+            Some(last) if last.lap != sample.lap => Some(*last),
+            _ => None,
+        };
+
+        if completed.is_some() || self.laps.is_empty() {
+            self.laps.push(LapFfb {
+                lap: sample.lap,
+                samples: 0,
+                clipped: 0,
+                peak_torque: 0.0,
+            });
+        }
+
+        let torque = sample.torque.abs();
+        if let Some(lap) = self.laps.last_mut() {
+            lap.samples += 1;
+            if sample.is_clipping() {
+                lap.clipped += 1;
+            }
+            lap.peak_torque = lap.peak_torque.max(torque);
+        }
+
+        let bucket = ((torque.min(MAX_TRACKED_NM)) / BUCKET_NM) as usize;
+        self.histogram[bucket] += 1;
+        self.samples += 1;
+
+        completed
+    }
+
+    /// Statistics for every lap seen, including the current lap.
+    pub fn laps(&self) -> &[LapFfb] {
+        &self.laps
+    }
+
+    /// Fraction of all samples spent clipping.
+    pub fn clipping(&self) -> f32 {
+        let (samples, clipped) = self
+            .laps
+            .iter()
+            .fold((0, 0), |(s, c), l| (s + l.samples, c + l.clipped));
+
+        if samples == 0 {
+            0.0
+        } else {
+            clipped as f32 / samples as f32
+        }
+    }
+
+    ///
+    /// Suggest a max force (Nm) at which only `clipping` (0.0 to 1.0) of the
+    /// samples seen so far would have clipped.
+    ///
+    /// The result can be applied with `BroadcastMessage::FFBCommand(FFBCommandMode::MaxForce(..))`.
+    pub fn suggest_max_force(&self, clipping: f32) -> Option<f32> {
+        if self.samples == 0 {
+            return None;
+        }
+
+        let allowed = (self.samples as f64 * clipping.clamp(0.0, 1.0) as f64) as u64;
+        let mut above = 0u64;
+
+        for (bucket, count) in self.histogram.iter().enumerate().rev() {
+            above += *count as u64;
+            if above > allowed {
+                return Some((bucket + 1) as f32 * BUCKET_NM);
+            }
+        }
+
+        Some(BUCKET_NM)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn clipping_per_lap() {
+        let mut monitor = FfbMonitor::new();
+        let max_force = 10.0;
+
+        for i in 0..100 {
+            let torque = i as f32 * 0.12;
+            let completed = monitor.update(&FfbSample {
+                lap: 1 + i / 50,
+                torque,
+                pct_torque: (torque / max_force).min(1.0),
+                max_force,
+            });
+
+            if i == 50 {
+                let lap = completed.unwrap();
+                assert_eq!(lap.lap, 1);
+                assert_eq!(lap.clipped, 0);
+            }
+        }
+
+        let lap = monitor.laps()[1];
+        assert_eq!(lap.samples, 50);
+        assert_eq!(lap.clipped, 17);
+        assert!((monitor.clipping() - 0.17).abs() < 1e-6);
+
+        // Just above the 95th percentile of requested torque
+        let force = monitor.suggest_max_force(0.05).unwrap();
+        assert!((force - 11.3).abs() < 0.05, "{}", force);
+    }
+}
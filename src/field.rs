@@ -0,0 +1,215 @@
+use crate::session::Driver;
+use serde::{Deserialize, Serialize};
+use std::str::FromStr;
+
+/// iRacing's Elo-style rating scale
+const RATING_SCALE: f64 = 1600.0 / std::f64::consts::LN_2;
+
+///
+/// License class, from lowest to highest.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+pub enum LicenseClass {
+    Rookie,
+    D,
+    C,
+    B,
+    A,
+    Pro,
+    ProWorldChampionship,
+}
+
+///
+/// A driver's license class and safety rating, e.g. "B 3.12".
+#[derive(Debug, Copy, Clone, PartialEq, Serialize, Deserialize)]
+pub struct License {
+    pub class: LicenseClass,
+    pub safety_rating: f32,
+}
+
+///
+/// Strength statistics for a group of cars.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Strength {
+    pub cars: usize,
+    pub sof: i64, // Strength of field, as calculated by iRacing
+    pub min_irating: i64,
+    pub max_irating: i64,
+    pub average_irating: f64,
+}
+
+///
+/// Field Stats
+///
+/// iRating and license statistics for the drivers in a session, computed from
+/// the `DriverInfo` section.
+///
+/// # Examples
+///
+/// ```
+/// use iracing::field::FieldStats;
+/// # let session: iracing::session::SessionDetails =
+/// #     serde_yaml::from_str(&std::fs::read_to_string("./session.yaml").unwrap()).unwrap();
+///
+/// let stats = FieldStats::from_drivers(&session.drivers.other_drivers);
+///
+/// if let Some(overall) = stats.overall {
+///     println!("SOF {}", overall.sof);
+/// }
+/// ```
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct FieldStats {
+    pub overall: Option<Strength>,
+    pub by_class: Vec<(u64, Strength)>, // Strength of each class, by CarClassID
+    pub licenses: Vec<(LicenseClass, usize)>, // Number of drivers holding each license class
+}
+
+impl FromStr for LicenseClass {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "R" => Ok(LicenseClass::Rookie),
+            "D" => Ok(LicenseClass::D),
+            "C" => Ok(LicenseClass::C),
+            "B" => Ok(LicenseClass::B),
+            "A" => Ok(LicenseClass::A),
+            "P" | "Pro" => Ok(LicenseClass::Pro),
+            "WC" | "PWC" => Ok(LicenseClass::ProWorldChampionship),
+            _ => Err(format!("Unknown license class {}", s)),
+        }
+    }
+}
+
+impl FromStr for License {
+    type Err = String;
+
+    ///
+    /// Parse a `LicString` such as "A 4.99".
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut parts = s.split_whitespace();
+        let class = parts.next().ok_or("Empty license")?.parse()?;
+        let safety_rating = parts
+            .next()
+            .ok_or("Missing safety rating")?
+            .parse()
+            .map_err(|e| format!("Invalid safety rating: {}", e))?;
+
+        Ok(License {
+            class,
+            safety_rating,
+        })
+    }
+}
+
+impl Strength {
+    ///
+    /// Statistics for a set of iRatings. Returns None for an empty set.
+    pub fn from_iratings(ratings: &[i64]) -> Option<Self> {
+        if ratings.is_empty() {
+            return None;
+        }
+
+        let n = ratings.len() as f64;
+        let sum: f64 = ratings
+            .iter()
+            .map(|&r| (-(r as f64) / RATING_SCALE).exp())
+            .sum();
+
+        Some(Strength {
+            cars: ratings.len(),
+            sof: (RATING_SCALE * (n / sum).ln()).round() as i64,
+            min_irating: *ratings.iter().min()?,
+            max_irating: *ratings.iter().max()?,
+            average_irating: ratings.iter().sum::<i64>() as f64 / n,
+        })
+    }
+}
+
+impl FieldStats {
+    ///
+    /// Compute statistics over every car in the session.
+    ///
+    /// The pace car and spectators are ignored.
+    pub fn from_drivers(drivers: &[Driver]) -> Self {
+        let field: Vec<&Driver> = drivers
+            .iter()
+            .filter(|d| !d.is_pace_car() && d.is_spectator == 0)
+            .collect();
+
+        let ratings: Vec<i64> = field.iter().map(|d| d.i_rating).collect();
+
+        let mut class_ids: Vec<u64> = field.iter().map(|d| d.car_class_id).collect();
+        class_ids.sort_unstable();
+        class_ids.dedup();
+
+        let by_class = class_ids
+            .into_iter()
+            .filter_map(|id| {
+                let ratings: Vec<i64> = field
+                    .iter()
+                    .filter(|d| d.car_class_id == id)
+                    .map(|d| d.i_rating)
+                    .collect();
+                Strength::from_iratings(&ratings).map(|s| (id, s))
+            })
+            .collect();
+
+        let mut licenses: Vec<(LicenseClass, usize)> = Vec::new();
+        for license in field
+            .iter()
+            .filter_map(|d| d.license.parse::<License>().ok())
+        {
+            match licenses.iter_mut().find(|(c, _)| *c == license.class) {
+                Some((_, count)) => *count += 1,
+                None => licenses.push((license.class, 1)),
+            }
+        }
+        licenses.sort();
+
+        FieldStats {
+            overall: Strength::from_iratings(&ratings),
+            by_class,
+            licenses,
+        }
+    }
+
+    /// Strength of a given class.
+    pub fn class(&self, car_class_id: u64) -> Option<&Strength> {
+        self.by_class
+            .iter()
+            .find(|(id, _)| *id == car_class_id)
+            .map(|(_, s)| s)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::session::SessionDetails;
+
+    #[test]
+    fn parse_license() {
+        let license: License = "B 3.12".parse().unwrap();
+        assert_eq!(license.class, LicenseClass::B);
+        assert!((license.safety_rating - 3.12).abs() < 1e-6);
+        assert!("X 1.00".parse::<License>().is_err());
+    }
+
+    #[test]
+    fn field_strength() {
+        let content = std::fs::read_to_string("./session.yaml").unwrap();
+        let session: SessionDetails = serde_yaml::from_str(&content).unwrap();
+        let stats = FieldStats::from_drivers(&session.drivers.other_drivers);
+
+        let overall = stats.overall.as_ref().unwrap();
+        assert_eq!(overall.cars, 6);
+        assert_eq!(overall.max_irating, 3650);
+        assert!(overall.sof > overall.min_irating && overall.sof < overall.max_irating);
+
+        // Equal ratings give that rating as the SOF
+        assert_eq!(Strength::from_iratings(&[2000, 2000]).unwrap().sof, 2000);
+
+        assert_eq!(stats.class(2708).unwrap().cars, 2);
+        assert_eq!(stats.licenses[0], (LicenseClass::Rookie, 1));
+    }
+}
@@ -0,0 +1,248 @@
+use serde::Serialize;
+use std::f64::consts::PI;
+
+#[cfg(feature = "telemetry")]
+use crate::ibt::{IbtError, IBT};
+#[cfg(feature = "telemetry")]
+use std::convert::TryInto;
+
+///
+/// Series
+///
+/// Time series of one channel, with filters which each return a new series
+/// so they can be chained. Times are in seconds and must be increasing.
+///
+/// # Examples
+///
+/// Longitudinal acceleration from a noisy speed trace:
+///
+/// ```
+/// use iracing::filters::Series;
+///
+/// let speed = Series::from_points((0..600).map(|i| {
+///     let t = i as f64 / 60.0;
+///     (t, 20.0 + 3.0 * t + if i % 2 == 0 { 0.2 } else { -0.2 })
+/// }));
+///
+/// let accel = speed.low_pass(2.0).derivative();
+/// assert!((accel.values[300] - 3.0).abs() < 0.1);
+/// ```
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct Series {
+    pub times: Vec<f64>,
+    pub values: Vec<f64>,
+}
+
+///
+/// First order low-pass filter for values arriving one at a time.
+///
+/// # Examples
+///
+/// ```
+/// use iracing::filters::LowPass;
+///
+/// let mut filter = LowPass::new(1.0);
+/// let smoothed = filter.update(1.0 / 60.0, 10.0);
+/// assert_eq!(smoothed, 10.0);
+/// ```
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct LowPass {
+    cutoff: f64,
+    value: Option<f64>,
+}
+
+impl LowPass {
+    /// A filter passing frequencies below `cutoff` Hz.
+    pub fn new(cutoff: f64) -> Self {
+        LowPass {
+            cutoff,
+            value: None,
+        }
+    }
+
+    ///
+    /// Filter a value arriving `dt` seconds after the previous one. The first
+    /// value passes through unchanged; NaN values are ignored.
+    pub fn update(&mut self, dt: f64, value: f64) -> f64 {
+        if value.is_nan() {
+            return self.value.unwrap_or(value);
+        }
+
+        let filtered = match self.value {
+            Some(previous) if dt > 0.0 => {
+                let rc = 1.0 / (2.0 * PI * self.cutoff);
+                previous + (value - previous) * (dt / (rc + dt))
+            }
+            Some(previous) => previous,
+            None => value,
+        };
+
+        self.value = Some(filtered);
+        filtered
+    }
+
+    /// The latest filtered value.
+    pub fn value(&self) -> Option<f64> {
+        self.value
+    }
+
+    pub fn reset(&mut self) {
+        self.value = None;
+    }
+}
+
+impl Series {
+    pub fn new(times: Vec<f64>, values: Vec<f64>) -> Self {
+        assert_eq!(
+            times.len(),
+            values.len(),
+            "Times and values differ in length"
+        );
+        Series { times, values }
+    }
+
+    /// A series from (time, value) points, such as `History::series`.
+    pub fn from_points<I: IntoIterator<Item = (f64, f64)>>(points: I) -> Self {
+        let (times, values) = points.into_iter().unzip();
+        Series { times, values }
+    }
+
+    ///
+    /// A float or double channel of a telemetry file, timed by `SessionTime`.
+    #[cfg(feature = "telemetry")]
+    pub fn from_ibt(ibt: &IBT, channel: &str) -> Result<Self, IbtError> {
+        let mut series = Series::default();
+        for sample in ibt.samples() {
+            let sample = sample?;
+            let time = sample
+                .get("SessionTime")
+                .ok()
+                .and_then(|v| v.try_into().ok());
+            let value = sample.get(channel).ok().and_then(|v| v.try_into().ok());
+
+            if let (Some(time), Some(value)) = (time, value) {
+                series.times.push(time);
+                series.values.push(value);
+            }
+        }
+        Ok(series)
+    }
+
+    pub fn len(&self) -> usize {
+        self.values.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.values.is_empty()
+    }
+
+    /// (time, value) points.
+    pub fn points(&self) -> impl Iterator<Item = (f64, f64)> + '_ {
+        self.times.iter().copied().zip(self.values.iter().copied())
+    }
+
+    ///
+    /// Centred moving average over `window` points. Near the ends the window
+    /// shrinks to the points available.
+    pub fn moving_average(&self, window: usize) -> Self {
+        let half = window.max(1) / 2;
+        let values = (0..self.len())
+            .map(|i| {
+                let slice = &self.values[i.saturating_sub(half)..(i + half + 1).min(self.len())];
+                slice.iter().sum::<f64>() / slice.len() as f64
+            })
+            .collect();
+
+        self.with_values(values)
+    }
+
+    ///
+    /// First order low-pass filter passing frequencies below `cutoff` Hz, run
+    /// forwards then backwards so the result isn't delayed.
+    pub fn low_pass(&self, cutoff: f64) -> Self {
+        let mut values = self.values.clone();
+        let mut dts: Vec<f64> = self.times.windows(2).map(|w| w[1] - w[0]).collect();
+        dts.insert(0, 0.0);
+
+        let mut forward = LowPass::new(cutoff);
+        for (v, dt) in values.iter_mut().zip(dts.iter()) {
+            *v = forward.update(*dt, *v);
+        }
+
+        let mut backward = LowPass::new(cutoff);
+        let mut next_dt = 0.0;
+        for (v, dt) in values.iter_mut().zip(dts.iter()).rev() {
+            *v = backward.update(next_dt, *v);
+            next_dt = *dt;
+        }
+
+        self.with_values(values)
+    }
+
+    ///
+    /// Rate of change per second, by central differences (one-sided at the
+    /// ends).
+    pub fn derivative(&self) -> Self {
+        let n = self.len();
+        let values = (0..n)
+            .map(|i| {
+                let (a, b) = (i.saturating_sub(1), (i + 1).min(n - 1));
+                let dt = self.times[b] - self.times[a];
+                if dt > 0.0 {
+                    (self.values[b] - self.values[a]) / dt
+                } else {
+                    0.0
+                }
+            })
+            .collect();
+
+        self.with_values(values)
+    }
+
+    /// Multiply every value, e.g. to convert units.
+    pub fn scale(&self, factor: f64) -> Self {
+        self.map(|v| v * factor)
+    }
+
+    /// Apply a function to every value.
+    pub fn map<F: Fn(f64) -> f64>(&self, f: F) -> Self {
+        self.with_values(self.values.iter().map(|v| f(*v)).collect())
+    }
+
+    fn with_values(&self, values: Vec<f64>) -> Self {
+        Series {
+            times: self.times.clone(),
+            values,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn filter_series() {
+        let series = Series::from_points((0..100).map(|i| (i as f64 * 0.1, (i % 2) as f64)));
+
+        // Alternating noise averages out
+        let average = series.moving_average(3);
+        assert!((average.values[50] - 2.0 / 3.0).abs() < 1e-9);
+        assert_eq!(average.values[0], 0.5);
+
+        let smoothed = series.low_pass(0.1);
+        assert!(smoothed.values[20..80]
+            .iter()
+            .all(|v| (v - 0.5).abs() < 0.15));
+        assert_eq!(smoothed.times, series.times);
+
+        let ramp = Series::new(vec![0.0, 1.0, 2.0, 4.0], vec![0.0, 2.0, 4.0, 8.0]);
+        assert_eq!(ramp.derivative().values, vec![2.0, 2.0, 2.0, 2.0]);
+        assert_eq!(ramp.scale(0.5).values[3], 4.0);
+
+        let mut filter = LowPass::new(1.0);
+        filter.update(0.0, 1.0);
+        assert_eq!(filter.update(0.1, f64::NAN), 1.0);
+        assert!(filter.update(0.1, 0.0) < 1.0);
+    }
+}
@@ -0,0 +1,205 @@
+use crate::simulation::Simulation;
+use serde::{Deserialize, Serialize};
+use std::time::{Duration, Instant};
+
+///
+/// Connection status of a source.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum SourceStatus {
+    /// Not checked yet
+    Unknown,
+    Connected,
+    Disconnected,
+}
+
+///
+/// A simulation being watched, with the identity it is known by.
+#[derive(Debug, Clone)]
+pub struct Source {
+    pub id: String, // e.g. "spotter-2" or "split-1"
+    pub simulation: Simulation,
+    pub status: SourceStatus,
+    pub last_checked: Option<Instant>,
+}
+
+///
+/// A change in a source's connection status.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum FleetEvent {
+    Connected(String),
+    Disconnected(String),
+}
+
+///
+/// Fleet
+///
+/// Manages several simulations at once, such as each spotter's rig or each
+/// split of a league race, keeping track of which are running.
+///
+/// # Examples
+///
+/// ```no_run
+/// use iracing::fleet::{Fleet, FleetEvent};
+///
+/// let mut fleet = Fleet::new();
+/// fleet.add("split-1", "192.168.5.125");
+/// fleet.add("split-2", "192.168.5.126");
+///
+/// for event in fleet.poll() {
+///     if let FleetEvent::Disconnected(id) = event {
+///         println!("Lost {}", id);
+///     }
+/// }
+/// ```
+#[derive(Debug, Clone)]
+pub struct Fleet {
+    sources: Vec<Source>,
+    timeout: Duration,
+}
+
+impl Default for Fleet {
+    fn default() -> Self {
+        Fleet {
+            sources: Vec::new(),
+            timeout: Simulation::STATUS_TIMEOUT,
+        }
+    }
+}
+
+impl Fleet {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    ///
+    /// How long to wait on a source before counting it disconnected.
+    /// `Simulation::STATUS_TIMEOUT` by default.
+    pub fn with_timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = timeout;
+        self
+    }
+
+    ///
+    /// Add a source, replacing any existing source with the same id.
+    pub fn add(&mut self, id: &str, host: &str) {
+        self.remove(id);
+        self.sources.push(Source {
+            id: id.to_owned(),
+            simulation: Simulation {
+                host: host.to_owned(),
+            },
+            status: SourceStatus::Unknown,
+            last_checked: None,
+        });
+    }
+
+    /// Remove a source, returning it if it existed.
+    pub fn remove(&mut self, id: &str) -> Option<Source> {
+        let idx = self.sources.iter().position(|s| s.id == id)?;
+        Some(self.sources.remove(idx))
+    }
+
+    /// All sources, in the order they were added.
+    pub fn sources(&self) -> &[Source] {
+        &self.sources
+    }
+
+    /// A source by id.
+    pub fn get(&self, id: &str) -> Option<&Source> {
+        self.sources.iter().find(|s| s.id == id)
+    }
+
+    /// Sources currently connected.
+    pub fn connected(&self) -> impl Iterator<Item = &Source> {
+        self.sources
+            .iter()
+            .filter(|s| s.status == SourceStatus::Connected)
+    }
+
+    ///
+    /// Check the status of every source, returning any changes.
+    ///
+    /// Each check is a blocking request to the source's sim status port,
+    /// taking up to the timeout for a source that doesn't answer.
+    pub fn poll(&mut self) -> Vec<FleetEvent> {
+        let mut events = Vec::new();
+
+        for source in self.sources.iter_mut() {
+            let status = if source.simulation.check_status_timeout(self.timeout) {
+                SourceStatus::Connected
+            } else {
+                SourceStatus::Disconnected
+            };
+            source.last_checked = Some(Instant::now());
+
+            if status != source.status {
+                match status {
+                    SourceStatus::Connected => {
+                        events.push(FleetEvent::Connected(source.id.clone()))
+                    }
+                    SourceStatus::Disconnected if source.status == SourceStatus::Connected => {
+                        events.push(FleetEvent::Disconnected(source.id.clone()))
+                    }
+                    _ => {}
+                }
+                source.status = status;
+            }
+        }
+
+        events
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::{Read, Write};
+    use std::net::TcpListener;
+    use std::thread;
+
+    #[test]
+    fn poll_sources() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap().to_string();
+
+        thread::spawn(move || {
+            let (mut stream, _) = listener.accept().unwrap();
+            let mut buf = [0; 512];
+            let _ = stream.read(&mut buf);
+            let _ = stream.write_all(b"HTTP/1.1 200 OK\r\n\r\nrunning:1");
+        });
+
+        let mut fleet = Fleet::new();
+        fleet.add("rig-1", &addr);
+        fleet.add("rig-2", "127.0.0.1:1");
+
+        assert_eq!(
+            fleet.poll(),
+            vec![FleetEvent::Connected(String::from("rig-1"))]
+        );
+        assert_eq!(
+            fleet.get("rig-2").unwrap().status,
+            SourceStatus::Disconnected
+        );
+        assert_eq!(fleet.connected().count(), 1);
+    }
+
+    #[test]
+    fn unresponsive_source() {
+        // Accepts connections but never answers
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap().to_string();
+
+        let mut fleet = Fleet::new().with_timeout(Duration::from_millis(100));
+        fleet.add("rig-1", &addr);
+
+        let start = Instant::now();
+        assert!(fleet.poll().is_empty());
+        assert!(start.elapsed() < Duration::from_secs(2));
+        assert_eq!(
+            fleet.get("rig-1").unwrap().status,
+            SourceStatus::Disconnected
+        );
+        drop(listener);
+    }
+}
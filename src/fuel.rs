@@ -0,0 +1,306 @@
+use crate::strategy::FuelModel;
+use serde::{Deserialize, Serialize};
+
+#[cfg(feature = "telemetry")]
+use crate::ibt::IBT;
+#[cfg(feature = "telemetry")]
+use crate::telemetry::Sample;
+#[cfg(feature = "telemetry")]
+use std::convert::TryInto;
+#[cfg(feature = "telemetry")]
+use std::error::Error;
+
+const CSV_HEADER: &str =
+    "Lap,Lap Time,Fuel Used,Fuel Remaining,Refueled,Energy Deployed,Average Speed,Position";
+
+///
+/// Fuel Sample
+///
+/// Fuel and energy state of the player's car at a point in time.
+#[derive(Debug, Copy, Clone, Default)]
+pub struct FuelSample {
+    pub session_time: f64, // Seconds since session start
+    pub lap: i32,
+    pub fuel_level: f32,         // FuelLevel - fuel in the car (l)
+    pub speed: f32,              // Speed - ground speed (m/s)
+    pub position: i32,           // PlayerCarPosition - overall position, 0 if unclassified
+    pub mguk_power: Option<f32>, // PowerMGU_K - MGU-K power, positive when deploying (W)
+}
+
+///
+/// Fuel and energy used over a single lap.
+#[derive(Debug, Copy, Clone, PartialEq, Serialize, Deserialize)]
+pub struct LapConsumption {
+    pub lap: i32,
+    pub lap_time: f64,        // From the lap's first sample to the next lap's (s)
+    pub fuel_used: f32,       // Fuel burnt, excluding any added (l)
+    pub fuel_remaining: f32,  // Fuel at the end of the lap (l)
+    pub refueled: bool,       // Fuel was added during the lap
+    pub energy_deployed: f64, // Energy deployed by the MGU-K (J)
+    pub average_speed: f32,   // Mean ground speed (m/s)
+    pub position: i32,        // Position at the end of the lap
+}
+
+///
+/// Fuel Report
+///
+/// Builds a per-lap table of fuel used, energy deployed, average speed and
+/// position, from live samples or a telemetry file. Laps where fuel was added
+/// still report only the fuel burnt.
+///
+/// # Examples
+///
+/// ```
+/// use iracing::fuel::{FuelReport, FuelSample};
+///
+/// let mut report = FuelReport::new();
+///
+/// for (i, lap) in [1, 1, 2].iter().enumerate() {
+///     report.update(&FuelSample {
+///         session_time: i as f64 * 50.0,
+///         lap: *lap,
+///         fuel_level: 60.0 - i as f32 * 1.5,
+///         ..Default::default()
+///     });
+/// }
+///
+/// assert_eq!(report.laps()[0].fuel_used, 1.5);
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct FuelReport {
+    last: Option<FuelSample>,
+    current: Option<LapConsumption>,
+    lap_start: f64,
+    speed_total: f64,
+    samples: u32,
+    laps: Vec<LapConsumption>,
+}
+
+impl FuelSample {
+    ///
+    /// Read a fuel sample from a telemetry sample.
+    #[cfg(feature = "telemetry")]
+    pub fn from_sample(sample: &Sample) -> Result<Self, Box<dyn Error>> {
+        let mguk_power: Option<f32> = if sample.has("PowerMGU_K") {
+            Some(sample.get("PowerMGU_K")?.try_into()?)
+        } else {
+            None
+        };
+
+        let position: i32 = if sample.has("PlayerCarPosition") {
+            sample.get("PlayerCarPosition")?.try_into()?
+        } else {
+            0
+        };
+
+        Ok(FuelSample {
+            session_time: sample.get("SessionTime")?.try_into()?,
+            lap: sample.get("Lap")?.try_into()?,
+            fuel_level: sample.get("FuelLevel")?.try_into()?,
+            speed: sample.get("Speed")?.try_into()?,
+            position,
+            mguk_power,
+        })
+    }
+}
+
+impl FuelReport {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    ///
+    /// Build a report from every sample of a telemetry file.
+    #[cfg(feature = "telemetry")]
+    pub fn from_ibt(ibt: &IBT) -> Result<Self, Box<dyn Error>> {
+        let mut report = FuelReport::new();
+        for sample in ibt.samples() {
+            report.update(&FuelSample::from_sample(&sample?)?);
+        }
+        Ok(report)
+    }
+
+    ///
+    /// Add a sample, returning the previous lap's consumption when a new lap
+    /// starts.
+    pub fn update(&mut self, sample: &FuelSample) -> Option<LapConsumption> {
+        let completed = match self.current {
+            Some(mut current) if current.lap != sample.lap => {
+                // The lap ran until this sample, not the one before it
+                current.lap_time = sample.session_time - self.lap_start;
+                self.laps.push(current);
+                self.current = None;
+                Some(current)
+            }
+            _ => None,
+        };
+
+        if self.current.is_none() {
+            self.lap_start = sample.session_time;
+            self.speed_total = 0.0;
+            self.samples = 0;
+        }
+        let lap = self.current.get_or_insert(LapConsumption {
+            lap: sample.lap,
+            lap_time: 0.0,
+            fuel_used: 0.0,
+            fuel_remaining: sample.fuel_level,
+            refueled: false,
+            energy_deployed: 0.0,
+            average_speed: 0.0,
+            position: sample.position,
+        });
+
+        if let Some(last) = self.last.filter(|l| l.session_time <= sample.session_time) {
+            let burnt = last.fuel_level - sample.fuel_level;
+            if burnt >= 0.0 {
+                lap.fuel_used += burnt;
+            } else {
+                lap.refueled = true;
+            }
+
+            // Integrate MGU-K deployment over the interval
+            if let (Some(power), Some(previous)) = (sample.mguk_power, last.mguk_power) {
+                let energy =
+                    (power + previous) as f64 / 2.0 * (sample.session_time - last.session_time);
+                lap.energy_deployed += energy.max(0.0);
+            }
+        }
+
+        self.speed_total += sample.speed as f64;
+        self.samples += 1;
+
+        lap.lap_time = sample.session_time - self.lap_start;
+        lap.fuel_remaining = sample.fuel_level;
+        lap.average_speed = (self.speed_total / self.samples as f64) as f32;
+        lap.position = sample.position;
+
+        self.last = Some(*sample);
+        completed
+    }
+
+    /// Completed laps, in order.
+    pub fn laps(&self) -> &[LapConsumption] {
+        &self.laps
+    }
+
+    /// The lap in progress.
+    pub fn current(&self) -> Option<&LapConsumption> {
+        self.current.as_ref()
+    }
+
+    ///
+    /// Average fuel used per lap over the last `laps` completed laps, skipping
+    /// laps where fuel was added.
+    pub fn average_per_lap(&self, laps: usize) -> Option<f32> {
+        let recent: Vec<f32> = self
+            .laps
+            .iter()
+            .rev()
+            .filter(|l| !l.refueled)
+            .take(laps)
+            .map(|l| l.fuel_used)
+            .collect();
+
+        if recent.is_empty() {
+            None
+        } else {
+            Some(recent.iter().sum::<f32>() / recent.len() as f32)
+        }
+    }
+
+    ///
+    /// Fuel model for the strategy planner, from the current fuel level and
+    /// the average of the last `laps` laps.
+    pub fn fuel_model(&self, laps: usize, capacity: f32, fill_rate: f32) -> Option<FuelModel> {
+        Some(FuelModel {
+            fuel: self.last?.fuel_level,
+            per_lap: self.average_per_lap(laps)?,
+            capacity,
+            fill_rate,
+        })
+    }
+
+    ///
+    /// Serialize the completed laps as JSON.
+    pub fn to_json(&self) -> serde_json::Result<String> {
+        serde_json::to_string_pretty(&self.laps)
+    }
+
+    ///
+    /// Format the completed laps as CSV, with a header row.
+    pub fn to_csv(&self) -> String {
+        let mut out = String::from(CSV_HEADER);
+        out.push('\n');
+
+        for l in self.laps.iter() {
+            out.push_str(&format!(
+                "{},{:.3},{:.3},{:.3},{},{:.0},{:.2},{}\n",
+                l.lap,
+                l.lap_time,
+                l.fuel_used,
+                l.fuel_remaining,
+                l.refueled,
+                l.energy_deployed,
+                l.average_speed,
+                l.position
+            ));
+        }
+
+        out
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn lap_consumption() {
+        let mut report = FuelReport::new();
+        let mut completed = Vec::new();
+
+        for i in 0..300 {
+            let lap = 1 + i / 100;
+            let mut fuel_level = 60.0 - i as f32 * 0.03;
+            if lap == 3 && i >= 250 {
+                // Refuel part way through lap 3
+                fuel_level += 20.0;
+            }
+
+            let sample = FuelSample {
+                session_time: i as f64,
+                lap,
+                fuel_level,
+                speed: 50.0,
+                position: 4 - lap,
+                mguk_power: Some(100_000.0),
+            };
+            completed.extend(report.update(&sample));
+        }
+
+        assert_eq!(completed.len(), 2);
+        let first = report.laps()[0];
+        assert_eq!(first.lap_time, 100.0);
+        assert!((first.fuel_used - 2.97).abs() < 1e-3);
+        assert_eq!(first.energy_deployed, 9_900_000.0);
+        assert_eq!((first.average_speed, first.position), (50.0, 3));
+
+        // The refuel isn't counted as fuel used
+        report.update(&FuelSample {
+            session_time: 301.0,
+            lap: 4,
+            ..Default::default()
+        });
+        let refuel = report.laps()[2];
+        assert!(refuel.refueled);
+        assert!((refuel.fuel_used - 2.97).abs() < 1e-3);
+        assert!((report.average_per_lap(5).unwrap() - 2.985).abs() < 1e-3);
+
+        let csv = report.to_csv();
+        assert!(csv.starts_with(CSV_HEADER));
+        assert_eq!(csv.lines().count(), 4);
+        assert!(report.to_json().unwrap().contains("\"refueled\": true"));
+        assert!(report.fuel_model(5, 100.0, 2.5).is_some());
+    }
+}
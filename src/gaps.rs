@@ -0,0 +1,259 @@
+use crate::validity;
+use serde::{Deserialize, Serialize};
+use std::fmt;
+
+#[cfg(feature = "telemetry")]
+use crate::telemetry::Sample;
+#[cfg(feature = "telemetry")]
+use std::convert::TryInto;
+#[cfg(feature = "telemetry")]
+use std::error::Error;
+
+///
+/// Gap Sample
+///
+/// Per-car track positions at a point in time, indexed by car index.
+#[derive(Debug, Clone, Default)]
+pub struct GapSample {
+    pub session_time: f64,      // Seconds since session start
+    pub laps: Vec<i32>,         // CarIdxLap
+    pub lap_dist_pct: Vec<f32>, // CarIdxLapDistPct - -1 when the car isn't on track
+    pub est_time: Vec<f32>, // CarIdxEstTime - estimated time to reach the current location on track (s)
+}
+
+///
+/// Direction and rate a gap is changing at.
+#[derive(Debug, Copy, Clone, PartialEq, Serialize, Deserialize)]
+pub struct GapTrend {
+    pub gap: f32,                   // Current gap (s)
+    pub closing: f32, // Rate the gap is shrinking at (s/lap), negative if it is growing
+    pub laps_to_catch: Option<f32>, // Laps until the gap closes at the current rate
+}
+
+#[derive(Debug, Clone)]
+struct WatchedPair {
+    ahead: usize,
+    behind: usize,
+    gap: Option<f32>,
+    last_lap: i32,
+    laps: Vec<(i32, f32)>, // Gap each time the car behind starts a lap
+}
+
+///
+/// Gap Tracker
+///
+/// Records the gap between selected pairs of cars once per lap, and reports
+/// whether each gap is closing or growing.
+///
+/// # Examples
+///
+/// ```
+/// use iracing::gaps::{GapSample, GapTracker};
+///
+/// let mut gaps = GapTracker::new(92.5);
+/// gaps.watch(3, 1);
+///
+/// gaps.update(&GapSample::default());
+///
+/// if let Some(trend) = gaps.trend(3, 1) {
+///     println!("{}", trend);
+/// }
+/// ```
+#[derive(Debug, Clone)]
+pub struct GapTracker {
+    lap_time: f32,
+    window: usize,
+    pairs: Vec<WatchedPair>,
+}
+
+impl GapSample {
+    ///
+    /// Read a gap sample from a telemetry sample.
+    #[cfg(feature = "telemetry")]
+    pub fn from_sample(sample: &Sample) -> Result<Self, Box<dyn Error>> {
+        Ok(GapSample {
+            session_time: sample.get("SessionTime")?.try_into()?,
+            laps: sample.get("CarIdxLap")?.try_into()?,
+            lap_dist_pct: sample.get("CarIdxLapDistPct")?.try_into()?,
+            est_time: sample.get("CarIdxEstTime")?.try_into()?,
+        })
+    }
+}
+
+impl fmt::Display for GapTrend {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.closing > 0.0 {
+            write!(f, "closing at {:.1}s/lap", self.closing)?;
+        } else if self.closing < 0.0 {
+            write!(f, "dropping back at {:.1}s/lap", -self.closing)?;
+        } else {
+            write!(f, "holding at {:.1}s", self.gap)?;
+        }
+
+        if let Some(laps) = self.laps_to_catch {
+            write!(f, ", catch in {} laps", laps.ceil())?;
+        }
+
+        Ok(())
+    }
+}
+
+impl GapTracker {
+    ///
+    /// Create a gap tracker for a track with the given estimated lap time (s).
+    pub fn new(lap_time: f32) -> Self {
+        GapTracker {
+            lap_time,
+            window: 5,
+            pairs: Vec::new(),
+        }
+    }
+
+    /// Number of recent laps used to work out trends.
+    pub fn with_window(mut self, laps: usize) -> Self {
+        self.window = laps.max(2);
+        self
+    }
+
+    ///
+    /// Start tracking the gap from one car to a car ahead of it.
+    pub fn watch(&mut self, ahead: usize, behind: usize) {
+        if self.pair(ahead, behind).is_none() {
+            self.pairs.push(WatchedPair {
+                ahead,
+                behind,
+                gap: None,
+                last_lap: -1,
+                laps: Vec::new(),
+            });
+        }
+    }
+
+    /// Stop tracking a pair of cars.
+    pub fn unwatch(&mut self, ahead: usize, behind: usize) {
+        self.pairs
+            .retain(|p| !(p.ahead == ahead && p.behind == behind));
+    }
+
+    ///
+    /// Update with a new sample.
+    pub fn update(&mut self, sample: &GapSample) {
+        let lap_time = self.lap_time;
+
+        for pair in self.pairs.iter_mut() {
+            pair.gap = gap(sample, lap_time, pair.ahead, pair.behind);
+
+            let lap = sample.laps.get(pair.behind).copied().unwrap_or(-1);
+            if lap > pair.last_lap {
+                if let (Some(gap), true) = (pair.gap, pair.last_lap >= 0) {
+                    pair.laps.push((lap, gap));
+                }
+                pair.last_lap = lap;
+            }
+        }
+    }
+
+    /// Current gap between two cars (s).
+    pub fn gap(&self, ahead: usize, behind: usize) -> Option<f32> {
+        self.pair(ahead, behind)?.gap
+    }
+
+    ///
+    /// Gap between two cars each time the car behind started a lap, as (lap, gap).
+    pub fn history(&self, ahead: usize, behind: usize) -> &[(i32, f32)] {
+        self.pair(ahead, behind)
+            .map(|p| p.laps.as_slice())
+            .unwrap_or(&[])
+    }
+
+    ///
+    /// Trend of the gap between two cars over recent laps.
+    ///
+    /// Computed as the least-squares slope of the gap over the last laps in the
+    /// window. Returns None when fewer than two laps have been recorded.
+    pub fn trend(&self, ahead: usize, behind: usize) -> Option<GapTrend> {
+        let pair = self.pair(ahead, behind)?;
+        let gap = pair.gap?;
+        let points = &pair.laps[pair.laps.len().saturating_sub(self.window)..];
+
+        if points.len() < 2 {
+            return None;
+        }
+
+        let n = points.len() as f32;
+        let mean_lap = points.iter().map(|(l, _)| *l as f32).sum::<f32>() / n;
+        let mean_gap = points.iter().map(|(_, g)| g).sum::<f32>() / n;
+
+        let (num, den) = points.iter().fold((0.0, 0.0), |(num, den), (l, g)| {
+            let dl = *l as f32 - mean_lap;
+            (num + dl * (g - mean_gap), den + dl * dl)
+        });
+
+        let closing = if den == 0.0 { 0.0 } else { -num / den };
+
+        Some(GapTrend {
+            gap,
+            closing,
+            laps_to_catch: if closing > 0.0 {
+                Some(gap / closing)
+            } else {
+                None
+            },
+        })
+    }
+
+    fn pair(&self, ahead: usize, behind: usize) -> Option<&WatchedPair> {
+        self.pairs
+            .iter()
+            .find(|p| p.ahead == ahead && p.behind == behind)
+    }
+}
+
+/// Time for the car behind to reach the position of the car ahead (s).
+fn gap(sample: &GapSample, lap_time: f32, ahead: usize, behind: usize) -> Option<f32> {
+    let pct = |idx: usize| {
+        sample
+            .lap_dist_pct
+            .get(idx)
+            .copied()
+            .and_then(validity::lap_dist_pct)
+    };
+    pct(ahead)?;
+    pct(behind)?;
+
+    let lap = |idx: usize| sample.laps.get(idx).copied().unwrap_or(0);
+    let est = |idx: usize| sample.est_time.get(idx).copied().unwrap_or(0.0);
+
+    Some(est(ahead) - est(behind) + (lap(ahead) - lap(behind)) as f32 * lap_time)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn gap_trend() {
+        let mut gaps = GapTracker::new(100.0);
+        gaps.watch(1, 2);
+
+        // Car 2 is 0.3s a lap quicker, starting 3s behind
+        for lap in 0..6 {
+            let t = lap as f32 * 100.0;
+            let behind = 3.0 - 0.3 * lap as f32;
+            gaps.update(&GapSample {
+                session_time: t as f64,
+                laps: vec![0, 10 + lap, 10 + lap],
+                lap_dist_pct: vec![-1.0, 0.03, 0.0],
+                est_time: vec![0.0, behind, 0.0],
+            });
+        }
+
+        assert_eq!(gaps.history(1, 2).len(), 5);
+
+        let trend = gaps.trend(1, 2).unwrap();
+        assert!((trend.gap - 1.5).abs() < 1e-3);
+        assert!((trend.closing - 0.3).abs() < 1e-3);
+        assert!((trend.laps_to_catch.unwrap() - 5.0).abs() < 1e-3);
+        assert_eq!(trend.to_string(), "closing at 0.3s/lap, catch in 5 laps");
+    }
+}
@@ -0,0 +1,403 @@
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+#[cfg(feature = "telemetry")]
+use crate::telemetry::Sample;
+#[cfg(feature = "telemetry")]
+use std::convert::TryInto;
+#[cfg(feature = "telemetry")]
+use std::error::Error;
+
+/// Clutch engagement above this counts as fully engaged
+const CLUTCH_ENGAGED: f32 = 0.95;
+
+/// Clutch engagement below this counts as the pedal pressed to the floor
+const CLUTCH_PRESSED: f32 = 0.1;
+
+/// Throttle held above this through an upshift makes it a flat shift
+const FLAT_THROTTLE: f32 = 0.9;
+
+/// Below this the car counts as stationary for a launch (m/s)
+const LAUNCH_SPEED: f32 = 0.5;
+
+///
+/// Gearbox Sample
+///
+/// The player's gear and drivetrain inputs at a point in time.
+#[derive(Debug, Copy, Clone, Default)]
+pub struct GearboxSample {
+    pub session_time: f64, // SessionTime
+    pub lap: i32,          // Lap
+    pub gear: i32,         // Gear - -1 reverse, 0 neutral
+    pub rpm: f32,          // RPM - engine speed (rev/min)
+    pub speed: f32,        // Speed - ground speed (m/s)
+    pub throttle: f32,     // Throttle - 0.0 to 1.0
+    pub clutch: f32,       // Clutch - 1.0 when fully engaged
+}
+
+///
+/// A launch from standstill.
+#[derive(Debug, Copy, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Launch {
+    pub lap: i32,
+    pub session_time: f64,   // When the clutch started to come up
+    pub rpm: f32,            // Engine speed as the clutch started to come up
+    pub clutch_release: f64, // Time from the clutch starting to come up to fully engaged (s)
+}
+
+///
+/// A change between forward gears.
+#[derive(Debug, Copy, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Shift {
+    pub lap: i32,
+    pub session_time: f64, // When drive was interrupted
+    pub from: i32,
+    pub to: i32,
+    pub rpm: f32,      // Engine speed before the shift
+    pub duration: f64, // Time without drive, from the clutch or leaving gear to drive again (s)
+    pub clutch: bool,  // Whether the clutch was used
+    pub flat: bool,    // Upshift with the throttle held and no clutch
+}
+
+///
+/// Something the gearbox analyzer saw.
+#[derive(Debug, Copy, Clone, PartialEq, Serialize, Deserialize)]
+pub enum GearboxEvent {
+    Launch(Launch),
+    Shift(Shift),
+}
+
+///
+/// Launches and shifts on a single lap.
+#[derive(Debug, Copy, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct LapGearbox {
+    pub lap: i32,
+    pub upshifts: u32,
+    pub downshifts: u32,
+    pub flat_shifts: u32,
+    pub upshift_time: Option<f64>,   // Average upshift duration (s)
+    pub downshift_time: Option<f64>, // Average downshift duration (s)
+    pub launch: Option<Launch>,
+}
+
+///
+/// Gearbox Analyzer
+///
+/// Derives launches and gear shifts from the player's gear, clutch and
+/// throttle, for driver development tools.
+///
+/// A launch is armed while the car is stationary in gear with the clutch
+/// pressed, and measured from the clutch starting to come up until it's
+/// fully engaged. A shift runs from drive being interrupted, by the clutch
+/// being pressed or the car leaving gear, until the car is in its next gear
+/// with the clutch engaged. Shifts to or from reverse are ignored.
+///
+/// # Examples
+///
+/// ```
+/// use iracing::gearbox::{GearboxAnalyzer, GearboxEvent, GearboxSample};
+///
+/// let mut analyzer = GearboxAnalyzer::new();
+///
+/// if let Some(GearboxEvent::Shift(shift)) = analyzer.update(&GearboxSample::default()) {
+///     println!("{} to {} in {:.0}ms", shift.from, shift.to, shift.duration * 1000.0);
+/// }
+///
+/// for lap in analyzer.laps() {
+///     println!("Lap {}: {} flat shifts", lap.lap, lap.flat_shifts);
+/// }
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct GearboxAnalyzer {
+    last: Option<GearboxSample>,
+    armed: bool,
+    release: Option<(f64, f32)>,
+    interrupted: Option<f64>,
+    pending: Option<PendingShift>,
+    events: Vec<GearboxEvent>,
+}
+
+/// A shift waiting for the clutch to engage in the new gear.
+#[derive(Debug, Copy, Clone)]
+struct PendingShift {
+    lap: i32,
+    start: f64,
+    from: i32,
+    to: Option<i32>,
+    rpm: f32,
+    min_throttle: f32,
+    clutch: bool,
+}
+
+impl GearboxSample {
+    ///
+    /// Read a gearbox sample from a telemetry sample.
+    #[cfg(feature = "telemetry")]
+    pub fn from_sample(sample: &Sample) -> Result<Self, Box<dyn Error>> {
+        Ok(GearboxSample {
+            session_time: sample.get("SessionTime")?.try_into()?,
+            lap: sample.get("Lap")?.try_into()?,
+            gear: sample.get("Gear")?.try_into()?,
+            rpm: sample.get("RPM")?.try_into()?,
+            speed: sample.get("Speed")?.try_into()?,
+            throttle: sample.get("Throttle")?.try_into()?,
+            clutch: sample.get("Clutch")?.try_into()?,
+        })
+    }
+}
+
+impl GearboxAnalyzer {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    ///
+    /// Update with the latest sample, returning a launch or shift once it's
+    /// complete.
+    pub fn update(&mut self, sample: &GearboxSample) -> Option<GearboxEvent> {
+        let last = match self.last.replace(*sample) {
+            Some(last) if sample.session_time >= last.session_time => last,
+            // First sample, or time went backwards
+            _ => {
+                self.armed = false;
+                self.release = None;
+                self.interrupted = None;
+                self.pending = None;
+                return None;
+            }
+        };
+
+        let launch = self.update_launch(sample);
+        let shift = self.update_shift(&last, sample);
+
+        let event = launch.or(shift);
+        self.events.extend(event);
+        event
+    }
+
+    /// Every launch and shift so far.
+    pub fn events(&self) -> &[GearboxEvent] {
+        &self.events
+    }
+
+    /// Launches and shifts grouped by lap, in lap order.
+    pub fn laps(&self) -> Vec<LapGearbox> {
+        let mut laps: BTreeMap<i32, (LapGearbox, Vec<f64>, Vec<f64>)> = BTreeMap::new();
+
+        for event in self.events.iter() {
+            let lap = match event {
+                GearboxEvent::Launch(launch) => launch.lap,
+                GearboxEvent::Shift(shift) => shift.lap,
+            };
+            let (totals, up, down) = laps.entry(lap).or_insert_with(|| {
+                let totals = LapGearbox {
+                    lap,
+                    ..Default::default()
+                };
+                (totals, Vec::new(), Vec::new())
+            });
+
+            match event {
+                GearboxEvent::Launch(launch) => totals.launch = Some(*launch),
+                GearboxEvent::Shift(shift) if shift.to > shift.from => {
+                    totals.upshifts += 1;
+                    totals.flat_shifts += shift.flat as u32;
+                    up.push(shift.duration);
+                }
+                GearboxEvent::Shift(shift) => {
+                    totals.downshifts += 1;
+                    down.push(shift.duration);
+                }
+            }
+        }
+
+        let mean = |times: &[f64]| {
+            if times.is_empty() {
+                None
+            } else {
+                Some(times.iter().sum::<f64>() / times.len() as f64)
+            }
+        };
+
+        laps.into_values()
+            .map(|(mut totals, up, down)| {
+                totals.upshift_time = mean(&up);
+                totals.downshift_time = mean(&down);
+                totals
+            })
+            .collect()
+    }
+
+    fn update_launch(&mut self, sample: &GearboxSample) -> Option<GearboxEvent> {
+        if sample.gear < 1 || (self.release.is_none() && sample.speed > LAUNCH_SPEED) {
+            self.armed = false;
+            self.release = None;
+            return None;
+        }
+
+        if sample.clutch < CLUTCH_PRESSED {
+            // Pressed again before the car got going
+            self.armed = sample.speed <= LAUNCH_SPEED;
+            self.release = None;
+        } else if self.armed && self.release.is_none() {
+            self.release = Some((sample.session_time, sample.rpm));
+        }
+
+        match self.release {
+            Some((start, rpm)) if sample.clutch >= CLUTCH_ENGAGED => {
+                self.armed = false;
+                self.release = None;
+                Some(GearboxEvent::Launch(Launch {
+                    lap: sample.lap,
+                    session_time: start,
+                    rpm,
+                    clutch_release: sample.session_time - start,
+                }))
+            }
+            _ => None,
+        }
+    }
+
+    fn update_shift(
+        &mut self,
+        last: &GearboxSample,
+        sample: &GearboxSample,
+    ) -> Option<GearboxEvent> {
+        let engaged = sample.clutch >= CLUTCH_ENGAGED;
+
+        if sample.gear != last.gear && self.pending.is_none() && last.gear > 0 {
+            self.pending = Some(PendingShift {
+                lap: last.lap,
+                start: self.interrupted.unwrap_or(last.session_time),
+                from: last.gear,
+                to: None,
+                rpm: last.rpm,
+                min_throttle: last.throttle,
+                clutch: self.interrupted.is_some(),
+            });
+        }
+
+        let mut pending = match self.pending.take() {
+            Some(pending) => pending,
+            None => {
+                // Pressing the clutch interrupts drive until it's released
+                self.interrupted = match self.interrupted {
+                    None if !engaged && sample.gear > 0 => Some(last.session_time),
+                    Some(_) if engaged => None,
+                    interrupted => interrupted,
+                };
+                return None;
+            }
+        };
+
+        pending.min_throttle = pending.min_throttle.min(sample.throttle);
+        pending.clutch |= !engaged;
+        if sample.gear != 0 {
+            pending.to = Some(sample.gear);
+        }
+
+        let to = match pending.to {
+            Some(to) if engaged && sample.gear == to => to,
+            _ => {
+                self.pending = Some(pending);
+                return None;
+            }
+        };
+
+        self.interrupted = None;
+        if to < 1 || to == pending.from {
+            return None;
+        }
+
+        Some(GearboxEvent::Shift(Shift {
+            lap: pending.lap,
+            session_time: pending.start,
+            from: pending.from,
+            to,
+            rpm: pending.rpm,
+            duration: sample.session_time - pending.start,
+            clutch: pending.clutch,
+            flat: to > pending.from && !pending.clutch && pending.min_throttle >= FLAT_THROTTLE,
+        }))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn launches_and_shifts() {
+        let mut analyzer = GearboxAnalyzer::new();
+        let mut time = 0.0;
+        let mut step = |analyzer: &mut GearboxAnalyzer, lap, gear, speed, throttle, clutch| {
+            time += 0.05;
+            analyzer.update(&GearboxSample {
+                session_time: time,
+                lap,
+                gear,
+                rpm: 6000.0,
+                speed,
+                throttle,
+                clutch,
+            })
+        };
+
+        // Launch, letting the clutch out over 0.15s
+        step(&mut analyzer, 0, 1, 0.0, 0.6, 0.0);
+        step(&mut analyzer, 0, 1, 0.0, 0.6, 0.0);
+        step(&mut analyzer, 0, 1, 0.2, 0.8, 0.3);
+        step(&mut analyzer, 0, 1, 1.0, 1.0, 0.7);
+        let launch = step(&mut analyzer, 0, 1, 2.0, 1.0, 1.0);
+        match launch {
+            Some(GearboxEvent::Launch(launch)) => {
+                assert!((launch.clutch_release - 0.1).abs() < 1e-9);
+                assert_eq!(launch.rpm, 6000.0);
+            }
+            other => panic!("Expected a launch, got {:?}", other),
+        }
+
+        // Flat upshift on a sequential box
+        step(&mut analyzer, 0, 1, 20.0, 1.0, 1.0);
+        let flat = step(&mut analyzer, 0, 2, 25.0, 1.0, 1.0);
+        assert!(matches!(
+            flat,
+            Some(GearboxEvent::Shift(Shift {
+                from: 1,
+                to: 2,
+                flat: true,
+                ..
+            }))
+        ));
+
+        // Lifted, clutched upshift through neutral
+        step(&mut analyzer, 1, 2, 30.0, 1.0, 1.0);
+        step(&mut analyzer, 1, 2, 30.0, 0.0, 0.0);
+        step(&mut analyzer, 1, 0, 30.0, 0.0, 0.0);
+        step(&mut analyzer, 1, 3, 30.0, 0.0, 0.0);
+        step(&mut analyzer, 1, 3, 30.0, 0.5, 0.5);
+        let shift = match step(&mut analyzer, 1, 3, 30.0, 1.0, 1.0) {
+            Some(GearboxEvent::Shift(shift)) => shift,
+            other => panic!("Expected a shift, got {:?}", other),
+        };
+        assert!(shift.clutch && !shift.flat);
+        assert!((shift.duration - 0.25).abs() < 1e-9);
+
+        // A blip of the clutch without changing gear isn't a shift
+        assert_eq!(step(&mut analyzer, 1, 3, 30.0, 1.0, 0.5), None);
+        assert_eq!(step(&mut analyzer, 1, 3, 30.0, 1.0, 1.0), None);
+
+        // Downshift
+        step(&mut analyzer, 1, 3, 30.0, 0.0, 1.0);
+        step(&mut analyzer, 1, 2, 25.0, 0.0, 1.0);
+
+        let laps = analyzer.laps();
+        assert_eq!(laps.len(), 2);
+        assert_eq!(laps[0].upshifts, 1);
+        assert_eq!(laps[0].flat_shifts, 1);
+        assert!(laps[0].launch.is_some());
+        assert_eq!(laps[1].upshifts, 1);
+        assert_eq!(laps[1].downshifts, 1);
+        assert!((laps[1].upshift_time.unwrap() - 0.25).abs() < 1e-9);
+    }
+}
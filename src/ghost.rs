@@ -0,0 +1,423 @@
+use serde::{Deserialize, Serialize};
+
+#[cfg(feature = "telemetry")]
+use crate::telemetry::Sample;
+#[cfg(feature = "telemetry")]
+use std::convert::TryInto;
+#[cfg(feature = "telemetry")]
+use std::error::Error;
+
+/// A lap must start within and end beyond this distance of the line (m)
+const LAP_MARGIN: f32 = 50.0;
+
+///
+/// Ghost Sample
+///
+/// Position, speed and inputs at a point in time.
+#[derive(Debug, Copy, Clone, Default)]
+pub struct GhostSample {
+    pub session_time: f64,            // SessionTime - seconds
+    pub lap: i32,                     // Lap
+    pub lap_dist: f32,                // LapDist - metres from the line
+    pub speed: f32,                   // Speed - m/s
+    pub throttle: f32,                // Throttle - 0.0 to 1.0
+    pub brake: f32,                   // Brake - 0.0 to 1.0
+    pub steering: f32,                // SteeringWheelAngle - rad
+    pub position: Option<(f64, f64)>, // Lat, Lon - IBT files only
+}
+
+///
+/// The reference lap at one point around the track.
+#[derive(Debug, Copy, Clone, PartialEq, Serialize, Deserialize)]
+pub struct GhostPoint {
+    pub time: f32,                    // Time since the line (s)
+    pub speed: f32,                   // m/s
+    pub throttle: f32,                // 0.0 to 1.0
+    pub brake: f32,                   // 0.0 to 1.0
+    pub steering: f32,                // rad
+    pub position: Option<(f64, f64)>, // Latitude and longitude, the racing line
+}
+
+///
+/// Ghost
+///
+/// A reference lap, such as a downloaded hotlap, to compare against. Points
+/// are evenly spaced by distance, so point `i` is `i * spacing` metres from
+/// the line, which keeps the format compact and lookups cheap.
+///
+/// # Examples
+///
+/// ```
+/// use iracing::ghost::{Ghost, GhostSample};
+///
+/// // A lap of a 1km track at a steady 50 m/s
+/// let samples: Vec<GhostSample> = (0..=200)
+///     .map(|i| GhostSample {
+///         session_time: i as f64 * 0.1,
+///         lap_dist: i as f32 * 5.0,
+///         speed: 50.0,
+///         ..Default::default()
+///     })
+///     .collect();
+///
+/// let ghost = Ghost::from_lap(&samples, 10.0).unwrap();
+/// assert!((ghost.time_at(500.0).unwrap() - 10.0).abs() < 1e-3);
+///
+/// let json = ghost.to_json().unwrap();
+/// assert_eq!(Ghost::from_json(&json).unwrap(), ghost);
+/// ```
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Ghost {
+    pub track: String,
+    pub car: String,
+    pub driver: String,
+    pub lap_time: f64,
+    pub spacing: f32, // Distance between points (m)
+    pub points: Vec<GhostPoint>,
+}
+
+///
+/// How a live lap compares with a ghost at one point.
+#[derive(Debug, Copy, Clone, PartialEq, Serialize, Deserialize)]
+pub struct GhostDelta {
+    pub distance: f32,    // Metres from the line
+    pub gap: f64,         // Seconds behind the ghost, negative when ahead
+    pub speed_delta: f32, // Speed over the ghost's (m/s)
+}
+
+///
+/// Ghost Recorder
+///
+/// Splits a stint into laps and turns each complete lap into a `Ghost`,
+/// keeping the fastest. Out laps and laps cut short by a tow are left out.
+///
+/// # Examples
+///
+/// ```
+/// use iracing::ghost::{GhostRecorder, GhostSample};
+///
+/// let mut recorder = GhostRecorder::new(5.0);
+/// recorder.update(&GhostSample { lap: 1, ..Default::default() });
+///
+/// if let Some(best) = recorder.best() {
+///     println!("{}", best.to_json().unwrap());
+/// }
+/// ```
+#[derive(Debug, Clone)]
+pub struct GhostRecorder {
+    spacing: f32,
+    lap: Vec<GhostSample>,
+    best: Option<Ghost>,
+}
+
+///
+/// Ghost Comparator
+///
+/// Reports the live gap to a ghost as the car goes around. Comparison
+/// starts once the car crosses the line, as the gap needs the time the lap
+/// started.
+///
+/// # Examples
+///
+/// ```no_run
+/// use iracing::ghost::{Ghost, GhostComparator, GhostSample};
+///
+/// let json = std::fs::read_to_string("hotlap.json").unwrap();
+/// let mut comparator = GhostComparator::new(Ghost::from_json(&json).unwrap());
+///
+/// # let sample = GhostSample::default();
+/// if let Some(delta) = comparator.update(&sample) {
+///     println!("{:+.2}s to the ghost", delta.gap);
+/// }
+/// ```
+#[derive(Debug, Clone)]
+pub struct GhostComparator {
+    ghost: Ghost,
+    lap: Option<(i32, Option<f64>)>, // Lap and the session time it started
+}
+
+impl GhostSample {
+    ///
+    /// Read a ghost sample from a telemetry sample.
+    #[cfg(feature = "telemetry")]
+    pub fn from_sample(sample: &Sample) -> Result<Self, Box<dyn Error>> {
+        let position: Option<(f64, f64)> = if sample.has("Lat") && sample.has("Lon") {
+            Some((
+                sample.get("Lat")?.try_into()?,
+                sample.get("Lon")?.try_into()?,
+            ))
+        } else {
+            None
+        };
+
+        Ok(GhostSample {
+            session_time: sample.get("SessionTime")?.try_into()?,
+            lap: sample.get("Lap")?.try_into()?,
+            lap_dist: sample.get("LapDist")?.try_into()?,
+            speed: sample.get("Speed")?.try_into()?,
+            throttle: sample.get("Throttle")?.try_into()?,
+            brake: sample.get("Brake")?.try_into()?,
+            steering: sample.get("SteeringWheelAngle")?.try_into()?,
+            position,
+        })
+    }
+}
+
+impl Ghost {
+    ///
+    /// Build a ghost from the samples of a single lap, resampled every
+    /// `spacing` metres. Returns None if there are too few samples to cover
+    /// the lap or the spacing isn't positive.
+    pub fn from_lap(samples: &[GhostSample], spacing: f32) -> Option<Ghost> {
+        // Ignore samples going backwards, such as a spin
+        let mut lap: Vec<&GhostSample> = Vec::with_capacity(samples.len());
+        for sample in samples.iter() {
+            if lap
+                .last()
+                .is_none_or(|last| sample.lap_dist > last.lap_dist)
+            {
+                lap.push(sample);
+            }
+        }
+
+        let (first, last) = (lap.first()?, lap.last()?);
+        if spacing <= 0.0 || lap.len() < 2 {
+            return None;
+        }
+
+        let start = first.session_time;
+        let mut points = Vec::new();
+        let mut next = 1;
+
+        // Points start at the line, before the first sample
+        let mut distance = 0.0;
+        while distance <= last.lap_dist {
+            while lap[next].lap_dist < distance {
+                next += 1;
+            }
+            let (a, b) = (lap[next - 1], lap[next]);
+            let t = ((distance - a.lap_dist) / (b.lap_dist - a.lap_dist)).max(0.0);
+            let lerp = |x: f32, y: f32| x + (y - x) * t;
+
+            points.push(GhostPoint {
+                time: (a.session_time + (b.session_time - a.session_time) * t as f64 - start)
+                    .max(0.0) as f32,
+                speed: lerp(a.speed, b.speed),
+                throttle: lerp(a.throttle, b.throttle),
+                brake: lerp(a.brake, b.brake),
+                steering: lerp(a.steering, b.steering),
+                position: match (a.position, b.position) {
+                    (Some(p), Some(q)) => {
+                        Some((p.0 + (q.0 - p.0) * t as f64, p.1 + (q.1 - p.1) * t as f64))
+                    }
+                    _ => None,
+                },
+            });
+
+            distance = points.len() as f32 * spacing;
+        }
+
+        Some(Ghost {
+            track: String::new(),
+            car: String::new(),
+            driver: String::new(),
+            lap_time: last.session_time - start,
+            spacing,
+            points,
+        })
+    }
+
+    pub fn with_details(mut self, track: &str, car: &str, driver: &str) -> Self {
+        self.track = track.to_owned();
+        self.car = car.to_owned();
+        self.driver = driver.to_owned();
+        self
+    }
+
+    /// Distance of the last point from the line (m).
+    pub fn length(&self) -> f32 {
+        self.points.len().saturating_sub(1) as f32 * self.spacing
+    }
+
+    ///
+    /// The ghost at a distance from the line, interpolated between points.
+    /// Distances beyond the last point give the last point.
+    pub fn at(&self, distance: f32) -> Option<GhostPoint> {
+        let index = (distance.max(0.0) / self.spacing).min(self.points.len() as f32 - 1.0);
+        let (i, t) = (index.floor() as usize, index.fract());
+        let (a, b) = (self.points.get(i)?, self.points.get(i + 1));
+
+        Some(match b {
+            Some(b) => {
+                let lerp = |x: f32, y: f32| x + (y - x) * t;
+                GhostPoint {
+                    time: lerp(a.time, b.time),
+                    speed: lerp(a.speed, b.speed),
+                    throttle: lerp(a.throttle, b.throttle),
+                    brake: lerp(a.brake, b.brake),
+                    steering: lerp(a.steering, b.steering),
+                    position: a.position,
+                }
+            }
+            None => *a,
+        })
+    }
+
+    /// Time the ghost took to reach a distance from the line (s).
+    pub fn time_at(&self, distance: f32) -> Option<f32> {
+        self.at(distance).map(|p| p.time)
+    }
+
+    ///
+    /// Serialize the ghost as JSON.
+    pub fn to_json(&self) -> serde_json::Result<String> {
+        serde_json::to_string(self)
+    }
+
+    pub fn from_json(json: &str) -> serde_json::Result<Ghost> {
+        serde_json::from_str(json)
+    }
+}
+
+impl GhostRecorder {
+    /// Record ghosts with points every `spacing` metres.
+    pub fn new(spacing: f32) -> Self {
+        GhostRecorder {
+            spacing,
+            lap: Vec::new(),
+            best: None,
+        }
+    }
+
+    ///
+    /// Add a sample, returning the ghost of the previous lap when a new lap
+    /// starts and the previous lap was complete.
+    pub fn update(&mut self, sample: &GhostSample) -> Option<Ghost> {
+        let completed = match self.lap.first() {
+            Some(first) if first.lap != sample.lap => {
+                let ghost = self.finish(sample);
+                self.lap.clear();
+                ghost
+            }
+            _ => None,
+        };
+        self.lap.push(*sample);
+
+        if let Some(ghost) = &completed {
+            if self
+                .best
+                .as_ref()
+                .is_none_or(|b| ghost.lap_time < b.lap_time)
+            {
+                self.best = Some(ghost.clone());
+            }
+        }
+        completed
+    }
+
+    /// The fastest complete lap so far.
+    pub fn best(&self) -> Option<&Ghost> {
+        self.best.as_ref()
+    }
+
+    fn finish(&self, next: &GhostSample) -> Option<Ghost> {
+        let first = self.lap.first()?;
+        if first.lap_dist > LAP_MARGIN || next.lap_dist > LAP_MARGIN {
+            return None;
+        }
+
+        // The lap ends where the next one starts
+        let mut ghost = Ghost::from_lap(&self.lap, self.spacing)?;
+        ghost.lap_time = next.session_time - first.session_time;
+        Some(ghost)
+    }
+}
+
+impl GhostComparator {
+    pub fn new(ghost: Ghost) -> Self {
+        GhostComparator { ghost, lap: None }
+    }
+
+    pub fn ghost(&self) -> &Ghost {
+        &self.ghost
+    }
+
+    ///
+    /// Add a sample, returning the gap to the ghost once the car has crossed
+    /// the line.
+    pub fn update(&mut self, sample: &GhostSample) -> Option<GhostDelta> {
+        let start = match self.lap {
+            Some((lap, start)) if lap == sample.lap => start,
+            previous => {
+                // The first lap seen started before the comparator did
+                let start = previous.map(|_| sample.session_time);
+                self.lap = Some((sample.lap, start));
+                start
+            }
+        }?;
+
+        let ghost = self.ghost.at(sample.lap_dist)?;
+        Some(GhostDelta {
+            distance: sample.lap_dist,
+            gap: sample.session_time - start - ghost.time as f64,
+            speed_delta: sample.speed - ghost.speed,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn record_and_compare_ghosts() {
+        let mut recorder = GhostRecorder::new(10.0);
+        let mut ghosts = Vec::new();
+        let mut time = 0.0;
+
+        // An out lap from the pits, then laps of a 1km track at 50 then 40 m/s
+        for (lap, speed) in [(0, 50.0), (1, 50.0), (2, 40.0)] {
+            let start = if lap == 0 { 500 } else { 0 };
+            for d in (start..1000).step_by(5) {
+                let sample = GhostSample {
+                    session_time: time,
+                    lap,
+                    lap_dist: d as f32,
+                    speed,
+                    throttle: 1.0,
+                    ..Default::default()
+                };
+                ghosts.extend(recorder.update(&sample));
+                time += 5.0 / speed as f64;
+            }
+        }
+        recorder.update(&GhostSample {
+            session_time: time,
+            lap: 3,
+            ..Default::default()
+        });
+
+        assert_eq!(ghosts.len(), 1);
+        let best = recorder.best().unwrap().clone();
+        assert!((best.lap_time - 20.0).abs() < 1e-6);
+        assert_eq!(best.points.len(), 100);
+        assert!((best.length() - 990.0).abs() < 1e-3);
+        assert!((best.time_at(250.0).unwrap() - 5.0).abs() < 1e-3);
+
+        // Compare a lap at 40 m/s against the 50 m/s ghost
+        let mut comparator = GhostComparator::new(best);
+        let at = |lap, d: f32| GhostSample {
+            session_time: 100.0 + d as f64 / 40.0,
+            lap,
+            lap_dist: d,
+            speed: 40.0,
+            ..Default::default()
+        };
+        assert_eq!(comparator.update(&at(4, 900.0)), None);
+        comparator.update(&at(5, 0.0));
+
+        let delta = comparator.update(&at(5, 500.0)).unwrap();
+        assert!((delta.gap - 2.5).abs() < 1e-3);
+        assert!((delta.speed_delta + 10.0).abs() < 1e-6);
+    }
+}
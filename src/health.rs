@@ -0,0 +1,239 @@
+use serde::{Deserialize, Serialize};
+use std::time::{Duration, Instant};
+
+///
+/// Health of a telemetry connection at a point in time.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Health {
+    pub connected: bool,            // Sim reports itself connected
+    pub last_tick: i32,             // Newest tick in the data buffers
+    pub tick_age: Duration,         // Time since the tick last advanced
+    pub session_info_version: i32,  // Current session info version
+    pub session_info_age: Duration, // Time since the session info last changed
+}
+
+///
+/// Change in health reported by a `Watchdog`.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum HealthEvent {
+    /// No new ticks have arrived for at least the watchdog's timeout
+    Stalled {
+        last_tick: i32,
+        stalled_for: Duration,
+    },
+
+    /// Ticks are arriving again after a stall
+    Resumed { last_tick: i32 },
+
+    /// The sim stopped reporting itself connected
+    Disconnected,
+
+    /// The sim is reporting itself connected again
+    Connected,
+}
+
+///
+/// Heartbeat
+///
+/// Remembers when the tick count and session info version of a connection
+/// last changed, so their age can be reported.
+///
+/// # Examples
+///
+/// ```
+/// use iracing::health::Heartbeat;
+/// use std::time::{Duration, Instant};
+///
+/// let start = Instant::now();
+/// let mut heartbeat = Heartbeat::new();
+///
+/// heartbeat.update_at(start, true, 100, 1);
+/// let health = heartbeat.update_at(start + Duration::from_secs(2), true, 100, 1);
+///
+/// assert_eq!(health.tick_age, Duration::from_secs(2));
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct Heartbeat {
+    last_tick: Option<(i32, Instant)>,
+    session_info: Option<(i32, Instant)>,
+}
+
+///
+/// Watchdog
+///
+/// Reports a `Stalled` event when no ticks arrive for a given time, so an
+/// app can tell a sim which has stopped sending data from one which is simply
+/// sat in a menu or paused with its data still flowing.
+///
+/// # Examples
+///
+/// ```
+/// use iracing::health::{Heartbeat, HealthEvent, Watchdog};
+/// use std::time::{Duration, Instant};
+///
+/// let start = Instant::now();
+/// let mut heartbeat = Heartbeat::new();
+/// let mut watchdog = Watchdog::new(Duration::from_millis(500));
+///
+/// // Live, `Connection::health()` keeps the heartbeat
+/// watchdog.check(&heartbeat.update_at(start, true, 100, 1));
+/// let health = heartbeat.update_at(start + Duration::from_secs(1), true, 100, 1);
+///
+/// assert!(matches!(watchdog.check(&health), Some(HealthEvent::Stalled { .. })));
+/// ```
+#[derive(Debug, Clone)]
+pub struct Watchdog {
+    timeout: Duration,
+    stalled: bool,
+    connected: bool,
+}
+
+impl Health {
+    /// True if no tick has arrived for at least `timeout`.
+    pub fn is_stalled(&self, timeout: Duration) -> bool {
+        self.tick_age >= timeout
+    }
+}
+
+impl Heartbeat {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Record the current state of a connection.
+    pub fn update(&mut self, connected: bool, tick: i32, session_info_version: i32) -> Health {
+        self.update_at(Instant::now(), connected, tick, session_info_version)
+    }
+
+    /// Record the state of a connection as seen at `now`.
+    pub fn update_at(
+        &mut self,
+        now: Instant,
+        connected: bool,
+        tick: i32,
+        session_info_version: i32,
+    ) -> Health {
+        let tick_changed = match self.last_tick {
+            Some((last, since)) if last == tick => since,
+            _ => now,
+        };
+        let info_changed = match self.session_info {
+            Some((last, since)) if last == session_info_version => since,
+            _ => now,
+        };
+
+        self.last_tick = Some((tick, tick_changed));
+        self.session_info = Some((session_info_version, info_changed));
+
+        Health {
+            connected,
+            last_tick: tick,
+            tick_age: now.saturating_duration_since(tick_changed),
+            session_info_version,
+            session_info_age: now.saturating_duration_since(info_changed),
+        }
+    }
+}
+
+impl Watchdog {
+    pub fn new(timeout: Duration) -> Self {
+        Watchdog {
+            timeout,
+            stalled: false,
+            connected: true,
+        }
+    }
+
+    pub fn timeout(&self) -> Duration {
+        self.timeout
+    }
+
+    pub fn is_stalled(&self) -> bool {
+        self.stalled
+    }
+
+    ///
+    /// Check the latest health of a connection.
+    ///
+    /// Returns an event when the connection stalls, resumes, disconnects or
+    /// reconnects. Events are only reported once per change.
+    pub fn check(&mut self, health: &Health) -> Option<HealthEvent> {
+        if health.connected != self.connected {
+            self.connected = health.connected;
+            self.stalled = false;
+
+            return Some(if health.connected {
+                HealthEvent::Connected
+            } else {
+                HealthEvent::Disconnected
+            });
+        }
+
+        let stalled = health.connected && health.is_stalled(self.timeout);
+        if stalled == self.stalled {
+            return None;
+        }
+
+        self.stalled = stalled;
+        Some(if stalled {
+            HealthEvent::Stalled {
+                last_tick: health.last_tick,
+                stalled_for: health.tick_age,
+            }
+        } else {
+            HealthEvent::Resumed {
+                last_tick: health.last_tick,
+            }
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn stall_and_resume() {
+        let start = Instant::now();
+        let at = |ms: u64| start + Duration::from_millis(ms);
+
+        let mut heartbeat = Heartbeat::new();
+        let mut watchdog = Watchdog::new(Duration::from_millis(500));
+
+        assert_eq!(
+            watchdog.check(&heartbeat.update_at(at(0), true, 1, 1)),
+            None
+        );
+        assert_eq!(
+            watchdog.check(&heartbeat.update_at(at(16), true, 2, 1)),
+            None
+        );
+        assert_eq!(
+            watchdog.check(&heartbeat.update_at(at(400), true, 2, 1)),
+            None
+        );
+
+        let health = heartbeat.update_at(at(600), true, 2, 1);
+        assert_eq!(health.session_info_age, Duration::from_millis(600));
+        assert_eq!(
+            watchdog.check(&health),
+            Some(HealthEvent::Stalled {
+                last_tick: 2,
+                stalled_for: Duration::from_millis(584)
+            })
+        );
+        assert_eq!(
+            watchdog.check(&heartbeat.update_at(at(700), true, 2, 1)),
+            None
+        );
+
+        assert_eq!(
+            watchdog.check(&heartbeat.update_at(at(716), true, 3, 2)),
+            Some(HealthEvent::Resumed { last_tick: 3 })
+        );
+        assert_eq!(
+            watchdog.check(&heartbeat.update_at(at(732), false, 3, 2)),
+            Some(HealthEvent::Disconnected)
+        );
+    }
+}
@@ -0,0 +1,241 @@
+use crate::replay::{Bookmark, BookmarkKind, Bookmarks};
+use serde::{Deserialize, Serialize};
+use std::time::Duration;
+
+#[cfg(all(target_os = "windows", feature = "broadcast"))]
+use crate::broadcast::{Broadcast, BroadcastMessage, VideoCaptureMode};
+
+///
+/// A section of the replay to capture.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Clip {
+    pub session_number: u8,
+    pub start: f64,          // Session time the clip starts (s)
+    pub end: f64,            // Session time the clip ends (s)
+    pub labels: Vec<String>, // Labels of the bookmarks covered by the clip
+}
+
+///
+/// A single step in capturing highlights.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub enum HighlightStep {
+    /// Jump the replay to a session time
+    Seek {
+        session_number: u8,
+        session_time: f64,
+    },
+
+    /// Play the replay at normal speed
+    Play,
+
+    /// Pause the replay
+    Pause,
+
+    StartCapture,
+    EndCapture,
+
+    /// Wait for a period of real time
+    Wait(Duration),
+}
+
+///
+/// Highlights
+///
+/// Turns replay bookmarks into clips and the sequence of replay and video
+/// capture commands needed to record them once the session is over.
+///
+/// Clips start a little before each bookmark and end a little after it;
+/// overlapping clips are merged.
+///
+/// # Examples
+///
+/// ```
+/// use iracing::highlights::Highlights;
+/// use iracing::replay::{BookmarkKind, Bookmarks};
+///
+/// let mut bookmarks = Bookmarks::new();
+/// bookmarks.mark(2, 120.0, "Spin at turn 3", BookmarkKind::Incident);
+///
+/// let highlights = Highlights::new().with_kinds(&[BookmarkKind::Incident, BookmarkKind::Overtake]);
+/// for clip in highlights.clips(&bookmarks) {
+///     println!("{:.1}s - {:.1}s: {}", clip.start, clip.end, clip.labels.join(", "));
+/// }
+/// ```
+#[derive(Debug, Clone)]
+pub struct Highlights {
+    pre_roll: f64,
+    post_roll: f64,
+    settle: Duration,
+    kinds: Option<Vec<BookmarkKind>>,
+}
+
+impl Default for Highlights {
+    fn default() -> Self {
+        Highlights {
+            pre_roll: 5.0,
+            post_roll: 5.0,
+            settle: Duration::from_secs(2),
+            kinds: None,
+        }
+    }
+}
+
+impl Clip {
+    /// Length of the clip (s).
+    pub fn duration(&self) -> f64 {
+        self.end - self.start
+    }
+}
+
+impl Highlights {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Seconds of replay to include before and after each bookmark.
+    pub fn with_padding(mut self, before: f64, after: f64) -> Self {
+        self.pre_roll = before.max(0.0);
+        self.post_roll = after.max(0.0);
+        self
+    }
+
+    ///
+    /// Real time to wait after seeking before capturing, while the replay loads.
+    pub fn with_settle_time(mut self, settle: Duration) -> Self {
+        self.settle = settle;
+        self
+    }
+
+    /// Only capture bookmarks of these kinds. All bookmarks are captured by default.
+    pub fn with_kinds(mut self, kinds: &[BookmarkKind]) -> Self {
+        self.kinds = Some(kinds.to_vec());
+        self
+    }
+
+    fn wanted(&self, bookmark: &Bookmark) -> bool {
+        match &self.kinds {
+            Some(kinds) => kinds.contains(&bookmark.kind),
+            None => true,
+        }
+    }
+
+    ///
+    /// Clips covering the wanted bookmarks, in timeline order.
+    pub fn clips(&self, bookmarks: &Bookmarks) -> Vec<Clip> {
+        let mut clips: Vec<Clip> = Vec::new();
+
+        for bookmark in bookmarks.all().iter().filter(|b| self.wanted(b)) {
+            let start = (bookmark.session_time - self.pre_roll).max(0.0);
+            let end = bookmark.session_time + self.post_roll;
+
+            match clips.last_mut() {
+                Some(last)
+                    if last.session_number == bookmark.session_number && start <= last.end =>
+                {
+                    last.end = last.end.max(end);
+                    last.labels.push(bookmark.label.clone());
+                }
+                _ => clips.push(Clip {
+                    session_number: bookmark.session_number,
+                    start,
+                    end,
+                    labels: vec![bookmark.label.clone()],
+                }),
+            }
+        }
+
+        clips
+    }
+
+    ///
+    /// The steps needed to capture every clip.
+    pub fn steps(&self, bookmarks: &Bookmarks) -> Vec<HighlightStep> {
+        let mut steps = Vec::new();
+
+        for clip in self.clips(bookmarks) {
+            steps.push(HighlightStep::Pause);
+            steps.push(HighlightStep::Seek {
+                session_number: clip.session_number,
+                session_time: clip.start,
+            });
+            steps.push(HighlightStep::Wait(self.settle));
+            steps.push(HighlightStep::StartCapture);
+            steps.push(HighlightStep::Play);
+            steps.push(HighlightStep::Wait(Duration::from_secs_f64(
+                clip.duration(),
+            )));
+            steps.push(HighlightStep::EndCapture);
+        }
+
+        if !steps.is_empty() {
+            steps.push(HighlightStep::Pause);
+        }
+
+        steps
+    }
+
+    ///
+    /// Capture every clip, blocking until done.
+    ///
+    /// Requires the sim to be showing the replay of the session, with video capture configured.
+    #[cfg(all(target_os = "windows", feature = "broadcast"))]
+    pub fn capture(&self, broadcast: &Broadcast, bookmarks: &Bookmarks) {
+        for step in self.steps(bookmarks) {
+            match step {
+                HighlightStep::Seek {
+                    session_number,
+                    session_time,
+                } => broadcast.send_message(BroadcastMessage::ReplaySearchSessionTime(
+                    session_number,
+                    (session_time * 1000.0) as u32,
+                )),
+                HighlightStep::Play => {
+                    broadcast.send_message(BroadcastMessage::ReplaySetPlaySpeed(1, false))
+                }
+                HighlightStep::Pause => {
+                    broadcast.send_message(BroadcastMessage::ReplaySetPlaySpeed(0, false))
+                }
+                HighlightStep::StartCapture => broadcast.send_message(
+                    BroadcastMessage::VideoCapture(VideoCaptureMode::StartCapture),
+                ),
+                HighlightStep::EndCapture => broadcast
+                    .send_message(BroadcastMessage::VideoCapture(VideoCaptureMode::EndCapture)),
+                HighlightStep::Wait(duration) => std::thread::sleep(duration),
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn merge_clips() {
+        let mut bookmarks = Bookmarks::new();
+        bookmarks.mark(2, 100.0, "Contact", BookmarkKind::Incident);
+        bookmarks.mark(2, 107.0, "Pass", BookmarkKind::Overtake);
+        bookmarks.mark(2, 300.0, "Spin", BookmarkKind::Incident);
+        bookmarks.mark(2, 400.0, "Note to self", BookmarkKind::User);
+
+        let highlights = Highlights::new()
+            .with_kinds(&[BookmarkKind::Incident, BookmarkKind::Overtake])
+            .with_settle_time(Duration::from_secs(1));
+
+        let clips = highlights.clips(&bookmarks);
+        assert_eq!(clips.len(), 2);
+        assert_eq!((clips[0].start, clips[0].end), (95.0, 112.0));
+        assert_eq!(clips[0].labels, vec!["Contact", "Pass"]);
+
+        let steps = highlights.steps(&bookmarks);
+        assert_eq!(steps.len(), 15);
+        assert_eq!(
+            steps[1],
+            HighlightStep::Seek {
+                session_number: 2,
+                session_time: 95.0
+            }
+        );
+        assert_eq!(steps[5], HighlightStep::Wait(Duration::from_secs(17)));
+    }
+}
@@ -0,0 +1,296 @@
+use serde::Serialize;
+
+#[cfg(feature = "telemetry")]
+use crate::telemetry::{Sample, Value};
+#[cfg(feature = "telemetry")]
+use std::convert::TryInto;
+#[cfg(feature = "telemetry")]
+use std::error::Error;
+
+///
+/// Retained values of a single channel.
+#[derive(Debug, Clone, Serialize)]
+pub struct Channel {
+    pub name: String,
+    pub values: Vec<f64>, // One value per retained point; NaN where the channel was missing
+}
+
+///
+/// History
+///
+/// Retains selected telemetry channels at a reduced rate for the whole session,
+/// answering queries such as "fuel level 10 laps ago" or "track temperature trend".
+///
+/// # Examples
+///
+/// ```
+/// use iracing::history::History;
+///
+/// let mut history = History::with_interval(1.0).with_channel("FuelLevel");
+///
+/// history.record(0.0, 1, &[("FuelLevel", 60.0)]);
+/// history.record(100.0, 2, &[("FuelLevel", 57.5)]);
+///
+/// assert_eq!(history.per_lap("FuelLevel", 1), Some(-2.5));
+/// ```
+#[derive(Debug, Clone, Serialize)]
+pub struct History {
+    interval: f64,
+    times: Vec<f64>,
+    laps: Vec<i32>,
+    channels: Vec<Channel>,
+}
+
+impl History {
+    ///
+    /// Create a new history, retaining at most one point every `interval` seconds of session time.
+    pub fn with_interval(interval: f64) -> Self {
+        History {
+            interval,
+            times: Vec::new(),
+            laps: Vec::new(),
+            channels: Vec::new(),
+        }
+    }
+
+    ///
+    /// Retain a channel. Points recorded before the channel was added are NaN.
+    pub fn with_channel(mut self, name: &str) -> Self {
+        self.track(name);
+        self
+    }
+
+    /// Retain a channel, if it isn't already retained.
+    pub fn track(&mut self, name: &str) {
+        if self.channel(name).is_none() {
+            self.channels.push(Channel {
+                name: name.to_owned(),
+                values: vec![f64::NAN; self.times.len()],
+            });
+        }
+    }
+
+    /// A retained channel.
+    pub fn channel(&self, name: &str) -> Option<&Channel> {
+        self.channels.iter().find(|c| c.name == name)
+    }
+
+    /// All retained channels.
+    pub fn channels(&self) -> &[Channel] {
+        &self.channels
+    }
+
+    ///
+    /// Record values for the retained channels.
+    ///
+    /// Returns true if the point was retained. Points arriving sooner than the
+    /// configured interval after the last retained point are discarded, and a
+    /// point with an earlier session time than the last (e.g. a new session)
+    /// clears the history.
+    pub fn record(&mut self, session_time: f64, lap: i32, values: &[(&str, f64)]) -> bool {
+        if let Some(&last) = self.times.last() {
+            if session_time < last {
+                self.clear();
+            } else if session_time - last < self.interval {
+                return false;
+            }
+        }
+
+        self.times.push(session_time);
+        self.laps.push(lap);
+
+        for channel in self.channels.iter_mut() {
+            let value = values
+                .iter()
+                .find(|(name, _)| channel.name == *name)
+                .map_or(f64::NAN, |(_, v)| *v);
+            channel.values.push(value);
+        }
+
+        true
+    }
+
+    ///
+    /// Record the retained channels from a telemetry sample.
+    #[cfg(feature = "telemetry")]
+    pub fn record_sample(&mut self, sample: &Sample) -> Result<bool, Box<dyn Error>> {
+        let session_time: f64 = sample.get("SessionTime")?.try_into()?;
+        let lap: i32 = sample.get("Lap")?.try_into()?;
+
+        let mut values = Vec::with_capacity(self.channels.len());
+        for channel in self.channels.iter() {
+            if !sample.has(&channel.name) {
+                continue;
+            }
+
+            let value = match sample.get(&channel.name)? {
+                Value::DOUBLE(v) => v,
+                Value::FLOAT(v) => v as f64,
+                Value::INT(v) => v as f64,
+                Value::BITS(v) => v as f64,
+                Value::BOOL(v) => v as u8 as f64,
+                Value::CHAR(v) => v as f64,
+                _ => continue,
+            };
+            values.push((channel.name.clone(), value));
+        }
+
+        let values: Vec<(&str, f64)> = values.iter().map(|(n, v)| (n.as_str(), *v)).collect();
+        Ok(self.record(session_time, lap, &values))
+    }
+
+    /// Discard all retained points, keeping the channel list.
+    pub fn clear(&mut self) {
+        self.times.clear();
+        self.laps.clear();
+        for channel in self.channels.iter_mut() {
+            channel.values.clear();
+        }
+    }
+
+    /// Number of retained points.
+    pub fn len(&self) -> usize {
+        self.times.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.times.is_empty()
+    }
+
+    /// The lap of the most recent point.
+    pub fn current_lap(&self) -> Option<i32> {
+        self.laps.last().copied()
+    }
+
+    ///
+    /// Retained (session time, value) points of a channel, skipping missing values.
+    pub fn series<'a>(&'a self, name: &str) -> impl Iterator<Item = (f64, f64)> + 'a {
+        let values = self.channel(name).map(|c| &c.values[..]).unwrap_or(&[]);
+
+        self.times
+            .iter()
+            .copied()
+            .zip(values.iter().copied())
+            .filter(|(_, v)| !v.is_nan())
+    }
+
+    /// The latest value of a channel.
+    pub fn latest(&self, name: &str) -> Option<f64> {
+        self.series(name).last().map(|(_, v)| v)
+    }
+
+    ///
+    /// The most recent value of a channel at or before `session_time`.
+    pub fn at(&self, name: &str, session_time: f64) -> Option<f64> {
+        self.series(name)
+            .take_while(|(t, _)| *t <= session_time)
+            .last()
+            .map(|(_, v)| v)
+    }
+
+    ///
+    /// The value of a channel at the start of a lap (the first point recorded on that lap).
+    pub fn at_lap(&self, name: &str, lap: i32) -> Option<f64> {
+        let channel = self.channel(name)?;
+
+        self.laps
+            .iter()
+            .zip(channel.values.iter())
+            .find(|(&l, v)| l == lap && !v.is_nan())
+            .map(|(_, v)| *v)
+    }
+
+    ///
+    /// The value of a channel at the start of the lap `laps` laps before the current lap.
+    pub fn laps_ago(&self, name: &str, laps: i32) -> Option<f64> {
+        self.at_lap(name, self.current_lap()? - laps)
+    }
+
+    ///
+    /// Average change of a channel per lap over the last `laps` complete laps,
+    /// measured between lap starts. E.g. fuel used per lap is `-per_lap("FuelLevel", 5)`.
+    pub fn per_lap(&self, name: &str, laps: i32) -> Option<f64> {
+        if laps <= 0 {
+            return None;
+        }
+
+        let current = self.current_lap()?;
+        let start = self.at_lap(name, current - laps)?;
+        let end = self.at_lap(name, current)?;
+
+        Some((end - start) / laps as f64)
+    }
+
+    ///
+    /// Rate of change of a channel over the last `window` seconds, per minute.
+    ///
+    /// Computed as the least-squares slope of all points in the window.
+    /// Returns None when there are fewer than two points in the window.
+    pub fn trend(&self, name: &str, window: f64) -> Option<f64> {
+        let latest = *self.times.last()?;
+        let points: Vec<(f64, f64)> = self
+            .series(name)
+            .filter(|(t, _)| *t >= latest - window)
+            .collect();
+
+        if points.len() < 2 {
+            return None;
+        }
+
+        let n = points.len() as f64;
+        let mean_t = points.iter().map(|(t, _)| t).sum::<f64>() / n;
+        let mean_v = points.iter().map(|(_, v)| v).sum::<f64>() / n;
+
+        let (num, den) = points.iter().fold((0.0, 0.0), |(num, den), (t, v)| {
+            let dt = t - mean_t;
+            (num + dt * (v - mean_v), den + dt * dt)
+        });
+
+        if den == 0.0 {
+            None
+        } else {
+            Some(num / den * 60.0)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn query_history() {
+        let mut history = History::with_interval(10.0)
+            .with_channel("FuelLevel")
+            .with_channel("TrackTempCrew");
+
+        for i in 0..=120 {
+            let t = i as f64 * 5.0;
+            history.record(
+                t,
+                1 + i / 20,
+                &[
+                    ("FuelLevel", 60.0 - t * 0.03),
+                    ("TrackTempCrew", 30.0 + t / 60.0),
+                ],
+            );
+        }
+
+        assert_eq!(history.len(), 61);
+        assert_eq!(history.current_lap(), Some(7));
+        assert_eq!(history.at_lap("FuelLevel", 2), Some(57.0));
+        assert_eq!(
+            history.laps_ago("FuelLevel", 5),
+            history.at_lap("FuelLevel", 2)
+        );
+        assert!((history.per_lap("FuelLevel", 5).unwrap() + 3.0).abs() < 1e-9);
+        assert!((history.trend("TrackTempCrew", 120.0).unwrap() - 1.0).abs() < 1e-9);
+
+        // Missing values are skipped rather than recorded
+        history.track("AirTemp");
+        assert_eq!(history.latest("AirTemp"), None);
+
+        history.record(0.0, 1, &[]);
+        assert_eq!(history.len(), 1);
+    }
+}
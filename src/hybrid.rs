@@ -0,0 +1,283 @@
+use serde::{Deserialize, Serialize};
+
+#[cfg(feature = "telemetry")]
+use crate::telemetry::Sample;
+#[cfg(feature = "telemetry")]
+use std::convert::TryInto;
+#[cfg(feature = "telemetry")]
+use std::error::Error;
+
+///
+/// Hybrid Sample
+///
+/// Energy deployment and push-to-pass state at a point in time. Each channel
+/// is only present for cars which have the corresponding system.
+#[derive(Debug, Copy, Clone, Default)]
+pub struct HybridSample {
+    pub session_time: f64, // Seconds since session start
+    pub lap: i32,
+    pub p2p_count: Option<i32>, // P2P_Count - push-to-pass activations remaining
+    pub p2p_active: Option<bool>, // P2P_Status - push-to-pass is active
+    pub battery: Option<f32>,   // EnergyERSBatteryPct - battery state of charge, 0.0 to 1.0
+    pub lap_deploy: Option<f32>, // EnergyMGU_KLapDeployPct - fraction of the lap's deployment allowance used
+    pub mguk_power: Option<f32>, // PowerMGU_K - MGU-K power, positive when deploying (W)
+}
+
+///
+/// Energy deployment over a single lap.
+#[derive(Debug, Copy, Clone, PartialEq, Serialize, Deserialize)]
+pub struct LapDeployment {
+    pub lap: i32,
+    pub p2p_activations: u32,
+    pub p2p_time: f64,              // Time with push-to-pass active (s)
+    pub battery_start: Option<f32>, // Battery charge at the start of the lap
+    pub battery_end: Option<f32>,   // Battery charge at the end of the lap
+    pub lap_deploy: Option<f32>,    // Fraction of the lap's deployment allowance used
+    pub deployed: f64,              // Energy deployed by the MGU-K (J)
+    pub recovered: f64,             // Energy recovered by the MGU-K (J)
+}
+
+///
+/// Events reported by the hybrid tracker.
+#[derive(Debug, Copy, Clone, PartialEq, Serialize, Deserialize)]
+pub enum HybridEvent {
+    PushToPassStarted {
+        session_time: f64,
+        remaining: Option<i32>,
+    },
+    PushToPassEnded {
+        session_time: f64,
+        duration: f64,
+    },
+    LapCompleted(LapDeployment),
+}
+
+///
+/// Hybrid Tracker
+///
+/// Tracks hybrid energy deployment and push-to-pass use, summarising each lap.
+///
+/// # Examples
+///
+/// ```
+/// use iracing::hybrid::{HybridEvent, HybridSample, HybridTracker};
+///
+/// let mut tracker = HybridTracker::new();
+///
+/// for event in tracker.update(&HybridSample::default()) {
+///     if let HybridEvent::LapCompleted(lap) = event {
+///         println!("Lap {}: {:.0} kJ deployed", lap.lap, lap.deployed / 1000.0);
+///     }
+/// }
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct HybridTracker {
+    last: Option<HybridSample>,
+    current: Option<LapDeployment>,
+    p2p_started: Option<f64>,
+    laps: Vec<LapDeployment>,
+}
+
+impl HybridSample {
+    ///
+    /// Read a hybrid sample from a telemetry sample.
+    #[cfg(feature = "telemetry")]
+    pub fn from_sample(sample: &Sample) -> Result<Self, Box<dyn Error>> {
+        let p2p_count: Option<i32> = if sample.has("P2P_Count") {
+            Some(sample.get("P2P_Count")?.try_into()?)
+        } else {
+            None
+        };
+
+        let p2p_active: Option<bool> = if sample.has("P2P_Status") {
+            Some(sample.get("P2P_Status")?.into())
+        } else {
+            None
+        };
+
+        let battery: Option<f32> = if sample.has("EnergyERSBatteryPct") {
+            Some(sample.get("EnergyERSBatteryPct")?.try_into()?)
+        } else {
+            None
+        };
+
+        let lap_deploy: Option<f32> = if sample.has("EnergyMGU_KLapDeployPct") {
+            Some(sample.get("EnergyMGU_KLapDeployPct")?.try_into()?)
+        } else {
+            None
+        };
+
+        let mguk_power: Option<f32> = if sample.has("PowerMGU_K") {
+            Some(sample.get("PowerMGU_K")?.try_into()?)
+        } else {
+            None
+        };
+
+        Ok(HybridSample {
+            session_time: sample.get("SessionTime")?.try_into()?,
+            lap: sample.get("Lap")?.try_into()?,
+            p2p_count,
+            p2p_active,
+            battery,
+            lap_deploy,
+            mguk_power,
+        })
+    }
+}
+
+impl LapDeployment {
+    fn new(sample: &HybridSample) -> Self {
+        LapDeployment {
+            lap: sample.lap,
+            p2p_activations: 0,
+            p2p_time: 0.0,
+            battery_start: sample.battery,
+            battery_end: sample.battery,
+            lap_deploy: sample.lap_deploy,
+            deployed: 0.0,
+            recovered: 0.0,
+        }
+    }
+}
+
+impl HybridTracker {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    ///
+    /// Update the tracker with a new sample, returning any events which occurred.
+    pub fn update(&mut self, sample: &HybridSample) -> Vec<HybridEvent> {
+        let mut events = Vec::new();
+        let session_time = sample.session_time;
+
+        if let Some(current) = self.current {
+            if current.lap != sample.lap {
+                self.laps.push(current);
+                events.push(HybridEvent::LapCompleted(current));
+                self.current = None;
+            }
+        }
+        let lap = self
+            .current
+            .get_or_insert_with(|| LapDeployment::new(sample));
+
+        let previous = self.last.filter(|l| l.session_time <= session_time);
+        let dt = previous.map_or(0.0, |l| session_time - l.session_time);
+
+        // Integrate MGU-K power over the interval
+        if let (Some(power), Some(last)) = (sample.mguk_power, previous.and_then(|l| l.mguk_power))
+        {
+            let energy = (power + last) as f64 / 2.0 * dt;
+            if energy > 0.0 {
+                lap.deployed += energy;
+            } else {
+                lap.recovered -= energy;
+            }
+        }
+
+        let active = sample.p2p_active.unwrap_or(false);
+        let was_active = self.p2p_started.is_some();
+        if active {
+            if was_active {
+                lap.p2p_time += dt;
+            } else {
+                lap.p2p_activations += 1;
+                self.p2p_started = Some(session_time);
+                events.push(HybridEvent::PushToPassStarted {
+                    session_time,
+                    remaining: sample.p2p_count,
+                });
+            }
+        } else if let Some(started) = self.p2p_started.take() {
+            lap.p2p_time += dt;
+            events.push(HybridEvent::PushToPassEnded {
+                session_time,
+                duration: session_time - started,
+            });
+        }
+
+        if sample.battery.is_some() {
+            lap.battery_end = sample.battery;
+        }
+        if sample.lap_deploy.is_some() {
+            lap.lap_deploy = sample.lap_deploy;
+        }
+
+        self.last = Some(*sample);
+        events
+    }
+
+    /// Completed laps, in order.
+    pub fn laps(&self) -> &[LapDeployment] {
+        &self.laps
+    }
+
+    /// The lap in progress.
+    pub fn current(&self) -> Option<&LapDeployment> {
+        self.current.as_ref()
+    }
+
+    /// True while push-to-pass is active.
+    pub fn p2p_active(&self) -> bool {
+        self.p2p_started.is_some()
+    }
+
+    /// Push-to-pass activations remaining, if the car has push-to-pass.
+    pub fn p2p_remaining(&self) -> Option<i32> {
+        self.last.and_then(|l| l.p2p_count)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn push_to_pass_and_deployment() {
+        let mut tracker = HybridTracker::new();
+        let mut sample = HybridSample {
+            lap: 1,
+            p2p_count: Some(10),
+            p2p_active: Some(false),
+            battery: Some(0.8),
+            mguk_power: Some(100_000.0),
+            ..HybridSample::default()
+        };
+        tracker.update(&sample);
+
+        sample.session_time = 1.0;
+        sample.p2p_active = Some(true);
+        sample.p2p_count = Some(9);
+        let events = tracker.update(&sample);
+        assert_eq!(
+            events,
+            vec![HybridEvent::PushToPassStarted {
+                session_time: 1.0,
+                remaining: Some(9)
+            }]
+        );
+
+        sample.session_time = 3.0;
+        sample.p2p_active = Some(false);
+        sample.mguk_power = Some(-100_000.0);
+        sample.battery = Some(0.7);
+        tracker.update(&sample);
+
+        sample.session_time = 4.0;
+        sample.lap = 2;
+        let events = tracker.update(&sample);
+        let lap = match events[..] {
+            [HybridEvent::LapCompleted(lap)] => lap,
+            _ => panic!("{:?}", events),
+        };
+
+        assert_eq!(lap.p2p_activations, 1);
+        assert_eq!(lap.p2p_time, 2.0);
+        assert_eq!(lap.deployed, 100_000.0);
+        assert_eq!(lap.recovered, 0.0);
+        assert_eq!(lap.battery_start, Some(0.8));
+        assert_eq!(lap.battery_end, Some(0.7));
+        assert_eq!(tracker.p2p_remaining(), Some(9));
+    }
+}
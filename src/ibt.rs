@@ -0,0 +1,745 @@
+use crate::session::SessionDetails;
+use crate::telemetry::{value_names, Header, Sample};
+use crate::yaml::{decode, detect, Encoding, Repair};
+use serde::{Deserialize, Serialize};
+use std::convert::TryFrom;
+use std::error::Error;
+use std::fmt;
+use std::fs::File;
+use std::io::{self, Read, Write};
+use std::ops::RangeInclusive;
+use std::path::Path;
+
+/// Size of the telemetry header at the start of a file.
+const HEADER_SIZE: usize = 112;
+
+/// Size of the disk header following the telemetry header.
+const DISK_HEADER_SIZE: usize = 32;
+
+/// Size of each variable header.
+const VAR_HEADER_SIZE: usize = 144;
+
+///
+/// Details of a recording, stored after the telemetry header in an IBT file.
+#[derive(Debug, Copy, Clone, PartialEq, Serialize, Deserialize)]
+pub struct DiskHeader {
+    pub start_date: i64,   // Unix time the recording started
+    pub start_time: f64,   // Session time the recording started (s)
+    pub end_time: f64,     // Session time the recording ended (s)
+    pub lap_count: i32,    // Laps in the recording
+    pub record_count: i32, // Samples in the recording
+}
+
+#[derive(Debug)]
+pub enum IbtError {
+    /// The file ends before the data it describes
+    Truncated(String),
+
+    /// The headers describe an impossible layout
+    InvalidHeader(String),
+
+    /// Files can't be merged, e.g. they record different variables
+    Incompatible(String),
+    IO(io::Error),
+}
+
+impl fmt::Display for IbtError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Truncated(e) => write!(f, "Truncated IBT file: {}", e),
+            Self::InvalidHeader(e) => write!(f, "Invalid IBT header: {}", e),
+            Self::Incompatible(e) => write!(f, "Incompatible IBT files: {}", e),
+            Self::IO(e) => write!(f, "IO Error: {}", e),
+        }
+    }
+}
+
+impl Error for IbtError {}
+
+impl From<io::Error> for IbtError {
+    fn from(e: io::Error) -> Self {
+        Self::IO(e)
+    }
+}
+
+///
+/// IBT
+///
+/// Telemetry file written by the sim to the `telemetry` folder, holding the
+/// session info and a sample of every channel at each tick while recording.
+///
+/// The whole file is read into memory and every offset is checked before use,
+/// so malformed files are reported as errors.
+///
+/// # Examples
+///
+/// ```
+/// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+/// use iracing::ibt::IBT;
+/// use std::convert::TryInto;
+///
+/// let ibt = IBT::open("./telemetry.ibt")?;
+/// println!("{} samples at {}Hz", ibt.len(), ibt.header().tick_rate);
+///
+/// for sample in ibt.samples() {
+///     let speed: f32 = sample?.get("Speed")?.try_into()?;
+/// }
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Debug, Clone)]
+pub struct IBT {
+    data: Vec<u8>,
+    header: Header,
+    disk_header: DiskHeader,
+    records: usize,
+}
+
+impl IBT {
+    pub fn open<P: AsRef<Path>>(path: P) -> Result<IBT, IbtError> {
+        Self::read_from(&mut File::open(path)?)
+    }
+
+    pub fn read_from<R: Read>(reader: &mut R) -> Result<IBT, IbtError> {
+        let mut data = Vec::new();
+        reader.read_to_end(&mut data)?;
+        Self::from_bytes(data)
+    }
+
+    ///
+    /// Parse a telemetry file held in memory.
+    pub fn from_bytes(data: Vec<u8>) -> Result<IBT, IbtError> {
+        if data.len() < HEADER_SIZE + DISK_HEADER_SIZE {
+            return Err(IbtError::Truncated(String::from(
+                "File is shorter than its headers",
+            )));
+        }
+
+        let header = Header::from_bytes(&data)
+            .ok_or_else(|| IbtError::Truncated(String::from("File is shorter than its header")))?;
+        let disk_header = DiskHeader::from_bytes(&data[HEADER_SIZE..]);
+
+        if header.buffer_length <= 0 {
+            return Err(IbtError::InvalidHeader(String::from(
+                "Empty sample records",
+            )));
+        }
+
+        let session_end = checked_end(header.session_info_offset, header.session_info_length)
+            .ok_or_else(|| IbtError::InvalidHeader(String::from("Invalid session info")))?;
+        if session_end > data.len() {
+            return Err(IbtError::Truncated(String::from(
+                "Session info is out of bounds",
+            )));
+        }
+
+        let vars_end = var_headers_range(&header)
+            .ok_or_else(|| IbtError::InvalidHeader(String::from("Invalid variable headers")))?
+            .end;
+        if vars_end > data.len() {
+            return Err(IbtError::Truncated(String::from(
+                "Variable headers are out of bounds",
+            )));
+        }
+
+        // Files still being written have no record count, and files cut short
+        // hold fewer records than their header says
+        let first = header.buffer_offset(0);
+        let available = data.len().saturating_sub(first) / header.buffer_length as usize;
+        let records = if disk_header.record_count > 0 {
+            available.min(disk_header.record_count as usize)
+        } else {
+            available
+        };
+
+        let ibt = IBT {
+            data,
+            header,
+            disk_header,
+            records,
+        };
+
+        // Check the variable headers once, up front
+        if ibt.records > 0 {
+            ibt.sample(0)?;
+        }
+
+        Ok(ibt)
+    }
+
+    pub fn header(&self) -> &Header {
+        &self.header
+    }
+
+    pub fn disk_header(&self) -> &DiskHeader {
+        &self.disk_header
+    }
+
+    /// Number of samples in the file.
+    pub fn len(&self) -> usize {
+        self.records
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.records == 0
+    }
+
+    ///
+    /// Session info exactly as written, without the NUL padding, for decoding
+    /// by hand.
+    pub fn session_info_bytes(&self) -> &[u8] {
+        let data = checked_end(
+            self.header.session_info_offset,
+            self.header.session_info_length,
+        )
+        .and_then(|end| self.data.get(self.header.session_info_offset as usize..end))
+        .unwrap_or_default();
+        let end = data.iter().position(|b| *b == 0).unwrap_or(data.len());
+        &data[..end]
+    }
+
+    /// Raw session info YAML, decoded as UTF-8 or Windows-1252.
+    pub fn session_info_raw(&self) -> String {
+        decode(self.session_info_bytes()).0
+    }
+
+    /// Encoding the session info was written in.
+    pub fn session_info_encoding(&self) -> Encoding {
+        detect(self.session_info_bytes())
+    }
+
+    pub fn session_info(&self) -> Result<SessionDetails, Box<dyn Error>> {
+        Ok(SessionDetails::parse(&self.session_info_raw())?)
+    }
+
+    /// Session info, with a report of any repairs needed to parse it.
+    pub fn session_info_lenient(&self) -> Result<(SessionDetails, Vec<Repair>), Box<dyn Error>> {
+        Ok(SessionDetails::parse_lenient(&self.session_info_raw())?)
+    }
+
+    ///
+    /// Sample at a given index.
+    pub fn sample(&self, idx: usize) -> Result<Sample, IbtError> {
+        if idx >= self.records {
+            return Err(IbtError::Truncated(format!("No sample {}", idx)));
+        }
+
+        let offset = self.header.buffer_offset(0) + idx * self.header.buffer_length as usize;
+        self.header
+            .sample_from(&self.data, offset, idx as i32)
+            .map_err(IbtError::InvalidHeader)
+    }
+
+    /// Every sample, in order.
+    pub fn samples(&self) -> impl Iterator<Item = Result<Sample, IbtError>> + '_ {
+        (0..self.records).map(move |idx| self.sample(idx))
+    }
+
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> io::Result<()> {
+        self.write_to(&mut File::create(path)?)
+    }
+
+    pub fn write_to<W: Write>(&self, writer: &mut W) -> io::Result<()> {
+        writer.write_all(&self.data)
+    }
+
+    ///
+    /// Write the samples with a session time in `range` to a new file, keeping
+    /// the headers and session info. Returns the number of samples written.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use iracing::ibt::IBT;
+    ///
+    /// let ibt = IBT::open("endurance.ibt").unwrap();
+    /// let laps = ibt.lap_times(12..=14).unwrap();
+    /// ibt.extract(laps, "laps_12_to_14.ibt").unwrap();
+    /// ```
+    pub fn extract<P: AsRef<Path>>(
+        &self,
+        range: RangeInclusive<f64>,
+        path: P,
+    ) -> Result<usize, IbtError> {
+        let window = self.window(range)?;
+        window.save(path)?;
+        Ok(window.len())
+    }
+
+    ///
+    /// The samples with a session time in `range`, as a new file in memory.
+    pub fn window(&self, range: RangeInclusive<f64>) -> Result<IBT, IbtError> {
+        let at = self
+            .var_offset("SessionTime")
+            .ok_or_else(|| IbtError::InvalidHeader(String::from("No SessionTime variable")))?;
+
+        let records: Vec<u8> = self
+            .records()
+            .chunks_exact(self.header.buffer_length as usize)
+            .filter(|record| range.contains(&read_f64(&record[at..])))
+            .flatten()
+            .copied()
+            .collect();
+
+        Ok(self.rebuild(
+            self.session_info_bytes(),
+            self.disk_header.start_date,
+            &records,
+        ))
+    }
+
+    ///
+    /// Session time from the first sample of lap `laps.start()` to the last
+    /// sample of lap `laps.end()`, if any of them were recorded.
+    pub fn lap_times(&self, laps: RangeInclusive<i32>) -> Option<RangeInclusive<f64>> {
+        let lap = self.var_offset("Lap")?;
+        let time = self.var_offset("SessionTime")?;
+
+        let mut times = self
+            .records()
+            .chunks_exact(self.header.buffer_length as usize)
+            .filter(|record| laps.contains(&read_i32(&record[lap..])))
+            .map(|record| read_f64(&record[time..]));
+
+        let first = times.next()?;
+        let last = times.next_back().unwrap_or(first);
+        Some(first..=last)
+    }
+
+    /// Names of the file's variables, from its variable headers.
+    pub fn variables(&self) -> Vec<String> {
+        value_names(self.var_headers())
+    }
+
+    fn var_headers(&self) -> &[u8] {
+        var_headers_range(&self.header)
+            .and_then(|range| self.data.get(range))
+            .unwrap_or_default()
+    }
+
+    fn records(&self) -> &[u8] {
+        let start = self.header.buffer_offset(0);
+        self.records
+            .checked_mul(self.header.buffer_length as usize)
+            .and_then(|len| self.data.get(start..start.checked_add(len)?))
+            .unwrap_or_default()
+    }
+
+    /// Offset of a variable within each record.
+    fn var_offset(&self, name: &str) -> Option<usize> {
+        self.var_headers()
+            .chunks_exact(VAR_HEADER_SIZE)
+            .find(|vh| {
+                let raw = &vh[16..48];
+                let end = raw.iter().position(|b| *b == 0).unwrap_or(raw.len());
+                &raw[..end] == name.as_bytes()
+            })
+            .and_then(|vh| usize::try_from(i32::from_le_bytes([vh[4], vh[5], vh[6], vh[7]])).ok())
+            .filter(|offset| offset + 8 <= self.header.buffer_length as usize)
+    }
+
+    ///
+    /// Build a file with this file's variables and the given session info and
+    /// records, filling in the disk header from the records.
+    fn rebuild(&self, session_info: &[u8], start_date: i64, records: &[u8]) -> IBT {
+        let buffer_length = self.header.buffer_length as usize;
+        let count = records.len() / buffer_length;
+        let time = |idx: usize| {
+            self.var_offset("SessionTime")
+                .map(|at| read_f64(&records[idx * buffer_length + at..]))
+                .unwrap_or_default()
+        };
+        let lap = |idx: usize| {
+            self.var_offset("Lap")
+                .map(|at| read_i32(&records[idx * buffer_length + at..]))
+        };
+
+        let disk_header = DiskHeader {
+            start_date,
+            start_time: if count > 0 { time(0) } else { 0.0 },
+            end_time: if count > 0 { time(count - 1) } else { 0.0 },
+            lap_count: match count {
+                0 => 0,
+                n => lap(0)
+                    .zip(lap(n - 1))
+                    .map_or(0, |(first, last)| last - first + 1),
+            },
+            record_count: count as i32,
+        };
+
+        let var_headers = self.var_headers();
+        let header_offset = HEADER_SIZE + DISK_HEADER_SIZE;
+        let session_offset = header_offset + var_headers.len();
+        let session_length = round_up(session_info.len() + 1);
+        let buffer_offset = session_offset + session_length;
+
+        let mut data = vec![0u8; buffer_offset];
+        let fields: [i32; 10] = [
+            self.header.version,
+            self.header.status,
+            self.header.tick_rate,
+            self.header.session_info_version,
+            session_length as i32,
+            session_offset as i32,
+            self.header.n_vars,
+            header_offset as i32,
+            1,
+            buffer_length as i32,
+        ];
+        for (i, v) in fields.iter().enumerate() {
+            data[i * 4..i * 4 + 4].copy_from_slice(&v.to_le_bytes());
+        }
+        data[48..52].copy_from_slice(&(count as i32).to_le_bytes());
+        data[52..56].copy_from_slice(&(buffer_offset as i32).to_le_bytes());
+
+        data[HEADER_SIZE..header_offset].copy_from_slice(&disk_header.to_bytes());
+        data[header_offset..session_offset].copy_from_slice(var_headers);
+        data[session_offset..session_offset + session_info.len()].copy_from_slice(session_info);
+        data.extend_from_slice(records);
+
+        let header = Header::from_bytes(&data).expect("Header is written in full");
+        IBT {
+            data,
+            header,
+            disk_header,
+            records: count,
+        }
+    }
+}
+
+///
+/// Merge telemetry files from one session, e.g. when telemetry was stopped and
+/// restarted between stints, into one continuous file.
+///
+/// Files are ordered by when they were recorded and must hold the same
+/// variables. If the session time goes backwards from one file to the next,
+/// as it does when the sim was restarted, the later file's `SessionTime` is
+/// shifted to carry on one tick after the earlier file ends. The merged file
+/// has the session info of the last file.
+///
+/// # Examples
+///
+/// ```no_run
+/// use iracing::ibt;
+///
+/// let merged = ibt::merge(&["stint1.ibt", "stint2.ibt"]).unwrap();
+/// merged.save("race.ibt").unwrap();
+/// ```
+pub fn merge<P: AsRef<Path>>(paths: &[P]) -> Result<IBT, IbtError> {
+    let files = paths
+        .iter()
+        .map(IBT::open)
+        .collect::<Result<Vec<IBT>, IbtError>>()?;
+    concat(files)
+}
+
+///
+/// Merge telemetry files already read into memory. See `merge`.
+pub fn concat(mut files: Vec<IBT>) -> Result<IBT, IbtError> {
+    files.sort_by(|a, b| {
+        (a.disk_header.start_date, a.disk_header.start_time)
+            .partial_cmp(&(b.disk_header.start_date, b.disk_header.start_time))
+            .unwrap_or(std::cmp::Ordering::Equal)
+    });
+
+    let first = files
+        .first()
+        .ok_or_else(|| IbtError::Incompatible(String::from("No files to merge")))?;
+    let last = files.last().unwrap_or(first);
+
+    let buffer_length = first.header.buffer_length as usize;
+    let tick = 1.0 / f64::from(first.header.tick_rate.max(1));
+    let time_offset = first.var_offset("SessionTime");
+
+    let mut records: Vec<u8> = Vec::new();
+    let mut end_time: Option<f64> = None;
+    for file in files.iter() {
+        if file.header.buffer_length != first.header.buffer_length
+            || file.var_headers() != first.var_headers()
+        {
+            return Err(IbtError::Incompatible(String::from(
+                "Files record different variables",
+            )));
+        }
+
+        let start = records.len();
+        records.extend_from_slice(file.records());
+
+        if let Some(at) = time_offset.filter(|_| file.records > 0) {
+            let first_time = read_f64(&records[start + at..]);
+            let shift = match end_time {
+                Some(end) if first_time <= end => end + tick - first_time,
+                _ => 0.0,
+            };
+
+            for record in records[start..].chunks_exact_mut(buffer_length) {
+                let time = read_f64(&record[at..]) + shift;
+                record[at..at + 8].copy_from_slice(&time.to_le_bytes());
+            }
+            end_time = Some(read_f64(&records[records.len() - buffer_length + at..]));
+        }
+    }
+
+    Ok(first.rebuild(
+        last.session_info_bytes(),
+        first.disk_header.start_date,
+        &records,
+    ))
+}
+
+impl DiskHeader {
+    fn from_bytes(bytes: &[u8]) -> DiskHeader {
+        let mut b8 = [0u8; 8];
+        let mut b4 = [0u8; 4];
+        let mut word = |at: usize| {
+            b8.copy_from_slice(&bytes[at..at + 8]);
+            b8
+        };
+        let (start_date, start_time, end_time) = (
+            i64::from_le_bytes(word(0)),
+            f64::from_le_bytes(word(8)),
+            f64::from_le_bytes(word(16)),
+        );
+
+        b4.copy_from_slice(&bytes[24..28]);
+        let lap_count = i32::from_le_bytes(b4);
+        b4.copy_from_slice(&bytes[28..32]);
+        let record_count = i32::from_le_bytes(b4);
+
+        DiskHeader {
+            start_date,
+            start_time,
+            end_time,
+            lap_count,
+            record_count,
+        }
+    }
+
+    fn to_bytes(self) -> [u8; DISK_HEADER_SIZE] {
+        let mut bytes = [0u8; DISK_HEADER_SIZE];
+        bytes[0..8].copy_from_slice(&self.start_date.to_le_bytes());
+        bytes[8..16].copy_from_slice(&self.start_time.to_le_bytes());
+        bytes[16..24].copy_from_slice(&self.end_time.to_le_bytes());
+        bytes[24..28].copy_from_slice(&self.lap_count.to_le_bytes());
+        bytes[28..32].copy_from_slice(&self.record_count.to_le_bytes());
+        bytes
+    }
+}
+
+fn read_f64(bytes: &[u8]) -> f64 {
+    let mut b8 = [0u8; 8];
+    b8.copy_from_slice(&bytes[..8]);
+    f64::from_le_bytes(b8)
+}
+
+fn read_i32(bytes: &[u8]) -> i32 {
+    i32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]])
+}
+
+fn round_up(len: usize) -> usize {
+    len.div_ceil(16) * 16
+}
+
+/// Bytes holding the variable headers, unless the header's count or offset
+/// is negative or overflows.
+fn var_headers_range(header: &Header) -> Option<std::ops::Range<usize>> {
+    let start = usize::try_from(header.header_offset).ok()?;
+    let len = usize::try_from(header.n_vars)
+        .ok()?
+        .checked_mul(VAR_HEADER_SIZE)?;
+    Some(start..start.checked_add(len)?)
+}
+
+fn checked_end(offset: i32, length: i32) -> Option<usize> {
+    let offset = usize::try_from(offset).ok()?;
+    offset.checked_add(usize::try_from(length).ok()?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::telemetry::Value;
+    use proptest::prelude::*;
+    use std::convert::TryInto;
+
+    fn fixture() -> Vec<u8> {
+        std::fs::read("./telemetry.ibt").unwrap()
+    }
+
+    #[test]
+    fn golden_file() {
+        let ibt = IBT::from_bytes(fixture()).unwrap();
+
+        assert_eq!(ibt.len(), 120);
+        assert_eq!(ibt.header().tick_rate, 60);
+        assert_eq!(ibt.disk_header().record_count, 120);
+        assert_eq!(
+            ibt.session_info().unwrap().drivers.other_drivers[1].user_name,
+            "L W Adamek"
+        );
+
+        let first = ibt.sample(0).unwrap();
+        let time: f64 = first.get("SessionTime").unwrap().try_into().unwrap();
+        let warnings: u32 = first.get("EngineWarnings").unwrap().try_into().unwrap();
+        assert_eq!(time, 120.0);
+        assert_eq!(warnings, 0x10);
+
+        let last = ibt.sample(119).unwrap();
+        let speed: f32 = last.get("Speed").unwrap().try_into().unwrap();
+        let gear: i32 = last.get("Gear").unwrap().try_into().unwrap();
+        let lap: i32 = last.get("Lap").unwrap().try_into().unwrap();
+        let pits: Vec<bool> = last.get("CarIdxOnPitRoad").unwrap().into();
+        let dist: Vec<f32> = last.get("CarIdxLapDistPct").unwrap().try_into().unwrap();
+        assert!((speed - 51.9).abs() < 1e-4);
+        assert_eq!(gear, 4);
+        assert_eq!(lap, 3);
+        assert!(pits[5]);
+        assert_eq!(dist.len(), 8);
+        assert_eq!(dist[0], -1.0);
+
+        assert!(ibt.sample(120).is_err());
+        assert_eq!(ibt.samples().filter(|s| s.is_ok()).count(), 120);
+        assert!(ibt.variables().iter().any(|name| name == "SessionTime"));
+    }
+
+    #[test]
+    fn truncated_file() {
+        let mut data = fixture();
+        data.truncate(data.len() - 100);
+
+        // Partial records are dropped
+        assert_eq!(IBT::from_bytes(data.clone()).unwrap().len(), 118);
+
+        data.truncate(2000);
+        assert!(matches!(IBT::from_bytes(data), Err(IbtError::Truncated(_))));
+    }
+
+    #[test]
+    fn merge_files() {
+        let first = IBT::from_bytes(fixture()).unwrap();
+        let merged = concat(vec![first.clone(), first.clone()]).unwrap();
+
+        assert_eq!(merged.len(), 240);
+        assert_eq!(merged.session_info_raw(), first.session_info_raw());
+        assert_eq!(merged.disk_header().record_count, 240);
+        assert_eq!(merged.disk_header().start_time, 120.0);
+
+        // The second file's session time carries on after the first
+        let time = |idx: usize| -> f64 {
+            merged
+                .sample(idx)
+                .unwrap()
+                .get("SessionTime")
+                .unwrap()
+                .try_into()
+                .unwrap()
+        };
+        assert!((time(120) - 122.0).abs() < 1e-9);
+        assert!((time(239) - merged.disk_header().end_time).abs() < 1e-9);
+
+        // The merged file reads back the same
+        let mut saved = Vec::new();
+        merged.write_to(&mut saved).unwrap();
+        assert_eq!(IBT::from_bytes(saved).unwrap().len(), 240);
+
+        let mut other = fixture();
+        other[first.header().header_offset as usize + 16] = b'X';
+        let other = IBT::from_bytes(other).unwrap();
+        assert!(matches!(
+            concat(vec![first, other]),
+            Err(IbtError::Incompatible(_))
+        ));
+    }
+
+    #[test]
+    fn extract_window() {
+        let ibt = IBT::from_bytes(fixture()).unwrap();
+        let laps = ibt.lap_times(3..=3).unwrap();
+        assert!(*laps.start() > 120.0);
+        assert!((laps.end() - ibt.disk_header().end_time).abs() < 1e-3);
+
+        let window = ibt.window(120.5..=121.0).unwrap();
+        assert_eq!(window.len(), 31);
+        assert_eq!(window.session_info_raw(), ibt.session_info_raw());
+        assert_eq!(window.disk_header().start_time, 120.5);
+        assert_eq!(window.disk_header().lap_count, 2);
+
+        let speed: f32 = window
+            .sample(30)
+            .unwrap()
+            .get("Speed")
+            .unwrap()
+            .try_into()
+            .unwrap();
+        let original: f32 = ibt
+            .sample(60)
+            .unwrap()
+            .get("Speed")
+            .unwrap()
+            .try_into()
+            .unwrap();
+        assert_eq!(speed, original);
+
+        let file = tempfile::NamedTempFile::new().unwrap();
+        assert_eq!(ibt.extract(200.0..=300.0, file.path()).unwrap(), 0);
+        assert!(IBT::open(file.path()).unwrap().is_empty());
+    }
+
+    proptest! {
+        #[test]
+        fn arbitrary_bytes_never_panic(data in proptest::collection::vec(any::<u8>(), 0..2048)) {
+            if let Ok(ibt) = IBT::from_bytes(data) {
+                for sample in ibt.samples().flatten() {
+                    let _ = sample.all();
+                }
+            }
+        }
+
+        #[test]
+        fn corrupt_headers_never_panic(at in 0usize..144 * 12, value in any::<i32>()) {
+            let mut data = fixture();
+            data[at..at + 4].copy_from_slice(&value.to_le_bytes());
+
+            if let Ok(ibt) = IBT::from_bytes(data) {
+                let _ = ibt.session_info_raw();
+                for sample in ibt.samples().take(2).flatten() {
+                    let _ = sample.all();
+                }
+            }
+        }
+
+        #[test]
+        fn corrupt_variable_headers_are_rejected(
+            n_vars in any::<i32>(),
+            offset in any::<i32>(),
+            len in 144usize..40_000,
+        ) {
+            // Cut short enough files hold no records to check the headers against
+            let mut data = fixture();
+            data.truncate(len);
+            data[24..28].copy_from_slice(&n_vars.to_le_bytes());
+            data[28..32].copy_from_slice(&offset.to_le_bytes());
+
+            if let Ok(ibt) = IBT::from_bytes(data) {
+                let _ = ibt.variables();
+                let _ = ibt.lap_times(0..=2);
+                let _ = ibt.window(0.0..=10.0);
+            }
+        }
+
+        #[test]
+        fn values_are_little_endian(speed in any::<f32>(), gear in any::<i32>(), at in 0usize..120) {
+            let mut data = fixture();
+            let ibt = IBT::from_bytes(data.clone()).unwrap();
+            let record = ibt.header().buffer_offset(0) + at * ibt.header().buffer_length as usize;
+
+            // Speed and Gear sit at 20 and 28 bytes into each record
+            data[record + 20..record + 24].copy_from_slice(&speed.to_le_bytes());
+            data[record + 28..record + 32].copy_from_slice(&gear.to_le_bytes());
+
+            let sample = IBT::from_bytes(data).unwrap().sample(at).unwrap();
+            prop_assert!(matches!(sample.get("Speed").unwrap(), Value::FLOAT(s) if s.to_bits() == speed.to_bits()));
+            prop_assert!(matches!(sample.get("Gear").unwrap(), Value::INT(g) if g == gear));
+        }
+    }
+}
@@ -0,0 +1,222 @@
+use crate::session::Driver;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+#[cfg(feature = "telemetry")]
+use crate::telemetry::Sample;
+#[cfg(feature = "telemetry")]
+use std::convert::TryInto;
+#[cfg(feature = "telemetry")]
+use std::error::Error;
+
+///
+/// Incident Sample
+///
+/// The player's incident counts at a point in the session. Incident counts for
+/// other cars are only available from the session info.
+#[derive(Debug, Copy, Clone, Default)]
+pub struct IncidentSample {
+    pub session_time: f64,   // Seconds since session start
+    pub car_idx: usize,      // Player's car index
+    pub lap: i32,            // Player's current lap
+    pub team_incidents: i32, // Incidents for the player's car (all drivers)
+}
+
+///
+/// Incident points added to a car.
+#[derive(Debug, Copy, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Incident {
+    pub car_idx: usize,
+    pub user_id: Option<i64>, // Driver at the wheel, if known
+    pub points: i32,          // Points added, e.g. 4 for a 4x
+    pub total: i32,           // Car's total after the incident
+    pub lap: Option<i32>,
+    pub session_time: f64, // Session time the incident was seen
+}
+
+///
+/// Incident Ledger
+///
+/// Per-car incident history, built from the player's incident channels and the
+/// incident counts in the session info.
+///
+/// Both sources report running totals; the ledger records each increase once,
+/// whichever source reports it first.
+///
+/// # Examples
+///
+/// ```
+/// use iracing::incidents::{IncidentLedger, IncidentSample};
+///
+/// let mut ledger = IncidentLedger::new();
+///
+/// if let Some(incident) = ledger.update(&IncidentSample::default()) {
+///     println!("{}x on lap {:?}", incident.points, incident.lap);
+/// }
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct IncidentLedger {
+    incidents: Vec<Incident>,
+    totals: HashMap<usize, i32>,
+    drivers: HashMap<usize, i64>,
+}
+
+impl IncidentSample {
+    ///
+    /// Read an incident sample from a telemetry sample.
+    #[cfg(feature = "telemetry")]
+    pub fn from_sample(sample: &Sample) -> Result<Self, Box<dyn Error>> {
+        let car_idx: i32 = sample.get("PlayerCarIdx")?.try_into()?;
+
+        Ok(IncidentSample {
+            session_time: sample.get("SessionTime")?.try_into()?,
+            car_idx: car_idx.max(0) as usize,
+            lap: sample.get("Lap")?.try_into()?,
+            team_incidents: sample.get("PlayerCarTeamIncidentCount")?.try_into()?,
+        })
+    }
+}
+
+impl IncidentLedger {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    ///
+    /// Update the ledger from the player's incident channels.
+    pub fn update(&mut self, sample: &IncidentSample) -> Option<Incident> {
+        self.record(
+            sample.car_idx,
+            sample.team_incidents,
+            Some(sample.lap),
+            sample.session_time,
+        )
+    }
+
+    ///
+    /// Update the ledger from the driver list in the session info, and per-car laps (`CarIdxLap`).
+    ///
+    /// The first count seen for each car is taken as its starting total.
+    pub fn update_drivers(
+        &mut self,
+        session_time: f64,
+        drivers: &[Driver],
+        car_laps: &[i32],
+    ) -> Vec<Incident> {
+        let mut incidents = Vec::new();
+
+        for driver in drivers.iter().filter(|d| !d.is_pace_car()) {
+            self.drivers.insert(driver.index, driver.user_id);
+
+            let total = match driver.team_incident_count.or(driver.driver_incident_count) {
+                Some(total) => total,
+                None => continue,
+            };
+
+            let lap = car_laps.get(driver.index).copied();
+            if let Some(incident) = self.record(driver.index, total, lap, session_time) {
+                incidents.push(incident);
+            }
+        }
+
+        incidents
+    }
+
+    fn record(
+        &mut self,
+        car_idx: usize,
+        total: i32,
+        lap: Option<i32>,
+        session_time: f64,
+    ) -> Option<Incident> {
+        let previous = self.totals.insert(car_idx, total)?;
+
+        if total <= previous {
+            // Counts never go down, so keep the highest seen
+            self.totals.insert(car_idx, previous);
+            return None;
+        }
+
+        let incident = Incident {
+            car_idx,
+            user_id: self.drivers.get(&car_idx).copied(),
+            points: total - previous,
+            total,
+            lap,
+            session_time,
+        };
+
+        self.incidents.push(incident);
+        Some(incident)
+    }
+
+    /// Every incident recorded, in order.
+    pub fn all(&self) -> &[Incident] {
+        &self.incidents
+    }
+
+    /// Incidents for a given car.
+    pub fn for_car(&self, car_idx: usize) -> impl Iterator<Item = &Incident> {
+        self.incidents.iter().filter(move |i| i.car_idx == car_idx)
+    }
+
+    /// Incidents for a given driver.
+    pub fn for_driver(&self, user_id: i64) -> impl Iterator<Item = &Incident> {
+        self.incidents
+            .iter()
+            .filter(move |i| i.user_id == Some(user_id))
+    }
+
+    /// Latest known incident total for a car.
+    pub fn total(&self, car_idx: usize) -> i32 {
+        self.totals.get(&car_idx).copied().unwrap_or(0)
+    }
+
+    ///
+    /// Incident points a car picked up on a given lap.
+    pub fn on_lap(&self, car_idx: usize, lap: i32) -> i32 {
+        self.for_car(car_idx)
+            .filter(|i| i.lap == Some(lap))
+            .map(|i| i.points)
+            .sum()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::session::SessionDetails;
+
+    #[test]
+    fn ledger() {
+        let content = std::fs::read_to_string("./session.yaml").unwrap();
+        let session: SessionDetails = serde_yaml::from_str(&content).unwrap();
+        let mut drivers = session.drivers.other_drivers;
+        let laps = vec![0, 5, 5, 5, 5, 5, 5];
+
+        let mut ledger = IncidentLedger::new();
+        assert!(ledger.update_drivers(10.0, &drivers, &laps).is_empty());
+        assert_eq!(ledger.total(4), 4);
+
+        // Player's car picks up a 4x, then a 2x, on lap 5
+        let mut sample = IncidentSample {
+            session_time: 20.0,
+            car_idx: 1,
+            lap: 5,
+            team_incidents: 4,
+        };
+        assert_eq!(ledger.update(&sample).unwrap().points, 4);
+        sample.team_incidents = 6;
+        sample.session_time = 25.0;
+        assert_eq!(ledger.update(&sample).unwrap().user_id, Some(81797));
+        assert_eq!(ledger.on_lap(1, 5), 6);
+
+        // The session info catching up doesn't count the incidents again
+        drivers[1].team_incident_count = Some(6);
+        drivers[4].team_incident_count = Some(5);
+        let incidents = ledger.update_drivers(30.0, &drivers, &laps);
+        assert_eq!(incidents.len(), 1);
+        assert_eq!(incidents[0].car_idx, 4);
+        assert_eq!(incidents[0].points, 1);
+    }
+}
@@ -0,0 +1,320 @@
+use crate::fps::Fps;
+use crate::steering;
+use serde::{Deserialize, Serialize};
+use std::collections::VecDeque;
+
+#[cfg(feature = "telemetry")]
+use crate::telemetry::Sample;
+#[cfg(feature = "telemetry")]
+use std::convert::TryInto;
+#[cfg(feature = "telemetry")]
+use std::error::Error;
+
+///
+/// Input Sample
+///
+/// The driver's inputs at a point in time.
+#[derive(Debug, Copy, Clone, Default)]
+pub struct InputSample {
+    pub session_time: f64, // SessionTime
+    pub throttle: f32,     // Throttle - 0.0 to 1.0
+    pub brake: f32,        // Brake - 0.0 to 1.0
+    pub clutch: f32,       // Clutch - 1.0 when fully engaged
+    pub steering: f32,     // SteeringWheelAngle - rad, positive left
+    pub steering_max: f32, // SteeringWheelAngleMax - rad, lock to lock / 2
+}
+
+///
+/// The last few seconds of inputs, oldest first, one value per channel at
+/// each tick of a fixed rate. Every channel always holds `len` values, so an
+/// input graph can draw it without scaling, and ticks before the trace
+/// started are zero.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct InputTrace {
+    pub rate: u8,           // Values per second
+    pub len: usize,         // Values in each channel
+    pub session_time: f64,  // Time of the newest values
+    pub throttle: Vec<f32>, // 0.0 to 1.0
+    pub brake: Vec<f32>,    // 0.0 to 1.0
+    pub clutch: Vec<f32>,   // Pedal position, 1.0 when fully pressed
+    pub steering: Vec<f32>, // -1.0 to 1.0 of full lock, positive left
+}
+
+///
+/// The values added to a trace since an earlier update, oldest first, so an
+/// input graph can append them rather than redraw the whole window.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct InputTraceUpdate {
+    pub rate: u8,           // Values per second
+    pub len: usize,         // Values in each channel of the whole trace
+    pub end: u64, // Values added to the buffer so far, pass to `since` for the next update
+    pub reset: bool, // The trace started again, so drop the values drawn so far
+    pub session_time: f64, // Time of the newest values
+    pub throttle: Vec<f32>, // 0.0 to 1.0
+    pub brake: Vec<f32>, // 0.0 to 1.0
+    pub clutch: Vec<f32>, // Pedal position, 1.0 when fully pressed
+    pub steering: Vec<f32>, // -1.0 to 1.0 of full lock, positive left
+}
+
+///
+/// Input Trace Buffer
+///
+/// Keeps a rolling window of the driver's inputs at a fixed rate, for
+/// brake and throttle overlays which only need the inputs rather than whole
+/// samples. Samples are resampled to the rate, repeating the last inputs over
+/// any gap.
+///
+/// # Examples
+///
+/// ```
+/// use iracing::fps::Fps;
+/// use iracing::input_trace::{InputSample, InputTraceBuffer};
+///
+/// let mut buffer = InputTraceBuffer::new(5.0, Fps::new(30));
+///
+/// buffer.update(&InputSample { session_time: 10.0, throttle: 1.0, ..Default::default() });
+///
+/// let trace = buffer.trace();
+/// assert_eq!(trace.throttle.len(), 150);
+/// assert_eq!(trace.throttle[149], 1.0);
+///
+/// // Only the values added since the last update are sent on
+/// let update = buffer.since(0);
+/// assert_eq!(update.throttle, vec![1.0]);
+/// assert!(buffer.since(update.end).throttle.is_empty());
+/// ```
+#[derive(Debug, Clone)]
+pub struct InputTraceBuffer {
+    rate: u8,
+    len: usize,
+    points: VecDeque<Point>,
+    last: Option<(f64, Point)>, // Time of the newest tick and its inputs
+    added: u64,                 // Points pushed and clears since the buffer was created
+    cleared: u64,               // Value of `added` just after the last clear
+}
+
+#[derive(Debug, Copy, Clone, Default)]
+struct Point {
+    throttle: f32,
+    brake: f32,
+    clutch: f32,
+    steering: f32,
+}
+
+impl InputSample {
+    ///
+    /// Read an input sample from a telemetry sample.
+    #[cfg(feature = "telemetry")]
+    pub fn from_sample(sample: &Sample) -> Result<Self, Box<dyn Error>> {
+        Ok(InputSample {
+            session_time: sample.get("SessionTime")?.try_into()?,
+            throttle: sample.get("Throttle")?.try_into()?,
+            brake: sample.get("Brake")?.try_into()?,
+            clutch: sample.get("Clutch")?.try_into()?,
+            steering: sample.get("SteeringWheelAngle")?.try_into()?,
+            steering_max: sample.get("SteeringWheelAngleMax")?.try_into()?,
+        })
+    }
+}
+
+impl InputTrace {
+    ///
+    /// Serialize the trace as JSON.
+    pub fn to_json(&self) -> serde_json::Result<String> {
+        serde_json::to_string(self)
+    }
+}
+
+impl InputTraceUpdate {
+    /// Whether there is nothing to draw or drop.
+    pub fn is_empty(&self) -> bool {
+        !self.reset && self.throttle.is_empty()
+    }
+}
+
+impl InputTraceBuffer {
+    /// Keep the last `seconds` of inputs at `rate` values a second.
+    pub fn new(seconds: f32, rate: Fps) -> Self {
+        let len = (seconds.max(0.0) * rate.0.get() as f32).round().max(1.0) as usize;
+
+        InputTraceBuffer {
+            rate: rate.0.get(),
+            len,
+            points: VecDeque::with_capacity(len),
+            last: None,
+            added: 0,
+            cleared: 0,
+        }
+    }
+
+    /// Values in each channel of a trace.
+    pub fn capacity(&self) -> usize {
+        self.len
+    }
+
+    /// Forget every input, such as when the car is reset.
+    pub fn clear(&mut self) {
+        self.points.clear();
+        self.last = None;
+        // A clear takes a place of its own, so every earlier end is before it
+        self.added += 1;
+        self.cleared = self.added;
+    }
+
+    ///
+    /// Add a sample. Time going backwards, such as a new session or a replay
+    /// being rewound, starts the trace again.
+    pub fn update(&mut self, sample: &InputSample) {
+        let point = Point {
+            throttle: sample.throttle.clamp(0.0, 1.0),
+            brake: sample.brake.clamp(0.0, 1.0),
+            clutch: (1.0 - sample.clutch).clamp(0.0, 1.0),
+            steering: steering::normalize(sample.steering, sample.steering_max),
+        };
+        let interval = 1.0 / self.rate as f64;
+
+        let (time, ticks) = match self.last {
+            Some((time, _)) if sample.session_time < time => {
+                self.clear();
+                (sample.session_time, 1)
+            }
+            Some((time, _)) => {
+                let ticks = ((sample.session_time - time) / interval + 1e-6).floor() as usize;
+                (time + ticks as f64 * interval, ticks)
+            }
+            None => (sample.session_time, 1),
+        };
+        if ticks == 0 {
+            return;
+        }
+
+        // Hold the previous inputs over a gap, the newest tick gets this sample
+        let held = self.last.map_or(point, |(_, last)| last);
+        for _ in 1..ticks.min(self.len) {
+            self.push(held);
+        }
+        self.push(point);
+        self.last = Some((time, point));
+    }
+
+    /// The trace as it stands, with every channel `len` values long.
+    pub fn trace(&self) -> InputTrace {
+        let pad = self.len - self.points.len();
+        let channel = |f: fn(&Point) -> f32| {
+            let mut values = vec![0.0; pad];
+            values.extend(self.points.iter().map(f));
+            values
+        };
+
+        InputTrace {
+            rate: self.rate,
+            len: self.len,
+            session_time: self.last.map_or(0.0, |(time, _)| time),
+            throttle: channel(|p| p.throttle),
+            brake: channel(|p| p.brake),
+            clutch: channel(|p| p.clutch),
+            steering: channel(|p| p.steering),
+        }
+    }
+
+    ///
+    /// The values added since the update which ended at `end`, or since the
+    /// trace started again if it has been cleared since. Pass 0 for the
+    /// first update.
+    pub fn since(&self, end: u64) -> InputTraceUpdate {
+        let reset = end < self.cleared || end > self.added;
+        let from = if reset { self.cleared } else { end };
+        let count = (self.added - from).min(self.points.len() as u64) as usize;
+        let skip = self.points.len() - count;
+        let channel = |f: fn(&Point) -> f32| self.points.iter().skip(skip).map(f).collect();
+
+        InputTraceUpdate {
+            rate: self.rate,
+            len: self.len,
+            end: self.added,
+            reset: reset && end > 0,
+            session_time: self.last.map_or(0.0, |(time, _)| time),
+            throttle: channel(|p| p.throttle),
+            brake: channel(|p| p.brake),
+            clutch: channel(|p| p.clutch),
+            steering: channel(|p| p.steering),
+        }
+    }
+
+    fn push(&mut self, point: Point) {
+        if self.points.len() == self.len {
+            self.points.pop_front();
+        }
+        self.points.push_back(point);
+        self.added += 1;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn rolling_input_trace() {
+        let mut buffer = InputTraceBuffer::new(1.0, Fps::new(10));
+        let input = |session_time: f64, throttle: f32| InputSample {
+            session_time,
+            throttle,
+            brake: 1.0 - throttle,
+            clutch: 1.0,
+            steering: -0.5,
+            steering_max: 2.0,
+        };
+
+        // Samples at 60Hz are resampled to 10Hz
+        for tick in 0..30 {
+            buffer.update(&input(tick as f64 / 60.0, 0.5));
+        }
+        let trace = buffer.trace();
+        assert_eq!(trace.len, 10);
+        assert_eq!(trace.throttle[..5], [0.0; 5]);
+        assert_eq!(trace.throttle[5..], [0.5; 5]);
+        assert_eq!(trace.steering[9], -0.25);
+        assert_eq!(trace.clutch[9], 0.0);
+
+        // A gap holds the last inputs, and the window rolls on
+        buffer.update(&input(0.8, 1.0));
+        let trace = buffer.trace();
+        assert_eq!(trace.throttle[0], 0.0);
+        assert_eq!(trace.throttle[1..9], [0.5; 8]);
+        assert_eq!(trace.throttle[9], 1.0);
+        assert_eq!(trace.brake[9], 0.0);
+
+        // Rewinding starts over
+        buffer.update(&input(0.1, 1.0));
+        assert_eq!(buffer.trace().throttle[..9], [0.0; 9]);
+    }
+
+    #[test]
+    fn incremental_updates() {
+        let mut buffer = InputTraceBuffer::new(1.0, Fps::new(10));
+        let input = |session_time: f64, throttle: f32| InputSample {
+            session_time,
+            throttle,
+            ..Default::default()
+        };
+
+        for tick in 0..3 {
+            buffer.update(&input(tick as f64 / 10.0, 0.5));
+        }
+        let first = buffer.since(0);
+        assert_eq!(first.throttle, vec![0.5; 3]);
+        assert!(!first.reset);
+
+        buffer.update(&input(0.3, 1.0));
+        let next = buffer.since(first.end);
+        assert_eq!(next.throttle, vec![1.0]);
+        assert!(buffer.since(next.end).is_empty());
+
+        // A rewind tells the graph to start again
+        buffer.update(&input(0.0, 0.25));
+        let rewound = buffer.since(next.end);
+        assert!(rewound.reset);
+        assert_eq!(rewound.throttle, vec![0.25]);
+    }
+}
@@ -1,14 +1,116 @@
 #![deny(clippy::all)]
 
+pub mod activity;
+pub mod admin;
+pub mod alerts;
+pub mod anonymize;
+pub mod archive;
+pub mod assets;
+pub mod battles;
+pub mod boundary;
+pub mod caution;
+pub mod classes;
+pub mod clock;
+pub mod coaching;
+pub mod consistency;
+pub mod drs;
+pub mod dynamics;
+pub mod engineer;
+pub mod ffb;
+pub mod field;
+pub mod filters;
+pub mod fleet;
 pub mod fps;
+pub mod fuel;
+pub mod gaps;
+pub mod gearbox;
+pub mod ghost;
+pub mod health;
+pub mod highlights;
+pub mod history;
+pub mod hybrid;
+pub mod incidents;
+pub mod input_trace;
+pub mod network;
+pub mod overtakes;
+pub mod pace;
+pub mod penalties;
+pub mod performance;
+pub mod pipe;
+pub mod pits;
+pub mod points;
+pub mod pool;
 pub mod replay;
+pub mod republish;
+pub mod results;
+pub mod schedule;
+pub mod sector_flags;
 pub mod session;
+pub mod setups;
+pub mod shift_lights;
 pub mod simulation;
+pub mod spotter;
+pub mod starts;
 pub mod states;
+pub mod stats;
+pub mod steering;
+pub mod strategy;
+pub mod team;
+pub mod track_limits;
 pub mod track_surface;
+pub mod traffic;
+pub mod units;
+pub mod validity;
+pub mod weather;
+pub mod yaml;
+
+#[cfg(feature = "telemetry")]
+pub mod chunked;
+
+#[cfg(feature = "telemetry")]
+pub mod companion;
+
+#[cfg(feature = "compression")]
+pub mod compression;
+
+#[cfg(feature = "sqlite")]
+pub mod database;
+
+#[cfg(feature = "http")]
+pub mod data;
+
+#[cfg(feature = "discord")]
+pub mod discord;
 
 #[cfg(all(target_os = "windows", feature = "broadcast"))]
 pub mod broadcast;
 
-#[cfg(all(target_os = "windows", feature = "telemetry"))]
+#[cfg(all(target_os = "windows", feature = "chat"))]
+pub mod chat;
+
+#[cfg(all(target_os = "windows", feature = "broadcast"))]
+pub mod commands;
+
+#[cfg(feature = "telemetry")]
+pub mod ibt;
+
+#[cfg(feature = "obs")]
+pub mod obs;
+
+#[cfg(feature = "telemetry")]
+pub mod pipeline;
+
+#[cfg(feature = "telemetry")]
+pub mod recording;
+
+#[cfg(feature = "telemetry")]
+pub mod sampler;
+
+#[cfg(feature = "telemetry")]
+pub mod soak;
+
+#[cfg(feature = "telemetry")]
 pub mod telemetry;
+
+#[cfg(all(target_os = "windows", feature = "broadcast"))]
+pub mod window;
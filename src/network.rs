@@ -0,0 +1,267 @@
+use crate::alerts::{AlertEvent, AlertRule, Alerts, Metric};
+use serde::{Deserialize, Serialize};
+use std::collections::VecDeque;
+
+#[cfg(feature = "telemetry")]
+use crate::telemetry::Sample;
+#[cfg(feature = "telemetry")]
+use std::convert::TryInto;
+#[cfg(feature = "telemetry")]
+use std::error::Error;
+
+///
+/// Network Sample
+///
+/// The sim's connection to the race server at a point in time. Every
+/// channel reads zero in offline sessions.
+#[derive(Debug, Copy, Clone, Default)]
+pub struct NetworkSample {
+    pub session_time: f64,    // SessionTime
+    pub quality: f32,         // ChanQuality - 0.0 to 1.0
+    pub partner_quality: f32, // ChanPartnerQuality - server's view, 0.0 to 1.0
+    pub latency: f32,         // ChanLatency - s
+    pub avg_latency: f32,     // ChanAvgLatency - s
+    pub clock_skew: f32,      // ChanClockSkew - s
+}
+
+///
+/// Connection quality over the monitor's window.
+#[derive(Debug, Copy, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct NetworkStats {
+    pub quality: f32,     // Average quality, the worse of both ends
+    pub min_quality: f32, // Lowest quality
+    pub latency: f32,     // Average latency (s)
+    pub max_latency: f32, // Highest latency (s)
+    pub clock_skew: f32,  // Latest clock skew (s)
+    pub connected: bool,  // Whether the last sample heard from the server
+}
+
+///
+/// Network Monitor
+///
+/// Watches the sim's connection to the race server, raising alerts when the
+/// quality drops, latency climbs or the server goes quiet, so endurance
+/// teams can swap drivers or call a stop before the car is towed.
+///
+/// The connection counts as dropped while the quality reads zero, and only
+/// connected samples are averaged. Nothing is raised until the sim has been
+/// heard from once, so offline sessions stay quiet.
+///
+/// # Examples
+///
+/// ```
+/// use iracing::network::{NetworkMonitor, NetworkSample};
+///
+/// let mut monitor = NetworkMonitor::new().with_default_rules();
+///
+/// let sample = NetworkSample { quality: 0.5, latency: 0.08, ..Default::default() };
+/// for event in monitor.update(&sample) {
+///     println!("{} {:?}", event.name, event.state);
+/// }
+/// ```
+#[derive(Debug, Clone)]
+pub struct NetworkMonitor {
+    window: f64,
+    samples: VecDeque<NetworkSample>,
+    last_heard: Option<f64>,
+    connected: bool,
+    alerts: Alerts,
+}
+
+impl NetworkSample {
+    ///
+    /// Read a network sample from a telemetry sample.
+    #[cfg(feature = "telemetry")]
+    pub fn from_sample(sample: &Sample) -> Result<Self, Box<dyn Error>> {
+        Ok(NetworkSample {
+            session_time: sample.get("SessionTime")?.try_into()?,
+            quality: sample.get("ChanQuality")?.try_into()?,
+            partner_quality: sample.get("ChanPartnerQuality")?.try_into()?,
+            latency: sample.get("ChanLatency")?.try_into()?,
+            avg_latency: sample.get("ChanAvgLatency")?.try_into()?,
+            clock_skew: sample.get("ChanClockSkew")?.try_into()?,
+        })
+    }
+
+    /// Whether the sim heard from the server.
+    pub fn connected(&self) -> bool {
+        self.quality > 0.0
+    }
+
+    /// The worse of the sim's and the server's view of the connection.
+    pub fn worst_quality(&self) -> f32 {
+        self.quality.min(self.partner_quality)
+    }
+}
+
+impl Default for NetworkMonitor {
+    fn default() -> Self {
+        NetworkMonitor {
+            window: 10.0,
+            samples: VecDeque::new(),
+            last_heard: None,
+            connected: false,
+            alerts: Alerts::new(),
+        }
+    }
+}
+
+impl NetworkMonitor {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Seconds of samples averaged. 10s by default.
+    pub fn with_window(mut self, seconds: f64) -> Self {
+        self.window = seconds;
+        self
+    }
+
+    pub fn with_rule(mut self, rule: AlertRule) -> Self {
+        self.alerts.add_rule(rule);
+        self
+    }
+
+    ///
+    /// Alerts for quality under 80%, latency over 150ms and nothing heard
+    /// from the server for 2 seconds.
+    pub fn with_default_rules(self) -> Self {
+        self.with_rule(AlertRule::network_quality_below(0.8))
+            .with_rule(AlertRule::latency_above(0.15))
+            .with_rule(AlertRule::disconnected_for(2.0))
+    }
+
+    /// Names of the alerts currently raised.
+    pub fn raised(&self) -> impl Iterator<Item = &str> {
+        self.alerts.raised()
+    }
+
+    /// Seconds since the sim last heard from the server.
+    pub fn silence(&self, now: f64) -> Option<f64> {
+        self.last_heard.map(|heard| (now - heard).max(0.0))
+    }
+
+    ///
+    /// Add a sample, returning alerts raised or cleared. Time going
+    /// backwards starts the window again.
+    pub fn update(&mut self, sample: &NetworkSample) -> Vec<AlertEvent> {
+        if self
+            .last_heard
+            .is_some_and(|heard| sample.session_time < heard)
+        {
+            self.samples.clear();
+            self.last_heard = None;
+        }
+
+        self.connected = sample.connected();
+        if self.connected {
+            self.last_heard = Some(sample.session_time);
+            self.samples.push_back(*sample);
+        }
+        while self
+            .samples
+            .front()
+            .is_some_and(|first| sample.session_time - first.session_time > self.window)
+        {
+            self.samples.pop_front();
+        }
+
+        if self.last_heard.is_none() {
+            return Vec::new();
+        }
+
+        // While disconnected that alert says it all
+        let stats = self.stats().filter(|stats| stats.connected);
+        let silence = self.silence(sample.session_time);
+        self.alerts
+            .check(sample.session_time, |metric| match metric {
+                Metric::NetworkQuality => stats.map(|stats| stats.quality),
+                Metric::Latency => stats.map(|stats| stats.latency),
+                Metric::Disconnected => silence.map(|s| s as f32),
+                _ => None,
+            })
+    }
+
+    /// Connection quality over the window, once the sim has been connected
+    /// within it.
+    pub fn stats(&self) -> Option<NetworkStats> {
+        let last = self.samples.back()?;
+        let n = self.samples.len() as f32;
+        let mean = |f: fn(&NetworkSample) -> f32| self.samples.iter().map(f).sum::<f32>() / n;
+
+        Some(NetworkStats {
+            quality: mean(NetworkSample::worst_quality),
+            min_quality: self
+                .samples
+                .iter()
+                .map(NetworkSample::worst_quality)
+                .fold(f32::INFINITY, f32::min),
+            latency: mean(|s| s.latency),
+            max_latency: self.samples.iter().map(|s| s.latency).fold(0.0, f32::max),
+            clock_skew: last.clock_skew,
+            connected: self.connected,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::alerts::AlertState;
+
+    #[test]
+    fn network_alerts() {
+        let mut monitor = NetworkMonitor::new().with_window(2.0).with_default_rules();
+        let mut events = Vec::new();
+        let sample = |t: f64, quality: f32, latency: f32| NetworkSample {
+            session_time: t,
+            quality,
+            partner_quality: quality,
+            latency,
+            avg_latency: latency,
+            clock_skew: 0.01,
+        };
+
+        // Offline until the sim joins the server at 2s, then quality
+        // degrades, the connection drops and comes back
+        for (t, quality, latency) in [
+            (0.0, 0.0, 0.0),
+            (1.0, 0.0, 0.0),
+            (2.0, 1.0, 0.05),
+            (3.0, 1.0, 0.05),
+            (4.0, 0.5, 0.3),
+            (5.0, 0.5, 0.3),
+            (6.0, 0.0, 0.0),
+            (7.0, 0.0, 0.0),
+            (8.0, 0.0, 0.0),
+            (9.0, 1.0, 0.05),
+            (10.0, 1.0, 0.05),
+            (11.0, 1.0, 0.05),
+            (12.0, 1.0, 0.05),
+        ] {
+            events.extend(
+                monitor
+                    .update(&sample(t, quality, latency))
+                    .into_iter()
+                    .map(|e| (e.session_time, e.name, e.state)),
+            );
+        }
+
+        assert_eq!(
+            events,
+            vec![
+                (5.0, "poor_network".to_string(), AlertState::Raised),
+                (5.0, "high_latency".to_string(), AlertState::Raised),
+                (8.0, "disconnected".to_string(), AlertState::Raised),
+                (9.0, "poor_network".to_string(), AlertState::Cleared),
+                (9.0, "high_latency".to_string(), AlertState::Cleared),
+                (9.0, "disconnected".to_string(), AlertState::Cleared),
+            ]
+        );
+
+        let stats = monitor.stats().unwrap();
+        assert!(stats.connected);
+        assert_eq!(stats.min_quality, 1.0);
+        assert_eq!(monitor.silence(12.0), Some(0.0));
+    }
+}
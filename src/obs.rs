@@ -0,0 +1,451 @@
+//!
+//! OBS Studio integration over obs-websocket (protocol v5), enabled by the
+//! `obs` feature.
+//!
+//! Race events from the session flags and the player's incident count are
+//! mapped to scene switches, source toggles and recording controls.
+
+use crate::states::Flags;
+use base64::engine::general_purpose::STANDARD;
+use base64::Engine;
+use serde_json::{json, Value};
+use sha2::{Digest, Sha256};
+use std::error::Error;
+use std::fmt;
+use std::net::TcpStream;
+use tungstenite::stream::MaybeTlsStream;
+use tungstenite::{Message, WebSocket};
+
+#[cfg(feature = "telemetry")]
+use crate::telemetry::Sample;
+#[cfg(feature = "telemetry")]
+use std::convert::TryInto;
+
+/// Default address of the obs-websocket server.
+pub const DEFAULT_URL: &str = "ws://localhost:4455";
+
+/// obs-websocket RPC version spoken by the client.
+pub const RPC_VERSION: u32 = 1;
+
+const OP_HELLO: u64 = 0;
+const OP_IDENTIFY: u64 = 1;
+const OP_IDENTIFIED: u64 = 2;
+const OP_REQUEST: u64 = 6;
+const OP_REQUEST_RESPONSE: u64 = 7;
+
+///
+/// Race Event Sample
+///
+/// Session flags and the player's incident count at a point in the session.
+#[derive(Debug, Copy, Clone, Default)]
+pub struct RaceEventSample {
+    pub session_time: f64,    // Seconds since session start
+    pub session_flags: Flags, // Session flags
+    pub incidents: i32,       // Player's incident count
+}
+
+///
+/// Race events which can trigger OBS actions.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub enum RaceEvent {
+    GreenFlag,
+    Checkered,
+    CautionStarted,
+    CautionEnded,
+    Incident, // The player picked up incident points
+}
+
+///
+/// Race Event Detector
+///
+/// Turns session flag and incident count changes into race events. Nothing
+/// is reported for the first sample, so connecting mid-race doesn't fire a
+/// green flag.
+///
+/// # Examples
+///
+/// ```
+/// use iracing::obs::{RaceEventDetector, RaceEventSample};
+///
+/// let mut detector = RaceEventDetector::new().with_min_incident(2);
+///
+/// for event in detector.update(&RaceEventSample::default()) {
+///     println!("{:?}", event);
+/// }
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct RaceEventDetector {
+    last: Option<RaceEventSample>,
+    min_incident: i32,
+}
+
+///
+/// Something OBS should do.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ObsAction {
+    SwitchScene(String),
+    SetSourceVisible {
+        scene: String,
+        item_id: i64, // Scene item ID, as listed by GetSceneItemList
+        visible: bool,
+    },
+    StartRecording,
+    StopRecording,
+    SaveReplayBuffer,
+}
+
+///
+/// Triggers
+///
+/// Which actions to run for each race event. Actions run in the order they
+/// were added.
+///
+/// # Examples
+///
+/// ```
+/// use iracing::obs::{ObsAction, RaceEvent, Triggers};
+///
+/// let triggers = Triggers::new()
+///     .on(RaceEvent::GreenFlag, ObsAction::SwitchScene(String::from("Race")))
+///     .on(RaceEvent::GreenFlag, ObsAction::StartRecording)
+///     .on(RaceEvent::Incident, ObsAction::SaveReplayBuffer)
+///     .on(RaceEvent::Checkered, ObsAction::SwitchScene(String::from("Results")));
+///
+/// assert_eq!(triggers.actions(RaceEvent::GreenFlag).count(), 2);
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct Triggers {
+    rules: Vec<(RaceEvent, ObsAction)>,
+}
+
+///
+/// Errors talking to OBS.
+#[derive(Debug)]
+pub enum ObsError {
+    Socket(Box<tungstenite::Error>),
+    Protocol(String),
+    Request {
+        request_type: String,
+        code: i64,
+        comment: Option<String>,
+    },
+}
+
+///
+/// OBS Client
+///
+/// A blocking connection to obs-websocket.
+///
+/// # Examples
+///
+/// ```no_run
+/// use iracing::obs::{ObsAction, ObsClient, DEFAULT_URL};
+///
+/// let mut obs = ObsClient::connect(DEFAULT_URL, Some("password")).expect("Unable to connect to OBS");
+/// obs.send(&ObsAction::SwitchScene(String::from("Race"))).unwrap();
+/// ```
+pub struct ObsClient {
+    socket: WebSocket<MaybeTlsStream<TcpStream>>,
+    next_id: u64,
+}
+
+impl RaceEventSample {
+    ///
+    /// Read a race event sample from a telemetry sample.
+    #[cfg(feature = "telemetry")]
+    pub fn from_sample(sample: &Sample) -> Result<Self, Box<dyn Error>> {
+        let session_flags: u32 = sample.get("SessionFlags")?.try_into()?;
+
+        Ok(RaceEventSample {
+            session_time: sample.get("SessionTime")?.try_into()?,
+            session_flags: Flags::from_bits_truncate(session_flags),
+            incidents: sample.get("PlayerCarMyIncidentCount")?.try_into()?,
+        })
+    }
+
+    fn is_caution(&self) -> bool {
+        self.session_flags
+            .intersects(Flags::CAUTION | Flags::CAUTION_WAVING)
+    }
+}
+
+impl RaceEventDetector {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    ///
+    /// Only report incidents of at least `points`, e.g. 2 to ignore 1x
+    /// off-tracks.
+    pub fn with_min_incident(mut self, points: i32) -> Self {
+        self.min_incident = points;
+        self
+    }
+
+    ///
+    /// Update the detector with a new sample, returning any events which occurred.
+    pub fn update(&mut self, sample: &RaceEventSample) -> Vec<RaceEvent> {
+        let mut events = Vec::new();
+
+        if let Some(last) = self.last {
+            let raised = |flag: Flags| {
+                sample.session_flags.contains(flag) && !last.session_flags.contains(flag)
+            };
+
+            if raised(Flags::GREEN_FLAG) {
+                events.push(RaceEvent::GreenFlag);
+            }
+            if raised(Flags::CHECKERED_FLAG) {
+                events.push(RaceEvent::Checkered);
+            }
+            if sample.is_caution() && !last.is_caution() {
+                events.push(RaceEvent::CautionStarted);
+            } else if !sample.is_caution() && last.is_caution() {
+                events.push(RaceEvent::CautionEnded);
+            }
+
+            let points = sample.incidents - last.incidents;
+            if points > 0 && points >= self.min_incident {
+                events.push(RaceEvent::Incident);
+            }
+        }
+
+        self.last = Some(*sample);
+        events
+    }
+}
+
+impl ObsAction {
+    /// obs-websocket request type for the action.
+    pub fn request_type(&self) -> &'static str {
+        match self {
+            ObsAction::SwitchScene(_) => "SetCurrentProgramScene",
+            ObsAction::SetSourceVisible { .. } => "SetSceneItemEnabled",
+            ObsAction::StartRecording => "StartRecord",
+            ObsAction::StopRecording => "StopRecord",
+            ObsAction::SaveReplayBuffer => "SaveReplayBuffer",
+        }
+    }
+
+    ///
+    /// The request message for the action, tagged with `request_id`.
+    pub fn request(&self, request_id: &str) -> Value {
+        let data = match self {
+            ObsAction::SwitchScene(scene) => json!({ "sceneName": scene }),
+            ObsAction::SetSourceVisible {
+                scene,
+                item_id,
+                visible,
+            } => json!({
+                "sceneName": scene,
+                "sceneItemId": item_id,
+                "sceneItemEnabled": visible,
+            }),
+            _ => json!({}),
+        };
+
+        json!({
+            "op": OP_REQUEST,
+            "d": {
+                "requestType": self.request_type(),
+                "requestId": request_id,
+                "requestData": data,
+            }
+        })
+    }
+}
+
+impl Triggers {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Run `action` when `event` occurs.
+    pub fn on(mut self, event: RaceEvent, action: ObsAction) -> Self {
+        self.rules.push((event, action));
+        self
+    }
+
+    /// Actions to run for `event`.
+    pub fn actions(&self, event: RaceEvent) -> impl Iterator<Item = &ObsAction> {
+        self.rules
+            .iter()
+            .filter(move |(e, _)| *e == event)
+            .map(|(_, action)| action)
+    }
+}
+
+impl fmt::Display for ObsError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ObsError::Socket(e) => write!(f, "OBS connection error: {}", e),
+            ObsError::Protocol(message) => write!(f, "OBS protocol error: {}", message),
+            ObsError::Request {
+                request_type,
+                code,
+                comment,
+            } => {
+                write!(f, "OBS request {} failed with code {}", request_type, code)?;
+                match comment {
+                    Some(comment) => write!(f, ": {}", comment),
+                    None => Ok(()),
+                }
+            }
+        }
+    }
+}
+
+impl Error for ObsError {}
+
+impl From<tungstenite::Error> for ObsError {
+    fn from(e: tungstenite::Error) -> Self {
+        ObsError::Socket(Box::new(e))
+    }
+}
+
+///
+/// Authentication string for the Identify message, from the password and the
+/// salt and challenge sent in Hello.
+pub fn authentication(password: &str, salt: &str, challenge: &str) -> String {
+    let secret = STANDARD.encode(Sha256::digest(format!("{}{}", password, salt)));
+    STANDARD.encode(Sha256::digest(format!("{}{}", secret, challenge)))
+}
+
+impl ObsClient {
+    ///
+    /// Connect and identify to obs-websocket at `url`, e.g. [`DEFAULT_URL`].
+    ///
+    /// `password` is only used if the server has authentication enabled.
+    pub fn connect(url: &str, password: Option<&str>) -> Result<Self, ObsError> {
+        let (socket, _) = tungstenite::connect(url)?;
+        let mut client = ObsClient { socket, next_id: 0 };
+
+        let hello = client.receive(OP_HELLO)?;
+        let mut identify = json!({ "rpcVersion": RPC_VERSION });
+
+        if let Some(auth) = hello.get("authentication") {
+            let password = password
+                .ok_or_else(|| ObsError::Protocol(String::from("OBS requires a password")))?;
+            let salt = auth["salt"].as_str().unwrap_or_default();
+            let challenge = auth["challenge"].as_str().unwrap_or_default();
+            identify["authentication"] = json!(authentication(password, salt, challenge));
+        }
+
+        client.write(json!({ "op": OP_IDENTIFY, "d": identify }))?;
+        client.receive(OP_IDENTIFIED)?;
+
+        Ok(client)
+    }
+
+    ///
+    /// Send an action, waiting for OBS to acknowledge it.
+    pub fn send(&mut self, action: &ObsAction) -> Result<(), ObsError> {
+        self.next_id += 1;
+        let request_id = self.next_id.to_string();
+        self.write(action.request(&request_id))?;
+
+        loop {
+            let response = self.receive(OP_REQUEST_RESPONSE)?;
+            if response["requestId"].as_str() != Some(request_id.as_str()) {
+                continue;
+            }
+
+            let status = &response["requestStatus"];
+            if status["result"].as_bool() == Some(true) {
+                return Ok(());
+            }
+
+            return Err(ObsError::Request {
+                request_type: action.request_type().to_owned(),
+                code: status["code"].as_i64().unwrap_or_default(),
+                comment: status["comment"].as_str().map(str::to_owned),
+            });
+        }
+    }
+
+    ///
+    /// Run the actions triggered by each event, stopping at the first error.
+    pub fn trigger(&mut self, triggers: &Triggers, events: &[RaceEvent]) -> Result<(), ObsError> {
+        for event in events {
+            for action in triggers.actions(*event) {
+                self.send(action)?;
+            }
+        }
+        Ok(())
+    }
+
+    fn write(&mut self, message: Value) -> Result<(), ObsError> {
+        self.socket.send(Message::Text(message.to_string()))?;
+        Ok(())
+    }
+
+    /// Wait for a message with opcode `op`, returning its data. Other messages
+    /// (e.g. events) are skipped.
+    fn receive(&mut self, op: u64) -> Result<Value, ObsError> {
+        loop {
+            let text = match self.socket.read()? {
+                Message::Text(text) => text,
+                Message::Close(_) => {
+                    return Err(ObsError::Protocol(String::from(
+                        "OBS closed the connection",
+                    )))
+                }
+                _ => continue,
+            };
+
+            let message: Value = serde_json::from_str(&text)
+                .map_err(|e| ObsError::Protocol(format!("Invalid message: {}", e)))?;
+
+            if message["op"].as_u64() == Some(op) {
+                return Ok(message["d"].clone());
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn events_and_requests() {
+        let mut detector = RaceEventDetector::new().with_min_incident(2);
+        let mut sample = RaceEventSample {
+            session_flags: Flags::GREEN_FLAG,
+            ..Default::default()
+        };
+        assert!(detector.update(&sample).is_empty());
+
+        sample.session_flags = Flags::CAUTION;
+        sample.incidents = 1;
+        assert_eq!(detector.update(&sample), vec![RaceEvent::CautionStarted]);
+
+        sample.session_flags = Flags::GREEN_FLAG;
+        sample.incidents = 5;
+        assert_eq!(
+            detector.update(&sample),
+            vec![
+                RaceEvent::GreenFlag,
+                RaceEvent::CautionEnded,
+                RaceEvent::Incident
+            ]
+        );
+
+        sample.session_flags = Flags::CHECKERED_FLAG;
+        assert_eq!(detector.update(&sample), vec![RaceEvent::Checkered]);
+
+        let request = ObsAction::SwitchScene(String::from("Race")).request("7");
+        assert_eq!(request["op"], 6);
+        assert_eq!(request["d"]["requestType"], "SetCurrentProgramScene");
+        assert_eq!(request["d"]["requestData"]["sceneName"], "Race");
+
+        assert_eq!(
+            authentication(
+                "supersecretpassword",
+                "lM1GncleQOaCu9lT1yeUZhFYnqhsLLP1G5lAGo3ixaI=",
+                "+IxH4CnCiqpX1rM9scsNynZzbOe4KhDeYcTNS3PDaeY="
+            ),
+            "1Ct943GAT+6YQUUX47Ia/ncufilbe6+oD6lY+5kaCu4="
+        );
+    }
+}
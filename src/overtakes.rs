@@ -0,0 +1,287 @@
+use crate::validity;
+use serde::{Deserialize, Serialize};
+
+#[cfg(feature = "telemetry")]
+use crate::telemetry::Sample;
+#[cfg(feature = "telemetry")]
+use std::convert::TryInto;
+#[cfg(feature = "telemetry")]
+use std::error::Error;
+
+/// Seconds after leaving pit road during which a car's position changes are ignored
+const PIT_GRACE: f64 = 10.0;
+
+///
+/// Race Sample
+///
+/// Per-car positions and track locations at a point in time, indexed by car index.
+#[derive(Debug, Clone, Default)]
+pub struct RaceSample {
+    pub session_time: f64,      // Seconds since session start
+    pub positions: Vec<i32>,    // CarIdxPosition - official race position, 0 if not yet classified
+    pub laps: Vec<i32>,         // CarIdxLap
+    pub lap_dist_pct: Vec<f32>, // CarIdxLapDistPct - -1 when the car isn't on track
+    pub on_pit_road: Vec<bool>, // CarIdxOnPitRoad
+}
+
+///
+/// Events reported by the overtake detector.
+#[derive(Debug, Copy, Clone, PartialEq, Serialize, Deserialize)]
+pub enum RaceEvent {
+    /// A car's official position changed and has held for the debounce period
+    PositionChange {
+        car_idx: usize,
+        from: i32,
+        to: i32,
+        lap: i32,
+    },
+
+    /// A car passed another on track
+    Overtake {
+        overtaker: usize,
+        overtaken: usize,
+        lap: i32,
+        lap_dist_pct: f32,
+    },
+}
+
+#[derive(Debug, Copy, Clone, Default)]
+struct CarState {
+    distance: Option<f64>, // Continuous race distance in laps
+    pct: f32,
+    position: i32,
+    pending: Option<(i32, f64)>, // Position waiting out the debounce period, and when it was first seen
+    pit_exit: Option<f64>,
+    on_pit_road: bool,
+}
+
+///
+/// Overtake Detector
+///
+/// Detects on-track passes and changes of official position by comparing
+/// per-car positions and lap distances between samples.
+///
+/// Cars on pit road, or which have left it recently, are ignored so that pit
+/// cycles don't show up as passes.
+///
+/// # Examples
+///
+/// ```
+/// use iracing::overtakes::{OvertakeDetector, RaceEvent, RaceSample};
+///
+/// let mut detector = OvertakeDetector::new();
+///
+/// for event in detector.update(&RaceSample::default()) {
+///     if let RaceEvent::Overtake { overtaker, overtaken, .. } = event {
+///         println!("Car {} passes car {}", overtaker, overtaken);
+///     }
+/// }
+/// ```
+#[derive(Debug, Clone)]
+pub struct OvertakeDetector {
+    debounce: f64,
+    cars: Vec<CarState>,
+}
+
+impl Default for OvertakeDetector {
+    fn default() -> Self {
+        OvertakeDetector {
+            debounce: 2.0,
+            cars: Vec::new(),
+        }
+    }
+}
+
+impl RaceSample {
+    ///
+    /// Read a race sample from a telemetry sample.
+    #[cfg(feature = "telemetry")]
+    pub fn from_sample(sample: &Sample) -> Result<Self, Box<dyn Error>> {
+        Ok(RaceSample {
+            session_time: sample.get("SessionTime")?.try_into()?,
+            positions: sample.get("CarIdxPosition")?.try_into()?,
+            laps: sample.get("CarIdxLap")?.try_into()?,
+            lap_dist_pct: sample.get("CarIdxLapDistPct")?.try_into()?,
+            on_pit_road: sample.get("CarIdxOnPitRoad")?.into(),
+        })
+    }
+}
+
+impl OvertakeDetector {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    ///
+    /// Seconds a new official position must hold before it is reported.
+    pub fn with_debounce(mut self, seconds: f64) -> Self {
+        self.debounce = seconds;
+        self
+    }
+
+    ///
+    /// Update with a new sample, returning any position changes and overtakes.
+    pub fn update(&mut self, sample: &RaceSample) -> Vec<RaceEvent> {
+        let now = sample.session_time;
+        let count = sample.lap_dist_pct.len().max(sample.positions.len());
+        if self.cars.len() < count {
+            self.cars.resize(count, CarState::default());
+        }
+
+        let previous: Vec<Option<f64>> = self.cars.iter().map(|c| c.distance).collect();
+        let mut events = Vec::new();
+
+        for (idx, car) in self.cars.iter_mut().enumerate().take(count) {
+            let on_pit_road = sample.on_pit_road.get(idx).copied().unwrap_or(false);
+            if car.on_pit_road && !on_pit_road {
+                car.pit_exit = Some(now);
+            }
+            car.on_pit_road = on_pit_road;
+
+            let pct = sample
+                .lap_dist_pct
+                .get(idx)
+                .copied()
+                .and_then(validity::lap_dist_pct);
+            car.distance = pct.map(|pct| match car.distance {
+                Some(distance) => {
+                    let mut delta = (pct - car.pct) as f64;
+                    if delta < -0.5 {
+                        delta += 1.0;
+                    } else if delta > 0.5 {
+                        delta -= 1.0;
+                    }
+                    distance + delta
+                }
+                None => {
+                    let lap = sample.laps.get(idx).copied().unwrap_or(0).max(0);
+                    lap as f64 + pct as f64
+                }
+            });
+            if let Some(pct) = pct {
+                car.pct = pct;
+            }
+        }
+
+        // On-track passes: race distances crossing between samples
+        for a in 0..count {
+            for b in 0..count {
+                if a == b || self.ignored(a, now) || self.ignored(b, now) {
+                    continue;
+                }
+
+                let (a_before, b_before, a_now, b_now) = match (
+                    previous[a],
+                    previous[b],
+                    self.cars[a].distance,
+                    self.cars[b].distance,
+                ) {
+                    (Some(a0), Some(b0), Some(a1), Some(b1)) => (a0, b0, a1, b1),
+                    _ => continue,
+                };
+
+                if a_before < b_before && a_now > b_now {
+                    events.push(RaceEvent::Overtake {
+                        overtaker: a,
+                        overtaken: b,
+                        lap: sample.laps.get(a).copied().unwrap_or(0),
+                        lap_dist_pct: self.cars[a].pct,
+                    });
+                }
+            }
+        }
+
+        // Official position changes, once they've held for the debounce period
+        for idx in 0..count {
+            let position = sample.positions.get(idx).copied().unwrap_or(0);
+            let ignored = self.ignored(idx, now);
+            let debounce = self.debounce;
+            let car = &mut self.cars[idx];
+
+            if position <= 0 || position == car.position {
+                car.pending = None;
+                continue;
+            }
+            if car.position <= 0 || ignored {
+                // First classification, or a pit cycle - take the position silently
+                car.position = position;
+                car.pending = None;
+                continue;
+            }
+
+            let since = match car.pending {
+                Some((pending, since)) if pending == position => since,
+                _ => {
+                    car.pending = Some((position, now));
+                    now
+                }
+            };
+
+            if now - since >= debounce {
+                events.push(RaceEvent::PositionChange {
+                    car_idx: idx,
+                    from: car.position,
+                    to: position,
+                    lap: sample.laps.get(idx).copied().unwrap_or(0),
+                });
+                car.position = position;
+                car.pending = None;
+            }
+        }
+
+        events
+    }
+
+    /// True if a car is on pit road or only just left it.
+    fn ignored(&self, idx: usize, now: f64) -> bool {
+        let car = &self.cars[idx];
+        car.on_pit_road || matches!(car.pit_exit, Some(exit) if now - exit < PIT_GRACE)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sample(t: f64, pct: [f32; 3], positions: [i32; 3], pits: [bool; 3]) -> RaceSample {
+        RaceSample {
+            session_time: t,
+            positions: positions.to_vec(),
+            laps: vec![3; 3],
+            lap_dist_pct: pct.to_vec(),
+            on_pit_road: pits.to_vec(),
+        }
+    }
+
+    #[test]
+    fn detect_overtake() {
+        let mut detector = OvertakeDetector::new().with_debounce(1.0);
+        let no_pits = [false; 3];
+
+        detector.update(&sample(0.0, [0.50, 0.51, 0.10], [2, 1, 3], no_pits));
+
+        let events = detector.update(&sample(1.0, [0.53, 0.52, 0.11], [2, 1, 3], no_pits));
+        assert_eq!(
+            events,
+            vec![RaceEvent::Overtake {
+                overtaker: 0,
+                overtaken: 1,
+                lap: 3,
+                lap_dist_pct: 0.53
+            }]
+        );
+
+        // Official positions update at the next timing line, and must hold
+        assert!(detector
+            .update(&sample(2.0, [0.56, 0.55, 0.12], [1, 2, 3], no_pits))
+            .is_empty());
+        let events = detector.update(&sample(3.0, [0.59, 0.58, 0.13], [1, 2, 3], no_pits));
+        assert_eq!(events.len(), 2);
+
+        // A car in the pits being passed isn't an overtake
+        let pits = [false, false, true];
+        detector.update(&sample(4.0, [0.12, 0.61, 0.14], [1, 2, 3], pits));
+        let events = detector.update(&sample(5.0, [0.16, 0.62, 0.15], [1, 2, 3], pits));
+        assert!(events.is_empty());
+    }
+}
@@ -0,0 +1,379 @@
+use crate::strategy::{CarState, TireModel};
+use crate::validity::PendingLap;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+#[cfg(feature = "telemetry")]
+use crate::states::Flags;
+#[cfg(feature = "telemetry")]
+use crate::telemetry::Sample;
+#[cfg(feature = "telemetry")]
+use std::convert::TryInto;
+#[cfg(feature = "telemetry")]
+use std::error::Error;
+
+///
+/// Pace Sample
+///
+/// Per-car laps and pit road state at a point in the session, indexed by car
+/// index.
+#[derive(Debug, Clone, Default)]
+pub struct PaceSample {
+    pub session_time: f64,        // SessionTime
+    pub caution: bool,            // A full-course caution is out
+    pub laps_completed: Vec<i32>, // CarIdxLapCompleted
+    pub last_lap_time: Vec<f32>,  // CarIdxLastLapTime - -1 until a lap is timed (s)
+    pub on_pit_road: Vec<bool>,   // CarIdxOnPitRoad
+}
+
+///
+/// A car's pace, fitted to its lap times against the age of its tires.
+#[derive(Debug, Copy, Clone, PartialEq, Serialize, Deserialize)]
+pub struct PaceModel {
+    pub car_idx: usize,
+    pub base: f64,        // Lap time on new tires (s)
+    pub degradation: f64, // Lap time lost per lap of tire age (s)
+    pub laps: usize,      // Laps the model is fitted to
+    pub tire_age: i32,    // Laps on the car's current tires
+}
+
+///
+/// Pace Tracker
+///
+/// Fits a linear tire degradation model to each car's representative laps, and
+/// learns how long each car runs between stops, so the strategy planner can
+/// predict when rivals will pit and how fast they'll be.
+///
+/// Laps run under caution, in and out laps and laps much slower than the
+/// car's median, such as laps in traffic, are left out.
+///
+/// # Examples
+///
+/// ```
+/// use iracing::pace::{PaceSample, PaceTracker};
+///
+/// let mut pace = PaceTracker::new();
+/// pace.update(&PaceSample::default());
+///
+/// if let Some(model) = pace.model(3) {
+///     println!("{:.3}s on new tires, losing {:.3}s a lap", model.base, model.degradation);
+/// }
+/// ```
+#[derive(Debug, Clone)]
+pub struct PaceTracker {
+    outlier: f64,
+    cars: HashMap<usize, CarLaps>,
+}
+
+/// Laps and stints of a single car.
+#[derive(Debug, Clone, Default)]
+struct CarLaps {
+    laps_completed: i32,
+    last_lap_time: f32,
+    stint_start: i32,
+    on_pit_road: bool,
+    excluded: bool,
+    pending: Option<(PendingLap, i32)>, // Lap waiting for its time, and its tire age
+    times: Vec<(i32, f64)>,
+    stints: Vec<i32>,
+}
+
+impl PaceSample {
+    ///
+    /// Read a pace sample from a telemetry sample.
+    #[cfg(feature = "telemetry")]
+    pub fn from_sample(sample: &Sample) -> Result<Self, Box<dyn Error>> {
+        let session_flags: u32 = sample.get("SessionFlags")?.try_into()?;
+
+        Ok(PaceSample {
+            session_time: sample.get("SessionTime")?.try_into()?,
+            caution: Flags::from_bits_truncate(session_flags)
+                .intersects(Flags::CAUTION | Flags::CAUTION_WAVING),
+            laps_completed: sample.get("CarIdxLapCompleted")?.try_into()?,
+            last_lap_time: sample.get("CarIdxLastLapTime")?.try_into()?,
+            on_pit_road: sample.get("CarIdxOnPitRoad")?.into(),
+        })
+    }
+}
+
+impl PaceModel {
+    /// Expected lap time on tires `age` laps old (s).
+    pub fn lap_time(&self, age: i32) -> f64 {
+        self.base + self.degradation * age as f64
+    }
+
+    /// Expected time for the car's next lap (s).
+    pub fn projected(&self) -> f64 {
+        self.lap_time(self.tire_age)
+    }
+
+    ///
+    /// Tire model for the strategy planner, taking `change_time` seconds to
+    /// change tires.
+    pub fn tire_model(&self, change_time: f64) -> TireModel {
+        TireModel {
+            age: self.tire_age,
+            degradation: self.degradation,
+            change_time,
+        }
+    }
+}
+
+impl Default for PaceTracker {
+    fn default() -> Self {
+        PaceTracker {
+            outlier: 0.03,
+            cars: HashMap::new(),
+        }
+    }
+}
+
+impl PaceTracker {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    ///
+    /// Leave out laps slower than the car's median by more than this fraction,
+    /// 0.03 (3%) by default.
+    pub fn with_outlier_threshold(mut self, fraction: f64) -> Self {
+        self.outlier = fraction;
+        self
+    }
+
+    ///
+    /// Update with a new sample, recording the lap time of every car which has
+    /// completed a lap, once the sim has updated it, and the length of every
+    /// stint ended by a stop.
+    pub fn update(&mut self, sample: &PaceSample) {
+        for (car_idx, completed) in sample.laps_completed.iter().enumerate() {
+            let on_pit_road = sample.on_pit_road.get(car_idx).copied().unwrap_or(false);
+            let time = sample.last_lap_time.get(car_idx).copied().unwrap_or(-1.0);
+            let car = self.cars.entry(car_idx).or_insert_with(|| CarLaps {
+                laps_completed: *completed,
+                last_lap_time: time,
+                stint_start: *completed,
+                on_pit_road,
+                excluded: true,
+                ..Default::default()
+            });
+            let previous_time = std::mem::replace(&mut car.last_lap_time, time);
+
+            // A lap still waiting is timed before the next is added
+            if let Some((lap, age)) = car.pending {
+                if let Some(reading) = lap.poll(time, sample.session_time) {
+                    car.times
+                        .extend(reading.value().map(|time| (age, time as f64)));
+                    car.pending = None;
+                }
+            }
+
+            if *completed > car.laps_completed {
+                if !car.excluded {
+                    let age = *completed - car.stint_start - 1;
+                    let lap = PendingLap::new(previous_time, sample.session_time);
+                    match lap.poll(time, sample.session_time) {
+                        Some(reading) => car
+                            .times
+                            .extend(reading.value().map(|time| (age, time as f64))),
+                        None => car.pending = Some((lap, age)),
+                    }
+                }
+                car.laps_completed = *completed;
+                car.excluded = false;
+            }
+
+            if on_pit_road && !car.on_pit_road && *completed > car.stint_start {
+                car.stints.push(*completed - car.stint_start);
+            }
+            if !on_pit_road && car.on_pit_road {
+                car.stint_start = *completed;
+            }
+            car.on_pit_road = on_pit_road;
+            car.excluded |= on_pit_road || sample.caution;
+        }
+    }
+
+    ///
+    /// Pace model for a car, once it has at least three representative laps
+    /// over two or more tire ages.
+    pub fn model(&self, car_idx: usize) -> Option<PaceModel> {
+        let car = self.cars.get(&car_idx)?;
+
+        let mut sorted: Vec<f64> = car.times.iter().map(|(_, t)| *t).collect();
+        sorted.sort_by(|a, b| a.partial_cmp(b).unwrap_or(std::cmp::Ordering::Equal));
+        let median = *sorted.get(sorted.len() / 2)?;
+
+        let laps: Vec<(f64, f64)> = car
+            .times
+            .iter()
+            .filter(|(_, t)| *t <= median * (1.0 + self.outlier))
+            .map(|(age, t)| (*age as f64, *t))
+            .collect();
+        if laps.len() < 3 {
+            return None;
+        }
+
+        // Least squares fit of lap time against tire age
+        let n = laps.len() as f64;
+        let mean_age = laps.iter().map(|(a, _)| a).sum::<f64>() / n;
+        let mean_time = laps.iter().map(|(_, t)| t).sum::<f64>() / n;
+        let variance: f64 = laps.iter().map(|(a, _)| (a - mean_age).powi(2)).sum();
+        if variance <= 0.0 {
+            return None;
+        }
+        let covariance: f64 = laps
+            .iter()
+            .map(|(a, t)| (a - mean_age) * (t - mean_time))
+            .sum();
+        let degradation = covariance / variance;
+
+        Some(PaceModel {
+            car_idx,
+            base: mean_time - degradation * mean_age,
+            degradation,
+            laps: laps.len(),
+            tire_age: car.laps_completed - car.stint_start,
+        })
+    }
+
+    /// Pace models of every car with enough laps.
+    pub fn models(&self) -> Vec<PaceModel> {
+        let mut models: Vec<PaceModel> = self
+            .cars
+            .keys()
+            .filter_map(|car_idx| self.model(*car_idx))
+            .collect();
+        models.sort_by_key(|m| m.car_idx);
+        models
+    }
+
+    ///
+    /// Average stint length in laps, from a car's own stops or, before its
+    /// first stop, from every car's.
+    pub fn stint_length(&self, car_idx: usize) -> Option<f64> {
+        let average = |stints: Vec<i32>| {
+            if stints.is_empty() {
+                None
+            } else {
+                Some(stints.iter().sum::<i32>() as f64 / stints.len() as f64)
+            }
+        };
+
+        let own = self.cars.get(&car_idx).map(|c| c.stints.clone());
+        average(own.unwrap_or_default()).or_else(|| {
+            average(
+                self.cars
+                    .values()
+                    .flat_map(|c| c.stints.iter().copied())
+                    .collect(),
+            )
+        })
+    }
+
+    ///
+    /// Lap a car is expected to pit at the end of, from the start of its
+    /// current stint and its average stint length.
+    pub fn predicted_pit_lap(&self, car_idx: usize) -> Option<i32> {
+        let car = self.cars.get(&car_idx)?;
+        let lap = car.stint_start + self.stint_length(car_idx)?.round() as i32;
+        Some(lap.max(car.laps_completed + 1))
+    }
+
+    ///
+    /// A car as seen by the strategy planner, `gap` seconds behind the leader,
+    /// with its projected pace and predicted stop.
+    pub fn car_state(&self, car_idx: usize, gap: f64) -> Option<CarState> {
+        let model = self.model(car_idx)?;
+
+        Some(CarState {
+            car_idx,
+            gap,
+            lap_time: model.projected(),
+            pit_lap: self.predicted_pit_lap(car_idx),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn fit_degradation() {
+        let mut pace = PaceTracker::new();
+        let mut sample = PaceSample {
+            session_time: 0.0,
+            caution: false,
+            laps_completed: vec![0, 0],
+            last_lap_time: vec![-1.0; 2],
+            on_pit_road: vec![false; 2],
+        };
+        pace.update(&sample);
+
+        for lap in 1..=15 {
+            // Car 0 loses 0.1s a lap on 100s, but is held up on lap 4 and runs
+            // lap 8 under caution. Car 1 pits at the end of lap 10.
+            let mut time = 100.0 + 0.1 * (lap - 1) as f32;
+            if lap == 4 {
+                time += 5.0;
+            }
+            sample.session_time = lap as f64 * 100.0;
+            sample.caution = lap == 7;
+            sample.laps_completed = vec![lap; 2];
+            sample.last_lap_time = vec![time, 100.5];
+            sample.on_pit_road = vec![false, lap == 10];
+            pace.update(&sample);
+
+            if lap == 10 {
+                sample.on_pit_road = vec![false; 2];
+                pace.update(&sample);
+            }
+        }
+
+        let model = pace.model(0).unwrap();
+        assert_eq!(model.laps, 12);
+        assert!((model.base - 100.0).abs() < 1e-3);
+        assert!((model.degradation - 0.1).abs() < 1e-4);
+        assert_eq!(model.tire_age, 15);
+        assert!((model.tire_model(20.0).degradation - 0.1).abs() < 1e-4);
+
+        // Car 1's ten lap stint predicts car 0 stopping after lap 15, and car 1
+        // ten laps after its own stop
+        assert_eq!(pace.stint_length(1), Some(10.0));
+        assert_eq!(pace.predicted_pit_lap(0), Some(16));
+        assert_eq!(pace.predicted_pit_lap(1), Some(20));
+
+        let state = pace.car_state(1, 12.0).unwrap();
+        assert_eq!(state.pit_lap, Some(20));
+        assert!((state.lap_time - 100.5).abs() < 1e-6);
+    }
+
+    #[test]
+    fn late_lap_times() {
+        let mut pace = PaceTracker::new();
+        let mut update = |session_time: f64, completed: i32, time: f32| {
+            pace.update(&PaceSample {
+                session_time,
+                laps_completed: vec![completed],
+                last_lap_time: vec![time],
+                on_pit_road: vec![false],
+                ..Default::default()
+            });
+        };
+
+        // Each lap's time is updated a tick after the lap completes
+        update(0.0, 0, -1.0);
+        update(100.0, 1, -1.0);
+        update(100.02, 1, 100.0);
+        for lap in 2..=4 {
+            let t = lap as f64 * 100.0;
+            update(t, lap, 100.0 + (lap - 2) as f32 * 0.5);
+            update(t + 0.02, lap, 100.0 + (lap - 1) as f32 * 0.5);
+        }
+
+        let model = pace.model(0).unwrap();
+        assert_eq!(model.laps, 3);
+        assert!((model.degradation - 0.5).abs() < 1e-4);
+    }
+}
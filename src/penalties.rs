@@ -0,0 +1,268 @@
+use crate::session::SessionResult;
+use crate::states::Flags;
+use serde::{Deserialize, Serialize};
+
+#[cfg(feature = "telemetry")]
+use crate::telemetry::Sample;
+#[cfg(feature = "telemetry")]
+use std::convert::TryInto;
+#[cfg(feature = "telemetry")]
+use std::error::Error;
+
+/// Results `ReasonOutStr` value for a disqualified car
+const DISQUALIFIED_REASON: &str = "Disqualified";
+
+///
+/// Type of penalty shown to a car.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum PenaltyKind {
+    /// Black flag - drive-through or stop-and-go to be served in the pits
+    BlackFlag,
+
+    /// Furled black flag - slow-down or warning
+    Furled,
+
+    /// Meatball flag - car must pit for repairs
+    Repair,
+
+    /// Disqualified from the session
+    Disqualified,
+}
+
+///
+/// A single penalty in the ledger.
+#[derive(Debug, Copy, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Penalty {
+    pub car_idx: usize,
+    pub kind: PenaltyKind,
+    pub lap: Option<i32>,        // Lap the penalty was issued on
+    pub issued_at: Option<f64>,  // Session time the penalty was issued, None if taken from results
+    pub cleared_at: Option<f64>, // Session time the penalty was served or cleared
+}
+
+///
+/// Events reported by the penalty ledger.
+#[derive(Debug, Copy, Clone, PartialEq, Serialize, Deserialize)]
+pub enum PenaltyEvent {
+    Issued(Penalty),
+    Cleared(Penalty),
+}
+
+///
+/// Penalty Sample
+///
+/// Per-car session flags and laps at a point in the session, indexed by car index.
+#[derive(Debug, Clone, Default)]
+pub struct PenaltySample {
+    pub session_time: f64,     // Seconds since session start
+    pub car_flags: Vec<Flags>, // CarIdxSessionFlags
+    pub car_laps: Vec<i32>,    // CarIdxLap
+}
+
+///
+/// Penalties
+///
+/// A ledger of penalties (black flags, furled flags, repair flags and
+/// disqualifications) issued to each car over a session, built by watching the
+/// per-car session flags.
+///
+/// # Examples
+///
+/// ```
+/// use iracing::penalties::{Penalties, PenaltyEvent, PenaltySample};
+///
+/// let mut penalties = Penalties::new();
+///
+/// for event in penalties.update(&PenaltySample::default()) {
+///     if let PenaltyEvent::Issued(p) = event {
+///         println!("Car {} shown {:?}", p.car_idx, p.kind);
+///     }
+/// }
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct Penalties {
+    ledger: Vec<Penalty>,
+    flags: Vec<Flags>,
+}
+
+impl PenaltyKind {
+    const ALL: [(PenaltyKind, Flags); 4] = [
+        (PenaltyKind::BlackFlag, Flags::BLACK_FLAG),
+        (PenaltyKind::Furled, Flags::FURLED_FLAG),
+        (PenaltyKind::Repair, Flags::REPAIR_FLAG),
+        (PenaltyKind::Disqualified, Flags::DISQUALIFIED_FLAG),
+    ];
+}
+
+impl PenaltySample {
+    ///
+    /// Read a penalty sample from a telemetry sample.
+    #[cfg(feature = "telemetry")]
+    pub fn from_sample(sample: &Sample) -> Result<Self, Box<dyn Error>> {
+        let car_flags: Vec<u32> = sample.get("CarIdxSessionFlags")?.try_into()?;
+
+        Ok(PenaltySample {
+            session_time: sample.get("SessionTime")?.try_into()?,
+            car_flags: car_flags
+                .into_iter()
+                .map(Flags::from_bits_truncate)
+                .collect(),
+            car_laps: sample.get("CarIdxLap")?.try_into()?,
+        })
+    }
+}
+
+impl Penalties {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    ///
+    /// Update the ledger with a new sample, returning any penalties issued or cleared.
+    pub fn update(&mut self, sample: &PenaltySample) -> Vec<PenaltyEvent> {
+        let mut events = Vec::new();
+
+        for (car_idx, flags) in sample.car_flags.iter().enumerate() {
+            let previous = self.flags.get(car_idx).copied().unwrap_or_default();
+
+            for (kind, flag) in PenaltyKind::ALL.iter() {
+                if flags.contains(*flag) && !previous.contains(*flag) {
+                    let penalty = Penalty {
+                        car_idx,
+                        kind: *kind,
+                        lap: sample.car_laps.get(car_idx).copied(),
+                        issued_at: Some(sample.session_time),
+                        cleared_at: None,
+                    };
+
+                    self.ledger.push(penalty);
+                    events.push(PenaltyEvent::Issued(penalty));
+                } else if !flags.contains(*flag) && previous.contains(*flag) {
+                    let open = self.ledger.iter_mut().rev().find(|p| {
+                        p.car_idx == car_idx && p.kind == *kind && p.cleared_at.is_none()
+                    });
+
+                    if let Some(penalty) = open {
+                        penalty.cleared_at = Some(sample.session_time);
+                        events.push(PenaltyEvent::Cleared(*penalty));
+                    }
+                }
+            }
+        }
+
+        self.flags = sample.car_flags.clone();
+        events
+    }
+
+    ///
+    /// Add disqualifications recorded in session results.
+    ///
+    /// Cars which already have a disqualification in the ledger are skipped.
+    pub fn add_results(&mut self, results: &[SessionResult]) {
+        for result in results {
+            if result.reason_out_str != DISQUALIFIED_REASON || result.car_idx < 0 {
+                continue;
+            }
+
+            let car_idx = result.car_idx as usize;
+            let known = self
+                .for_car(car_idx)
+                .any(|p| p.kind == PenaltyKind::Disqualified);
+
+            if !known {
+                self.ledger.push(Penalty {
+                    car_idx,
+                    kind: PenaltyKind::Disqualified,
+                    lap: Some(result.lap),
+                    issued_at: None,
+                    cleared_at: None,
+                });
+            }
+        }
+    }
+
+    /// All penalties in the order they were issued.
+    pub fn all(&self) -> &[Penalty] {
+        &self.ledger
+    }
+
+    /// Penalties issued to a given car.
+    pub fn for_car(&self, car_idx: usize) -> impl Iterator<Item = &Penalty> {
+        self.ledger.iter().filter(move |p| p.car_idx == car_idx)
+    }
+
+    /// Penalties which have not yet been served or cleared.
+    pub fn outstanding(&self) -> impl Iterator<Item = &Penalty> {
+        self.ledger.iter().filter(|p| p.cleared_at.is_none())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn issue_and_clear() {
+        let mut penalties = Penalties::new();
+        let mut sample = PenaltySample {
+            session_time: 1.0,
+            car_flags: vec![Flags::empty(); 2],
+            car_laps: vec![3, 4],
+        };
+
+        assert!(penalties.update(&sample).is_empty());
+
+        sample.session_time = 2.0;
+        sample.car_flags[1] = Flags::BLACK_FLAG;
+        let events = penalties.update(&sample);
+        assert_eq!(events.len(), 1);
+        assert!(matches!(
+            events[0],
+            PenaltyEvent::Issued(Penalty {
+                car_idx: 1,
+                kind: PenaltyKind::BlackFlag,
+                lap: Some(4),
+                ..
+            })
+        ));
+        assert_eq!(penalties.outstanding().count(), 1);
+
+        sample.session_time = 30.0;
+        sample.car_flags[1] = Flags::empty();
+        let events = penalties.update(&sample);
+        assert!(matches!(
+            events[0],
+            PenaltyEvent::Cleared(Penalty {
+                cleared_at: Some(t),
+                ..
+            }) if t == 30.0
+        ));
+        assert_eq!(penalties.outstanding().count(), 0);
+        assert_eq!(penalties.for_car(1).count(), 1);
+    }
+
+    #[cfg(feature = "telemetry")]
+    #[test]
+    fn from_sample() {
+        use crate::recording::{Recording, SnapshotBuilder};
+        use crate::telemetry::Value;
+
+        // Types as the sim publishes them, session flags are a bitfield array
+        let mut recording = Recording::new();
+        recording.push(
+            SnapshotBuilder::new(1)
+                .with_value("SessionTime", Value::DOUBLE(12.0))
+                .with_value(
+                    "CarIdxSessionFlags",
+                    Value::BitsVec(vec![0, Flags::BLACK_FLAG.bits()]),
+                )
+                .with_value("CarIdxLap", Value::IntVec(vec![3, 4]))
+                .build(),
+        );
+        let sample = recording.player().last().unwrap().unwrap();
+
+        let sample = PenaltySample::from_sample(&sample).unwrap();
+        assert_eq!(sample.car_flags, vec![Flags::empty(), Flags::BLACK_FLAG]);
+        assert_eq!(sample.car_laps, vec![3, 4]);
+    }
+}
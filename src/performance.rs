@@ -0,0 +1,252 @@
+use crate::alerts::{AlertEvent, AlertRule, Alerts, Metric};
+use serde::{Deserialize, Serialize};
+use std::collections::VecDeque;
+
+#[cfg(feature = "telemetry")]
+use crate::telemetry::Sample;
+#[cfg(feature = "telemetry")]
+use std::convert::TryInto;
+#[cfg(feature = "telemetry")]
+use std::error::Error;
+
+///
+/// Performance Sample
+///
+/// How hard the sim is working at a point in time.
+#[derive(Debug, Copy, Clone, Default)]
+pub struct PerformanceSample {
+    pub session_time: f64,        // SessionTime
+    pub frame_rate: f32,          // FrameRate - FPS
+    pub cpu_fg: f32,              // CpuUsageFG - main thread, 0.0 to 1.0
+    pub cpu_bg: f32,              // CpuUsageBG - background thread, 0.0 to 1.0
+    pub gpu: f32,                 // GpuUsage - 0.0 to 1.0
+    pub page_faults: Option<f32>, // MemPageFaultSec - hard faults a second
+}
+
+///
+/// Sim performance over the monitor's window.
+#[derive(Debug, Copy, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct PerformanceStats {
+    pub frame_rate: f32,          // Average FPS
+    pub min_frame_rate: f32,      // Lowest FPS
+    pub frame_time: f32,          // Average time per frame (ms)
+    pub cpu_fg: f32,              // Average main thread usage
+    pub cpu_bg: f32,              // Average background thread usage
+    pub gpu: f32,                 // Average GPU usage
+    pub page_faults: Option<f32>, // Average hard faults a second
+}
+
+///
+/// Sim Performance
+///
+/// Tracks the sim's frame rate, CPU and GPU usage over a rolling window and
+/// raises alerts when they degrade, so rig health overlays can warn the
+/// driver mid-race. Alerts are checked against window averages, so a single
+/// slow frame, such as when a replay loads, doesn't raise one.
+///
+/// # Examples
+///
+/// ```
+/// use iracing::alerts::AlertRule;
+/// use iracing::performance::{PerformanceSample, SimPerformance};
+///
+/// let mut performance = SimPerformance::new()
+///     .with_window(10.0)
+///     .with_rule(AlertRule::frame_rate_below(50.0));
+///
+/// let sample = PerformanceSample { frame_rate: 30.0, ..Default::default() };
+/// for event in performance.update(&sample) {
+///     println!("{} {:?} at {:.0} FPS", event.name, event.state, event.value);
+/// }
+/// ```
+#[derive(Debug, Clone)]
+pub struct SimPerformance {
+    window: f64,
+    samples: VecDeque<PerformanceSample>,
+    alerts: Alerts,
+}
+
+impl PerformanceSample {
+    ///
+    /// Read a performance sample from a telemetry sample.
+    #[cfg(feature = "telemetry")]
+    pub fn from_sample(sample: &Sample) -> Result<Self, Box<dyn Error>> {
+        let page_faults: Option<f32> = if sample.has("MemPageFaultSec") {
+            Some(sample.get("MemPageFaultSec")?.try_into()?)
+        } else {
+            None
+        };
+
+        Ok(PerformanceSample {
+            session_time: sample.get("SessionTime")?.try_into()?,
+            frame_rate: sample.get("FrameRate")?.try_into()?,
+            cpu_fg: sample.get("CpuUsageFG")?.try_into()?,
+            cpu_bg: sample.get("CpuUsageBG")?.try_into()?,
+            gpu: sample.get("GpuUsage")?.try_into()?,
+            page_faults,
+        })
+    }
+}
+
+impl Default for SimPerformance {
+    fn default() -> Self {
+        SimPerformance {
+            window: 5.0,
+            samples: VecDeque::new(),
+            alerts: Alerts::new(),
+        }
+    }
+}
+
+impl SimPerformance {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Seconds of samples averaged. 5s by default.
+    pub fn with_window(mut self, seconds: f64) -> Self {
+        self.window = seconds;
+        self
+    }
+
+    pub fn with_rule(mut self, rule: AlertRule) -> Self {
+        self.alerts.add_rule(rule);
+        self
+    }
+
+    ///
+    /// Alerts for under 50 FPS and the sim's main thread using over 90% of
+    /// a core.
+    pub fn with_default_rules(self) -> Self {
+        self.with_rule(AlertRule::frame_rate_below(50.0))
+            .with_rule(AlertRule::cpu_usage_above(0.9))
+    }
+
+    /// Names of the alerts currently raised.
+    pub fn raised(&self) -> impl Iterator<Item = &str> {
+        self.alerts.raised()
+    }
+
+    ///
+    /// Add a sample, returning alerts raised or cleared. Time going
+    /// backwards starts the window again.
+    pub fn update(&mut self, sample: &PerformanceSample) -> Vec<AlertEvent> {
+        if self
+            .samples
+            .back()
+            .is_some_and(|last| sample.session_time < last.session_time)
+        {
+            self.samples.clear();
+        }
+
+        self.samples.push_back(*sample);
+        while self
+            .samples
+            .front()
+            .is_some_and(|first| sample.session_time - first.session_time > self.window)
+        {
+            self.samples.pop_front();
+        }
+
+        let stats = match self.stats() {
+            Some(stats) => stats,
+            None => return Vec::new(),
+        };
+        self.alerts
+            .check(sample.session_time, |metric| match metric {
+                Metric::FrameRate => Some(stats.frame_rate),
+                Metric::CpuUsage => Some(stats.cpu_fg.max(stats.cpu_bg)),
+                Metric::GpuUsage => Some(stats.gpu),
+                _ => None,
+            })
+    }
+
+    /// Performance over the window, once there are samples.
+    pub fn stats(&self) -> Option<PerformanceStats> {
+        let n = self.samples.len() as f32;
+        if n == 0.0 {
+            return None;
+        }
+
+        let mean = |f: fn(&PerformanceSample) -> f32| self.samples.iter().map(f).sum::<f32>() / n;
+        let faults: Vec<f32> = self.samples.iter().filter_map(|s| s.page_faults).collect();
+        let frame_rate = mean(|s| s.frame_rate);
+
+        Some(PerformanceStats {
+            frame_rate,
+            min_frame_rate: self
+                .samples
+                .iter()
+                .map(|s| s.frame_rate)
+                .fold(f32::INFINITY, f32::min),
+            frame_time: if frame_rate > 0.0 {
+                1000.0 / frame_rate
+            } else {
+                0.0
+            },
+            cpu_fg: mean(|s| s.cpu_fg),
+            cpu_bg: mean(|s| s.cpu_bg),
+            gpu: mean(|s| s.gpu),
+            page_faults: if faults.is_empty() {
+                None
+            } else {
+                Some(faults.iter().sum::<f32>() / faults.len() as f32)
+            },
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::alerts::AlertState;
+
+    #[test]
+    fn performance_alerts() {
+        let mut performance = SimPerformance::new().with_window(2.0).with_default_rules();
+        let mut events = Vec::new();
+        let sample = |t: f64, frame_rate: f32, cpu_fg: f32| PerformanceSample {
+            session_time: t,
+            frame_rate,
+            cpu_fg,
+            cpu_bg: 0.2,
+            gpu: 0.7,
+            page_faults: None,
+        };
+
+        // A single slow frame is averaged out, a sustained drop isn't
+        for (t, fps, cpu) in [
+            (0.0, 90.0, 0.5),
+            (1.0, 40.0, 0.5),
+            (2.0, 90.0, 0.5),
+            (3.0, 30.0, 0.95),
+            (4.0, 30.0, 0.95),
+            (5.0, 30.0, 0.95),
+            (6.0, 90.0, 0.5),
+            (7.0, 90.0, 0.5),
+            (8.0, 90.0, 0.5),
+        ] {
+            events.extend(
+                performance
+                    .update(&sample(t, fps, cpu))
+                    .into_iter()
+                    .map(|e| (e.session_time, e.name, e.state)),
+            );
+        }
+
+        assert_eq!(
+            events,
+            vec![
+                (5.0, "low_frame_rate".to_string(), AlertState::Raised),
+                (5.0, "high_cpu".to_string(), AlertState::Raised),
+                (6.0, "high_cpu".to_string(), AlertState::Cleared),
+                (7.0, "low_frame_rate".to_string(), AlertState::Cleared),
+            ]
+        );
+
+        let stats = performance.stats().unwrap();
+        assert_eq!(stats.min_frame_rate, 90.0);
+        assert!((stats.frame_time - 11.111).abs() < 1e-3);
+        assert!((stats.gpu - 0.7).abs() < 1e-6);
+    }
+}
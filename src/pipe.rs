@@ -0,0 +1,474 @@
+use std::convert::TryFrom;
+use std::error::Error;
+use std::fmt;
+use std::io::{self, Read, Write};
+
+#[cfg(all(target_os = "windows", feature = "telemetry"))]
+use crate::input_trace::InputTraceBuffer;
+#[cfg(all(target_os = "windows", feature = "telemetry"))]
+use crate::telemetry::{Sample, Value};
+#[cfg(all(target_os = "windows", feature = "telemetry"))]
+use std::os::windows::ffi::OsStrExt;
+#[cfg(all(target_os = "windows", feature = "telemetry"))]
+use std::os::windows::io::FromRawHandle;
+#[cfg(all(target_os = "windows", feature = "telemetry"))]
+use std::{
+    ffi::OsStr,
+    fs::File,
+    ptr::null_mut,
+    sync::atomic::{AtomicBool, Ordering},
+    sync::mpsc::{sync_channel, SyncSender},
+    sync::{Arc, Mutex},
+    thread::{self, JoinHandle},
+    time::{Duration, Instant},
+};
+#[cfg(all(target_os = "windows", feature = "telemetry"))]
+use winapi::shared::winerror::ERROR_PIPE_CONNECTED;
+#[cfg(all(target_os = "windows", feature = "telemetry"))]
+use winapi::um::errhandlingapi::GetLastError;
+#[cfg(all(target_os = "windows", feature = "telemetry"))]
+use winapi::um::handleapi::{CloseHandle, INVALID_HANDLE_VALUE};
+#[cfg(all(target_os = "windows", feature = "telemetry"))]
+use winapi::um::namedpipeapi::{ConnectNamedPipe, CreateNamedPipeW};
+#[cfg(all(target_os = "windows", feature = "telemetry"))]
+use winapi::um::winbase::{
+    PIPE_ACCESS_OUTBOUND, PIPE_TYPE_BYTE, PIPE_UNLIMITED_INSTANCES, PIPE_WAIT,
+};
+
+/// Default name of the telemetry pipe.
+pub const PIPE_NAME: &str = r"\\.\pipe\iracing-telemetry";
+
+/// Version of the framing protocol, sent in the `Hello` frame.
+pub const PROTOCOL_VERSION: u16 = 1;
+
+/// Largest payload a frame may carry, to guard readers against corrupt lengths.
+pub const MAX_PAYLOAD: usize = 16 * 1024 * 1024;
+
+/// Frames a client may fall behind by before it is dropped.
+pub const CLIENT_BACKLOG: usize = 120;
+
+/// How long dropping a server waits for its accept thread to stop.
+#[cfg(all(target_os = "windows", feature = "telemetry"))]
+const SHUTDOWN_TIMEOUT: Duration = Duration::from_secs(1);
+
+///
+/// Type of a frame's payload.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+#[repr(u8)]
+pub enum FrameKind {
+    /// Protocol version, as a little-endian u16. Sent first on every connection.
+    Hello = 1,
+
+    /// Session info YAML, as UTF-8
+    SessionInfo = 2,
+
+    /// Telemetry values, as a UTF-8 JSON object of channel name to value
+    Telemetry = 3,
+
+    /// Driver inputs added since the previous trace frame, as a UTF-8 JSON
+    /// `InputTraceUpdate`. The crate has no WebSocket or SSE server, so
+    /// overlays read the trace here, or from a bridge forwarding these frames.
+    InputTrace = 4,
+}
+
+///
+/// A single message on the pipe.
+///
+/// Frames are encoded as a little-endian u32 payload length, a kind byte, and
+/// the payload.
+///
+/// # Examples
+///
+/// ```
+/// use iracing::pipe::{Frame, FrameKind};
+///
+/// let frame = Frame::new(FrameKind::SessionInfo, b"---\n".to_vec());
+/// let bytes = frame.encode();
+///
+/// assert_eq!(Frame::read_from(&mut bytes.as_slice()).unwrap(), frame);
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Frame {
+    pub kind: FrameKind,
+    pub payload: Vec<u8>,
+}
+
+#[derive(Debug)]
+pub enum FrameError {
+    UnknownKind(u8),
+    TooLarge(usize),
+    IO(io::Error),
+}
+
+impl fmt::Display for FrameError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::UnknownKind(kind) => write!(f, "Unknown frame kind {}", kind),
+            Self::TooLarge(len) => write!(f, "Frame payload of {} bytes is too large", len),
+            Self::IO(e) => write!(f, "IO Error: {}", e),
+        }
+    }
+}
+
+impl Error for FrameError {}
+
+impl From<io::Error> for FrameError {
+    fn from(e: io::Error) -> Self {
+        Self::IO(e)
+    }
+}
+
+impl TryFrom<u8> for FrameKind {
+    type Error = FrameError;
+
+    fn try_from(v: u8) -> Result<Self, FrameError> {
+        match v {
+            1 => Ok(Self::Hello),
+            2 => Ok(Self::SessionInfo),
+            3 => Ok(Self::Telemetry),
+            4 => Ok(Self::InputTrace),
+            _ => Err(FrameError::UnknownKind(v)),
+        }
+    }
+}
+
+impl Frame {
+    pub fn new(kind: FrameKind, payload: Vec<u8>) -> Self {
+        Frame { kind, payload }
+    }
+
+    /// The `Hello` frame for this protocol version.
+    pub fn hello() -> Self {
+        Self::new(FrameKind::Hello, PROTOCOL_VERSION.to_le_bytes().to_vec())
+    }
+
+    pub fn encode(&self) -> Vec<u8> {
+        let mut out = Vec::with_capacity(5 + self.payload.len());
+        out.extend_from_slice(&(self.payload.len() as u32).to_le_bytes());
+        out.push(self.kind as u8);
+        out.extend_from_slice(&self.payload);
+        out
+    }
+
+    pub fn write_to<W: Write>(&self, writer: &mut W) -> io::Result<()> {
+        writer.write_all(&self.encode())
+    }
+
+    ///
+    /// Read one frame, blocking until it is complete.
+    pub fn read_from<R: Read>(reader: &mut R) -> Result<Frame, FrameError> {
+        let mut head = [0u8; 5];
+        reader.read_exact(&mut head)?;
+
+        let len = u32::from_le_bytes([head[0], head[1], head[2], head[3]]) as usize;
+        if len > MAX_PAYLOAD {
+            return Err(FrameError::TooLarge(len));
+        }
+
+        let kind = FrameKind::try_from(head[4])?;
+        let mut payload = vec![0u8; len];
+        reader.read_exact(&mut payload)?;
+
+        Ok(Frame { kind, payload })
+    }
+}
+
+///
+/// Named Pipe Server
+///
+/// Republishes telemetry over a named pipe, so other local processes can read
+/// it from a single reader instead of each opening the shared memory.
+///
+/// Each client receives a `Hello` frame and the latest session info as soon as
+/// it connects, then every frame published afterwards. Clients are written to
+/// on threads of their own, so a stalled reader doesn't hold up publishing.
+/// Clients which fall over, or fall `CLIENT_BACKLOG` frames behind, are
+/// dropped. Dropping the server stops accepting clients and frees the pipe
+/// name.
+///
+/// # Examples
+///
+/// ```no_run
+/// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+/// use iracing::pipe::{PipeServer, PIPE_NAME};
+/// use iracing::telemetry::Connection;
+/// use std::time::Duration;
+///
+/// let mut conn = Connection::new()?;
+/// let server = PipeServer::start(PIPE_NAME)?.with_channels(&["Speed", "RPM", "Gear"]);
+///
+/// server.publish_session(&serde_yaml::to_string(&conn.session_info()?)?);
+///
+/// let sampler = conn.blocking()?;
+/// loop {
+///     server.publish_sample(&sampler.sample(Duration::from_millis(50))?)?;
+/// }
+/// # }
+/// ```
+#[cfg(all(target_os = "windows", feature = "telemetry"))]
+pub struct PipeServer {
+    name: String,
+    channels: Vec<String>,
+    clients: Arc<Mutex<Vec<ClientQueue>>>,
+    session: Arc<Mutex<Option<Frame>>>,
+    trace_end: Mutex<u64>,
+    stop: Arc<AtomicBool>,
+    accept: Option<JoinHandle<()>>,
+}
+
+#[cfg(all(target_os = "windows", feature = "telemetry"))]
+impl PipeServer {
+    ///
+    /// Create the pipe and start accepting clients on a background thread.
+    pub fn start(name: &str) -> io::Result<PipeServer> {
+        let wide: Vec<u16> = OsStr::new(name)
+            .encode_wide()
+            .chain(std::iter::once(0))
+            .collect();
+
+        // Create the first instance up front so errors are reported to the caller
+        let mut pipe = create_instance(&wide)?;
+
+        let clients = Arc::new(Mutex::new(Vec::new()));
+        let session = Arc::new(Mutex::new(None));
+        let stop = Arc::new(AtomicBool::new(false));
+        let (accepted, greeting, stopping) = (clients.clone(), session.clone(), stop.clone());
+
+        let accept = thread::spawn(move || loop {
+            let connected = connect(pipe);
+            if stopping.load(Ordering::SeqCst) {
+                return;
+            }
+
+            if let Ok(mut client) = connected {
+                let session = greeting.lock().unwrap().clone();
+                let greeted = Frame::hello()
+                    .write_to(&mut client)
+                    .and_then(|_| match session {
+                        Some(frame) => frame.write_to(&mut client),
+                        None => Ok(()),
+                    });
+
+                if greeted.is_ok() {
+                    accepted.lock().unwrap().push(spawn_writer(client));
+                }
+            }
+
+            pipe = match create_instance(&wide) {
+                Ok(p) => p,
+                Err(_) => return,
+            };
+        });
+
+        Ok(PipeServer {
+            name: name.to_string(),
+            channels: Vec::new(),
+            clients,
+            session,
+            trace_end: Mutex::new(0),
+            stop,
+            accept: Some(accept),
+        })
+    }
+
+    ///
+    /// Channels to include in telemetry frames. All channels are sent if none are given.
+    pub fn with_channels(mut self, channels: &[&str]) -> Self {
+        self.channels = channels.iter().map(|c| c.to_string()).collect();
+        self
+    }
+
+    /// Number of connected clients.
+    pub fn clients(&self) -> usize {
+        self.clients.lock().unwrap().len()
+    }
+
+    ///
+    /// Queue a frame for every client, dropping any which can't be written
+    /// to or are too far behind.
+    pub fn send(&self, frame: &Frame) {
+        let bytes = Arc::new(frame.encode());
+        self.clients
+            .lock()
+            .unwrap()
+            .retain(|client| client.try_send(bytes.clone()).is_ok());
+    }
+
+    ///
+    /// Publish session info YAML. It is also sent to clients which connect later.
+    pub fn publish_session(&self, yaml: &str) {
+        let frame = Frame::new(FrameKind::SessionInfo, yaml.as_bytes().to_vec());
+        self.send(&frame);
+        *self.session.lock().unwrap() = Some(frame);
+    }
+
+    ///
+    /// Publish the selected channels of a telemetry sample.
+    pub fn publish_sample(&self, sample: &Sample) -> Result<(), Box<dyn Error>> {
+        let mut values = serde_json::Map::new();
+
+        if self.channels.is_empty() {
+            for v in sample.all() {
+                values.insert(v.name, to_json(v.value));
+            }
+        } else {
+            for channel in self.channels.iter() {
+                values.insert(channel.clone(), to_json(sample.get(channel)?));
+            }
+        }
+
+        let payload = serde_json::to_vec(&values)?;
+        self.send(&Frame::new(FrameKind::Telemetry, payload));
+        Ok(())
+    }
+
+    ///
+    /// Publish the driver inputs added to `buffer` since the last call, so
+    /// input graphs don't need to read whole samples. Clients which connect
+    /// later fill their graph from the frames which follow.
+    pub fn publish_trace(&self, buffer: &InputTraceBuffer) -> Result<(), Box<dyn Error>> {
+        let mut end = self.trace_end.lock().unwrap();
+        let update = buffer.since(*end);
+        *end = update.end;
+
+        if !update.is_empty() {
+            let payload = serde_json::to_vec(&update)?;
+            self.send(&Frame::new(FrameKind::InputTrace, payload));
+        }
+        Ok(())
+    }
+}
+
+#[cfg(all(target_os = "windows", feature = "telemetry"))]
+impl Drop for PipeServer {
+    fn drop(&mut self) {
+        self.stop.store(true, Ordering::SeqCst);
+        let accept = match self.accept.take() {
+            Some(accept) => accept,
+            None => return,
+        };
+
+        // Wake the accept thread from ConnectNamedPipe by connecting to it.
+        // It may be between instances, so keep trying until it has stopped.
+        let deadline = Instant::now() + SHUTDOWN_TIMEOUT;
+        while !accept.is_finished() && Instant::now() < deadline {
+            let _ = File::open(&self.name);
+            thread::sleep(Duration::from_millis(10));
+        }
+
+        if accept.is_finished() {
+            let _ = accept.join();
+        }
+    }
+}
+
+/// Frames waiting to be written to a client.
+#[cfg(all(target_os = "windows", feature = "telemetry"))]
+type ClientQueue = SyncSender<Arc<Vec<u8>>>;
+
+///
+/// Write queued frames to a client until it falls over or is dropped.
+#[cfg(all(target_os = "windows", feature = "telemetry"))]
+fn spawn_writer(mut client: File) -> ClientQueue {
+    let (sender, frames) = sync_channel::<Arc<Vec<u8>>>(CLIENT_BACKLOG);
+
+    thread::spawn(move || {
+        for bytes in frames {
+            if client.write_all(&bytes).is_err() {
+                return;
+            }
+        }
+    });
+
+    sender
+}
+
+/// A pipe instance waiting for a client.
+#[cfg(all(target_os = "windows", feature = "telemetry"))]
+struct Instance(winapi::um::winnt::HANDLE);
+
+// The handle is only used by the accept thread once it has been created
+#[cfg(all(target_os = "windows", feature = "telemetry"))]
+unsafe impl Send for Instance {}
+
+#[cfg(all(target_os = "windows", feature = "telemetry"))]
+fn create_instance(wide_name: &[u16]) -> io::Result<Instance> {
+    let handle = unsafe {
+        CreateNamedPipeW(
+            wide_name.as_ptr(),
+            PIPE_ACCESS_OUTBOUND,
+            PIPE_TYPE_BYTE | PIPE_WAIT,
+            PIPE_UNLIMITED_INSTANCES,
+            64 * 1024,
+            0,
+            0,
+            null_mut(),
+        )
+    };
+
+    if handle == INVALID_HANDLE_VALUE {
+        Err(io::Error::last_os_error())
+    } else {
+        Ok(Instance(handle))
+    }
+}
+
+/// Wait for a client to connect to a pipe instance.
+#[cfg(all(target_os = "windows", feature = "telemetry"))]
+fn connect(Instance(pipe): Instance) -> io::Result<File> {
+    let connected = unsafe { ConnectNamedPipe(pipe, null_mut()) } != 0
+        || unsafe { GetLastError() } == ERROR_PIPE_CONNECTED;
+
+    if connected {
+        Ok(unsafe { File::from_raw_handle(pipe as _) })
+    } else {
+        let err = io::Error::last_os_error();
+        unsafe { CloseHandle(pipe) };
+        Err(err)
+    }
+}
+
+#[cfg(all(target_os = "windows", feature = "telemetry"))]
+fn to_json(value: Value) -> serde_json::Value {
+    use serde_json::json;
+
+    match value {
+        Value::CHAR(v) => json!(v),
+        Value::BOOL(v) => json!(v),
+        Value::INT(v) => json!(v),
+        Value::BITS(v) => json!(v),
+        Value::FLOAT(v) => json!(v),
+        Value::DOUBLE(v) => json!(v),
+        Value::UNKNOWN(_) => serde_json::Value::Null,
+        Value::IntVec(v) => json!(v),
+        Value::FloatVec(v) => json!(v),
+        Value::BoolVec(v) => json!(v),
+        Value::BitsVec(v) => json!(v),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn frame_round_trip() {
+        let mut stream = Vec::new();
+        Frame::hello().write_to(&mut stream).unwrap();
+        Frame::new(FrameKind::Telemetry, br#"{"Gear":3}"#.to_vec())
+            .write_to(&mut stream)
+            .unwrap();
+
+        let mut reader = stream.as_slice();
+        assert_eq!(Frame::read_from(&mut reader).unwrap().payload, vec![1, 0]);
+
+        let frame = Frame::read_from(&mut reader).unwrap();
+        assert_eq!(frame.kind, FrameKind::Telemetry);
+        assert_eq!(frame.payload, br#"{"Gear":3}"#.to_vec());
+
+        assert!(matches!(
+            Frame::read_from(&mut [0, 0, 0, 0, 9].as_ref()),
+            Err(FrameError::UnknownKind(9))
+        ));
+    }
+}
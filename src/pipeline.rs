@@ -0,0 +1,498 @@
+use crate::chunked::{ChunkedWriter, RotatingWriter};
+use crate::sampler::{offer, Overflow};
+use crate::telemetry::Sample;
+use crossbeam_channel::{bounded, Receiver, Sender};
+use serde::{Deserialize, Serialize};
+use std::convert::TryFrom;
+use std::error::Error;
+use std::io::Write;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::thread::{self, JoinHandle};
+use std::time::{Duration, Instant};
+
+#[cfg(target_os = "windows")]
+use crate::pipe::PipeServer;
+
+///
+/// A destination for telemetry samples, such as a file, a pipe or an HTTP
+/// endpoint.
+///
+/// Closures taking a sample are exporters, as are the crate's writers.
+pub trait Exporter: Send {
+    fn export(&mut self, sample: &Sample) -> Result<(), Box<dyn Error>>;
+
+    /// Called once the pipeline is finished, after the last sample.
+    fn finish(self) -> Result<(), Box<dyn Error>>
+    where
+        Self: Sized,
+    {
+        Ok(())
+    }
+}
+
+///
+/// What a pipeline does with samples for an exporter which can't keep up.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Backpressure {
+    /// Queue up to this many samples, dropping the oldest once full
+    Buffer(usize),
+
+    /// Queue up to `capacity` samples, passing on only every `every`th sample
+    /// while the queue is over half full, and dropping the oldest once full
+    Decimate { capacity: usize, every: u32 },
+
+    /// Pass on samples only while the exporter keeps up, dropping the rest
+    Drop,
+}
+
+///
+/// How an exporter is keeping up.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct ExporterStats {
+    pub name: String,
+    pub exported: u64,              // Samples exported
+    pub dropped: u64,               // Samples dropped because the queue was full
+    pub decimated: u64,             // Samples skipped while decimating
+    pub errors: u64,                // Samples the exporter failed to export
+    pub last_error: Option<String>, // Most recent failure
+    pub queued: usize,              // Samples waiting to be exported
+    pub slowest: Duration,          // Longest time taken to export a sample
+    pub slow: bool,                 // Falling behind, see `Pipeline::with_slow_threshold`
+}
+
+/// How long an exporter is reported slow after a slow sample.
+const SLOW_WINDOW: Duration = Duration::from_secs(5);
+
+///
+/// Pipeline
+///
+/// Hands samples to exporters, each on its own thread behind a bounded queue,
+/// so a slow sink such as a stalled HTTP endpoint is handled by its own
+/// `Backpressure` policy without holding up the other exporters or the
+/// thread pushing samples. Pushing never blocks.
+///
+/// # Examples
+///
+/// ```no_run
+/// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+/// use iracing::chunked::ChunkedWriter;
+/// use iracing::pipeline::{Backpressure, Pipeline};
+/// use iracing::telemetry::Sample;
+/// # let samples: Vec<Sample> = Vec::new();
+///
+/// let mut pipeline = Pipeline::new()
+///     .with_exporter("file", ChunkedWriter::create("race.irc")?, Backpressure::Buffer(600))
+///     .with_exporter(
+///         "dashboard",
+///         |sample: &Sample| -> Result<(), Box<dyn std::error::Error>> {
+///             println!("Tick {}", sample.tick());
+///             Ok(())
+///         },
+///         Backpressure::Drop,
+///     );
+///
+/// for sample in samples.iter() {
+///     pipeline.push(sample);
+/// }
+/// for stats in pipeline.stats().iter().filter(|s| s.slow) {
+///     println!("{} is falling behind, {} dropped", stats.name, stats.dropped);
+/// }
+/// pipeline.finish();
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Debug)]
+pub struct Pipeline {
+    stages: Vec<Stage>,
+    slow_threshold: Arc<AtomicU64>, // Shared with the exporter threads (ns)
+    shutdown_timeout: Duration,
+}
+
+/// An exporter's queue and thread.
+#[derive(Debug)]
+struct Stage {
+    policy: Backpressure,
+    sender: Sender<Sample>,
+    queue: Receiver<Sample>,
+    stats: Arc<Mutex<ExporterStats>>,
+    thread: Option<JoinHandle<()>>,
+    skipped: u32,
+}
+
+impl<F> Exporter for F
+where
+    F: FnMut(&Sample) -> Result<(), Box<dyn Error>> + Send,
+{
+    fn export(&mut self, sample: &Sample) -> Result<(), Box<dyn Error>> {
+        self(sample)
+    }
+}
+
+impl<W: Write + Send> Exporter for ChunkedWriter<W> {
+    fn export(&mut self, sample: &Sample) -> Result<(), Box<dyn Error>> {
+        Ok(self.push(sample)?)
+    }
+
+    fn finish(self) -> Result<(), Box<dyn Error>> {
+        ChunkedWriter::finish(self)?;
+        Ok(())
+    }
+}
+
+impl Exporter for RotatingWriter {
+    fn export(&mut self, sample: &Sample) -> Result<(), Box<dyn Error>> {
+        self.push(sample)?;
+        Ok(())
+    }
+
+    fn finish(self) -> Result<(), Box<dyn Error>> {
+        RotatingWriter::finish(self)?;
+        Ok(())
+    }
+}
+
+#[cfg(target_os = "windows")]
+impl Exporter for PipeServer {
+    fn export(&mut self, sample: &Sample) -> Result<(), Box<dyn Error>> {
+        self.publish_sample(sample)
+    }
+}
+
+impl Backpressure {
+    fn capacity(&self) -> usize {
+        match self {
+            Self::Buffer(capacity) | Self::Decimate { capacity, .. } => (*capacity).max(1),
+            Self::Drop => 1,
+        }
+    }
+}
+
+impl Default for Pipeline {
+    fn default() -> Self {
+        Pipeline {
+            stages: Vec::new(),
+            slow_threshold: Arc::new(AtomicU64::new(100_000_000)),
+            shutdown_timeout: Duration::from_secs(5),
+        }
+    }
+}
+
+impl Pipeline {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    ///
+    /// Report an exporter as slow for 5s after a sample takes longer than
+    /// this to export, or while its queue is more than three quarters full.
+    /// 100ms by default. Applies to exporters added before and after.
+    pub fn with_slow_threshold(self, threshold: Duration) -> Self {
+        let nanos = u64::try_from(threshold.as_nanos()).unwrap_or(u64::MAX);
+        self.slow_threshold.store(nanos, Ordering::Relaxed);
+        self
+    }
+
+    ///
+    /// How long finishing waits for exporters to export what's queued and
+    /// finish. Exporters still running after this are left to finish in the
+    /// background. 5s by default.
+    pub fn with_shutdown_timeout(mut self, timeout: Duration) -> Self {
+        self.shutdown_timeout = timeout;
+        self
+    }
+
+    ///
+    /// Add an exporter, starting its thread.
+    pub fn with_exporter<E: Exporter + 'static>(
+        mut self,
+        name: &str,
+        mut exporter: E,
+        policy: Backpressure,
+    ) -> Self {
+        let (sender, queue) = bounded::<Sample>(policy.capacity());
+        let stats = Arc::new(Mutex::new(ExporterStats {
+            name: name.to_owned(),
+            ..Default::default()
+        }));
+
+        let thread = {
+            let (queue, stats) = (queue.clone(), stats.clone());
+            let threshold = self.slow_threshold.clone();
+
+            thread::spawn(move || {
+                let mut last_slow: Option<Instant> = None;
+                for sample in queue.iter() {
+                    let started = Instant::now();
+                    let result = exporter.export(&sample);
+                    let took = started.elapsed();
+
+                    let mut stats = stats.lock().unwrap();
+                    match result {
+                        Ok(()) => stats.exported += 1,
+                        Err(e) => {
+                            stats.errors += 1;
+                            stats.last_error = Some(e.to_string());
+                        }
+                    }
+                    stats.slowest = stats.slowest.max(took);
+                    if took > Duration::from_nanos(threshold.load(Ordering::Relaxed)) {
+                        last_slow = Some(Instant::now());
+                    }
+                    stats.slow = last_slow.is_some_and(|at| at.elapsed() < SLOW_WINDOW);
+                }
+
+                if let Err(e) = exporter.finish() {
+                    let mut stats = stats.lock().unwrap();
+                    stats.errors += 1;
+                    stats.last_error = Some(e.to_string());
+                }
+            })
+        };
+
+        self.stages.push(Stage {
+            policy,
+            sender,
+            queue,
+            stats,
+            thread: Some(thread),
+            skipped: 0,
+        });
+        self
+    }
+
+    ///
+    /// Queue a sample for every exporter, following each one's policy.
+    pub fn push(&mut self, sample: &Sample) {
+        for stage in self.stages.iter_mut() {
+            stage.push(sample);
+        }
+    }
+
+    /// Stats of every exporter, in the order they were added.
+    pub fn stats(&self) -> Vec<ExporterStats> {
+        self.stages
+            .iter()
+            .map(|stage| {
+                let mut stats = stage.stats.lock().unwrap().clone();
+                stats.queued = stage.queue.len();
+                stats.slow |= stats.queued * 4 > stage.policy.capacity() * 3;
+                stats
+            })
+            .collect()
+    }
+
+    ///
+    /// Export everything queued, finish every exporter and wait for them up
+    /// to the shutdown timeout, returning their final stats.
+    pub fn finish(mut self) -> Vec<ExporterStats> {
+        self.close();
+        self.stats()
+    }
+
+    fn close(&mut self) {
+        // The worker holds a receiver of its own, so the queue ends when
+        // the last sender is dropped
+        for stage in self.stages.iter_mut() {
+            let (sender, _) = bounded(0);
+            drop(std::mem::replace(&mut stage.sender, sender));
+        }
+
+        let deadline = Instant::now().checked_add(self.shutdown_timeout);
+        for stage in self.stages.iter_mut() {
+            let thread = match stage.thread.take() {
+                Some(thread) => thread,
+                None => continue,
+            };
+
+            while !thread.is_finished() && deadline.is_none_or(|d| Instant::now() < d) {
+                thread::sleep(Duration::from_millis(10));
+            }
+
+            if thread.is_finished() {
+                let _ = thread.join();
+            } else {
+                let mut stats = stage.stats.lock().unwrap();
+                stats.errors += 1;
+                stats.last_error = Some("Exporter didn't finish in time".to_owned());
+            }
+        }
+    }
+}
+
+impl Drop for Pipeline {
+    fn drop(&mut self) {
+        self.close();
+    }
+}
+
+impl Stage {
+    fn push(&mut self, sample: &Sample) {
+        if let Backpressure::Decimate { every, .. } = self.policy {
+            if self.queue.len() * 2 > self.policy.capacity() {
+                self.skipped += 1;
+                if self.skipped < every.max(1) {
+                    self.stats.lock().unwrap().decimated += 1;
+                    return;
+                }
+            }
+            self.skipped = 0;
+        }
+
+        let overflow = match self.policy {
+            Backpressure::Drop => Overflow::DropNewest,
+            _ => Overflow::DropOldest,
+        };
+        let poll = Duration::from_millis(0);
+        if offer(
+            &self.sender,
+            &self.queue,
+            sample.clone(),
+            overflow,
+            poll,
+            || true,
+        ) {
+            self.stats.lock().unwrap().dropped += 1;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::recording::{Recording, SnapshotBuilder};
+    use crate::telemetry::Value;
+
+    fn sample(tick: i32) -> Sample {
+        let mut recording = Recording::new();
+        recording.push(
+            SnapshotBuilder::new(tick)
+                .with_value("Speed", Value::FLOAT(40.0))
+                .build(),
+        );
+        recording.sample(0).unwrap().unwrap()
+    }
+
+    #[test]
+    fn isolate_slow_exporters() {
+        // The slow exporter stalls on its first sample until released
+        let (started, stalled) = bounded::<()>(1);
+        let (release, gate) = bounded::<()>(0);
+        let slow = move |_: &Sample| -> Result<(), Box<dyn Error>> {
+            let _ = started.try_send(());
+            let _ = gate.recv();
+            Ok(())
+        };
+        let mut fast_ticks = Vec::new();
+        let (ticks, fast_done) = bounded(1);
+        let fast = move |sample: &Sample| -> Result<(), Box<dyn Error>> {
+            fast_ticks.push(sample.tick());
+            if sample.tick() == 3 {
+                Err("Endpoint returned 500")?;
+            }
+            if sample.tick() == 20 {
+                let _ = ticks.send(fast_ticks.clone());
+            }
+            Ok(())
+        };
+
+        let mut pipeline = Pipeline::new()
+            .with_exporter("slow", slow, Backpressure::Drop)
+            .with_exporter("fast", fast, Backpressure::Buffer(100))
+            .with_exporter(
+                "decimated",
+                |_: &Sample| -> Result<(), Box<dyn Error>> { Ok(()) },
+                Backpressure::Decimate {
+                    capacity: 4,
+                    every: 2,
+                },
+            );
+
+        pipeline.push(&sample(1));
+        stalled.recv().unwrap();
+        for tick in 2..=20 {
+            pipeline.push(&sample(tick));
+        }
+
+        // The fast exporter got everything while the slow one was stuck
+        assert_eq!(fast_done.recv().unwrap(), (1..=20).collect::<Vec<i32>>());
+        let stats = pipeline.stats();
+        assert_eq!(stats[0].dropped, 18);
+        assert_eq!(stats[0].queued, 1);
+        assert!(stats[0].slow);
+
+        drop(release);
+        let stats = pipeline.finish();
+        assert_eq!(stats[0].exported, 2);
+        assert_eq!(stats[1].exported, 19);
+        assert_eq!(stats[1].errors, 1);
+        assert_eq!(
+            stats[1].last_error.as_deref(),
+            Some("Endpoint returned 500")
+        );
+        assert_eq!(
+            stats[2].exported + stats[2].dropped + stats[2].decimated,
+            20
+        );
+    }
+
+    #[test]
+    fn stalled_exporters() {
+        // One slow sample keeps the exporter reported slow
+        let (done, finished) = bounded(1);
+        let hiccup = move |sample: &Sample| -> Result<(), Box<dyn Error>> {
+            if sample.tick() == 1 {
+                thread::sleep(Duration::from_millis(50));
+            }
+            if sample.tick() == 5 {
+                let _ = done.send(());
+            }
+            Ok(())
+        };
+        let mut pipeline = Pipeline::new()
+            .with_slow_threshold(Duration::from_millis(10))
+            .with_exporter("hiccup", hiccup, Backpressure::Buffer(10));
+        for tick in 1..=5 {
+            pipeline.push(&sample(tick));
+        }
+        finished.recv().unwrap();
+        assert!(pipeline.stats()[0].slow);
+
+        // The threshold applies to exporters added before it was set
+        let (done, finished) = bounded(1);
+        let hiccup = move |sample: &Sample| -> Result<(), Box<dyn Error>> {
+            if sample.tick() == 1 {
+                thread::sleep(Duration::from_millis(50));
+            }
+            if sample.tick() == 2 {
+                let _ = done.send(());
+            }
+            Ok(())
+        };
+        let mut pipeline = Pipeline::new()
+            .with_exporter("hiccup", hiccup, Backpressure::Buffer(10))
+            .with_slow_threshold(Duration::from_millis(10));
+        for tick in 1..=2 {
+            pipeline.push(&sample(tick));
+        }
+        finished.recv().unwrap();
+        assert!(pipeline.stats()[0].slow);
+
+        // An exporter which never returns doesn't hold up finishing
+        let (_hold, stuck) = bounded::<()>(0);
+        let stalled = move |_: &Sample| -> Result<(), Box<dyn Error>> {
+            let _ = stuck.recv();
+            Ok(())
+        };
+        let mut pipeline = Pipeline::new()
+            .with_shutdown_timeout(Duration::from_millis(100))
+            .with_exporter("stalled", stalled, Backpressure::Buffer(10));
+        pipeline.push(&sample(1));
+
+        let started = Instant::now();
+        let stats = pipeline.finish();
+        assert!(started.elapsed() < Duration::from_secs(2));
+        assert_eq!(stats[0].exported, 0);
+        assert_eq!(stats[0].errors, 1);
+    }
+}
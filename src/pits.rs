@@ -0,0 +1,312 @@
+use crate::session::WeekendInfo;
+use crate::units::{Measurement, Speed, Unit};
+use serde::{Deserialize, Serialize};
+use std::convert::TryFrom;
+
+#[cfg(feature = "telemetry")]
+use crate::telemetry::Sample;
+#[cfg(feature = "telemetry")]
+use std::convert::TryInto;
+#[cfg(feature = "telemetry")]
+use std::error::Error;
+
+/// Speed (m/s) below which the car is considered stationary in its pit box
+const STATIONARY_SPEED: f32 = 0.5;
+
+///
+/// Pit Sample
+///
+/// The player's car position and speed at a point in time.
+#[derive(Debug, Copy, Clone, Default)]
+pub struct PitSample {
+    pub session_time: f64, // Seconds since session start
+    pub lap: i32,
+    pub lap_dist_pct: f32, // Distance around the lap, 0.0 to 1.0
+    pub speed: f32,        // Speed (m/s)
+    pub on_pit_road: bool,
+}
+
+///
+/// Timing of a single trip through pit lane.
+#[derive(Debug, Copy, Clone, PartialEq, Serialize, Deserialize)]
+pub struct PitStop {
+    pub lap: i32,
+    pub entry_time: f64,
+    pub exit_time: f64,
+    pub entry_pct: f32,         // Lap distance at pit entry
+    pub exit_pct: f32,          // Lap distance at pit exit
+    pub stationary: f64,        // Time stopped in the pit box (s)
+    pub max_speed: f32,         // Highest speed on pit road (m/s)
+    pub speeding: f64,          // Time spent above the pit speed limit (s)
+    pub time_lost: Option<f64>, // Time lost compared to racing past the pits, if a reference lap is set
+}
+
+///
+/// Events reported by the pit lane helper.
+#[derive(Debug, Copy, Clone, PartialEq, Serialize, Deserialize)]
+pub enum PitEvent {
+    Entered { session_time: f64, lap: i32 },
+    Exited(PitStop),
+    SpeedingStarted { session_time: f64, speed: f32 },
+    SpeedingEnded { session_time: f64 },
+}
+
+///
+/// Pit Lane
+///
+/// Times the player's trips through pit lane, warns when the pit speed limit is
+/// exceeded, and estimates the time lost compared to staying on track.
+///
+/// # Examples
+///
+/// ```
+/// use iracing::pits::{PitEvent, PitLane, PitSample};
+/// # let session: iracing::session::SessionDetails =
+/// #     serde_yaml::from_str(&std::fs::read_to_string("./session.yaml").unwrap()).unwrap();
+///
+/// let mut pits = PitLane::from_weekend(&session.weekend).with_reference_lap(101.5);
+///
+/// for event in pits.update(&PitSample::default()) {
+///     match event {
+///         PitEvent::SpeedingStarted { speed, .. } => println!("Slow down! {:.1} m/s", speed),
+///         PitEvent::Exited(stop) => println!("Lost {:?}s in the pits", stop.time_lost),
+///         _ => {}
+///     }
+/// }
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct PitLane {
+    speed_limit: Option<f32>,
+    tolerance: f32,
+    reference_lap: Option<f64>,
+    current: Option<PitStop>,
+    last: Option<PitSample>,
+    is_speeding: bool,
+    stops: Vec<PitStop>,
+}
+
+impl PitSample {
+    ///
+    /// Read a pit sample from a telemetry sample.
+    #[cfg(feature = "telemetry")]
+    pub fn from_sample(sample: &Sample) -> Result<Self, Box<dyn Error>> {
+        Ok(PitSample {
+            session_time: sample.get("SessionTime")?.try_into()?,
+            lap: sample.get("Lap")?.try_into()?,
+            lap_dist_pct: sample.get("LapDistPct")?.try_into()?,
+            speed: sample.get("Speed")?.try_into()?,
+            on_pit_road: sample.get("OnPitRoad")?.into(),
+        })
+    }
+}
+
+///
+/// Parse a `TrackPitSpeedLimit` value such as "60.00 kph" into m/s.
+pub fn parse_speed_limit(limit: &str) -> Option<f32> {
+    let mut limit: Measurement = limit.parse().ok()?;
+    if limit.unit == Unit::None {
+        limit.unit = Unit::KilometersPerHour;
+    }
+
+    let speed = Speed::try_from(limit).ok()?;
+    Some(speed.meters_per_second() as f32)
+}
+
+impl PitLane {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Pit lane helper using the track's pit speed limit.
+    pub fn from_weekend(weekend: &WeekendInfo) -> Self {
+        PitLane {
+            speed_limit: parse_speed_limit(&weekend.track_pit_speed_limit),
+            ..Self::default()
+        }
+    }
+
+    /// Set the pit speed limit (m/s).
+    pub fn with_speed_limit(mut self, limit: f32) -> Self {
+        self.speed_limit = Some(limit);
+        self
+    }
+
+    /// Allow this much over the speed limit (m/s) before warning.
+    pub fn with_tolerance(mut self, tolerance: f32) -> Self {
+        self.tolerance = tolerance;
+        self
+    }
+
+    ///
+    /// Set a representative racing lap time (s), used to estimate the time lost in pit lane.
+    pub fn with_reference_lap(mut self, lap_time: f64) -> Self {
+        self.reference_lap = Some(lap_time);
+        self
+    }
+
+    pub fn speed_limit(&self) -> Option<f32> {
+        self.speed_limit
+    }
+
+    ///
+    /// Update with a new sample, returning any events which occurred.
+    pub fn update(&mut self, sample: &PitSample) -> Vec<PitEvent> {
+        let mut events = Vec::new();
+        let dt = self
+            .last
+            .map(|l| (sample.session_time - l.session_time).max(0.0))
+            .unwrap_or(0.0);
+
+        if sample.on_pit_road && self.current.is_none() {
+            self.current = Some(PitStop {
+                lap: sample.lap,
+                entry_time: sample.session_time,
+                exit_time: sample.session_time,
+                entry_pct: sample.lap_dist_pct,
+                exit_pct: sample.lap_dist_pct,
+                stationary: 0.0,
+                max_speed: 0.0,
+                speeding: 0.0,
+                time_lost: None,
+            });
+            events.push(PitEvent::Entered {
+                session_time: sample.session_time,
+                lap: sample.lap,
+            });
+        }
+
+        let speeding = sample.on_pit_road
+            && matches!(self.speed_limit, Some(limit) if sample.speed > limit + self.tolerance);
+
+        if let Some(stop) = self.current.as_mut() {
+            stop.max_speed = stop.max_speed.max(sample.speed);
+            let was_stationary = matches!(self.last, Some(l) if l.speed < STATIONARY_SPEED);
+            if was_stationary && sample.speed < STATIONARY_SPEED {
+                stop.stationary += dt;
+            }
+            if speeding && self.is_speeding {
+                stop.speeding += dt;
+            }
+        }
+
+        if speeding && !self.is_speeding {
+            events.push(PitEvent::SpeedingStarted {
+                session_time: sample.session_time,
+                speed: sample.speed,
+            });
+        } else if !speeding && self.is_speeding {
+            events.push(PitEvent::SpeedingEnded {
+                session_time: sample.session_time,
+            });
+        }
+        self.is_speeding = speeding;
+
+        if !sample.on_pit_road {
+            if let Some(mut stop) = self.current.take() {
+                stop.exit_time = sample.session_time;
+                stop.exit_pct = sample.lap_dist_pct;
+                stop.time_lost = self.reference_lap.map(|lap| {
+                    let distance = (stop.exit_pct - stop.entry_pct).rem_euclid(1.0) as f64;
+                    stop.duration() - distance * lap
+                });
+
+                self.stops.push(stop);
+                events.push(PitEvent::Exited(stop));
+            }
+        }
+
+        self.last = Some(*sample);
+        events
+    }
+
+    /// True while the car is on pit road.
+    pub fn in_pit_lane(&self) -> bool {
+        self.current.is_some()
+    }
+
+    /// Completed trips through pit lane.
+    pub fn stops(&self) -> &[PitStop] {
+        &self.stops
+    }
+
+    ///
+    /// Average time lost to a pit stop, from the completed stops.
+    pub fn average_time_lost(&self) -> Option<f64> {
+        let lost: Vec<f64> = self.stops.iter().filter_map(|s| s.time_lost).collect();
+
+        if lost.is_empty() {
+            None
+        } else {
+            Some(lost.iter().sum::<f64>() / lost.len() as f64)
+        }
+    }
+}
+
+impl PitStop {
+    /// Time from pit entry to pit exit (s).
+    pub fn duration(&self) -> f64 {
+        self.exit_time - self.entry_time
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn pit_stop() {
+        assert!((parse_speed_limit("60.00 kph").unwrap() - 16.666_666).abs() < 1e-4);
+
+        let mut pits = PitLane::new()
+            .with_speed_limit(16.67)
+            .with_reference_lap(100.0);
+
+        let mut sample = PitSample {
+            session_time: 0.0,
+            lap: 5,
+            lap_dist_pct: 0.95,
+            speed: 16.0,
+            on_pit_road: true,
+        };
+
+        assert!(matches!(
+            pits.update(&sample)[..],
+            [PitEvent::Entered { lap: 5, .. }]
+        ));
+
+        sample.session_time = 5.0;
+        sample.speed = 18.0;
+        assert!(matches!(
+            pits.update(&sample)[..],
+            [PitEvent::SpeedingStarted { .. }]
+        ));
+
+        sample.session_time = 6.0;
+        sample.speed = 0.0;
+        assert!(matches!(
+            pits.update(&sample)[..],
+            [PitEvent::SpeedingEnded { .. }]
+        ));
+
+        sample.session_time = 26.0;
+        pits.update(&sample);
+
+        sample.session_time = 40.0;
+        sample.lap = 6;
+        sample.lap_dist_pct = 0.05;
+        sample.speed = 20.0;
+        sample.on_pit_road = false;
+
+        let events = pits.update(&sample);
+        let stop = match events[..] {
+            [PitEvent::Exited(stop)] => stop,
+            _ => panic!("{:?}", events),
+        };
+
+        assert_eq!(stop.duration(), 40.0);
+        assert_eq!(stop.stationary, 20.0);
+        assert_eq!(stop.speeding, 0.0);
+        assert!((stop.time_lost.unwrap() - 30.0).abs() < 1e-3);
+        assert!(!pits.in_pit_lane());
+    }
+}
@@ -0,0 +1,382 @@
+use crate::results::{Results, Standing};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+/// `ReasonOutStr` of a disqualified car
+const DISQUALIFIED: &str = "Disqualified";
+
+///
+/// Who championship points are awarded to.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum ScoreBy {
+    /// Points are awarded to each driver (by iRacing user ID)
+    Driver,
+
+    /// Points are awarded to each team (by iRacing team ID)
+    Team,
+}
+
+///
+/// Points Table
+///
+/// A scoring system applied to the results of a single race.
+///
+/// # Examples
+///
+/// ```
+/// use iracing::points::PointsTable;
+///
+/// let table = PointsTable::new(vec![25, 18, 15, 12, 10, 8, 6, 4, 2, 1])
+///     .with_fastest_lap_bonus(1)
+///     .with_most_laps_led_bonus(1);
+/// ```
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PointsTable {
+    pub positions: Vec<u32>, // Points for each finishing position, starting with the winner
+    pub participation: u32,  // Points for every classified car outside the table
+    pub fastest_lap_bonus: u32, // Bonus for the fastest lap
+    pub most_laps_led_bonus: u32, // Bonus for leading the most laps
+    pub per_class: bool,     // Award points by class position rather than overall position
+    pub score_disqualified: bool, // Award points to disqualified cars
+}
+
+///
+/// Points scored in a single round.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RoundScore {
+    pub round: String,
+    pub position: u32,
+    pub points: u32,
+}
+
+///
+/// A driver or team's position in the championship.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ChampionshipStanding {
+    pub id: i64,      // User ID or team ID
+    pub name: String, // Driver or team name
+    pub class: String,
+    pub points: u32, // Total points, after dropped rounds
+    pub wins: u32,
+    pub rounds: Vec<RoundScore>,
+}
+
+///
+/// Championship
+///
+/// Applies a points table to the results of multiple races to produce season standings.
+///
+/// # Examples
+///
+/// ```no_run
+/// use iracing::points::{Championship, PointsTable, ScoreBy};
+/// use iracing::results::Results;
+/// # let races: Vec<(String, Results)> = vec![];
+///
+/// let mut championship = Championship::new(PointsTable::formula_one(), ScoreBy::Driver);
+///
+/// for (round, results) in races.iter() {
+///     championship.add_round(round, results);
+/// }
+///
+/// for standing in championship.standings() {
+///     println!("{:<30} {:>4}", standing.name, standing.points);
+/// }
+/// ```
+#[derive(Debug, Clone)]
+pub struct Championship {
+    table: PointsTable,
+    score_by: ScoreBy,
+    drop_rounds: usize,
+    rounds: Vec<String>,
+    entries: HashMap<i64, ChampionshipStanding>,
+}
+
+impl PointsTable {
+    pub fn new(positions: Vec<u32>) -> Self {
+        PointsTable {
+            positions,
+            participation: 0,
+            fastest_lap_bonus: 0,
+            most_laps_led_bonus: 0,
+            per_class: false,
+            score_disqualified: false,
+        }
+    }
+
+    /// The 2010+ Formula One points table, with a bonus point for the fastest lap.
+    pub fn formula_one() -> Self {
+        Self::new(vec![25, 18, 15, 12, 10, 8, 6, 4, 2, 1]).with_fastest_lap_bonus(1)
+    }
+
+    pub fn with_participation(mut self, points: u32) -> Self {
+        self.participation = points;
+        self
+    }
+
+    pub fn with_fastest_lap_bonus(mut self, points: u32) -> Self {
+        self.fastest_lap_bonus = points;
+        self
+    }
+
+    pub fn with_most_laps_led_bonus(mut self, points: u32) -> Self {
+        self.most_laps_led_bonus = points;
+        self
+    }
+
+    pub fn per_class(mut self, per_class: bool) -> Self {
+        self.per_class = per_class;
+        self
+    }
+
+    pub fn score_disqualified(mut self, score: bool) -> Self {
+        self.score_disqualified = score;
+        self
+    }
+
+    ///
+    /// Points awarded for finishing in a given (1-based) position, excluding bonuses.
+    pub fn for_position(&self, position: u32) -> u32 {
+        if position == 0 {
+            return 0;
+        }
+
+        self.positions
+            .get(position as usize - 1)
+            .copied()
+            .unwrap_or(self.participation)
+    }
+
+    ///
+    /// Points for every car in a set of results, as (car index, points).
+    pub fn score(&self, results: &Results) -> Vec<(usize, u32)> {
+        let standings = &results.standings;
+
+        let fastest = self.best_by(standings, |a, b| match (a.fastest_time, b.fastest_time) {
+            (Some(a), Some(b)) => a < b,
+            (Some(_), None) => true,
+            _ => false,
+        });
+        let most_led = self.best_by(standings, |a, b| a.laps_led > b.laps_led);
+
+        standings
+            .iter()
+            .map(|s| {
+                if s.reason_out == DISQUALIFIED && !self.score_disqualified {
+                    return (s.car_idx, 0);
+                }
+
+                let position = if self.per_class {
+                    s.class_position
+                } else {
+                    s.position
+                };
+
+                let mut points = self.for_position(position);
+
+                if fastest.iter().any(|f| f.car_idx == s.car_idx) {
+                    points += self.fastest_lap_bonus;
+                }
+                if most_led
+                    .iter()
+                    .any(|f| f.car_idx == s.car_idx && f.laps_led > 0)
+                {
+                    points += self.most_laps_led_bonus;
+                }
+
+                (s.car_idx, points)
+            })
+            .collect()
+    }
+
+    /// Best standing in each class (or overall) according to `better`.
+    fn best_by<'a, F>(&self, standings: &'a [Standing], better: F) -> Vec<&'a Standing>
+    where
+        F: Fn(&Standing, &Standing) -> bool,
+    {
+        let mut best: Vec<&Standing> = Vec::new();
+
+        for s in standings {
+            let class = if self.per_class {
+                s.car_class.as_str()
+            } else {
+                ""
+            };
+            let existing = best.iter_mut().find(|b| {
+                let b_class = if self.per_class {
+                    b.car_class.as_str()
+                } else {
+                    ""
+                };
+                b_class == class
+            });
+
+            match existing {
+                Some(b) if better(s, b) => *b = s,
+                Some(_) => {}
+                None => best.push(s),
+            }
+        }
+
+        best
+    }
+}
+
+impl Championship {
+    pub fn new(table: PointsTable, score_by: ScoreBy) -> Self {
+        Championship {
+            table,
+            score_by,
+            drop_rounds: 0,
+            rounds: Vec::new(),
+            entries: HashMap::new(),
+        }
+    }
+
+    ///
+    /// Discard each entry's worst `rounds` results from their total.
+    pub fn with_drop_rounds(mut self, rounds: usize) -> Self {
+        self.drop_rounds = rounds;
+        self
+    }
+
+    ///
+    /// Score a round of the championship.
+    pub fn add_round(&mut self, round: &str, results: &Results) {
+        self.rounds.push(round.to_owned());
+
+        for (car_idx, points) in self.table.score(results) {
+            let standing = match results.for_car(car_idx) {
+                Some(s) => s,
+                None => continue,
+            };
+
+            let (id, name) = match self.score_by {
+                ScoreBy::Driver => (standing.user_id, standing.driver_name.clone()),
+                ScoreBy::Team => (standing.team_id as i64, standing.team_name.clone()),
+            };
+
+            let position = if self.table.per_class {
+                standing.class_position
+            } else {
+                standing.position
+            };
+
+            let entry = self
+                .entries
+                .entry(id)
+                .or_insert_with(|| ChampionshipStanding {
+                    id,
+                    name: name.clone(),
+                    class: standing.car_class.clone(),
+                    points: 0,
+                    wins: 0,
+                    rounds: Vec::new(),
+                });
+
+            // Teams may enter more than one car; only their best result counts.
+            if let Some(existing) = entry.rounds.iter_mut().find(|r| r.round == round) {
+                if points > existing.points {
+                    existing.points = points;
+                    existing.position = position;
+                }
+                continue;
+            }
+
+            entry.name = name;
+            entry.rounds.push(RoundScore {
+                round: round.to_owned(),
+                position,
+                points,
+            });
+        }
+    }
+
+    /// Rounds scored so far, in order.
+    pub fn rounds(&self) -> &[String] {
+        &self.rounds
+    }
+
+    ///
+    /// Championship standings, ordered by points then wins.
+    pub fn standings(&self) -> Vec<ChampionshipStanding> {
+        let mut standings: Vec<ChampionshipStanding> = self
+            .entries
+            .values()
+            .cloned()
+            .map(|mut s| {
+                let mut scores: Vec<u32> = s.rounds.iter().map(|r| r.points).collect();
+                scores.sort_unstable();
+
+                // Rounds not entered count as zero-point drops
+                let missed = self.rounds.len().saturating_sub(scores.len());
+                let drop = self.drop_rounds.saturating_sub(missed).min(scores.len());
+
+                s.points = scores[drop..].iter().sum();
+                s.wins = s.rounds.iter().filter(|r| r.position == 1).count() as u32;
+                s
+            })
+            .collect();
+
+        standings.sort_by(|a, b| {
+            b.points
+                .cmp(&a.points)
+                .then(b.wins.cmp(&a.wins))
+                .then(a.name.cmp(&b.name))
+        });
+
+        standings
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::session::SessionDetails;
+
+    fn race() -> Results {
+        let content = std::fs::read_to_string("./session.yaml").unwrap();
+        let session: SessionDetails = serde_yaml::from_str(&content).unwrap();
+        Results::from_session(&session, 2).unwrap()
+    }
+
+    #[test]
+    fn score_race() {
+        let table = PointsTable::new(vec![10, 8, 6, 5, 4, 3]).with_fastest_lap_bonus(1);
+        let scores = table.score(&race());
+
+        // Winner also set the fastest lap, the disqualified car scores nothing
+        assert_eq!(
+            scores,
+            vec![(3, 11), (4, 8), (1, 6), (5, 5), (2, 4), (6, 0)]
+        );
+
+        let class_scores = table.per_class(true).score(&race());
+        assert_eq!(class_scores[2], (1, 11));
+    }
+
+    #[test]
+    fn season_standings() {
+        let mut championship =
+            Championship::new(PointsTable::new(vec![10, 8, 6, 5, 4, 3]), ScoreBy::Driver);
+        championship.add_round("Imola", &race());
+        championship.add_round("Imola 2", &race());
+
+        let standings = championship.standings();
+        assert_eq!(standings[0].name, "Ana Lucia Ferreira");
+        assert_eq!(standings[0].points, 20);
+        assert_eq!(standings[0].wins, 2);
+
+        let mut teams = Championship::new(PointsTable::new(vec![10, 8, 6, 5, 4, 3]), ScoreBy::Team)
+            .with_drop_rounds(1);
+        teams.add_round("Imola", &race());
+        teams.add_round("Imola 2", &race());
+
+        let breaker = teams
+            .standings()
+            .into_iter()
+            .find(|s| s.name == "Breaker Racing")
+            .unwrap();
+        assert_eq!(breaker.points, 6);
+    }
+}
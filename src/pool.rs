@@ -0,0 +1,139 @@
+use std::ops::Deref;
+use std::sync::{Arc, Mutex, Weak};
+
+type Free = Mutex<Vec<Vec<u8>>>;
+
+///
+/// Buffer Pool
+///
+/// Data buffers which are reused between samples, so a long running
+/// collector isn't allocating and freeing a buffer for every tick. A buffer
+/// goes back to the pool when the last sample holding it is dropped.
+///
+/// # Examples
+///
+/// ```
+/// use iracing::pool::BufferPool;
+///
+/// let pool = BufferPool::new(4);
+///
+/// let buffer = pool.copy(&[1, 2, 3]);
+/// assert_eq!(&buffer[..], &[1, 2, 3]);
+///
+/// drop(buffer);
+/// assert_eq!(pool.available(), 1);
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct BufferPool {
+    free: Arc<Free>,
+    capacity: usize,
+}
+
+///
+/// A data buffer, returned to its pool when dropped.
+#[derive(Debug, Default)]
+pub struct PooledBuffer {
+    data: Vec<u8>,
+    pool: Option<(Weak<Free>, usize)>,
+}
+
+impl BufferPool {
+    ///
+    /// Pool holding up to `capacity` free buffers. A capacity of 0 disables
+    /// pooling.
+    pub fn new(capacity: usize) -> Self {
+        BufferPool {
+            free: Arc::new(Mutex::new(Vec::with_capacity(capacity))),
+            capacity,
+        }
+    }
+
+    /// Number of free buffers waiting to be reused.
+    pub fn available(&self) -> usize {
+        self.free.lock().map(|free| free.len()).unwrap_or(0)
+    }
+
+    ///
+    /// Copy `data` into a buffer from the pool, allocating a new buffer if
+    /// none is free.
+    pub fn copy(&self, data: &[u8]) -> PooledBuffer {
+        if self.capacity == 0 {
+            return PooledBuffer::from(data.to_vec());
+        }
+
+        let mut buffer = self
+            .free
+            .lock()
+            .ok()
+            .and_then(|mut free| free.pop())
+            .unwrap_or_default();
+
+        buffer.clear();
+        buffer.extend_from_slice(data);
+
+        PooledBuffer {
+            data: buffer,
+            pool: Some((Arc::downgrade(&self.free), self.capacity)),
+        }
+    }
+}
+
+impl From<Vec<u8>> for PooledBuffer {
+    /// A buffer which doesn't belong to any pool.
+    fn from(data: Vec<u8>) -> Self {
+        PooledBuffer { data, pool: None }
+    }
+}
+
+impl Deref for PooledBuffer {
+    type Target = [u8];
+
+    fn deref(&self) -> &[u8] {
+        &self.data
+    }
+}
+
+impl Drop for PooledBuffer {
+    fn drop(&mut self) {
+        let (pool, capacity) = match self.pool.take() {
+            Some(pool) => pool,
+            None => return,
+        };
+
+        if let Some(free) = pool.upgrade() {
+            if let Ok(mut free) = free.lock() {
+                if free.len() < capacity {
+                    free.push(std::mem::take(&mut self.data));
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn reuse_buffers() {
+        let pool = BufferPool::new(2);
+
+        let first = pool.copy(&[0; 64]);
+        let address = first.as_ptr();
+        drop(first);
+
+        let second = pool.copy(&[1; 32]);
+        assert_eq!(second.as_ptr(), address);
+        assert_eq!(second.len(), 32);
+        assert_eq!(pool.available(), 0);
+
+        let buffers: Vec<PooledBuffer> = (0..4).map(|_| pool.copy(&[2; 8])).collect();
+        drop(buffers);
+        drop(second);
+        assert_eq!(pool.available(), 2);
+
+        let orphan = pool.copy(&[3; 8]);
+        drop(pool);
+        drop(orphan);
+    }
+}
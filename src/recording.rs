@@ -0,0 +1,487 @@
+use crate::session::SessionDetails;
+use crate::telemetry::{Header, Sample};
+use crate::yaml::decode;
+use std::error::Error;
+use std::fmt;
+use std::fs::File;
+use std::io::{self, BufWriter, Read, Write};
+use std::path::Path;
+
+#[cfg(feature = "compression")]
+use crate::compression::{CompressedReader, CompressedWriter, Compression};
+#[cfg(not(feature = "compression"))]
+use std::io::BufReader;
+
+/// Identifies a recording file.
+pub const MAGIC: [u8; 8] = *b"IRSDKREC";
+
+/// Version of the recording format.
+pub const RECORDING_VERSION: u32 = 1;
+
+#[derive(Debug)]
+pub enum RecordingError {
+    BadMagic,
+    UnsupportedVersion(u32),
+
+    /// A snapshot's header describes data outside the snapshot
+    InvalidSnapshot(usize, String),
+    IO(io::Error),
+}
+
+impl fmt::Display for RecordingError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::BadMagic => write!(f, "Not a recording"),
+            Self::UnsupportedVersion(v) => write!(f, "Unsupported recording version {}", v),
+            Self::InvalidSnapshot(idx, e) => write!(f, "Snapshot {} is invalid: {}", idx, e),
+            Self::IO(e) => write!(f, "IO Error: {}", e),
+        }
+    }
+}
+
+impl Error for RecordingError {}
+
+impl From<io::Error> for RecordingError {
+    fn from(e: io::Error) -> Self {
+        Self::IO(e)
+    }
+}
+
+///
+/// Recording
+///
+/// A sequence of raw memory map snapshots - header, variable headers, data
+/// buffers and session info - which can be played back through the same
+/// parsing as live telemetry. Recordings make fixtures for tests which don't
+/// need a running sim.
+///
+/// On disk a recording is the magic `"IRSDKREC"`, the format version as a
+/// little-endian u32, then each snapshot as a little-endian u32 length
+/// followed by its bytes.
+///
+/// Snapshots are taken from a live sim with `Connection::snapshot`.
+///
+/// # Examples
+///
+/// ```no_run
+/// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+/// use iracing::recording::Recording;
+/// use std::convert::TryInto;
+///
+/// let recording = Recording::load("fixtures/pit_stop.irrec")?;
+///
+/// for sample in recording.player() {
+///     let speed: f32 = sample?.get("Speed")?.try_into()?;
+/// }
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Recording {
+    snapshots: Vec<Vec<u8>>,
+}
+
+///
+/// Plays a recording back one snapshot at a time, in order.
+#[derive(Debug)]
+pub struct Player<'a> {
+    recording: &'a Recording,
+    position: usize,
+}
+
+impl Recording {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Add a raw memory map snapshot, as returned by `Connection::snapshot`.
+    pub fn push(&mut self, snapshot: Vec<u8>) {
+        self.snapshots.push(snapshot);
+    }
+
+    pub fn len(&self) -> usize {
+        self.snapshots.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.snapshots.is_empty()
+    }
+
+    /// Raw snapshots, in the order they were recorded.
+    pub fn snapshots(&self) -> &[Vec<u8>] {
+        &self.snapshots
+    }
+
+    pub fn write_to<W: Write>(&self, writer: &mut W) -> io::Result<()> {
+        writer.write_all(&MAGIC)?;
+        writer.write_all(&RECORDING_VERSION.to_le_bytes())?;
+
+        for snapshot in self.snapshots.iter() {
+            writer.write_all(&(snapshot.len() as u32).to_le_bytes())?;
+            writer.write_all(snapshot)?;
+        }
+
+        Ok(())
+    }
+
+    ///
+    /// Read a recording, checking every snapshot describes a valid layout.
+    pub fn read_from<R: Read>(reader: &mut R) -> Result<Recording, RecordingError> {
+        let mut magic = [0u8; 8];
+        reader.read_exact(&mut magic)?;
+        if magic != MAGIC {
+            return Err(RecordingError::BadMagic);
+        }
+
+        let mut word = [0u8; 4];
+        reader.read_exact(&mut word)?;
+        let version = u32::from_le_bytes(word);
+        if version != RECORDING_VERSION {
+            return Err(RecordingError::UnsupportedVersion(version));
+        }
+
+        let mut recording = Recording::new();
+        loop {
+            match reader.read_exact(&mut word) {
+                Ok(()) => {}
+                Err(e) if e.kind() == io::ErrorKind::UnexpectedEof => break,
+                Err(e) => return Err(e.into()),
+            }
+
+            // The length comes from the file, so only allocate what is there
+            let len = u32::from_le_bytes(word) as u64;
+            let mut snapshot = Vec::new();
+            reader.by_ref().take(len).read_to_end(&mut snapshot)?;
+            if (snapshot.len() as u64) < len {
+                return Err(io::Error::from(io::ErrorKind::UnexpectedEof).into());
+            }
+
+            check(&snapshot).map_err(|e| RecordingError::InvalidSnapshot(recording.len(), e))?;
+            recording.push(snapshot);
+        }
+
+        Ok(recording)
+    }
+
+    ///
+    /// Save to a file. With the `compression` feature, paths ending in `.gz`
+    /// or `.zst` are compressed.
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> io::Result<()> {
+        #[cfg(feature = "compression")]
+        let compression = Compression::from_path(&path);
+
+        let mut writer = BufWriter::new(File::create(path)?);
+
+        #[cfg(feature = "compression")]
+        {
+            let mut compressed = CompressedWriter::new(writer, compression)?;
+            self.write_to(&mut compressed)?;
+            writer = compressed.finish()?;
+        }
+        #[cfg(not(feature = "compression"))]
+        self.write_to(&mut writer)?;
+
+        writer.flush()
+    }
+
+    ///
+    /// Load from a file. With the `compression` feature, gzip and zstd
+    /// compressed files are detected and decompressed.
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Recording, RecordingError> {
+        let file = File::open(path)?;
+
+        #[cfg(feature = "compression")]
+        return Self::read_from(&mut CompressedReader::new(file)?);
+
+        #[cfg(not(feature = "compression"))]
+        Self::read_from(&mut BufReader::new(file))
+    }
+
+    /// Play the recording back from the start.
+    pub fn player(&self) -> Player<'_> {
+        Player {
+            recording: self,
+            position: 0,
+        }
+    }
+
+    ///
+    /// Telemetry sample from a snapshot.
+    pub fn sample(&self, idx: usize) -> Option<Result<Sample, Box<dyn Error>>> {
+        let snapshot = self.snapshots.get(idx)?;
+        Some(sample(snapshot))
+    }
+
+    ///
+    /// Session info from a snapshot.
+    pub fn session_info(&self, idx: usize) -> Option<Result<SessionDetails, Box<dyn Error>>> {
+        let snapshot = self.snapshots.get(idx)?;
+        Some(session_info(snapshot))
+    }
+}
+
+impl<'a> Player<'a> {
+    /// Index of the next snapshot to be played.
+    pub fn position(&self) -> usize {
+        self.position
+    }
+
+    /// Session info as of the last snapshot played.
+    pub fn session_info(&self) -> Option<Result<SessionDetails, Box<dyn Error>>> {
+        self.recording.session_info(self.position.checked_sub(1)?)
+    }
+
+    /// Go back to the start of the recording.
+    pub fn rewind(&mut self) {
+        self.position = 0;
+    }
+}
+
+impl<'a> Iterator for Player<'a> {
+    type Item = Result<Sample, Box<dyn Error>>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let sample = self.recording.sample(self.position)?;
+        self.position += 1;
+        Some(sample)
+    }
+}
+
+fn check(snapshot: &[u8]) -> Result<Header, String> {
+    let header = Header::from_bytes(snapshot).ok_or("Snapshot is shorter than a header")?;
+    header.validate(snapshot.len())?;
+    Ok(header)
+}
+
+fn sample(snapshot: &[u8]) -> Result<Sample, Box<dyn Error>> {
+    let header = check(snapshot)?;
+    let (idx, tick) = header.latest_buffer().ok_or("No valid data buffer")?;
+
+    Ok(header.sample_from(snapshot, header.buffer_offset(idx), tick)?)
+}
+
+fn session_info(snapshot: &[u8]) -> Result<SessionDetails, Box<dyn Error>> {
+    let header = check(snapshot)?;
+    let start = header.session_info_offset as usize;
+    let data = &snapshot[start..start + header.session_info_length as usize];
+
+    // Session info is NUL padded
+    let end = data.iter().position(|b| *b == 0).unwrap_or(data.len());
+    Ok(SessionDetails::parse(&decode(&data[..end]).0)?)
+}
+
+///
+/// Snapshot Builder
+///
+/// Builds synthetic memory map snapshots, for tests which need specific
+/// telemetry values.
+///
+/// # Examples
+///
+/// ```
+/// use iracing::recording::{Recording, SnapshotBuilder};
+/// use iracing::telemetry::Value;
+/// use std::convert::TryInto;
+///
+/// let mut recording = Recording::new();
+/// for tick in 1..=3 {
+///     let snapshot = SnapshotBuilder::new(tick)
+///         .with_value("Speed", Value::FLOAT(20.0 * tick as f32))
+///         .with_value("CarIdxLap", Value::IntVec(vec![0, 4, 5]))
+///         .build();
+///     recording.push(snapshot);
+/// }
+///
+/// let last = recording.player().last().unwrap().unwrap();
+/// let speed: f32 = last.get("Speed").unwrap().try_into().unwrap();
+/// assert_eq!(speed, 60.0);
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct SnapshotBuilder {
+    tick: i32,
+    values: Vec<(String, crate::telemetry::Value)>,
+    session_info: String,
+}
+
+const HEADER_SIZE: usize = 112;
+const VAR_HEADER_SIZE: usize = 144;
+
+impl SnapshotBuilder {
+    pub fn new(tick: i32) -> Self {
+        SnapshotBuilder {
+            tick,
+            ..Default::default()
+        }
+    }
+
+    pub fn with_value(mut self, name: &str, value: crate::telemetry::Value) -> Self {
+        self.values.push((name.to_owned(), value));
+        self
+    }
+
+    /// Session info YAML.
+    pub fn with_session_info(mut self, yaml: &str) -> Self {
+        self.session_info = yaml.to_owned();
+        self
+    }
+
+    ///
+    /// Lay the snapshot out as the sim does: header, variable headers, session
+    /// info, then a single data buffer.
+    pub fn build(&self) -> Vec<u8> {
+        use crate::telemetry::Value;
+
+        let encoded: Vec<(i32, usize, Vec<u8>)> = self
+            .values
+            .iter()
+            .map(|(_, value)| match value {
+                Value::CHAR(v) => (0, 1, vec![*v]),
+                Value::BOOL(v) => (1, 1, vec![*v as u8]),
+                Value::INT(v) => (2, 1, v.to_le_bytes().to_vec()),
+                Value::BITS(v) => (3, 1, v.to_le_bytes().to_vec()),
+                Value::FLOAT(v) => (4, 1, v.to_le_bytes().to_vec()),
+                Value::DOUBLE(v) => (5, 1, v.to_le_bytes().to_vec()),
+                Value::UNKNOWN(_) => (6, 1, vec![0]),
+                Value::IntVec(v) => (2, v.len(), v.iter().flat_map(|n| n.to_le_bytes()).collect()),
+                Value::FloatVec(v) => {
+                    (4, v.len(), v.iter().flat_map(|n| n.to_le_bytes()).collect())
+                }
+                Value::BoolVec(v) => (1, v.len(), v.iter().map(|b| *b as u8).collect()),
+                Value::BitsVec(v) => (3, v.len(), v.iter().flat_map(|n| n.to_le_bytes()).collect()),
+            })
+            .collect();
+
+        let var_offset = HEADER_SIZE;
+        let session_offset = var_offset + self.values.len() * VAR_HEADER_SIZE;
+        let session_length = round_up(self.session_info.len() + 1);
+        let buffer_offset = session_offset + session_length;
+
+        let mut data = Vec::new();
+        let mut offsets = Vec::new();
+        for (_, _, bytes) in encoded.iter() {
+            offsets.push(data.len());
+            data.extend_from_slice(bytes);
+            data.resize(round_up(data.len()), 0);
+        }
+
+        let mut out = vec![0u8; buffer_offset + data.len().max(16)];
+        let header: [i32; 10] = [
+            2, // version
+            1, // status - connected
+            60,
+            1,
+            session_length as i32,
+            session_offset as i32,
+            self.values.len() as i32,
+            var_offset as i32,
+            1,
+            data.len() as i32,
+        ];
+        for (i, v) in header.iter().enumerate() {
+            out[i * 4..i * 4 + 4].copy_from_slice(&v.to_le_bytes());
+        }
+        out[48..52].copy_from_slice(&self.tick.to_le_bytes());
+        out[52..56].copy_from_slice(&(buffer_offset as i32).to_le_bytes());
+
+        for (i, ((name, _), (value_type, count, _))) in
+            self.values.iter().zip(encoded.iter()).enumerate()
+        {
+            let at = var_offset + i * VAR_HEADER_SIZE;
+            out[at..at + 4].copy_from_slice(&value_type.to_le_bytes());
+            out[at + 4..at + 8].copy_from_slice(&(offsets[i] as i32).to_le_bytes());
+            out[at + 8..at + 12].copy_from_slice(&(*count as i32).to_le_bytes());
+
+            let name = name.as_bytes();
+            let len = name.len().min(31);
+            out[at + 16..at + 16 + len].copy_from_slice(&name[..len]);
+        }
+
+        out[session_offset..session_offset + self.session_info.len()]
+            .copy_from_slice(self.session_info.as_bytes());
+        out[buffer_offset..buffer_offset + data.len()].copy_from_slice(&data);
+
+        out
+    }
+}
+
+fn round_up(len: usize) -> usize {
+    len.div_ceil(16) * 16
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::telemetry::Value;
+    use std::convert::TryInto;
+
+    #[test]
+    fn record_and_play() {
+        let yaml = std::fs::read_to_string("./session.yaml").unwrap();
+        let mut recording = Recording::new();
+
+        for tick in 1..=3 {
+            recording.push(
+                SnapshotBuilder::new(tick)
+                    .with_value("SessionTime", Value::DOUBLE(tick as f64 / 60.0))
+                    .with_value("Gear", Value::INT(tick))
+                    .with_value("CarIdxOnPitRoad", Value::BoolVec(vec![false, true]))
+                    .with_session_info(&yaml)
+                    .build(),
+            );
+        }
+
+        let mut file = Vec::new();
+        recording.write_to(&mut file).unwrap();
+        let loaded = Recording::read_from(&mut file.as_slice()).unwrap();
+        assert_eq!(loaded, recording);
+
+        let mut player = loaded.player();
+        let gears: Vec<i32> = player
+            .by_ref()
+            .map(|s| s.unwrap().get("Gear").unwrap().try_into().unwrap())
+            .collect();
+        assert_eq!(gears, vec![1, 2, 3]);
+
+        let session = player.session_info().unwrap().unwrap();
+        assert_eq!(session.drivers.other_drivers[1].user_name, "L W Adamek");
+
+        let sample = loaded.sample(2).unwrap().unwrap();
+        assert_eq!(sample.tick(), 3);
+        let pits: Vec<bool> = sample.get("CarIdxOnPitRoad").unwrap().into();
+        assert_eq!(pits, vec![false, true]);
+
+        #[cfg(feature = "compression")]
+        {
+            let dir = tempfile::tempdir().unwrap();
+            let path = dir.path().join("race.irrec.zst");
+            recording.save(&path).unwrap();
+
+            let stored = std::fs::read(&path).unwrap();
+            assert_eq!(Compression::detect(&stored), Compression::Zstd);
+            assert_eq!(Recording::load(&path).unwrap(), recording);
+        }
+
+        // Snapshots pointing outside themselves are rejected
+        let mut bytes = Vec::new();
+        Recording {
+            snapshots: vec![recording.snapshots[0][..200].to_vec()],
+        }
+        .write_to(&mut bytes)
+        .unwrap();
+        assert!(matches!(
+            Recording::read_from(&mut bytes.as_slice()),
+            Err(RecordingError::InvalidSnapshot(0, _))
+        ));
+
+        // A length running past the end of the file is a truncated recording
+        let mut bytes = Vec::new();
+        recording.write_to(&mut bytes).unwrap();
+        bytes.truncate(12);
+        bytes.extend_from_slice(&u32::MAX.to_le_bytes());
+        bytes.extend_from_slice(&recording.snapshots[0]);
+        assert!(matches!(
+            Recording::read_from(&mut bytes.as_slice()),
+            Err(RecordingError::IO(e)) if e.kind() == io::ErrorKind::UnexpectedEof
+        ));
+    }
+}
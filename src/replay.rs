@@ -1,13 +1,28 @@
+#[cfg(all(target_os = "windows", feature = "broadcast"))]
+use crate::broadcast::{Broadcast, BroadcastMessage, ReplayPositionMode, ReplayStateMode};
+use crate::incidents::Incident;
+use crate::penalties::Penalty;
 use chrono::NaiveDateTime;
+use serde::{Deserialize, Serialize};
 use std::io;
 use std::io::Read;
 use std::io::Result as IOResult;
 use std::io::{Error as IOError, ErrorKind};
 use std::u32;
 
+#[cfg(feature = "telemetry")]
+use crate::telemetry::Sample;
+#[cfg(feature = "telemetry")]
+use std::convert::TryInto;
+#[cfg(feature = "telemetry")]
+use std::error::Error;
+
 /// Magic number found at the start of replay files
 pub const FILE_MAGIC: &[u8] = b"YLPR";
 
+/// Replay frames recorded per second of session time
+pub const FRAME_RATE: f64 = 60.0;
+
 /// Length of an individual entry in the file preamble.
 const ENTRY_LENGTH: usize = 12;
 
@@ -207,10 +222,323 @@ pub fn validate_reader<R: Read>(mut src: R) -> IOResult<()> {
     }
 }
 
+///
+/// What a bookmark marks.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum BookmarkKind {
+    User,
+    Incident,
+    Overtake,
+    Penalty,
+    Other(String),
+}
+
+///
+/// A marker on the replay timeline.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Bookmark {
+    pub session_number: u8,
+    pub session_time: f64,      // Seconds since session start
+    pub frame: Option<u32>,     // Replay frame number (`ReplayFrameNum`), if known
+    pub car_idx: Option<usize>, // Car involved, if any
+    pub kind: BookmarkKind,
+    pub label: String,
+}
+
+///
+/// Bookmarks
+///
+/// Markers on the replay timeline, added by the user or from detected events,
+/// which can be used to jump around the replay.
+///
+/// # Examples
+///
+/// ```
+/// use iracing::replay::{BookmarkKind, Bookmarks};
+///
+/// let mut bookmarks = Bookmarks::new();
+/// bookmarks.mark(2, 95.0, "Great save", BookmarkKind::User);
+/// bookmarks.mark(2, 42.5, "Turn 1 contact", BookmarkKind::Incident);
+///
+/// let first = bookmarks.next(2, 0.0).unwrap();
+/// assert_eq!(first.label, "Turn 1 contact");
+/// ```
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct Bookmarks {
+    marks: Vec<Bookmark>,
+}
+
+impl Bookmark {
+    ///
+    /// The broadcast message which moves the replay to this bookmark.
+    #[cfg(all(target_os = "windows", feature = "broadcast"))]
+    pub fn seek(&self) -> BroadcastMessage {
+        match self.frame {
+            Some(frame) => {
+                BroadcastMessage::ReplaySetPlayPosition(ReplayPositionMode::Begin, frame)
+            }
+            None => BroadcastMessage::ReplaySearchSessionTime(
+                self.session_number,
+                (self.session_time.max(0.0) * 1000.0) as u32,
+            ),
+        }
+    }
+}
+
+impl Bookmarks {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    ///
+    /// Add a bookmark, keeping bookmarks in timeline order.
+    pub fn add(&mut self, bookmark: Bookmark) {
+        let idx = self.marks.partition_point(|b| {
+            (b.session_number, b.session_time) <= (bookmark.session_number, bookmark.session_time)
+        });
+        self.marks.insert(idx, bookmark);
+    }
+
+    /// Add a bookmark at a session time.
+    pub fn mark(&mut self, session_number: u8, session_time: f64, label: &str, kind: BookmarkKind) {
+        self.add(Bookmark {
+            session_number,
+            session_time,
+            frame: None,
+            car_idx: None,
+            kind,
+            label: label.to_owned(),
+        });
+    }
+
+    ///
+    /// Bookmark an incident from the incident ledger.
+    pub fn add_incident(&mut self, session_number: u8, incident: &Incident) {
+        self.add(Bookmark {
+            session_number,
+            session_time: incident.session_time,
+            frame: None,
+            car_idx: Some(incident.car_idx),
+            kind: BookmarkKind::Incident,
+            label: format!("{}x for car {}", incident.points, incident.car_idx),
+        });
+    }
+
+    ///
+    /// Bookmark a penalty being issued. Penalties taken from results have no time and are skipped.
+    pub fn add_penalty(&mut self, session_number: u8, penalty: &Penalty) {
+        if let Some(session_time) = penalty.issued_at {
+            self.add(Bookmark {
+                session_number,
+                session_time,
+                frame: None,
+                car_idx: Some(penalty.car_idx),
+                kind: BookmarkKind::Penalty,
+                label: format!("{:?} for car {}", penalty.kind, penalty.car_idx),
+            });
+        }
+    }
+
+    /// All bookmarks in timeline order.
+    pub fn all(&self) -> &[Bookmark] {
+        &self.marks
+    }
+
+    /// Bookmarks of a given kind.
+    pub fn of_kind<'a>(&'a self, kind: &'a BookmarkKind) -> impl Iterator<Item = &'a Bookmark> {
+        self.marks.iter().filter(move |b| b.kind == *kind)
+    }
+
+    ///
+    /// The first bookmark after a point on the timeline.
+    pub fn next(&self, session_number: u8, session_time: f64) -> Option<&Bookmark> {
+        self.marks
+            .iter()
+            .find(|b| (b.session_number, b.session_time) > (session_number, session_time))
+    }
+
+    ///
+    /// The last bookmark before a point on the timeline.
+    pub fn previous(&self, session_number: u8, session_time: f64) -> Option<&Bookmark> {
+        self.marks
+            .iter()
+            .rev()
+            .find(|b| (b.session_number, b.session_time) < (session_number, session_time))
+    }
+
+    /// Remove bookmarks matching a predicate.
+    pub fn remove<F: Fn(&Bookmark) -> bool>(&mut self, predicate: F) {
+        self.marks.retain(|b| !predicate(b));
+    }
+}
+
+///
+/// Replay Timeline
+///
+/// Position of the sim within the replay, read from the `Replay*` channels.
+///
+/// # Examples
+///
+/// ```
+/// use iracing::replay::ReplayTimeline;
+///
+/// let timeline = ReplayTimeline {
+///     current_frame: 600,
+///     frames_total: 6000,
+///     session_number: 2,
+///     session_time: 110.0,
+/// };
+///
+/// assert_eq!(timeline.session_time_at_frame(1200), 120.0);
+/// ```
+#[derive(Debug, Copy, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct ReplayTimeline {
+    pub current_frame: u32, // Frame being shown (`ReplayFrameNum`)
+    pub frames_total: u32,  // Frames in the replay
+    pub session_number: i32,
+    pub session_time: f64, // Session time of the current frame
+}
+
+impl ReplayTimeline {
+    ///
+    /// Read the replay timeline from a telemetry sample.
+    #[cfg(feature = "telemetry")]
+    pub fn from_sample(sample: &Sample) -> Result<Self, Box<dyn Error>> {
+        let current: i32 = sample.get("ReplayFrameNum")?.try_into()?;
+        let remaining: i32 = sample.get("ReplayFrameNumEnd")?.try_into()?;
+
+        Ok(ReplayTimeline {
+            current_frame: current.max(0) as u32,
+            frames_total: (current.max(0) + remaining.max(0)) as u32,
+            session_number: sample.get("ReplaySessionNum")?.try_into()?,
+            session_time: sample.get("ReplaySessionTime")?.try_into()?,
+        })
+    }
+
+    /// Frames after the current frame.
+    pub fn frames_remaining(&self) -> u32 {
+        self.frames_total.saturating_sub(self.current_frame)
+    }
+
+    ///
+    /// Estimated session time of a frame in the current session.
+    ///
+    /// Frames are recorded at `FRAME_RATE`, so the estimate is only valid
+    /// within the session being shown.
+    pub fn session_time_at_frame(&self, frame: u32) -> f64 {
+        self.session_time + (frame as f64 - self.current_frame as f64) / FRAME_RATE
+    }
+
+    ///
+    /// Estimated frame at a session time in the current session, clamped to
+    /// the replay.
+    pub fn frame_at_session_time(&self, session_time: f64) -> u32 {
+        let frame = self.current_frame as f64 + (session_time - self.session_time) * FRAME_RATE;
+
+        frame.round().clamp(0.0, self.frames_total as f64) as u32
+    }
+}
+
+///
+/// Erase Request
+///
+/// Erasing the replay tape can't be undone, so it takes three steps: the
+/// request records the length of the replay and describes what will be lost,
+/// it must be confirmed before it can be sent, and once sent telemetry is
+/// checked to make sure the tape was erased.
+///
+/// # Examples
+///
+/// ```
+/// use iracing::replay::{EraseRequest, ReplayTimeline};
+/// # let timeline = ReplayTimeline::default();
+///
+/// let request = EraseRequest::new(&timeline);
+/// println!("{}", request.prompt());
+///
+/// // Once the user agrees, `send` it with a `Broadcast` and `verify` the
+/// // timeline read afterwards
+/// let confirmed = request.confirm();
+/// ```
+#[derive(Debug, Clone, PartialEq)]
+pub struct EraseRequest {
+    frames: u32,
+}
+
+///
+/// An erase request which has been confirmed and may be sent.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ConfirmedErase {
+    frames: u32,
+}
+
+///
+/// An erase which has been sent, waiting to be checked against telemetry.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SentErase {
+    frames: u32,
+}
+
+impl EraseRequest {
+    pub fn new(timeline: &ReplayTimeline) -> Self {
+        EraseRequest {
+            frames: timeline.frames_total,
+        }
+    }
+
+    /// Frames which will be erased.
+    pub fn frames(&self) -> u32 {
+        self.frames
+    }
+
+    /// Text asking the user to confirm the erase.
+    pub fn prompt(&self) -> String {
+        let seconds = (self.frames as f64 / FRAME_RATE).round() as u32;
+
+        format!(
+            "Erase {} frames ({}:{:02}) of replay? This can't be undone.",
+            self.frames,
+            seconds / 60,
+            seconds % 60
+        )
+    }
+
+    /// Confirm the user has agreed to the erase.
+    pub fn confirm(self) -> ConfirmedErase {
+        ConfirmedErase {
+            frames: self.frames,
+        }
+    }
+}
+
+impl ConfirmedErase {
+    ///
+    /// Send the erase to the sim.
+    #[cfg(all(target_os = "windows", feature = "broadcast"))]
+    pub fn send(self, broadcast: &Broadcast) -> SentErase {
+        broadcast.send_message(BroadcastMessage::ReplaySetState(ReplayStateMode::EraseTape));
+
+        SentErase {
+            frames: self.frames,
+        }
+    }
+}
+
+impl SentErase {
+    ///
+    /// Check a timeline read after the erase was sent.
+    ///
+    /// The sim keeps recording after erasing, so the erase is taken to have
+    /// worked if the replay is shorter than it was when requested.
+    pub fn verify(&self, timeline: &ReplayTimeline) -> bool {
+        timeline.frames_total < self.frames || timeline.frames_total == 0
+    }
+}
+
 #[cfg(test)]
 mod tests {
 
-    use crate::replay::Header;
+    use crate::replay::{BookmarkKind, Bookmarks, EraseRequest, Header, ReplayTimeline, SentErase};
     use std::fs::File;
     use std::io::BufReader;
     use std::io::ErrorKind;
@@ -258,4 +586,66 @@ mod tests {
         assert_eq!(metadata.layout, Some(String::from("oval")));
         assert_eq!(metadata.user_name, String::from("L W Adamek"));
     }
+
+    #[test]
+    fn navigate_bookmarks() {
+        let mut bookmarks = Bookmarks::new();
+        bookmarks.mark(2, 300.0, "Overtake for the lead", BookmarkKind::Overtake);
+        bookmarks.mark(2, 120.0, "Spin at turn 3", BookmarkKind::Incident);
+        bookmarks.mark(1, 500.0, "Pole lap", BookmarkKind::User);
+
+        let labels: Vec<&str> = bookmarks.all().iter().map(|b| b.label.as_str()).collect();
+        assert_eq!(
+            labels,
+            vec!["Pole lap", "Spin at turn 3", "Overtake for the lead"]
+        );
+
+        assert_eq!(
+            bookmarks.next(2, 120.0).unwrap().kind,
+            BookmarkKind::Overtake
+        );
+        assert_eq!(bookmarks.previous(2, 10.0).unwrap().label, "Pole lap");
+        assert_eq!(bookmarks.of_kind(&BookmarkKind::Incident).count(), 1);
+
+        bookmarks.remove(|b| b.session_number == 1);
+        assert!(bookmarks.previous(2, 10.0).is_none());
+    }
+
+    #[test]
+    fn replay_timeline() {
+        let timeline = ReplayTimeline {
+            current_frame: 600,
+            frames_total: 6000,
+            session_number: 2,
+            session_time: 110.0,
+        };
+
+        assert_eq!(timeline.frames_remaining(), 5400);
+        assert_eq!(timeline.session_time_at_frame(0), 100.0);
+        assert_eq!(timeline.frame_at_session_time(115.0), 900);
+        assert_eq!(timeline.frame_at_session_time(50.0), 0);
+        assert_eq!(timeline.frame_at_session_time(1000.0), 6000);
+    }
+
+    #[test]
+    fn erase_flow() {
+        let mut timeline = ReplayTimeline {
+            frames_total: 7260,
+            ..Default::default()
+        };
+
+        let request = EraseRequest::new(&timeline);
+        assert_eq!(
+            request.prompt(),
+            "Erase 7260 frames (2:01) of replay? This can't be undone."
+        );
+
+        let sent = SentErase {
+            frames: request.confirm().frames,
+        };
+        assert!(!sent.verify(&timeline));
+
+        timeline.frames_total = 30;
+        assert!(sent.verify(&timeline));
+    }
 }
@@ -0,0 +1,576 @@
+use crate::results::Standing;
+use std::error::Error;
+use std::fmt;
+
+#[cfg(all(target_os = "windows", feature = "telemetry"))]
+use crate::telemetry::{Sample, Value};
+#[cfg(all(target_os = "windows", feature = "telemetry"))]
+use std::sync::atomic::{fence, Ordering};
+#[cfg(all(target_os = "windows", feature = "telemetry"))]
+use winapi::um::handleapi::{CloseHandle, INVALID_HANDLE_VALUE};
+#[cfg(all(target_os = "windows", feature = "telemetry"))]
+use winapi::um::memoryapi::{
+    CreateFileMappingW, MapViewOfFile, UnmapViewOfFile, FILE_MAP_ALL_ACCESS,
+};
+#[cfg(all(target_os = "windows", feature = "telemetry"))]
+use winapi::um::winnt::{HANDLE, PAGE_READWRITE};
+
+/// Default name of the republished shared memory block.
+pub const REPUBLISH_PATH: &str = r"Local\IRacingRsRepublish";
+
+/// Identifies a republished block.
+pub const MAGIC: [u8; 4] = *b"IRRP";
+
+/// Version of the block layout. Bumped on any incompatible change.
+pub const LAYOUT_VERSION: u32 = 1;
+
+const HEADER_SIZE: usize = 64;
+const CHANNEL_ENTRY_SIZE: usize = 48;
+const CHANNEL_NAME_SIZE: usize = 40;
+const STANDING_ENTRY_SIZE: usize = 64;
+const CAR_NUMBER_SIZE: usize = 8;
+const DRIVER_NAME_SIZE: usize = 40;
+
+///
+/// Layout
+///
+/// Describes a simplified shared memory block of selected channels and
+/// standings, which stays the same however iRacing's own layout changes.
+///
+/// All values are little-endian. The block is:
+///
+/// | Offset | Field |
+/// |--------|-------|
+/// | 0      | Magic `"IRRP"` |
+/// | 4      | Layout version (u32) |
+/// | 8      | Sequence (u32), odd while the block is being written |
+/// | 12     | Total size of the block (u32) |
+/// | 16     | Session time (f64) |
+/// | 24     | Channel count (u32) |
+/// | 28     | Offset of the channel table (u32) |
+/// | 32     | Standings count (u32) |
+/// | 36     | Standings capacity (u32) |
+/// | 40     | Offset of the standings (u32) |
+///
+/// Each channel table entry is 48 bytes: a NUL padded name (40 bytes), the
+/// number of values (u32) and the offset of its values (u32). Every value is
+/// stored as an f64.
+///
+/// Each standing is 64 bytes: position, class position, car index and laps
+/// complete (u32 each), a NUL padded car number (8 bytes) and a NUL padded
+/// driver name (40 bytes).
+///
+/// # Examples
+///
+/// ```
+/// use iracing::republish::Layout;
+///
+/// let layout = Layout::new()
+///     .with_channel("Speed", 1)
+///     .with_channel("CarIdxLapDistPct", 64)
+///     .with_standings(64);
+///
+/// let mut block = vec![0u8; layout.size()];
+/// layout.encode(&mut block, 2, 123.4, &[vec![54.2], vec![0.5; 64]], &[]);
+///
+/// let snapshot = Layout::decode(&block).unwrap();
+/// assert_eq!(snapshot.channel("Speed"), Some(&[54.2][..]));
+/// ```
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Layout {
+    channels: Vec<(String, usize)>,
+    standings: usize,
+}
+
+///
+/// A standing as stored in the block.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BlockStanding {
+    pub position: u32,
+    pub class_position: u32,
+    pub car_idx: u32,
+    pub laps_complete: u32,
+    pub car_number: String,
+    pub driver_name: String,
+}
+
+///
+/// The decoded contents of a block.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Snapshot {
+    pub sequence: u32,
+    pub session_time: f64,
+    pub channels: Vec<(String, Vec<f64>)>,
+    pub standings: Vec<BlockStanding>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum LayoutError {
+    /// Block is too small or a table points outside it
+    Truncated,
+    BadMagic,
+    UnsupportedVersion(u32),
+
+    /// Block was being written while it was read
+    Torn,
+}
+
+impl fmt::Display for LayoutError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Truncated => write!(f, "Block is truncated"),
+            Self::BadMagic => write!(f, "Block has the wrong magic number"),
+            Self::UnsupportedVersion(v) => write!(f, "Unsupported layout version {}", v),
+            Self::Torn => write!(f, "Block was being written"),
+        }
+    }
+}
+
+impl Error for LayoutError {}
+
+impl Layout {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    ///
+    /// Add a channel with a fixed number of values, e.g. 64 for `CarIdx` channels.
+    pub fn with_channel(mut self, name: &str, count: usize) -> Self {
+        self.channels.push((name.to_owned(), count));
+        self
+    }
+
+    /// Room for this many standings.
+    pub fn with_standings(mut self, capacity: usize) -> Self {
+        self.standings = capacity;
+        self
+    }
+
+    /// Channels in the layout, as (name, count).
+    pub fn channels(&self) -> &[(String, usize)] {
+        &self.channels
+    }
+
+    fn channel_table_offset(&self) -> usize {
+        HEADER_SIZE
+    }
+
+    fn values_offset(&self) -> usize {
+        HEADER_SIZE + self.channels.len() * CHANNEL_ENTRY_SIZE
+    }
+
+    fn standings_offset(&self) -> usize {
+        let values: usize = self.channels.iter().map(|(_, count)| count).sum();
+        self.values_offset() + values * 8
+    }
+
+    /// Size of the block in bytes.
+    pub fn size(&self) -> usize {
+        self.standings_offset() + self.standings * STANDING_ENTRY_SIZE
+    }
+
+    ///
+    /// Write a block.
+    ///
+    /// `values` holds the values of each channel, in layout order. Missing values
+    /// are written as NaN and extra values are dropped, as are standings beyond
+    /// the capacity. `sequence` should be even, as odd sequences mark a block
+    /// which is being written.
+    ///
+    /// # Panics
+    ///
+    /// If `out` is smaller than `size()`.
+    pub fn encode(
+        &self,
+        out: &mut [u8],
+        sequence: u32,
+        session_time: f64,
+        values: &[Vec<f64>],
+        standings: &[Standing],
+    ) {
+        let out = &mut out[..self.size()];
+        for b in out.iter_mut() {
+            *b = 0;
+        }
+
+        let standings = &standings[..standings.len().min(self.standings)];
+
+        out[0..4].copy_from_slice(&MAGIC);
+        put_u32(out, 4, LAYOUT_VERSION);
+        put_u32(out, 8, sequence);
+        put_u32(out, 12, self.size() as u32);
+        out[16..24].copy_from_slice(&session_time.to_le_bytes());
+        put_u32(out, 24, self.channels.len() as u32);
+        put_u32(out, 28, self.channel_table_offset() as u32);
+        put_u32(out, 32, standings.len() as u32);
+        put_u32(out, 36, self.standings as u32);
+        put_u32(out, 40, self.standings_offset() as u32);
+
+        let mut value_offset = self.values_offset();
+        for (i, (name, count)) in self.channels.iter().enumerate() {
+            let entry = self.channel_table_offset() + i * CHANNEL_ENTRY_SIZE;
+            put_str(&mut out[entry..entry + CHANNEL_NAME_SIZE], name);
+            put_u32(out, entry + 40, *count as u32);
+            put_u32(out, entry + 44, value_offset as u32);
+
+            let channel = values.get(i).map(|v| v.as_slice()).unwrap_or(&[]);
+            for n in 0..*count {
+                let v = channel.get(n).copied().unwrap_or(f64::NAN);
+                out[value_offset..value_offset + 8].copy_from_slice(&v.to_le_bytes());
+                value_offset += 8;
+            }
+        }
+
+        for (i, s) in standings.iter().enumerate() {
+            let entry = self.standings_offset() + i * STANDING_ENTRY_SIZE;
+            put_u32(out, entry, s.position);
+            put_u32(out, entry + 4, s.class_position);
+            put_u32(out, entry + 8, s.car_idx as u32);
+            put_u32(out, entry + 12, s.laps_complete.max(0) as u32);
+            put_str(
+                &mut out[entry + 16..entry + 16 + CAR_NUMBER_SIZE],
+                &s.car_number,
+            );
+            put_str(
+                &mut out[entry + 24..entry + 24 + DRIVER_NAME_SIZE],
+                &s.driver_name,
+            );
+        }
+    }
+
+    ///
+    /// Read a block written by any layout of a supported version.
+    pub fn decode(block: &[u8]) -> Result<Snapshot, LayoutError> {
+        if block.len() < HEADER_SIZE {
+            return Err(LayoutError::Truncated);
+        }
+        if block[0..4] != MAGIC {
+            return Err(LayoutError::BadMagic);
+        }
+
+        let version = get_u32(block, 4)?;
+        if version != LAYOUT_VERSION {
+            return Err(LayoutError::UnsupportedVersion(version));
+        }
+
+        let sequence = get_u32(block, 8)?;
+        if sequence % 2 == 1 {
+            return Err(LayoutError::Torn);
+        }
+
+        let mut session_time = [0u8; 8];
+        session_time.copy_from_slice(&block[16..24]);
+
+        let channel_count = get_u32(block, 24)? as usize;
+        let channel_table = get_u32(block, 28)? as usize;
+        let standing_count = get_u32(block, 32)? as usize;
+        let standings_offset = get_u32(block, 40)? as usize;
+
+        // Counts come off the wire, so check their tables fit before allocating
+        table(block, channel_table, channel_count, CHANNEL_ENTRY_SIZE)?;
+        table(block, standings_offset, standing_count, STANDING_ENTRY_SIZE)?;
+
+        let mut channels = Vec::with_capacity(channel_count);
+        for i in 0..channel_count {
+            let entry = channel_table + i * CHANNEL_ENTRY_SIZE;
+            let name = get_str(block, entry, CHANNEL_NAME_SIZE)?;
+            let count = get_u32(block, entry + 40)? as usize;
+            let offset = get_u32(block, entry + 44)? as usize;
+
+            table(block, offset, count, 8)?;
+            let values = (0..count)
+                .map(|n| get_f64(block, offset + n * 8))
+                .collect::<Result<Vec<f64>, LayoutError>>()?;
+            channels.push((name, values));
+        }
+
+        let mut standings = Vec::with_capacity(standing_count);
+        for i in 0..standing_count {
+            let entry = standings_offset + i * STANDING_ENTRY_SIZE;
+            standings.push(BlockStanding {
+                position: get_u32(block, entry)?,
+                class_position: get_u32(block, entry + 4)?,
+                car_idx: get_u32(block, entry + 8)?,
+                laps_complete: get_u32(block, entry + 12)?,
+                car_number: get_str(block, entry + 16, CAR_NUMBER_SIZE)?,
+                driver_name: get_str(block, entry + 24, DRIVER_NAME_SIZE)?,
+            });
+        }
+
+        Ok(Snapshot {
+            sequence,
+            session_time: f64::from_le_bytes(session_time),
+            channels,
+            standings,
+        })
+    }
+}
+
+impl Snapshot {
+    /// Values of a channel.
+    pub fn channel(&self, name: &str) -> Option<&[f64]> {
+        self.channels
+            .iter()
+            .find(|(n, _)| n == name)
+            .map(|(_, v)| v.as_slice())
+    }
+}
+
+fn put_u32(out: &mut [u8], at: usize, v: u32) {
+    out[at..at + 4].copy_from_slice(&v.to_le_bytes());
+}
+
+/// Write a NUL padded string, truncated on a character boundary to leave room for the NUL.
+fn put_str(out: &mut [u8], s: &str) {
+    let mut end = s.len().min(out.len() - 1);
+    while !s.is_char_boundary(end) {
+        end -= 1;
+    }
+    out[..end].copy_from_slice(&s.as_bytes()[..end]);
+}
+
+/// Check a table of `count` entries at `at` lies within the block.
+fn table(block: &[u8], at: usize, count: usize, entry_size: usize) -> Result<(), LayoutError> {
+    count
+        .checked_mul(entry_size)
+        .and_then(|len| at.checked_add(len))
+        .filter(|end| *end <= block.len())
+        .map(|_| ())
+        .ok_or(LayoutError::Truncated)
+}
+
+fn get_u32(block: &[u8], at: usize) -> Result<u32, LayoutError> {
+    let bytes = block.get(at..at + 4).ok_or(LayoutError::Truncated)?;
+    Ok(u32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]))
+}
+
+fn get_f64(block: &[u8], at: usize) -> Result<f64, LayoutError> {
+    let mut bytes = [0u8; 8];
+    bytes.copy_from_slice(block.get(at..at + 8).ok_or(LayoutError::Truncated)?);
+    Ok(f64::from_le_bytes(bytes))
+}
+
+fn get_str(block: &[u8], at: usize, len: usize) -> Result<String, LayoutError> {
+    let bytes = block.get(at..at + len).ok_or(LayoutError::Truncated)?;
+    let end = bytes.iter().position(|b| *b == 0).unwrap_or(len);
+    Ok(String::from_utf8_lossy(&bytes[..end]).into_owned())
+}
+
+///
+/// Shared Memory Republisher
+///
+/// Writes a `Layout` block to a named shared memory map for tools which don't
+/// understand the iRacing SDK.
+///
+/// Readers should copy the block and retry if `Layout::decode` reports it torn
+/// or the sequence changed while copying.
+///
+/// # Examples
+///
+/// ```no_run
+/// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+/// use iracing::republish::{Layout, Republisher, REPUBLISH_PATH};
+/// use iracing::telemetry::Connection;
+/// use std::time::Duration;
+///
+/// let layout = Layout::new().with_channel("Speed", 1).with_channel("Gear", 1).with_standings(64);
+/// let mut republisher = Republisher::create(REPUBLISH_PATH, layout)?;
+///
+/// let sampler = Connection::new()?.blocking()?;
+/// loop {
+///     republisher.publish(&sampler.sample(Duration::from_millis(50))?, &[])?;
+/// }
+/// # }
+/// ```
+#[cfg(all(target_os = "windows", feature = "telemetry"))]
+pub struct Republisher {
+    layout: Layout,
+    mapping: HANDLE,
+    view: *mut u8,
+    sequence: u32,
+    scratch: Vec<u8>,
+}
+
+#[cfg(all(target_os = "windows", feature = "telemetry"))]
+impl Republisher {
+    ///
+    /// Create the shared memory map.
+    pub fn create(name: &str, layout: Layout) -> std::io::Result<Republisher> {
+        let mut path: Vec<u16> = name.encode_utf16().collect();
+        path.push(0);
+
+        let size = layout.size();
+        let mapping = unsafe {
+            CreateFileMappingW(
+                INVALID_HANDLE_VALUE,
+                std::ptr::null_mut(),
+                PAGE_READWRITE,
+                0,
+                size as u32,
+                path.as_ptr(),
+            )
+        };
+
+        if mapping.is_null() {
+            return Err(std::io::Error::last_os_error());
+        }
+
+        let view = unsafe { MapViewOfFile(mapping, FILE_MAP_ALL_ACCESS, 0, 0, size) };
+        if view.is_null() {
+            let err = std::io::Error::last_os_error();
+            unsafe { CloseHandle(mapping) };
+            return Err(err);
+        }
+
+        Ok(Republisher {
+            layout,
+            mapping,
+            view: view as *mut u8,
+            sequence: 0,
+            scratch: vec![0; size],
+        })
+    }
+
+    ///
+    /// Publish the layout's channels from a sample, along with standings.
+    ///
+    /// Channels missing from the sample are written as NaN.
+    pub fn publish(
+        &mut self,
+        sample: &Sample,
+        standings: &[Standing],
+    ) -> Result<(), Box<dyn Error>> {
+        let mut values = Vec::with_capacity(self.layout.channels().len());
+        let session_time: f64 = match sample.get("SessionTime") {
+            Ok(Value::DOUBLE(t)) => t,
+            _ => f64::NAN,
+        };
+
+        for v in sample.all() {
+            if let Some(i) = self
+                .layout
+                .channels()
+                .iter()
+                .position(|(n, _)| *n == v.name)
+            {
+                values.resize(values.len().max(i + 1), Vec::new());
+                values[i] = to_f64s(v.value);
+            }
+        }
+
+        self.write(session_time, &values, standings);
+        Ok(())
+    }
+
+    ///
+    /// Publish values and standings directly.
+    pub fn write(&mut self, session_time: f64, values: &[Vec<f64>], standings: &[Standing]) {
+        let next = self.sequence.wrapping_add(2);
+        self.layout
+            .encode(&mut self.scratch, next, session_time, values, standings);
+
+        // Mark the block as being written, copy it over, then publish the new sequence
+        unsafe {
+            let sequence = self.view.add(8) as *mut u32;
+            std::ptr::write_volatile(sequence, (next.wrapping_sub(1)).to_le());
+            fence(Ordering::Release);
+
+            std::ptr::copy_nonoverlapping(self.scratch.as_ptr(), self.view, 8);
+            std::ptr::copy_nonoverlapping(
+                self.scratch.as_ptr().add(12),
+                self.view.add(12),
+                self.scratch.len() - 12,
+            );
+
+            fence(Ordering::Release);
+            std::ptr::write_volatile(sequence, next.to_le());
+        }
+
+        self.sequence = next;
+    }
+}
+
+#[cfg(all(target_os = "windows", feature = "telemetry"))]
+impl Drop for Republisher {
+    fn drop(&mut self) {
+        unsafe {
+            UnmapViewOfFile(self.view as _);
+            CloseHandle(self.mapping);
+        }
+    }
+}
+
+#[cfg(all(target_os = "windows", feature = "telemetry"))]
+fn to_f64s(value: Value) -> Vec<f64> {
+    match value {
+        Value::CHAR(v) => vec![v as f64],
+        Value::BOOL(v) => vec![v as u8 as f64],
+        Value::INT(v) => vec![v as f64],
+        Value::BITS(v) => vec![v as f64],
+        Value::FLOAT(v) => vec![v as f64],
+        Value::DOUBLE(v) => vec![v],
+        Value::UNKNOWN(_) => Vec::new(),
+        Value::IntVec(v) => v.into_iter().map(|v| v as f64).collect(),
+        Value::FloatVec(v) => v.into_iter().map(|v| v as f64).collect(),
+        Value::BoolVec(v) => v.into_iter().map(|v| v as u8 as f64).collect(),
+        Value::BitsVec(v) => v.into_iter().map(|v| v as f64).collect(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::results::Results;
+    use crate::session::SessionDetails;
+
+    #[test]
+    fn encode_decode() {
+        let content = std::fs::read_to_string("./session.yaml").unwrap();
+        let session: SessionDetails = serde_yaml::from_str(&content).unwrap();
+        let results = Results::from_session(&session, 2).unwrap();
+
+        let layout = Layout::new()
+            .with_channel("Speed", 1)
+            .with_channel("CarIdxPosition", 4)
+            .with_standings(4);
+
+        let mut block = vec![0u8; layout.size()];
+        layout.encode(
+            &mut block,
+            6,
+            321.5,
+            &[vec![41.0], vec![1.0, 2.0]],
+            &results.standings,
+        );
+
+        let snapshot = Layout::decode(&block).unwrap();
+        assert_eq!(snapshot.sequence, 6);
+        assert_eq!(snapshot.session_time, 321.5);
+        assert_eq!(snapshot.channel("Speed"), Some(&[41.0][..]));
+
+        let positions = snapshot.channel("CarIdxPosition").unwrap();
+        assert_eq!(positions.len(), 4);
+        assert!(positions[3].is_nan());
+
+        assert_eq!(snapshot.standings.len(), 4);
+        assert_eq!(snapshot.standings[0].driver_name, "Ana Lucia Ferreira");
+        assert_eq!(snapshot.standings[0].car_idx, 3);
+
+        block[8] = 7;
+        assert_eq!(Layout::decode(&block), Err(LayoutError::Torn));
+    }
+
+    #[test]
+    fn bogus_counts() {
+        let layout = Layout::new().with_channel("Speed", 1).with_standings(1);
+        let mut block = vec![0u8; layout.size()];
+        layout.encode(&mut block, 2, 0.0, &[vec![41.0]], &[]);
+
+        // Counts far larger than the block are rejected before allocating
+        for at in [24, 32] {
+            let mut bogus = block.clone();
+            bogus[at..at + 4].copy_from_slice(&u32::MAX.to_le_bytes());
+            assert_eq!(Layout::decode(&bogus), Err(LayoutError::Truncated));
+        }
+    }
+}
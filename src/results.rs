@@ -0,0 +1,353 @@
+use crate::session::{Driver, QualifyResult, SessionDetails, SessionResult};
+use serde::{Deserialize, Serialize};
+use std::fmt::Write;
+
+///
+/// Gap between a car and the car it is being compared to.
+#[derive(Debug, Copy, Clone, PartialEq, Serialize, Deserialize)]
+pub enum Gap {
+    /// The car is the reference (e.g. the leader)
+    None,
+
+    /// Gap in seconds
+    Time(f32),
+
+    /// Gap in whole laps
+    Laps(i32),
+}
+
+///
+/// A single car's position in a set of results.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Standing {
+    pub position: u32,       // Overall position (1-based)
+    pub class_position: u32, // Position in class (1-based)
+    pub car_idx: usize,
+    pub car_number: String,
+    pub car_class: String,
+    pub user_id: i64,
+    pub driver_name: String,
+    pub team_id: u64,
+    pub team_name: String,
+
+    pub laps_complete: i32,
+    pub laps_led: i32,
+    pub time: Option<f32>, // Total time for races, best lap for other sessions (s)
+    pub fastest_lap: Option<i32>, // Lap number of the fastest lap
+    pub fastest_time: Option<f32>, // Fastest lap time (s)
+    pub last_time: Option<f32>, // Last lap time (s)
+    pub gap_to_leader: Gap,
+    pub interval: Gap, // Gap to the car one position ahead
+    pub incidents: i32,
+    pub reason_out: String, // Running, Disqualified, Disconnected etc.
+
+    #[serde(default)]
+    pub consistency: Option<f32>, // Standard deviation of recent lap times (s), see `ConsistencyTracker`
+}
+
+///
+/// Results
+///
+/// Typed standings for a session, built from the `ResultsPositions` of a session
+/// or the `QualifyResultsInfo` section of the session string.
+///
+/// # Examples
+///
+/// ```
+/// use iracing::results::Results;
+/// # let session: iracing::session::SessionDetails =
+/// #     serde_yaml::from_str(&std::fs::read_to_string("./session.yaml").unwrap()).unwrap();
+///
+/// if let Some(results) = Results::from_session(&session, 2) {
+///     if let Some(classification) = results.final_classification() {
+///         print!("{}", Results::to_csv(classification));
+///     }
+/// }
+/// ```
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Results {
+    pub session_number: u64,
+    pub session_type: String,
+    pub official: bool, // Results are final
+    pub standings: Vec<Standing>,
+}
+
+const CSV_HEADER: &str = "Position,Class Position,Car Index,Car Number,Class,Driver,Team,Laps,Laps Led,Time,Fastest Lap,Fastest Time,Last Time,Gap,Interval,Incidents,Status";
+
+impl Gap {
+    pub(crate) fn between(leader: (i32, Option<f32>), car: (i32, Option<f32>), race: bool) -> Gap {
+        if race && car.0 < leader.0 {
+            return Gap::Laps(leader.0 - car.0);
+        }
+
+        match (leader.1, car.1) {
+            (Some(l), Some(c)) => Gap::Time(c - l),
+            _ => Gap::None,
+        }
+    }
+}
+
+fn valid_time(t: f32) -> Option<f32> {
+    if t > 0.0 {
+        Some(t)
+    } else {
+        None
+    }
+}
+
+impl Standing {
+    pub(crate) fn new(
+        position: u32,
+        class_position: u32,
+        car_idx: usize,
+        driver: Option<&Driver>,
+    ) -> Self {
+        Standing {
+            position,
+            class_position,
+            car_idx,
+            car_number: driver.map(|d| d.car_number.to_string()).unwrap_or_default(),
+            car_class: driver
+                .map(|d| d.car_class_short_name.clone())
+                .unwrap_or_default(),
+            user_id: driver.map(|d| d.user_id).unwrap_or_default(),
+            driver_name: driver.map(|d| d.user_name.clone()).unwrap_or_default(),
+            team_id: driver.map(|d| d.team_id).unwrap_or_default(),
+            team_name: driver.map(|d| d.team_name.clone()).unwrap_or_default(),
+            laps_complete: 0,
+            laps_led: 0,
+            time: None,
+            fastest_lap: None,
+            fastest_time: None,
+            last_time: None,
+            gap_to_leader: Gap::None,
+            interval: Gap::None,
+            incidents: 0,
+            reason_out: String::new(),
+            consistency: None,
+        }
+    }
+}
+
+impl Results {
+    ///
+    /// Build results for a session from its `ResultsPositions`.
+    ///
+    /// Returns None if the session does not exist or has no results yet.
+    pub fn from_session(details: &SessionDetails, session_number: u64) -> Option<Results> {
+        let session = details
+            .session
+            .sessions
+            .iter()
+            .find(|s| s.session_number == session_number)?;
+        let results = session.results.as_ref()?;
+        let race = session.session_type.contains("Race");
+
+        let mut standings: Vec<Standing> = results
+            .iter()
+            .filter(|r| r.car_idx >= 0)
+            .map(|r: &SessionResult| {
+                let car_idx = r.car_idx as usize;
+                let mut standing = Standing::new(
+                    r.position.max(0) as u32,
+                    r.class_position.max(0) as u32 + 1,
+                    car_idx,
+                    driver(details, car_idx),
+                );
+
+                standing.laps_complete = r.laps_complete;
+                standing.laps_led = r.laps_led;
+                standing.time = valid_time(r.time);
+                standing.fastest_lap = Some(r.fastest_lap).filter(|l| *l > 0);
+                standing.fastest_time = valid_time(r.fastest_time);
+                standing.last_time = valid_time(r.last_time);
+                standing.incidents = r.incidents;
+                standing.reason_out = r.reason_out_str.clone();
+                standing
+            })
+            .collect();
+
+        standings.sort_by_key(|s| s.position);
+        Self::compute_gaps(&mut standings, race);
+
+        Some(Results {
+            session_number,
+            session_type: session.session_type.clone(),
+            official: session.results_official.unwrap_or(0) > 0,
+            standings,
+        })
+    }
+
+    ///
+    /// Build results from the `QualifyResultsInfo` section, used to set the race grid.
+    pub fn qualifying(details: &SessionDetails) -> Option<Results> {
+        let results = details.qualify_results.as_ref()?.results.as_ref()?;
+
+        let mut standings: Vec<Standing> = results
+            .iter()
+            .filter(|r| r.car_idx >= 0)
+            .map(|r: &QualifyResult| {
+                let car_idx = r.car_idx as usize;
+                let mut standing = Standing::new(
+                    r.position.max(0) as u32 + 1,
+                    r.class_position.max(0) as u32 + 1,
+                    car_idx,
+                    driver(details, car_idx),
+                );
+
+                standing.time = valid_time(r.fastest_time);
+                standing.fastest_lap = Some(r.fastest_lap).filter(|l| *l > 0);
+                standing.fastest_time = standing.time;
+                standing.reason_out = String::from("Running");
+                standing
+            })
+            .collect();
+
+        standings.sort_by_key(|s| s.position);
+        Self::compute_gaps(&mut standings, false);
+
+        Some(Results {
+            session_number: 0,
+            session_type: String::from("Qualify"),
+            official: true,
+            standings,
+        })
+    }
+
+    fn compute_gaps(standings: &mut [Standing], race: bool) {
+        let summary: Vec<(i32, Option<f32>)> = standings
+            .iter()
+            .map(|s| (s.laps_complete, s.time))
+            .collect();
+
+        for (i, standing) in standings.iter_mut().enumerate() {
+            if i == 0 {
+                continue;
+            }
+
+            standing.gap_to_leader = Gap::between(summary[0], summary[i], race);
+            standing.interval = Gap::between(summary[i - 1], summary[i], race);
+        }
+    }
+
+    ///
+    /// The final classification, once the results are official.
+    pub fn final_classification(&self) -> Option<&[Standing]> {
+        if self.official {
+            Some(&self.standings)
+        } else {
+            None
+        }
+    }
+
+    /// Standing of a given car.
+    pub fn for_car(&self, car_idx: usize) -> Option<&Standing> {
+        self.standings.iter().find(|s| s.car_idx == car_idx)
+    }
+
+    ///
+    /// Serialize the results as JSON.
+    pub fn to_json(&self) -> serde_json::Result<String> {
+        serde_json::to_string_pretty(self)
+    }
+
+    ///
+    /// Format standings as CSV, with a header row.
+    pub fn to_csv(standings: &[Standing]) -> String {
+        let mut out = String::from(CSV_HEADER);
+        out.push('\n');
+
+        for s in standings {
+            let fields = [
+                s.position.to_string(),
+                s.class_position.to_string(),
+                s.car_idx.to_string(),
+                csv_escape(&s.car_number),
+                csv_escape(&s.car_class),
+                csv_escape(&s.driver_name),
+                csv_escape(&s.team_name),
+                s.laps_complete.to_string(),
+                s.laps_led.to_string(),
+                format_time(s.time),
+                s.fastest_lap.map(|l| l.to_string()).unwrap_or_default(),
+                format_time(s.fastest_time),
+                format_time(s.last_time),
+                format_gap(s.gap_to_leader),
+                format_gap(s.interval),
+                s.incidents.to_string(),
+                csv_escape(&s.reason_out),
+            ];
+
+            let _ = writeln!(out, "{}", fields.join(","));
+        }
+
+        out
+    }
+}
+
+fn driver(details: &SessionDetails, car_idx: usize) -> Option<&Driver> {
+    details
+        .drivers
+        .other_drivers
+        .iter()
+        .find(|d| d.index == car_idx)
+}
+
+fn format_time(t: Option<f32>) -> String {
+    t.map(|t| format!("{:.4}", t)).unwrap_or_default()
+}
+
+fn format_gap(gap: Gap) -> String {
+    match gap {
+        Gap::None => String::new(),
+        Gap::Time(t) => format!("{:.3}", t),
+        Gap::Laps(1) => String::from("1 lap"),
+        Gap::Laps(l) => format!("{} laps", l),
+    }
+}
+
+fn csv_escape(s: &str) -> String {
+    if s.contains(',') || s.contains('"') || s.contains('\n') {
+        format!("\"{}\"", s.replace('"', "\"\""))
+    } else {
+        s.to_owned()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn session() -> SessionDetails {
+        let content = std::fs::read_to_string("./session.yaml").unwrap();
+        serde_yaml::from_str(&content).unwrap()
+    }
+
+    #[test]
+    fn race_results() {
+        let results = Results::from_session(&session(), 2).unwrap();
+        let classification = results.final_classification().unwrap();
+
+        assert_eq!(classification.len(), 6);
+        assert_eq!(classification[0].driver_name, "Ana Lucia Ferreira");
+        assert_eq!(classification[0].gap_to_leader, Gap::None);
+        assert!(
+            matches!(classification[1].gap_to_leader, Gap::Time(t) if (t - 2.5602).abs() < 1e-3)
+        );
+        assert_eq!(classification[2].gap_to_leader, Gap::Laps(1));
+        assert_eq!(classification[5].reason_out, "Disqualified");
+
+        let csv = Results::to_csv(classification);
+        assert_eq!(csv.lines().count(), 7);
+        assert!(csv.contains(",L W Adamek,Breaker Racing,"));
+    }
+
+    #[test]
+    fn qualifying_results() {
+        let results = Results::qualifying(&session()).unwrap();
+
+        assert_eq!(results.standings[0].position, 1);
+        assert_eq!(results.standings[0].car_idx, 3);
+        assert_eq!(results.standings[2].class_position, 1);
+    }
+}
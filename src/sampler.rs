@@ -0,0 +1,652 @@
+use crate::fps::Fps;
+use crate::telemetry::{Sample, Value};
+use crossbeam_channel::{bounded, Receiver, SendTimeoutError, Sender, TrySendError};
+use serde::Serialize;
+use std::time::Duration;
+
+#[cfg(target_os = "windows")]
+use crate::telemetry::{Blocking, CancelToken, Header, TelemetryError};
+#[cfg(target_os = "windows")]
+use crossbeam_channel::{RecvTimeoutError, TryRecvError};
+#[cfg(target_os = "windows")]
+use std::error::Error;
+#[cfg(target_os = "windows")]
+use std::io::Result as IOResult;
+#[cfg(target_os = "windows")]
+use std::os::raw::c_void;
+#[cfg(target_os = "windows")]
+use std::sync::atomic::{AtomicU64, Ordering};
+#[cfg(target_os = "windows")]
+use std::sync::{mpsc, Arc, Mutex};
+#[cfg(target_os = "windows")]
+use std::thread::{self, JoinHandle};
+#[cfg(target_os = "windows")]
+use std::time::Instant;
+
+///
+/// What a sampler does with a new sample when its channel is full.
+#[derive(Debug, Copy, Clone, Default, PartialEq, Eq)]
+pub enum Overflow {
+    /// Drop the oldest queued sample to make room, so the consumer catches up
+    /// on the newest data
+    #[default]
+    DropOldest,
+
+    /// Drop the new sample, keeping the queued ones
+    DropNewest,
+
+    /// Wait for the consumer to make room. Ticks the sim writes meanwhile are
+    /// missed.
+    Block,
+}
+
+///
+/// Channels delivered together at a rate of their own, such as inputs at 60
+/// FPS and temperatures at 1.
+#[derive(Debug, Clone)]
+pub struct ChannelGroup {
+    pub name: String,
+    pub channels: Vec<String>,
+    pub fps: Fps,
+}
+
+///
+/// A group's channels at one tick. Channels the sample doesn't have are
+/// left out.
+#[derive(Debug, Clone, Serialize)]
+pub struct GroupSample {
+    pub group: String,
+    pub tick: i32,
+    pub values: Vec<(String, Value)>,
+}
+
+///
+/// Fanout
+///
+/// Splits samples into channel groups, each delivered on its own bounded
+/// channel at its own rate, so a networked exporter only pays for the
+/// channels it needs at the rate it needs them. Rates are kept by tick, so
+/// recordings fan out the same as live data. Full channels drop their oldest
+/// sample.
+///
+/// A `Sampler` has a fanout of its own, see `Sampler::subscribe`.
+///
+/// # Examples
+///
+/// ```
+/// use iracing::fps::Fps;
+/// use iracing::sampler::{ChannelGroup, Fanout};
+/// # let samples: Vec<iracing::telemetry::Sample> = Vec::new();
+///
+/// // Ticks a second, from the telemetry header
+/// let mut fanout = Fanout::new(60);
+/// let inputs = fanout.add(
+///     ChannelGroup::new("inputs", &["Throttle", "Brake", "SteeringWheelAngle"], Fps::MAX),
+///     60,
+/// );
+/// let temperatures = fanout.add(
+///     ChannelGroup::new("temperatures", &["TrackTempCrew", "AirTemp"], Fps::MIN),
+///     4,
+/// );
+///
+/// for sample in samples.iter() {
+///     fanout.dispatch(sample);
+/// }
+/// ```
+#[derive(Debug)]
+pub struct Fanout {
+    tick_rate: i32,
+    routes: Vec<Route>,
+    dropped: u64,
+}
+
+/// A group and the channel it is delivered on.
+#[derive(Debug)]
+struct Route {
+    group: ChannelGroup,
+    sender: Sender<GroupSample>,
+    queue: Receiver<GroupSample>,
+    last: Option<i32>,
+}
+
+///
+/// Background Sampler
+///
+/// Owns a `Blocking` sampler on a dedicated thread, and delivers samples over
+/// a bounded channel at up to the requested rate. The thread is stopped when
+/// the sampler is dropped.
+///
+/// Timeouts while the sim is idle and samples which can't be read are skipped;
+/// the thread only stops early if waiting for telemetry fails, and the error
+/// is returned by `stop()`.
+///
+/// # Examples
+///
+/// ```no_run
+/// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+/// use iracing::fps::Fps;
+/// use iracing::sampler::Overflow;
+/// use iracing::telemetry::Connection;
+/// use std::time::Duration;
+///
+/// let sampler = Connection::new()?
+///     .spawn_sampler(Fps::new(30), 8)?
+///     .with_overflow(Overflow::DropNewest);
+///
+/// while let Some(sample) = sampler.recv_timeout(Duration::from_secs(1)) {
+///     println!("Tick {}, {} dropped", sample.tick(), sampler.dropped());
+/// }
+///
+/// // Drop to a trickle while the player is in the menus
+/// sampler.set_fps(Fps::new(2));
+/// sampler.pause();
+/// sampler.resume();
+/// sampler.stop()?;
+/// # Ok(())
+/// # }
+/// ```
+#[cfg(target_os = "windows")]
+pub struct Sampler {
+    receiver: Receiver<Sample>,
+    token: CancelToken,
+    shared: Arc<Shared>,
+    thread: Option<JoinHandle<Result<(), String>>>,
+}
+
+/// State shared with the sampler thread.
+#[cfg(target_os = "windows")]
+#[derive(Debug)]
+struct Shared {
+    control: Mutex<Control>,
+    fanout: Mutex<Fanout>,
+    dropped: AtomicU64,
+}
+
+/// Settings shared with the sampler thread.
+#[cfg(target_os = "windows")]
+#[derive(Debug, Copy, Clone)]
+struct Control {
+    fps: Fps,
+    overflow: Overflow,
+    paused: bool,
+}
+
+/// Longest the thread sleeps between checks for a paused sampler or a new rate.
+#[cfg(target_os = "windows")]
+const POLL_INTERVAL: Duration = Duration::from_millis(50);
+
+/// Location of the memory map, which is mapped for the life of the process.
+#[cfg(target_os = "windows")]
+#[derive(Copy, Clone)]
+struct Location(*const c_void);
+
+// The map is only read, and stays mapped while the sampler runs
+#[cfg(target_os = "windows")]
+unsafe impl Send for Location {}
+
+impl ChannelGroup {
+    pub fn new(name: &str, channels: &[&str], fps: Fps) -> Self {
+        ChannelGroup {
+            name: name.to_owned(),
+            channels: channels.iter().map(|c| c.to_string()).collect(),
+            fps,
+        }
+    }
+
+    /// Ticks between deliveries of the group, at `tick_rate` ticks a second.
+    fn interval(&self, tick_rate: i32) -> i32 {
+        (tick_rate / self.fps.0.get() as i32).max(1)
+    }
+}
+
+impl Fanout {
+    /// Fanout for telemetry written at `tick_rate` ticks a second, the
+    /// header's `tick_rate()`.
+    pub fn new(tick_rate: i32) -> Self {
+        Fanout {
+            tick_rate,
+            routes: Vec::new(),
+            dropped: 0,
+        }
+    }
+
+    ///
+    /// Add a group, returning the channel it is delivered on, which holds up
+    /// to `capacity` samples. A group with the same name is replaced.
+    pub fn add(&mut self, group: ChannelGroup, capacity: usize) -> Receiver<GroupSample> {
+        self.remove(&group.name);
+
+        let (sender, receiver) = bounded(capacity.max(1));
+        self.routes.push(Route {
+            group,
+            sender,
+            queue: receiver.clone(),
+            last: None,
+        });
+        receiver
+    }
+
+    /// Remove a group, closing its channel once it has been drained.
+    pub fn remove(&mut self, name: &str) {
+        self.routes.retain(|route| route.group.name != name);
+    }
+
+    /// Names of the groups, in the order they were added.
+    pub fn groups(&self) -> Vec<&str> {
+        self.routes.iter().map(|r| r.group.name.as_str()).collect()
+    }
+
+    /// Group samples dropped because a group's channel was full.
+    pub fn dropped(&self) -> u64 {
+        self.dropped
+    }
+
+    ///
+    /// Deliver a sample to every group due one. A tick earlier than the last
+    /// delivered, from a new session or a replay, restarts a group's timing.
+    pub fn dispatch(&mut self, sample: &Sample) {
+        let tick = sample.tick();
+        let tick_rate = self.tick_rate;
+
+        for route in self.routes.iter_mut() {
+            let interval = route.group.interval(tick_rate);
+            let due = route
+                .last
+                .is_none_or(|last| tick < last || tick - last >= interval);
+            if !due {
+                continue;
+            }
+            route.last = Some(tick);
+
+            let values = route
+                .group
+                .channels
+                .iter()
+                .filter_map(|channel| sample.get(channel).ok().map(|v| (channel.clone(), v)))
+                .collect();
+            let item = GroupSample {
+                group: route.group.name.clone(),
+                tick,
+                values,
+            };
+
+            let poll = Duration::from_millis(0);
+            if offer(
+                &route.sender,
+                &route.queue,
+                item,
+                Overflow::DropOldest,
+                poll,
+                || true,
+            ) {
+                self.dropped += 1;
+            }
+        }
+    }
+}
+
+#[cfg(target_os = "windows")]
+impl Sampler {
+    ///
+    /// Start sampling on a new thread, delivering into a channel holding up
+    /// to `capacity` samples.
+    pub(crate) fn spawn(
+        location: *const c_void,
+        header: Header,
+        fps: Fps,
+        capacity: usize,
+    ) -> IOResult<Self> {
+        let token = CancelToken::new()?;
+        let shared = Arc::new(Shared {
+            control: Mutex::new(Control {
+                fps,
+                overflow: Overflow::default(),
+                paused: false,
+            }),
+            fanout: Mutex::new(Fanout::new(header.tick_rate())),
+            dropped: AtomicU64::new(0),
+        });
+        let (sender, receiver) = bounded(capacity.max(1));
+
+        // The blocking sampler isn't Send, so it's created on the thread and
+        // any error creating it is passed back
+        let (started, start) = mpsc::sync_channel(1);
+        let location = Location(location);
+        let thread = {
+            let (token, shared) = (token.clone(), shared.clone());
+            let queue = receiver.clone();
+
+            thread::spawn(move || {
+                let blocking = match Blocking::new(location.0, header) {
+                    Ok(blocking) => blocking.with_cancel(token.clone()),
+                    Err(e) => {
+                        let _ = started.send(Err(e));
+                        return Ok(());
+                    }
+                };
+                let _ = started.send(Ok(()));
+
+                run(&blocking, &token, &shared, &sender, &queue)
+            })
+        };
+
+        match start.recv() {
+            Ok(Err(e)) => Err(e),
+            _ => Ok(Sampler {
+                receiver,
+                token,
+                shared,
+                thread: Some(thread),
+            }),
+        }
+    }
+
+    /// What to do with new samples when the channel is full, `DropOldest` by default.
+    pub fn with_overflow(self, overflow: Overflow) -> Self {
+        self.shared.control.lock().unwrap().overflow = overflow;
+        self
+    }
+
+    ///
+    /// Stop delivering samples until resumed, keeping the thread and its
+    /// connection to the sim. Samples already queued are still delivered.
+    pub fn pause(&self) {
+        self.shared.control.lock().unwrap().paused = true;
+    }
+
+    /// Start delivering samples again, from the newest tick.
+    pub fn resume(&self) {
+        self.shared.control.lock().unwrap().paused = false;
+    }
+
+    pub fn is_paused(&self) -> bool {
+        self.shared.control.lock().unwrap().paused
+    }
+
+    ///
+    /// Change the rate samples are delivered at, such as 60 FPS while driving
+    /// and 2 in the menus. Takes effect within 50ms or the
+    /// next sample, whichever is sooner.
+    pub fn set_fps(&self, fps: Fps) {
+        self.shared.control.lock().unwrap().fps = fps;
+    }
+
+    /// The rate samples are delivered at.
+    pub fn fps(&self) -> Fps {
+        self.shared.control.lock().unwrap().fps
+    }
+
+    ///
+    /// The channel samples are delivered on, for use with `crossbeam_channel::select!`
+    /// or handing to another thread.
+    pub fn receiver(&self) -> &Receiver<Sample> {
+        &self.receiver
+    }
+
+    /// Wait for the next sample, or `None` once the thread has stopped.
+    pub fn recv(&self) -> Option<Sample> {
+        self.receiver.recv().ok()
+    }
+
+    /// Wait up to `timeout` for the next sample.
+    pub fn recv_timeout(&self, timeout: Duration) -> Option<Sample> {
+        match self.receiver.recv_timeout(timeout) {
+            Ok(sample) => Some(sample),
+            Err(RecvTimeoutError::Timeout) | Err(RecvTimeoutError::Disconnected) => None,
+        }
+    }
+
+    /// The next sample if one is queued.
+    pub fn try_recv(&self) -> Option<Sample> {
+        match self.receiver.try_recv() {
+            Ok(sample) => Some(sample),
+            Err(TryRecvError::Empty) | Err(TryRecvError::Disconnected) => None,
+        }
+    }
+
+    ///
+    /// Deliver a group of channels at a rate of its own on a channel holding
+    /// up to `capacity` samples. Groups are fed from the sampler's samples,
+    /// so run it at least as fast as the fastest group.
+    pub fn subscribe(&self, group: ChannelGroup, capacity: usize) -> Receiver<GroupSample> {
+        self.shared.fanout.lock().unwrap().add(group, capacity)
+    }
+
+    /// Stop delivering a group.
+    pub fn unsubscribe(&self, name: &str) {
+        self.shared.fanout.lock().unwrap().remove(name);
+    }
+
+    /// Samples dropped because the channel was full.
+    pub fn dropped(&self) -> u64 {
+        self.shared.dropped.load(Ordering::Relaxed)
+    }
+
+    /// True while the sampler thread is running.
+    pub fn is_running(&self) -> bool {
+        self.thread.as_ref().is_some_and(|t| !t.is_finished())
+    }
+
+    ///
+    /// Stop the sampler thread and wait for it to finish, returning the error
+    /// which stopped it early if there was one.
+    pub fn stop(mut self) -> Result<(), Box<dyn Error>> {
+        self.shutdown()
+    }
+
+    fn shutdown(&mut self) -> Result<(), Box<dyn Error>> {
+        self.token.cancel();
+
+        match self.thread.take().map(|t| t.join()) {
+            Some(Ok(Err(e))) => Err(e.into()),
+            Some(Err(_)) => Err("Sampler thread panicked".into()),
+            _ => Ok(()),
+        }
+    }
+}
+
+#[cfg(target_os = "windows")]
+impl Drop for Sampler {
+    fn drop(&mut self) {
+        let _ = self.shutdown();
+    }
+}
+
+/// Body of the sampler thread.
+#[cfg(target_os = "windows")]
+fn run(
+    blocking: &Blocking,
+    token: &CancelToken,
+    shared: &Shared,
+    sender: &Sender<Sample>,
+    queue: &Receiver<Sample>,
+) -> Result<(), String> {
+    loop {
+        let Control {
+            fps,
+            overflow,
+            paused,
+        } = *shared.control.lock().unwrap();
+        if paused {
+            if token.wait(POLL_INTERVAL) {
+                return Ok(());
+            }
+            continue;
+        }
+
+        let interval = fps.to_duration();
+        let started = Instant::now();
+
+        match blocking.sample(interval) {
+            Ok(sample) => {
+                shared.fanout.lock().unwrap().dispatch(&sample);
+                if offer(sender, queue, sample, overflow, interval, || {
+                    token.is_cancelled()
+                }) {
+                    shared.dropped.fetch_add(1, Ordering::Relaxed);
+                }
+            }
+            Err(e) => match e.downcast_ref::<TelemetryError>() {
+                Some(TelemetryError::CANCELLED) => return Ok(()),
+                Some(TelemetryError::TIMEOUT(_)) => continue,
+                Some(_) => return Err(e.to_string()),
+                None if e.is::<std::io::Error>() => return Err(e.to_string()),
+                None => (),
+            },
+        }
+
+        // Hold to the requested rate, picking up a new rate or a pause while waiting
+        loop {
+            let Control { fps, paused, .. } = *shared.control.lock().unwrap();
+            let remaining = fps.to_duration().saturating_sub(started.elapsed());
+            if paused || remaining.is_zero() {
+                break;
+            }
+            if token.wait(remaining.min(POLL_INTERVAL)) {
+                return Ok(());
+            }
+        }
+    }
+}
+
+///
+/// Queue `item` according to `overflow`, returning true if a sample was
+/// dropped to do so.
+///
+/// `queue` is a receiver on the same channel, used to drop the oldest item.
+/// When blocking, `stopped` is checked every `poll` while waiting for room.
+pub(crate) fn offer<T>(
+    sender: &Sender<T>,
+    queue: &Receiver<T>,
+    item: T,
+    overflow: Overflow,
+    poll: Duration,
+    stopped: impl Fn() -> bool,
+) -> bool {
+    match overflow {
+        Overflow::DropNewest => sender.try_send(item).is_err(),
+        Overflow::DropOldest => {
+            let mut item = item;
+            let mut dropped = false;
+            loop {
+                match sender.try_send(item) {
+                    Ok(()) => return dropped,
+                    Err(TrySendError::Full(back)) => {
+                        // The consumer may take the oldest first, which is as good
+                        dropped |= queue.try_recv().is_ok();
+                        item = back;
+                    }
+                    Err(TrySendError::Disconnected(_)) => return true,
+                }
+            }
+        }
+        Overflow::Block => {
+            let mut item = item;
+            loop {
+                match sender.send_timeout(item, poll) {
+                    Ok(()) => return false,
+                    Err(SendTimeoutError::Timeout(back)) if !stopped() => item = back,
+                    Err(_) => return true,
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::recording::{Recording, SnapshotBuilder};
+
+    #[test]
+    fn fan_out_groups() {
+        let sample = |tick: i32| {
+            let mut recording = Recording::new();
+            recording.push(
+                SnapshotBuilder::new(tick)
+                    .with_value("Throttle", Value::FLOAT(0.5))
+                    .with_value("AirTemp", Value::FLOAT(21.0))
+                    .build(),
+            );
+            recording.sample(0).unwrap().unwrap()
+        };
+        let group = |name: &str, channels: &[&str], fps: u8| {
+            ChannelGroup::new(name, channels, Fps::new(fps))
+        };
+
+        let mut fanout = Fanout::new(60);
+        let inputs = fanout.add(group("inputs", &["Throttle", "Brake"], 60), 200);
+        let weather = fanout.add(group("weather", &["AirTemp"], 2), 1);
+        for tick in 1..=120 {
+            fanout.dispatch(&sample(tick));
+        }
+
+        assert_eq!(inputs.try_iter().count(), 120);
+
+        // Ticks 1, 31, 61 and 91 were due, and the channel keeps only the newest
+        let latest = weather.try_recv().unwrap();
+        assert_eq!(latest.tick, 91);
+        assert_eq!(latest.values.len(), 1);
+        assert_eq!(latest.values[0].0, "AirTemp");
+        assert_eq!(fanout.dropped(), 3);
+
+        // A new session starts the timing again
+        fanout.dispatch(&sample(5));
+        assert_eq!(weather.try_recv().unwrap().tick, 5);
+
+        fanout.remove("inputs");
+        assert_eq!(fanout.groups(), vec!["weather"]);
+
+        // Rates follow the header's tick rate
+        let mut slow = Fanout::new(30);
+        let weather = slow.add(group("weather", &["AirTemp"], 2), 10);
+        for tick in 1..=30 {
+            slow.dispatch(&sample(tick));
+        }
+        let ticks: Vec<i32> = weather.try_iter().map(|s| s.tick).collect();
+        assert_eq!(ticks, vec![1, 16]);
+    }
+
+    #[test]
+    fn overflow_policies() {
+        let poll = Duration::from_millis(1);
+        let fill = |overflow: Overflow| {
+            let (sender, receiver) = bounded(2);
+            let dropped: Vec<bool> = (1..=4)
+                .map(|i| offer(&sender, &receiver, i, overflow, poll, || true))
+                .collect();
+            let queued: Vec<i32> = receiver.try_iter().collect();
+            (dropped, queued)
+        };
+
+        assert_eq!(
+            fill(Overflow::DropOldest),
+            (vec![false, false, true, true], vec![3, 4])
+        );
+        assert_eq!(
+            fill(Overflow::DropNewest),
+            (vec![false, false, true, true], vec![1, 2])
+        );
+
+        // Blocking waits for room, until stopped
+        assert_eq!(
+            fill(Overflow::Block),
+            (vec![false, false, true, true], vec![1, 2])
+        );
+
+        let (sender, receiver) = bounded(1);
+        sender.send(1).unwrap();
+        let consumer = {
+            let receiver = receiver.clone();
+            std::thread::spawn(move || {
+                std::thread::sleep(Duration::from_millis(20));
+                receiver.recv().unwrap()
+            })
+        };
+        assert!(!offer(&sender, &receiver, 2, Overflow::Block, poll, || {
+            false
+        }));
+        assert_eq!(consumer.join().unwrap(), 1);
+        assert_eq!(receiver.try_recv(), Ok(2));
+    }
+}
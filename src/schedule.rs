@@ -0,0 +1,236 @@
+use crate::results::Results;
+use crate::session::{Session, SessionDetails};
+use serde::{Deserialize, Serialize};
+
+///
+/// The role a session plays in an event.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum SessionKind {
+    Practice,
+    Qualify,
+    Warmup,
+
+    /// A heat race, numbered from 1
+    Heat(u32),
+
+    /// A consolation (last chance qualifier) race
+    Consolation,
+
+    /// The feature race of a heat racing event
+    Feature,
+
+    /// A race in a conventional (non-heat) event
+    Race,
+
+    /// An unrecognised session type
+    Other(String),
+}
+
+///
+/// A single session in an event's schedule.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Stage {
+    pub session_number: u64,
+    pub kind: SessionKind,
+    pub name: String,         // Session name as shown in the sim (e.g. "HEAT 1")
+    pub session_type: String, // Raw session type
+    pub laps: Option<u64>,    // Lap limit, None if unlimited
+    pub skipped: bool,        // Session was skipped by the host
+}
+
+///
+/// Schedule
+///
+/// The chain of sessions making up an event, including heat racing events with
+/// heats, consolations and a feature.
+///
+/// # Examples
+///
+/// ```
+/// use iracing::schedule::Schedule;
+/// # let session: iracing::session::SessionDetails =
+/// #     serde_yaml::from_str(&std::fs::read_to_string("./session.yaml").unwrap()).unwrap();
+/// # let session_num = 0;
+///
+/// let schedule = Schedule::from_session(&session);
+///
+/// if let Some(stage) = schedule.current(session_num) {
+///     println!("Now: {}", stage.name);
+/// }
+/// if let Some(stage) = schedule.next(session_num) {
+///     println!("Next: {}", stage.name);
+/// }
+/// ```
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct Schedule {
+    pub heat_racing: bool,
+    pub stages: Vec<Stage>,
+}
+
+impl SessionKind {
+    ///
+    /// Classify a session from its type, sub-type and name.
+    pub fn from_session(session: &Session) -> Self {
+        let sub_type = session.session_sub_type.as_deref().unwrap_or_default();
+        let name = session.session_name.as_deref().unwrap_or_default();
+        let labels = format!("{} {}", sub_type, name).to_uppercase();
+
+        if labels.contains("HEAT") {
+            let number = labels
+                .split(|c: char| !c.is_ascii_digit())
+                .find_map(|n| n.parse().ok())
+                .unwrap_or(1);
+            return SessionKind::Heat(number);
+        }
+        if labels.contains("CONSOLATION") || labels.contains("CONSI") || labels.contains("LCQ") {
+            return SessionKind::Consolation;
+        }
+        if labels.contains("FEATURE") {
+            return SessionKind::Feature;
+        }
+
+        let session_type = session.session_type.as_str();
+        if session_type.contains("Practice") {
+            SessionKind::Practice
+        } else if session_type.contains("Qualify") {
+            SessionKind::Qualify
+        } else if session_type.contains("Warmup") {
+            SessionKind::Warmup
+        } else if session_type.contains("Race") {
+            SessionKind::Race
+        } else {
+            SessionKind::Other(session.session_type.clone())
+        }
+    }
+
+    /// True for sessions which produce a race classification.
+    pub fn is_race(&self) -> bool {
+        matches!(
+            self,
+            SessionKind::Heat(_)
+                | SessionKind::Consolation
+                | SessionKind::Feature
+                | SessionKind::Race
+        )
+    }
+}
+
+impl Schedule {
+    ///
+    /// Build the schedule from the session info.
+    pub fn from_session(details: &SessionDetails) -> Self {
+        let mut stages: Vec<Stage> = details
+            .session
+            .sessions
+            .iter()
+            .map(|s| Stage {
+                session_number: s.session_number,
+                kind: SessionKind::from_session(s),
+                name: s
+                    .session_name
+                    .clone()
+                    .unwrap_or_else(|| s.session_type.clone()),
+                session_type: s.session_type.clone(),
+                laps: s.max_laps(),
+                skipped: s.session_skipped.unwrap_or(0) > 0,
+            })
+            .collect();
+        stages.sort_by_key(|s| s.session_number);
+
+        let heat_racing = details.weekend.heat_racing.unwrap_or(0) > 0
+            || stages
+                .iter()
+                .any(|s| matches!(s.kind, SessionKind::Heat(_) | SessionKind::Feature));
+
+        Schedule {
+            heat_racing,
+            stages,
+        }
+    }
+
+    /// The stage for a session number (`SessionNum`).
+    pub fn current(&self, session_number: u64) -> Option<&Stage> {
+        self.stages
+            .iter()
+            .find(|s| s.session_number == session_number)
+    }
+
+    ///
+    /// The next stage to be run after a session, skipping skipped sessions.
+    pub fn next(&self, session_number: u64) -> Option<&Stage> {
+        self.stages
+            .iter()
+            .find(|s| s.session_number > session_number && !s.skipped)
+    }
+
+    ///
+    /// The stage run before a session, skipping skipped sessions.
+    pub fn previous(&self, session_number: u64) -> Option<&Stage> {
+        self.stages
+            .iter()
+            .rev()
+            .find(|s| s.session_number < session_number && !s.skipped)
+    }
+
+    /// Heat races, in order.
+    pub fn heats(&self) -> impl Iterator<Item = &Stage> {
+        self.stages
+            .iter()
+            .filter(|s| matches!(s.kind, SessionKind::Heat(_)))
+    }
+
+    ///
+    /// The main race of the event - the feature for heat racing events,
+    /// otherwise the last race session.
+    pub fn feature(&self) -> Option<&Stage> {
+        self.stages
+            .iter()
+            .find(|s| s.kind == SessionKind::Feature)
+            .or_else(|| self.stages.iter().rev().find(|s| s.kind.is_race()))
+    }
+
+    ///
+    /// Results of every race session run before a session, in order.
+    ///
+    /// Used to carry heat and consolation results forward to later sessions.
+    pub fn results_before(&self, details: &SessionDetails, session_number: u64) -> Vec<Results> {
+        self.stages
+            .iter()
+            .filter(|s| s.session_number < session_number && s.kind.is_race() && !s.skipped)
+            .filter_map(|s| Results::from_session(details, s.session_number))
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn heat_chain() {
+        let content = std::fs::read_to_string("./session.yaml").unwrap();
+        let mut session: SessionDetails = serde_yaml::from_str(&content).unwrap();
+
+        let schedule = Schedule::from_session(&session);
+        assert!(!schedule.heat_racing);
+        assert_eq!(schedule.current(1).unwrap().kind, SessionKind::Qualify);
+        assert_eq!(schedule.feature().unwrap().session_number, 2);
+
+        // Convert the event into practice, a heat and a feature
+        session.session.sessions[1].session_type = String::from("Race");
+        session.session.sessions[1].session_name = Some(String::from("HEAT 1"));
+        session.session.sessions[2].session_name = Some(String::from("FEATURE"));
+        session.session.sessions[1].results = session.session.sessions[2].results.clone();
+
+        let schedule = Schedule::from_session(&session);
+        assert!(schedule.heat_racing);
+        assert_eq!(schedule.heats().count(), 1);
+        assert_eq!(schedule.next(0).unwrap().kind, SessionKind::Heat(1));
+        assert_eq!(schedule.previous(2).unwrap().name, "HEAT 1");
+        assert_eq!(schedule.feature().unwrap().kind, SessionKind::Feature);
+
+        let carried = schedule.results_before(&session, 2);
+        assert_eq!(carried.len(), 1);
+        assert_eq!(carried[0].session_number, 1);
+    }
+}
@@ -0,0 +1,331 @@
+use crate::session::SplitTimeInfo;
+use crate::states::Flags;
+use crate::validity;
+use serde::{Deserialize, Serialize};
+
+#[cfg(feature = "telemetry")]
+use crate::telemetry::Sample;
+#[cfg(feature = "telemetry")]
+use std::convert::TryInto;
+#[cfg(feature = "telemetry")]
+use std::error::Error;
+
+///
+/// Sector Flag Sample
+///
+/// Session and per-car flags at a point in the session, with where each car
+/// is on track. Per-car values are indexed by car index.
+#[derive(Debug, Clone, Default)]
+pub struct SectorFlagSample {
+    pub session_time: f64,      // Seconds since session start
+    pub session_flags: Flags,   // SessionFlags
+    pub car_flags: Vec<Flags>,  // CarIdxSessionFlags
+    pub lap_dist_pct: Vec<f32>, // CarIdxLapDistPct - -1 when the car isn't on track
+}
+
+///
+/// Flag shown in a part of the track, from least to most severe.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+pub enum SectorFlag {
+    Green,
+    Yellow,
+    YellowWaving,
+    Caution,
+}
+
+///
+/// A part of the track and the flag shown there.
+#[derive(Debug, Copy, Clone, PartialEq, Serialize, Deserialize)]
+pub struct TrackSegment {
+    pub index: usize,
+    pub start_pct: f32, // Lap distance the segment starts at
+    pub end_pct: f32,   // Lap distance the next segment starts at
+    pub flag: SectorFlag,
+}
+
+///
+/// A change of flag in a segment.
+#[derive(Debug, Copy, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SectorFlagEvent {
+    pub session_time: f64,
+    pub segment: usize,
+    pub flag: SectorFlag,
+}
+
+///
+/// Sector Flags
+///
+/// Maps local yellows onto parts of the track, for overlays showing where a
+/// yellow is waving.
+///
+/// iRacing doesn't report flags by sector. Instead each car's session flags
+/// include a yellow while the car is in a yellow zone, so a segment shows the
+/// most severe flag of the cars in it. A segment keeps its flag for a few
+/// seconds after the last car showing it has left, so yellows don't flicker as
+/// cars pass through. A full-course caution shows everywhere.
+///
+/// Segments may be the timing sectors from the session info, or the lap split
+/// into equal parts to match a track map.
+///
+/// # Examples
+///
+/// ```
+/// use iracing::sector_flags::{SectorFlag, SectorFlagSample, SectorFlags};
+///
+/// let mut flags = SectorFlags::equal(20);
+///
+/// for event in flags.update(&SectorFlagSample::default()) {
+///     println!("Segment {} now {:?}", event.segment, event.flag);
+/// }
+///
+/// let yellows = flags.segments().iter().filter(|s| s.flag > SectorFlag::Green).count();
+/// ```
+#[derive(Debug, Clone)]
+pub struct SectorFlags {
+    segments: Vec<TrackSegment>,
+    hold: f64,
+    last_seen: Vec<(SectorFlag, f64)>,
+}
+
+impl SectorFlagSample {
+    ///
+    /// Read a sector flag sample from a telemetry sample.
+    #[cfg(feature = "telemetry")]
+    pub fn from_sample(sample: &Sample) -> Result<Self, Box<dyn Error>> {
+        let session_flags: u32 = sample.get("SessionFlags")?.try_into()?;
+        let car_flags: Vec<u32> = sample.get("CarIdxSessionFlags")?.try_into()?;
+
+        Ok(SectorFlagSample {
+            session_time: sample.get("SessionTime")?.try_into()?,
+            session_flags: Flags::from_bits_truncate(session_flags),
+            car_flags: car_flags
+                .into_iter()
+                .map(Flags::from_bits_truncate)
+                .collect(),
+            lap_dist_pct: sample.get("CarIdxLapDistPct")?.try_into()?,
+        })
+    }
+}
+
+impl SectorFlag {
+    /// The local flag shown by a car's session flags.
+    pub fn from_car_flags(flags: Flags) -> Self {
+        if flags.contains(Flags::YELLOW_WAVING_FLAG) {
+            SectorFlag::YellowWaving
+        } else if flags.contains(Flags::YELLOW_FLAG) {
+            SectorFlag::Yellow
+        } else {
+            SectorFlag::Green
+        }
+    }
+}
+
+impl SectorFlags {
+    ///
+    /// Segments starting at the given lap distances, 0.0 to 1.0.
+    pub fn new(starts: &[f32]) -> Self {
+        let mut starts: Vec<f32> = starts
+            .iter()
+            .copied()
+            .filter(|s| (0.0..1.0).contains(s))
+            .collect();
+        starts.sort_by(|a, b| a.partial_cmp(b).unwrap_or(std::cmp::Ordering::Equal));
+        starts.dedup();
+        if starts.first() != Some(&0.0) {
+            starts.insert(0, 0.0);
+        }
+
+        let segments: Vec<TrackSegment> = starts
+            .iter()
+            .enumerate()
+            .map(|(index, start)| TrackSegment {
+                index,
+                start_pct: *start,
+                end_pct: starts.get(index + 1).copied().unwrap_or(1.0),
+                flag: SectorFlag::Green,
+            })
+            .collect();
+
+        SectorFlags {
+            last_seen: vec![(SectorFlag::Green, 0.0); segments.len()],
+            segments,
+            hold: 5.0,
+        }
+    }
+
+    /// The lap split into `count` equal segments.
+    pub fn equal(count: usize) -> Self {
+        let count = count.max(1);
+        let starts: Vec<f32> = (0..count).map(|i| i as f32 / count as f32).collect();
+        Self::new(&starts)
+    }
+
+    /// The timing sectors from the session info.
+    pub fn from_split_times(split_times: &SplitTimeInfo) -> Self {
+        let starts: Vec<f32> = split_times.sectors.iter().map(|s| s.start_pct).collect();
+        Self::new(&starts)
+    }
+
+    ///
+    /// How long a segment keeps its flag after the last car showing it has
+    /// left (s), 5 by default.
+    pub fn with_hold(mut self, seconds: f64) -> Self {
+        self.hold = seconds;
+        self
+    }
+
+    ///
+    /// Update with a new sample, returning the segments whose flag changed.
+    pub fn update(&mut self, sample: &SectorFlagSample) -> Vec<SectorFlagEvent> {
+        let session_time = sample.session_time;
+        let caution = sample
+            .session_flags
+            .intersects(Flags::CAUTION | Flags::CAUTION_WAVING);
+
+        // Most severe flag shown by a car in each segment
+        let mut shown = vec![SectorFlag::Green; self.segments.len()];
+        for (car_idx, flags) in sample.car_flags.iter().enumerate() {
+            let flag = SectorFlag::from_car_flags(*flags);
+            let pct = sample
+                .lap_dist_pct
+                .get(car_idx)
+                .copied()
+                .and_then(validity::lap_dist_pct);
+            if flag == SectorFlag::Green {
+                continue;
+            }
+            if let Some(segment) = pct.and_then(|pct| self.segment_index(pct)) {
+                shown[segment] = shown[segment].max(flag);
+            }
+        }
+
+        let mut events = Vec::new();
+        for (segment, flag) in self.segments.iter_mut().zip(shown) {
+            let last = &mut self.last_seen[segment.index];
+            if flag > SectorFlag::Green && (flag >= last.0 || session_time - last.1 > self.hold) {
+                *last = (flag, session_time);
+            } else if flag == last.0 {
+                last.1 = session_time;
+            }
+
+            let held = if session_time - last.1 <= self.hold {
+                last.0
+            } else {
+                SectorFlag::Green
+            };
+            let flag = if caution { SectorFlag::Caution } else { held };
+
+            if flag != segment.flag {
+                segment.flag = flag;
+                events.push(SectorFlagEvent {
+                    session_time,
+                    segment: segment.index,
+                    flag,
+                });
+            }
+        }
+
+        events
+    }
+
+    /// All segments, in order around the lap.
+    pub fn segments(&self) -> &[TrackSegment] {
+        &self.segments
+    }
+
+    /// The segment containing a lap distance.
+    pub fn segment_at(&self, pct: f32) -> Option<&TrackSegment> {
+        self.segments.get(self.segment_index(pct)?)
+    }
+
+    /// The flag shown at a lap distance.
+    pub fn flag_at(&self, pct: f32) -> SectorFlag {
+        self.segment_at(pct)
+            .map_or(SectorFlag::Green, |segment| segment.flag)
+    }
+
+    fn segment_index(&self, pct: f32) -> Option<usize> {
+        if !(0.0..=1.0).contains(&pct) {
+            return None;
+        }
+        self.segments.iter().rposition(|s| pct >= s.start_pct)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::session::SessionDetails;
+
+    #[test]
+    fn local_yellows() {
+        let content = std::fs::read_to_string("./session.yaml").unwrap();
+        let session: SessionDetails = serde_yaml::from_str(&content).unwrap();
+        let mut flags = SectorFlags::from_split_times(&session.split_time_info.unwrap());
+        assert_eq!(flags.segments().len(), 3);
+        assert_eq!(flags.segments()[1].end_pct, 0.65521);
+
+        // A car approaching a stopped car in the second sector
+        let mut sample = SectorFlagSample {
+            session_time: 10.0,
+            session_flags: Flags::GREEN_FLAG,
+            car_flags: vec![
+                Flags::empty(),
+                Flags::YELLOW_WAVING_FLAG,
+                Flags::YELLOW_FLAG,
+            ],
+            lap_dist_pct: vec![0.1, 0.4, 0.45],
+        };
+        let events = flags.update(&sample);
+        assert_eq!(
+            events,
+            vec![SectorFlagEvent {
+                session_time: 10.0,
+                segment: 1,
+                flag: SectorFlag::YellowWaving
+            }]
+        );
+        assert_eq!(flags.flag_at(0.5), SectorFlag::YellowWaving);
+
+        // The flag is held after the cars leave the sector
+        sample.session_time = 12.0;
+        sample.car_flags = vec![Flags::empty(); 3];
+        sample.lap_dist_pct = vec![0.2, 0.7, 0.75];
+        assert!(flags.update(&sample).is_empty());
+
+        sample.session_time = 16.0;
+        assert_eq!(flags.update(&sample)[0].flag, SectorFlag::Green);
+
+        // A full-course caution shows everywhere
+        sample.session_flags = Flags::CAUTION_WAVING;
+        assert_eq!(flags.update(&sample).len(), 3);
+        assert_eq!(flags.flag_at(0.9), SectorFlag::Caution);
+    }
+
+    #[cfg(feature = "telemetry")]
+    #[test]
+    fn from_sample() {
+        use crate::recording::{Recording, SnapshotBuilder};
+        use crate::telemetry::Value;
+
+        // Types as the sim publishes them, flags are bitfields
+        let mut recording = Recording::new();
+        recording.push(
+            SnapshotBuilder::new(1)
+                .with_value("SessionTime", Value::DOUBLE(10.0))
+                .with_value("SessionFlags", Value::BITS(Flags::GREEN_FLAG.bits()))
+                .with_value(
+                    "CarIdxSessionFlags",
+                    Value::BitsVec(vec![0, Flags::YELLOW_FLAG.bits()]),
+                )
+                .with_value("CarIdxLapDistPct", Value::FloatVec(vec![0.1, 0.4]))
+                .build(),
+        );
+        let sample = recording.player().last().unwrap().unwrap();
+
+        let sample = SectorFlagSample::from_sample(&sample).unwrap();
+        assert_eq!(sample.session_flags, Flags::GREEN_FLAG);
+        assert_eq!(sample.car_flags, vec![Flags::empty(), Flags::YELLOW_FLAG]);
+        assert_eq!(sample.lap_dist_pct, vec![0.1, 0.4]);
+    }
+}
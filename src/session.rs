@@ -1,4 +1,8 @@
-use serde::{Deserialize, Serialize};
+use crate::boundary::{BoundarySample, SessionKey};
+use crate::setups::CarSetup;
+use crate::units::{Angle, Length, Pressure, Speed, Temperature};
+use crate::yaml::{sanitize, Repair};
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
 
 ///
 /// Session Details
@@ -14,6 +18,18 @@ pub struct SessionDetails {
 
     #[serde(rename = "DriverInfo")]
     pub drivers: DriverInfo, // Driver information
+
+    #[serde(rename = "QualifyResultsInfo")]
+    pub qualify_results: Option<QualifyResultsInfo>, // Qualifying results carried into the race
+
+    #[serde(rename = "RadioInfo")]
+    pub radio: Option<RadioInfo>, // Voice chat radios and frequencies
+
+    #[serde(rename = "CarSetup")]
+    pub car_setup: Option<CarSetup>, // Player's garage setup
+
+    #[serde(rename = "SplitTimeInfo")]
+    pub split_time_info: Option<SplitTimeInfo>, // Sectors used for split timing
 }
 
 ///
@@ -38,11 +54,14 @@ pub struct WeekendInfo {
     pub track_longitude: String,          // Track Longitude (deg)
     pub track_north_offset: String,       // Track rotation relative to true north (rad)
 
+    pub track_length_official: Option<String>, // Official track length (as string of km)
+
     #[serde(rename = "TrackNumTurns")]
     pub track_turns: u32, // Number of turns
 
-    pub track_pit_speed_limit: String, // Pit speed limit (km/h)
-    pub track_type: String,            // Track type (Road, Oval, Dirt, DOval)
+    pub track_pit_speed_limit: String,   // Pit speed limit (km/h)
+    pub track_type: String,              // Track type (Road, Oval, Dirt, DOval)
+    pub track_direction: Option<String>, // Direction of travel (neutral, left, right)
 
     #[serde(rename = "TrackWeatherType")]
     pub track_weather: String, // Track Weather
@@ -63,10 +82,16 @@ pub struct WeekendInfo {
     #[serde(rename = "TrackWindDir")]
     pub track_wind_direction: String, // Track wind direction relative to north (rad)
     pub track_fog_level: String, // Track fogginess
-    pub track_cleanup: i32,      // Track cleanup
+
+    #[serde(rename = "TrackRelativeHumidity")]
+    pub track_relative_humidity: Option<String>, // Track relative humidity (%) - dynamic weather builds only
+    pub track_precipitation: Option<String>, // Track precipitation (%) - dynamic weather builds only
+
+    pub track_cleanup: i32, // Track cleanup
 
     #[serde(rename = "TrackDynamicTrack")]
     pub track_dynamic: i32, // Track Dynamic
+    pub track_version: Option<String>, // Version of the track build
 
     #[serde(rename = "SeriesID")]
     pub series_id: i32, // iRacing series ID
@@ -101,8 +126,17 @@ pub struct WeekendInfo {
     #[serde(rename = "NumCarTypes")]
     pub n_car_types: u32, // Number of car types eligible for the race
 
+    pub heat_racing: Option<i8>, // Event uses heat racing (heats, consolations and a feature)
+
+    pub build_type: Option<String>,    // Sim build type (Release)
+    pub build_target: Option<String>,  // Sim build audience (Members)
+    pub build_version: Option<String>, // Sim build version
+
     #[serde(rename = "WeekendOptions")]
     pub options: WeekendOptions,
+
+    #[serde(rename = "TelemetryOptions")]
+    pub telemetry_options: Option<TelemetryOptions>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -133,6 +167,76 @@ pub struct WeekendOptions {
     pub strict_laps_checking: String,
     pub has_open_registration: i8, // On if anyone can register, off if registration requires a specific license or invitation.
     pub hardcore_level: i8,        // Hardcoreness
+
+    pub date: Option<String>, // In-sim date of the session (YYYY-MM-DD)
+    pub time_of_day: Option<String>, // In-sim time of day at the start of the session
+    pub earth_rotation_speedup_factor: Option<u32>, // Time of day multiplier
+
+    pub short_parade_lap: Option<i8>, // Parade lap is shortened
+    pub num_joker_laps: Option<u32>,  // Joker laps each car must take (rallycross)
+
+    #[serde(default)]
+    pub incident_limit: Option<Limit>, // Incidents before disqualification
+
+    #[serde(default)]
+    pub fast_repairs_limit: Option<Limit>, // Fast repairs per car
+
+    #[serde(default)]
+    pub green_white_checkered_limit: Option<Limit>, // Green-white-checkered attempts
+}
+
+///
+/// A limit from the weekend options, which the sim writes as a number or
+/// `unlimited`.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum Limit {
+    Unlimited,
+    Count(u32),
+}
+
+///
+/// Telemetry logging options for the session.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "PascalCase")]
+pub struct TelemetryOptions {
+    pub telemetry_disk_file: Option<String>, // File telemetry is being logged to, empty if not logging
+}
+
+///
+/// Race Rules
+///
+/// The rules a race is run to, gathered from the weekend info so league tools
+/// can check a session is set up as expected before the start.
+///
+/// # Examples
+///
+/// ```no_run
+/// use iracing::session::{RaceRules, SessionDetails};
+///
+/// # fn check(session: &SessionDetails, expected: &RaceRules) {
+/// for rule in session.weekend.rules().mismatches(expected) {
+///     println!("{} is not set up as expected", rule);
+/// }
+/// # }
+/// ```
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RaceRules {
+    pub standing_start: bool,
+    pub short_parade_lap: bool,
+    pub starting_grid: String,
+    pub qualify_scoring: String,
+    pub course_cautions: String,
+    pub restarts: String,
+    pub fixed_setup: bool,
+    pub incident_limit: Option<u32>,     // None if unlimited
+    pub fast_repairs_limit: Option<u32>, // None if unlimited
+    pub green_white_checkered_limit: Option<u32>,
+    pub joker_laps: u32,
+    pub team_racing: bool,
+    pub min_drivers: i8,
+    pub max_drivers: i8,
+    pub driver_change_rules: String,
+    pub qualifier_must_start_race: bool,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -162,6 +266,22 @@ pub struct Session {
 
     #[serde(rename = "ResultsPositions")]
     pub results: Option<Vec<SessionResult>>,
+
+    pub session_name: Option<String>,
+    pub session_sub_type: Option<String>, // Heat racing session role (e.g. Heat, Consolation, Feature)
+    pub session_skipped: Option<i8>,      // Session was skipped by the host
+
+    #[serde(rename = "ResultsNumCautionFlags")]
+    pub caution_flags: Option<i32>, // Number of cautions in the session
+
+    #[serde(rename = "ResultsNumCautionLaps")]
+    pub caution_laps: Option<i32>, // Number of laps run under caution
+
+    #[serde(rename = "ResultsNumLeadChanges")]
+    pub lead_changes: Option<i32>, // Number of lead changes
+
+    #[serde(rename = "ResultsOfficial")]
+    pub results_official: Option<i8>, // Results are final
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -183,6 +303,103 @@ pub struct SessionResult {
     pub reason_out_str: String,
 }
 
+///
+/// Qualifying results, present once qualifying has finished.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "PascalCase")]
+pub struct QualifyResultsInfo {
+    pub results: Option<Vec<QualifyResult>>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "PascalCase")]
+pub struct QualifyResult {
+    pub position: i32,       // Overall position (zero-based)
+    pub class_position: i32, // Position in class (zero-based)
+    pub car_idx: i32,
+    pub fastest_lap: i32,
+    pub fastest_time: f32,
+}
+
+///
+/// Sectors the track is split into for split timing.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "PascalCase")]
+pub struct SplitTimeInfo {
+    pub sectors: Vec<Sector>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Sector {
+    #[serde(rename = "SectorNum")]
+    pub number: u32,
+
+    #[serde(rename = "SectorStartPct")]
+    pub start_pct: f32, // Lap distance the sector starts at, 0.0 to 1.0
+}
+
+///
+/// Voice chat radios available to the player.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "PascalCase")]
+pub struct RadioInfo {
+    #[serde(rename = "SelectedRadioNum")]
+    pub selected_radio: usize, // Radio currently selected for transmitting
+    pub radios: Vec<Radio>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "PascalCase")]
+pub struct Radio {
+    #[serde(rename = "RadioNum")]
+    pub radio_number: usize,
+    pub hop_count: i32,
+
+    #[serde(rename = "NumFrequencies")]
+    pub n_frequencies: usize,
+
+    #[serde(rename = "TunedToFrequencyNum")]
+    pub tuned_frequency: i32, // Frequency the radio is tuned to, -1 if none
+    pub scanning_is_on: i8, // Radio scans other frequencies for traffic
+    pub frequencies: Vec<RadioFrequency>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "PascalCase")]
+pub struct RadioFrequency {
+    #[serde(rename = "FrequencyNum")]
+    pub frequency_number: usize,
+
+    #[serde(rename = "FrequencyName")]
+    pub name: String, // Frequency name (e.g. "@RACECONTROL")
+    pub priority: i32,
+    pub car_idx: i32,   // Car the frequency belongs to, -1 if not a car frequency
+    pub entry_idx: i32, // Entry the frequency belongs to, -1 if none
+
+    #[serde(rename = "ClubID")]
+    pub club_id: i32, // Club the frequency belongs to, 0 if not a club frequency
+    pub can_scan: i8,
+    pub can_squawk: i8,
+    pub muted: i8,
+    pub is_mutable: i8,
+    pub is_deletable: i8,
+}
+
+///
+/// Purpose of a radio frequency, derived from its name.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum FrequencyRole {
+    AllTeams,
+    Drivers,
+    Team,
+    Club,
+    Admins,
+    RaceControl,
+
+    /// A user-created frequency
+    Custom(String),
+}
+
 ///
 /// Details of Player driver, and other drivers.Deserialize
 ///
@@ -193,6 +410,9 @@ pub struct DriverInfo {
     #[serde(rename = "DriverCarIdx")]
     pub car_index: usize, // Drivers' Car Index
 
+    #[serde(rename = "PaceCarIdx")]
+    pub pace_car_index: Option<i32>, // Pace car's Car Index, -1 if there is no pace car
+
     #[serde(rename = "DriverHeadPosX")]
     pub head_position_x: f32, // Head Position (X)
 
@@ -217,6 +437,9 @@ pub struct DriverInfo {
     #[serde(rename = "DriverCarMaxFuelPct")]
     pub fuel_max_fill_percent: f32, // Fuel Fill Percent
 
+    #[serde(rename = "DriverCarGearNumForward")]
+    pub forward_gears: Option<i32>, // Number of forward gears
+
     #[serde(rename = "DriverCarSLFirstRPM")]
     pub shift_light_first_rpm: f32, // RPM at which the first shift-indicator light triggers
 
@@ -273,8 +496,17 @@ pub struct Driver {
     #[serde(rename = "CarNumberRaw")]
     pub car_number: i64,
 
+    #[serde(rename = "CarNumber")]
+    pub car_number_text: Option<String>, // Car number as painted, keeping leading zeros
+
     pub car_path: String,
 
+    #[serde(rename = "CarIsPaceCar")]
+    pub car_is_pace_car: Option<i8>, // Car is the pace car
+
+    #[serde(rename = "CarIsAI")]
+    pub car_is_ai: Option<i8>, // Car is driven by the AI
+
     #[serde(rename = "CarClassID")]
     pub car_class_id: u64,
 
@@ -297,6 +529,9 @@ pub struct Driver {
 
     pub car_class_color: String,
 
+    #[serde(rename = "CarClassEstLapTime")]
+    pub car_class_est_lap_time: Option<f32>, // Estimated lap time of the class (s)
+
     pub i_rating: i64,
 
     #[serde(rename = "LicLevel")]
@@ -321,6 +556,260 @@ pub struct Driver {
 
     pub club_name: Option<String>, // User's club name - Not present for safety car.
     pub division_name: Option<String>, // User's disivision name - Not present for safety car.
+
+    #[serde(rename = "CurDriverIncidentCount")]
+    pub driver_incident_count: Option<i32>, // Incidents of the driver currently in the car
+
+    #[serde(rename = "TeamIncidentCount")]
+    pub team_incident_count: Option<i32>, // Incidents of all drivers of the car
+}
+
+impl SessionDetails {
+    ///
+    /// Parse session info YAML, repairing it if it doesn't parse as is.
+    ///
+    /// The error is from the original YAML, if the repaired YAML fails too.
+    pub fn parse(yaml: &str) -> Result<Self, serde_yaml::Error> {
+        Self::parse_lenient(yaml).map(|(details, _)| details)
+    }
+
+    ///
+    /// Parse session info YAML, repairing it if it doesn't parse as is, with
+    /// a report of the repairs made. See [`sanitize`](crate::yaml::sanitize).
+    pub fn parse_lenient(yaml: &str) -> Result<(Self, Vec<Repair>), serde_yaml::Error> {
+        let error = match serde_yaml::from_str(yaml) {
+            Ok(details) => return Ok((details, Vec::new())),
+            Err(e) => e,
+        };
+
+        let (sanitized, repairs) = sanitize(yaml);
+        match serde_yaml::from_str(&sanitized) {
+            Ok(details) => Ok((details, repairs)),
+            Err(_) => Err(error),
+        }
+    }
+
+    ///
+    /// Key of the session the sim is in, for storing data about it. See
+    /// [`SessionKey`](crate::boundary::SessionKey).
+    pub fn key(&self, sample: &BoundarySample) -> SessionKey {
+        SessionKey::new(self.weekend.sub_session_id, sample)
+    }
+}
+
+///
+/// Read the sub-session ID from session info YAML without parsing the rest,
+/// for keying data by session cheaply.
+pub fn sub_session_id(yaml: &str) -> Option<i32> {
+    let mut lines = yaml
+        .lines()
+        .skip_while(|line| line.trim_end() != "WeekendInfo:");
+    lines.next()?;
+
+    lines
+        .take_while(|line| line.is_empty() || line.starts_with(' '))
+        .find_map(|line| line.trim().strip_prefix("SubSessionID:"))
+        .and_then(|id| id.trim().parse().ok())
+}
+
+impl WeekendInfo {
+    /// The rules the race is run to.
+    pub fn rules(&self) -> RaceRules {
+        let options = &self.options;
+
+        RaceRules {
+            standing_start: options.standing_start != 0,
+            short_parade_lap: options.short_parade_lap.unwrap_or(0) != 0,
+            starting_grid: options.starting_grid.clone(),
+            qualify_scoring: options.qualify_scoring.clone(),
+            course_cautions: options.course_cautions.clone(),
+            restarts: options.restarts.clone(),
+            fixed_setup: options.is_fixed_setup != 0,
+            incident_limit: options.incident_limit(),
+            fast_repairs_limit: options.fast_repairs_limit(),
+            green_white_checkered_limit: options.green_white_checkered_limit(),
+            joker_laps: options.num_joker_laps.unwrap_or(0),
+            team_racing: self.team_racing != 0,
+            min_drivers: self.min_drivers,
+            max_drivers: self.max_drivers,
+            driver_change_rules: self.dc_rule_set.clone(),
+            qualifier_must_start_race: self.qualifier_must_start_race != 0,
+        }
+    }
+
+    /// Track length.
+    pub fn length(&self) -> Option<Length> {
+        self.track_length.parse().ok()
+    }
+
+    /// Official track length, as published by the circuit.
+    pub fn official_length(&self) -> Option<Length> {
+        self.track_length_official.as_deref()?.parse().ok()
+    }
+
+    /// Track altitude.
+    pub fn altitude(&self) -> Option<Length> {
+        self.track_altitude.parse().ok()
+    }
+
+    /// Track rotation relative to true north.
+    pub fn north_offset(&self) -> Option<Angle> {
+        self.track_north_offset.parse().ok()
+    }
+
+    /// Pit lane speed limit.
+    pub fn pit_speed_limit(&self) -> Option<Speed> {
+        self.track_pit_speed_limit.parse().ok()
+    }
+
+    /// Track surface temperature.
+    pub fn surface_temperature(&self) -> Option<Temperature> {
+        self.track_surface_temperature.parse().ok()
+    }
+
+    /// Air temperature.
+    pub fn air_temperature(&self) -> Option<Temperature> {
+        self.track_air_tempearture.parse().ok()
+    }
+
+    /// Air pressure.
+    pub fn air_pressure(&self) -> Option<Pressure> {
+        self.track_air_pressure.parse().ok()
+    }
+
+    /// Wind speed.
+    pub fn wind_speed(&self) -> Option<Speed> {
+        self.track_wind_speed.parse().ok()
+    }
+
+    /// Wind direction relative to north.
+    pub fn wind_direction(&self) -> Option<Angle> {
+        self.track_wind_direction.parse().ok()
+    }
+}
+
+impl WeekendOptions {
+    /// Incidents before a driver is disqualified, None if unlimited.
+    pub fn incident_limit(&self) -> Option<u32> {
+        self.incident_limit.and_then(Limit::count)
+    }
+
+    /// Fast repairs available to each car, None if unlimited.
+    pub fn fast_repairs_limit(&self) -> Option<u32> {
+        self.fast_repairs_limit.and_then(Limit::count)
+    }
+
+    /// Green-white-checkered finish attempts, None if unlimited.
+    pub fn green_white_checkered_limit(&self) -> Option<u32> {
+        self.green_white_checkered_limit.and_then(Limit::count)
+    }
+}
+
+impl Limit {
+    /// The limit, None if unlimited.
+    pub fn count(self) -> Option<u32> {
+        match self {
+            Limit::Unlimited => None,
+            Limit::Count(count) => Some(count),
+        }
+    }
+}
+
+impl Serialize for Limit {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        match self {
+            Limit::Unlimited => serializer.serialize_str("unlimited"),
+            Limit::Count(count) => serializer.serialize_u32(*count),
+        }
+    }
+}
+
+impl<'de> Deserialize<'de> for Limit {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        use serde::de::Error;
+        use serde_yaml::Value;
+        use std::convert::TryFrom;
+
+        let value = Value::deserialize(deserializer)?;
+        let count = match &value {
+            Value::String(s) if s.trim().eq_ignore_ascii_case("unlimited") => {
+                return Ok(Limit::Unlimited)
+            }
+            Value::String(s) => s.trim().parse().ok(),
+            Value::Number(n) => n.as_u64().and_then(|n| u32::try_from(n).ok()),
+            _ => None,
+        };
+
+        count
+            .map(Limit::Count)
+            .ok_or_else(|| D::Error::custom(format!("Expected a limit, found {:?}", value)))
+    }
+}
+
+impl RaceRules {
+    /// Names of the rules which differ from those expected.
+    pub fn mismatches(&self, expected: &RaceRules) -> Vec<&'static str> {
+        let checks = [
+            (
+                "standing start",
+                self.standing_start == expected.standing_start,
+            ),
+            (
+                "short parade lap",
+                self.short_parade_lap == expected.short_parade_lap,
+            ),
+            (
+                "starting grid",
+                self.starting_grid == expected.starting_grid,
+            ),
+            (
+                "qualify scoring",
+                self.qualify_scoring == expected.qualify_scoring,
+            ),
+            (
+                "course cautions",
+                self.course_cautions == expected.course_cautions,
+            ),
+            ("restarts", self.restarts == expected.restarts),
+            ("fixed setup", self.fixed_setup == expected.fixed_setup),
+            (
+                "incident limit",
+                self.incident_limit == expected.incident_limit,
+            ),
+            (
+                "fast repairs limit",
+                self.fast_repairs_limit == expected.fast_repairs_limit,
+            ),
+            (
+                "green-white-checkered limit",
+                self.green_white_checkered_limit == expected.green_white_checkered_limit,
+            ),
+            ("joker laps", self.joker_laps == expected.joker_laps),
+            ("team racing", self.team_racing == expected.team_racing),
+            ("minimum drivers", self.min_drivers == expected.min_drivers),
+            ("maximum drivers", self.max_drivers == expected.max_drivers),
+            (
+                "driver change rules",
+                self.driver_change_rules == expected.driver_change_rules,
+            ),
+            (
+                "qualifier must start race",
+                self.qualifier_must_start_race == expected.qualifier_must_start_race,
+            ),
+        ];
+
+        checks
+            .iter()
+            .filter(|(_, matches)| !matches)
+            .map(|(name, _)| *name)
+            .collect()
+    }
 }
 
 impl Session {
@@ -333,3 +822,158 @@ impl Session {
         self.laps.as_u64()
     }
 }
+
+impl Driver {
+    /// True for the pace car.
+    pub fn is_pace_car(&self) -> bool {
+        self.car_is_pace_car.unwrap_or(0) != 0
+    }
+
+    /// Car number as painted, such as "044".
+    pub fn number(&self) -> String {
+        self.car_number_text
+            .clone()
+            .unwrap_or_else(|| self.car_number.to_string())
+    }
+}
+
+impl RadioInfo {
+    /// The radio currently selected for transmitting.
+    pub fn selected(&self) -> Option<&Radio> {
+        self.radios
+            .iter()
+            .find(|r| r.radio_number == self.selected_radio)
+    }
+
+    ///
+    /// Find the first frequency with a given role on any radio.
+    pub fn frequency(&self, role: &FrequencyRole) -> Option<&RadioFrequency> {
+        self.radios
+            .iter()
+            .flat_map(|r| r.frequencies.iter())
+            .find(|f| f.role() == *role)
+    }
+
+    /// The race control frequency.
+    pub fn race_control(&self) -> Option<&RadioFrequency> {
+        self.frequency(&FrequencyRole::RaceControl)
+    }
+}
+
+impl Radio {
+    /// The frequency this radio is tuned to.
+    pub fn tuned(&self) -> Option<&RadioFrequency> {
+        self.frequencies
+            .iter()
+            .find(|f| f.frequency_number as i32 == self.tuned_frequency)
+    }
+
+    /// Frequencies which are not muted.
+    pub fn unmuted(&self) -> impl Iterator<Item = &RadioFrequency> {
+        self.frequencies.iter().filter(|f| !f.is_muted())
+    }
+}
+
+impl RadioFrequency {
+    ///
+    /// Determine what the frequency is used for from its name.
+    pub fn role(&self) -> FrequencyRole {
+        match self.name.to_uppercase().as_str() {
+            "@ALLTEAMS" => FrequencyRole::AllTeams,
+            "@DRIVERS" => FrequencyRole::Drivers,
+            "@TEAM" => FrequencyRole::Team,
+            "@CLUB" => FrequencyRole::Club,
+            "@ADMINS" => FrequencyRole::Admins,
+            "@RACECONTROL" => FrequencyRole::RaceControl,
+            _ => FrequencyRole::Custom(self.name.clone()),
+        }
+    }
+
+    pub fn is_muted(&self) -> bool {
+        self.muted != 0
+    }
+
+    /// The car for a team frequency.
+    pub fn car(&self) -> Option<usize> {
+        if self.car_idx >= 0 {
+            Some(self.car_idx as usize)
+        } else {
+            None
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn radio_frequencies() {
+        let content = std::fs::read_to_string("./session.yaml").unwrap();
+        let session: SessionDetails = serde_yaml::from_str(&content).unwrap();
+        let radio = session.radio.unwrap();
+
+        let selected = radio.selected().unwrap();
+        assert_eq!(selected.frequencies.len(), selected.n_frequencies);
+        assert_eq!(selected.tuned().unwrap().role(), FrequencyRole::AllTeams);
+        assert_eq!(selected.unmuted().count(), 5);
+
+        assert_eq!(radio.race_control().unwrap().frequency_number, 5);
+        assert_eq!(
+            radio.frequency(&FrequencyRole::Team).unwrap().car(),
+            Some(1)
+        );
+        assert!(radio.frequency(&FrequencyRole::Club).unwrap().is_muted());
+    }
+    #[test]
+    fn race_rules() {
+        let content = std::fs::read_to_string("./session.yaml").unwrap();
+        let session: SessionDetails = serde_yaml::from_str(&content).unwrap();
+        let weekend = &session.weekend;
+        assert_eq!(weekend.build_version.as_deref(), Some("2023.06.13.01"));
+        assert_eq!(
+            weekend
+                .telemetry_options
+                .as_ref()
+                .unwrap()
+                .telemetry_disk_file
+                .as_deref(),
+            Some("")
+        );
+
+        let rules = weekend.rules();
+        assert_eq!(rules.incident_limit, Some(25));
+        assert_eq!(rules.fast_repairs_limit, Some(1));
+        assert_eq!(rules.restarts, "double file lapped cars behind");
+        assert!(rules.mismatches(&rules).is_empty());
+
+        assert_eq!(weekend.options.incident_limit, Some(Limit::Count(25)));
+
+        let unlimited = content.replace("IncidentLimit: 25", "IncidentLimit: unlimited");
+        let session: SessionDetails = serde_yaml::from_str(&unlimited).unwrap();
+        assert_eq!(
+            session.weekend.options.incident_limit,
+            Some(Limit::Unlimited)
+        );
+        assert_eq!(
+            serde_json::to_string(&Limit::Unlimited).unwrap(),
+            "\"unlimited\""
+        );
+        assert_eq!(serde_json::to_string(&Limit::Count(3)).unwrap(), "3");
+        let expected = RaceRules {
+            standing_start: true,
+            ..rules
+        };
+        assert_eq!(
+            session.weekend.rules().mismatches(&expected),
+            vec!["standing start", "incident limit"]
+        );
+    }
+
+    #[test]
+    fn read_sub_session_id() {
+        let content = std::fs::read_to_string("./session.yaml").unwrap();
+        assert_eq!(sub_session_id(&content), Some(31470051));
+        assert_eq!(sub_session_id("DriverInfo:\n SubSessionID: 4\n"), None);
+    }
+}
@@ -1,29 +1,290 @@
-use std::path::Path;
-use std::fs::File;
-use std::io::prelude::*;
-use std::convert::TryInto;
-use std::io;
-
-pub struct Setup;
-
-impl Setup {
-
-    pub fn new(data: Vec<u8>) -> io::Result<Self> {
-
-        let ints: Vec<i32> = data.chunks_exact(4).map( |bytes| {
-            i32::from_le_bytes(bytes.try_into().unwrap())
-        }).collect();
-
-        println!("Ints: {:#?}", ints);
-
-        Ok(Self{})
-    }
-
-    pub fn from_file(path: &Path) -> io::Result<Self> {
-        let mut file = File::open(path)?;
-        let mut contents = Vec::<u8>::new(); 
-        file.read_to_end(&mut contents)?;
-
-        Setup::new(contents)
-    }
-}
\ No newline at end of file
+use crate::session::SessionDetails;
+use serde::{Deserialize, Deserializer, Serialize};
+use std::collections::BTreeMap;
+
+///
+/// A node in the garage setup tree - either a group of settings or a single setting.
+///
+/// Setting values are kept as displayed in the garage (e.g. "165 kPa").
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(untagged)]
+pub enum SetupNode {
+    Group(BTreeMap<String, SetupNode>),
+    Value(String),
+}
+
+///
+/// Car Setup
+///
+/// The garage setup of the player's car, from the `CarSetup` section of the session info.
+///
+/// Sections vary by car model. Most cars have tires (`TiresAero` or `Tires`),
+/// `Chassis` and `Drivetrain` sections; settings are addressed by a dotted path,
+/// e.g. `Chassis.LeftFront.Camber`.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "PascalCase")]
+pub struct CarSetup {
+    pub update_count: u32, // Incremented each time the setup is changed in the garage
+
+    #[serde(flatten)]
+    pub sections: BTreeMap<String, SetupNode>,
+}
+
+///
+/// A single difference between two setups.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SetupChange {
+    pub path: String,
+    pub before: Option<String>, // None if the setting only exists in the other setup
+    pub after: Option<String>,  // None if the setting only exists in this setup
+    pub delta: Option<f64>,     // Numeric change, when both values are numbers in the same unit
+}
+
+///
+/// Setup
+///
+/// A named car setup, as exported with telemetry.
+///
+/// # Examples
+///
+/// ```
+/// use iracing::setups::Setup;
+/// # let before: iracing::session::SessionDetails =
+/// #     serde_yaml::from_str(&std::fs::read_to_string("./session.yaml").unwrap()).unwrap();
+/// # let after = before.clone();
+///
+/// let old = Setup::from_session(&before).unwrap();
+/// let new = Setup::from_session(&after).unwrap();
+///
+/// for change in old.diff(&new) {
+///     println!("{}: {:?} -> {:?}", change.path, change.before, change.after);
+/// }
+/// ```
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Setup {
+    pub name: String,   // Setup file name
+    pub modified: bool, // Modified since it was loaded
+    pub car: CarSetup,
+}
+
+impl<'de> Deserialize<'de> for SetupNode {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        serde_yaml::Value::deserialize(deserializer).map(SetupNode::from)
+    }
+}
+
+impl From<serde_yaml::Value> for SetupNode {
+    fn from(value: serde_yaml::Value) -> Self {
+        use serde_yaml::Value;
+
+        match value {
+            Value::Mapping(m) => SetupNode::Group(
+                m.into_iter()
+                    .map(|(k, v)| (SetupNode::from(k).to_string(), SetupNode::from(v)))
+                    .collect(),
+            ),
+            Value::String(s) => SetupNode::Value(s),
+            Value::Number(n) => SetupNode::Value(n.to_string()),
+            Value::Bool(b) => SetupNode::Value(b.to_string()),
+            Value::Null => SetupNode::Value(String::new()),
+            Value::Sequence(s) => SetupNode::Value(
+                s.into_iter()
+                    .map(|v| SetupNode::from(v).to_string())
+                    .collect::<Vec<String>>()
+                    .join(", "),
+            ),
+        }
+    }
+}
+
+impl std::fmt::Display for SetupNode {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            SetupNode::Value(v) => write!(f, "{}", v),
+            SetupNode::Group(g) => write!(f, "{{{} settings}}", g.len()),
+        }
+    }
+}
+
+impl SetupNode {
+    /// Child node of a group.
+    pub fn get(&self, key: &str) -> Option<&SetupNode> {
+        match self {
+            SetupNode::Group(g) => g.get(key),
+            SetupNode::Value(_) => None,
+        }
+    }
+
+    /// The setting's value, if this is a setting.
+    pub fn value(&self) -> Option<&str> {
+        match self {
+            SetupNode::Value(v) => Some(v),
+            SetupNode::Group(_) => None,
+        }
+    }
+
+    fn flatten_into(&self, prefix: &str, out: &mut BTreeMap<String, String>) {
+        match self {
+            SetupNode::Value(v) => {
+                out.insert(prefix.to_owned(), v.clone());
+            }
+            SetupNode::Group(g) => {
+                for (key, node) in g {
+                    node.flatten_into(&format!("{}.{}", prefix, key), out);
+                }
+            }
+        }
+    }
+}
+
+impl CarSetup {
+    ///
+    /// Look up a node by dotted path, e.g. `Chassis.LeftFront.Camber`.
+    pub fn get(&self, path: &str) -> Option<&SetupNode> {
+        let mut parts = path.split('.');
+        let mut node = self.sections.get(parts.next()?)?;
+
+        for part in parts {
+            node = node.get(part)?;
+        }
+
+        Some(node)
+    }
+
+    /// The tire section (`TiresAero` or `Tires`).
+    pub fn tires(&self) -> Option<&SetupNode> {
+        self.sections
+            .get("TiresAero")
+            .or_else(|| self.sections.get("Tires"))
+    }
+
+    pub fn chassis(&self) -> Option<&SetupNode> {
+        self.sections.get("Chassis")
+    }
+
+    pub fn drivetrain(&self) -> Option<&SetupNode> {
+        self.sections.get("Drivetrain")
+    }
+
+    ///
+    /// Every setting in the setup, keyed by dotted path.
+    pub fn settings(&self) -> BTreeMap<String, String> {
+        let mut out = BTreeMap::new();
+
+        for (key, node) in &self.sections {
+            node.flatten_into(key, &mut out);
+        }
+
+        out
+    }
+}
+
+impl Setup {
+    ///
+    /// The player's current setup from the session info.
+    ///
+    /// Returns None if the session has no `CarSetup` section (e.g. when spectating).
+    pub fn from_session(details: &SessionDetails) -> Option<Self> {
+        Some(Setup {
+            name: details.drivers.setup_name.clone(),
+            modified: details.drivers.setup_is_modified != 0,
+            car: details.car_setup.clone()?,
+        })
+    }
+
+    ///
+    /// Settings which differ between this setup and another, ordered by path.
+    pub fn diff(&self, other: &Setup) -> Vec<SetupChange> {
+        let before = self.car.settings();
+        let mut after = other.car.settings();
+        let mut changes = Vec::new();
+
+        for (path, value) in before {
+            match after.remove(&path) {
+                Some(ref v) if *v == value => {}
+                new => changes.push(SetupChange {
+                    delta: new.as_deref().and_then(|n| delta(&value, n)),
+                    path,
+                    before: Some(value),
+                    after: new,
+                }),
+            }
+        }
+
+        for (path, value) in after {
+            changes.push(SetupChange {
+                path,
+                before: None,
+                after: Some(value),
+                delta: None,
+            });
+        }
+
+        changes.sort_by(|a, b| a.path.cmp(&b.path));
+        changes
+    }
+}
+
+/// Split a garage value such as "-3.5 deg" into its number and unit.
+fn split_value(value: &str) -> Option<(f64, &str)> {
+    let value = value.trim();
+    let end = value
+        .find(|c: char| !(c.is_ascii_digit() || c == '.' || c == '-' || c == '+'))
+        .unwrap_or(value.len());
+
+    let number = value[..end].parse().ok()?;
+    Some((number, value[end..].trim()))
+}
+
+fn delta(before: &str, after: &str) -> Option<f64> {
+    let (a, unit_a) = split_value(before)?;
+    let (b, unit_b) = split_value(after)?;
+
+    if unit_a == unit_b {
+        Some(b - a)
+    } else {
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn diff_setups() {
+        let content = std::fs::read_to_string("./session.yaml").unwrap();
+        let session: SessionDetails = serde_yaml::from_str(&content).unwrap();
+
+        let old = Setup::from_session(&session).unwrap();
+        assert_eq!(old.car.update_count, 2);
+        assert_eq!(
+            old.car.get("Chassis.LeftFront.Camber").unwrap().value(),
+            Some("-3.5 deg")
+        );
+        assert_eq!(
+            old.car.get("Chassis.Front.ArbSetting").unwrap().value(),
+            Some("3")
+        );
+        assert!(old.car.tires().is_some());
+        assert!(old.diff(&old).is_empty());
+
+        let mut new = old.clone();
+        if let Some(SetupNode::Group(chassis)) = new.car.sections.get_mut("Chassis") {
+            if let Some(SetupNode::Group(lf)) = chassis.get_mut("LeftFront") {
+                lf.insert(
+                    String::from("Camber"),
+                    SetupNode::Value(String::from("-3.0 deg")),
+                );
+            }
+        }
+
+        let changes = old.diff(&new);
+        assert_eq!(changes.len(), 1);
+        assert_eq!(changes[0].path, "Chassis.LeftFront.Camber");
+        assert_eq!(changes[0].delta, Some(0.5));
+    }
+}
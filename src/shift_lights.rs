@@ -0,0 +1,167 @@
+use crate::session::DriverInfo;
+use serde::{Deserialize, Serialize};
+
+#[cfg(feature = "telemetry")]
+use crate::telemetry::Sample;
+#[cfg(feature = "telemetry")]
+use std::convert::TryInto;
+#[cfg(feature = "telemetry")]
+use std::error::Error;
+
+///
+/// Shift Sample
+///
+/// Engine speed and gear at a point in time.
+#[derive(Debug, Copy, Clone, Default)]
+pub struct ShiftSample {
+    pub rpm: f32,  // Engine RPM
+    pub gear: i32, // -1 reverse, 0 neutral, 1.. forward gears
+}
+
+///
+/// State of a strip of shift lights.
+#[derive(Debug, Copy, Clone, PartialEq, Serialize, Deserialize)]
+pub struct LightState {
+    pub leds: usize,   // Number of LEDs in the strip
+    pub lit: usize,    // Number of LEDs lit, from the left
+    pub shift: bool,   // Engine has reached the shift point
+    pub blink: bool,   // Engine is past the blink point - the driver should have shifted already
+    pub fraction: f32, // Progress from the first light to the last light, 0.0 to 1.0
+}
+
+///
+/// Shift Lights
+///
+/// Computes rev-light states from the car's shift light RPM points, for
+/// rendering on external LED hardware.
+///
+/// # Examples
+///
+/// ```
+/// use iracing::shift_lights::ShiftLights;
+///
+/// let lights = ShiftLights::new(7000.0, 8000.0, 7800.0, 8200.0, 10);
+/// let state = lights.state(7400.0, 3);
+///
+/// let pattern: String = state.pattern(true).iter().map(|&on| if on { '*' } else { '.' }).collect();
+/// assert_eq!(pattern, "*****.....");
+/// ```
+#[derive(Debug, Copy, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ShiftLights {
+    pub first_rpm: f32, // First light
+    pub shift_rpm: f32, // Shift point
+    pub last_rpm: f32,  // All lights lit
+    pub blink_rpm: f32, // Lights blink
+    pub leds: usize,
+    pub top_gear: Option<i32>, // No shift indication is given in top gear
+}
+
+impl ShiftSample {
+    ///
+    /// Read a shift sample from a telemetry sample.
+    #[cfg(feature = "telemetry")]
+    pub fn from_sample(sample: &Sample) -> Result<Self, Box<dyn Error>> {
+        Ok(ShiftSample {
+            rpm: sample.get("RPM")?.try_into()?,
+            gear: sample.get("Gear")?.try_into()?,
+        })
+    }
+}
+
+impl LightState {
+    ///
+    /// On/off state of each LED, left to right.
+    ///
+    /// `blink_on` is the caller's blink phase; when blinking every LED follows it.
+    pub fn pattern(&self, blink_on: bool) -> Vec<bool> {
+        if self.blink {
+            return vec![blink_on; self.leds];
+        }
+
+        (0..self.leds).map(|i| i < self.lit).collect()
+    }
+}
+
+impl ShiftLights {
+    pub fn new(first_rpm: f32, shift_rpm: f32, last_rpm: f32, blink_rpm: f32, leds: usize) -> Self {
+        ShiftLights {
+            first_rpm,
+            shift_rpm,
+            last_rpm,
+            blink_rpm,
+            leds,
+            top_gear: None,
+        }
+    }
+
+    ///
+    /// Shift lights for the player's car, from the `DriverCarSL*` values in the session info.
+    pub fn from_driver_info(info: &DriverInfo, leds: usize) -> Self {
+        ShiftLights {
+            top_gear: info.forward_gears,
+            ..Self::new(
+                info.shift_light_first_rpm,
+                info.shift_light_shift_rpm,
+                info.shift_light_last_rpm,
+                info.shift_light_blink_rpm,
+                leds,
+            )
+        }
+    }
+
+    ///
+    /// Light state at a given RPM and gear.
+    pub fn state(&self, rpm: f32, gear: i32) -> LightState {
+        let range = self.last_rpm - self.first_rpm;
+        let fraction = if range > 0.0 {
+            ((rpm - self.first_rpm) / range).clamp(0.0, 1.0)
+        } else if rpm >= self.first_rpm {
+            1.0
+        } else {
+            0.0
+        };
+
+        let can_shift = gear > 0 && !matches!(self.top_gear, Some(top) if gear >= top);
+
+        LightState {
+            leds: self.leds,
+            lit: (fraction * self.leds as f32).round() as usize,
+            shift: can_shift && rpm >= self.shift_rpm,
+            blink: can_shift && self.blink_rpm > 0.0 && rpm >= self.blink_rpm,
+            fraction,
+        }
+    }
+
+    /// Light state for a telemetry sample.
+    pub fn state_for(&self, sample: &ShiftSample) -> LightState {
+        self.state(sample.rpm, sample.gear)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::session::SessionDetails;
+
+    #[test]
+    fn light_states() {
+        let content = std::fs::read_to_string("./session.yaml").unwrap();
+        let session: SessionDetails = serde_yaml::from_str(&content).unwrap();
+        let lights = ShiftLights::from_driver_info(&session.drivers, 8);
+
+        assert_eq!(lights.state(6000.0, 3).lit, 0);
+        assert_eq!(lights.state(7400.0, 3).lit, 4);
+
+        let state = lights.state(8100.0, 3);
+        assert_eq!(state.lit, 8);
+        assert!(state.shift && !state.blink);
+
+        let state = lights.state(8300.0, 3);
+        assert!(state.blink);
+        assert_eq!(state.pattern(false), vec![false; 8]);
+
+        // No shift indication in top gear
+        let state = lights.state(8300.0, 6);
+        assert!(!state.shift && !state.blink);
+    }
+}
@@ -1,5 +1,15 @@
-use std::io::{Read, Write};
-use std::net::TcpStream;
+use std::io::{self, Read, Write};
+use std::net::{IpAddr, Ipv4Addr, SocketAddr, TcpStream, ToSocketAddrs, UdpSocket};
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+use std::thread::{self, JoinHandle};
+use std::time::{Duration, Instant};
+
+/// Most hosts probed at once by a subnet scan.
+const PROBE_WORKERS: usize = 32;
+
+/// Discovery query, answered by advertisers with this followed by their port.
+const DISCOVERY_MAGIC: &[u8] = b"iracing.rs forwarder";
 
 ///
 /// Simulation instance.
@@ -26,7 +36,22 @@ impl Simulation {
     /// The default path to retrieve sim status
     pub const SIM_STATUS_PATH: &str = "/get_sim_status?object=simStatus";
 
+    /// How long a status check waits on each of connecting, sending and reading.
+    pub const STATUS_TIMEOUT: Duration = Duration::from_secs(2);
+
+    /// The UDP port forwarders answer discovery queries on, see `advertise`.
+    pub const DISCOVERY_PORT: u16 = 32035;
+
+    ///
+    /// Address of the sim status port.
+    ///
+    /// A host which already includes a port, such as a forwarder at
+    /// "192.168.5.125:40000", is used as is.
     pub fn host_uri(&self) -> String {
+        if self.host.parse::<SocketAddr>().is_ok() {
+            return self.host.clone();
+        }
+
         format!("{}:{}", self.host, Self::PORT)
     }
 
@@ -40,13 +65,35 @@ impl Simulation {
     /// Makes a request to {self.host}:{PORT}/{SIM_STATUS_PATH} to retrieve
     /// the sim status and returns true if connected, false otherwise.
     pub fn check_status(&self) -> bool {
-        let mut stream = match TcpStream::connect(self.host_uri()) {
+        self.check_status_timeout(Self::STATUS_TIMEOUT)
+    }
+
+    ///
+    /// Checks if the sim is running, giving up on a host that takes longer
+    /// than `timeout` to connect, accept the request or answer it.
+    pub fn check_status_timeout(&self, timeout: Duration) -> bool {
+        let addr = match self.host_uri().to_socket_addrs().map(|mut a| a.next()) {
+            Ok(Some(addr)) => addr,
+            _ => {
+                println!("Failed to resolve iRacing sim client: {}", self.host);
+                return false;
+            }
+        };
+
+        let mut stream = match TcpStream::connect_timeout(&addr, timeout) {
             Ok(s) => s,
             Err(e) => {
                 println!("Failed to connect to iRacing sim client: {}", e);
                 return false;
             }
         };
+        if let Err(e) = stream
+            .set_read_timeout(Some(timeout))
+            .and_then(|_| stream.set_write_timeout(Some(timeout)))
+        {
+            println!("Failed to set timeouts: {}", e);
+            return false;
+        }
 
         // Raw HTTP request string
         let http_request = format!(
@@ -71,6 +118,262 @@ impl Simulation {
 
         response.contains("running:1")
     }
+
+    ///
+    /// Find running simulations on the local network.
+    ///
+    /// Scans this machine and the /24 subnet of its primary interface for
+    /// hosts answering on the sim status port, then asks for forwarders
+    /// advertised with `advertise`. See `discover_subnet` and
+    /// `discover_forwarders`.
+    pub fn discover() -> Vec<Simulation> {
+        let timeout = Duration::from_millis(250);
+        let mut hosts = vec![IpAddr::V4(Ipv4Addr::LOCALHOST)];
+
+        if let Some(IpAddr::V4(local)) = local_address() {
+            hosts.extend(subnet(local).map(IpAddr::V4));
+        }
+
+        let addrs: Vec<SocketAddr> = hosts
+            .into_iter()
+            .map(|ip| SocketAddr::new(ip, Self::PORT))
+            .collect();
+        let mut found = Self::probe(&addrs, timeout);
+
+        for sim in Self::discover_forwarders(timeout) {
+            if !found.iter().any(|f| f.host == sim.host) {
+                found.push(sim);
+            }
+        }
+        found
+    }
+
+    ///
+    /// Find running simulations on the /24 subnet containing `address`.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use iracing::simulation::Simulation;
+    /// use std::time::Duration;
+    ///
+    /// for sim in Simulation::discover_subnet("192.168.5.0".parse().unwrap(), Duration::from_millis(250)) {
+    ///     println!("Found sim at {}", sim.host);
+    /// }
+    /// ```
+    pub fn discover_subnet(address: Ipv4Addr, timeout: Duration) -> Vec<Simulation> {
+        let addrs: Vec<SocketAddr> = subnet(address)
+            .map(|ip| SocketAddr::new(IpAddr::V4(ip), Self::PORT))
+            .collect();
+        Self::probe(&addrs, timeout)
+    }
+
+    ///
+    /// Find forwarders on the local network advertised with `advertise`.
+    ///
+    /// Broadcasts a query to `DISCOVERY_PORT`, waits `timeout` for answers,
+    /// then checks each forwarder reports a running sim.
+    pub fn discover_forwarders(timeout: Duration) -> Vec<Simulation> {
+        let targets = [
+            SocketAddr::new(IpAddr::V4(Ipv4Addr::BROADCAST), Self::DISCOVERY_PORT),
+            SocketAddr::new(IpAddr::V4(Ipv4Addr::LOCALHOST), Self::DISCOVERY_PORT),
+        ];
+
+        match query_forwarders(&targets, timeout) {
+            Ok(addrs) => Self::probe(&addrs, timeout),
+            Err(e) => {
+                println!("Failed to query forwarders: {}", e);
+                Vec::new()
+            }
+        }
+    }
+
+    ///
+    /// Advertise a forwarder serving the sim status on `port` of this
+    /// machine, so `discover` finds it.
+    ///
+    /// Queries are answered until the returned `Advertiser` is dropped. Only
+    /// one forwarder on a machine can advertise at a time.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use iracing::simulation::Simulation;
+    ///
+    /// let _advertiser = Simulation::advertise(40000).expect("Discovery port in use");
+    /// // Forward the sim status on port 40000...
+    /// ```
+    pub fn advertise(port: u16) -> io::Result<Advertiser> {
+        Advertiser::bind(
+            SocketAddr::new(IpAddr::V4(Ipv4Addr::UNSPECIFIED), Self::DISCOVERY_PORT),
+            port,
+        )
+    }
+
+    ///
+    /// Check each address on a few worker threads, returning those with a
+    /// running sim in the order given.
+    fn probe(addrs: &[SocketAddr], timeout: Duration) -> Vec<Simulation> {
+        let next = AtomicUsize::new(0);
+        let found = Mutex::new(Vec::new());
+
+        thread::scope(|scope| {
+            for _ in 0..PROBE_WORKERS.min(addrs.len()) {
+                scope.spawn(|| loop {
+                    let idx = next.fetch_add(1, Ordering::Relaxed);
+                    let addr = match addrs.get(idx) {
+                        Some(addr) => *addr,
+                        None => break,
+                    };
+
+                    if let Some(sim) = Self::probe_host(addr, timeout) {
+                        found.lock().unwrap().push((idx, sim));
+                    }
+                });
+            }
+        });
+
+        let mut found = found.into_inner().unwrap();
+        found.sort_by_key(|(idx, _)| *idx);
+        found.into_iter().map(|(_, sim)| sim).collect()
+    }
+
+    /// The sim at `addr`, if it answers within `timeout` and is running.
+    fn probe_host(addr: SocketAddr, timeout: Duration) -> Option<Simulation> {
+        TcpStream::connect_timeout(&addr, timeout).ok()?;
+
+        let host = if addr.port() == Self::PORT {
+            addr.ip().to_string()
+        } else {
+            addr.to_string()
+        };
+        let sim = Simulation { host };
+        if sim.check_status_timeout(timeout) {
+            Some(sim)
+        } else {
+            None
+        }
+    }
+}
+
+///
+/// Advertiser
+///
+/// Answers discovery queries for a forwarder, until dropped. Created by
+/// `Simulation::advertise`.
+#[derive(Debug)]
+pub struct Advertiser {
+    addr: SocketAddr,
+    stop: Arc<AtomicBool>,
+    thread: Option<JoinHandle<()>>,
+}
+
+impl Advertiser {
+    /// How often the answering thread checks whether it should stop.
+    const POLL_INTERVAL: Duration = Duration::from_millis(100);
+
+    fn bind(addr: SocketAddr, port: u16) -> io::Result<Self> {
+        let socket = UdpSocket::bind(addr)?;
+        socket.set_read_timeout(Some(Self::POLL_INTERVAL))?;
+        let addr = socket.local_addr()?;
+
+        let stop = Arc::new(AtomicBool::new(false));
+        let stopped = stop.clone();
+        let thread = thread::spawn(move || {
+            let mut reply = DISCOVERY_MAGIC.to_vec();
+            reply.extend_from_slice(&port.to_be_bytes());
+
+            let mut buf = [0u8; 64];
+            while !stopped.load(Ordering::Relaxed) {
+                match socket.recv_from(&mut buf) {
+                    Ok((n, from)) if &buf[..n] == DISCOVERY_MAGIC => {
+                        let _ = socket.send_to(&reply, from);
+                    }
+                    _ => {}
+                }
+            }
+        });
+
+        Ok(Advertiser {
+            addr,
+            stop,
+            thread: Some(thread),
+        })
+    }
+
+    /// The address queries are answered on.
+    pub fn local_addr(&self) -> SocketAddr {
+        self.addr
+    }
+}
+
+impl Drop for Advertiser {
+    fn drop(&mut self) {
+        self.stop.store(true, Ordering::Relaxed);
+        if let Some(thread) = self.thread.take() {
+            let _ = thread.join();
+        }
+    }
+}
+
+///
+/// Send a discovery query to each of `targets`, returning the forwarder
+/// addresses answering within `timeout`.
+fn query_forwarders(targets: &[SocketAddr], timeout: Duration) -> io::Result<Vec<SocketAddr>> {
+    let socket = UdpSocket::bind("0.0.0.0:0")?;
+    socket.set_broadcast(true)?;
+    for target in targets {
+        // The broadcast may be refused without a network, localhost still works
+        let _ = socket.send_to(DISCOVERY_MAGIC, target);
+    }
+
+    let deadline = Instant::now() + timeout;
+    let mut found = Vec::new();
+    let mut buf = [0u8; 64];
+    loop {
+        let remaining = deadline.saturating_duration_since(Instant::now());
+        if remaining.is_zero() {
+            break;
+        }
+        socket.set_read_timeout(Some(remaining))?;
+
+        let (n, from) = match socket.recv_from(&mut buf) {
+            Ok(received) => received,
+            Err(e)
+                if matches!(
+                    e.kind(),
+                    io::ErrorKind::WouldBlock | io::ErrorKind::TimedOut
+                ) =>
+            {
+                break
+            }
+            Err(e) => return Err(e),
+        };
+
+        let reply = &buf[..n];
+        if reply.len() == DISCOVERY_MAGIC.len() + 2 && reply.starts_with(DISCOVERY_MAGIC) {
+            let port = u16::from_be_bytes([reply[n - 2], reply[n - 1]]);
+            let addr = SocketAddr::new(from.ip(), port);
+            if !found.contains(&addr) {
+                found.push(addr);
+            }
+        }
+    }
+
+    Ok(found)
+}
+
+/// Address of the interface used for outgoing traffic. No packets are sent.
+fn local_address() -> Option<IpAddr> {
+    let socket = UdpSocket::bind("0.0.0.0:0").ok()?;
+    socket.connect("192.0.2.1:80").ok()?;
+    Some(socket.local_addr().ok()?.ip())
+}
+
+/// Every host address in the /24 subnet containing `address`.
+fn subnet(address: Ipv4Addr) -> impl Iterator<Item = Ipv4Addr> {
+    let [a, b, c, _] = address.octets();
+    (1..=254).map(move |d| Ipv4Addr::new(a, b, c, d))
 }
 
 #[cfg(test)]
@@ -85,4 +388,63 @@ mod tests {
 
         assert!(sim.check_status())
     }
+
+    #[test]
+    fn probe_hosts() {
+        use std::net::TcpListener;
+
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let port = listener.local_addr().unwrap().port();
+
+        thread::spawn(move || {
+            for stream in listener.incoming().take(2) {
+                let mut stream = stream.unwrap();
+                let mut buf = [0; 512];
+                let _ = stream.read(&mut buf);
+                let _ = stream.write_all(b"HTTP/1.1 200 OK\r\n\r\nrunning:1");
+            }
+        });
+
+        let addrs = [SocketAddr::new(IpAddr::V4(Ipv4Addr::LOCALHOST), port)];
+        let found = Simulation::probe(&addrs, Duration::from_millis(250));
+
+        assert_eq!(found.len(), 1);
+        assert_eq!(found[0].host, format!("127.0.0.1:{}", port));
+        assert_eq!(subnet("10.0.4.17".parse().unwrap()).count(), 254);
+    }
+
+    #[test]
+    fn probe_unresponsive_host() {
+        use std::net::TcpListener;
+        use std::time::Instant;
+
+        // Accepts connections but never answers
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let port = listener.local_addr().unwrap().port();
+
+        let start = Instant::now();
+        let addrs = [SocketAddr::new(IpAddr::V4(Ipv4Addr::LOCALHOST), port)];
+        assert!(Simulation::probe(&addrs, Duration::from_millis(100)).is_empty());
+        assert!(start.elapsed() < Duration::from_secs(2));
+
+        // A sweep of closed ports is spread over a few workers
+        let closed: Vec<SocketAddr> = (0..100)
+            .map(|_| SocketAddr::new(IpAddr::V4(Ipv4Addr::LOCALHOST), 1))
+            .collect();
+        assert!(Simulation::probe(&closed, Duration::from_millis(100)).is_empty());
+        drop(listener);
+    }
+
+    #[test]
+    fn advertise_forwarder() {
+        let advertiser = Advertiser::bind("127.0.0.1:0".parse().unwrap(), 40000).unwrap();
+
+        let found = query_forwarders(&[advertiser.local_addr()], Duration::from_millis(250));
+        assert_eq!(
+            found.unwrap(),
+            vec!["127.0.0.1:40000".parse::<SocketAddr>().unwrap()]
+        );
+
+        drop(advertiser);
+    }
 }
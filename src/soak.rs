@@ -0,0 +1,427 @@
+use crate::ibt::IbtError;
+use crate::pits::{PitEvent, PitLane, PitSample};
+use crate::republish::Layout;
+use crate::results::{Results, Standing};
+use crate::telemetry::Sample;
+use crate::validity;
+use serde::{Deserialize, Serialize};
+use std::convert::TryInto;
+use std::error::Error;
+use std::panic::{self, AssertUnwindSafe};
+use std::time::{Duration, Instant};
+
+/// Channels written to the republished block by the soak test.
+const EXPORT_CHANNELS: [(&str, usize); 4] = [
+    ("SessionTime", 1),
+    ("Lap", 1),
+    ("Speed", 1),
+    ("CarIdxLapDistPct", 64),
+];
+
+///
+/// An invariant broken during a soak test.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Violation {
+    pub sample: usize, // Index of the sample in the run
+    pub session_time: f64,
+    pub message: String,
+}
+
+///
+/// Outcome of a soak test.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct SoakReport {
+    pub samples: usize,
+    pub sessions: usize, // Sessions seen, counting each time session time goes backwards
+    pub laps: usize,     // Laps started by the player
+    pub pit_entries: usize, // Player pit lane entries
+    pub pit_exits: usize, // Player pit lane exits
+    pub car_pit_entries: usize, // Pit road entries of every car
+    pub car_pit_exits: usize, // Pit road exits of every car
+    pub exports: usize,  // Times standings were exported
+    pub exported_bytes: usize, // Bytes written by the exporters
+    pub elapsed: Duration, // Wall clock time taken
+    pub violations: Vec<Violation>,
+}
+
+///
+/// Soak Test
+///
+/// Runs recorded telemetry through the derived subsystems as fast as possible:
+/// pit lane events, live track order standings and the JSON, CSV and
+/// republished block exporters. Checks invariants as it goes, to catch
+/// regressions which only show up over long sessions:
+///
+/// - no stage panics
+/// - the player's lap never goes backwards within a session, a new session
+///   starting whenever session time goes backwards
+/// - pit lane entries and exits balance
+/// - standings positions are unique and contiguous
+/// - exports round trip
+///
+/// Broken invariants are collected as violations rather than stopping the run.
+///
+/// # Examples
+///
+/// ```no_run
+/// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+/// use iracing::ibt::IBT;
+/// use iracing::results::Results;
+/// use iracing::soak::Soak;
+///
+/// let ibt = IBT::open("./long_race.ibt")?;
+/// let results = Results::from_session(&ibt.session_info()?, 2);
+///
+/// let report = Soak::new().with_results(results).run(ibt.samples());
+/// assert!(report.is_ok(), "{:#?}", report.violations);
+/// println!("{:.0} samples/s", report.samples_per_second());
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Debug, Clone)]
+pub struct Soak {
+    results: Option<Results>,
+    export_interval: usize,
+}
+
+/// State carried across samples within a session.
+#[derive(Debug, Default)]
+struct Session {
+    pits: PitLane,
+    last_time: Option<f64>,
+    last_lap: Option<i32>,
+    on_pit_road: Vec<bool>,
+    in_pit_lane: bool,
+    entries: usize,
+    exits: usize,
+}
+
+impl SoakReport {
+    /// True if no invariants were broken.
+    pub fn is_ok(&self) -> bool {
+        self.violations.is_empty()
+    }
+
+    /// Rate samples were processed at.
+    pub fn samples_per_second(&self) -> f64 {
+        self.samples as f64 / self.elapsed.as_secs_f64().max(f64::EPSILON)
+    }
+}
+
+impl Default for Soak {
+    fn default() -> Self {
+        Soak {
+            results: None,
+            export_interval: 60,
+        }
+    }
+}
+
+impl Soak {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    ///
+    /// Standings to reorder by track position and export, such as those from
+    /// `Results::from_session`.
+    pub fn with_results(mut self, results: Option<Results>) -> Self {
+        self.results = results;
+        self
+    }
+
+    /// Export the standings every this many samples, 60 by default.
+    pub fn with_export_interval(mut self, samples: usize) -> Self {
+        self.export_interval = samples.max(1);
+        self
+    }
+
+    ///
+    /// Run every sample through the pipeline, returning the report.
+    pub fn run<I>(&self, samples: I) -> SoakReport
+    where
+        I: IntoIterator<Item = Result<Sample, IbtError>>,
+    {
+        let started = Instant::now();
+        let mut report = SoakReport::default();
+        let mut session = Session::default();
+        let mut standings: Vec<Standing> = self
+            .results
+            .as_ref()
+            .map(|r| r.standings.clone())
+            .unwrap_or_default();
+
+        for (idx, sample) in samples.into_iter().enumerate() {
+            report.samples += 1;
+
+            let sample = match sample {
+                Ok(sample) => sample,
+                Err(e) => {
+                    report.violate(idx, 0.0, format!("Unreadable sample: {}", e));
+                    continue;
+                }
+            };
+
+            let outcome = panic::catch_unwind(AssertUnwindSafe(|| {
+                self.step(idx, &sample, &mut session, &mut standings, &mut report)
+            }));
+            match outcome {
+                Ok(Ok(())) => {}
+                Ok(Err(e)) => report.violate(idx, session.time(), e.to_string()),
+                Err(cause) => {
+                    let message = cause
+                        .downcast_ref::<&str>()
+                        .map(|s| s.to_string())
+                        .or_else(|| cause.downcast_ref::<String>().cloned())
+                        .unwrap_or_default();
+                    report.violate(idx, session.time(), format!("Panicked: {}", message));
+                }
+            }
+        }
+
+        session.finish(report.samples, &mut report);
+        report.elapsed = started.elapsed();
+        report
+    }
+
+    /// Run one sample through every stage.
+    fn step(
+        &self,
+        idx: usize,
+        sample: &Sample,
+        session: &mut Session,
+        standings: &mut [Standing],
+        report: &mut SoakReport,
+    ) -> Result<(), Box<dyn Error>> {
+        let session_time: f64 = sample.get("SessionTime")?.try_into()?;
+
+        // A new session, from a restarted or concatenated recording
+        if session.last_time.is_none_or(|t| session_time < t) {
+            session.finish(idx, report);
+            *session = Session::default();
+            report.sessions += 1;
+        }
+        session.last_time = Some(session_time);
+
+        let pit_sample = PitSample::from_sample(sample)?;
+        if let Some(last) = session.last_lap {
+            if pit_sample.lap < last {
+                report.violate(
+                    idx,
+                    session_time,
+                    format!("Lap went backwards from {} to {}", last, pit_sample.lap),
+                );
+            }
+            if pit_sample.lap > last {
+                report.laps += (pit_sample.lap - last) as usize;
+            }
+        }
+        session.last_lap = Some(pit_sample.lap);
+
+        for event in session.pits.update(&pit_sample) {
+            match event {
+                PitEvent::Entered { .. } => {
+                    if session.in_pit_lane {
+                        report.violate(idx, session_time, "Entered pit lane twice".to_string());
+                    }
+                    session.in_pit_lane = true;
+                    session.entries += 1;
+                    report.pit_entries += 1;
+                }
+                PitEvent::Exited(_) => {
+                    if !session.in_pit_lane {
+                        report.violate(
+                            idx,
+                            session_time,
+                            "Exited pit lane without entering".to_string(),
+                        );
+                    }
+                    session.in_pit_lane = false;
+                    session.exits += 1;
+                    report.pit_exits += 1;
+                }
+                _ => {}
+            }
+        }
+
+        // Pit road transitions of every car
+        let on_pit_road: Vec<bool> = if sample.has("CarIdxOnPitRoad") {
+            sample.get("CarIdxOnPitRoad")?.into()
+        } else {
+            Vec::new()
+        };
+        for (car_idx, on) in on_pit_road.iter().enumerate() {
+            match session.on_pit_road.get(car_idx) {
+                Some(false) if *on => report.car_pit_entries += 1,
+                Some(true) if !*on => report.car_pit_exits += 1,
+                _ => {}
+            }
+        }
+        session.on_pit_road = on_pit_road;
+
+        // Standings in track order
+        let lap_dist_pct: Vec<f32> = if sample.has("CarIdxLapDistPct") {
+            sample.get("CarIdxLapDistPct")?.try_into()?
+        } else {
+            Vec::new()
+        };
+        reorder(standings, &lap_dist_pct);
+        let mut positions: Vec<u32> = standings.iter().map(|s| s.position).collect();
+        positions.sort_unstable();
+        if positions
+            .iter()
+            .enumerate()
+            .any(|(i, p)| *p != i as u32 + 1)
+        {
+            report.violate(
+                idx,
+                session_time,
+                format!("Positions not contiguous: {:?}", positions),
+            );
+        }
+
+        if idx.is_multiple_of(self.export_interval) {
+            self.export(idx, sample, session_time, standings, report)?;
+        }
+
+        Ok(())
+    }
+
+    /// Export the standings, checking the republished block round trips.
+    fn export(
+        &self,
+        idx: usize,
+        sample: &Sample,
+        session_time: f64,
+        standings: &[Standing],
+        report: &mut SoakReport,
+    ) -> Result<(), Box<dyn Error>> {
+        if let Some(results) = self.results.as_ref() {
+            let results = Results {
+                standings: standings.to_vec(),
+                ..results.clone()
+            };
+            report.exported_bytes += results.to_json()?.len();
+            report.exported_bytes += Results::to_csv(standings).len();
+        }
+
+        let mut layout = Layout::new().with_standings(standings.len());
+        let mut values = Vec::new();
+        for (name, count) in EXPORT_CHANNELS.iter() {
+            layout = layout.with_channel(name, *count);
+            values.push(match sample.get(name) {
+                Ok(value) => channel_values(value),
+                Err(_) => Vec::new(),
+            });
+        }
+
+        let mut block = vec![0; layout.size()];
+        layout.encode(
+            &mut block,
+            report.exports as u32 * 2,
+            session_time,
+            &values,
+            standings,
+        );
+        let snapshot = Layout::decode(&block)?;
+        if snapshot.session_time != session_time || snapshot.standings.len() != standings.len() {
+            report.violate(
+                idx,
+                session_time,
+                "Republished block didn't round trip".to_string(),
+            );
+        }
+
+        report.exports += 1;
+        report.exported_bytes += block.len();
+        Ok(())
+    }
+}
+
+impl SoakReport {
+    fn violate(&mut self, sample: usize, session_time: f64, message: String) {
+        self.violations.push(Violation {
+            sample,
+            session_time,
+            message,
+        });
+    }
+}
+
+impl Session {
+    fn time(&self) -> f64 {
+        self.last_time.unwrap_or(0.0)
+    }
+
+    /// Check the pit lane entries and exits of a session balance.
+    fn finish(&self, idx: usize, report: &mut SoakReport) {
+        if self.entries != self.exits + self.in_pit_lane as usize {
+            report.violate(
+                idx,
+                self.time(),
+                format!("{} pit lane entries but {} exits", self.entries, self.exits),
+            );
+        }
+    }
+}
+
+/// Number standings by track position, leaving cars not on track at the back.
+fn reorder(standings: &mut [Standing], lap_dist_pct: &[f32]) {
+    let progress = |s: &Standing| {
+        let pct = lap_dist_pct
+            .get(s.car_idx)
+            .copied()
+            .and_then(validity::lap_dist_pct);
+        pct.map(|p| s.laps_complete as f32 + p)
+    };
+
+    standings.sort_by(|a, b| {
+        progress(b)
+            .partial_cmp(&progress(a))
+            .unwrap_or(std::cmp::Ordering::Equal)
+            .then(a.position.cmp(&b.position))
+    });
+    for (i, standing) in standings.iter_mut().enumerate() {
+        standing.position = i as u32 + 1;
+    }
+}
+
+/// A channel's values as f64, for the republished block.
+fn channel_values(value: crate::telemetry::Value) -> Vec<f64> {
+    if let Ok(v) = TryInto::<f64>::try_into(value.clone()) {
+        return vec![v];
+    }
+    if let Ok(v) = TryInto::<i32>::try_into(value.clone()) {
+        return vec![v as f64];
+    }
+    if let Ok(v) = TryInto::<Vec<f32>>::try_into(value) {
+        return v.into_iter().map(|v| v as f64).collect();
+    }
+    Vec::new()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ibt::IBT;
+    use crate::session::SessionDetails;
+
+    #[test]
+    fn soak_recorded_sessions() {
+        let ibt = IBT::open("./telemetry.ibt").unwrap();
+        let content = std::fs::read_to_string("./session.yaml").unwrap();
+        let session: SessionDetails = serde_yaml::from_str(&content).unwrap();
+        let results = Results::from_session(&session, 2);
+
+        // The recording played back to back fifty times
+        let samples = (0..50).flat_map(|_| ibt.samples());
+        let report = Soak::new().with_results(results).run(samples);
+
+        assert!(report.is_ok(), "{:#?}", report.violations);
+        assert_eq!(report.samples, 50 * ibt.len());
+        assert_eq!(report.sessions, 50);
+        assert_eq!(report.laps, 50);
+        assert_eq!(report.exports, 100);
+        assert!(report.exported_bytes > 0);
+    }
+}
@@ -0,0 +1,393 @@
+use crate::classes::Classes;
+use crate::states::CarLeftRight;
+use crate::validity;
+use serde::{Deserialize, Serialize};
+
+#[cfg(feature = "telemetry")]
+use crate::telemetry::Sample;
+#[cfg(feature = "telemetry")]
+use std::convert::TryInto;
+#[cfg(feature = "telemetry")]
+use std::error::Error;
+
+///
+/// Spotter Sample
+///
+/// The player's surroundings and fuel state at a point in time.
+#[derive(Debug, Clone, Default)]
+pub struct SpotterSample {
+    pub session_time: f64,            // Seconds since session start
+    pub player_car_idx: usize,        // PlayerCarIdx
+    pub car_left_right: CarLeftRight, // CarLeftRight
+    pub lap: i32,                     // Lap the player is on
+    pub fuel_level: f32,              // Liters of fuel remaining
+    pub lap_dist_pct: Vec<f32>,       // CarIdxLapDistPct - -1 when the car isn't on track
+    pub est_time: Vec<f32>, // CarIdxEstTime - estimated time to reach the current location on track (s)
+    pub on_pit_road: Vec<bool>, // CarIdxOnPitRoad
+}
+
+///
+/// Something the spotter should tell the driver.
+#[derive(Debug, Copy, Clone, PartialEq, Serialize, Deserialize)]
+pub enum SpotterCue {
+    CarLeft,
+    CarRight,
+    ThreeWide,
+    TwoCarsLeft,
+    TwoCarsRight,
+
+    /// No longer alongside anyone
+    Clear,
+
+    /// Fuel for fewer than the configured number of laps
+    LowFuel {
+        laps: f32,
+    },
+
+    /// The pit window has opened
+    PitWindowOpen,
+
+    /// The last lap of the pit window has started
+    PitWindowClosing,
+
+    /// A car in a faster class is closing in from behind
+    FasterClassApproaching {
+        car_idx: usize,
+        gap: f32,
+    },
+}
+
+///
+/// How urgently a cue should be delivered, least urgent first.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+pub enum Priority {
+    Low,
+    Normal,
+    High,
+    Critical,
+}
+
+///
+/// A cue with its suggested priority.
+#[derive(Debug, Copy, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Cue {
+    pub session_time: f64,
+    pub cue: SpotterCue,
+    pub priority: Priority,
+}
+
+///
+/// Spotter
+///
+/// Turns the player's surroundings into a stream of spotter cues, ready to
+/// be read out by text-to-speech or mapped to sounds.
+///
+/// Proximity cues are only produced when the state alongside changes. Low fuel
+/// is called once a lap while it applies, and each approaching faster car is
+/// called once until it has passed or dropped back.
+///
+/// # Examples
+///
+/// ```
+/// use iracing::classes::Classes;
+/// use iracing::spotter::{Spotter, SpotterSample};
+/// # let session: iracing::session::SessionDetails =
+/// #     serde_yaml::from_str(&std::fs::read_to_string("./session.yaml").unwrap()).unwrap();
+/// # let sample = SpotterSample::default();
+///
+/// let classes = Classes::from_drivers(&session.drivers.other_drivers);
+/// let mut spotter = Spotter::new(classes, session.drivers.estimated_lap_time).with_pit_window(20, 30);
+///
+/// for cue in spotter.update(&sample) {
+///     println!("{:?}: {:?}", cue.priority, cue.cue);
+/// }
+/// ```
+#[derive(Debug, Clone)]
+pub struct Spotter {
+    classes: Classes,
+    lap_time: f32,
+    low_fuel_laps: f32,
+    fuel_per_lap: Option<f32>,
+    pit_window: Option<(i32, i32)>,
+    approach_gap: f32,
+
+    alongside: CarLeftRight,
+    lap: i32,
+    lap_start_fuel: Option<f32>,
+    used_per_lap: Vec<f32>,
+    approaching: Vec<usize>,
+}
+
+impl SpotterSample {
+    ///
+    /// Read a spotter sample from a telemetry sample.
+    #[cfg(feature = "telemetry")]
+    pub fn from_sample(sample: &Sample) -> Result<Self, Box<dyn Error>> {
+        let player_car_idx: i32 = sample.get("PlayerCarIdx")?.try_into()?;
+        let car_left_right: i32 = sample.get("CarLeftRight")?.try_into()?;
+
+        Ok(SpotterSample {
+            session_time: sample.get("SessionTime")?.try_into()?,
+            player_car_idx: player_car_idx.max(0) as usize,
+            car_left_right: car_left_right.into(),
+            lap: sample.get("Lap")?.try_into()?,
+            fuel_level: sample.get("FuelLevel")?.try_into()?,
+            lap_dist_pct: sample.get("CarIdxLapDistPct")?.try_into()?,
+            est_time: sample.get("CarIdxEstTime")?.try_into()?,
+            on_pit_road: sample.get("CarIdxOnPitRoad")?.into(),
+        })
+    }
+}
+
+impl Spotter {
+    ///
+    /// Create a spotter for the given classes and estimated lap time (s).
+    pub fn new(classes: Classes, lap_time: f32) -> Self {
+        Spotter {
+            classes,
+            lap_time,
+            low_fuel_laps: 2.0,
+            fuel_per_lap: None,
+            pit_window: None,
+            approach_gap: 3.0,
+            alongside: CarLeftRight::Off,
+            lap: -1,
+            lap_start_fuel: None,
+            used_per_lap: Vec::new(),
+            approaching: Vec::new(),
+        }
+    }
+
+    /// Laps of fuel remaining below which low fuel is called.
+    pub fn with_low_fuel_laps(mut self, laps: f32) -> Self {
+        self.low_fuel_laps = laps;
+        self
+    }
+
+    ///
+    /// Fuel used per lap (l).
+    ///
+    /// Without this, usage is averaged from the laps driven so far.
+    pub fn with_fuel_per_lap(mut self, liters: f32) -> Self {
+        self.fuel_per_lap = Some(liters);
+        self
+    }
+
+    /// First and last laps on which the player may pit.
+    pub fn with_pit_window(mut self, opens: i32, closes: i32) -> Self {
+        self.pit_window = Some((opens, closes));
+        self
+    }
+
+    /// Gap at which a faster car behind is called (s).
+    pub fn with_approach_gap(mut self, seconds: f32) -> Self {
+        self.approach_gap = seconds;
+        self
+    }
+
+    ///
+    /// Update with a new sample, returning any cues.
+    pub fn update(&mut self, sample: &SpotterSample) -> Vec<Cue> {
+        let mut cues = Vec::new();
+        let mut push = |cue: SpotterCue, priority: Priority| {
+            cues.push(Cue {
+                session_time: sample.session_time,
+                cue,
+                priority,
+            })
+        };
+
+        // Alongside
+        if sample.car_left_right != self.alongside {
+            let cue = match sample.car_left_right {
+                CarLeftRight::CarLeft => Some(SpotterCue::CarLeft),
+                CarLeftRight::CarRight => Some(SpotterCue::CarRight),
+                CarLeftRight::CarLeftRight => Some(SpotterCue::ThreeWide),
+                CarLeftRight::TwoCarsLeft => Some(SpotterCue::TwoCarsLeft),
+                CarLeftRight::TwoCarsRight => Some(SpotterCue::TwoCarsRight),
+                CarLeftRight::Clear if self.alongside != CarLeftRight::Off => {
+                    Some(SpotterCue::Clear)
+                }
+                _ => None,
+            };
+
+            if let Some(cue) = cue {
+                let priority = match cue {
+                    SpotterCue::Clear => Priority::High,
+                    _ => Priority::Critical,
+                };
+                push(cue, priority);
+            }
+            self.alongside = sample.car_left_right;
+        }
+
+        // Once a lap: fuel and pit window
+        if sample.lap > self.lap {
+            if let Some(start) = self.lap_start_fuel {
+                let used = start - sample.fuel_level;
+                if used > 0.0 && self.lap >= 0 {
+                    self.used_per_lap.push(used);
+                }
+            }
+            self.lap_start_fuel = Some(sample.fuel_level);
+
+            if let Some(per_lap) = self.fuel_per_lap() {
+                let laps = sample.fuel_level / per_lap;
+                if laps < self.low_fuel_laps {
+                    let priority = if laps < 1.0 {
+                        Priority::High
+                    } else {
+                        Priority::Normal
+                    };
+                    push(SpotterCue::LowFuel { laps }, priority);
+                }
+            }
+
+            if let Some((opens, closes)) = self.pit_window {
+                if sample.lap == opens {
+                    push(SpotterCue::PitWindowOpen, Priority::Normal);
+                } else if sample.lap == closes && closes > opens {
+                    push(SpotterCue::PitWindowClosing, Priority::High);
+                }
+            }
+
+            self.lap = sample.lap;
+        }
+
+        // Faster classes closing from behind
+        let player = sample.player_car_idx;
+        let player_speed = self
+            .classes
+            .for_car(player)
+            .map(|c| c.relative_speed)
+            .unwrap_or(0);
+
+        let mut approaching = Vec::new();
+        for class in self
+            .classes
+            .all()
+            .iter()
+            .filter(|c| c.relative_speed > player_speed)
+        {
+            for &car_idx in class.cars.iter() {
+                let gap = match self.gap_behind(sample, car_idx) {
+                    Some(gap) if gap <= self.approach_gap => gap,
+                    _ => continue,
+                };
+
+                if !self.approaching.contains(&car_idx) {
+                    push(
+                        SpotterCue::FasterClassApproaching { car_idx, gap },
+                        Priority::Low,
+                    );
+                }
+                approaching.push(car_idx);
+            }
+        }
+        self.approaching = approaching;
+
+        cues
+    }
+
+    /// Fuel used per lap, configured or averaged from the laps driven so far.
+    pub fn fuel_per_lap(&self) -> Option<f32> {
+        self.fuel_per_lap.or_else(|| {
+            if self.used_per_lap.is_empty() {
+                None
+            } else {
+                Some(self.used_per_lap.iter().sum::<f32>() / self.used_per_lap.len() as f32)
+            }
+        })
+    }
+
+    /// Time for a car to reach the player's position on track (s).
+    fn gap_behind(&self, sample: &SpotterSample, car_idx: usize) -> Option<f32> {
+        let player = sample.player_car_idx;
+        if car_idx == player || sample.on_pit_road.get(car_idx).copied().unwrap_or(false) {
+            return None;
+        }
+
+        let pct = |idx: usize| {
+            sample
+                .lap_dist_pct
+                .get(idx)
+                .copied()
+                .and_then(validity::lap_dist_pct)
+        };
+        let est = |idx: usize| sample.est_time.get(idx).copied().unwrap_or(0.0);
+
+        let mut gap = est(player) - est(car_idx);
+        if pct(player)? < pct(car_idx)? {
+            gap += self.lap_time;
+        }
+
+        Some(gap)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::session::SessionDetails;
+
+    fn session() -> SessionDetails {
+        let content = std::fs::read_to_string("./session.yaml").unwrap();
+        serde_yaml::from_str(&content).unwrap()
+    }
+
+    #[test]
+    fn spotter_cues() {
+        let classes = Classes::from_drivers(&session().drivers.other_drivers);
+        let mut spotter = Spotter::new(classes, 100.0)
+            .with_low_fuel_laps(2.0)
+            .with_pit_window(5, 8);
+
+        let mut sample = SpotterSample {
+            session_time: 0.0,
+            player_car_idx: 1,
+            car_left_right: CarLeftRight::Clear,
+            lap: 3,
+            fuel_level: 10.0,
+            lap_dist_pct: vec![-1.0, 0.5, 0.5, 0.9, 0.2, 0.4, 0.6],
+            est_time: vec![0.0, 50.0, 50.0, 90.0, 20.0, 40.0, 60.0],
+            on_pit_road: vec![false; 7],
+        };
+
+        assert!(spotter.update(&sample).is_empty());
+
+        sample.car_left_right = CarLeftRight::CarLeft;
+        let cues = spotter.update(&sample);
+        assert_eq!(cues[0].cue, SpotterCue::CarLeft);
+        assert_eq!(cues[0].priority, Priority::Critical);
+
+        sample.car_left_right = CarLeftRight::Clear;
+        assert_eq!(spotter.update(&sample)[0].cue, SpotterCue::Clear);
+
+        // 4l a lap with 6l left, and the pit window opens
+        sample.lap = 4;
+        sample.fuel_level = 6.0;
+        spotter.update(&sample);
+        sample.lap = 5;
+        sample.fuel_level = 2.0;
+        let cues = spotter.update(&sample);
+        assert_eq!(cues[0].cue, SpotterCue::LowFuel { laps: 0.5 });
+        assert_eq!(cues[1].cue, SpotterCue::PitWindowOpen);
+
+        // A GT3 car 2.5s behind, called once
+        sample.lap_dist_pct[4] = 0.48;
+        sample.est_time[4] = 47.5;
+        let cues = spotter.update(&sample);
+        assert_eq!(
+            cues,
+            vec![Cue {
+                session_time: 0.0,
+                cue: SpotterCue::FasterClassApproaching {
+                    car_idx: 4,
+                    gap: 2.5
+                },
+                priority: Priority::Low,
+            }]
+        );
+        assert!(spotter.update(&sample).is_empty());
+    }
+}
@@ -0,0 +1,288 @@
+use crate::states::Flags;
+use crate::validity;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+#[cfg(feature = "telemetry")]
+use crate::telemetry::Sample;
+#[cfg(feature = "telemetry")]
+use std::convert::TryInto;
+#[cfg(feature = "telemetry")]
+use std::error::Error;
+
+/// Throttle position counted as the driver reacting to the start
+const REACTION_THROTTLE: f32 = 0.9;
+
+/// Engine speed to road speed ratio above the gear's grip ratio counted as wheelspin
+const WHEELSPIN_THRESHOLD: f32 = 0.1;
+
+/// Below this speed the engine to road speed ratio is too noisy to use (m/s)
+const MIN_SPIN_SPEED: f32 = 2.0;
+
+///
+/// Start Sample
+///
+/// The player's car and the progress of every car around the lap at a point in
+/// time. Per-car values are indexed by car index.
+#[derive(Debug, Clone, Default)]
+pub struct StartSample {
+    pub session_time: f64,        // Seconds since session start
+    pub session_flags: Flags,     // SessionFlags
+    pub player_car_idx: usize,    // PlayerCarIdx
+    pub throttle: f32,            // Throttle - 0.0 to 1.0
+    pub rpm: f32,                 // RPM - engine speed (rev/min)
+    pub speed: f32,               // Speed - ground speed (m/s)
+    pub gear: i32,                // Gear - -1 reverse, 0 neutral
+    pub laps_completed: Vec<i32>, // CarIdxLapCompleted
+    pub lap_dist_pct: Vec<f32>,   // CarIdxLapDistPct - -1 when the car isn't on track
+}
+
+///
+/// How the player's start went.
+#[derive(Debug, Copy, Clone, PartialEq, Serialize, Deserialize)]
+pub struct StartReport {
+    pub green_time: f64,            // Session time the start was given
+    pub reaction_time: Option<f64>, // Time from the start to near full throttle (s)
+    pub wheelspin_time: f64,        // Time spent with the driven wheels spinning (s)
+    pub peak_wheelspin: f32,        // Most wheelspin, as a fraction of road speed
+    pub start_position: usize,      // Track position at the start
+    pub sector_position: usize,     // Track position at the end of the first sector
+    pub positions_gained: i32,      // Negative if positions were lost
+    pub sector_time: f64,           // Time from the start to the end of the first sector (s)
+}
+
+///
+/// Start Analyzer
+///
+/// Measures the player's start: reaction time to the green flag, wheelspin,
+/// and positions gained or lost by the end of the first sector.
+///
+/// Wheelspin is estimated from the ratio of engine speed to road speed in each
+/// gear, as telemetry doesn't include wheel speeds. The lowest ratio seen in a
+/// gear is taken as full grip and spin is counted while the ratio is more than
+/// 10% above it. Positions are track positions, from each car's distance
+/// around the lap.
+///
+/// # Examples
+///
+/// ```
+/// use iracing::starts::{StartAnalyzer, StartSample};
+///
+/// let mut analyzer = StartAnalyzer::new().with_sector_end(0.31);
+///
+/// if let Some(report) = analyzer.update(&StartSample::default()) {
+///     println!("Reaction {:?}, {:+} positions", report.reaction_time, report.positions_gained);
+/// }
+/// ```
+#[derive(Debug, Clone)]
+pub struct StartAnalyzer {
+    sector_end: f32,
+    green: bool,
+    start: Option<Launch>,
+    report: Option<StartReport>,
+}
+
+/// A start in progress.
+#[derive(Debug, Clone)]
+struct Launch {
+    green_time: f64,
+    target: f32,
+    start_position: usize,
+    reaction_time: Option<f64>,
+    last_time: f64,
+    ratios: HashMap<i32, f32>,
+    samples: Vec<(f64, i32, f32)>,
+}
+
+impl StartSample {
+    ///
+    /// Read a start sample from a telemetry sample.
+    #[cfg(feature = "telemetry")]
+    pub fn from_sample(sample: &Sample) -> Result<Self, Box<dyn Error>> {
+        let session_flags: u32 = sample.get("SessionFlags")?.try_into()?;
+        let player_car_idx: i32 = sample.get("PlayerCarIdx")?.try_into()?;
+
+        Ok(StartSample {
+            session_time: sample.get("SessionTime")?.try_into()?,
+            session_flags: Flags::from_bits_truncate(session_flags),
+            player_car_idx: player_car_idx.max(0) as usize,
+            throttle: sample.get("Throttle")?.try_into()?,
+            rpm: sample.get("RPM")?.try_into()?,
+            speed: sample.get("Speed")?.try_into()?,
+            gear: sample.get("Gear")?.try_into()?,
+            laps_completed: sample.get("CarIdxLapCompleted")?.try_into()?,
+            lap_dist_pct: sample.get("CarIdxLapDistPct")?.try_into()?,
+        })
+    }
+
+    /// Distance travelled by a car, in laps.
+    fn progress(&self, car_idx: usize) -> Option<f32> {
+        let pct = self
+            .lap_dist_pct
+            .get(car_idx)
+            .copied()
+            .and_then(validity::lap_dist_pct)?;
+        Some(self.laps_completed.get(car_idx).copied().unwrap_or(0) as f32 + pct)
+    }
+
+    /// Track position of a car, from 1.
+    fn position(&self, car_idx: usize) -> Option<usize> {
+        let own = self.progress(car_idx)?;
+        let ahead = (0..self.lap_dist_pct.len())
+            .filter(|idx| *idx != car_idx)
+            .filter_map(|idx| self.progress(idx))
+            .filter(|p| *p > own)
+            .count();
+        Some(ahead + 1)
+    }
+}
+
+impl Default for StartAnalyzer {
+    fn default() -> Self {
+        StartAnalyzer {
+            sector_end: 0.33,
+            green: false,
+            start: None,
+            report: None,
+        }
+    }
+}
+
+impl StartAnalyzer {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Lap distance the first sector ends at, 0.33 by default.
+    pub fn with_sector_end(mut self, pct: f32) -> Self {
+        self.sector_end = pct;
+        self
+    }
+
+    ///
+    /// Update with a new sample, returning the report once the player reaches
+    /// the end of the first sector after the start.
+    pub fn update(&mut self, sample: &StartSample) -> Option<StartReport> {
+        let green = sample
+            .session_flags
+            .intersects(Flags::GREEN_FLAG | Flags::START_GO);
+        let started = green && !self.green;
+        self.green = green;
+
+        let player = sample.player_car_idx;
+        if started && self.report.is_none() {
+            let progress = sample.progress(player)?;
+            self.start = Some(Launch {
+                green_time: sample.session_time,
+                target: (progress - self.sector_end).floor() + 1.0 + self.sector_end,
+                start_position: sample.position(player)?,
+                reaction_time: None,
+                last_time: sample.session_time,
+                ratios: HashMap::new(),
+                samples: Vec::new(),
+            });
+        }
+
+        let launch = self.start.as_mut()?;
+        let elapsed = sample.session_time - launch.green_time;
+        if launch.reaction_time.is_none() && sample.throttle >= REACTION_THROTTLE {
+            launch.reaction_time = Some(elapsed);
+        }
+
+        // Engine to road speed ratio, kept until the grip ratio for each gear is known
+        let dt = sample.session_time - launch.last_time;
+        launch.last_time = sample.session_time;
+        if sample.gear > 0 && sample.speed >= MIN_SPIN_SPEED {
+            let ratio = sample.rpm / sample.speed;
+            let grip = launch.ratios.entry(sample.gear).or_insert(ratio);
+            *grip = grip.min(ratio);
+            launch.samples.push((dt, sample.gear, ratio));
+        }
+
+        if sample.progress(player)? < launch.target {
+            return None;
+        }
+
+        let launch = self.start.take()?;
+        let mut wheelspin_time = 0.0;
+        let mut peak_wheelspin: f32 = 0.0;
+        for (dt, gear, ratio) in launch.samples.iter() {
+            let spin = ratio / launch.ratios[gear] - 1.0;
+            if spin > WHEELSPIN_THRESHOLD {
+                wheelspin_time += dt;
+            }
+            peak_wheelspin = peak_wheelspin.max(spin);
+        }
+
+        let sector_position = sample.position(player)?;
+        let report = StartReport {
+            green_time: launch.green_time,
+            reaction_time: launch.reaction_time,
+            wheelspin_time,
+            peak_wheelspin,
+            start_position: launch.start_position,
+            sector_position,
+            positions_gained: launch.start_position as i32 - sector_position as i32,
+            sector_time: sample.session_time - launch.green_time,
+        };
+        self.report = Some(report);
+        Some(report)
+    }
+
+    /// The report for the start, once complete.
+    pub fn report(&self) -> Option<&StartReport> {
+        self.report.as_ref()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn standing_start() {
+        let mut analyzer = StartAnalyzer::new().with_sector_end(0.3);
+        let mut sample = StartSample {
+            session_flags: Flags::START_SET,
+            player_car_idx: 1,
+            laps_completed: vec![0, 0, 0],
+            lap_dist_pct: vec![0.99, 0.98, 0.97],
+            ..Default::default()
+        };
+        assert!(analyzer.update(&sample).is_none());
+
+        sample.session_flags = Flags::START_GO;
+        let mut time = 0.0;
+        let mut step = |analyzer: &mut StartAnalyzer, sample: &mut StartSample| {
+            time += 0.1;
+            sample.session_time = time;
+            analyzer.update(sample)
+        };
+        assert!(step(&mut analyzer, &mut sample).is_none());
+
+        // Full throttle after 0.3s, spinning the wheels in first before gripping
+        step(&mut analyzer, &mut sample);
+        step(&mut analyzer, &mut sample);
+        sample.throttle = 1.0;
+        sample.gear = 1;
+        step(&mut analyzer, &mut sample);
+        for (rpm, speed) in [(6000.0, 4.0), (6500.0, 5.0), (5000.0, 5.0), (6000.0, 6.0)].iter() {
+            sample.rpm = *rpm;
+            sample.speed = *speed;
+            step(&mut analyzer, &mut sample);
+        }
+
+        // Passing car 0 into the first sector
+        sample.laps_completed = vec![1, 1, 1];
+        sample.lap_dist_pct = vec![0.25, 0.3, 0.2];
+        let report = step(&mut analyzer, &mut sample).unwrap();
+
+        assert!((report.reaction_time.unwrap() - 0.3).abs() < 1e-9);
+        assert!((report.wheelspin_time - 0.2).abs() < 1e-9);
+        assert!((report.peak_wheelspin - 0.5).abs() < 1e-6);
+        assert_eq!((report.start_position, report.sector_position), (2, 1));
+        assert_eq!(report.positions_gained, 1);
+        assert!((report.sector_time - 0.8).abs() < 1e-9);
+        assert_eq!(analyzer.report(), Some(&report));
+    }
+}
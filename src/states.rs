@@ -1,4 +1,5 @@
 use bitflags::bitflags;
+use serde::{Deserialize, Serialize};
 
 #[derive(Debug, Copy, Clone)]
 pub enum SessionState {
@@ -55,6 +56,10 @@ bitflags! {
     ///
     /// Bitfield of current camera state
     ///
+    /// The session screen and scenic flags are reported by the sim only; the
+    /// others may be changed with a `CameraSetState` broadcast, most readably
+    /// through a `CameraStateBuilder`.
+    ///
     /// # Examples
     ///
     /// ```
@@ -64,19 +69,129 @@ bitflags! {
     /// ```
     #[derive(Default)]
     pub struct CameraState: u32 {
+        /// The driver is on the session screen, out of the car
         const IS_SESSION_SCREEN = 0x01;
+
+        /// The scenic camera is active (no focus car)
         const IS_SCENIC_ACTIVE = 0x02;
 
+        /// The camera tool is open
         const CAM_TOOL_ACTIVE = 0x04;
+
+        /// The user interface is hidden
         const UI_HIDDEN = 0x08;
+
+        /// The sim picks camera shots automatically
         const USE_AUTO_SHOT_SELECTION = 0x10;
+
+        /// Camera edits are temporary and discarded when the camera changes
         const USE_TEMPORARY_EDITS = 0x20;
+
+        /// Camera keys accelerate the longer they are held
         const USE_KEY_ACCELERATION = 0x40;
+
+        /// Camera keys move ten times faster
         const USE_KEY_10X_ACCELERATION = 0x80;
+
+        /// The mouse aims the camera
         const USE_MOUSE_AIM_MODE = 0x100;
     }
 }
 
+impl CameraState {
+    /// Flags which may be changed with a `CameraSetState` broadcast.
+    pub const SETTABLE: CameraState = CameraState::from_bits_truncate(
+        CameraState::CAM_TOOL_ACTIVE.bits()
+            | CameraState::UI_HIDDEN.bits()
+            | CameraState::USE_AUTO_SHOT_SELECTION.bits()
+            | CameraState::USE_TEMPORARY_EDITS.bits()
+            | CameraState::USE_KEY_ACCELERATION.bits()
+            | CameraState::USE_KEY_10X_ACCELERATION.bits()
+            | CameraState::USE_MOUSE_AIM_MODE.bits(),
+    );
+
+    /// Start building a camera state with no flags set.
+    pub fn builder() -> CameraStateBuilder {
+        CameraStateBuilder::default()
+    }
+}
+
+///
+/// Camera State Builder
+///
+/// Composes a `CameraState` to send with a `CameraSetState` broadcast. Only
+/// flags in `CameraState::SETTABLE` are kept.
+///
+/// # Examples
+///
+/// ```
+/// use iracing::states::{CameraState, CameraStateBuilder};
+///
+/// let state = CameraState::builder()
+///     .cam_tool(true)
+///     .hide_ui(true)
+///     .mouse_aim(true)
+///     .build();
+/// assert!(state.contains(CameraState::UI_HIDDEN | CameraState::USE_MOUSE_AIM_MODE));
+///
+/// // Show the UI again, keeping everything else as it is
+/// # let current = state;
+/// let state = CameraStateBuilder::from(current).hide_ui(false).build();
+/// assert!(!state.contains(CameraState::UI_HIDDEN));
+/// ```
+#[derive(Debug, Copy, Clone, Default)]
+pub struct CameraStateBuilder {
+    state: CameraState,
+}
+
+impl From<CameraState> for CameraStateBuilder {
+    /// Start from an existing state, such as the current `CamCameraState`.
+    fn from(state: CameraState) -> Self {
+        CameraStateBuilder {
+            state: state & CameraState::SETTABLE,
+        }
+    }
+}
+
+impl CameraStateBuilder {
+    fn with(mut self, flag: CameraState, on: bool) -> Self {
+        self.state.set(flag, on);
+        self
+    }
+
+    pub fn cam_tool(self, on: bool) -> Self {
+        self.with(CameraState::CAM_TOOL_ACTIVE, on)
+    }
+
+    pub fn hide_ui(self, on: bool) -> Self {
+        self.with(CameraState::UI_HIDDEN, on)
+    }
+
+    pub fn auto_shot_selection(self, on: bool) -> Self {
+        self.with(CameraState::USE_AUTO_SHOT_SELECTION, on)
+    }
+
+    pub fn temporary_edits(self, on: bool) -> Self {
+        self.with(CameraState::USE_TEMPORARY_EDITS, on)
+    }
+
+    pub fn key_acceleration(self, on: bool) -> Self {
+        self.with(CameraState::USE_KEY_ACCELERATION, on)
+    }
+
+    pub fn key_10x_acceleration(self, on: bool) -> Self {
+        self.with(CameraState::USE_KEY_10X_ACCELERATION, on)
+    }
+
+    pub fn mouse_aim(self, on: bool) -> Self {
+        self.with(CameraState::USE_MOUSE_AIM_MODE, on)
+    }
+
+    pub fn build(self) -> CameraState {
+        self.state
+    }
+}
+
 bitflags! {
     ///
     /// Bitfield of requested services for the next pitstop.
@@ -165,3 +280,284 @@ impl From<i32> for Units {
         }
     }
 }
+
+///
+/// Wetness of the racing surface, as reported by the `TrackWetness` channel.
+///
+/// Variants are ordered from driest to wettest, so they may be compared directly.
+///
+/// # Examples
+///
+/// ```
+/// use iracing::states::TrackWetness;
+///
+/// assert!(TrackWetness::from(5) > TrackWetness::Dry);
+/// assert!(TrackWetness::ModeratelyWet.is_wet());
+/// ```
+#[derive(
+    Debug, Copy, Clone, Default, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize,
+)]
+pub enum TrackWetness {
+    #[default]
+    Unknown,
+    Dry,
+    MostlyDry,
+    VeryLightlyWet,
+    LightlyWet,
+    ModeratelyWet,
+    VeryWet,
+    ExtremelyWet,
+}
+
+impl From<i32> for TrackWetness {
+    fn from(v: i32) -> TrackWetness {
+        match v {
+            1 => Self::Dry,
+            2 => Self::MostlyDry,
+            3 => Self::VeryLightlyWet,
+            4 => Self::LightlyWet,
+            5 => Self::ModeratelyWet,
+            6 => Self::VeryWet,
+            7 => Self::ExtremelyWet,
+            _ => Self::Unknown,
+        }
+    }
+}
+
+impl TrackWetness {
+    /// True if there is any standing water on the racing surface.
+    pub fn is_wet(&self) -> bool {
+        *self >= Self::VeryLightlyWet
+    }
+}
+
+///
+/// Rubber laid on the racing line, as set for a session by
+/// `SessionTrackRubberState` in the session info.
+///
+/// The sim doesn't publish a live usage or marbles channel, so this is the
+/// state the session started with.
+///
+/// # Examples
+///
+/// ```
+/// use iracing::states::TrackUsage;
+///
+/// assert_eq!(TrackUsage::from("moderate usage"), TrackUsage::ModerateUsage);
+/// assert_eq!(TrackUsage::from("carry over"), TrackUsage::CarryOver);
+/// ```
+#[derive(Debug, Copy, Clone, Default, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum TrackUsage {
+    Clean,
+    SlightUsage,
+    LowUsage,
+    ModeratelyLowUsage,
+    ModerateUsage,
+    ModeratelyHighUsage,
+    HighUsage,
+    ExtensiveUsage,
+    MaximumUsage,
+
+    /// Rubber carries over from the previous session
+    CarryOver,
+
+    #[default]
+    Unknown,
+}
+
+impl From<&str> for TrackUsage {
+    fn from(v: &str) -> TrackUsage {
+        match v.trim().to_ascii_lowercase().as_str() {
+            "clean" => Self::Clean,
+            "slight usage" => Self::SlightUsage,
+            "low usage" => Self::LowUsage,
+            "moderately low usage" => Self::ModeratelyLowUsage,
+            "moderate usage" => Self::ModerateUsage,
+            "moderately high usage" => Self::ModeratelyHighUsage,
+            "high usage" => Self::HighUsage,
+            "extensive usage" => Self::ExtensiveUsage,
+            "maximum usage" => Self::MaximumUsage,
+            "carry over" => Self::CarryOver,
+            _ => Self::Unknown,
+        }
+    }
+}
+
+/**
+ * Sky cover, as reported by the `Skies` channel
+ */
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Skies {
+    Clear,
+    PartlyCloudy,
+    MostlyCloudy,
+    Overcast,
+    Unknown(i32),
+}
+
+impl From<i32> for Skies {
+    fn from(v: i32) -> Skies {
+        match v {
+            0 => Self::Clear,
+            1 => Self::PartlyCloudy,
+            2 => Self::MostlyCloudy,
+            3 => Self::Overcast,
+            _ => Self::Unknown(v),
+        }
+    }
+}
+
+/**
+ * Pacing mode of the field, as reported by the `PaceMode` channel
+ */
+#[derive(Debug, Copy, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum PaceMode {
+    SingleFileStart,
+    DoubleFileStart,
+    SingleFileRestart,
+    DoubleFileRestart,
+    #[default]
+    NotPacing,
+}
+
+impl From<i32> for PaceMode {
+    fn from(v: i32) -> PaceMode {
+        match v {
+            0 => Self::SingleFileStart,
+            1 => Self::DoubleFileStart,
+            2 => Self::SingleFileRestart,
+            3 => Self::DoubleFileRestart,
+            _ => Self::NotPacing,
+        }
+    }
+}
+
+bitflags! {
+    ///
+    /// Per-car pacing flags, as reported by the `CarIdxPaceFlags` channel.
+    #[derive(Default)]
+    pub struct PaceFlags: u32 {
+        /// Car has been sent to the end of the line
+        const END_OF_LINE = 0x01;
+
+        /// Car has been awarded the free pass (lucky dog)
+        const FREE_PASS = 0x02;
+
+        /// Car has been waved around the pace car
+        const WAVED_AROUND = 0x04;
+    }
+}
+
+/**
+ * DRS state, as reported by the `DRS_Status` channel
+ */
+#[derive(Debug, Copy, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum DrsState {
+    /// DRS can't be used
+    #[default]
+    Unavailable,
+
+    /// Car is within the detection gap and DRS will be available in the next zone
+    Armed,
+
+    /// Car is in a DRS zone and may open the flap
+    Available,
+
+    /// DRS flap is open
+    Open,
+}
+
+impl From<i32> for DrsState {
+    fn from(v: i32) -> DrsState {
+        match v {
+            1 => Self::Armed,
+            2 => Self::Available,
+            3 => Self::Open,
+            _ => Self::Unavailable,
+        }
+    }
+}
+
+/**
+ * Cars alongside the player, as reported by the `CarLeftRight` channel
+ */
+#[derive(Debug, Copy, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum CarLeftRight {
+    /// Spotter is off
+    #[default]
+    Off,
+    Clear,
+    CarLeft,
+    CarRight,
+
+    /// Cars on both sides (three wide)
+    CarLeftRight,
+    TwoCarsLeft,
+    TwoCarsRight,
+}
+
+impl From<i32> for CarLeftRight {
+    fn from(v: i32) -> CarLeftRight {
+        match v {
+            1 => Self::Clear,
+            2 => Self::CarLeft,
+            3 => Self::CarRight,
+            4 => Self::CarLeftRight,
+            5 => Self::TwoCarsLeft,
+            6 => Self::TwoCarsRight,
+            _ => Self::Off,
+        }
+    }
+}
+
+/**
+ * Where a car is, as reported by the `CarIdxTrackSurface` channel
+ */
+#[derive(Debug, Copy, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum TrackLocation {
+    /// Not in a car, or being towed
+    #[default]
+    NotInWorld,
+
+    /// All four wheels off the racing surface
+    OffTrack,
+    InPitStall,
+
+    /// On pit road, or in the pit entry or exit lane
+    ApproachingPits,
+    OnTrack,
+}
+
+impl From<i32> for TrackLocation {
+    fn from(v: i32) -> TrackLocation {
+        match v {
+            0 => Self::OffTrack,
+            1 => Self::InPitStall,
+            2 => Self::ApproachingPits,
+            3 => Self::OnTrack,
+            _ => Self::NotInWorld,
+        }
+    }
+}
+
+/**
+ * What the sim is currently doing, derived by `iracing::activity::ActivityTracker`
+ */
+#[derive(Debug, Copy, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum SimActivity {
+    /// Not in a car, e.g. in the menus or spectating
+    #[default]
+    InMenus,
+
+    /// Sat in the car in the garage
+    InGarage,
+
+    /// Driving the car on track
+    OnTrack,
+
+    /// On track, but the session clock isn't advancing
+    Paused,
+
+    /// Watching a replay
+    Replay,
+}
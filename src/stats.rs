@@ -0,0 +1,294 @@
+use serde::Serialize;
+use std::collections::BTreeMap;
+
+#[cfg(feature = "telemetry")]
+use crate::ibt::{IbtError, IBT};
+#[cfg(feature = "telemetry")]
+use crate::telemetry::{Sample, Value};
+
+///
+/// Summary statistics of a set of values.
+#[derive(Debug, Copy, Clone, PartialEq, Serialize)]
+pub struct Summary {
+    pub count: usize,
+    pub min: f64,
+    pub max: f64,
+    pub mean: f64,
+    pub stddev: f64, // Population standard deviation
+    pub p5: f64,
+    pub p25: f64,
+    pub median: f64,
+    pub p75: f64,
+    pub p95: f64,
+}
+
+///
+/// Summary of one channel, overall and for each lap.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct ChannelSummary {
+    pub name: String,
+    pub overall: Summary,
+    pub laps: BTreeMap<i32, Summary>,
+}
+
+///
+/// Stats
+///
+/// Collects the values of every numeric scalar channel, so each can be
+/// summarised over the whole recording and lap by lap. Array channels, such
+/// as the `CarIdx` channels, are skipped.
+///
+/// NaN values are ignored.
+///
+/// # Examples
+///
+/// ```
+/// use iracing::stats::Stats;
+///
+/// let mut stats = Stats::new();
+/// stats.record(1, &[("Speed", 50.0), ("Throttle", 1.0)]);
+/// stats.record(1, &[("Speed", 60.0), ("Throttle", 0.5)]);
+/// stats.record(2, &[("Speed", 70.0), ("Throttle", 0.0)]);
+///
+/// let speed = stats.summary("Speed").unwrap();
+/// assert_eq!(speed.mean, 60.0);
+/// assert_eq!(stats.lap_summary("Speed", 1).unwrap().max, 60.0);
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct Stats {
+    channels: BTreeMap<String, Vec<(i32, f64)>>,
+}
+
+impl Summary {
+    ///
+    /// Summarise a set of values, ignoring NaN. Returns None if no values
+    /// remain.
+    pub fn of(values: &[f64]) -> Option<Summary> {
+        let mut sorted: Vec<f64> = values.iter().copied().filter(|v| !v.is_nan()).collect();
+        if sorted.is_empty() {
+            return None;
+        }
+        sorted.sort_by(|a, b| a.partial_cmp(b).unwrap_or(std::cmp::Ordering::Equal));
+
+        let n = sorted.len() as f64;
+        let mean = sorted.iter().sum::<f64>() / n;
+        let variance = sorted.iter().map(|v| (v - mean).powi(2)).sum::<f64>() / n;
+
+        Some(Summary {
+            count: sorted.len(),
+            min: sorted[0],
+            max: sorted[sorted.len() - 1],
+            mean,
+            stddev: variance.sqrt(),
+            p5: percentile(&sorted, 5.0),
+            p25: percentile(&sorted, 25.0),
+            median: percentile(&sorted, 50.0),
+            p75: percentile(&sorted, 75.0),
+            p95: percentile(&sorted, 95.0),
+        })
+    }
+}
+
+///
+/// Percentile `p` (0 to 100) of sorted values, interpolating between the
+/// closest ranks.
+pub fn percentile(sorted: &[f64], p: f64) -> f64 {
+    if sorted.is_empty() {
+        return f64::NAN;
+    }
+
+    let rank = (p.clamp(0.0, 100.0) / 100.0) * (sorted.len() - 1) as f64;
+    let (lower, upper) = (rank.floor() as usize, rank.ceil() as usize);
+    sorted[lower] + (sorted[upper] - sorted[lower]) * (rank - lower as f64)
+}
+
+impl Stats {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    ///
+    /// Add the values of some channels on a lap.
+    pub fn record(&mut self, lap: i32, values: &[(&str, f64)]) {
+        for (name, value) in values.iter() {
+            if value.is_nan() {
+                continue;
+            }
+
+            match self.channels.get_mut(*name) {
+                Some(channel) => channel.push((lap, *value)),
+                None => {
+                    self.channels.insert(name.to_string(), vec![(lap, *value)]);
+                }
+            }
+        }
+    }
+
+    ///
+    /// Add every numeric scalar channel of a telemetry sample. The lap is read
+    /// from `Lap`, or 0 if the sample has none.
+    #[cfg(feature = "telemetry")]
+    pub fn record_sample(&mut self, sample: &Sample) {
+        let all = sample.all();
+        let lap = all
+            .iter()
+            .find(|v| v.name == "Lap")
+            .and_then(|v| match v.value {
+                Value::INT(lap) => Some(lap),
+                _ => None,
+            })
+            .unwrap_or(0);
+
+        let values: Vec<(&str, f64)> = all
+            .iter()
+            .filter_map(|v| {
+                let value = match v.value {
+                    Value::DOUBLE(v) => v,
+                    Value::FLOAT(v) => v as f64,
+                    Value::INT(v) => v as f64,
+                    Value::BITS(v) => v as f64,
+                    Value::BOOL(v) => v as u8 as f64,
+                    Value::CHAR(v) => v as f64,
+                    _ => return None,
+                };
+                Some((v.name.as_str(), value))
+            })
+            .collect();
+
+        self.record(lap, &values);
+    }
+
+    ///
+    /// Collect statistics from a sequence of samples.
+    #[cfg(feature = "telemetry")]
+    pub fn summarize<I: IntoIterator<Item = Sample>>(samples: I) -> Stats {
+        let mut stats = Stats::new();
+        for sample in samples {
+            stats.record_sample(&sample);
+        }
+        stats
+    }
+
+    ///
+    /// Collect statistics from every sample of a telemetry file.
+    #[cfg(feature = "telemetry")]
+    pub fn from_ibt(ibt: &IBT) -> Result<Stats, IbtError> {
+        let mut stats = Stats::new();
+        for sample in ibt.samples() {
+            stats.record_sample(&sample?);
+        }
+        Ok(stats)
+    }
+
+    /// Names of the channels seen, in alphabetical order.
+    pub fn channels(&self) -> impl Iterator<Item = &str> {
+        self.channels.keys().map(String::as_str)
+    }
+
+    /// Laps seen on any channel, in order.
+    pub fn laps(&self) -> Vec<i32> {
+        let mut laps: Vec<i32> = self
+            .channels
+            .values()
+            .flat_map(|values| values.iter().map(|(lap, _)| *lap))
+            .collect();
+        laps.sort_unstable();
+        laps.dedup();
+        laps
+    }
+
+    /// Summary of a channel over everything recorded.
+    pub fn summary(&self, name: &str) -> Option<Summary> {
+        let values: Vec<f64> = self.channels.get(name)?.iter().map(|(_, v)| *v).collect();
+        Summary::of(&values)
+    }
+
+    /// Summary of a channel on one lap.
+    pub fn lap_summary(&self, name: &str, lap: i32) -> Option<Summary> {
+        let values: Vec<f64> = self
+            .channels
+            .get(name)?
+            .iter()
+            .filter(|(l, _)| *l == lap)
+            .map(|(_, v)| *v)
+            .collect();
+        Summary::of(&values)
+    }
+
+    ///
+    /// Summaries of every channel, overall and per lap.
+    pub fn summaries(&self) -> Vec<ChannelSummary> {
+        self.channels
+            .iter()
+            .filter_map(|(name, values)| {
+                let mut by_lap: BTreeMap<i32, Vec<f64>> = BTreeMap::new();
+                for (lap, value) in values.iter() {
+                    by_lap.entry(*lap).or_default().push(*value);
+                }
+
+                let all: Vec<f64> = values.iter().map(|(_, v)| *v).collect();
+                Some(ChannelSummary {
+                    name: name.clone(),
+                    overall: Summary::of(&all)?,
+                    laps: by_lap
+                        .into_iter()
+                        .filter_map(|(lap, v)| Some((lap, Summary::of(&v)?)))
+                        .collect(),
+                })
+            })
+            .collect()
+    }
+
+    ///
+    /// Serialize every channel's summaries as JSON.
+    pub fn to_json(&self) -> serde_json::Result<String> {
+        serde_json::to_string_pretty(&self.summaries())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn summarize_channels() {
+        let mut stats = Stats::new();
+        for i in 0..=100 {
+            let lap = 1 + i / 50;
+            stats.record(lap, &[("Speed", i as f64), ("Brake", f64::NAN)]);
+        }
+
+        let speed = stats.summary("Speed").unwrap();
+        assert_eq!(speed.count, 101);
+        assert_eq!((speed.min, speed.max, speed.mean), (0.0, 100.0, 50.0));
+        assert_eq!((speed.p5, speed.median, speed.p95), (5.0, 50.0, 95.0));
+        assert!((speed.stddev - 29.1548).abs() < 1e-4);
+
+        assert_eq!(stats.laps(), vec![1, 2, 3]);
+        assert_eq!(stats.lap_summary("Speed", 2).unwrap().min, 50.0);
+        assert_eq!(stats.lap_summary("Speed", 3).unwrap().count, 1);
+
+        // Channels with only NaN are never summarised
+        assert_eq!(stats.summary("Brake"), None);
+        assert_eq!(stats.channels().collect::<Vec<_>>(), vec!["Speed"]);
+
+        let json: serde_json::Value = serde_json::from_str(&stats.to_json().unwrap()).unwrap();
+        assert_eq!(json[0]["name"], "Speed");
+        assert_eq!(json[0]["laps"]["2"]["max"], 99.0);
+
+        assert_eq!(percentile(&[1.0, 2.0], 50.0), 1.5);
+    }
+
+    #[cfg(feature = "telemetry")]
+    #[test]
+    fn summarize_ibt() {
+        let ibt = IBT::open("./telemetry.ibt").unwrap();
+        let stats = Stats::from_ibt(&ibt).unwrap();
+
+        let time = stats.summary("SessionTime").unwrap();
+        assert_eq!(time.count, 120);
+        assert_eq!(time.min, 120.0);
+        assert!(stats.summary("CarIdxLapDistPct").is_none());
+        assert_eq!(stats.laps(), vec![2, 3]);
+    }
+}
@@ -0,0 +1,184 @@
+use crate::units::Angle;
+use serde::{Deserialize, Serialize};
+use std::fmt;
+
+#[cfg(feature = "telemetry")]
+use crate::telemetry::Sample;
+#[cfg(feature = "telemetry")]
+use std::convert::TryInto;
+#[cfg(feature = "telemetry")]
+use std::error::Error;
+
+/// Within this of the car's lock counts as at full lock (rad)
+const LOCK_MARGIN: f32 = 0.01;
+
+///
+/// Steering Sample
+///
+/// The steering wheel's angle and the car's steering lock at a point in
+/// time.
+///
+/// # Examples
+///
+/// ```
+/// use iracing::steering::SteeringSample;
+///
+/// let sample = SteeringSample { angle: -1.0, angle_max: 4.0 };
+///
+/// assert_eq!(sample.normalized(), -0.25);
+/// assert_eq!(sample.lock().degrees().round(), 458.0);
+/// ```
+#[derive(Debug, Copy, Clone, Default, PartialEq)]
+pub struct SteeringSample {
+    pub angle: f32,     // SteeringWheelAngle - rad, positive left
+    pub angle_max: f32, // SteeringWheelAngleMax - rad, half of lock to lock
+}
+
+///
+/// How the rotation set for the wheel compares to the car's steering lock.
+#[derive(Debug, Copy, Clone, PartialEq, Serialize, Deserialize)]
+pub enum Rotation {
+    /// Within tolerance of the car's lock
+    Matched,
+
+    /// The wheel stops before the car reaches full lock
+    TooNarrow { car: f64, hardware: f64 },
+
+    /// The wheel turns past the car's full lock, leaving a dead zone at
+    /// either end
+    TooWide { car: f64, hardware: f64 },
+}
+
+impl SteeringSample {
+    ///
+    /// Read a steering sample from a telemetry sample.
+    #[cfg(feature = "telemetry")]
+    pub fn from_sample(sample: &Sample) -> Result<Self, Box<dyn Error>> {
+        Ok(SteeringSample {
+            angle: sample.get("SteeringWheelAngle")?.try_into()?,
+            angle_max: sample.get("SteeringWheelAngleMax")?.try_into()?,
+        })
+    }
+
+    /// The wheel's angle from center.
+    pub fn angle(&self) -> Angle {
+        Angle(self.angle as f64)
+    }
+
+    /// The car's steering lock, lock to lock.
+    pub fn lock(&self) -> Angle {
+        Angle(self.angle_max as f64 * 2.0)
+    }
+
+    /// The wheel's angle from -1.0 at full right lock to 1.0 at full left.
+    pub fn normalized(&self) -> f32 {
+        normalize(self.angle, self.angle_max)
+    }
+
+    /// Whether the wheel is at the car's full lock either way.
+    pub fn at_lock(&self) -> bool {
+        self.angle_max > 0.0 && self.angle.abs() >= self.angle_max - LOCK_MARGIN
+    }
+
+    ///
+    /// Compare the rotation set for the wheel in its driver software, lock
+    /// to lock in degrees, to the car's steering lock. The sim doesn't know
+    /// the wheel's rotation, so it must come from the user or the wheel.
+    pub fn check_rotation(&self, hardware: f64, tolerance: f64) -> Rotation {
+        check_rotation(self.lock(), hardware, tolerance)
+    }
+}
+
+///
+/// A steering wheel angle from -1.0 at full right lock to 1.0 at full left,
+/// given the car's half lock. Zero when the lock isn't known, such as
+/// before the car is loaded.
+pub fn normalize(angle: f32, angle_max: f32) -> f32 {
+    if angle_max > 0.0 {
+        (angle / angle_max).clamp(-1.0, 1.0)
+    } else {
+        0.0
+    }
+}
+
+///
+/// Compare a wheel's rotation, lock to lock in degrees, to a car's steering
+/// lock, allowing `tolerance` degrees either way.
+///
+/// # Examples
+///
+/// ```
+/// use iracing::steering::{check_rotation, Rotation};
+/// use iracing::units::Angle;
+///
+/// let car = Angle(540f64.to_radians());
+///
+/// assert_eq!(check_rotation(car, 540.0, 5.0), Rotation::Matched);
+/// assert!(matches!(check_rotation(car, 900.0, 5.0), Rotation::TooWide { .. }));
+/// ```
+pub fn check_rotation(lock: Angle, hardware: f64, tolerance: f64) -> Rotation {
+    let car = lock.degrees();
+
+    if car <= 0.0 || (hardware - car).abs() <= tolerance {
+        Rotation::Matched
+    } else if hardware < car {
+        Rotation::TooNarrow { car, hardware }
+    } else {
+        Rotation::TooWide { car, hardware }
+    }
+}
+
+impl fmt::Display for Rotation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Rotation::Matched => write!(f, "Wheel rotation matches the car"),
+            Rotation::TooNarrow { car, hardware } => write!(
+                f,
+                "Wheel rotation of {:.0}° is less than the car's {:.0}° lock",
+                hardware, car
+            ),
+            Rotation::TooWide { car, hardware } => write!(
+                f,
+                "Wheel rotation of {:.0}° is more than the car's {:.0}° lock",
+                hardware, car
+            ),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn steering_calibration() {
+        let half_lock = 270f32.to_radians();
+        let sample = |degrees: f32| SteeringSample {
+            angle: degrees.to_radians(),
+            angle_max: half_lock,
+        };
+
+        assert_eq!(sample(0.0).normalized(), 0.0);
+        assert!((sample(-135.0).normalized() + 0.5).abs() < 1e-6);
+        assert_eq!(sample(300.0).normalized(), 1.0);
+        assert!(sample(270.0).at_lock());
+        assert!(!sample(200.0).at_lock());
+
+        // Nothing to compare before the car is loaded
+        let unloaded = SteeringSample::default();
+        assert_eq!(unloaded.normalized(), 0.0);
+        assert_eq!(unloaded.check_rotation(900.0, 5.0), Rotation::Matched);
+
+        assert_eq!(sample(0.0).check_rotation(542.0, 5.0), Rotation::Matched);
+        let narrow = sample(0.0).check_rotation(360.0, 5.0);
+        assert!(matches!(narrow, Rotation::TooNarrow { .. }));
+        assert_eq!(
+            narrow.to_string(),
+            "Wheel rotation of 360° is less than the car's 540° lock"
+        );
+        assert!(matches!(
+            sample(0.0).check_rotation(900.0, 5.0),
+            Rotation::TooWide { .. }
+        ));
+    }
+}
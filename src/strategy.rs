@@ -0,0 +1,795 @@
+use serde::{Deserialize, Serialize};
+use std::fmt;
+
+///
+/// A car on track, as seen by the strategy planner.
+#[derive(Debug, Copy, Clone, PartialEq, Serialize, Deserialize)]
+pub struct CarState {
+    pub car_idx: usize,
+    pub gap: f64,             // Race time behind the leader (s)
+    pub lap_time: f64,        // Expected lap time (s)
+    pub pit_lap: Option<i32>, // Lap the car is expected to pit on, if known
+}
+
+///
+/// Fuel consumption model.
+#[derive(Debug, Copy, Clone, PartialEq, Serialize, Deserialize)]
+pub struct FuelModel {
+    pub fuel: f32,      // Fuel in the car (l)
+    pub per_lap: f32,   // Fuel used per lap (l)
+    pub capacity: f32,  // Tank capacity (l)
+    pub fill_rate: f32, // Refuelling rate (l/s)
+}
+
+///
+/// Tire degradation model.
+#[derive(Debug, Copy, Clone, PartialEq, Serialize, Deserialize)]
+pub struct TireModel {
+    pub age: i32,         // Laps on the current tires
+    pub degradation: f64, // Lap time lost per lap of tire age (s)
+    pub change_time: f64, // Time to change tires (s), 0 to not change tires
+}
+
+///
+/// Projected outcome of pitting on a given lap.
+#[derive(Debug, Copy, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Projection {
+    pub stop_lap: i32,
+    pub position: usize, // Track position (1-based) at the end of the lap after the stop
+    pub gap_ahead: Option<f64>, // Gap to the car ahead after the stop (s)
+    pub gap_behind: Option<f64>, // Gap to the car behind after the stop (s)
+    pub service_time: f64, // Time stationary for fuel and tires (s)
+    pub race_time: f64,  // Projected time to the end of the race (s)
+}
+
+///
+/// Planner
+///
+/// Simulates pit windows for a car, projecting where it will rejoin after
+/// stopping on each lap and its time to the end of the race, to compare
+/// undercut and overcut options.
+///
+/// Gaps and lap times may come from live telemetry or from recorded data.
+///
+/// # Examples
+///
+/// ```
+/// use iracing::strategy::{CarState, FuelModel, Planner, TireModel};
+///
+/// let fuel = FuelModel { fuel: 30.0, per_lap: 3.0, capacity: 100.0, fill_rate: 2.5 };
+/// let tires = TireModel { age: 10, degradation: 0.1, change_time: 0.0 };
+/// let me = CarState { car_idx: 1, gap: 0.0, lap_time: 100.0, pit_lap: None };
+///
+/// let planner = Planner::new(me, fuel, tires, 25.0, 10, 30);
+///
+/// if let Some(best) = planner.best() {
+///     println!("Pit on lap {} to finish in {:.1}s", best.stop_lap, best.race_time);
+/// }
+/// ```
+#[derive(Debug, Clone)]
+pub struct Planner {
+    car: CarState,
+    others: Vec<CarState>,
+    fuel: FuelModel,
+    tires: TireModel,
+    pit_loss: f64,
+    lap: i32,
+    race_laps: i32,
+}
+
+///
+/// A stint in a stint plan.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct PlannedStint {
+    pub laps: i32,              // Target laps in the stint
+    pub driver: Option<String>, // Driver for the stint, for team races
+}
+
+///
+/// Stint Plan
+///
+/// The planned stints of a race, each with a target number of laps and,
+/// for team races, a driver. Every stint but the last ends with a stop.
+///
+/// # Examples
+///
+/// ```
+/// use iracing::strategy::StintPlan;
+///
+/// let plan = StintPlan::new(90)
+///     .with_stint(30, Some("Alice"))
+///     .with_stint(30, Some("Bob"))
+///     .with_stint(30, Some("Alice"));
+///
+/// assert_eq!(plan.stops(), vec![30, 60]);
+/// ```
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct StintPlan {
+    pub race_laps: i32,
+    pub stints: Vec<PlannedStint>,
+}
+
+///
+/// What moved a projected stop.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Cause {
+    /// Laps under caution use less fuel, stretching the stint
+    Caution,
+
+    /// The fuel in the car doesn't reach the planned stop
+    Fuel,
+}
+
+///
+/// A way the race has departed from the plan.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub enum DeviationKind {
+    /// The projected stop lap has moved
+    StopMoved {
+        planned: i32,
+        projected: i32,
+        cause: Cause,
+    },
+
+    /// The car stopped before the projected stop lap
+    EarlyStop { projected: i32, actual: i32 },
+
+    /// The car stopped during the final stint
+    ExtraStop { actual: i32 },
+
+    /// A different driver than planned is driving the stint
+    WrongDriver { planned: String, actual: String },
+}
+
+///
+/// A deviation from a stint plan.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Deviation {
+    pub lap: i32,     // Lap the deviation was seen on
+    pub stint: usize, // Stint affected, from 0
+    pub kind: DeviationKind,
+}
+
+///
+/// State of the car, as seen by the plan tracker.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct PlanSample {
+    pub lap: i32,
+    pub on_pit_road: bool,
+    pub caution: bool,           // The lap is being run under caution
+    pub fuel: Option<FuelModel>, // Current fuel state, if known
+    pub driver: Option<String>,  // Driver at the wheel, for team races
+}
+
+///
+/// Plan Tracker
+///
+/// Follows a race against its stint plan, projecting when the current stint
+/// will end and reporting deviations such as a stop slipping back after a
+/// caution, fuel falling short of a planned stop, an unplanned stop or the
+/// wrong driver in the car.
+///
+/// Laps under caution count as part of a lap towards the stint length, see
+/// `with_caution_fuel`. When the fuel state is known, the stop is never
+/// projected later than the fuel lasts.
+///
+/// # Examples
+///
+/// ```
+/// use iracing::strategy::{PlanSample, PlanTracker, StintPlan};
+///
+/// let plan = StintPlan::new(60).with_stint(30, None).with_stint(30, None);
+/// let mut tracker = PlanTracker::new(plan);
+///
+/// for lap in 1..=12 {
+///     let sample = PlanSample { lap, caution: (5..=10).contains(&lap), ..Default::default() };
+///     for deviation in tracker.update(&sample) {
+///         println!("Lap {}: {}", deviation.lap, deviation);
+///     }
+/// }
+///
+/// assert_eq!(tracker.projected_stops(), vec![33]);
+/// ```
+#[derive(Debug, Clone)]
+pub struct PlanTracker {
+    plan: StintPlan,
+    caution_fuel: f32,
+    stint: usize,
+    stint_start: i32,
+    caution_laps: i32,
+    last_caution_lap: Option<i32>,
+    projected: Option<i32>,
+    on_pit_road: bool,
+    driver_checked: bool,
+    stops: Vec<i32>,
+}
+
+impl FuelModel {
+    /// Number of whole laps the current fuel lasts.
+    pub fn laps_remaining(&self) -> i32 {
+        if self.per_lap <= 0.0 {
+            i32::MAX
+        } else {
+            (self.fuel / self.per_lap).floor() as i32
+        }
+    }
+
+    /// Time to add fuel for `laps` laps, limited by tank capacity (s).
+    pub(crate) fn fill_time(&self, fuel_left: f32, laps: i32) -> f64 {
+        let needed = (self.per_lap * laps as f32 - fuel_left)
+            .min(self.capacity - fuel_left)
+            .max(0.0);
+
+        if self.fill_rate > 0.0 {
+            (needed / self.fill_rate) as f64
+        } else {
+            0.0
+        }
+    }
+}
+
+impl TireModel {
+    /// Lap time penalty for tires of a given age (s).
+    fn penalty(&self, age: i32) -> f64 {
+        self.degradation * age as f64
+    }
+}
+
+impl Planner {
+    ///
+    /// Plan for `car` on lap `lap` of a `race_laps` lap race.
+    ///
+    /// `pit_loss` is the time lost driving through pit lane compared to staying
+    /// on track, excluding service time; see `PitLane::average_time_lost`.
+    pub fn new(
+        car: CarState,
+        fuel: FuelModel,
+        tires: TireModel,
+        pit_loss: f64,
+        lap: i32,
+        race_laps: i32,
+    ) -> Self {
+        Planner {
+            car,
+            others: Vec::new(),
+            fuel,
+            tires,
+            pit_loss,
+            lap,
+            race_laps,
+        }
+    }
+
+    /// Add the cars the plan is compared against.
+    pub fn with_cars(mut self, cars: &[CarState]) -> Self {
+        self.others = cars
+            .iter()
+            .filter(|c| c.car_idx != self.car.car_idx)
+            .copied()
+            .collect();
+        self
+    }
+
+    ///
+    /// Laps at the end of which the car can stop, from the current lap up to
+    /// but not including the first lap the fuel doesn't reach.
+    pub fn window(&self) -> std::ops::Range<i32> {
+        let end = self
+            .lap
+            .saturating_add(self.fuel.laps_remaining())
+            .min(self.race_laps);
+        self.lap..end
+    }
+
+    ///
+    /// Project the outcome of stopping at the end of `stop_lap`.
+    pub fn simulate(&self, stop_lap: i32) -> Projection {
+        let laps_to_stop = (stop_lap - self.lap + 1).max(0);
+        let fuel_left = self.fuel.fuel - self.fuel.per_lap * laps_to_stop as f32;
+
+        let fuel_time = self.fuel.fill_time(fuel_left, self.race_laps - stop_lap);
+        let service_time = fuel_time.max(self.tires.change_time);
+        let fresh_tires = self.tires.change_time > 0.0;
+
+        // Time for each remaining lap, with the stop at the end of stop_lap
+        let mut time = self.car.gap;
+        let mut after_stop = None;
+        for (i, lap) in (self.lap..=self.race_laps).enumerate() {
+            let age = if fresh_tires && lap > stop_lap {
+                lap - stop_lap - 1
+            } else {
+                self.tires.age + i as i32
+            };
+
+            time += self.car.lap_time + self.tires.penalty(age);
+            if lap == stop_lap {
+                time += self.pit_loss + service_time;
+            }
+            if lap == stop_lap + 1 {
+                after_stop = Some(time);
+            }
+        }
+        let race_time = time - self.car.gap;
+
+        // Where the car is at the end of the lap after its stop
+        let target = stop_lap + 1;
+        let own = after_stop.unwrap_or(time);
+        let mut times: Vec<f64> = self
+            .others
+            .iter()
+            .map(|c| self.time_at(c, target))
+            .collect();
+        times.sort_by(|a, b| a.partial_cmp(b).unwrap_or(std::cmp::Ordering::Equal));
+
+        let ahead = times.iter().rev().find(|&&t| t <= own).copied();
+        let behind = times.iter().find(|&&t| t > own).copied();
+
+        Projection {
+            stop_lap,
+            position: times.iter().filter(|&&t| t <= own).count() + 1,
+            gap_ahead: ahead.map(|t| own - t),
+            gap_behind: behind.map(|t| t - own),
+            service_time,
+            race_time,
+        }
+    }
+
+    /// Projected race time of another car at the end of a lap.
+    fn time_at(&self, car: &CarState, lap: i32) -> f64 {
+        let laps = (lap - self.lap + 1).max(0) as f64;
+        let stop = match car.pit_lap {
+            Some(pit) if pit >= self.lap && pit <= lap => self.pit_loss,
+            _ => 0.0,
+        };
+
+        car.gap + laps * car.lap_time + stop
+    }
+
+    /// Projections for stopping on every lap in the window.
+    pub fn plan(&self) -> Vec<Projection> {
+        self.window().map(|lap| self.simulate(lap)).collect()
+    }
+
+    /// The stop lap with the shortest projected race time.
+    pub fn best(&self) -> Option<Projection> {
+        self.plan().into_iter().min_by(|a, b| {
+            a.race_time
+                .partial_cmp(&b.race_time)
+                .unwrap_or(std::cmp::Ordering::Equal)
+        })
+    }
+}
+
+impl StintPlan {
+    /// An empty plan for a race of `race_laps` laps.
+    pub fn new(race_laps: i32) -> Self {
+        StintPlan {
+            race_laps,
+            stints: Vec::new(),
+        }
+    }
+
+    ///
+    /// A plan splitting the race into `stints` stints of as even a length as
+    /// possible.
+    pub fn even(race_laps: i32, stints: usize) -> Self {
+        let count = stints.max(1) as i32;
+        let mut plan = StintPlan::new(race_laps);
+        for i in 0..count {
+            let laps = race_laps / count + if i < race_laps % count { 1 } else { 0 };
+            plan = plan.with_stint(laps, None);
+        }
+        plan
+    }
+
+    /// Add a stint of `laps` laps.
+    pub fn with_stint(mut self, laps: i32, driver: Option<&str>) -> Self {
+        self.stints.push(PlannedStint {
+            laps,
+            driver: driver.map(String::from),
+        });
+        self
+    }
+
+    /// Laps the planned stops are made at the end of.
+    pub fn stops(&self) -> Vec<i32> {
+        let mut lap = 0;
+        self.stints
+            .iter()
+            .take(self.stints.len().saturating_sub(1))
+            .map(|s| {
+                lap += s.laps;
+                lap
+            })
+            .filter(|lap| *lap < self.race_laps)
+            .collect()
+    }
+}
+
+impl fmt::Display for Deviation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let stint = self.stint + 1;
+
+        match &self.kind {
+            DeviationKind::StopMoved {
+                planned,
+                projected,
+                cause,
+            } => {
+                let cause = match cause {
+                    Cause::Caution => "caution",
+                    Cause::Fuel => "fuel",
+                };
+                let laps = |n: i32| if n == 1 { "lap" } else { "laps" };
+                let moved = projected - planned;
+
+                if moved > 0 {
+                    write!(
+                        f,
+                        "Stint {} stop lap slipped by {} {} due to {}",
+                        stint,
+                        moved,
+                        laps(moved),
+                        cause
+                    )
+                } else if moved < 0 {
+                    write!(
+                        f,
+                        "Stint {} stop lap brought forward by {} {} due to {}",
+                        stint,
+                        -moved,
+                        laps(-moved),
+                        cause
+                    )
+                } else {
+                    write!(f, "Stint {} stop back on plan for lap {}", stint, planned)
+                }
+            }
+            DeviationKind::EarlyStop { projected, actual } => write!(
+                f,
+                "Stint {} ended on lap {}, {} laps before lap {}",
+                stint,
+                actual,
+                projected - actual,
+                projected
+            ),
+            DeviationKind::ExtraStop { actual } => {
+                write!(f, "Unplanned stop on lap {} in the final stint", actual)
+            }
+            DeviationKind::WrongDriver { planned, actual } => write!(
+                f,
+                "{} is driving stint {}, planned for {}",
+                actual, stint, planned
+            ),
+        }
+    }
+}
+
+impl PlanTracker {
+    /// Track a race against `plan`.
+    pub fn new(plan: StintPlan) -> Self {
+        PlanTracker {
+            plan,
+            caution_fuel: 0.5,
+            stint: 0,
+            stint_start: 0,
+            caution_laps: 0,
+            last_caution_lap: None,
+            projected: None,
+            on_pit_road: false,
+            driver_checked: false,
+            stops: Vec::new(),
+        }
+    }
+
+    ///
+    /// Fraction of a green flag lap's fuel used by a lap under caution,
+    /// 0.5 by default.
+    pub fn with_caution_fuel(mut self, fraction: f32) -> Self {
+        self.caution_fuel = fraction.clamp(0.0, 1.0);
+        self
+    }
+
+    ///
+    /// Update with the car's current state, returning any new deviations from
+    /// the plan.
+    pub fn update(&mut self, sample: &PlanSample) -> Vec<Deviation> {
+        let mut deviations = Vec::new();
+
+        // A stop ends the current stint
+        let entered_pits = sample.on_pit_road && !self.on_pit_road;
+        self.on_pit_road = sample.on_pit_road;
+        if entered_pits {
+            let kind = if self.is_last_stint() {
+                Some(DeviationKind::ExtraStop { actual: sample.lap })
+            } else {
+                let projected = self.projected.unwrap_or_else(|| self.planned_stop());
+                if sample.lap < projected - 1 {
+                    Some(DeviationKind::EarlyStop {
+                        projected,
+                        actual: sample.lap,
+                    })
+                } else {
+                    None
+                }
+            };
+            if let Some(kind) = kind {
+                deviations.push(Deviation {
+                    lap: sample.lap,
+                    stint: self.stint,
+                    kind,
+                });
+            }
+
+            self.stops.push(sample.lap);
+            self.stint = (self.stint + 1).min(self.plan.stints.len().saturating_sub(1));
+            self.stint_start = sample.lap;
+            self.caution_laps = 0;
+            self.projected = None;
+            self.driver_checked = false;
+        }
+
+        if sample.caution && self.last_caution_lap != Some(sample.lap) {
+            self.last_caution_lap = Some(sample.lap);
+            self.caution_laps += 1;
+        }
+
+        // Check the driver once they're in the car
+        if let (false, Some(actual)) = (self.driver_checked, &sample.driver) {
+            self.driver_checked = true;
+            let planned = self
+                .plan
+                .stints
+                .get(self.stint)
+                .and_then(|s| s.driver.as_ref());
+            if let Some(planned) = planned.filter(|p| *p != actual) {
+                deviations.push(Deviation {
+                    lap: sample.lap,
+                    stint: self.stint,
+                    kind: DeviationKind::WrongDriver {
+                        planned: planned.clone(),
+                        actual: actual.clone(),
+                    },
+                });
+            }
+        }
+
+        if !self.is_last_stint() {
+            let planned = self.planned_stop();
+            let stretched =
+                planned + (self.caution_laps as f32 * (1.0 - self.caution_fuel)).floor() as i32;
+            let (projected, cause) = match sample.fuel {
+                Some(fuel) if sample.lap.saturating_add(fuel.laps_remaining()) < stretched => {
+                    (sample.lap + fuel.laps_remaining(), Cause::Fuel)
+                }
+                _ => (stretched, Cause::Caution),
+            };
+
+            if projected != self.projected.unwrap_or(planned) {
+                deviations.push(Deviation {
+                    lap: sample.lap,
+                    stint: self.stint,
+                    kind: DeviationKind::StopMoved {
+                        planned,
+                        projected,
+                        cause,
+                    },
+                });
+            }
+            self.projected = Some(projected);
+        }
+
+        deviations
+    }
+
+    fn is_last_stint(&self) -> bool {
+        self.stint + 1 >= self.plan.stints.len()
+    }
+
+    /// The current stint's planned stop, counted from when it actually started.
+    fn planned_stop(&self) -> i32 {
+        let laps = self.plan.stints.get(self.stint).map_or(0, |s| s.laps);
+        self.stint_start + laps
+    }
+
+    /// The current stint, from 0.
+    pub fn stint(&self) -> usize {
+        self.stint
+    }
+
+    /// Laps the car has stopped on.
+    pub fn stops(&self) -> &[i32] {
+        &self.stops
+    }
+
+    ///
+    /// Laps the remaining stops are projected at the end of, from the current
+    /// stint's projected stop and the planned length of the stints after it.
+    pub fn projected_stops(&self) -> Vec<i32> {
+        if self.is_last_stint() {
+            return Vec::new();
+        }
+
+        let mut lap = self.projected.unwrap_or_else(|| self.planned_stop());
+        let mut stops = vec![lap];
+        let remaining = self.plan.stints.len() - 1;
+        for stint in self.plan.stints[self.stint + 1..remaining].iter() {
+            lap += stint.laps;
+            stops.push(lap);
+        }
+
+        stops.retain(|lap| *lap < self.plan.race_laps);
+        stops
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn undercut() {
+        let fuel = FuelModel {
+            fuel: 15.0,
+            per_lap: 3.0,
+            capacity: 100.0,
+            fill_rate: 2.0,
+        };
+        let tires = TireModel {
+            age: 20,
+            degradation: 0.5,
+            change_time: 20.0,
+        };
+        let me = CarState {
+            car_idx: 1,
+            gap: 2.0,
+            lap_time: 90.0, // Around 100s on the worn tires
+            pit_lap: None,
+        };
+        let leader = CarState {
+            car_idx: 2,
+            gap: 0.0,
+            lap_time: 100.0,
+            pit_lap: Some(14),
+        };
+
+        let planner = Planner::new(me, fuel, tires, 20.0, 10, 20).with_cars(&[leader]);
+        assert_eq!(planner.window(), 10..15);
+
+        // Fresh tires make stopping early quickest
+        let best = planner.best().unwrap();
+        assert_eq!(best.stop_lap, 10);
+
+        // Stopping after the leader has made their stop loses less ground to them
+        assert_eq!(planner.simulate(10).position, 2);
+        assert_eq!(planner.simulate(14).position, 2);
+        assert!(planner.simulate(14).gap_ahead.unwrap() < planner.simulate(10).gap_ahead.unwrap());
+    }
+
+    #[test]
+    fn window_boundary() {
+        let fuel = FuelModel {
+            fuel: 15.0,
+            per_lap: 3.0,
+            capacity: 100.0,
+            fill_rate: 2.0,
+        };
+        let tires = TireModel {
+            age: 0,
+            degradation: 0.0,
+            change_time: 0.0,
+        };
+        let car = CarState {
+            car_idx: 1,
+            gap: 0.0,
+            lap_time: 90.0,
+            pit_lap: None,
+        };
+
+        // Five laps of fuel from lap 10 runs dry at the end of lap 14
+        let planner = Planner::new(car, fuel, tires, 20.0, 10, 20);
+        assert_eq!(planner.window().last(), Some(14));
+        assert!(!planner.window().contains(&15));
+
+        let short = FuelModel { fuel: 14.9, ..fuel };
+        let planner = Planner::new(car, short, tires, 20.0, 10, 20);
+        assert_eq!(planner.window(), 10..14);
+    }
+
+    #[test]
+    fn stint_plan_deviations() {
+        let plan = StintPlan::new(60)
+            .with_stint(20, Some("Alice"))
+            .with_stint(20, Some("Bob"))
+            .with_stint(20, Some("Alice"));
+        assert_eq!(plan.stops(), vec![20, 40]);
+        assert_eq!(StintPlan::even(61, 3).stops(), vec![21, 41]);
+
+        let mut tracker = PlanTracker::new(plan);
+        let run = |tracker: &mut PlanTracker, laps: std::ops::RangeInclusive<i32>, driver: &str| {
+            let mut deviations = Vec::new();
+            for lap in laps {
+                let sample = PlanSample {
+                    lap,
+                    on_pit_road: false,
+                    caution: (5..=8).contains(&lap),
+                    fuel: None,
+                    driver: Some(driver.to_string()),
+                };
+                deviations.extend(tracker.update(&sample));
+                deviations.extend(tracker.update(&sample));
+            }
+            deviations
+        };
+
+        // Four laps under caution stretch the first stint by two laps
+        let deviations = run(&mut tracker, 1..=21, "Alice");
+        assert_eq!(deviations.len(), 2);
+        assert_eq!(
+            deviations[1].to_string(),
+            "Stint 1 stop lap slipped by 2 laps due to caution"
+        );
+        assert_eq!(tracker.projected_stops(), vec![22, 42]);
+
+        // The wrong driver takes over after the stop
+        let stop = PlanSample {
+            lap: 22,
+            on_pit_road: true,
+            ..Default::default()
+        };
+        assert!(tracker.update(&stop).is_empty());
+        let deviations = run(&mut tracker, 22..=29, "Carol");
+        assert_eq!(tracker.stint(), 1);
+        assert_eq!(
+            deviations[0].kind,
+            DeviationKind::WrongDriver {
+                planned: "Bob".to_string(),
+                actual: "Carol".to_string()
+            }
+        );
+
+        // Fuel falls short of the planned stop on lap 42
+        let fuel = FuelModel {
+            fuel: 30.0,
+            per_lap: 3.0,
+            capacity: 100.0,
+            fill_rate: 2.0,
+        };
+        let short = tracker.update(&PlanSample {
+            lap: 30,
+            fuel: Some(fuel),
+            ..Default::default()
+        });
+        assert_eq!(
+            short[0].to_string(),
+            "Stint 2 stop lap brought forward by 2 laps due to fuel"
+        );
+        assert_eq!(tracker.projected_stops(), vec![40]);
+
+        // Stopping early, then again in the final stint
+        let early = tracker.update(&PlanSample {
+            lap: 35,
+            on_pit_road: true,
+            ..Default::default()
+        });
+        assert_eq!(
+            early[0].kind,
+            DeviationKind::EarlyStop {
+                projected: 40,
+                actual: 35
+            }
+        );
+        tracker.update(&PlanSample {
+            lap: 36,
+            ..Default::default()
+        });
+        let extra = tracker.update(&PlanSample {
+            lap: 50,
+            on_pit_road: true,
+            ..Default::default()
+        });
+        assert_eq!(extra[0].kind, DeviationKind::ExtraStop { actual: 50 });
+        assert_eq!(tracker.stops(), &[22, 35, 50]);
+        assert!(tracker.projected_stops().is_empty());
+    }
+}
@@ -0,0 +1,209 @@
+use crate::session::Driver;
+use serde::{Deserialize, Serialize};
+
+///
+/// A continuous period of a single driver at the wheel of a car.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Stint {
+    pub car_idx: usize,
+    pub team_id: u64,
+    pub team_name: String,
+    pub user_id: i64,
+    pub user_name: String,
+
+    pub start_lap: i32,        // Lap the driver took over the car
+    pub start_time: f64,       // Session time the driver took over the car
+    pub end_lap: Option<i32>,  // Lap the driver handed over the car, None for the current driver
+    pub end_time: Option<f64>, // Session time the driver handed over the car
+}
+
+///
+/// A change of driver in a car.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct DriverSwap {
+    pub car_idx: usize,
+    pub lap: i32,
+    pub session_time: f64,
+    pub from_user_id: i64,
+    pub from_user_name: String,
+    pub to_user_id: i64,
+    pub to_user_name: String,
+}
+
+///
+/// Team Stint Log
+///
+/// Tracks which driver is in each car over a team session, detecting driver
+/// swaps from updates to the session's driver list.
+///
+/// # Examples
+///
+/// ```
+/// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+/// use iracing::team::TeamStintLog;
+/// # let session: iracing::session::SessionDetails =
+/// #     serde_yaml::from_str(&std::fs::read_to_string("./session.yaml").unwrap()).unwrap();
+/// # let (session_time, car_laps) = (0.0, vec![0; 64]);
+///
+/// let mut log = TeamStintLog::new();
+///
+/// for swap in log.update(session_time, &session.drivers.other_drivers, &car_laps) {
+///     println!("{} handed over to {} on lap {}", swap.from_user_name, swap.to_user_name, swap.lap);
+/// }
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct TeamStintLog {
+    stints: Vec<Stint>,
+    laps: Vec<i32>,
+}
+
+impl Stint {
+    ///
+    /// Number of laps completed in this stint.
+    ///
+    /// For the current driver `current_lap` is used as the end of the stint.
+    pub fn laps(&self, current_lap: i32) -> i32 {
+        (self.end_lap.unwrap_or(current_lap) - self.start_lap).max(0)
+    }
+}
+
+impl TeamStintLog {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    ///
+    /// Update the log with the current driver list and per-car laps (`CarIdxLap`).
+    ///
+    /// Returns any driver swaps since the last update. The first time a car is
+    /// seen its driver starts a new stint without reporting a swap.
+    pub fn update(
+        &mut self,
+        session_time: f64,
+        drivers: &[Driver],
+        car_laps: &[i32],
+    ) -> Vec<DriverSwap> {
+        let mut swaps = Vec::new();
+
+        if !car_laps.is_empty() {
+            self.laps = car_laps.to_vec();
+        }
+
+        for driver in drivers.iter().filter(|d| d.is_spectator == 0) {
+            let lap = self.laps.get(driver.index).copied().unwrap_or(0).max(0);
+
+            match self.current_mut(driver.index) {
+                Some(stint) if stint.user_id == driver.user_id => continue,
+                Some(stint) => {
+                    stint.end_lap = Some(lap);
+                    stint.end_time = Some(session_time);
+
+                    swaps.push(DriverSwap {
+                        car_idx: driver.index,
+                        lap,
+                        session_time,
+                        from_user_id: stint.user_id,
+                        from_user_name: stint.user_name.clone(),
+                        to_user_id: driver.user_id,
+                        to_user_name: driver.user_name.clone(),
+                    });
+                }
+                None => {}
+            }
+
+            self.stints.push(Stint {
+                car_idx: driver.index,
+                team_id: driver.team_id,
+                team_name: driver.team_name.clone(),
+                user_id: driver.user_id,
+                user_name: driver.user_name.clone(),
+                start_lap: lap,
+                start_time: session_time,
+                end_lap: None,
+                end_time: None,
+            });
+        }
+
+        swaps
+    }
+
+    fn current_mut(&mut self, car_idx: usize) -> Option<&mut Stint> {
+        self.stints
+            .iter_mut()
+            .rev()
+            .find(|s| s.car_idx == car_idx && s.end_lap.is_none())
+    }
+
+    /// All stints, in the order they started.
+    pub fn stints(&self) -> &[Stint] {
+        &self.stints
+    }
+
+    /// Stints driven in a given car.
+    pub fn stints_for_car(&self, car_idx: usize) -> impl Iterator<Item = &Stint> {
+        self.stints.iter().filter(move |s| s.car_idx == car_idx)
+    }
+
+    /// The stint currently being driven in a given car.
+    pub fn current(&self, car_idx: usize) -> Option<&Stint> {
+        self.stints_for_car(car_idx).find(|s| s.end_lap.is_none())
+    }
+
+    ///
+    /// Total laps driven by each driver of a car, as (user id, user name, laps).
+    pub fn laps_by_driver(&self, car_idx: usize) -> Vec<(i64, String, i32)> {
+        let current_lap = self.laps.get(car_idx).copied().unwrap_or(0).max(0);
+        let mut totals: Vec<(i64, String, i32)> = Vec::new();
+
+        for stint in self.stints_for_car(car_idx) {
+            let laps = stint.laps(current_lap);
+
+            match totals.iter_mut().find(|(id, _, _)| *id == stint.user_id) {
+                Some(total) => total.2 += laps,
+                None => totals.push((stint.user_id, stint.user_name.clone(), laps)),
+            }
+        }
+
+        totals
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::session::SessionDetails;
+
+    fn session() -> SessionDetails {
+        let content = std::fs::read_to_string("./session.yaml").unwrap();
+        serde_yaml::from_str(&content).unwrap()
+    }
+
+    #[test]
+    fn detect_swap() {
+        let mut drivers = session().drivers.other_drivers;
+        let mut laps = vec![0; 7];
+        let mut log = TeamStintLog::new();
+
+        assert!(log.update(0.0, &drivers, &laps).is_empty());
+        assert_eq!(log.current(1).unwrap().user_name, "L W Adamek");
+
+        laps[1] = 12;
+        drivers[1].user_id = 12345;
+        drivers[1].user_name = String::from("Second Driver");
+
+        let swaps = log.update(1200.0, &drivers, &laps);
+        assert_eq!(swaps.len(), 1);
+        assert_eq!(swaps[0].car_idx, 1);
+        assert_eq!(swaps[0].lap, 12);
+        assert_eq!(swaps[0].from_user_id, 81797);
+
+        laps[1] = 20;
+        log.update(1900.0, &drivers, &laps);
+
+        let totals = log.laps_by_driver(1);
+        assert_eq!(totals[0], (81797, String::from("L W Adamek"), 12));
+        assert_eq!(totals[1], (12345, String::from("Second Driver"), 8));
+    }
+}
@@ -1,38 +1,109 @@
-use crate::fps::Fps;
-use crate::session::*;
+use crate::pool::{BufferPool, PooledBuffer};
+use bitflags::bitflags;
 use encoding_rs::mem::decode_latin1;
 use serde::{Deserialize, Serialize};
-use serde_yaml::from_str as yaml_from;
-use std::convert::TryInto;
+use std::convert::{TryFrom, TryInto};
 use std::default::Default;
 use std::error::Error;
-use std::ffi::CStr;
 use std::fmt::{self, Display};
-use std::io::Result as IOResult;
-use std::mem::transmute;
+use std::mem::size_of;
 use std::os::raw::{c_char, c_void};
-use std::os::windows::raw::HANDLE;
 use std::slice::from_raw_parts;
-use std::time::Duration;
+use std::sync::Arc;
+
+#[cfg(target_os = "windows")]
+use crate::fps::Fps;
+#[cfg(target_os = "windows")]
+use crate::health::{Health, Heartbeat};
+#[cfg(target_os = "windows")]
+use crate::sampler::Sampler;
+#[cfg(target_os = "windows")]
+use crate::session::*;
+#[cfg(target_os = "windows")]
+use crate::yaml::decode;
+#[cfg(target_os = "windows")]
+use std::cell::RefCell;
+#[cfg(target_os = "windows")]
+use std::collections::VecDeque;
+#[cfg(target_os = "windows")]
+use std::io::Result as IOResult;
+#[cfg(target_os = "windows")]
+use std::os::windows::raw::HANDLE;
+#[cfg(target_os = "windows")]
+use std::ptr::{null, null_mut};
+#[cfg(target_os = "windows")]
+use std::time::{Duration, Instant};
+#[cfg(target_os = "windows")]
 use winapi::shared::minwindef::LPVOID;
+#[cfg(target_os = "windows")]
+use winapi::shared::winerror::WAIT_TIMEOUT;
+#[cfg(target_os = "windows")]
 use winapi::um::errhandlingapi::GetLastError;
+#[cfg(target_os = "windows")]
 use winapi::um::handleapi::CloseHandle;
+#[cfg(target_os = "windows")]
 use winapi::um::memoryapi::{MapViewOfFile, OpenFileMappingW, FILE_MAP_READ};
+#[cfg(target_os = "windows")]
 use winapi::um::minwinbase::LPSECURITY_ATTRIBUTES;
-use winapi::um::synchapi::{CreateEventW, ResetEvent, WaitForSingleObject};
+#[cfg(target_os = "windows")]
+use winapi::um::synchapi::{
+    CreateEventW, ResetEvent, SetEvent, WaitForMultipleObjects, WaitForSingleObject,
+};
+#[cfg(target_os = "windows")]
+use winapi::um::winbase::{INFINITE, WAIT_ABANDONED_0, WAIT_FAILED, WAIT_OBJECT_0};
 
 /// System path where the shared memory map is located.
 pub const TELEMETRY_PATH: &str = r"Local\IRSDKMemMapFileName";
 
-/// Magic number specifying an unlimited number of laps
-pub const UNLIMITED_LAPS: i32 = 32767;
-
-/// Magic number specifying unlimited time
-pub const UNLIMITED_TIME: f32 = 604800.0;
+pub use crate::validity::{UNLIMITED_LAPS, UNLIMITED_TIME};
 
+#[cfg(target_os = "windows")]
 const DATA_EVENT_NAME: &str = r"Local\IRSDKDataValidEvent";
 
-#[derive(Copy, Clone, Debug)]
+/// Size of the telemetry header (bytes)
+const HEADER_SIZE: usize = 112;
+
+/// Size of a variable header (bytes)
+const VALUE_HEADER_SIZE: usize = 144;
+
+// The parsers read the sim's layout field by field, so the structs must match it
+const _: () = assert!(size_of::<Header>() == HEADER_SIZE);
+const _: () = assert!(size_of::<ValueHeader>() == VALUE_HEADER_SIZE);
+
+bitflags! {
+    ///
+    /// Status bits of the telemetry header.
+    #[derive(Default)]
+    pub struct Status: i32 {
+        /// The sim is running and writing telemetry
+        const CONNECTED = 0x01;
+    }
+}
+
+///
+/// Telemetry Header
+///
+/// The header at the start of the telemetry memory map and of IBT files,
+/// describing where the session info, variable headers and data buffers are.
+///
+/// The fields are laid out as the sim writes them. The accessors clamp the
+/// sizes and offsets to be non-negative, and only list the data buffers in
+/// use.
+///
+/// # Examples
+///
+/// ```no_run
+/// use iracing::ibt::IBT;
+///
+/// let ibt = IBT::open("telemetry.ibt").unwrap();
+/// let header = ibt.header();
+/// println!("{} vars at {} Hz", header.var_count(), header.tick_rate());
+///
+/// for buffer in header.buffers() {
+///     println!("Buffer {} at {} holds tick {}", buffer.index, buffer.offset, buffer.ticks);
+/// }
+/// ```
+#[derive(Copy, Clone, Debug, Serialize)]
 #[repr(C)]
 pub struct Header {
     pub version: i32,              // Telemetry version
@@ -56,13 +127,160 @@ pub struct Header {
 ///
 /// Calling `sample()` on a Blocking interface will block until a new telemetry sample is made available.
 ///
+#[cfg(target_os = "windows")]
 pub struct Blocking {
     origin: *const c_void,
-    header: Header,
+    reader: RefCell<SampleReader>,
     event_handle: HANDLE,
+    cursor: RefCell<TickCursor>,
+    pending: RefCell<VecDeque<Sample>>,
+    cancel: Option<CancelToken>,
+}
+
+///
+/// Cancel Token
+///
+/// Interrupts a `Blocking` sampler waiting for telemetry, from any thread, so
+/// an application can shut down without waiting out the timeout. Waits fail
+/// with `TelemetryError::CANCELLED` until the token is reset.
+///
+/// # Examples
+///
+/// ```no_run
+/// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+/// use iracing::telemetry::{CancelToken, Connection, TelemetryError};
+/// use std::time::{Duration, Instant};
+///
+/// let token = CancelToken::new()?;
+/// let sampler = Connection::new()?.blocking()?.with_cancel(token.clone());
+///
+/// // Called by the UI thread on shutdown
+/// let shutdown = token.clone();
+/// std::thread::spawn(move || shutdown.cancel());
+///
+/// match sampler.sample_until(Instant::now() + Duration::from_secs(10)) {
+///     Ok(sample) => println!("Tick {}", sample.tick()),
+///     Err(e) => match e.downcast_ref::<TelemetryError>() {
+///         Some(TelemetryError::CANCELLED) => println!("Shutting down"),
+///         _ => return Err(e),
+///     },
+/// }
+/// # Ok(())
+/// # }
+/// ```
+#[cfg(target_os = "windows")]
+#[derive(Debug, Clone)]
+pub struct CancelToken {
+    event: Arc<Event>,
 }
 
-#[derive(Copy, Clone, Debug)]
+/// An owned Windows event handle.
+#[cfg(target_os = "windows")]
+#[derive(Debug)]
+struct Event(HANDLE);
+
+// Event handles may be signalled and waited on from any thread
+#[cfg(target_os = "windows")]
+unsafe impl Send for Event {}
+#[cfg(target_os = "windows")]
+unsafe impl Sync for Event {}
+
+///
+/// How a sampler delivers ticks to a consumer which reads slower or faster
+/// than the sim writes them.
+#[derive(Debug, Copy, Clone, Default, PartialEq, Eq)]
+pub enum ConsumptionMode {
+    /// Skip straight to the newest tick, dropping any the consumer missed
+    #[default]
+    Latest,
+
+    /// Deliver every tick in order. Ticks still held in the shared memory's
+    /// buffers are queued when the consumer falls behind.
+    EveryTick,
+
+    /// Deliver at most every nth tick
+    Decimated(u32),
+}
+
+///
+/// Whether a sample was taken while driving or while watching a replay, so
+/// replay scrubbing isn't mixed into live logs.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum SampleOrigin {
+    Live,
+
+    /// Replay playback, at a given frame (`ReplayFrameNum`)
+    Replay {
+        frame: i32,
+    },
+}
+
+///
+/// Sample Reader
+///
+/// Reads samples from a memory map, keeping state between reads: the
+/// variable headers, which are shared by samples while unchanged, and a pool
+/// of data buffers so repeated sampling doesn't allocate.
+///
+/// `Connection` and `Blocking` keep their own reader; this is for reading a
+/// copy of the memory map, such as a recording.
+///
+/// # Examples
+///
+/// ```
+/// use iracing::pool::BufferPool;
+/// use iracing::recording::SnapshotBuilder;
+/// use iracing::telemetry::{SampleReader, Value};
+///
+/// // A copy of a memory map, 8-byte aligned as the sim's is
+/// let snapshot = SnapshotBuilder::new(1)
+///     .with_value("Speed", Value::FLOAT(42.0))
+///     .build();
+/// let mut memory = vec![0u64; (snapshot.len() + 7) / 8];
+/// unsafe {
+///     let to = memory.as_mut_ptr() as *mut u8;
+///     std::ptr::copy_nonoverlapping(snapshot.as_ptr(), to, snapshot.len());
+/// }
+///
+/// let mut reader = SampleReader::new(BufferPool::new(4));
+/// for _ in 0..60 {
+///     // `memory` holds the whole map and outlives the read
+///     let location = memory.as_ptr() as *const std::ffi::c_void;
+///     let sample = unsafe { reader.read(location) }.unwrap();
+/// }
+/// ```
+#[derive(Debug)]
+pub struct SampleReader {
+    headers: Option<(Vec<u8>, Arc<[ValueHeader]>)>,
+    pool: BufferPool,
+}
+
+///
+/// Tick Cursor
+///
+/// Chooses which of the ticks held in the data buffers to deliver next,
+/// following a `ConsumptionMode`, and counts ticks which were lost.
+///
+/// # Examples
+///
+/// ```
+/// use iracing::telemetry::{ConsumptionMode, TickCursor};
+///
+/// let mut cursor = TickCursor::new(ConsumptionMode::EveryTick);
+/// assert_eq!(cursor.select(&[10, 8, 9]), vec![10]);
+///
+/// // The consumer was slow, so two ticks arrived at once
+/// assert_eq!(cursor.select(&[11, 12, 10]), vec![11, 12]);
+/// assert_eq!(cursor.missed(), 0);
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct TickCursor {
+    mode: ConsumptionMode,
+    last: Option<i32>,
+    missed: u64,
+}
+
+#[derive(Copy, Clone, Debug, Serialize)]
 #[repr(C)]
 struct ValueBuffer {
     pub ticks: i32,        // Tick count
@@ -70,6 +288,15 @@ struct ValueBuffer {
     pub padding: [u32; 2], // (16-byte align) Padding
 }
 
+///
+/// A data buffer of the telemetry memory map.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct BufferInfo {
+    pub index: usize,
+    pub ticks: i32,    // Tick of the sample held in the buffer
+    pub offset: usize, // Offset of the buffer from the start of the map
+}
+
 #[derive(Clone)]
 #[repr(C)]
 struct ValueHeader {
@@ -98,11 +325,29 @@ pub struct ValueDescription {
 ///
 /// Sample represents a single sample of telemetry data from iRacing
 /// either from live telemetry, or from a telemetry file.
-#[derive(Debug, Default)]
+///
+/// # Performance
+///
+/// Taking a sample copies the data buffer once, into a pooled buffer;
+/// variable headers are shared between samples while they are unchanged, and
+/// values are only decoded when read with `get`. Samples are cheap to clone.
+/// With 300 channels (`cargo bench --features telemetry`):
+///
+/// | Operation                         | Time    |
+/// |-----------------------------------|---------|
+/// | Parse header                      | 11 ns   |
+/// | Copy sample                       | 6.1 µs  |
+/// | Copy sample with a `SampleReader` | 1.8 µs  |
+/// | Look up first value               | 0.2 µs  |
+/// | Look up last value                | 1.6 µs  |
+///
+/// So a 60Hz consumer reading a few dozen values per tick uses well under 1%
+/// of a core.
+#[derive(Debug, Clone, Default)]
 pub struct Sample {
     tick: i32,
-    buffer: Vec<u8>,
-    values: Vec<ValueHeader>,
+    buffer: Arc<PooledBuffer>,
+    values: Arc<[ValueHeader]>,
 }
 
 /// Telemetry Value
@@ -117,12 +362,10 @@ pub struct Sample {
 /// # Examples
 ///
 /// ## Known, Expected Data Type
-/// ```
+/// ```no_run
 /// # fn main() -> Result<(), Box<dyn std::error::Error>> {
-/// # use iracing::telemetry::{Connection, Value};
-/// # use std::time::Duration;
-/// # let sampler = Connection::new()?.blocking()?;
-/// # let sample = sampler.sample(Duration::from_millis(50))?;
+/// # use iracing::telemetry::{Sample, Value};
+/// # let sample = Sample::default();
 /// use std::convert::TryInto;
 ///
 /// let gear: i32 = sample.get("Gear").unwrap().try_into().unwrap();
@@ -132,12 +375,10 @@ pub struct Sample {
 ///
 /// ## Unknown data type
 ///
-/// ```
+/// ```no_run
 /// # fn main() -> Result<(), Box<dyn std::error::Error>> {
-/// # use iracing::telemetry::{Connection, Value};
-/// # use std::time::Duration;
-/// # let sampler = Connection::new()?.blocking()?;
-/// # let sample = sampler.sample(Duration::from_millis(50))?;
+/// # use iracing::telemetry::{Sample, Value};
+/// # let sample = Sample::default();
 /// match sample.get("some_key") {
 ///     Err(err) => println!("Didn't find that value: {}", err),
 ///     Ok(value) => match value {
@@ -165,6 +406,7 @@ pub enum Value {
     IntVec(Vec<i32>),
     FloatVec(Vec<f32>),
     BoolVec(Vec<bool>),
+    BitsVec(Vec<u32>),
 }
 
 impl From<i32> for Value {
@@ -185,9 +427,12 @@ impl Value {
     pub fn size(&self) -> usize {
         match self {
             Self::CHAR(_) | Self::BOOL(_) | Self::BoolVec(_) => 1,
-            Self::INT(_) | Self::BITS(_) | Self::FLOAT(_) | Self::IntVec(_) | Self::FloatVec(_) => {
-                4
-            }
+            Self::INT(_)
+            | Self::BITS(_)
+            | Self::FLOAT(_)
+            | Self::IntVec(_)
+            | Self::FloatVec(_)
+            | Self::BitsVec(_) => 4,
             Self::DOUBLE(_) => 8,
             Self::UNKNOWN(_) => 1,
         }
@@ -240,6 +485,46 @@ impl TryInto<f64> for Value {
     }
 }
 
+impl TryInto<Vec<i32>> for Value {
+    type Error = &'static str;
+
+    fn try_into(self) -> Result<Vec<i32>, Self::Error> {
+        match self {
+            Self::IntVec(v) => Ok(v),
+            Self::INT(n) => Ok(vec![n]),
+            Self::BitsVec(v) => Ok(v.into_iter().map(|n| n as i32).collect()),
+            Self::BITS(n) => Ok(vec![n as i32]),
+            _ => Err("Value is not a signed 4-byte integer array"),
+        }
+    }
+}
+
+impl TryInto<Vec<u32>> for Value {
+    type Error = &'static str;
+
+    fn try_into(self) -> Result<Vec<u32>, Self::Error> {
+        match self {
+            Self::BitsVec(v) => Ok(v),
+            Self::BITS(n) => Ok(vec![n]),
+            Self::IntVec(v) => Ok(v.into_iter().map(|n| n as u32).collect()),
+            Self::INT(n) => Ok(vec![n as u32]),
+            _ => Err("Value is not a 4-byte integer array"),
+        }
+    }
+}
+
+impl TryInto<Vec<f32>> for Value {
+    type Error = &'static str;
+
+    fn try_into(self) -> Result<Vec<f32>, Self::Error> {
+        match self {
+            Self::FloatVec(v) => Ok(v),
+            Self::FLOAT(n) => Ok(vec![n]),
+            _ => Err("Value is not a float array"),
+        }
+    }
+}
+
 impl From<Value> for bool {
     fn from(value: Value) -> Self {
         match value {
@@ -258,6 +543,102 @@ impl From<Value> for Vec<bool> {
     }
 }
 
+impl Value {
+    /// Every element as an f64, for comparing values of any type.
+    fn as_f64s(&self) -> Vec<f64> {
+        match self {
+            Self::CHAR(v) => vec![*v as f64],
+            Self::BOOL(v) => vec![*v as u8 as f64],
+            Self::INT(v) => vec![*v as f64],
+            Self::BITS(v) => vec![*v as f64],
+            Self::FLOAT(v) => vec![*v as f64],
+            Self::DOUBLE(v) => vec![*v],
+            Self::UNKNOWN(_) => Vec::new(),
+            Self::IntVec(v) => v.iter().map(|n| *n as f64).collect(),
+            Self::FloatVec(v) => v.iter().map(|n| *n as f64).collect(),
+            Self::BoolVec(v) => v.iter().map(|b| *b as u8 as f64).collect(),
+            Self::BitsVec(v) => v.iter().map(|n| *n as f64).collect(),
+        }
+    }
+
+    ///
+    /// True if two values differ by more than `tolerance` in any element.
+    ///
+    /// Values of different types or lengths always differ. NaN is equal to NaN.
+    pub fn differs(&self, other: &Value, tolerance: f64) -> bool {
+        if std::mem::discriminant(self) != std::mem::discriminant(other) {
+            return true;
+        }
+
+        let (a, b) = (self.as_f64s(), other.as_f64s());
+        a.len() != b.len()
+            || a.iter().zip(b.iter()).any(|(a, b)| {
+                if a.is_nan() || b.is_nan() {
+                    a.is_nan() != b.is_nan()
+                } else {
+                    (a - b).abs() > tolerance
+                }
+            })
+    }
+}
+
+///
+/// A variable which differs between two samples.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ChangedVar {
+    pub name: String,
+    pub before: Option<Value>, // None if the variable was added
+    pub after: Option<Value>,  // None if the variable was removed
+}
+
+///
+/// How far each channel may move before it counts as changed.
+///
+/// # Examples
+///
+/// ```
+/// use iracing::telemetry::Tolerances;
+///
+/// // Ignore sensor noise, but report any change of gear
+/// let tolerances = Tolerances::new()
+///     .with_default(0.01)
+///     .with_channel("RPM", 25.0)
+///     .with_channel("Gear", 0.0);
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct Tolerances {
+    default: f64,
+    channels: Vec<(String, f64)>,
+}
+
+impl Tolerances {
+    /// No tolerance - any change is reported.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Tolerance for channels without their own.
+    pub fn with_default(mut self, tolerance: f64) -> Self {
+        self.default = tolerance;
+        self
+    }
+
+    pub fn with_channel(mut self, name: &str, tolerance: f64) -> Self {
+        self.channels.retain(|(n, _)| n != name);
+        self.channels.push((name.to_owned(), tolerance));
+        self
+    }
+
+    /// Tolerance for a channel.
+    pub fn get(&self, name: &str) -> f64 {
+        self.channels
+            .iter()
+            .find(|(n, _)| n == name)
+            .map(|(_, t)| *t)
+            .unwrap_or(self.default)
+    }
+}
+
 impl ValueHeader {
     ///
     /// Maximum length of a variable name/unit
@@ -269,19 +650,107 @@ impl ValueHeader {
 
     /// Convert the name from a c_char[32] to a rust String
     pub fn name(&self) -> String {
-        let name = unsafe { CStr::from_ptr(self._name.as_ptr()) };
-        decode_latin1(name.to_bytes()).to_string()
+        latin1(&self._name)
+    }
+
+    /// Compare the name without decoding it.
+    fn is_named(&self, name: &str) -> bool {
+        let name = name.as_bytes();
+        let len = self
+            ._name
+            .iter()
+            .position(|c| *c == 0)
+            .unwrap_or(self._name.len());
+
+        len == name.len()
+            && self._name[..len]
+                .iter()
+                .zip(name)
+                .all(|(a, b)| *a as u8 == *b)
     }
 
     pub fn description(&self) -> String {
-        let description = unsafe { CStr::from_ptr(self._description.as_ptr()) };
-        decode_latin1(description.to_bytes()).to_string()
+        latin1(&self._description)
     }
 
     pub fn unit(&self) -> String {
-        let unit = unsafe { CStr::from_ptr(self._unit.as_ptr()) };
-        decode_latin1(unit.to_bytes()).to_string()
+        latin1(&self._unit)
+    }
+
+    ///
+    /// Read a variable header from its little-endian layout, as written by the
+    /// sim. The bytes may be at any alignment.
+    ///
+    /// Returns None if `raw` is too short to hold a header.
+    fn from_bytes(raw: &[u8]) -> Option<ValueHeader> {
+        let raw = raw.get(..VALUE_HEADER_SIZE)?;
+        let chars = |field: &mut [c_char], at: usize| {
+            for (c, b) in field.iter_mut().zip(&raw[at..]) {
+                *c = *b as c_char;
+            }
+        };
+
+        let mut vh = ValueHeader {
+            value_type: le_i32(raw, 0)?,
+            offset: le_i32(raw, 4)?,
+            count: le_i32(raw, 8)?,
+            count_as_time: raw[12] != 0,
+            ..ValueHeader::default()
+        };
+        chars(&mut vh._name, 16);
+        chars(&mut vh._description, 48);
+        chars(&mut vh._unit, 112);
+
+        Some(vh)
     }
+
+    /// Write the variable header in the layout read by `from_bytes`.
+    fn write_bytes(&self, out: &mut Vec<u8>) {
+        out.extend_from_slice(&self.value_type.to_le_bytes());
+        out.extend_from_slice(&self.offset.to_le_bytes());
+        out.extend_from_slice(&self.count.to_le_bytes());
+        out.extend_from_slice(&[self.count_as_time as u8, 0, 0, 0]);
+        for field in [&self._name[..], &self._description[..], &self._unit[..]].iter() {
+            out.extend(field.iter().map(|c| *c as u8));
+        }
+    }
+}
+
+/// Raw bytes of a run of variable headers, in the layout written by the sim.
+fn to_bytes(headers: &[ValueHeader]) -> Vec<u8> {
+    let mut out = Vec::with_capacity(headers.len() * VALUE_HEADER_SIZE);
+    for vh in headers.iter() {
+        vh.write_bytes(&mut out);
+    }
+    out
+}
+
+/// Names of a run of variable headers, ignoring any partial header at the end.
+pub(crate) fn value_names(raw: &[u8]) -> Vec<String> {
+    from_bytes(raw).iter().map(ValueHeader::name).collect()
+}
+
+/// Parse a run of variable headers, ignoring any partial header at the end.
+fn from_bytes(raw: &[u8]) -> Vec<ValueHeader> {
+    raw.chunks_exact(VALUE_HEADER_SIZE)
+        .filter_map(ValueHeader::from_bytes)
+        .collect()
+}
+
+/// Little-endian `i32` at byte `at`, or None past the end of `bytes`.
+fn le_i32(bytes: &[u8], at: usize) -> Option<i32> {
+    let word = bytes.get(at..at.checked_add(4)?)?;
+    Some(i32::from_le_bytes([word[0], word[1], word[2], word[3]]))
+}
+
+/// Decode a NUL terminated Latin-1 field, stopping at the end of the field if there is no NUL.
+fn latin1(chars: &[c_char]) -> String {
+    let bytes: Vec<u8> = chars
+        .iter()
+        .take_while(|c| **c != 0)
+        .map(|c| *c as u8)
+        .collect();
+    decode_latin1(&bytes).to_string()
 }
 
 impl Default for ValueHeader {
@@ -315,18 +784,243 @@ impl fmt::Debug for ValueHeader {
 }
 
 impl Header {
-    fn latest_buffer(&self) -> (i32, ValueBuffer) {
-        let mut latest_tick: i32 = 0;
-        let mut buffer = self.buffers[0];
-
-        for b in self.buffers.iter() {
-            if b.ticks > latest_tick {
-                buffer = *b;
-                latest_tick = b.ticks;
-            }
+    const READ_ATTEMPTS: usize = 4;
+
+    /// Telemetry format version.
+    pub fn version(&self) -> i32 {
+        self.version
+    }
+
+    /// Status bits.
+    pub fn status(&self) -> Status {
+        Status::from_bits_truncate(self.status)
+    }
+
+    /// True while the sim is running and writing telemetry.
+    pub fn is_connected(&self) -> bool {
+        self.status().contains(Status::CONNECTED)
+    }
+
+    /// Samples written per second (Hz).
+    pub fn tick_rate(&self) -> i32 {
+        self.tick_rate
+    }
+
+    /// Increments each time the session info is updated.
+    pub fn session_info_version(&self) -> i32 {
+        self.session_info_version
+    }
+
+    /// Offset and length of the session info.
+    pub fn session_info_range(&self) -> (usize, usize) {
+        (
+            self.session_info_offset.max(0) as usize,
+            self.session_info_length.max(0) as usize,
+        )
+    }
+
+    /// Number of telemetry variables.
+    pub fn var_count(&self) -> usize {
+        self.n_vars.max(0) as usize
+    }
+
+    /// Offset of the variable headers.
+    pub fn var_header_offset(&self) -> usize {
+        self.header_offset.max(0) as usize
+    }
+
+    /// Length of a single sample in a data buffer.
+    pub fn buffer_length(&self) -> usize {
+        self.buffer_length.max(0) as usize
+    }
+
+    /// The data buffers in use.
+    pub fn buffers(&self) -> Vec<BufferInfo> {
+        self.buffers
+            .iter()
+            .take(self.n_buffers.clamp(0, 4) as usize)
+            .enumerate()
+            .map(|(index, b)| BufferInfo {
+                index,
+                ticks: b.ticks,
+                offset: b.offset.max(0) as usize,
+            })
+            .collect()
+    }
+
+    ///
+    /// Read a header from the start of a copy of the memory map.
+    ///
+    /// Every field is read as little-endian from the bytes, which may be at
+    /// any alignment, so buffers received over the network can be parsed the
+    /// same as the memory map.
+    ///
+    /// Returns None if `bytes` is too short to hold a header.
+    pub fn from_bytes(bytes: &[u8]) -> Option<Header> {
+        let bytes = bytes.get(..HEADER_SIZE)?;
+        let int = |idx: usize| le_i32(bytes, idx * 4).unwrap_or(0);
+        let buffer = |idx: usize| ValueBuffer {
+            ticks: int(12 + idx * 4),
+            offset: int(13 + idx * 4),
+            padding: [int(14 + idx * 4) as u32, int(15 + idx * 4) as u32],
+        };
+
+        Some(Header {
+            version: int(0),
+            status: int(1),
+            tick_rate: int(2),
+            session_info_version: int(3),
+            session_info_length: int(4),
+            session_info_offset: int(5),
+            n_vars: int(6),
+            header_offset: int(7),
+            n_buffers: int(8),
+            buffer_length: int(9),
+            padding: [int(10) as u32, int(11) as u32],
+            buffers: [buffer(0), buffer(1), buffer(2), buffer(3)],
+        })
+    }
+
+    ///
+    /// Copy the header from the start of the memory map at `from_loc`.
+    ///
+    /// The header is copied as bytes, so the sim updating it during the copy
+    /// can't produce an invalid value, then parsed with `from_bytes`.
+    ///
+    /// # Safety
+    ///
+    /// `from_loc` must point to at least a header's worth of readable memory.
+    unsafe fn read_live(from_loc: *const c_void) -> Header {
+        let raw = std::ptr::read_volatile(from_loc as *const [u8; HEADER_SIZE]);
+        Header::from_bytes(&raw).expect("copy holds a whole header")
+    }
+
+    ///
+    /// Number of bytes of the memory map described by this header, from its
+    /// start to the end of the furthest of the session info, variable headers
+    /// and data buffers.
+    pub fn map_size(&self) -> usize {
+        let session_info =
+            self.session_info_offset.max(0) as usize + self.session_info_length.max(0) as usize;
+        let var_headers =
+            self.header_offset.max(0) as usize + self.n_vars.max(0) as usize * VALUE_HEADER_SIZE;
+        let buffers = self
+            .buffers
+            .iter()
+            .take(self.n_buffers.clamp(0, 4) as usize)
+            .map(|b| b.offset.max(0) as usize + self.buffer_length.max(0) as usize)
+            .max()
+            .unwrap_or(0);
+
+        HEADER_SIZE.max(session_info).max(var_headers).max(buffers)
+    }
+
+    ///
+    /// Check the header describes a valid layout within `len` bytes, so it can
+    /// safely be passed to `telemetry`.
+    pub(crate) fn validate(&self, len: usize) -> Result<(), String> {
+        if self.n_vars < 0 || self.header_offset < 0 || self.buffer_length < 0 {
+            return Err(String::from("Negative size or offset in header"));
+        }
+        if !(1..=4).contains(&self.n_buffers) {
+            return Err(format!("Invalid buffer count {}", self.n_buffers));
+        }
+        if self.session_info_offset < 0 || self.session_info_length < 0 {
+            return Err(String::from("Negative session info offset or length"));
+        }
+        if self
+            .buffers
+            .iter()
+            .enumerate()
+            .any(|(i, b)| b.offset < 0 || (i >= self.n_buffers as usize && b.ticks > 0))
+        {
+            return Err(String::from("Invalid data buffer"));
+        }
+        if self.map_size() > len {
+            return Err(format!(
+                "Header describes {} bytes but only {} are available",
+                self.map_size(),
+                len
+            ));
+        }
+
+        Ok(())
+    }
+
+    /// Offset of a data buffer, or 0 if there is no such buffer.
+    pub(crate) fn buffer_offset(&self, idx: usize) -> usize {
+        self.buffers
+            .get(idx)
+            .map(|b| b.offset.max(0) as usize)
+            .unwrap_or(0)
+    }
+
+    ///
+    /// Index and tick count of the newest data buffer.
+    ///
+    /// Only the `n_buffers` in use are considered, and buffers without a
+    /// valid offset are skipped.
+    pub(crate) fn latest_buffer(&self) -> Option<(usize, i32)> {
+        if self.buffer_length <= 0 {
+            return None;
+        }
+
+        self.buffers
+            .iter()
+            .take(self.n_buffers.clamp(0, 4) as usize)
+            .enumerate()
+            .filter(|(_, b)| b.offset > 0)
+            .max_by_key(|(_, b)| b.ticks)
+            .map(|(idx, b)| (idx, b.ticks))
+    }
+
+    ///
+    /// Copy data buffer `idx` of the memory map at `from_loc`.
+    ///
+    /// Returns None if the sim started writing a newer tick to the buffer
+    /// while it was being copied.
+    fn read_buffer(
+        &self,
+        from_loc: *const c_void,
+        idx: usize,
+        reader: &mut SampleReader,
+    ) -> Option<Sample> {
+        let buffer = self.buffers[idx];
+        let values = reader.pool.copy(self.var_buffer(buffer, from_loc));
+
+        let live = unsafe { Self::read_live(from_loc) };
+        if live.buffers[idx].ticks != buffer.ticks {
+            return None;
         }
 
-        (latest_tick, buffer)
+        Some(Sample::new(
+            buffer.ticks,
+            self.shared_var_headers(from_loc, &mut reader.headers),
+            values,
+        ))
+    }
+
+    ///
+    /// Variable headers of the memory map at `from_loc`, shared with the
+    /// cached copy if they haven't changed.
+    ///
+    /// The headers are fixed for the life of a session, so comparing them is
+    /// much cheaper than copying them for every sample.
+    fn shared_var_headers(
+        &self,
+        from_loc: *const c_void,
+        cache: &mut Option<(Vec<u8>, Arc<[ValueHeader]>)>,
+    ) -> Arc<[ValueHeader]> {
+        let current = self.var_header_bytes(from_loc);
+
+        match cache {
+            Some((raw, cached)) if raw.as_slice() == current => cached.clone(),
+            _ => {
+                let headers: Arc<[ValueHeader]> = Arc::from(from_bytes(current));
+                *cache = Some((current.to_vec(), headers.clone()));
+                headers
+            }
+        }
     }
 
     fn var_buffer(&self, lb: ValueBuffer, from_loc: *const c_void) -> &[u8] {
@@ -336,49 +1030,392 @@ impl Header {
         unsafe { from_raw_parts(buffer_loc as *const u8, sz) }
     }
 
-    fn get_var_header(&self, from_loc: *const c_void) -> &[ValueHeader] {
-        let n_vars = self.n_vars as usize;
-        let header_loc = from_loc as usize + self.header_offset as usize;
+    /// Raw variable headers of the memory map at `from_loc`.
+    fn var_header_bytes(&self, from_loc: *const c_void) -> &[u8] {
+        let header_loc = from_loc as usize + self.header_offset.max(0) as usize;
+
+        unsafe {
+            from_raw_parts(
+                header_loc as *const u8,
+                self.var_count() * VALUE_HEADER_SIZE,
+            )
+        }
+    }
+
+    /// Tick count of each data buffer in use.
+    #[cfg(target_os = "windows")]
+    pub(crate) fn buffer_ticks(&self) -> Vec<i32> {
+        self.buffers
+            .iter()
+            .take(self.n_buffers.clamp(0, 4) as usize)
+            .map(|b| b.ticks)
+            .collect()
+    }
+
+    /// Read the data buffer holding a given tick, if it is still held.
+    #[cfg(target_os = "windows")]
+    fn telemetry_at(
+        &self,
+        from_loc: *const c_void,
+        tick: i32,
+        reader: &mut SampleReader,
+    ) -> Option<Sample> {
+        let idx = self
+            .buffers
+            .iter()
+            .take(self.n_buffers.clamp(0, 4) as usize)
+            .position(|b| b.ticks == tick && b.offset > 0)?;
+
+        self.read_buffer(from_loc, idx, reader)
+    }
+
+    ///
+    /// Read the newest data buffer of the memory map at `from_loc`.
+    ///
+    /// The sim cycles through its data buffers, so the tick counts are re-read
+    /// from `from_loc` rather than taken from this header, which may be an
+    /// older copy. The read is retried if the buffer is overwritten while it
+    /// is copied.
+    ///
+    /// # Safety
+    ///
+    /// `from_loc` must point to a mapped telemetry memory map, such as the
+    /// one a `Connection` opens, which stays mapped for the duration of the
+    /// call.
+    pub unsafe fn telemetry(
+        &self,
+        from_loc: *const c_void,
+    ) -> Result<Sample, Box<dyn std::error::Error>> {
+        Self::read_latest(from_loc, &mut SampleReader::new(BufferPool::new(0)))
+    }
+
+    ///
+    /// Read every data buffer of the memory map at `from_loc`, oldest first.
+    ///
+    /// The sim keeps the last few ticks in its buffers, so a consumer which
+    /// woke up late can fill in what it missed. Buffers overwritten while being
+    /// copied are left out, so check the ticks for gaps with `missing_ticks`.
+    ///
+    /// # Safety
+    ///
+    /// `from_loc` must point to a mapped telemetry memory map, such as the
+    /// one a `Connection` opens, which stays mapped for the duration of the
+    /// call.
+    pub unsafe fn history(&self, from_loc: *const c_void) -> Vec<Sample> {
+        Self::read_history(from_loc, None, &mut SampleReader::new(BufferPool::new(0)))
+    }
+
+    /// Read every data buffer newer than `after`, oldest first.
+    fn read_history(
+        from_loc: *const c_void,
+        after: Option<i32>,
+        reader: &mut SampleReader,
+    ) -> Vec<Sample> {
+        let header = unsafe { Self::read_live(from_loc) };
+
+        let mut held: Vec<(usize, i32)> = header
+            .buffers
+            .iter()
+            .take(header.n_buffers.clamp(0, 4) as usize)
+            .enumerate()
+            .filter(|(_, b)| b.ticks > 0 && b.offset > 0 && after.is_none_or(|t| b.ticks > t))
+            .map(|(idx, b)| (idx, b.ticks))
+            .collect();
+        held.sort_unstable_by_key(|(_, tick)| *tick);
+
+        held.into_iter()
+            .filter_map(|(idx, _)| header.read_buffer(from_loc, idx, reader))
+            .collect()
+    }
 
-        let content = unsafe { from_raw_parts(header_loc as *const ValueHeader, n_vars) };
+    ///
+    /// Read the newest data buffer, reusing the reader's headers and buffers.
+    fn read_latest(
+        from_loc: *const c_void,
+        reader: &mut SampleReader,
+    ) -> Result<Sample, Box<dyn std::error::Error>> {
+        for _ in 0..Self::READ_ATTEMPTS {
+            let header = unsafe { Self::read_live(from_loc) };
+            let (idx, _) = header.latest_buffer().ok_or("No valid data buffer")?;
+
+            if let Some(sample) = header.read_buffer(from_loc, idx, reader) {
+                return Ok(sample);
+            }
+        }
+
+        Err(Box::from("Data buffers were overwritten while being read"))
+    }
+
+    ///
+    /// Read the data buffer at `offset` of a copy of the memory map or a telemetry file.
+    ///
+    /// Unlike `telemetry`, every read is bounds checked against `bytes` and
+    /// makes no assumption about alignment, so malformed data is reported as an
+    /// error rather than read out of bounds.
+    pub(crate) fn sample_from(
+        &self,
+        bytes: &[u8],
+        offset: usize,
+        tick: i32,
+    ) -> Result<Sample, String> {
+        let n_vars = usize::try_from(self.n_vars).map_err(|_| "Negative variable count")?;
+        let header_offset =
+            usize::try_from(self.header_offset).map_err(|_| "Negative variable header offset")?;
+        let buffer_length =
+            usize::try_from(self.buffer_length).map_err(|_| "Negative buffer length")?;
+
+        let var_headers = n_vars
+            .checked_mul(VALUE_HEADER_SIZE)
+            .and_then(|len| bytes.get(header_offset..header_offset.checked_add(len)?))
+            .ok_or("Variable headers are out of bounds")?;
+
+        let buffer = offset
+            .checked_add(buffer_length)
+            .and_then(|end| bytes.get(offset..end))
+            .ok_or("Data buffer is out of bounds")?;
+
+        SampleLayout::from_bytes(var_headers, buffer_length)?.sample(tick, buffer)
+    }
+}
+
+///
+/// The variable headers and buffer length shared by samples, checked once so
+/// samples can be rebuilt from stored data buffers.
+#[derive(Clone)]
+pub(crate) struct SampleLayout {
+    values: Arc<[ValueHeader]>,
+    buffer_length: usize,
+}
+
+impl SampleLayout {
+    ///
+    /// Parse raw variable headers, checking every value fits in a buffer of
+    /// `buffer_length` bytes.
+    pub(crate) fn from_bytes(var_headers: &[u8], buffer_length: usize) -> Result<Self, String> {
+        if !var_headers.len().is_multiple_of(VALUE_HEADER_SIZE) {
+            return Err(String::from("Variable headers are truncated"));
+        }
+
+        let mut values = Vec::with_capacity(var_headers.len() / VALUE_HEADER_SIZE);
+        for vh in from_bytes(var_headers) {
+            let size = match vh.value_type {
+                0..=5 => Value::from(vh.value_type).size(),
+                t => return Err(format!("Unknown type {} for '{}'", t, vh.name())),
+            };
+            let end = usize::try_from(vh.offset)
+                .ok()
+                .zip(usize::try_from(vh.count).ok().filter(|c| *c > 0))
+                .and_then(|(o, c)| o.checked_add(size.checked_mul(c)?));
+
+            match end {
+                Some(end) if end <= buffer_length => values.push(vh),
+                _ => return Err(format!("Value '{}' is out of bounds", vh.name())),
+            }
+        }
+
+        Ok(SampleLayout {
+            values: Arc::from(values),
+            buffer_length,
+        })
+    }
+
+    /// Layout of an existing sample.
+    pub(crate) fn of(sample: &Sample) -> Self {
+        SampleLayout {
+            values: sample.values.clone(),
+            buffer_length: sample.buffer.len(),
+        }
+    }
+
+    /// Raw variable headers, as read by `from_bytes`.
+    pub(crate) fn to_bytes(&self) -> Vec<u8> {
+        to_bytes(&self.values)
+    }
+
+    ///
+    /// Whether both layouts have the same variable headers. Samples from the
+    /// same reader share their headers, so those are checked without
+    /// serializing them.
+    pub(crate) fn same_values(&self, other: &SampleLayout) -> bool {
+        Arc::ptr_eq(&self.values, &other.values) || self.to_bytes() == other.to_bytes()
+    }
+
+    pub(crate) fn buffer_length(&self) -> usize {
+        self.buffer_length
+    }
+
+    ///
+    /// Sample from a copy of a data buffer laid out as described.
+    pub(crate) fn sample(&self, tick: i32, buffer: &[u8]) -> Result<Sample, String> {
+        if buffer.len() != self.buffer_length {
+            return Err(format!(
+                "Data buffer is {} bytes, expected {}",
+                buffer.len(),
+                self.buffer_length
+            ));
+        }
+
+        Ok(Sample::new(
+            tick,
+            self.values.clone(),
+            PooledBuffer::from(buffer.to_vec()),
+        ))
+    }
+}
+
+impl SampleReader {
+    /// Free buffers kept for reuse by a live connection.
+    #[cfg(target_os = "windows")]
+    const POOL_SIZE: usize = 8;
+
+    pub fn new(pool: BufferPool) -> Self {
+        SampleReader {
+            headers: None,
+            pool,
+        }
+    }
+
+    pub fn pool(&self) -> &BufferPool {
+        &self.pool
+    }
+
+    ///
+    /// Read the newest data buffer of the memory map at `from_loc`.
+    ///
+    /// # Safety
+    ///
+    /// `from_loc` must point to a telemetry memory map, or a copy of one,
+    /// covering the header, variable headers and data buffers it describes,
+    /// which stays valid for the duration of the call.
+    pub unsafe fn read(&mut self, from_loc: *const c_void) -> Result<Sample, Box<dyn Error>> {
+        Header::read_latest(from_loc, self)
+    }
+}
+
+impl TickCursor {
+    pub fn new(mode: ConsumptionMode) -> Self {
+        TickCursor {
+            mode,
+            last: None,
+            missed: 0,
+        }
+    }
+
+    pub fn mode(&self) -> ConsumptionMode {
+        self.mode
+    }
+
+    /// Last tick delivered.
+    pub fn last(&self) -> Option<i32> {
+        self.last
+    }
+
+    ///
+    /// Ticks lost because they were overwritten before they could be read.
+    ///
+    /// Ticks skipped on purpose by `Latest` and `Decimated` aren't counted.
+    pub fn missed(&self) -> u64 {
+        self.missed
+    }
+
+    ///
+    /// Given the ticks currently held in the data buffers, in any order,
+    /// return those to deliver, oldest first.
+    pub fn select(&mut self, ticks: &[i32]) -> Vec<i32> {
+        self.select_as(self.mode, ticks)
+    }
+
+    /// Select ticks following `mode` rather than the cursor's own.
+    fn select_as(&mut self, mode: ConsumptionMode, ticks: &[i32]) -> Vec<i32> {
+        let mut fresh: Vec<i32> = ticks
+            .iter()
+            .copied()
+            .filter(|t| *t > 0 && self.last.map(|last| *t > last).unwrap_or(true))
+            .collect();
+        fresh.sort_unstable();
+        fresh.dedup();
+
+        let newest = match fresh.last() {
+            Some(newest) => *newest,
+            None => return Vec::new(),
+        };
 
-        content
-    }
+        let selected = match (mode, self.last) {
+            (ConsumptionMode::EveryTick, Some(last)) => {
+                let mut expected = last + 1;
+                for tick in fresh.iter() {
+                    self.missed += (tick - expected).max(0) as u64;
+                    expected = tick + 1;
+                }
+                fresh
+            }
+            // Wider than a tick, so large rates and late ticks can't overflow
+            (ConsumptionMode::Decimated(n), Some(last))
+                if (newest as i64) < last as i64 + n.max(1) as i64 =>
+            {
+                Vec::new()
+            }
+            _ => vec![newest],
+        };
 
-    pub fn telemetry(&self, from_loc: *const c_void) -> Result<Sample, Box<dyn std::error::Error>> {
-        let (tick, vbh) = self.latest_buffer();
-        let value_buffer = self.var_buffer(vbh, from_loc);
-        let value_header = self.get_var_header(from_loc);
+        if let Some(last) = selected.last() {
+            self.last = Some(*last);
+        }
 
-        Ok(Sample::new(
-            tick,
-            value_header.to_vec(),
-            value_buffer.to_vec(),
-        ))
+        selected
     }
 }
 
 impl Sample {
-    fn new(tick: i32, header: Vec<ValueHeader>, buffer: Vec<u8>) -> Self {
+    fn new(tick: i32, header: Arc<[ValueHeader]>, buffer: PooledBuffer) -> Self {
         Sample {
             tick,
             values: header,
-            buffer,
+            buffer: Arc::new(buffer),
         }
     }
 
-    fn header_for(&self, name: &'static str) -> Option<ValueHeader> {
-        for v in self.values.iter() {
-            if v.name() == name {
-                return Some(v.clone());
-            }
+    fn header_for(&self, name: &str) -> Option<&ValueHeader> {
+        self.values.iter().find(|v| v.is_named(name))
+    }
+
+    /// Tick count of the buffer the sample was read from.
+    pub fn tick(&self) -> i32 {
+        self.tick
+    }
+
+    /// Raw data buffer.
+    pub(crate) fn data(&self) -> &[u8] {
+        &self.buffer
+    }
+
+    ///
+    /// Whether the sample was taken live or while a replay was playing.
+    ///
+    /// Samples without the `IsReplayPlaying` channel are assumed to be live.
+    pub fn origin(&self) -> SampleOrigin {
+        let replay = self.get("IsReplayPlaying").map(bool::from).unwrap_or(false);
+        if !replay {
+            return SampleOrigin::Live;
+        }
+
+        SampleOrigin::Replay {
+            frame: self
+                .get("ReplayFrameNum")
+                .ok()
+                .and_then(|v| v.try_into().ok())
+                .unwrap_or(0),
         }
-        None
+    }
+
+    /// True if the sample was taken while a replay was playing.
+    pub fn is_replay(&self) -> bool {
+        self.origin() != SampleOrigin::Live
     }
 
     ///
     /// Check if a given variable is available in the telemetry sample
-    pub fn has(&self, name: &'static str) -> bool {
+    pub fn has(&self, name: &str) -> bool {
         self.header_for(name).is_some()
     }
 
@@ -407,6 +1444,66 @@ impl Sample {
         r.collect::<Vec<ValueDescription>>()
     }
 
+    ///
+    /// Variables which differ between this sample and another.
+    ///
+    /// Any change is reported; see `diff_with` to ignore small changes.
+    pub fn diff(&self, other: &Sample) -> Vec<ChangedVar> {
+        self.diff_with(other, &Tolerances::new())
+    }
+
+    ///
+    /// Variables which differ between this sample and another by more than
+    /// their tolerance, in the order they appear in this sample followed by
+    /// any only in the other.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use iracing::telemetry::{Sample, Tolerances};
+    /// # let (previous, sample) = (Sample::default(), Sample::default());
+    ///
+    /// let tolerances = Tolerances::new().with_default(0.01);
+    /// for change in sample.diff_with(&previous, &tolerances) {
+    ///     println!("{} changed to {:?}", change.name, change.after);
+    /// }
+    /// ```
+    pub fn diff_with(&self, other: &Sample, tolerances: &Tolerances) -> Vec<ChangedVar> {
+        let mut changes = Vec::new();
+
+        for vh in self.values.iter() {
+            let name = vh.name();
+            let before = self.value(vh);
+            let after = other.header_for(&name).map(|h| other.value(h));
+
+            let changed = match after.as_ref() {
+                Some(after) => before.differs(after, tolerances.get(&name)),
+                None => true,
+            };
+
+            if changed {
+                changes.push(ChangedVar {
+                    name,
+                    before: Some(before),
+                    after,
+                });
+            }
+        }
+
+        for vh in other.values.iter() {
+            let name = vh.name();
+            if self.header_for(&name).is_none() {
+                changes.push(ChangedVar {
+                    name,
+                    before: None,
+                    after: Some(other.value(vh)),
+                });
+            }
+        }
+
+        changes
+    }
+
     ///
     /// Get a Value from the sample.
     ///
@@ -419,10 +1516,10 @@ impl Sample {
     ///
     /// `name`  Name of the telemetry variable to get
     ///   - see the iRacing Telemtry documentation for a complete list of possible values
-    pub fn get(&self, name: &'static str) -> Result<Value, String> {
+    pub fn get(&self, name: &str) -> Result<Value, String> {
         match self.header_for(name) {
             None => Err(format!("No value '{}' found", name)),
-            Some(vh) => Ok(self.value(&vh)),
+            Some(vh) => Ok(self.value(vh)),
         }
     }
 
@@ -435,14 +1532,13 @@ impl Sample {
 
         let raw_val = &self.buffer[vs..ve];
 
-        let v: Value;
-        v = match vt {
+        let v: Value = match vt {
             Value::INT(_) => {
                 if vc == 1 {
                     Value::INT(i32::from_le_bytes(raw_val.try_into().unwrap()))
                 } else {
                     let mut values: Vec<i32> = Vec::with_capacity(vc);
-                    for i in 0..vc - 1 {
+                    for i in 0..vc {
                         values.push(i32::from_le_bytes(
                             self.buffer[vs + vz * i..vs + vz * (i + 1)]
                                 .try_into()
@@ -459,7 +1555,7 @@ impl Sample {
                 } else {
                     let mut values: Vec<f32> = Vec::with_capacity(vc);
 
-                    for i in 0..vc - 1 {
+                    for i in 0..vc {
                         values.push(f32::from_le_bytes(
                             self.buffer[vs + vz * i..vs + vz * (i + 1)]
                                 .try_into()
@@ -471,15 +1567,31 @@ impl Sample {
                 }
             }
             Value::DOUBLE(_) => Value::DOUBLE(f64::from_le_bytes(raw_val.try_into().unwrap())),
-            Value::BITS(_) => Value::BITS(u32::from_le_bytes(raw_val.try_into().unwrap())),
-            Value::CHAR(_) => Value::CHAR(raw_val[0] as u8),
+            Value::BITS(_) => {
+                if vc == 1 {
+                    Value::BITS(u32::from_le_bytes(raw_val.try_into().unwrap()))
+                } else {
+                    let mut values: Vec<u32> = Vec::with_capacity(vc);
+
+                    for i in 0..vc {
+                        values.push(u32::from_le_bytes(
+                            self.buffer[vs + vz * i..vs + vz * (i + 1)]
+                                .try_into()
+                                .unwrap(),
+                        ));
+                    }
+
+                    Value::BitsVec(values)
+                }
+            }
+            Value::CHAR(_) => Value::CHAR(raw_val[0]),
             Value::BOOL(_) => {
                 if vc == 1 {
                     Value::BOOL(raw_val[0] > 0)
                 } else {
                     let mut values: Vec<bool> = Vec::with_capacity(vc);
 
-                    for i in 0..vc - 1 {
+                    for i in 0..vc {
                         values.push(self.buffer[vs + i] > 0);
                     }
 
@@ -493,6 +1605,31 @@ impl Sample {
     }
 }
 
+///
+/// Count the ticks after `after` which are missing from `samples`, which are
+/// in tick order.
+///
+/// # Examples
+///
+/// ```
+/// use iracing::telemetry::missing_ticks;
+///
+/// assert_eq!(missing_ticks(10, &[]), 0);
+/// assert_eq!(missing_ticks(i32::MAX, &[]), 0);
+/// ```
+pub fn missing_ticks(after: i32, samples: &[Sample]) -> u64 {
+    // Widened so ticks near i32::MAX can't overflow
+    let mut expected = i64::from(after) + 1;
+    let mut missing = 0;
+
+    for tick in samples.iter().map(Sample::tick).filter(|t| *t > after) {
+        missing += (i64::from(tick) - expected).max(0) as u64;
+        expected = i64::from(tick) + 1;
+    }
+
+    missing
+}
+
 ///
 /// Telemetry Error
 ///
@@ -500,6 +1637,7 @@ impl Sample {
 #[derive(Debug)]
 pub enum TelemetryError {
     ABANDONED,
+    CANCELLED,
     TIMEOUT(usize),
     UNKNOWN(u32),
 }
@@ -508,6 +1646,7 @@ impl Display for TelemetryError {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         match self {
             Self::ABANDONED => write!(f, "Abandoned"),
+            Self::CANCELLED => write!(f, "Cancelled"),
             Self::TIMEOUT(ms) => write!(f, "Timeout after {}ms", ms),
             Self::UNKNOWN(v) => write!(f, "Unknown error code = {:x?}", v),
         }
@@ -516,6 +1655,52 @@ impl Display for TelemetryError {
 
 impl Error for TelemetryError {}
 
+#[cfg(target_os = "windows")]
+impl Drop for Event {
+    fn drop(&mut self) {
+        unsafe { CloseHandle(self.0) };
+    }
+}
+
+#[cfg(target_os = "windows")]
+impl CancelToken {
+    pub fn new() -> IOResult<Self> {
+        // Manual reset, so the token stays cancelled for every waiter
+        let handle = unsafe { CreateEventW(null_mut(), 1, 0, null()) };
+
+        if handle.is_null() {
+            let errno: i32 = unsafe { GetLastError() as i32 };
+
+            return Err(std::io::Error::from_raw_os_error(errno));
+        }
+
+        Ok(CancelToken {
+            event: Arc::new(Event(handle)),
+        })
+    }
+
+    /// Interrupt any current and future waits.
+    pub fn cancel(&self) {
+        unsafe { SetEvent(self.event.0) };
+    }
+
+    /// Allow waits again after cancelling.
+    pub fn reset(&self) {
+        unsafe { ResetEvent(self.event.0) };
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        unsafe { WaitForSingleObject(self.event.0, 0) == WAIT_OBJECT_0 }
+    }
+
+    /// Wait up to `timeout` for the token to be cancelled, returning true if it was.
+    pub(crate) fn wait(&self, timeout: Duration) -> bool {
+        let wait_time: u32 = timeout.as_millis().try_into().unwrap_or(INFINITE);
+        unsafe { WaitForSingleObject(self.event.0, wait_time) == WAIT_OBJECT_0 }
+    }
+}
+
+#[cfg(target_os = "windows")]
 impl Blocking {
     pub fn new(location: *const c_void, head: Header) -> std::io::Result<Self> {
         let mut event_name: Vec<u16> = DATA_EVENT_NAME.encode_utf16().collect();
@@ -533,11 +1718,46 @@ impl Blocking {
 
         Ok(Blocking {
             origin: location,
-            header: head,
+            reader: RefCell::new(SampleReader {
+                headers: Some((
+                    head.var_header_bytes(location).to_vec(),
+                    Arc::from(from_bytes(head.var_header_bytes(location))),
+                )),
+                pool: BufferPool::new(SampleReader::POOL_SIZE),
+            }),
             event_handle: handle,
+            cursor: RefCell::new(TickCursor::default()),
+            pending: RefCell::new(VecDeque::new()),
+            cancel: None,
         })
     }
 
+    ///
+    /// Set how `next_sample` delivers ticks.
+    pub fn with_mode(self, mode: ConsumptionMode) -> Self {
+        self.cursor.replace(TickCursor::new(mode));
+        self.pending.borrow_mut().clear();
+        self
+    }
+
+    ///
+    /// Interrupt waits for telemetry when `token` is cancelled.
+    pub fn with_cancel(mut self, token: CancelToken) -> Self {
+        self.cancel = Some(token);
+        self
+    }
+
+    pub fn mode(&self) -> ConsumptionMode {
+        self.cursor.borrow().mode()
+    }
+
+    ///
+    /// Ticks lost by `next_sample` because they were overwritten before they
+    /// could be read.
+    pub fn missed_ticks(&self) -> u64 {
+        self.cursor.borrow().missed()
+    }
+
     pub fn close(&self) -> std::io::Result<()> {
         if self.event_handle.is_null() {
             return Ok(());
@@ -605,27 +1825,181 @@ impl Blocking {
     /// # }
     /// ```
     pub fn sample(&self, timeout: Duration) -> Result<Sample, Box<dyn Error>> {
-        let wait_time: u32 = match timeout.as_millis().try_into() {
-            Ok(v) => v,
-            Err(e) => return Err(Box::new(e)),
+        self.wait(timeout)?;
+        Header::read_latest(self.origin, &mut self.reader.borrow_mut())
+    }
+
+    ///
+    /// Sample Telemetry Data Until
+    ///
+    /// Waits for new telemetry data until `deadline`, for callers sharing one
+    /// deadline across several waits. A deadline already passed still checks
+    /// for new data once.
+    pub fn sample_until(&self, deadline: Instant) -> Result<Sample, Box<dyn Error>> {
+        self.sample(deadline.saturating_duration_since(Instant::now()))
+    }
+
+    ///
+    /// Next Sample
+    ///
+    /// Returns the next sample according to the sampler's `ConsumptionMode`,
+    /// waiting up to `timeout` for new data if none is queued.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+    /// use iracing::telemetry::{Connection, ConsumptionMode};
+    /// use std::time::Duration;
+    ///
+    /// let sampler = Connection::new()?
+    ///     .blocking()?
+    ///     .with_mode(ConsumptionMode::EveryTick);
+    ///
+    /// loop {
+    ///     let sample = sampler.next_sample(Duration::from_millis(50))?;
+    ///     println!("Tick {}, {} lost so far", sample.tick(), sampler.missed_ticks());
+    /// }
+    /// # }
+    /// ```
+    pub fn next_sample(&self, timeout: Duration) -> Result<Sample, Box<dyn Error>> {
+        let deadline = Instant::now() + timeout;
+
+        loop {
+            if let Some(sample) = self.pending.borrow_mut().pop_front() {
+                return Ok(sample);
+            }
+
+            self.wait(deadline.saturating_duration_since(Instant::now()))?;
+
+            // The copy of the header taken when connecting has stale tick counts
+            let header = unsafe { Header::read_live(self.origin) };
+            let ticks = self.cursor.borrow_mut().select(&header.buffer_ticks());
+
+            let mut pending = self.pending.borrow_mut();
+            for tick in ticks {
+                pending.extend(header.telemetry_at(
+                    self.origin,
+                    tick,
+                    &mut self.reader.borrow_mut(),
+                ));
+            }
+        }
+    }
+
+    ///
+    /// Sample Batch
+    ///
+    /// Returns up to `n` consecutive ticks, oldest first, collecting every tick
+    /// still held in the data buffers after each wait. Returns early with what
+    /// it has once `timeout` passes or the sampler is cancelled, and with the
+    /// error if it has nothing. A timeout too long to express waits forever.
+    ///
+    /// For loggers which can take samples in bulk rather than as they arrive.
+    /// Ticks are taken in order whatever the sampler's `ConsumptionMode`, and
+    /// any beyond `n` are kept for the next call.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+    /// use iracing::telemetry::Connection;
+    /// use std::time::Duration;
+    ///
+    /// let sampler = Connection::new()?.blocking()?;
+    ///
+    /// loop {
+    ///     let batch = sampler.sample_batch(30, Duration::from_millis(600))?;
+    ///     println!("{} ticks, {} lost so far", batch.len(), sampler.missed_ticks());
+    /// }
+    /// # }
+    /// ```
+    pub fn sample_batch(&self, n: usize, timeout: Duration) -> Result<Vec<Sample>, Box<dyn Error>> {
+        let deadline = Instant::now().checked_add(timeout);
+        let mut batch = Vec::with_capacity(n);
+
+        loop {
+            {
+                let mut pending = self.pending.borrow_mut();
+                let count = pending.len().min(n - batch.len());
+                batch.extend(pending.drain(..count));
+            }
+            if batch.len() >= n {
+                return Ok(batch);
+            }
+
+            // Take whatever the buffers hold before waiting for more
+            let header = unsafe { Header::read_live(self.origin) };
+            let ticks = self
+                .cursor
+                .borrow_mut()
+                .select_as(ConsumptionMode::EveryTick, &header.buffer_ticks());
+
+            for tick in ticks {
+                let sample = header.telemetry_at(self.origin, tick, &mut self.reader.borrow_mut());
+                if batch.len() < n {
+                    batch.extend(sample);
+                } else {
+                    self.pending.borrow_mut().extend(sample);
+                }
+            }
+            if batch.len() >= n {
+                return Ok(batch);
+            }
+
+            let remaining = match deadline {
+                Some(deadline) => deadline.saturating_duration_since(Instant::now()),
+                None => Duration::MAX,
+            };
+            match self.wait(remaining) {
+                Ok(()) => (),
+                Err(e) if !batch.is_empty() => match e.downcast_ref::<TelemetryError>() {
+                    Some(TelemetryError::TIMEOUT(_)) | Some(TelemetryError::CANCELLED) => {
+                        return Ok(batch)
+                    }
+                    _ => return Err(e),
+                },
+                Err(e) => return Err(e),
+            }
+        }
+    }
+
+    ///
+    /// Wait for the data event, or the cancel token if there is one.
+    ///
+    /// The timeout is rounded up to whole milliseconds so short waits aren't
+    /// cut to nothing, and a timeout too long to express waits forever.
+    fn wait(&self, timeout: Duration) -> Result<(), Box<dyn Error>> {
+        let wait_time: u32 = match timeout.as_nanos().div_ceil(1_000_000).try_into() {
+            Ok(ms) => ms,
+            Err(_) => INFINITE,
         };
 
-        let signal = unsafe { WaitForSingleObject(self.event_handle, wait_time) };
+        // The cancel token comes first, so it wins over data arriving at the same time
+        let mut handles = Vec::with_capacity(2);
+        handles.extend(self.cancel.as_ref().map(|token| token.event.0));
+        handles.push(self.event_handle);
+        let data = handles.len() as u32 - 1;
+
+        let signal =
+            unsafe { WaitForMultipleObjects(handles.len() as u32, handles.as_ptr(), 0, wait_time) };
 
         match signal {
-            0x80 => Err(Box::new(TelemetryError::ABANDONED)), // Abandoned
-            0x102 => Err(Box::new(TelemetryError::TIMEOUT(wait_time as usize))), // Timeout
-            0xFFFFFFFF => {
-                // Error
-                let errno = unsafe { GetLastError() as i32 };
-                Err(Box::new(std::io::Error::from_raw_os_error(errno)))
-            }
-            0x00 => {
+            s if s == WAIT_OBJECT_0 + data => {
                 // OK
                 unsafe { ResetEvent(self.event_handle) };
-                self.header.telemetry(self.origin)
+                Ok(())
+            }
+            s if s < WAIT_OBJECT_0 + data => Err(Box::new(TelemetryError::CANCELLED)),
+            s if (WAIT_ABANDONED_0..=WAIT_ABANDONED_0 + data).contains(&s) => {
+                Err(Box::new(TelemetryError::ABANDONED))
+            }
+            WAIT_TIMEOUT => Err(Box::new(TelemetryError::TIMEOUT(wait_time as usize))),
+            WAIT_FAILED => {
+                let errno = unsafe { GetLastError() as i32 };
+                Err(Box::new(std::io::Error::from_raw_os_error(errno)))
             }
-            _ => Err(Box::new(TelemetryError::UNKNOWN(signal as u32))),
+            _ => Err(Box::new(TelemetryError::UNKNOWN(signal))),
         }
     }
 }
@@ -644,10 +2018,14 @@ impl Blocking {
 ///
 /// let _ = Connection::new().expect("Unable to find telemetry data");
 /// ```
+#[cfg(target_os = "windows")]
 pub struct Connection {
     location: *mut c_void,
+    heartbeat: RefCell<Heartbeat>,
+    reader: RefCell<SampleReader>,
 }
 
+#[cfg(target_os = "windows")]
 impl Connection {
     pub fn new() -> IOResult<Connection> {
         let mut path: Vec<u16> = TELEMETRY_PATH.encode_utf16().collect();
@@ -682,7 +2060,11 @@ impl Connection {
             return Err(std::io::Error::from_raw_os_error(errno));
         }
 
-        Ok(Connection { location: view })
+        Ok(Connection {
+            location: view,
+            heartbeat: RefCell::new(Heartbeat::new()),
+            reader: RefCell::new(SampleReader::new(BufferPool::new(SampleReader::POOL_SIZE))),
+        })
     }
 
     ///
@@ -691,8 +2073,7 @@ impl Connection {
     /// Reads the data header from the shared memory map and returns a copy of the header
     /// which can be used safely elsewhere.
     unsafe fn read_header(from: *const c_void) -> Header {
-        let raw_header: *const Header = transmute(from);
-        *raw_header
+        Header::read_live(from)
     }
 
     ///
@@ -719,13 +2100,67 @@ impl Connection {
 
         let data: &[u8] = unsafe { from_raw_parts(start, size) };
 
-        // Decode the data as Latin-1 (Rust wants UTF-8)
-        let content = decode_latin1(data);
-        let details = yaml_from(&content)?;
+        // Decode the data as UTF-8 or Windows-1252 (Rust wants UTF-8)
+        let end = data.iter().position(|b| *b == 0).unwrap_or(data.len());
+        let (content, _) = decode(&data[..end]);
+        let details = SessionDetails::parse(&content)?;
 
         Ok(details)
     }
 
+    ///
+    /// Session info exactly as written, without the NUL padding, for users
+    /// handling its encoding themselves.
+    pub fn session_info_bytes(&mut self) -> Vec<u8> {
+        let header = unsafe { Self::read_header(self.location) };
+
+        let start = (self.location as usize + header.session_info_offset as usize) as *const u8;
+        let size = header.session_info_length as usize;
+
+        let data: &[u8] = unsafe { from_raw_parts(start, size) };
+        let end = data.iter().position(|b| *b == 0).unwrap_or(data.len());
+        data[..end].to_vec()
+    }
+
+    ///
+    /// Connection health
+    ///
+    /// Reports whether the sim is connected, the newest tick and how long ago
+    /// it arrived, and how long ago the session info last changed. Ages are
+    /// measured between calls, so call this regularly.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+    /// use iracing::telemetry::Connection;
+    ///
+    /// let health = Connection::new()?.health();
+    /// println!("Tick {} is {:?} old", health.last_tick, health.tick_age);
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn health(&self) -> Health {
+        let header = unsafe { Self::read_header(self.location) };
+        let tick = header.latest_buffer().map(|(_, tick)| tick).unwrap_or(0);
+
+        self.heartbeat
+            .borrow_mut()
+            .update(header.is_connected(), tick, header.session_info_version)
+    }
+
+    ///
+    /// Copy the raw memory map.
+    ///
+    /// The copy holds the header, variable headers, data buffers and session
+    /// info, and can be stored in a `Recording` and played back later.
+    pub fn snapshot(&self) -> Vec<u8> {
+        let header = unsafe { Self::read_header(self.location) };
+        let data = unsafe { from_raw_parts(self.location as *const u8, header.map_size()) };
+
+        data.to_vec()
+    }
+
     ///
     /// Get latest telemetry.
     ///
@@ -742,8 +2177,42 @@ impl Connection {
     /// # }
     /// ```
     pub fn telemetry(&self) -> Result<Sample, Box<dyn std::error::Error>> {
-        let header = unsafe { Self::read_header(self.location) };
-        header.telemetry(self.location as *const std::ffi::c_void)
+        Header::read_latest(self.location, &mut self.reader.borrow_mut())
+    }
+
+    ///
+    /// Get recent telemetry.
+    ///
+    /// Every tick still held in the data buffers, usually the last three,
+    /// oldest first.
+    pub fn history(&self) -> Vec<Sample> {
+        Header::read_history(self.location, None, &mut self.reader.borrow_mut())
+    }
+
+    ///
+    /// Get telemetry missed since a tick.
+    ///
+    /// The ticks after `tick` still held in the data buffers, oldest first, for
+    /// a consumer which woke up late to fill the gap. Older ticks have been
+    /// overwritten; `missing_ticks` counts them.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+    /// use iracing::telemetry::{missing_ticks, Connection};
+    ///
+    /// let connection = Connection::new()?;
+    /// let last = connection.telemetry()?.tick();
+    ///
+    /// // ... busy for a while ...
+    /// let missed = connection.history_since(last);
+    /// println!("Caught up {} ticks, {} lost", missed.len(), missing_ticks(last, &missed));
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn history_since(&self, tick: i32) -> Vec<Sample> {
+        Header::read_history(self.location, Some(tick), &mut self.reader.borrow_mut())
     }
 
     ///
@@ -768,6 +2237,37 @@ impl Connection {
         Blocking::new(self.location, unsafe { Self::read_header(self.location) })
     }
 
+    ///
+    /// Spawn Background Sampler.
+    ///
+    /// Starts a thread which waits for telemetry and delivers samples at up to
+    /// `fps` over a channel holding `capacity` samples. See
+    /// `iracing::sampler::Sampler`.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+    /// use iracing::fps::Fps;
+    /// use iracing::telemetry::Connection;
+    ///
+    /// let sampler = Connection::new()?.spawn_sampler(Fps::MAX, 4)?;
+    ///
+    /// while let Some(sample) = sampler.recv() {
+    ///     println!("Tick {}", sample.tick());
+    /// }
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn spawn_sampler(&self, fps: Fps, capacity: usize) -> IOResult<Sampler> {
+        Sampler::spawn(
+            self.location,
+            unsafe { Self::read_header(self.location) },
+            fps,
+            capacity,
+        )
+    }
+
     pub fn close(&self) -> IOResult<()> {
         let succ = unsafe { CloseHandle(self.location) };
 
@@ -784,7 +2284,230 @@ impl Connection {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::recording::SnapshotBuilder;
+
+    fn sample(builder: SnapshotBuilder) -> Sample {
+        let snapshot = builder.build();
+        let header = Header::from_bytes(&snapshot).unwrap();
+        header
+            .sample_from(&snapshot, header.buffer_offset(0), 1)
+            .unwrap()
+    }
+
+    #[test]
+    fn header_accessors() {
+        let snapshot = SnapshotBuilder::new(7)
+            .with_value("RPM", Value::FLOAT(6000.0))
+            .with_value("Gear", Value::INT(3))
+            .build();
+        let header = Header::from_bytes(&snapshot).unwrap();
+
+        assert!(header.is_connected());
+        assert_eq!(header.tick_rate(), 60);
+        assert_eq!(header.var_count(), 2);
+        assert_eq!(
+            header.buffers(),
+            vec![BufferInfo {
+                index: 0,
+                ticks: 7,
+                offset: header.buffer_offset(0),
+            }]
+        );
+
+        let json = serde_json::to_value(header).unwrap();
+        assert_eq!(json["tick_rate"], 60);
+    }
+
+    #[test]
+    fn parse_unaligned() {
+        let snapshot = SnapshotBuilder::new(3)
+            .with_value("SessionTime", Value::DOUBLE(12.5))
+            .with_value("CarIdxLap", Value::IntVec(vec![4, 5]))
+            .build();
+
+        // A buffer received over the network needn't be aligned
+        let mut received = vec![0u8];
+        received.extend_from_slice(&snapshot);
+        let bytes = &received[1..];
+
+        let header = Header::from_bytes(bytes).unwrap();
+        let sample = header
+            .sample_from(bytes, header.buffer_offset(0), 3)
+            .unwrap();
+        let time: f64 = sample.get("SessionTime").unwrap().try_into().unwrap();
+        assert_eq!(time, 12.5);
+
+        let layout = SampleLayout::of(&sample);
+        assert_eq!(
+            layout.to_bytes(),
+            &bytes[header.var_header_offset()..][..2 * VALUE_HEADER_SIZE]
+        );
+    }
+
+    #[test]
+    fn diff_samples() {
+        let before = sample(
+            SnapshotBuilder::new(1)
+                .with_value("RPM", Value::FLOAT(6000.0))
+                .with_value("Gear", Value::INT(3))
+                .with_value("CarIdxLap", Value::IntVec(vec![4, 5]))
+                .with_value("Speed", Value::FLOAT(40.0)),
+        );
+        let after = sample(
+            SnapshotBuilder::new(2)
+                .with_value("RPM", Value::FLOAT(6010.0))
+                .with_value("Gear", Value::INT(3))
+                .with_value("CarIdxLap", Value::IntVec(vec![4, 6]))
+                .with_value("FuelLevel", Value::FLOAT(10.0)),
+        );
+
+        let names = |changes: Vec<ChangedVar>| -> Vec<String> {
+            changes.into_iter().map(|c| c.name).collect()
+        };
+
+        assert_eq!(
+            names(before.diff(&after)),
+            vec!["RPM", "CarIdxLap", "Speed", "FuelLevel"]
+        );
+
+        let tolerances = Tolerances::new().with_channel("RPM", 25.0);
+        let changes = before.diff_with(&after, &tolerances);
+        assert_eq!(changes[0].name, "CarIdxLap");
+        assert!(changes[1].after.is_none());
+        assert!(changes[2].before.is_none());
+    }
+
+    #[test]
+    fn sample_origin() {
+        let live =
+            sample(SnapshotBuilder::new(1).with_value("IsReplayPlaying", Value::BOOL(false)));
+        let replay = sample(
+            SnapshotBuilder::new(1)
+                .with_value("IsReplayPlaying", Value::BOOL(true))
+                .with_value("ReplayFrameNum", Value::INT(1200)),
+        );
+
+        assert_eq!(live.origin(), SampleOrigin::Live);
+        assert_eq!(replay.origin(), SampleOrigin::Replay { frame: 1200 });
+        assert!(!sample(SnapshotBuilder::new(1)).is_replay());
+    }
+
+    #[test]
+    fn newest_buffer() {
+        let build = |tick: i32, speed: f32| {
+            SnapshotBuilder::new(tick)
+                .with_value("Speed", Value::FLOAT(speed))
+                .build()
+        };
+        let mut snapshot = build(5, 1.0);
+        let header = Header::from_bytes(&snapshot).unwrap();
+        let data = header.buffer_offset(0)..snapshot.len();
+
+        // Add two more buffers, and a stale one beyond n_buffers
+        for (i, (tick, speed)) in [(7i32, 2.0), (6, 3.0), (9, 4.0)].iter().enumerate() {
+            let at = 48 + (i + 1) * 16;
+            let offset = snapshot.len() as i32;
+            snapshot[at..at + 4].copy_from_slice(&tick.to_le_bytes());
+            snapshot[at + 4..at + 8].copy_from_slice(&offset.to_le_bytes());
+            snapshot.extend_from_slice(&build(*tick, *speed)[data.clone()]);
+        }
+        snapshot[32..36].copy_from_slice(&3i32.to_le_bytes());
+
+        let words: Vec<u64> = snapshot
+            .chunks(8)
+            .map(|c| {
+                let mut word = [0u8; 8];
+                word[..c.len()].copy_from_slice(c);
+                u64::from_ne_bytes(word)
+            })
+            .collect();
+        let header = Header::from_bytes(&snapshot).unwrap();
+        // `words` holds the whole snapshot for the rest of the test
+        let sample = unsafe { header.telemetry(words.as_ptr() as *const c_void) }.unwrap();
+
+        assert_eq!(sample.tick(), 7);
+        let speed: f32 = sample.get("Speed").unwrap().try_into().unwrap();
+        assert_eq!(speed, 2.0);
+    }
+
+    #[test]
+    fn buffer_history() {
+        let build = |tick: i32| {
+            SnapshotBuilder::new(tick)
+                .with_value("Speed", Value::FLOAT(tick as f32))
+                .build()
+        };
+        let mut snapshot = build(12);
+        let header = Header::from_bytes(&snapshot).unwrap();
+        let data = header.buffer_offset(0)..snapshot.len();
+
+        // The sim wrote tick 14 over 11, and 13 is still being written
+        for (i, tick) in [14i32, 13].iter().enumerate() {
+            let at = 48 + (i + 1) * 16;
+            let offset = if *tick == 13 {
+                0
+            } else {
+                snapshot.len() as i32
+            };
+            snapshot[at..at + 4].copy_from_slice(&tick.to_le_bytes());
+            snapshot[at + 4..at + 8].copy_from_slice(&offset.to_le_bytes());
+            snapshot.extend_from_slice(&build(*tick)[data.clone()]);
+        }
+        snapshot[32..36].copy_from_slice(&3i32.to_le_bytes());
+
+        let words: Vec<u64> = snapshot
+            .chunks(8)
+            .map(|c| {
+                let mut word = [0u8; 8];
+                word[..c.len()].copy_from_slice(c);
+                u64::from_ne_bytes(word)
+            })
+            .collect();
+        let header = Header::from_bytes(&snapshot).unwrap();
+        let history = unsafe { header.history(words.as_ptr() as *const c_void) };
+
+        let ticks: Vec<i32> = history.iter().map(Sample::tick).collect();
+        assert_eq!(ticks, vec![12, 14]);
+        let speed: f32 = history[1].get("Speed").unwrap().try_into().unwrap();
+        assert_eq!(speed, 14.0);
+
+        assert_eq!(missing_ticks(10, &history), 2);
+        assert_eq!(missing_ticks(12, &history), 1);
+        assert_eq!(missing_ticks(14, &history), 0);
+
+        // Ticks at the end of the range don't overflow
+        let snapshot = build(12);
+        let header = Header::from_bytes(&snapshot).unwrap();
+        let last = header
+            .sample_from(&snapshot, header.buffer_offset(0), i32::MAX)
+            .unwrap();
+        assert_eq!(missing_ticks(i32::MAX - 3, &[last]), 2);
+    }
+
+    #[test]
+    fn consumption_modes() {
+        let mut latest = TickCursor::new(ConsumptionMode::Latest);
+        assert_eq!(latest.select(&[3, 1, 2]), vec![3]);
+        assert!(latest.select(&[3, 1, 2]).is_empty());
+        assert_eq!(latest.select(&[6, 4, 5]), vec![6]);
+
+        let mut every = TickCursor::new(ConsumptionMode::EveryTick);
+        every.select(&[3, 1, 2]);
+        assert_eq!(every.select(&[6, 4, 5]), vec![4, 5, 6]);
+        assert_eq!(every.select(&[12, 10, 11]), vec![10, 11, 12]);
+        assert_eq!(every.missed(), 3);
+
+        let mut decimated = TickCursor::new(ConsumptionMode::Decimated(4));
+        let delivered: Vec<i32> = (1..=12).flat_map(|t| decimated.select(&[t])).collect();
+        assert_eq!(delivered, vec![1, 5, 9]);
+
+        // Rates beyond the range of a tick deliver only the first
+        let mut sparse = TickCursor::new(ConsumptionMode::Decimated(u32::MAX));
+        assert_eq!(sparse.select(&[i32::MAX - 1]), vec![i32::MAX - 1]);
+        assert!(sparse.select(&[i32::MAX]).is_empty());
+    }
 
+    #[cfg(target_os = "windows")]
     #[test]
     fn test_session_info() {
         let session_info = Connection::new()
@@ -793,6 +2516,7 @@ mod tests {
         assert!(session_info.is_ok());
     }
 
+    #[cfg(target_os = "windows")]
     #[test]
     fn test_latest_telemetry() {
         let session_tick: u32 = Connection::new()
@@ -0,0 +1,307 @@
+use crate::assets::{Corner, TrackAsset};
+use crate::states::TrackLocation;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
+
+#[cfg(feature = "telemetry")]
+use crate::telemetry::Sample;
+#[cfg(feature = "telemetry")]
+use std::convert::TryInto;
+#[cfg(feature = "telemetry")]
+use std::error::Error;
+
+///
+/// Track Limits Sample
+///
+/// Where every car is at a point in time.
+#[derive(Debug, Clone, Default)]
+pub struct TrackLimitsSample {
+    pub session_time: f64,             // SessionTime
+    pub laps: Vec<i32>,                // CarIdxLap
+    pub lap_dist_pct: Vec<f32>,        // CarIdxLapDistPct
+    pub locations: Vec<TrackLocation>, // CarIdxTrackSurface
+}
+
+///
+/// A car leaving the track, with where and for how long.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct TrackLimitViolation {
+    pub car_idx: usize,
+    pub lap: i32,
+    pub lap_dist_pct: f32,      // Where the car left the track
+    pub corner: Option<String>, // Corner on the track map, if any
+    pub session_time: f64,      // When the car left the track
+    pub duration: f64,          // Time off track (s)
+}
+
+///
+/// A car's violations on one lap.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct LapViolations {
+    pub car_idx: usize,
+    pub lap: i32,
+    pub count: usize,
+    pub corners: Vec<String>, // Corners of the violations, if on the track map
+}
+
+///
+/// Track Limits Monitor
+///
+/// Logs probable track limit violations, each time a car has all four wheels
+/// off the track and comes back on. Violations are placed in the corners of
+/// the track map when known, so stewards can see who is running wide where.
+///
+/// Cars which leave the world while off track, such as when towed, are
+/// treated as crashes rather than violations.
+///
+/// # Examples
+///
+/// ```
+/// use iracing::assets::AssetCatalog;
+/// use iracing::track_limits::{TrackLimitsMonitor, TrackLimitsSample};
+///
+/// let track = AssetCatalog::bundled().track("imola gp").cloned().unwrap();
+/// let mut monitor = TrackLimitsMonitor::new().with_track(&track);
+///
+/// for violation in monitor.update(&TrackLimitsSample::default()) {
+///     println!("Car {} off at {:?} on lap {}", violation.car_idx, violation.corner, violation.lap);
+/// }
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct TrackLimitsMonitor {
+    corners: Vec<Corner>,
+    min_duration: f64,
+    off_track: HashMap<usize, Excursion>,
+    violations: Vec<TrackLimitViolation>,
+}
+
+#[derive(Debug, Copy, Clone)]
+struct Excursion {
+    lap: i32,
+    lap_dist_pct: f32,
+    start: f64,
+}
+
+impl TrackLimitsSample {
+    ///
+    /// Read a track limits sample from a telemetry sample.
+    #[cfg(feature = "telemetry")]
+    pub fn from_sample(sample: &Sample) -> Result<Self, Box<dyn Error>> {
+        let locations: Vec<i32> = sample.get("CarIdxTrackSurface")?.try_into()?;
+
+        Ok(TrackLimitsSample {
+            session_time: sample.get("SessionTime")?.try_into()?,
+            laps: sample.get("CarIdxLap")?.try_into()?,
+            lap_dist_pct: sample.get("CarIdxLapDistPct")?.try_into()?,
+            locations: locations.into_iter().map(TrackLocation::from).collect(),
+        })
+    }
+}
+
+impl TrackLimitsMonitor {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Name violations after the corners of a track's map.
+    pub fn with_track(mut self, track: &TrackAsset) -> Self {
+        self.corners = track.corners.clone();
+        self
+    }
+
+    ///
+    /// Ignore cars off track for less than this, such as a wheel briefly
+    /// reported off over a kerb. No minimum by default.
+    pub fn with_min_duration(mut self, seconds: f64) -> Self {
+        self.min_duration = seconds;
+        self
+    }
+
+    ///
+    /// Update with the latest car locations, returning violations by cars
+    /// which have just come back on track.
+    pub fn update(&mut self, sample: &TrackLimitsSample) -> Vec<TrackLimitViolation> {
+        let mut found = Vec::new();
+
+        for (car_idx, location) in sample.locations.iter().enumerate() {
+            match location {
+                TrackLocation::OffTrack => {
+                    self.off_track.entry(car_idx).or_insert(Excursion {
+                        lap: sample.laps.get(car_idx).copied().unwrap_or(0),
+                        lap_dist_pct: sample.lap_dist_pct.get(car_idx).copied().unwrap_or(0.0),
+                        start: sample.session_time,
+                    });
+                }
+                TrackLocation::NotInWorld => {
+                    self.off_track.remove(&car_idx);
+                }
+                _ => {
+                    let excursion = match self.off_track.remove(&car_idx) {
+                        Some(excursion) => excursion,
+                        None => continue,
+                    };
+
+                    let duration = sample.session_time - excursion.start;
+                    if duration < self.min_duration {
+                        continue;
+                    }
+
+                    found.push(TrackLimitViolation {
+                        car_idx,
+                        lap: excursion.lap,
+                        lap_dist_pct: excursion.lap_dist_pct,
+                        corner: self
+                            .corners
+                            .iter()
+                            .find(|c| c.contains(excursion.lap_dist_pct))
+                            .map(|c| c.name.clone()),
+                        session_time: excursion.start,
+                        duration,
+                    });
+                }
+            }
+        }
+
+        self.violations.extend(found.iter().cloned());
+        found
+    }
+
+    /// Every violation so far, in the order cars came back on track.
+    pub fn violations(&self) -> &[TrackLimitViolation] {
+        &self.violations
+    }
+
+    /// Number of violations by a car on a lap.
+    pub fn count(&self, car_idx: usize, lap: i32) -> usize {
+        self.violations
+            .iter()
+            .filter(|v| v.car_idx == car_idx && v.lap == lap)
+            .count()
+    }
+
+    ///
+    /// Violations grouped by car and lap, ordered by car index then lap.
+    pub fn by_lap(&self) -> Vec<LapViolations> {
+        let mut laps: BTreeMap<(usize, i32), LapViolations> = BTreeMap::new();
+
+        for violation in self.violations.iter() {
+            let lap = laps
+                .entry((violation.car_idx, violation.lap))
+                .or_insert_with(|| LapViolations {
+                    car_idx: violation.car_idx,
+                    lap: violation.lap,
+                    count: 0,
+                    corners: Vec::new(),
+                });
+
+            lap.count += 1;
+            lap.corners.extend(violation.corner.iter().cloned());
+        }
+
+        laps.into_values().collect()
+    }
+
+    ///
+    /// Violations per corner across every car, most first. Violations
+    /// outside the corners on the map are left out.
+    pub fn by_corner(&self) -> Vec<(String, usize)> {
+        let mut corners: HashMap<&str, usize> = HashMap::new();
+        for corner in self.violations.iter().filter_map(|v| v.corner.as_deref()) {
+            *corners.entry(corner).or_default() += 1;
+        }
+
+        let mut corners: Vec<(String, usize)> = corners
+            .into_iter()
+            .map(|(name, count)| (name.to_owned(), count))
+            .collect();
+        corners.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+        corners
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn monitor_track_limits() {
+        let corners = vec![
+            Corner {
+                name: "Tamburello".to_string(),
+                start: 0.05,
+                end: 0.12,
+            },
+            Corner {
+                name: "Rivazza".to_string(),
+                start: 0.85,
+                end: 0.95,
+            },
+        ];
+        let track = TrackAsset {
+            name: "imola gp".to_string(),
+            display_name: "Imola".to_string(),
+            short_name: "Imola".to_string(),
+            config: String::new(),
+            length_km: 4.909,
+            sectors: Vec::new(),
+            logo: None,
+            map: None,
+            corners,
+        };
+        let mut monitor = TrackLimitsMonitor::new()
+            .with_track(&track)
+            .with_min_duration(0.2);
+
+        use TrackLocation::*;
+        let mut time = 0.0;
+        let mut step = |monitor: &mut TrackLimitsMonitor, lap, pct, locations| {
+            time += 0.1;
+            monitor.update(&TrackLimitsSample {
+                session_time: time,
+                laps: vec![lap, lap],
+                lap_dist_pct: vec![pct, pct],
+                locations,
+            })
+        };
+
+        // Car 0 runs wide at Tamburello, car 1 only clips the grass
+        step(&mut monitor, 3, 0.06, vec![OnTrack, OnTrack]);
+        step(&mut monitor, 3, 0.07, vec![OffTrack, OffTrack]);
+        step(&mut monitor, 3, 0.08, vec![OffTrack, OnTrack]);
+        step(&mut monitor, 3, 0.09, vec![OffTrack, OnTrack]);
+        let found = step(&mut monitor, 3, 0.10, vec![OnTrack, OnTrack]);
+        assert_eq!(found.len(), 1);
+        assert_eq!(found[0].corner.as_deref(), Some("Tamburello"));
+        assert!((found[0].duration - 0.3).abs() < 1e-6);
+
+        // Twice more at Rivazza, then off on the straight
+        for lap in [3, 4] {
+            step(&mut monitor, lap, 0.9, vec![OffTrack, OnTrack]);
+            step(&mut monitor, lap, 0.9, vec![OffTrack, OnTrack]);
+            step(&mut monitor, lap, 0.9, vec![OffTrack, OnTrack]);
+            step(&mut monitor, lap, 0.91, vec![OnTrack, OnTrack]);
+        }
+        step(&mut monitor, 5, 0.5, vec![OffTrack, OnTrack]);
+        step(&mut monitor, 5, 0.5, vec![OffTrack, OnTrack]);
+        step(&mut monitor, 5, 0.5, vec![OffTrack, OnTrack]);
+        step(&mut monitor, 5, 0.51, vec![ApproachingPits, OnTrack]);
+
+        // A tow out of the gravel isn't a violation
+        step(&mut monitor, 6, 0.3, vec![OffTrack, OnTrack]);
+        step(&mut monitor, 6, 0.3, vec![NotInWorld, OnTrack]);
+        step(&mut monitor, 6, 0.0, vec![InPitStall, OnTrack]);
+
+        assert_eq!(monitor.violations().len(), 4);
+        assert_eq!(monitor.count(0, 3), 2);
+        assert_eq!(monitor.count(1, 3), 0);
+
+        let laps = monitor.by_lap();
+        assert_eq!(laps.len(), 3);
+        assert_eq!(laps[0].corners, vec!["Tamburello", "Rivazza"]);
+        assert!(laps[2].corners.is_empty());
+        assert_eq!(
+            monitor.by_corner(),
+            vec![("Rivazza".to_string(), 2), ("Tamburello".to_string(), 1)]
+        );
+    }
+}
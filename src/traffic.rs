@@ -0,0 +1,429 @@
+use crate::classes::Classes;
+use crate::validity;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fmt;
+
+#[cfg(feature = "telemetry")]
+use crate::telemetry::Sample;
+#[cfg(feature = "telemetry")]
+use std::convert::TryInto;
+#[cfg(feature = "telemetry")]
+use std::error::Error;
+
+/// Weight of each new measurement in the smoothed closing rate
+const CLOSING_SMOOTHING: f32 = 0.2;
+
+/// Shortest interval a closing rate is measured over (s)
+const MIN_CLOSING_INTERVAL: f64 = 0.5;
+
+///
+/// Traffic Sample
+///
+/// Positions of every car at a point in time, indexed by car index.
+#[derive(Debug, Clone, Default)]
+pub struct TrafficSample {
+    pub session_time: f64,         // Seconds since session start
+    pub player_car_idx: usize,     // PlayerCarIdx
+    pub laps: Vec<i32>,            // CarIdxLap
+    pub lap_dist_pct: Vec<f32>,    // CarIdxLapDistPct - -1 when the car isn't on track
+    pub est_time: Vec<f32>, // CarIdxEstTime - estimated time to reach the current location on track (s)
+    pub on_pit_road: Vec<bool>, // CarIdxOnPitRoad
+    pub class_positions: Vec<i32>, // CarIdxClassPosition - 0 until classified
+}
+
+///
+/// What kind of traffic a car is.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum TrafficKind {
+    /// A car in a faster class is catching from behind
+    FasterClassBehind,
+
+    /// A car in the same class is about to lap the player
+    BlueFlag,
+
+    /// A car in a slower class is ahead
+    SlowerClassAhead,
+
+    /// A car in the same class the player has lapped is ahead
+    LappedAhead,
+}
+
+///
+/// A car the driver should know about.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Advisory {
+    pub session_time: f64,
+    pub car_idx: usize,
+    pub kind: TrafficKind,
+    pub class: String,
+    pub class_position: Option<u32>,
+    pub gap: f32,               // Time between the cars on track (s)
+    pub closing: Option<f32>,   // Rate the gap shrinks at (s/s), negative if it grows
+    pub catch_in: Option<f32>,  // Time until the cars meet at the current rate (s)
+    pub catch_at: Option<f32>,  // Lap distance where the cars meet, 0.0 to 1.0
+    pub corner: Option<String>, // Corner where the cars meet, if corners are known
+}
+
+///
+/// Traffic Advisor
+///
+/// Finds faster-class cars and cars lapping the player closing from behind,
+/// and slower-class and lapped cars ahead. For each it estimates where the
+/// cars will meet, for multiclass and endurance driving aids.
+///
+/// Closing rates are measured from how each gap changes. Until a rate has
+/// been measured, it is estimated from the classes' estimated lap times.
+///
+/// # Examples
+///
+/// ```
+/// use iracing::classes::Classes;
+/// use iracing::traffic::{TrafficAdvisor, TrafficSample};
+/// # let session: iracing::session::SessionDetails =
+/// #     serde_yaml::from_str(&std::fs::read_to_string("./session.yaml").unwrap()).unwrap();
+/// # let sample = TrafficSample::default();
+///
+/// let classes = Classes::from_drivers(&session.drivers.other_drivers);
+/// let mut advisor = TrafficAdvisor::new(classes, session.drivers.estimated_lap_time)
+///     .with_corners(&[(0.05, "T1"), (0.22, "T2"), (0.41, "T3"), (0.63, "T4")]);
+///
+/// for advisory in advisor.update(&sample) {
+///     println!("{}", advisory); // e.g. "P1 GT3 Class car 3.0s behind, catch in T4"
+/// }
+/// ```
+#[derive(Debug, Clone)]
+pub struct TrafficAdvisor {
+    classes: Classes,
+    lap_time: f32,
+    range: f32,
+    corners: Vec<(f32, String)>,
+    gaps: HashMap<usize, (f64, f32)>,
+    closing: HashMap<usize, f32>,
+}
+
+impl TrafficSample {
+    ///
+    /// Read a traffic sample from a telemetry sample.
+    #[cfg(feature = "telemetry")]
+    pub fn from_sample(sample: &Sample) -> Result<Self, Box<dyn Error>> {
+        let player_car_idx: i32 = sample.get("PlayerCarIdx")?.try_into()?;
+
+        Ok(TrafficSample {
+            session_time: sample.get("SessionTime")?.try_into()?,
+            player_car_idx: player_car_idx.max(0) as usize,
+            laps: sample.get("CarIdxLap")?.try_into()?,
+            lap_dist_pct: sample.get("CarIdxLapDistPct")?.try_into()?,
+            est_time: sample.get("CarIdxEstTime")?.try_into()?,
+            on_pit_road: sample.get("CarIdxOnPitRoad")?.into(),
+            class_positions: sample.get("CarIdxClassPosition")?.try_into()?,
+        })
+    }
+}
+
+impl fmt::Display for Advisory {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let position = self
+            .class_position
+            .map(|p| format!("P{} ", p))
+            .unwrap_or_default();
+
+        match self.kind {
+            TrafficKind::FasterClassBehind => {
+                write!(f, "{}{} car {:.1}s behind", position, self.class, self.gap)?
+            }
+            TrafficKind::BlueFlag => {
+                write!(f, "Blue flag, {}car {:.1}s behind", position, self.gap)?
+            }
+            TrafficKind::SlowerClassAhead => {
+                write!(f, "Slower {} car {:.1}s ahead", self.class, self.gap)?
+            }
+            TrafficKind::LappedAhead => write!(f, "Lapped car {:.1}s ahead", self.gap)?,
+        }
+
+        match (&self.corner, self.catch_in) {
+            (Some(corner), Some(_)) => write!(f, ", catch in {}", corner),
+            (None, Some(secs)) => write!(f, ", catch in {:.0}s", secs),
+            _ => Ok(()),
+        }
+    }
+}
+
+impl TrafficAdvisor {
+    ///
+    /// Create an advisor for the given classes and the player's estimated lap
+    /// time (s).
+    pub fn new(classes: Classes, lap_time: f32) -> Self {
+        TrafficAdvisor {
+            classes,
+            lap_time,
+            range: 5.0,
+            corners: Vec::new(),
+            gaps: HashMap::new(),
+            closing: HashMap::new(),
+        }
+    }
+
+    /// Largest gap, ahead or behind, at which cars are reported (s).
+    pub fn with_range(mut self, seconds: f32) -> Self {
+        self.range = seconds;
+        self
+    }
+
+    ///
+    /// Corner names by the lap distance where each corner starts, to say where
+    /// cars will meet.
+    pub fn with_corners(mut self, corners: &[(f32, &str)]) -> Self {
+        self.corners = corners
+            .iter()
+            .map(|(pct, name)| (*pct, name.to_string()))
+            .collect();
+        self.corners
+            .sort_by(|a, b| a.0.partial_cmp(&b.0).unwrap_or(std::cmp::Ordering::Equal));
+        self
+    }
+
+    ///
+    /// Update with a new sample, returning every car currently worth an
+    /// advisory, soonest to meet first.
+    pub fn update(&mut self, sample: &TrafficSample) -> Vec<Advisory> {
+        let player = sample.player_car_idx;
+        let pct = |idx: usize| {
+            sample
+                .lap_dist_pct
+                .get(idx)
+                .copied()
+                .and_then(validity::lap_dist_pct)
+        };
+        let est = |idx: usize| sample.est_time.get(idx).copied().unwrap_or(0.0);
+        let distance =
+            |idx: usize| Some(sample.laps.get(idx).copied().unwrap_or(0) as f32 + pct(idx)?);
+
+        let player_pct = match pct(player) {
+            Some(p) if !sample.on_pit_road.get(player).copied().unwrap_or(false) => p,
+            _ => {
+                self.gaps.clear();
+                self.closing.clear();
+                return Vec::new();
+            }
+        };
+        let player_class = self.classes.for_car(player);
+        let player_speed = player_class.map_or(0, |c| c.relative_speed);
+        let player_lap_time = player_class
+            .and_then(|c| c.est_lap_time)
+            .unwrap_or(self.lap_time);
+
+        let mut advisories = Vec::new();
+        let mut seen = Vec::new();
+
+        for class in self.classes.all().iter() {
+            for &car_idx in class.cars.iter() {
+                if car_idx == player || sample.on_pit_road.get(car_idx).copied().unwrap_or(false) {
+                    continue;
+                }
+                let car_pct = match pct(car_idx) {
+                    Some(p) => p,
+                    None => continue,
+                };
+
+                // Time for the car to reach the player, and for the player to reach the car
+                let mut behind = est(player) - est(car_idx);
+                if player_pct < car_pct {
+                    behind += self.lap_time;
+                }
+                let mut ahead = est(car_idx) - est(player);
+                if car_pct < player_pct {
+                    ahead += self.lap_time;
+                }
+
+                let laps_apart = distance(car_idx)
+                    .zip(distance(player))
+                    .map_or(0.0, |(c, p)| c - p);
+                let same_class = class.relative_speed == player_speed;
+
+                let (kind, gap) = if behind <= ahead {
+                    let kind = if class.relative_speed > player_speed {
+                        TrafficKind::FasterClassBehind
+                    } else if same_class && laps_apart > 0.5 {
+                        TrafficKind::BlueFlag
+                    } else {
+                        continue;
+                    };
+                    (kind, behind)
+                } else {
+                    let kind = if class.relative_speed < player_speed {
+                        TrafficKind::SlowerClassAhead
+                    } else if same_class && laps_apart < -0.5 {
+                        TrafficKind::LappedAhead
+                    } else {
+                        continue;
+                    };
+                    (kind, ahead)
+                };
+
+                if gap > self.range {
+                    continue;
+                }
+                seen.push(car_idx);
+
+                let measured = Self::measure_closing(
+                    &mut self.gaps,
+                    &mut self.closing,
+                    car_idx,
+                    sample.session_time,
+                    gap,
+                );
+                let closing = measured.or_else(|| {
+                    let car_lap_time = class.est_lap_time?;
+                    match kind {
+                        TrafficKind::FasterClassBehind | TrafficKind::BlueFlag => {
+                            Some(1.0 - car_lap_time / player_lap_time)
+                        }
+                        _ => Some(1.0 - player_lap_time / car_lap_time),
+                    }
+                });
+
+                let catch_in = closing.filter(|c| *c > 0.0).map(|c| gap / c);
+                let catch_at = catch_in.map(|t| (player_pct + t / player_lap_time).fract());
+
+                advisories.push(Advisory {
+                    session_time: sample.session_time,
+                    car_idx,
+                    kind,
+                    class: class.short_name.clone(),
+                    class_position: sample
+                        .class_positions
+                        .get(car_idx)
+                        .copied()
+                        .filter(|p| *p > 0)
+                        .map(|p| p as u32),
+                    gap,
+                    closing,
+                    catch_in,
+                    catch_at,
+                    corner: catch_at.and_then(|pct| self.corner_at(pct)),
+                });
+            }
+        }
+
+        self.gaps.retain(|idx, _| seen.contains(idx));
+        self.closing.retain(|idx, _| seen.contains(idx));
+
+        advisories.sort_by(|a, b| {
+            let key = |a: &Advisory| a.catch_in.unwrap_or(f32::MAX);
+            key(a)
+                .partial_cmp(&key(b))
+                .unwrap_or(std::cmp::Ordering::Equal)
+                .then(
+                    a.gap
+                        .partial_cmp(&b.gap)
+                        .unwrap_or(std::cmp::Ordering::Equal),
+                )
+        });
+        advisories
+    }
+
+    /// Smoothed rate a gap is shrinking at, once measured.
+    fn measure_closing(
+        gaps: &mut HashMap<usize, (f64, f32)>,
+        closing: &mut HashMap<usize, f32>,
+        car_idx: usize,
+        session_time: f64,
+        gap: f32,
+    ) -> Option<f32> {
+        match gaps.get(&car_idx).copied() {
+            Some((time, previous)) if session_time - time >= MIN_CLOSING_INTERVAL => {
+                let rate = (previous - gap) / (session_time - time) as f32;
+                let smoothed = match closing.get(&car_idx) {
+                    Some(last) => last + (rate - last) * CLOSING_SMOOTHING,
+                    None => rate,
+                };
+                closing.insert(car_idx, smoothed);
+                gaps.insert(car_idx, (session_time, gap));
+            }
+            Some(_) => {}
+            None => {
+                gaps.insert(car_idx, (session_time, gap));
+            }
+        }
+
+        closing.get(&car_idx).copied()
+    }
+
+    /// The corner containing a lap distance.
+    fn corner_at(&self, pct: f32) -> Option<String> {
+        self.corners
+            .iter()
+            .rev()
+            .find(|(start, _)| pct >= *start)
+            .or_else(|| self.corners.last())
+            .map(|(_, name)| name.clone())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::session::SessionDetails;
+
+    #[test]
+    fn traffic_advisories() {
+        let content = std::fs::read_to_string("./session.yaml").unwrap();
+        let session: SessionDetails = serde_yaml::from_str(&content).unwrap();
+        let classes = Classes::from_drivers(&session.drivers.other_drivers);
+
+        let mut advisor = TrafficAdvisor::new(classes, 100.0).with_corners(&[
+            (0.1, "T1"),
+            (0.5, "T4"),
+            (0.75, "T7"),
+        ]);
+
+        // The player in a Cup car, with a GT3 car behind, a Cup car they lapped
+        // ahead and a Cup car well out of range
+        let mut sample = TrafficSample {
+            session_time: 0.0,
+            player_car_idx: 1,
+            laps: vec![0, 5, 4, 5, 5, 5, 5],
+            lap_dist_pct: vec![-1.0, 0.5, 0.53, 0.9, 0.47, 0.2, 0.4],
+            est_time: vec![0.0, 50.0, 53.0, 90.0, 47.0, 20.0, 40.0],
+            on_pit_road: vec![false; 7],
+            class_positions: vec![0, 2, 3, 1, 1, 1, 1],
+        };
+
+        let advisories = advisor.update(&sample);
+        assert_eq!(advisories.len(), 2);
+
+        let gt3 = advisories
+            .iter()
+            .find(|a| a.kind == TrafficKind::FasterClassBehind)
+            .unwrap();
+        assert_eq!((gt3.car_idx, gt3.gap), (4, 3.0));
+        assert!(gt3.closing.unwrap() > 0.0);
+        assert!(gt3
+            .to_string()
+            .starts_with("P1 GT3 Class car 3.0s behind, catch in"));
+
+        let lapped = advisories
+            .iter()
+            .find(|a| a.kind == TrafficKind::LappedAhead)
+            .unwrap();
+        assert_eq!(lapped.car_idx, 2);
+
+        // The GT3 car closes 1s in 10s, meeting the player 10s and 0.1 laps later
+        sample.session_time = 10.0;
+        sample.est_time[1] = 60.0;
+        sample.lap_dist_pct[1] = 0.6;
+        sample.est_time[4] = 58.0;
+        sample.lap_dist_pct[4] = 0.58;
+        sample.est_time[2] = 100.0;
+        sample.lap_dist_pct[2] = 0.99;
+
+        let advisories = advisor.update(&sample);
+        assert_eq!(advisories.len(), 1);
+        let gt3 = &advisories[0];
+        assert!((gt3.closing.unwrap() - 0.1).abs() < 1e-4);
+        assert!((gt3.catch_in.unwrap() - 20.0).abs() < 1e-3);
+        assert_eq!(gt3.corner.as_deref(), Some("T7"));
+        assert_eq!(gt3.to_string(), "P1 GT3 Class car 2.0s behind, catch in T7");
+    }
+}
@@ -0,0 +1,445 @@
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use std::convert::TryFrom;
+use std::fmt;
+use std::str::FromStr;
+
+///
+/// Unit of a value in session info.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Unit {
+    None,
+    Kilometers,
+    Meters,
+    Millimeters,
+    Celsius,
+    Fahrenheit,
+    KilometersPerHour,
+    MilesPerHour,
+    MetersPerSecond,
+    Radians,
+    Degrees,
+    Percent,
+    Kilograms,
+    Pounds,
+    Liters,
+    Kilopascals,
+    Psi,
+    InchesOfMercury,
+    Newtons,
+    NewtonMeters,
+    Seconds,
+    Other(String),
+}
+
+///
+/// A number with its unit, as written in session info (e.g. "4.86 km").
+///
+/// Numbers with a decimal comma, as written by some locales, are read the same
+/// as with a decimal point.
+///
+/// # Examples
+///
+/// ```
+/// use iracing::units::{Measurement, Unit};
+///
+/// let length: Measurement = "4.86 km".parse().unwrap();
+/// assert_eq!(length.value, 4.86);
+/// assert_eq!(length.unit, Unit::Kilometers);
+/// ```
+#[derive(Debug, Clone, PartialEq)]
+pub struct Measurement {
+    pub value: f64,
+    pub unit: Unit,
+}
+
+/// A length (m).
+#[derive(Debug, Copy, Clone, PartialEq, PartialOrd, Serialize, Deserialize)]
+pub struct Length(pub f64);
+
+/// A speed (m/s).
+#[derive(Debug, Copy, Clone, PartialEq, PartialOrd, Serialize, Deserialize)]
+pub struct Speed(pub f64);
+
+/// A temperature (C).
+#[derive(Debug, Copy, Clone, PartialEq, PartialOrd, Serialize, Deserialize)]
+pub struct Temperature(pub f64);
+
+/// An angle (rad).
+#[derive(Debug, Copy, Clone, PartialEq, PartialOrd, Serialize, Deserialize)]
+pub struct Angle(pub f64);
+
+/// A pressure (Pa).
+#[derive(Debug, Copy, Clone, PartialEq, PartialOrd, Serialize, Deserialize)]
+pub struct Pressure(pub f64);
+
+/// A mass (kg).
+#[derive(Debug, Copy, Clone, PartialEq, PartialOrd, Serialize, Deserialize)]
+pub struct Mass(pub f64);
+
+impl FromStr for Unit {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Ok(match s {
+            "" => Unit::None,
+            "km" => Unit::Kilometers,
+            "m" => Unit::Meters,
+            "mm" => Unit::Millimeters,
+            "C" => Unit::Celsius,
+            "F" => Unit::Fahrenheit,
+            "kph" | "km/h" => Unit::KilometersPerHour,
+            "mph" => Unit::MilesPerHour,
+            "m/s" => Unit::MetersPerSecond,
+            "rad" => Unit::Radians,
+            "deg" => Unit::Degrees,
+            "%" => Unit::Percent,
+            "kg" => Unit::Kilograms,
+            "lb" | "lbs" => Unit::Pounds,
+            "L" | "l" => Unit::Liters,
+            "kPa" => Unit::Kilopascals,
+            "psi" => Unit::Psi,
+            "Hg" | "inHg" => Unit::InchesOfMercury,
+            "N" => Unit::Newtons,
+            "Nm" => Unit::NewtonMeters,
+            "s" | "sec" => Unit::Seconds,
+            other => Unit::Other(other.to_owned()),
+        })
+    }
+}
+
+impl fmt::Display for Unit {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let unit = match self {
+            Unit::None => "",
+            Unit::Kilometers => "km",
+            Unit::Meters => "m",
+            Unit::Millimeters => "mm",
+            Unit::Celsius => "C",
+            Unit::Fahrenheit => "F",
+            Unit::KilometersPerHour => "kph",
+            Unit::MilesPerHour => "mph",
+            Unit::MetersPerSecond => "m/s",
+            Unit::Radians => "rad",
+            Unit::Degrees => "deg",
+            Unit::Percent => "%",
+            Unit::Kilograms => "kg",
+            Unit::Pounds => "lbs",
+            Unit::Liters => "L",
+            Unit::Kilopascals => "kPa",
+            Unit::Psi => "psi",
+            Unit::InchesOfMercury => "Hg",
+            Unit::Newtons => "N",
+            Unit::NewtonMeters => "Nm",
+            Unit::Seconds => "sec",
+            Unit::Other(unit) => unit,
+        };
+        write!(f, "{}", unit)
+    }
+}
+
+impl FromStr for Measurement {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let s = s.trim();
+        let end = s
+            .find(|c: char| !(c.is_ascii_digit() || ".,-+eE".contains(c)))
+            .unwrap_or(s.len());
+
+        // A number with a decimal comma and no decimal point
+        let number = &s[..end];
+        let number = if number.contains('.') {
+            number.replace(',', "")
+        } else {
+            number.replace(',', ".")
+        };
+
+        let value = number
+            .parse()
+            .map_err(|e| format!("Invalid number in {:?}: {}", s, e))?;
+        Ok(Measurement {
+            value,
+            unit: s[end..].trim().parse()?,
+        })
+    }
+}
+
+impl fmt::Display for Measurement {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.unit {
+            Unit::None => write!(f, "{}", self.value),
+            _ => write!(f, "{} {}", self.value, self.unit),
+        }
+    }
+}
+
+impl Serialize for Measurement {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        serializer.collect_str(self)
+    }
+}
+
+impl<'de> Deserialize<'de> for Measurement {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        use serde::de::Error;
+        use serde_yaml::Value;
+
+        match Value::deserialize(deserializer)? {
+            Value::String(s) => s.parse().map_err(D::Error::custom),
+            Value::Number(n) => Ok(Measurement {
+                value: n.as_f64().unwrap_or_default(),
+                unit: Unit::None,
+            }),
+            other => Err(D::Error::custom(format!(
+                "Expected a measurement, found {:?}",
+                other
+            ))),
+        }
+    }
+}
+
+impl Length {
+    pub fn meters(&self) -> f64 {
+        self.0
+    }
+
+    pub fn kilometers(&self) -> f64 {
+        self.0 / 1000.0
+    }
+}
+
+impl Speed {
+    pub fn meters_per_second(&self) -> f64 {
+        self.0
+    }
+
+    pub fn kilometers_per_hour(&self) -> f64 {
+        self.0 * 3.6
+    }
+
+    pub fn miles_per_hour(&self) -> f64 {
+        self.0 / 0.447_04
+    }
+}
+
+impl Temperature {
+    pub fn celsius(&self) -> f64 {
+        self.0
+    }
+
+    pub fn fahrenheit(&self) -> f64 {
+        self.0 * 1.8 + 32.0
+    }
+}
+
+impl Angle {
+    pub fn radians(&self) -> f64 {
+        self.0
+    }
+
+    pub fn degrees(&self) -> f64 {
+        self.0.to_degrees()
+    }
+}
+
+impl Pressure {
+    pub fn pascals(&self) -> f64 {
+        self.0
+    }
+
+    pub fn kilopascals(&self) -> f64 {
+        self.0 / 1000.0
+    }
+
+    pub fn psi(&self) -> f64 {
+        self.0 / 6_894.757
+    }
+}
+
+impl Mass {
+    pub fn kilograms(&self) -> f64 {
+        self.0
+    }
+
+    pub fn pounds(&self) -> f64 {
+        self.0 / 0.453_592_37
+    }
+}
+
+/// Error for a measurement in a unit that doesn't convert.
+fn wrong_unit(m: &Measurement, expected: &str) -> String {
+    format!("Expected {}, found {}", expected, m)
+}
+
+impl TryFrom<Measurement> for Length {
+    type Error = String;
+
+    fn try_from(m: Measurement) -> Result<Self, Self::Error> {
+        match m.unit {
+            Unit::Kilometers => Ok(Length(m.value * 1000.0)),
+            Unit::Meters => Ok(Length(m.value)),
+            Unit::Millimeters => Ok(Length(m.value / 1000.0)),
+            _ => Err(wrong_unit(&m, "a length")),
+        }
+    }
+}
+
+impl TryFrom<Measurement> for Speed {
+    type Error = String;
+
+    fn try_from(m: Measurement) -> Result<Self, Self::Error> {
+        match m.unit {
+            Unit::KilometersPerHour => Ok(Speed(m.value / 3.6)),
+            Unit::MilesPerHour => Ok(Speed(m.value * 0.447_04)),
+            Unit::MetersPerSecond => Ok(Speed(m.value)),
+            _ => Err(wrong_unit(&m, "a speed")),
+        }
+    }
+}
+
+impl TryFrom<Measurement> for Temperature {
+    type Error = String;
+
+    fn try_from(m: Measurement) -> Result<Self, Self::Error> {
+        match m.unit {
+            Unit::Celsius => Ok(Temperature(m.value)),
+            Unit::Fahrenheit => Ok(Temperature((m.value - 32.0) / 1.8)),
+            _ => Err(wrong_unit(&m, "a temperature")),
+        }
+    }
+}
+
+impl TryFrom<Measurement> for Angle {
+    type Error = String;
+
+    fn try_from(m: Measurement) -> Result<Self, Self::Error> {
+        match m.unit {
+            Unit::Radians => Ok(Angle(m.value)),
+            Unit::Degrees => Ok(Angle(m.value.to_radians())),
+            _ => Err(wrong_unit(&m, "an angle")),
+        }
+    }
+}
+
+impl TryFrom<Measurement> for Pressure {
+    type Error = String;
+
+    fn try_from(m: Measurement) -> Result<Self, Self::Error> {
+        match m.unit {
+            Unit::Kilopascals => Ok(Pressure(m.value * 1000.0)),
+            Unit::Psi => Ok(Pressure(m.value * 6_894.757)),
+            Unit::InchesOfMercury => Ok(Pressure(m.value * 3_386.389)),
+            _ => Err(wrong_unit(&m, "a pressure")),
+        }
+    }
+}
+
+impl TryFrom<Measurement> for Mass {
+    type Error = String;
+
+    fn try_from(m: Measurement) -> Result<Self, Self::Error> {
+        match m.unit {
+            Unit::Kilograms => Ok(Mass(m.value)),
+            Unit::Pounds => Ok(Mass(m.value * 0.453_592_37)),
+            _ => Err(wrong_unit(&m, "a mass")),
+        }
+    }
+}
+
+impl FromStr for Length {
+    type Err = String;
+
+    ///
+    /// Parse a length such as "4.86 km".
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Self::try_from(s.parse::<Measurement>()?)
+    }
+}
+
+impl FromStr for Speed {
+    type Err = String;
+
+    ///
+    /// Parse a speed such as "60.00 kph".
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Self::try_from(s.parse::<Measurement>()?)
+    }
+}
+
+impl FromStr for Temperature {
+    type Err = String;
+
+    ///
+    /// Parse a temperature such as "40.56 C".
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Self::try_from(s.parse::<Measurement>()?)
+    }
+}
+
+impl FromStr for Angle {
+    type Err = String;
+
+    ///
+    /// Parse an angle such as "4.9098 rad".
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Self::try_from(s.parse::<Measurement>()?)
+    }
+}
+
+impl FromStr for Pressure {
+    type Err = String;
+
+    ///
+    /// Parse a pressure such as "165 kPa".
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Self::try_from(s.parse::<Measurement>()?)
+    }
+}
+
+impl FromStr for Mass {
+    type Err = String;
+
+    ///
+    /// Parse a mass such as "15.000 kg".
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Self::try_from(s.parse::<Measurement>()?)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::session::SessionDetails;
+
+    #[test]
+    fn weekend_units() {
+        let content = std::fs::read_to_string("./session.yaml").unwrap();
+        let session: SessionDetails = serde_yaml::from_str(&content).unwrap();
+        let weekend = &session.weekend;
+
+        assert!((weekend.length().unwrap().kilometers() - 4.86).abs() < 1e-9);
+        assert!((weekend.pit_speed_limit().unwrap().kilometers_per_hour() - 60.0).abs() < 1e-9);
+        assert!((weekend.surface_temperature().unwrap().celsius() - 40.56).abs() < 1e-9);
+        assert!((weekend.air_pressure().unwrap().pascals() - 101_320.8).abs() < 1.0);
+        assert!((weekend.wind_speed().unwrap().meters_per_second() - 2.0).abs() < 1e-9);
+        assert!((weekend.north_offset().unwrap().radians() - 4.9098).abs() < 1e-9);
+
+        // Decimal commas and units that don't convert
+        let m: Measurement = "1,76 km".parse().unwrap();
+        assert_eq!(
+            m,
+            Measurement {
+                value: 1.76,
+                unit: Unit::Kilometers
+            }
+        );
+        assert!("45.0 C".parse::<Length>().is_err());
+        assert!((Temperature(45.0).fahrenheit() - 113.0).abs() < 1e-9);
+    }
+}
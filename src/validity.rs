@@ -0,0 +1,281 @@
+use serde::{Deserialize, Serialize};
+
+#[cfg(feature = "telemetry")]
+use crate::telemetry::{Sample, Value};
+#[cfg(feature = "telemetry")]
+use std::error::Error;
+
+/// Magic number specifying an unlimited number of laps
+pub const UNLIMITED_LAPS: i32 = 32767;
+
+/// Magic number specifying unlimited time
+pub const UNLIMITED_TIME: f32 = 604800.0;
+
+/// Gaps at or above this are placeholders, such as a car a lap or more down (s)
+pub const NO_GAP: f64 = 999.0;
+
+/// Longest wait for a completed lap's time to be updated (s)
+pub const LAP_TIME_DELAY: f64 = 1.0;
+
+///
+/// A channel value checked for validity.
+#[derive(Debug, Copy, Clone, PartialEq, Serialize, Deserialize)]
+pub enum Reading<T> {
+    Valid(T),
+
+    /// The sim's marker for no value, such as -1 for a car not in the world
+    Missing,
+
+    /// Not a number or infinite
+    Invalid,
+}
+
+///
+/// How a channel marks a value as missing, on top of NaN and infinities
+/// which are never valid.
+///
+/// # Examples
+///
+/// ```
+/// use iracing::validity::{Reading, Sentinel};
+///
+/// let policy = Sentinel::for_channel("CarIdxLastLapTime");
+///
+/// assert_eq!(policy.check(92.4f32), Reading::Valid(92.4));
+/// assert_eq!(policy.check(-1f32), Reading::Missing);
+/// assert_eq!(policy.check(f32::NAN), Reading::Invalid);
+/// ```
+#[derive(Debug, Copy, Clone, PartialEq, Serialize, Deserialize)]
+pub enum Sentinel {
+    /// Every finite value is valid
+    None,
+
+    /// Negative values are missing, such as the -1 lap distance of a car not
+    /// in the world
+    Negative,
+
+    /// Zero and below are missing, such as lap times not yet set and
+    /// positions of cars not yet classified
+    NotPositive,
+
+    /// Values outside `min..max` are missing, such as unlimited session time
+    Outside(f64, f64),
+}
+
+///
+/// Pending Lap
+///
+/// A lap a car has just completed, waiting for its time. The sim updates
+/// `CarIdxLastLapTime` a few ticks after `CarIdxLapCompleted`, so on the tick
+/// a lap completes the time is usually still the previous lap's. The lap is
+/// timed once the time changes, or after `LAP_TIME_DELAY` in case the two
+/// laps were timed the same.
+///
+/// # Examples
+///
+/// ```
+/// use iracing::validity::{PendingLap, Reading};
+///
+/// // Completed at 300s, while the last lap time still read the lap before's
+/// let lap = PendingLap::new(101.2, 300.0);
+///
+/// assert_eq!(lap.poll(101.2, 300.05), None);
+/// assert_eq!(lap.poll(100.9, 300.1), Some(Reading::Valid(100.9)));
+/// ```
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct PendingLap {
+    previous: f32,
+    completed_at: f64,
+}
+
+impl<T> Reading<T> {
+    /// The value, if valid.
+    pub fn value(self) -> Option<T> {
+        match self {
+            Reading::Valid(value) => Some(value),
+            _ => None,
+        }
+    }
+
+    pub fn is_valid(&self) -> bool {
+        matches!(self, Reading::Valid(_))
+    }
+}
+
+impl Sentinel {
+    ///
+    /// The policy for a telemetry channel. Channels without a known marker
+    /// only reject NaN and infinities.
+    pub fn for_channel(name: &str) -> Sentinel {
+        match name {
+            "CarIdxLapDistPct" | "CarIdxLap" | "CarIdxLapCompleted" | "CarIdxEstTime"
+            | "CarIdxF2Time" => Sentinel::Negative,
+            "CarIdxLastLapTime"
+            | "CarIdxBestLapTime"
+            | "LapLastLapTime"
+            | "LapBestLapTime"
+            | "CarIdxPosition"
+            | "CarIdxClassPosition"
+            | "PlayerCarPosition"
+            | "PlayerCarClassPosition" => Sentinel::NotPositive,
+            "SessionTimeRemain" => Sentinel::Outside(0.0, UNLIMITED_TIME.into()),
+            "SessionLapsRemain" | "SessionLapsRemainEx" => {
+                Sentinel::Outside(0.0, UNLIMITED_LAPS.into())
+            }
+            _ => Sentinel::None,
+        }
+    }
+
+    /// Check a value against the policy.
+    pub fn check<T: Copy + Into<f64>>(&self, value: T) -> Reading<T> {
+        let v: f64 = value.into();
+
+        let missing = match *self {
+            _ if !v.is_finite() => return Reading::Invalid,
+            Sentinel::None => false,
+            Sentinel::Negative => v < 0.0,
+            Sentinel::NotPositive => v <= 0.0,
+            Sentinel::Outside(min, max) => v < min || v >= max,
+        };
+
+        if missing {
+            Reading::Missing
+        } else {
+            Reading::Valid(value)
+        }
+    }
+}
+
+impl PendingLap {
+    ///
+    /// A lap completed at `session_time`, while the last lap time read
+    /// `previous` on the sample before.
+    pub fn new(previous: f32, session_time: f64) -> Self {
+        PendingLap {
+            previous,
+            completed_at: session_time,
+        }
+    }
+
+    ///
+    /// The lap's time, checked as `CarIdxLastLapTime`, once it is known.
+    /// `None` while still waiting for it.
+    pub fn poll(&self, last_lap_time: f32, session_time: f64) -> Option<Reading<f32>> {
+        let waited = session_time - self.completed_at;
+        let changed = last_lap_time.to_bits() != self.previous.to_bits();
+
+        // Time going backwards is a new session, so stop waiting
+        if changed || !(0.0..LAP_TIME_DELAY).contains(&waited) {
+            Some(Sentinel::NotPositive.check(last_lap_time))
+        } else {
+            None
+        }
+    }
+}
+
+///
+/// A car's distance around the lap, if it's in the world.
+pub fn lap_dist_pct(value: f32) -> Option<f32> {
+    Sentinel::Negative.check(value).value()
+}
+
+///
+/// A lap time, if one has been set.
+pub fn lap_time(value: f32) -> Option<f32> {
+    Sentinel::NotPositive.check(value).value()
+}
+
+///
+/// A gap between cars (s), if it's a real gap rather than a placeholder.
+pub fn gap(value: f64) -> Option<f64> {
+    Sentinel::Outside(f64::MIN, NO_GAP).check(value).value()
+}
+
+///
+/// Read a numeric channel, or each car's value of a per-car channel, checked
+/// against the channel's policy.
+///
+/// # Examples
+///
+/// ```no_run
+/// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+/// use iracing::telemetry::Sample;
+/// use iracing::validity;
+/// # let sample = Sample::default();
+///
+/// for (car_idx, time) in validity::read(&sample, "CarIdxLastLapTime")?.iter().enumerate() {
+///     if let Some(time) = time.value() {
+///         println!("Car {} ran a {:.3}", car_idx, time);
+///     }
+/// }
+/// # Ok(())
+/// # }
+/// ```
+#[cfg(feature = "telemetry")]
+pub fn read(sample: &Sample, name: &str) -> Result<Vec<Reading<f64>>, Box<dyn Error>> {
+    let values = match sample.get(name)? {
+        Value::INT(v) => vec![v as f64],
+        Value::BITS(v) => vec![v as f64],
+        Value::FLOAT(v) => vec![v as f64],
+        Value::DOUBLE(v) => vec![v],
+        Value::IntVec(v) => v.into_iter().map(f64::from).collect(),
+        Value::FloatVec(v) => v.into_iter().map(f64::from).collect(),
+        Value::BitsVec(v) => v.into_iter().map(f64::from).collect(),
+        other => return Err(format!("'{}' is not numeric: {:?}", name, other).into()),
+    };
+
+    let policy = Sentinel::for_channel(name);
+    Ok(values.into_iter().map(|v| policy.check(v)).collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn sentinel_policies() {
+        let pct = Sentinel::for_channel("CarIdxLapDistPct");
+        assert_eq!(pct.check(0.0f32), Reading::Valid(0.0));
+        assert_eq!(pct.check(-1.0f32), Reading::Missing);
+        assert_eq!(pct.check(f32::INFINITY), Reading::Invalid);
+
+        let position = Sentinel::for_channel("CarIdxPosition");
+        assert_eq!(position.check(0), Reading::Missing);
+        assert_eq!(position.check(3), Reading::Valid(3));
+
+        let remaining = Sentinel::for_channel("SessionTimeRemain");
+        assert_eq!(remaining.check(UNLIMITED_TIME), Reading::Missing);
+        let laps = Sentinel::for_channel("SessionLapsRemainEx");
+        assert_eq!(laps.check(UNLIMITED_LAPS), Reading::Missing);
+        assert_eq!(laps.check(12), Reading::Valid(12));
+        assert_eq!(remaining.check(-0.5), Reading::Missing);
+        assert_eq!(remaining.check(1200.0).value(), Some(1200.0));
+
+        // Channels without a marker still reject NaN
+        let speed = Sentinel::for_channel("Speed");
+        assert_eq!(speed.check(-1.0), Reading::Valid(-1.0));
+        assert_eq!(speed.check(f64::NAN), Reading::Invalid);
+
+        assert_eq!(lap_time(0.0), None);
+        assert_eq!(lap_time(f32::NAN), None);
+        assert_eq!(lap_dist_pct(0.25), Some(0.25));
+        assert_eq!(gap(999.0), None);
+        assert_eq!(gap(-1.5), Some(-1.5));
+    }
+
+    #[test]
+    fn pending_lap_times() {
+        // The time is updated a few ticks after the lap completes
+        let lap = PendingLap::new(92.5, 10.0);
+        assert_eq!(lap.poll(92.5, 10.0), None);
+        assert_eq!(lap.poll(92.5, 10.05), None);
+        assert_eq!(lap.poll(91.8, 10.1), Some(Reading::Valid(91.8)));
+
+        // Two laps timed the same are booked after the delay
+        assert_eq!(lap.poll(92.5, 11.0), Some(Reading::Valid(92.5)));
+
+        // An untimed lap, such as a tow, stays untimed
+        let towed = PendingLap::new(-1.0, 10.0);
+        assert_eq!(towed.poll(-1.0, 12.0), Some(Reading::Missing));
+    }
+}
@@ -0,0 +1,343 @@
+use crate::session::SessionDetails;
+use crate::states::{Skies, TrackUsage, TrackWetness};
+use serde::{Deserialize, Serialize};
+
+#[cfg(feature = "telemetry")]
+use crate::telemetry::Sample;
+#[cfg(feature = "telemetry")]
+use std::convert::TryInto;
+#[cfg(feature = "telemetry")]
+use std::error::Error;
+
+///
+/// Weather Sample
+///
+/// A snapshot of the weather and track state at a point in the session.
+/// Channels introduced with dynamic weather are optional, as older sim builds
+/// and replays do not provide them.
+///
+/// The sim publishes no live rubber or marbles channels. Track usage is the
+/// rubber state the session started with, taken from the session info by
+/// `with_session`, and marbles aren't reported at all.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct WeatherSample {
+    pub session_time: f64,      // Seconds since session start
+    pub air_temperature: f32,   // Air temperature (degC)
+    pub track_temperature: f32, // Track temperature as measured by the crew (degC)
+    pub air_pressure: f32,      // Air pressure (Pa)
+    pub air_density: f32,       // Air density (kg/m^3)
+    pub relative_humidity: f32, // Relative humidity (%)
+    pub fog_level: f32,         // Fog level (%)
+    pub wind_velocity: f32,     // Wind velocity (m/s)
+    pub wind_direction: f32,    // Wind direction relative to north (rad)
+    pub skies: Skies,           // Sky cover
+
+    pub precipitation: Option<f32>,          // Precipitation (%)
+    pub track_wetness: Option<TrackWetness>, // Wetness of the racing surface
+    pub declared_wet: Option<bool>,          // Race control has declared the session wet
+    pub track_usage: Option<TrackUsage>,     // Rubber state of the session, see `with_session`
+}
+
+///
+/// Weather Log
+///
+/// A time series of weather samples over a session. Samples are retained at a
+/// reduced rate (see `with_interval`), as weather changes slowly compared to the
+/// telemetry update rate.
+///
+/// # Examples
+///
+/// ```
+/// use iracing::weather::{WeatherLog, WeatherSample};
+///
+/// let mut log = WeatherLog::with_interval(60.0);
+/// # let sample = WeatherSample {
+/// #     session_time: 0.0, air_temperature: 21.0, track_temperature: 30.0, air_pressure: 101325.0,
+/// #     air_density: 1.2, relative_humidity: 55.0, fog_level: 0.0, wind_velocity: 2.0,
+/// #     wind_direction: 0.0, skies: iracing::states::Skies::PartlyCloudy, precipitation: None, track_wetness: None, declared_wet: None,
+/// #     track_usage: None,
+/// # };
+/// log.record(sample);
+///
+/// if let Some(trend) = log.track_temperature_trend(600.0) {
+///     println!("Track temperature changing at {:.2}C/min", trend);
+/// }
+/// ```
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct WeatherLog {
+    interval: f64,
+    samples: Vec<WeatherSample>,
+}
+
+impl WeatherSample {
+    ///
+    /// Read a weather sample from a telemetry sample.
+    #[cfg(feature = "telemetry")]
+    pub fn from_sample(sample: &Sample) -> Result<Self, Box<dyn Error>> {
+        let precipitation: Option<f32> = if sample.has("Precipitation") {
+            Some(sample.get("Precipitation")?.try_into()?)
+        } else {
+            None
+        };
+
+        let track_wetness = if sample.has("TrackWetness") {
+            let raw: i32 = sample.get("TrackWetness")?.try_into()?;
+            Some(TrackWetness::from(raw))
+        } else {
+            None
+        };
+
+        let declared_wet: Option<bool> = if sample.has("WeatherDeclaredWet") {
+            Some(sample.get("WeatherDeclaredWet")?.into())
+        } else {
+            None
+        };
+
+        Ok(WeatherSample {
+            session_time: sample.get("SessionTime")?.try_into()?,
+            air_temperature: sample.get("AirTemp")?.try_into()?,
+            track_temperature: sample.get("TrackTempCrew")?.try_into()?,
+            air_pressure: sample.get("AirPressure")?.try_into()?,
+            air_density: sample.get("AirDensity")?.try_into()?,
+            relative_humidity: sample.get("RelativeHumidity")?.try_into()?,
+            fog_level: sample.get("FogLevel")?.try_into()?,
+            wind_velocity: sample.get("WindVel")?.try_into()?,
+            wind_direction: sample.get("WindDir")?.try_into()?,
+            skies: Skies::from(TryInto::<i32>::try_into(sample.get("Skies")?)?),
+            precipitation,
+            track_wetness,
+            declared_wet,
+            track_usage: None,
+        })
+    }
+
+    ///
+    /// Set the track usage from the rubber state of session `session_number`
+    /// in the session info, the `SessionNum` channel of the live session.
+    pub fn with_session(mut self, details: &SessionDetails, session_number: u64) -> Self {
+        self.track_usage = details
+            .session
+            .sessions
+            .iter()
+            .find(|s| s.session_number == session_number)
+            .map(|s| TrackUsage::from(s.track_rubber_state.as_str()));
+        self
+    }
+}
+
+impl WeatherSample {
+    ///
+    /// Check whether wet tyres are needed.
+    ///
+    /// True when the racing surface is at least lightly wet and, for sessions
+    /// which report it, race control has declared the session wet.
+    pub fn needs_wets(&self) -> bool {
+        let allowed = self.declared_wet.unwrap_or(true);
+        let wet = matches!(self.track_wetness, Some(w) if w >= TrackWetness::LightlyWet);
+
+        allowed && wet
+    }
+}
+
+impl WeatherLog {
+    ///
+    /// Create a new weather log, retaining at most one sample every `interval` seconds of session time.
+    pub fn with_interval(interval: f64) -> Self {
+        WeatherLog {
+            interval,
+            samples: Vec::new(),
+        }
+    }
+
+    ///
+    /// Record a weather sample.
+    ///
+    /// Returns true if the sample was retained. Samples arriving sooner than the
+    /// configured interval after the last retained sample are discarded. A sample
+    /// with an earlier session time than the last (e.g. a new session) clears the log.
+    pub fn record(&mut self, sample: WeatherSample) -> bool {
+        if let Some(last) = self.samples.last() {
+            if sample.session_time < last.session_time {
+                self.samples.clear();
+            } else if sample.session_time - last.session_time < self.interval {
+                return false;
+            }
+        }
+
+        self.samples.push(sample);
+        true
+    }
+
+    /// All retained samples, in order of session time.
+    pub fn samples(&self) -> &[WeatherSample] {
+        &self.samples
+    }
+
+    /// The most recent sample.
+    pub fn latest(&self) -> Option<&WeatherSample> {
+        self.samples.last()
+    }
+
+    ///
+    /// Get the most recent sample at or before `session_time`.
+    pub fn at(&self, session_time: f64) -> Option<&WeatherSample> {
+        let idx = self
+            .samples
+            .partition_point(|s| s.session_time <= session_time);
+
+        if idx == 0 {
+            None
+        } else {
+            self.samples.get(idx - 1)
+        }
+    }
+
+    ///
+    /// Get all samples recorded between `from` and `to` (inclusive) session time.
+    pub fn between(&self, from: f64, to: f64) -> &[WeatherSample] {
+        let start = self.samples.partition_point(|s| s.session_time < from);
+        let end = self.samples.partition_point(|s| s.session_time <= to);
+
+        if start >= end {
+            &[]
+        } else {
+            &self.samples[start..end]
+        }
+    }
+
+    ///
+    /// Rate of change of a value over the last `window` seconds, per minute.
+    ///
+    /// Computed as the least-squares slope of all samples in the window.
+    /// Returns None when there are fewer than two samples in the window.
+    pub fn trend<F>(&self, window: f64, value: F) -> Option<f32>
+    where
+        F: Fn(&WeatherSample) -> f32,
+    {
+        let latest = self.latest()?.session_time;
+        let samples = self.between(latest - window, latest);
+
+        if samples.len() < 2 {
+            return None;
+        }
+
+        let n = samples.len() as f64;
+        let mean_t = samples.iter().map(|s| s.session_time).sum::<f64>() / n;
+        let mean_v = samples.iter().map(|s| value(s) as f64).sum::<f64>() / n;
+
+        let mut num = 0.0;
+        let mut den = 0.0;
+        for s in samples {
+            let dt = s.session_time - mean_t;
+            num += dt * (value(s) as f64 - mean_v);
+            den += dt * dt;
+        }
+
+        if den == 0.0 {
+            None
+        } else {
+            Some((num / den * 60.0) as f32)
+        }
+    }
+
+    /// Rate of change of track temperature (degC/min) over the last `window` seconds.
+    pub fn track_temperature_trend(&self, window: f64) -> Option<f32> {
+        self.trend(window, |s| s.track_temperature)
+    }
+
+    /// Rate of change of air temperature (degC/min) over the last `window` seconds.
+    pub fn air_temperature_trend(&self, window: f64) -> Option<f32> {
+        self.trend(window, |s| s.air_temperature)
+    }
+
+    /// Rate of change of precipitation (%/min) over the last `window` seconds.
+    pub fn precipitation_trend(&self, window: f64) -> Option<f32> {
+        self.trend(window, |s| s.precipitation.unwrap_or(0.0))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sample(session_time: f64, track_temperature: f32) -> WeatherSample {
+        WeatherSample {
+            session_time,
+            air_temperature: 20.0,
+            track_temperature,
+            air_pressure: 101325.0,
+            air_density: 1.2,
+            relative_humidity: 50.0,
+            fog_level: 0.0,
+            wind_velocity: 1.0,
+            wind_direction: 0.0,
+            skies: Skies::Clear,
+            precipitation: None,
+            track_wetness: None,
+            declared_wet: None,
+            track_usage: None,
+        }
+    }
+
+    #[test]
+    fn record_at_interval() {
+        let mut log = WeatherLog::with_interval(10.0);
+
+        assert!(log.record(sample(0.0, 30.0)));
+        assert!(!log.record(sample(5.0, 30.0)));
+        assert!(log.record(sample(10.0, 30.0)));
+        assert_eq!(log.samples().len(), 2);
+
+        // Session restarted
+        assert!(log.record(sample(1.0, 30.0)));
+        assert_eq!(log.samples().len(), 1);
+    }
+
+    #[test]
+    fn query_by_time() {
+        let mut log = WeatherLog::with_interval(0.0);
+        for t in 0..10 {
+            log.record(sample(t as f64 * 60.0, 30.0 + t as f32));
+        }
+
+        assert_eq!(log.at(125.0).unwrap().session_time, 120.0);
+        assert!(log.at(-1.0).is_none());
+        assert_eq!(log.between(60.0, 180.0).len(), 3);
+        assert_eq!(log.between(1000.0, 2000.0).len(), 0);
+
+        let trend = log.track_temperature_trend(300.0).unwrap();
+        assert!((trend - 1.0).abs() < 1e-4);
+    }
+
+    #[test]
+    fn needs_wets() {
+        let mut s = sample(0.0, 20.0);
+        assert!(!s.needs_wets());
+
+        s.track_wetness = Some(TrackWetness::VeryLightlyWet);
+        assert!(!s.needs_wets());
+
+        s.track_wetness = Some(TrackWetness::ModeratelyWet);
+        assert!(s.needs_wets());
+
+        s.declared_wet = Some(false);
+        assert!(!s.needs_wets());
+    }
+
+    #[test]
+    fn track_usage() {
+        let content = std::fs::read_to_string("./session.yaml").unwrap();
+        let session: SessionDetails = serde_yaml::from_str(&content).unwrap();
+
+        let s = sample(0.0, 20.0).with_session(&session, 0);
+        assert_eq!(s.track_usage, Some(TrackUsage::ModerateUsage));
+
+        let s = sample(0.0, 20.0).with_session(&session, 1);
+        assert_eq!(s.track_usage, Some(TrackUsage::CarryOver));
+
+        assert_eq!(
+            sample(0.0, 20.0).with_session(&session, 9).track_usage,
+            None
+        );
+    }
+}
@@ -0,0 +1,186 @@
+use std::ffi::OsStr;
+use std::io::{Error, ErrorKind, Result as IOResult};
+use std::os::windows::ffi::OsStrExt;
+use std::process::Command;
+use std::ptr::null;
+use std::thread;
+use std::time::{Duration, Instant};
+use winapi::shared::windef::HWND;
+use winapi::um::processthreadsapi::GetCurrentThreadId;
+use winapi::um::winuser::{
+    AttachThreadInput, BringWindowToTop, FindWindowW, GetForegroundWindow,
+    GetWindowThreadProcessId, IsIconic, IsWindow, SetForegroundWindow, ShowWindow, SW_RESTORE,
+};
+
+use crate::broadcast::{Broadcast, BroadcastMessage};
+
+/// Title of the sim's main window.
+const WINDOW_TITLE: &str = "iRacing.com Simulator";
+
+/// Scheme of the links handled by the iRacing UI.
+const URI_SCHEME: &str = "iracing:";
+
+///
+/// Sim Window
+///
+/// The sim's main window, for bringing it to the foreground. Chat and a few
+/// other broadcasts are only acted on while the sim has focus.
+///
+/// # Examples
+///
+/// ```no_run
+/// use iracing::window::SimWindow;
+///
+/// if let Some(window) = SimWindow::find() {
+///     window.focus().expect("Unable to focus the sim");
+/// }
+/// ```
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct SimWindow {
+    handle: HWND,
+}
+
+// Window handles are global to the desktop and may be used from any thread
+unsafe impl Send for SimWindow {}
+unsafe impl Sync for SimWindow {}
+
+impl SimWindow {
+    /// Find the sim's window, if the sim is running.
+    pub fn find() -> Option<SimWindow> {
+        let title: Vec<u16> = OsStr::new(WINDOW_TITLE)
+            .encode_wide()
+            .chain(std::iter::once(0))
+            .collect();
+        let handle = unsafe { FindWindowW(null(), title.as_ptr()) };
+
+        if handle.is_null() {
+            None
+        } else {
+            Some(SimWindow { handle })
+        }
+    }
+
+    ///
+    /// Wait for the sim's window to appear, such as after `launch`, checking
+    /// every `poll` until `timeout`.
+    pub fn wait(timeout: Duration, poll: Duration) -> Option<SimWindow> {
+        let deadline = Instant::now() + timeout;
+
+        loop {
+            if let Some(window) = Self::find() {
+                return Some(window);
+            }
+            if Instant::now() >= deadline {
+                return None;
+            }
+            thread::sleep(poll);
+        }
+    }
+
+    ///
+    /// Launch the sim through the iRacing UI's protocol handler, using a link
+    /// such as those on the members site's "Join" buttons. The sim starts in
+    /// the background; see `wait` for its window.
+    ///
+    /// Anything other than an `iracing:` link is rejected, as explorer would
+    /// open paths and run programs too.
+    pub fn launch(uri: &str) -> IOResult<()> {
+        let scheme = uri.get(..URI_SCHEME.len()).unwrap_or_default();
+        let plain = uri.chars().all(|c| c.is_ascii_graphic() && c != '"');
+        if !scheme.eq_ignore_ascii_case(URI_SCHEME) || !plain {
+            return Err(Error::new(ErrorKind::InvalidInput, "Not an iracing: link"));
+        }
+
+        // Explorer hands the link to whichever app registered its protocol
+        Command::new("explorer.exe").arg(uri).spawn()?;
+        Ok(())
+    }
+
+    /// False once the window has been closed.
+    pub fn exists(&self) -> bool {
+        unsafe { IsWindow(self.handle) != 0 }
+    }
+
+    pub fn is_focused(&self) -> bool {
+        unsafe { GetForegroundWindow() == self.handle }
+    }
+
+    ///
+    /// Bring the window to the foreground, restoring it if minimized.
+    ///
+    /// Windows only lets the foreground app hand over focus, so this briefly
+    /// shares input with the current foreground window while switching.
+    pub fn focus(&self) -> IOResult<()> {
+        if !self.exists() {
+            return Err(Error::new(ErrorKind::NotFound, "Sim window closed"));
+        }
+        if self.is_focused() {
+            return Ok(());
+        }
+
+        unsafe {
+            if IsIconic(self.handle) != 0 {
+                ShowWindow(self.handle, SW_RESTORE);
+            }
+
+            let current = GetCurrentThreadId();
+            let foreground = GetWindowThreadProcessId(GetForegroundWindow(), std::ptr::null_mut());
+            let attached = foreground != current && AttachThreadInput(current, foreground, 1) != 0;
+
+            BringWindowToTop(self.handle);
+            SetForegroundWindow(self.handle);
+
+            if attached {
+                AttachThreadInput(current, foreground, 0);
+            }
+        }
+
+        if self.is_focused() {
+            Ok(())
+        } else {
+            Err(Error::new(
+                ErrorKind::PermissionDenied,
+                "Windows refused to focus the sim",
+            ))
+        }
+    }
+}
+
+impl BroadcastMessage {
+    ///
+    /// True for messages the sim only acts on while it has focus, such as
+    /// opening the chat box.
+    pub fn needs_focus(&self) -> bool {
+        matches!(
+            self,
+            BroadcastMessage::ChatCommand(_) | BroadcastMessage::ChatCommandMacro(_)
+        )
+    }
+}
+
+impl Broadcast {
+    ///
+    /// Send a message, first bringing the sim to the foreground if the
+    /// message needs focus. Fails without sending if the sim isn't running or
+    /// can't be focused.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use iracing::broadcast::{Broadcast, BroadcastMessage};
+    ///
+    /// Broadcast::new()
+    ///     .send_focused(BroadcastMessage::ChatCommandMacro(3))
+    ///     .expect("Sim not running");
+    /// ```
+    pub fn send_focused(&self, message: BroadcastMessage) -> IOResult<()> {
+        if message.needs_focus() {
+            SimWindow::find()
+                .ok_or_else(|| Error::new(ErrorKind::NotFound, "Sim window not found"))?
+                .focus()?;
+        }
+
+        self.send_message(message);
+        Ok(())
+    }
+}
@@ -0,0 +1,397 @@
+use encoding_rs::WINDOWS_1252;
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+use std::fmt;
+
+///
+/// Text encoding of session info.
+///
+/// Older sim builds write Windows-1252, newer ones UTF-8. Plain ASCII reads
+/// the same either way, and is reported as UTF-8.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Encoding {
+    Utf8,
+    Windows1252,
+}
+
+///
+/// A problem found in session info YAML, and fixed so it would parse.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Repair {
+    pub line: usize, // Line of the original YAML, from 1
+    pub kind: RepairKind,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub enum RepairKind {
+    ControlCharacter,     // Replaced with U+FFFD
+    InvalidEscape,        // Backslash in a double quoted string escaped
+    UnquotedValue,        // Value with YAML syntax in it single quoted
+    DuplicateKey(String), // Later copy of a key, and anything nested in it, removed
+}
+
+impl fmt::Display for Repair {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match &self.kind {
+            RepairKind::ControlCharacter => {
+                write!(f, "line {}: control character replaced", self.line)
+            }
+            RepairKind::InvalidEscape => write!(f, "line {}: invalid escape sequence", self.line),
+            RepairKind::UnquotedValue => write!(f, "line {}: value quoted", self.line),
+            RepairKind::DuplicateKey(key) => {
+                write!(f, "line {}: duplicate key {} removed", self.line, key)
+            }
+        }
+    }
+}
+
+///
+/// Detect the encoding of session info bytes.
+///
+/// Windows-1252 text with characters outside ASCII is almost never valid UTF-8, so
+/// anything that is valid UTF-8 is taken to be UTF-8.
+pub fn detect(bytes: &[u8]) -> Encoding {
+    if std::str::from_utf8(bytes).is_ok() {
+        Encoding::Utf8
+    } else {
+        Encoding::Windows1252
+    }
+}
+
+///
+/// Decode session info bytes in whichever encoding they were written in.
+///
+/// # Examples
+///
+/// ```
+/// use iracing::yaml::{decode, Encoding};
+///
+/// assert_eq!(decode("UserName: Zoë".as_bytes()), ("UserName: Zoë".to_owned(), Encoding::Utf8));
+/// assert_eq!(decode(b"UserName: Zo\xEB"), ("UserName: Zoë".to_owned(), Encoding::Windows1252));
+/// assert_eq!(decode(b"TeamName: \x80 Racing").0, "TeamName: € Racing");
+/// ```
+pub fn decode(bytes: &[u8]) -> (String, Encoding) {
+    match std::str::from_utf8(bytes) {
+        Ok(text) => (text.to_owned(), Encoding::Utf8),
+        Err(_) => (decode_cp1252(bytes), Encoding::Windows1252),
+    }
+}
+
+///
+/// Decode session info written in Windows-1252, the sim's code page, which
+/// unlike Latin-1 has printable characters such as €, curly quotes and Š in
+/// 0x80 to 0x9F.
+///
+/// # Examples
+///
+/// ```
+/// use iracing::yaml::decode_cp1252;
+///
+/// assert_eq!(decode_cp1252(b"TeamName: O\x92Neill \x80uro"), "TeamName: O’Neill €uro");
+/// ```
+pub fn decode_cp1252(bytes: &[u8]) -> String {
+    WINDOWS_1252
+        .decode_without_bom_handling(bytes)
+        .0
+        .into_owned()
+}
+
+///
+/// Sanitize session info YAML
+///
+/// iRacing writes session info line by line without escaping anything, so
+/// driver and team names, file paths and the odd track can produce YAML that
+/// doesn't parse. This fixes the quirks seen in practice, returning the fixed
+/// YAML and a report of what was changed:
+///
+/// - Control characters, usually from names in code pages other than
+///   Windows-1252, are replaced.
+/// - Unknown escapes in double quoted strings, such as Windows paths, are escaped.
+/// - Values containing `: `, ` #`, starting with a YAML indicator or an
+///   unbalanced quote are single quoted.
+/// - Repeated keys in a mapping are removed, keeping the first.
+///
+/// YAML that already parses is best left alone, see
+/// [`SessionDetails::parse`](crate::session::SessionDetails::parse).
+///
+/// # Examples
+///
+/// ```
+/// use iracing::yaml::sanitize;
+///
+/// let (yaml, repairs) = sanitize("TeamName: Team #1: The Fast Ones\n");
+/// assert_eq!(yaml, "TeamName: 'Team #1: The Fast Ones'\n");
+/// assert_eq!(repairs.len(), 1);
+/// ```
+pub fn sanitize(yaml: &str) -> (String, Vec<Repair>) {
+    let mut out = String::with_capacity(yaml.len());
+    let mut repairs = Vec::new();
+    let mut scopes: Vec<(usize, HashSet<String>)> = Vec::new();
+    let mut skip_below: Option<usize> = None;
+
+    for (idx, original) in yaml.lines().enumerate() {
+        let number = idx + 1;
+
+        let mut line = original.to_owned();
+        if line.chars().any(|c| c.is_control() && c != '\t') {
+            line = line
+                .chars()
+                .map(|c| {
+                    if c.is_control() && c != '\t' {
+                        '\u{FFFD}'
+                    } else {
+                        c
+                    }
+                })
+                .collect();
+            repairs.push(Repair {
+                line: number,
+                kind: RepairKind::ControlCharacter,
+            });
+        }
+
+        let trimmed = line.trim_start_matches(' ');
+        let indent = line.len() - trimmed.len();
+        if trimmed.is_empty() || trimmed.starts_with('#') || trimmed == "---" || trimmed == "..." {
+            out.push_str(&line);
+            out.push('\n');
+            continue;
+        }
+
+        // Lines nested in a removed duplicate go with it
+        if let Some(depth) = skip_below {
+            if indent > depth {
+                continue;
+            }
+            skip_below = None;
+        }
+
+        let (key_indent, item, body) = if trimmed == "-" || trimmed.starts_with("- ") {
+            (indent + 2, true, trimmed.get(2..).unwrap_or(""))
+        } else {
+            (indent, false, trimmed)
+        };
+
+        // Leave any mappings this line is outside of, and start a new one for a list item
+        while scopes
+            .last()
+            .is_some_and(|(depth, _)| *depth > key_indent || (item && *depth == key_indent))
+        {
+            scopes.pop();
+        }
+
+        let (key, value) = match split_key(body) {
+            Some(pair) => pair,
+            None => {
+                out.push_str(&line);
+                out.push('\n');
+                continue;
+            }
+        };
+
+        if scopes.last().is_none_or(|(depth, _)| *depth != key_indent) {
+            scopes.push((key_indent, HashSet::new()));
+        }
+        let keys = &mut scopes.last_mut().expect("scope pushed above").1;
+        if !keys.insert(key.to_owned()) {
+            repairs.push(Repair {
+                line: number,
+                kind: RepairKind::DuplicateKey(key.to_owned()),
+            });
+            skip_below = Some(indent);
+            continue;
+        }
+
+        let fixed = if value.starts_with('"') && closed_double(value) {
+            escape_double(value).map(|v| (v, RepairKind::InvalidEscape))
+        } else if needs_quotes(value) {
+            Some((
+                format!("'{}'", value.replace('\'', "''")),
+                RepairKind::UnquotedValue,
+            ))
+        } else {
+            None
+        };
+
+        match fixed {
+            Some((value, kind)) => {
+                repairs.push(Repair { line: number, kind });
+                out.push_str(&line[..indent]);
+                if item {
+                    out.push_str("- ");
+                }
+                out.push_str(key);
+                out.push_str(": ");
+                out.push_str(&value);
+            }
+            None => out.push_str(&line),
+        }
+        out.push('\n');
+    }
+
+    (out, repairs)
+}
+
+/// Split `Key: value` into its key and trimmed value.
+fn split_key(body: &str) -> Option<(&str, &str)> {
+    let (key, value) = match body.find(": ") {
+        Some(idx) => (&body[..idx], &body[idx + 2..]),
+        None if body.ends_with(':') => (&body[..body.len() - 1], ""),
+        None => return None,
+    };
+
+    if key.is_empty() || !key.chars().all(|c| c.is_ascii_alphanumeric() || c == '_') {
+        return None;
+    }
+    Some((key, value.trim()))
+}
+
+/// True for a double quoted string closed at the end of the value.
+fn closed_double(value: &str) -> bool {
+    let mut escaped = false;
+    for (idx, c) in value.char_indices().skip(1) {
+        match c {
+            _ if escaped => escaped = false,
+            '\\' => escaped = true,
+            '"' => return idx == value.len() - 1,
+            _ => (),
+        }
+    }
+    false
+}
+
+/// True for a single quoted string closed at the end of the value.
+fn closed_single(value: &str) -> bool {
+    if value.len() < 2 || !value.ends_with('\'') {
+        return false;
+    }
+    let inner = &value[1..value.len() - 1];
+    inner.replace("''", "").find('\'').is_none()
+}
+
+/// True for a plain value that YAML would read as something else, or not at all.
+fn needs_quotes(value: &str) -> bool {
+    let first = match value.chars().next() {
+        Some(c) => c,
+        None => return false,
+    };
+
+    match first {
+        '"' => true,
+        '\'' => !closed_single(value),
+        '[' => !value.ends_with(']'),
+        '{' => !value.ends_with('}'),
+        '|' | '>' => !value[1..]
+            .chars()
+            .all(|c| c == '+' || c == '-' || c.is_ascii_digit()),
+        '!' | '&' | '*' | '@' | '`' | '%' | '#' | ',' => true,
+        '-' | '?' => value.len() == 1 || value[1..].starts_with(' '),
+        _ => value.contains(": ") || value.contains(" #") || value.ends_with(':'),
+    }
+}
+
+/// Double quoted string with unknown escapes escaped, if it has any.
+fn escape_double(value: &str) -> Option<String> {
+    let chars: Vec<char> = value.chars().collect();
+    let mut out = String::with_capacity(value.len() + 4);
+    let mut changed = false;
+
+    let mut idx = 0;
+    while idx < chars.len() {
+        let c = chars[idx];
+        if c != '\\' {
+            out.push(c);
+            idx += 1;
+            continue;
+        }
+
+        let next = chars.get(idx + 1).copied();
+        let hex = |count: usize| {
+            chars.len() > idx + 1 + count
+                && chars[idx + 2..idx + 2 + count]
+                    .iter()
+                    .all(|c| c.is_ascii_hexdigit())
+        };
+        let valid = match next {
+            Some('0') | Some('a') | Some('b') | Some('t') | Some('\t') | Some('n') | Some('v')
+            | Some('f') | Some('r') | Some('e') | Some(' ') | Some('"') | Some('/')
+            | Some('\\') | Some('N') | Some('_') | Some('L') | Some('P') => true,
+            Some('x') => hex(2),
+            Some('u') => hex(4),
+            Some('U') => hex(8),
+            _ => false,
+        };
+
+        if valid {
+            out.push(c);
+            out.extend(next);
+            idx += 2;
+        } else {
+            out.push_str("\\\\");
+            changed = true;
+            idx += 1;
+        }
+    }
+
+    if changed {
+        Some(out)
+    } else {
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::session::SessionDetails;
+
+    #[test]
+    fn repair_session_info() {
+        let content = std::fs::read_to_string("./session.yaml").unwrap();
+        let broken = content
+            .replacen(
+                " TrackName: imola gp\n",
+                " TrackName: imola gp\n TrackName: imola\n",
+                1,
+            )
+            .replacen(
+                "TelemetryDiskFile: \"\"",
+                "TelemetryDiskFile: \"C:\\iRacing\\telemetry\"",
+                1,
+            )
+            .replacen("UserName: L W Adamek", "UserName: L W Adamek: Jr #7", 1)
+            .replacen(
+                "TeamName: Breaker Racing",
+                "TeamName: 'Breaker's Racing\u{7}",
+                1,
+            );
+        assert!(serde_yaml::from_str::<SessionDetails>(&broken).is_err());
+
+        let (session, repairs) = SessionDetails::parse_lenient(&broken).unwrap();
+        assert_eq!(session.weekend.track_name, "imola gp");
+        let driver = &session.drivers.other_drivers[1];
+        assert_eq!(driver.user_name, "L W Adamek: Jr #7");
+        assert_eq!(driver.team_name, "'Breaker's Racing\u{FFFD}");
+
+        // Windows-1252 punctuation and letters aren't control characters
+        let cp1252 = decode_cp1252(b"UserName: \x8Aime \x93Quick\x94 \x8Cuvre\n");
+        assert_eq!(sanitize(&cp1252), (cp1252.clone(), Vec::new()));
+        assert_eq!(cp1252, "UserName: Šime “Quick” Œuvre\n");
+
+        let kinds: Vec<RepairKind> = repairs.into_iter().map(|r| r.kind).collect();
+        assert_eq!(
+            kinds,
+            vec![
+                RepairKind::DuplicateKey("TrackName".to_owned()),
+                RepairKind::InvalidEscape,
+                RepairKind::UnquotedValue,
+                RepairKind::ControlCharacter,
+                RepairKind::UnquotedValue,
+            ]
+        );
+
+        // Valid YAML is parsed as is
+        let (_, repairs) = SessionDetails::parse_lenient(&content).unwrap();
+        assert!(repairs.is_empty());
+    }
+}